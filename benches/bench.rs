@@ -1,12 +1,33 @@
+//! `cargo bench` (or `make bench`), a single benchmark with `cargo bench -- 8digits_sink`.
+//!
+//! baseline of the generation hot paths, release profile on a single core linux VM - compare
+//! against a run of the parent commit on the same machine rather than these numbers:
+//!
+//! | benchmark                 | time     | throughput       |
+//! |---------------------------|----------|------------------|
+//! | 8digits_sink              | 1.19 s   | 724 MiB/s        |
+//! | 8digits_tp (/dev/null)    | 1.12 s   | 766 MiB/s        |
+//! | 5all_sink                 | 69.4 s   | 638 MiB/s        |
+//! | wordlists_mixed_lengths   | 248 ms   | 40.3 Mwords/s    |
+//! | wordlist_load_1m          | 53.2 ms  | 18.8 Mlines/s    |
+//!
+//! `combinations` has no baseline yet, it is expected in the microseconds
 extern crate cracken;
 #[macro_use]
 extern crate criterion;
 
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, LineWriter, Write};
+use std::process::{Command, Stdio};
 use std::time::Duration;
 
 use criterion::{Criterion, Throughput};
 
+use cracken::builder::GeneratorBuilder;
+use cracken::generators::get_word_generator;
 use cracken::runner;
+use cracken::stackbuf::StackBuf;
+use cracken::wordlists::Wordlist;
 use std::path;
 
 fn bench_5digits(c: &mut Criterion) {
@@ -96,12 +117,282 @@ fn bench_wordlists_charset_tp(c: &mut Criterion) {
     group.finish();
 }
 
+/// the default stdout sink: line buffered like `io::stdout()` vs buffered like the locked
+/// `BufWriter` the runner uses, both writing to /dev/null
+fn bench_4digits_stdout_writers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("4digits_stdout_writers");
+    let n_elements = 10_000;
+    let item_len = 5;
+    group.throughput(Throughput::Bytes(n_elements * item_len));
+    group.bench_function("line_writer", |b| {
+        b.iter(|| {
+            let mut out: Box<dyn Write> = Box::new(LineWriter::new(dev_null()));
+            gen_4digits(&mut out)
+        })
+    });
+    group.bench_function("buf_writer", |b| {
+        b.iter(|| {
+            let mut out: Box<dyn Write> = Box::new(BufWriter::with_capacity(8192, dev_null()));
+            gen_4digits(&mut out)
+        })
+    });
+    group.finish();
+}
+
+/// --io-buffer sizes from interactive consumers to fast disks, 64 and 8k are stack buffers
+fn bench_6digits_io_buffers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("6digits_io_buffers");
+    let n_elements = 1_000_000;
+    let item_len = 7;
+    group
+        .throughput(Throughput::Bytes(n_elements * item_len))
+        .sample_size(10)
+        .warm_up_time(Duration::new(1, 0));
+    for io_buffer in ["64", "8k", "64k", "1M", "4M"] {
+        group.bench_function(io_buffer, |b| {
+            b.iter(|| run_bench(vec!["--io-buffer", io_buffer, "?d?d?d?d?d?d"]))
+        });
+    }
+    group.finish();
+}
+
+/// --io-backend writing 1M buffers into a pipe read by cat, which copies them to /dev/null
+fn bench_6digits_io_backends(c: &mut Criterion) {
+    let mut group = c.benchmark_group("6digits_io_backends");
+    let n_elements = 1_000_000;
+    let item_len = 7;
+    group
+        .throughput(Throughput::Bytes(n_elements * item_len))
+        .sample_size(10)
+        .warm_up_time(Duration::new(1, 0));
+    for io_backend in ["std", "vmsplice", "uring"] {
+        group.bench_function(io_backend, |b| {
+            b.iter(|| {
+                let mut run_args = vec!["cracken", "--pipe-to", "cat > /dev/null"];
+                run_args.extend([
+                    "--io-buffer",
+                    "1M",
+                    "--io-backend",
+                    io_backend,
+                    "?d?d?d?d?d?d",
+                ]);
+                runner::run(Some(run_args)).unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+/// batches of 8 bytes words into a stack vs a heap buffer of the default size
+fn bench_stackbuf_backings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stackbuf_backings");
+    let n_batches = 1_000;
+    let size = 8192;
+    group.throughput(Throughput::Bytes(n_batches * size as u64));
+    let fill = |mut buf: StackBuf| {
+        for _ in 0..n_batches {
+            while buf.pos() + 8 <= buf.len() {
+                buf.write(b"1234567\n");
+            }
+            criterion::black_box(buf.getdata());
+            buf.clear();
+        }
+    };
+    group.bench_function("stack", |b| b.iter(|| fill(StackBuf::with_size(size))));
+    group.bench_function("heap", |b| b.iter(|| fill(StackBuf::on_heap(size))));
+    group.finish();
+}
+
+/// all the lengths of ?d?d?d?d?d written straight to a file and to a pipe, the short lengths
+/// partial batches are written with vectored writes
+fn bench_5digits_vectored_sinks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("5digits_vectored_sinks");
+    let n_bytes = 10 * 2 + 100 * 3 + 1_000 * 4 + 10_000 * 5 + 100_000 * 6;
+    group.throughput(Throughput::Bytes(n_bytes));
+    let fname = std::env::temp_dir().join("cracken-bench-vectored.txt");
+    for buffer_size in [64, 8192] {
+        group.bench_function(format!("file_{}", buffer_size), |b| {
+            b.iter(|| {
+                let mut out: Box<dyn Write> = Box::new(File::create(&fname).unwrap());
+                gen_5digits_lengths(&mut out, buffer_size)
+            })
+        });
+        group.bench_function(format!("pipe_{}", buffer_size), |b| {
+            b.iter(|| {
+                let mut child = Command::new("cat")
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .spawn()
+                    .unwrap();
+                {
+                    let mut out: Box<dyn Write> = Box::new(child.stdin.take().unwrap());
+                    gen_5digits_lengths(&mut out, buffer_size);
+                }
+                child.wait().unwrap();
+            })
+        });
+    }
+    group.finish();
+    let _ = std::fs::remove_file(fname);
+}
+
+fn gen_5digits_lengths(out: &mut Box<dyn Write>, buffer_size: usize) {
+    let mut word_generator = get_word_generator("?d?d?d?d?d", Some(1), None, &[], &[]).unwrap();
+    word_generator.set_buffer_size(buffer_size);
+    word_generator.gen(out).unwrap();
+    out.flush().unwrap();
+}
+
+/// generators created from borrowed args vs owned ones through the builder, both generate
+/// the same way once created
+fn bench_4mixed_owned_inputs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("4mixed_owned_inputs");
+    let n_elements = 26 * 26 * 33 * 10;
+    let item_len = 5;
+    group.throughput(Throughput::Bytes(n_elements * item_len));
+    group.bench_function("borrowed", |b| {
+        b.iter(|| {
+            let word_generator = get_word_generator("?u?l?s?d", None, None, &[], &[]).unwrap();
+            let mut out: Box<dyn Write> = Box::new(BufWriter::new(dev_null()));
+            word_generator.gen(&mut out).unwrap();
+        })
+    });
+    group.bench_function("owned", |b| {
+        b.iter(|| {
+            let builder = GeneratorBuilder::new().mask(String::from("?u?l?s?d"));
+            let word_generator = builder.build().unwrap();
+            let mut out: Box<dyn Write> = Box::new(BufWriter::new(dev_null()));
+            word_generator.gen(&mut out).unwrap();
+        })
+    });
+    group.finish();
+}
+
+/// the whole generate command writing to `io::sink()` through `runner::run_to`, so no
+/// stdout or file writes are measured
+fn bench_8digits_sink(c: &mut Criterion) {
+    let mut group = c.benchmark_group("8digits_sink");
+    let n_elements = 100_000_000;
+    let item_len = 9;
+    group
+        .throughput(Throughput::Bytes(n_elements * item_len))
+        .sample_size(10)
+        .warm_up_time(Duration::new(1, 0));
+    group.bench_function("8digits_sink", |b| {
+        b.iter(|| run_bench_sink(vec!["?d?d?d?d?d?d?d?d"]))
+    });
+    group.finish();
+}
+
+/// 95 ** 5 words - the largest charset, takes a while per sample
+fn bench_5all_sink(c: &mut Criterion) {
+    let mut group = c.benchmark_group("5all_sink");
+    let n_elements = 7_737_809_375;
+    let item_len = 6;
+    group
+        .throughput(Throughput::Bytes(n_elements * item_len))
+        .sample_size(10)
+        .warm_up_time(Duration::new(1, 0));
+    group.bench_function("5all_sink", |b| {
+        b.iter(|| run_bench_sink(vec!["?a?a?a?a?a"]))
+    });
+    group.finish();
+}
+
+/// two wordlists of 1 to 16 and 1 to 32 bytes words, every length has its own words buffer
+fn bench_wordlists_mixed_lengths(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wordlists_mixed_lengths");
+    let w1 = mixed_lengths_wordlist("cracken-bench-mixed-10k.txt", 10_000, 16);
+    let w2 = mixed_lengths_wordlist("cracken-bench-mixed-100.txt", 100, 32);
+    let n_elements = 10_000 * 10 * 100;
+    group
+        .throughput(Throughput::Elements(n_elements))
+        .sample_size(10)
+        .warm_up_time(Duration::new(1, 0));
+    group.bench_function("wordlists_mixed_lengths", |b| {
+        b.iter(|| {
+            run_bench_sink(vec![
+                "-w",
+                w1.to_str().unwrap(),
+                "-w",
+                w2.to_str().unwrap(),
+                "?w1?d?w2",
+            ])
+        })
+    });
+    group.finish();
+}
+
+/// reading, splitting and grouping by length a 1M lines wordlist, without generating
+fn bench_wordlist_load_1m(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wordlist_load_1m");
+    let fname = mixed_lengths_wordlist("cracken-bench-mixed-1m.txt", 1_000_000, 16);
+    group
+        .throughput(Throughput::Elements(1_000_000))
+        .sample_size(10);
+    group.bench_function("wordlist_load_1m", |b| {
+        b.iter(|| Wordlist::from_file(&fname).unwrap())
+    });
+    group.finish();
+}
+
+/// the keyspace of masks with huge numbers of words, with and without a length range
+fn bench_combinations(c: &mut Criterion) {
+    let mut group = c.benchmark_group("combinations");
+    let w1 = wordlist_fname("wordlist1.txt");
+    let wordlists = [w1.as_str()];
+    let cases = [
+        ("64all", "?a".repeat(64), None),
+        ("64all_minlen", "?a".repeat(64), Some(1)),
+        ("wordlist_32all", format!("?w1{}", "?a".repeat(32)), None),
+    ];
+    for (name, mask, minlen) in cases {
+        let word_generator = get_word_generator(&mask, minlen, None, &[], &wordlists).unwrap();
+        group.bench_function(name, |b| b.iter(|| word_generator.combinations()));
+    }
+    group.finish();
+}
+
+/// creates `fname` in the temp dir with `n_words` words of 1 to `max_len` bytes, once
+fn mixed_lengths_wordlist(fname: &str, n_words: usize, max_len: usize) -> path::PathBuf {
+    let fname = std::env::temp_dir().join(fname);
+    if fname.exists() {
+        return fname;
+    }
+    let mut out = BufWriter::new(File::create(&fname).unwrap());
+    for i in 0..n_words {
+        // a different word for every index, the lengths cycle through 1 to max_len
+        let len = 1 + i * 7 % max_len;
+        let word: Vec<u8> = (0..len).map(|j| b'a' + ((i >> j) % 26) as u8).collect();
+        out.write_all(&word).unwrap();
+        out.write_all(b"\n").unwrap();
+    }
+    out.flush().unwrap();
+    fname
+}
+
+fn dev_null() -> File {
+    OpenOptions::new().write(true).open("/dev/null").unwrap()
+}
+
+fn gen_4digits(out: &mut Box<dyn Write>) {
+    let word_generator = get_word_generator("?d?d?d?d", None, None, &[], &[]).unwrap();
+    word_generator.gen(out).unwrap();
+    out.flush().unwrap();
+}
+
 fn wordlist_fname(fname: &str) -> String {
     let mut d = path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     d.extend(vec!["test-resources", fname]);
     d.to_str().unwrap().to_owned()
 }
 
+fn run_bench_sink(args: Vec<&str>) {
+    let mut run_args = vec!["cracken"];
+    run_args.extend(args);
+    runner::run_to(Some(run_args), Box::new(io::sink())).unwrap();
+}
+
 fn run_bench(args: Vec<&str>) {
     let mut run_args = vec!["cracken", "-o", "/dev/null"];
     run_args.extend(args);
@@ -113,12 +404,23 @@ criterion_group!(
     bench_5digits,
     bench_4mixed,
     bench_wordlist_simple,
-    bench_wordlist_and_custom_charset
+    bench_wordlist_and_custom_charset,
+    bench_4digits_stdout_writers,
+    bench_stackbuf_backings,
+    bench_4mixed_owned_inputs,
+    bench_wordlist_load_1m,
+    bench_combinations
 );
 criterion_group!(
     benches_throughput,
     bench_8digits_tp,
     bench_6lower_tp,
-    bench_wordlists_charset_tp
+    bench_wordlists_charset_tp,
+    bench_6digits_io_buffers,
+    bench_6digits_io_backends,
+    bench_5digits_vectored_sinks,
+    bench_8digits_sink,
+    bench_5all_sink,
+    bench_wordlists_mixed_lengths
 );
 criterion_main!(benches, benches_throughput);
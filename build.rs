@@ -1,5 +1,8 @@
+#[cfg(feature = "cli")]
 extern crate built;
 
 fn main() {
+    // the build info is only printed by the cli (--version and the version subcommand)
+    #[cfg(feature = "cli")]
     built::write_built_file().expect("Failed to acquire build-time information");
 }
@@ -0,0 +1,190 @@
+//! `cargo fuzz run generator` - builds small generators of arbitrary masks and in-memory
+//! wordlists, and checks the number of generated lines is `combinations()` and every line
+//! length is within the bounds of the mask
+#![no_main]
+
+use std::io::Write;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use cracken::builder::GeneratorBuilder;
+use cracken::MAX_WORD_SIZE;
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigUint;
+
+/// larger generators are skipped to keep every run fast
+const MAX_COMBINATIONS: u64 = 100_000;
+const CUSTOM_CHARSETS: [&str; 2] = ["ab", "x!"];
+const WORDLISTS: usize = 3;
+
+#[derive(Debug)]
+enum Op {
+    Builtin(char),
+    Custom(usize),
+    Char(char),
+    Wordlist(usize),
+    ReversedWordlist(usize),
+}
+
+impl<'a> Arbitrary<'a> for Op {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Op> {
+        Ok(match u.int_in_range(0..=4)? {
+            0 => Op::Builtin(*u.choose(&['d', 'l', 'u', 's', 'a'])?),
+            1 => Op::Custom(u.int_in_range(0..=CUSTOM_CHARSETS.len() - 1)?),
+            2 => Op::Char(char::from(u.int_in_range(0x20u8..=0x7e)?)),
+            3 => Op::Wordlist(u.int_in_range(0..=WORDLISTS - 1)?),
+            _ => Op::ReversedWordlist(u.int_in_range(0..=WORDLISTS - 1)?),
+        })
+    }
+}
+
+/// a wordlist word of an adversarial length - empty, 127 bytes or short, without newlines
+#[derive(Debug)]
+struct Word(Vec<u8>);
+
+impl<'a> Arbitrary<'a> for Word {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Word> {
+        let len = match u.int_in_range(0..=3)? {
+            0 => 0,
+            1 => 127,
+            2 => u.int_in_range(1..=126)?,
+            _ => u.int_in_range(1..=4)?,
+        };
+        let mut word = Vec::with_capacity(len);
+        for _ in 0..len {
+            match u.int_in_range(0..=255)? {
+                b'\n' => word.push(b' '),
+                ch => word.push(ch),
+            }
+        }
+        Ok(Word(word))
+    }
+}
+
+#[derive(Debug)]
+struct Input {
+    ops: Vec<Op>,
+    wordlists: Vec<Vec<Word>>,
+    minlen: Option<usize>,
+}
+
+impl<'a> Arbitrary<'a> for Input {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Input> {
+        let mut ops = vec![];
+        for _ in 0..u.int_in_range(1..=8)? {
+            ops.push(Op::arbitrary(u)?);
+        }
+        let mut wordlists = vec![];
+        for _ in 0..WORDLISTS {
+            let mut words = vec![];
+            for _ in 0..u.int_in_range(0..=4)? {
+                words.push(Word::arbitrary(u)?);
+            }
+            wordlists.push(words);
+        }
+        let minlen = if u.arbitrary()? {
+            Some(u.int_in_range(0..=8)?)
+        } else {
+            None
+        };
+        Ok(Input {
+            ops,
+            wordlists,
+            minlen,
+        })
+    }
+}
+
+impl Input {
+    fn mask(&self) -> String {
+        let mut mask = String::new();
+        for op in self.ops.iter() {
+            match op {
+                Op::Builtin(ch) => mask.push_str(&format!("?{}", ch)),
+                Op::Custom(idx) => mask.push_str(&format!("?{}", idx + 1)),
+                Op::Char(ch) if ch.is_ascii_alphanumeric() => mask.push(*ch),
+                // escaped so it is never parsed as a transform of a preceding wordlist
+                Op::Char(ch) => mask.push_str(&format!("\\{}", ch)),
+                Op::Wordlist(idx) => mask.push_str(&format!("?w{}", idx + 1)),
+                Op::ReversedWordlist(idx) => mask.push_str(&format!("?w{}:rev", idx + 1)),
+            }
+        }
+        mask
+    }
+
+    fn builder(&self) -> GeneratorBuilder<'static> {
+        let mut builder = GeneratorBuilder::new().mask(self.mask());
+        for charset in CUSTOM_CHARSETS {
+            builder = builder.custom_charset(charset);
+        }
+        for words in self.wordlists.iter() {
+            builder = builder.wordlist_in_memory(words.iter().map(|word| word.0.as_slice()));
+        }
+        if let Some(minlen) = self.minlen {
+            builder = builder.minlen(minlen);
+        }
+        builder
+    }
+
+    /// min and max length of the generated words
+    fn len_bounds(&self) -> (usize, usize) {
+        let (mut min_len, mut max_len) = (0, 0);
+        for op in self.ops.iter() {
+            let (op_min, op_max) = match op {
+                Op::Wordlist(idx) | Op::ReversedWordlist(idx) => {
+                    let lens = self.wordlists[*idx]
+                        .iter()
+                        .map(|word| word.0.len())
+                        .filter(|&len| len > 0);
+                    (lens.clone().min().unwrap_or(0), lens.max().unwrap_or(0))
+                }
+                _ => (1, 1),
+            };
+            min_len += op_min;
+            max_len += op_max;
+        }
+        (self.minlen.unwrap_or(min_len).max(1), max_len)
+    }
+}
+
+fuzz_target!(|input: Input| {
+    // invalid masks (e.g. minlen with wordlists or too long words) are rejected with an error
+    let word_gen = match input.builder().build() {
+        Ok(word_gen) => word_gen,
+        Err(_) => return,
+    };
+    let combinations = word_gen.combinations();
+    if combinations > BigUint::from(MAX_COMBINATIONS) {
+        return;
+    }
+
+    let mut buf = vec![];
+    {
+        let mut out: Box<dyn Write> = Box::new(&mut buf);
+        word_gen.gen(&mut out).unwrap();
+    }
+    let words = match buf.strip_suffix(b"\n") {
+        Some(words) => words,
+        None => {
+            assert!(buf.is_empty(), "{:?}: output must end with a newline", input);
+            &[]
+        }
+    };
+
+    let (min_len, max_len) = input.len_bounds();
+    let mut lines = 0u64;
+    if !buf.is_empty() {
+        for word in words.split(|&c| c == b'\n') {
+            lines += 1;
+            assert!(
+                min_len <= word.len() && word.len() <= max_len && word.len() < MAX_WORD_SIZE,
+                "{:?}: word {:?} of length {} not in {}..={}",
+                input,
+                word,
+                word.len(),
+                min_len,
+                max_len
+            );
+        }
+    }
+    assert_eq!(BigUint::from(lines), combinations, "{:?}", input);
+});
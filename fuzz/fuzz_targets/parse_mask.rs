@@ -0,0 +1,13 @@
+//! `cargo fuzz run parse_mask` - arbitrary strings never panic the mask parser
+#![no_main]
+
+use cracken::mask::parse_mask;
+use cracken::MAX_WORD_SIZE;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|mask: &str| {
+    if let Ok(mask_ops) = parse_mask(mask) {
+        assert!(!mask_ops.is_empty());
+        assert!(mask_ops.len() < MAX_WORD_SIZE);
+    }
+});
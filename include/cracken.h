@@ -0,0 +1,107 @@
+#ifndef CRACKEN_H
+#define CRACKEN_H
+
+/* generated by cbindgen from src/ffi.rs, do not edit */
+
+#include <stddef.h>
+#include <stdint.h>
+#include <sys/types.h>
+
+/**
+ * a NULL pointer was passed for a required argument
+ */
+#define CRACKEN_ERR_NULL -1
+
+/**
+ * the next word is longer than the buffer, it is kept for the next call
+ */
+#define CRACKEN_ERR_BUF_TOO_SMALL -2
+
+/**
+ * buffers of this size can hold any generated word
+ */
+#define CRACKEN_MAX_WORD_SIZE 512
+
+/**
+ * a generator running on its own thread, pulled by `cracken_generator_next`
+ */
+typedef struct CrackenGenerator CrackenGenerator;
+
+/**
+ * creates a generator of `mask` with the custom charsets ?1 to ?9 and the wordlist files
+ * ?w1 to ?w9 (same syntax as the -c and -w arguments). returns NULL on error
+ *
+ * # Safety
+ *
+ * `mask` must be a NUL terminated string, `charsets` and `wordlists` arrays of
+ * `n_charsets` and `n_wordlists` NUL terminated strings (may be NULL if empty) and
+ * `err_out` NULL or a valid pointer
+ */
+CrackenGenerator *cracken_generator_new(const char *mask,
+                                        const char *const *charsets,
+                                        size_t n_charsets,
+                                        const char *const *wordlists,
+                                        size_t n_wordlists,
+                                        char **err_out);
+
+/**
+ * copies the next word (without a newline or a NUL) to `buf` and returns its length,
+ * or 0 after the last word. returns `CRACKEN_ERR_BUF_TOO_SMALL` if the word is longer
+ * than `buf_len`, a buffer of `CRACKEN_MAX_WORD_SIZE` bytes always fits
+ *
+ * # Safety
+ *
+ * `gen` must be returned by `cracken_generator_new` and `buf` point to `buf_len` bytes
+ */
+ssize_t cracken_generator_next(CrackenGenerator *gen, uint8_t *buf, size_t buf_len);
+
+/**
+ * copies as many of the next words as fit in `buf`, every one followed by a newline (without
+ * a NUL), and returns the number of bytes copied, or 0 after the last word. returns
+ * `CRACKEN_ERR_BUF_TOO_SMALL` if the next word and its newline are longer than `buf_len`.
+ * pulls many words per call, faster than `cracken_generator_next` for large keyspaces
+ *
+ * # Safety
+ *
+ * `gen` must be returned by `cracken_generator_new` and `buf` point to `buf_len` bytes
+ */
+ssize_t cracken_generator_next_chunk(CrackenGenerator *gen, uint8_t *buf, size_t buf_len);
+
+/**
+ * returns the number of words of the generator, UINT64_MAX if it doesn't fit
+ *
+ * # Safety
+ *
+ * `gen` must be returned by `cracken_generator_new`
+ */
+uint64_t cracken_generator_combinations(const CrackenGenerator *gen);
+
+/**
+ * skips the next `n` words and returns the number of skipped words, less than `n` only
+ * after the last word
+ *
+ * # Safety
+ *
+ * `gen` must be returned by `cracken_generator_new`
+ */
+uint64_t cracken_generator_skip(CrackenGenerator *gen, uint64_t n);
+
+/**
+ * stops the generator and frees it, NULL is ignored
+ *
+ * # Safety
+ *
+ * `gen` must be returned by `cracken_generator_new` and not used afterwards
+ */
+void cracken_generator_free(CrackenGenerator *gen);
+
+/**
+ * frees an error message returned by the cracken functions, NULL is ignored
+ *
+ * # Safety
+ *
+ * `s` must be returned by a cracken function and not used afterwards
+ */
+void cracken_string_free(char *s);
+
+#endif /* CRACKEN_H */
@@ -0,0 +1,97 @@
+"""compares the python bindings to the cracken CLI, run with `make python-test`"""
+import os
+import shutil
+import subprocess
+from pathlib import Path
+
+import pytest
+
+import cracken
+
+ROOT = Path(__file__).resolve().parents[2]
+RESOURCES = ROOT / "test-resources"
+WORDLIST = str(RESOURCES / "wordlist-simple.txt")
+
+CASES = [
+    ("?d?d", [], [], None, None),
+    ("?u?l?u?l", [], [], 1, 4),
+    ("?w1?d?1", ["!@"], [WORDLIST], None, None),
+    ("?1?w1?w2", ["ab"], [WORDLIST, str(RESOURCES / "wordlist-no-newline.txt")], None, None),
+]
+
+
+def cracken_bin():
+    exe = os.environ.get("CRACKEN_BIN") or shutil.which("cracken")
+    if exe is None:
+        exe = str(ROOT / "target" / "release" / "cracken")
+    if not os.path.exists(exe):
+        pytest.skip("cracken CLI not found, set $CRACKEN_BIN")
+    return exe
+
+
+def cli_args(mask, charsets, wordlists, minlen, maxlen):
+    args = []
+    for charset in charsets:
+        args += ["-c", charset]
+    for fname in wordlists:
+        args += ["-w", fname]
+    if minlen is not None:
+        args += ["-m", str(minlen)]
+    if maxlen is not None:
+        args += ["-x", str(maxlen)]
+    return args + [mask]
+
+
+def cli_output(*args):
+    res = subprocess.run([cracken_bin(), *args], stdout=subprocess.PIPE, check=True)
+    return res.stdout
+
+
+@pytest.mark.parametrize("mask,charsets,wordlists,minlen,maxlen", CASES)
+def test_generator_matches_cli(mask, charsets, wordlists, minlen, maxlen):
+    expected = cli_output(*cli_args(mask, charsets, wordlists, minlen, maxlen)).splitlines()
+    gen = cracken.Generator(
+        mask, charsets=charsets, wordlists=wordlists, minlen=minlen, maxlen=maxlen
+    )
+    assert gen.combinations() == len(expected)
+    assert list(gen) == expected
+
+
+@pytest.mark.parametrize("mask,charsets,wordlists,minlen,maxlen", CASES)
+def test_generate_to_file_matches_cli(tmp_path, mask, charsets, wordlists, minlen, maxlen):
+    expected = cli_output(*cli_args(mask, charsets, wordlists, minlen, maxlen))
+    out = tmp_path / "words.txt"
+    gen = cracken.Generator(
+        mask, charsets=charsets, wordlists=wordlists, minlen=minlen, maxlen=maxlen
+    )
+    next(gen)
+    gen.generate_to_file(str(out))
+    assert out.read_bytes() == expected
+
+
+def test_skip():
+    gen = cracken.Generator("?d?d?d?d")
+    assert gen.combinations() == 10000
+    assert gen.skip(1234) == 1234
+    assert next(gen) == b"1234"
+    assert gen.skip(10000) == 8765
+    assert list(gen) == []
+
+
+def test_big_combinations():
+    assert cracken.Generator("?a" * 20).combinations() == 95 ** 20
+
+
+def test_errors():
+    with pytest.raises(cracken.MaskError):
+        cracken.Generator("?d?")
+    with pytest.raises(cracken.CharsetError):
+        cracken.Generator("?1")
+    with pytest.raises(cracken.InvalidLengthError):
+        cracken.Generator("?d?d", minlen=3)
+    with pytest.raises(cracken.UnsupportedError):
+        cracken.Generator("?w1", wordlists=[WORDLIST], minlen=1)
+    with pytest.raises(cracken.WordlistError) as exc:
+        cracken.Generator("?w1", wordlists=["/tmp/this/dir/not/exisT.txt"])
+    assert str(exc.value).startswith("wordlist /tmp/this/dir/not/exisT.txt: ")
+    assert issubclass(cracken.WordlistError, cracken.Error)
@@ -0,0 +1,225 @@
+//! the distinct permutations of the chars of a word (`cracken anagram`)
+use std::io::Write;
+
+use num_bigint::BigUint;
+
+use crate::error::CrackenError;
+use crate::generators::{
+    max_written_len, write_word, LengthOrder, RepeatLimits, WordGenerator, WordRank,
+};
+use crate::stackbuf::StackBuf;
+use crate::{BUFFER_SIZE, MAX_WORD_SIZE};
+
+/// generates every distinct permutation of the chars of a word once, in lexicographic order
+pub struct AnagramGenerator {
+    /// the chars of the word sorted, the first permutation
+    chars: Vec<u8>,
+    hex_output: bool,
+    buffer_size: usize,
+    repeat_limits: RepeatLimits,
+}
+
+impl AnagramGenerator {
+    pub fn new(word: &[u8]) -> Result<AnagramGenerator, CrackenError> {
+        if word.is_empty() || word.len() > MAX_WORD_SIZE {
+            return Err(CrackenError::InvalidLength(format!(
+                "anagram word length must be between 1 and {}",
+                MAX_WORD_SIZE
+            )));
+        }
+        if word.contains(&b'\n') {
+            return Err(CrackenError::Unsupported(
+                "anagram word can't contain a newline".to_string(),
+            ));
+        }
+        let mut chars = word.to_vec();
+        chars.sort_unstable();
+        Ok(AnagramGenerator {
+            chars,
+            hex_output: false,
+            buffer_size: BUFFER_SIZE,
+            repeat_limits: RepeatLimits::default(),
+        })
+    }
+
+    fn counts(&self) -> [usize; 256] {
+        let mut counts = [0; 256];
+        for &chr in self.chars.iter() {
+            counts[chr as usize] += 1;
+        }
+        counts
+    }
+}
+
+/// number of distinct permutations of a multiset with these counts - the multinomial
+/// coefficient, as a product of binomials to keep every step an integer
+fn permutations(counts: &[usize]) -> BigUint {
+    let mut result = BigUint::from(1u8);
+    let mut total = 0usize;
+    for &count in counts.iter() {
+        for i in 1..=count {
+            total += 1;
+            result = result * total / i;
+        }
+    }
+    result
+}
+
+/// rearranges `word` to the next permutation in lexicographic order, returns false after the
+/// last one. repeated chars are swapped only with greater chars so no permutation repeats
+fn next_permutation(word: &mut [u8]) -> bool {
+    let pivot = match word.windows(2).rposition(|pair| pair[0] < pair[1]) {
+        Some(pivot) => pivot,
+        None => return false,
+    };
+    let successor = word.iter().rposition(|&chr| chr > word[pivot]).unwrap();
+    word.swap(pivot, successor);
+    word[pivot + 1..].reverse();
+    true
+}
+
+impl WordGenerator for AnagramGenerator {
+    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), CrackenError> {
+        let len = self.chars.len();
+        let max_word_len = max_written_len(len + 1, self.hex_output);
+        let mut buf = StackBuf::with_size(self.buffer_size.max(max_word_len));
+        let mut word = self.chars.clone();
+        word.push(b'\n');
+        loop {
+            if !self.repeat_limits.is_set() || self.repeat_limits.allows(&word[..len]) {
+                if buf.remaining() < max_word_len {
+                    out.write_all(buf.getdata())?;
+                    buf.clear();
+                }
+                write_word(&mut buf, &word, self.hex_output);
+            }
+            if !next_permutation(&mut word[..len]) {
+                break;
+            }
+        }
+        out.write_all(buf.getdata())?;
+        Ok(())
+    }
+
+    fn combinations(&self) -> BigUint {
+        permutations(&self.counts())
+    }
+
+    fn length_combinations(&self) -> Vec<(usize, BigUint)> {
+        vec![(self.chars.len(), self.combinations())]
+    }
+
+    fn output_bytes(&self) -> BigUint {
+        self.combinations() * (self.chars.len() + 1)
+    }
+
+    fn set_hex_output(&mut self, hex_output: bool) {
+        self.hex_output = hex_output;
+    }
+
+    fn set_buffer_size(&mut self, buffer_size: usize) {
+        self.buffer_size = buffer_size;
+    }
+
+    fn set_repeat_limits(&mut self, repeat_limits: RepeatLimits) {
+        self.repeat_limits = repeat_limits;
+    }
+
+    /// the permutations starting with a smaller char at every position come before `word`
+    fn index_of(&self, word: &[u8]) -> Option<WordRank> {
+        let mut sorted = word.to_vec();
+        sorted.sort_unstable();
+        if sorted != self.chars {
+            return None;
+        }
+        let mut counts = self.counts();
+        let mut index = BigUint::from(0u8);
+        for &chr in word.iter() {
+            for smaller in 0..chr as usize {
+                if counts[smaller] > 0 {
+                    counts[smaller] -= 1;
+                    index += permutations(&counts);
+                    counts[smaller] += 1;
+                }
+            }
+            counts[chr as usize] -= 1;
+        }
+        Some(WordRank {
+            index,
+            wordlist_words: vec![],
+        })
+    }
+
+    /// all the words have the length of the anagram word
+    fn set_length_order(&mut self, _length_order: LengthOrder) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use num_bigint::BigUint;
+
+    use super::AnagramGenerator;
+    use crate::error::CrackenError;
+    use crate::generators::WordGenerator;
+
+    fn gen_str(word_gen: &dyn WordGenerator) -> String {
+        let mut buf = vec![];
+        {
+            let mut out: Box<dyn Write> = Box::new(&mut buf);
+            word_gen.gen(&mut out).unwrap();
+        }
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_anagram_distinct() {
+        let word_gen = AnagramGenerator::new(b"cab").unwrap();
+        assert_eq!(word_gen.combinations(), BigUint::from(6u8));
+        assert_eq!(gen_str(&word_gen), "abc\nacb\nbac\nbca\ncab\ncba\n");
+        assert_eq!(word_gen.output_bytes(), BigUint::from(24u8));
+        let index = word_gen.index_of(b"bca").unwrap().index;
+        assert_eq!(index, BigUint::from(3u8));
+        assert!(word_gen.index_of(b"abb").is_none());
+    }
+
+    #[test]
+    fn test_anagram_repeated() {
+        let word_gen = AnagramGenerator::new(b"baba").unwrap();
+        assert_eq!(word_gen.combinations(), BigUint::from(6u8));
+        assert_eq!(gen_str(&word_gen), "aabb\nabab\nabba\nbaab\nbaba\nbbaa\n");
+
+        // 6! / (2! * 2! * 1! * 1!)
+        let word_gen = AnagramGenerator::new(b"aabble").unwrap();
+        assert_eq!(word_gen.combinations(), BigUint::from(180u8));
+        let out = gen_str(&word_gen);
+        let words: Vec<_> = out.lines().collect();
+        assert_eq!(words.len(), 180);
+        assert!(words.windows(2).all(|pair| pair[0] < pair[1]));
+        for (idx, word) in words.iter().enumerate() {
+            let index = word_gen.index_of(word.as_bytes()).unwrap().index;
+            assert_eq!(index, BigUint::from(idx));
+        }
+
+        let word_gen = AnagramGenerator::new(b"zzz").unwrap();
+        assert_eq!(word_gen.combinations(), BigUint::from(1u8));
+        assert_eq!(gen_str(&word_gen), "zzz\n");
+    }
+
+    #[test]
+    fn test_anagram_errors() {
+        assert!(matches!(
+            AnagramGenerator::new(b""),
+            Err(CrackenError::InvalidLength(_))
+        ));
+        assert!(matches!(
+            AnagramGenerator::new(&[b'a'; 513]),
+            Err(CrackenError::InvalidLength(_))
+        ));
+        assert!(matches!(
+            AnagramGenerator::new(b"a\nb"),
+            Err(CrackenError::Unsupported(_))
+        ));
+    }
+}
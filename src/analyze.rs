@@ -0,0 +1,866 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+use num_bigint::{BigUint, ToBigUint};
+use serde::Serialize;
+
+use crate::charsets::Charset;
+use crate::helpers::{serialize_str, JsonObject};
+use crate::password_entropy::{char_class, password_mask_entropy};
+
+/// counts the charset mask (e.g. ?u?l?l?l?d?d) of every password, memory is bounded by the
+/// number of distinct masks
+#[derive(Default)]
+pub struct MaskStats {
+    total: u64,
+    counts: HashMap<String, u64>,
+}
+
+impl MaskStats {
+    pub fn new() -> MaskStats {
+        MaskStats::default()
+    }
+
+    pub fn add(&mut self, pwd: &[u8]) {
+        let (_, mask) = password_mask_entropy(pwd);
+        *self.counts.entry(mask).or_insert(0) += 1;
+        self.total += 1;
+    }
+
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// returns the `n` most common masks with their counts, ties ordered by mask
+    pub fn top(&self, n: usize) -> Vec<(&str, u64)> {
+        let mut masks: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(mask, &count)| (mask.as_str(), count))
+            .collect();
+        masks.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        masks.truncate(n);
+        masks
+    }
+
+    /// writes the `n` most common masks as `mask<TAB>count<TAB>percent` lines
+    pub fn write<W: Write>(&self, out: &mut W, n: usize) -> Result<(), std::io::Error> {
+        for (mask, count) in self.top(n) {
+            let percent = 100.0 * count as f64 / self.total as f64;
+            writeln!(out, "{}\t{}\t{:.2}%", mask, count, percent)?;
+        }
+        Ok(())
+    }
+
+    /// selects masks until they cover `target_coverage` (0 to 1) of the passwords, greedy by
+    /// hits per word of the mask. masks which don't fit in `max_keyspace` are skipped. every
+    /// password has a single mask so the coverage of the selected masks is never counted twice
+    pub fn select(&self, target_coverage: f64, max_keyspace: Option<&BigUint>) -> MaskSelection {
+        let mut candidates: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(mask, &hits)| (mask.as_str(), hits, mask_keyspace(mask)))
+            .collect();
+        // hits_a / keyspace_a > hits_b / keyspace_b without rounding
+        candidates.sort_unstable_by(|a, b| match (&b.2 * a.1).cmp(&(&a.2 * b.1)) {
+            Ordering::Equal => a.0.cmp(b.0),
+            ord => ord.reverse(),
+        });
+
+        let target = target_coverage * self.total as f64;
+        let mut selection = MaskSelection {
+            masks: vec![],
+            total: self.total,
+            hits: 0,
+            keyspace: 0.to_biguint().unwrap(),
+            target_coverage,
+        };
+        for (mask, hits, keyspace) in candidates {
+            if selection.hits as f64 >= target {
+                break;
+            }
+            let cumulative_keyspace = &selection.keyspace + &keyspace;
+            if matches!(max_keyspace, Some(max) if cumulative_keyspace > *max) {
+                continue;
+            }
+            selection.hits += hits;
+            selection.keyspace = cumulative_keyspace;
+            selection.masks.push(SelectedMask {
+                mask: mask.to_string(),
+                hits,
+                keyspace,
+            });
+        }
+        selection
+    }
+}
+
+/// number of words of a mask of `MaskStats`
+fn mask_keyspace(mask: &str) -> BigUint {
+    mask.chars()
+        .filter(|&ch| ch != '?')
+        .fold(1.to_biguint().unwrap(), |acc, symbol| {
+            acc * Charset::from_symbol(symbol).len
+        })
+}
+
+/// a mask chosen by `MaskStats::select`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectedMask {
+    pub mask: String,
+    /// number of passwords of the mask
+    pub hits: u64,
+    pub keyspace: BigUint,
+}
+
+/// the masks chosen by `MaskStats::select` in selection order
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaskSelection {
+    pub masks: Vec<SelectedMask>,
+    /// number of analyzed passwords
+    pub total: u64,
+    /// number of passwords covered by the masks
+    pub hits: u64,
+    pub keyspace: BigUint,
+    pub target_coverage: f64,
+}
+
+impl MaskSelection {
+    pub fn coverage(&self) -> f64 {
+        self.hits as f64 / self.total.max(1) as f64
+    }
+
+    pub fn reached_target(&self) -> bool {
+        self.hits as f64 >= self.target_coverage * self.total as f64
+    }
+
+    /// writes the masks one per line, e.g. as a masks file of generate -i
+    pub fn write_masks<W: Write>(&self, out: &mut W) -> Result<(), std::io::Error> {
+        for selected in self.masks.iter() {
+            writeln!(out, "{}", selected.mask)?;
+        }
+        Ok(())
+    }
+
+    /// writes `mask<TAB>hits<TAB>cumulative coverage<TAB>cumulative keyspace` lines and a
+    /// summary line
+    pub fn write_report<W: Write>(&self, out: &mut W) -> Result<(), std::io::Error> {
+        let mut hits = 0;
+        let mut keyspace = 0.to_biguint().unwrap();
+        for selected in self.masks.iter() {
+            hits += selected.hits;
+            keyspace += &selected.keyspace;
+            let coverage = 100.0 * hits as f64 / self.total.max(1) as f64;
+            writeln!(
+                out,
+                "{}\t{}\t{:.2}%\t{}",
+                selected.mask, selected.hits, coverage, keyspace
+            )?;
+        }
+        writeln!(
+            out,
+            "{} masks cover {:.2}% of {} passwords with {} words{}",
+            self.masks.len(),
+            100.0 * self.coverage(),
+            self.total,
+            self.keyspace,
+            if self.reached_target() {
+                String::new()
+            } else {
+                format!(
+                    " - target coverage {:.2}% not reached",
+                    100.0 * self.target_coverage
+                )
+            }
+        )
+    }
+}
+
+/// the char classes counted at every position by `FreqStats`, in output order
+pub const CHAR_CLASSES: [char; 5] = ['l', 'u', 'd', 's', 'b'];
+
+/// counts of values keeping at most about `capacity` of them - the least common values are
+/// dropped when twice as many are counted, so the counts are exact only until then
+pub struct BoundedCounts {
+    counts: HashMap<Vec<u8>, u64>,
+    capacity: usize,
+    pruned: bool,
+}
+
+impl BoundedCounts {
+    pub fn new(capacity: usize) -> BoundedCounts {
+        BoundedCounts {
+            counts: HashMap::new(),
+            capacity: capacity.max(1),
+            pruned: false,
+        }
+    }
+
+    pub fn add(&mut self, value: &[u8]) {
+        match self.counts.get_mut(value) {
+            Some(count) => *count += 1,
+            None => {
+                self.counts.insert(value.to_vec(), 1);
+            }
+        }
+        if self.counts.len() >= 2 * self.capacity {
+            let mut counts: Vec<_> = self.counts.drain().collect();
+            counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            counts.truncate(self.capacity);
+            self.counts.extend(counts);
+            self.pruned = true;
+        }
+    }
+
+    /// true if values were dropped, the counts are lower bounds
+    pub fn is_approximate(&self) -> bool {
+        self.pruned
+    }
+
+    /// the `n` most common values with their counts, ties ordered by value
+    pub fn top(&self, n: usize) -> Vec<(&[u8], u64)> {
+        let mut counts: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(value, &count)| (value.as_slice(), count))
+            .collect();
+        counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts.truncate(n);
+        counts
+    }
+}
+
+/// frequencies of a passwords corpus: the lengths, the char classes at every position and the
+/// most common passwords, prefixes and suffixes of `affix_len` bytes. memory is bounded by the
+/// longest password and the `capacity` of the most common values
+pub struct FreqStats {
+    total: u64,
+    lengths: BTreeMap<usize, u64>,
+    /// counts of every class of CHAR_CLASSES at every position
+    positions: Vec<[u64; CHAR_CLASSES.len()]>,
+    passwords: BoundedCounts,
+    prefixes: BoundedCounts,
+    suffixes: BoundedCounts,
+    affix_len: usize,
+}
+
+impl FreqStats {
+    pub fn new(affix_len: usize, capacity: usize) -> FreqStats {
+        FreqStats {
+            total: 0,
+            lengths: BTreeMap::new(),
+            positions: vec![],
+            passwords: BoundedCounts::new(capacity),
+            prefixes: BoundedCounts::new(capacity),
+            suffixes: BoundedCounts::new(capacity),
+            affix_len,
+        }
+    }
+
+    /// counts `pwd`, its prefix and suffix only if it is longer than them
+    pub fn add(&mut self, pwd: &[u8]) {
+        self.total += 1;
+        *self.lengths.entry(pwd.len()).or_insert(0) += 1;
+        if self.positions.len() < pwd.len() {
+            self.positions.resize(pwd.len(), [0; CHAR_CLASSES.len()]);
+        }
+        for (counts, &ch) in self.positions.iter_mut().zip(pwd) {
+            let class = char_class(ch);
+            counts[CHAR_CLASSES.iter().position(|&c| c == class).unwrap()] += 1;
+        }
+        self.passwords.add(pwd);
+        if pwd.len() > self.affix_len {
+            self.prefixes.add(&pwd[..self.affix_len]);
+            self.suffixes.add(&pwd[pwd.len() - self.affix_len..]);
+        }
+    }
+
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// the number of passwords of every length, shortest first
+    pub fn lengths(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.lengths.iter().map(|(&len, &count)| (len, count))
+    }
+
+    /// the counts of the CHAR_CLASSES at every position
+    pub fn positions(&self) -> &[[u64; CHAR_CLASSES.len()]] {
+        &self.positions
+    }
+
+    pub fn top_passwords(&self, n: usize) -> Vec<(&[u8], u64)> {
+        self.passwords.top(n)
+    }
+
+    pub fn top_prefixes(&self, n: usize) -> Vec<(&[u8], u64)> {
+        self.prefixes.top(n)
+    }
+
+    pub fn top_suffixes(&self, n: usize) -> Vec<(&[u8], u64)> {
+        self.suffixes.top(n)
+    }
+
+    /// true if the most common values counts are lower bounds
+    pub fn is_approximate(&self) -> bool {
+        self.passwords.is_approximate()
+            || self.prefixes.is_approximate()
+            || self.suffixes.is_approximate()
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        100.0 * count as f64 / self.total.max(1) as f64
+    }
+
+    /// writes tab separated tables of the lengths, the classes percents at every position and
+    /// the `n` most common passwords, prefixes and suffixes
+    pub fn write<W: Write>(&self, out: &mut W, n: usize) -> Result<(), std::io::Error> {
+        writeln!(out, "passwords: {}", self.total)?;
+        writeln!(out, "\nlength\tcount\tpercent")?;
+        for (len, count) in self.lengths() {
+            writeln!(out, "{}\t{}\t{:.2}%", len, count, self.percent(count))?;
+        }
+        write!(out, "\nposition")?;
+        for class in CHAR_CLASSES {
+            write!(out, "\t?{}", class)?;
+        }
+        writeln!(out)?;
+        // the percents of the passwords at least as long as the position
+        for (pos, counts) in self.positions.iter().enumerate() {
+            let passwords = counts.iter().sum::<u64>();
+            write!(out, "{}", pos + 1)?;
+            for &count in counts {
+                write!(out, "\t{:.2}%", 100.0 * count as f64 / passwords as f64)?;
+            }
+            writeln!(out)?;
+        }
+        let approximate = match self.is_approximate() {
+            true => " (approximate counts)",
+            false => "",
+        };
+        let tables = [
+            ("password", self.top_passwords(n)),
+            ("prefix", self.top_prefixes(n)),
+            ("suffix", self.top_suffixes(n)),
+        ];
+        for (name, top) in tables {
+            writeln!(out, "\n{}\tcount\tpercent{}", name, approximate)?;
+            for (value, count) in top {
+                let value = String::from_utf8_lossy(value);
+                writeln!(out, "{}\t{}\t{:.2}%", value, count, self.percent(count))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// writes the same tables as a json object
+    pub fn write_json<W: Write>(&self, out: &mut W, n: usize) -> Result<(), std::io::Error> {
+        let top = |top: Vec<(&[u8], u64)>| -> Vec<TopJson> {
+            top.into_iter()
+                .map(|(value, count)| TopJson {
+                    value: String::from_utf8_lossy(value).into_owned(),
+                    count,
+                })
+                .collect()
+        };
+        let json = FreqStatsJson {
+            passwords: self.total,
+            lengths: self
+                .lengths()
+                .map(|(length, count)| LengthJson { length, count })
+                .collect(),
+            positions: self
+                .positions
+                .iter()
+                .enumerate()
+                .map(|(pos, counts)| PositionJson {
+                    position: pos + 1,
+                    classes: JsonObject(CHAR_CLASSES.iter().copied().zip(*counts).collect()),
+                })
+                .collect(),
+            affix_len: self.affix_len,
+            top_passwords: top(self.top_passwords(n)),
+            top_prefixes: top(self.top_prefixes(n)),
+            top_suffixes: top(self.top_suffixes(n)),
+            approximate: self.is_approximate(),
+        };
+        serde_json::to_writer(&mut *out, &json)?;
+        writeln!(out)
+    }
+}
+
+/// the json object of `FreqStats::write_json`
+#[derive(Serialize)]
+struct FreqStatsJson {
+    passwords: u64,
+    lengths: Vec<LengthJson>,
+    positions: Vec<PositionJson>,
+    affix_len: usize,
+    top_passwords: Vec<TopJson>,
+    top_prefixes: Vec<TopJson>,
+    top_suffixes: Vec<TopJson>,
+    approximate: bool,
+}
+
+#[derive(Serialize)]
+struct LengthJson {
+    length: usize,
+    count: u64,
+}
+
+/// the counts of every class of CHAR_CLASSES at a position
+#[derive(Serialize)]
+struct PositionJson {
+    position: usize,
+    #[serde(flatten)]
+    classes: JsonObject<char, u64>,
+}
+
+#[derive(Serialize)]
+struct TopJson {
+    value: String,
+    count: u64,
+}
+
+/// the alphabets of builtin charsets whose coverage `CharsetStats` reports, as CHAR_CLASSES
+/// symbols - ?a is every class but ?b and ?b is every byte
+pub const COVERAGE_ALPHABETS: [&str; 10] = [
+    "?l", "?u", "?d", "?s", "?l?d", "?u?d", "?l?u", "?l?u?d", "?a", "?b",
+];
+
+/// the bit of the class of `ch` in a set of CHAR_CLASSES
+fn class_bit(ch: u8) -> u8 {
+    let class = char_class(ch);
+    1 << CHAR_CLASSES.iter().position(|&c| c == class).unwrap()
+}
+
+/// the set of CHAR_CLASSES of an alphabet of COVERAGE_ALPHABETS
+fn alphabet_bits(alphabet: &str) -> u8 {
+    match alphabet {
+        "?a" => 0b01111,
+        "?b" => 0b11111,
+        _ => alphabet
+            .chars()
+            .filter(|&c| c != '?')
+            .map(|c| 1 << CHAR_CLASSES.iter().position(|&class| class == c).unwrap())
+            .sum(),
+    }
+}
+
+/// the smallest alphabet made of builtin charsets of a set of classes, e.g. ?d or [?l?d],
+/// and its number of chars
+fn classes_alphabet(bits: u8) -> (String, usize) {
+    // ?b covers the others
+    if bits & alphabet_bits("?b") & !alphabet_bits("?a") != 0 {
+        return ("?b".to_string(), 256);
+    }
+    let classes: Vec<_> = CHAR_CLASSES
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| bits & (1 << idx) != 0)
+        .map(|(_, &class)| class)
+        .collect();
+    let len = classes
+        .iter()
+        .map(|&class| Charset::from_symbol(class).len)
+        .sum();
+    let symbols: String = classes.iter().map(|class| format!("?{}", class)).collect();
+    match classes.len() {
+        1 => (symbols, len),
+        _ => (format!("[{}]", symbols), len),
+    }
+}
+
+/// the classes of the entries of one length
+#[derive(Debug, Clone, Default)]
+struct LengthClasses {
+    entries: u64,
+    /// the entries by their set of classes
+    sets: [u64; 1 << CHAR_CLASSES.len()],
+    /// the set of classes seen at every position
+    positions: Vec<u8>,
+}
+
+/// the char classes composition of the entries of a wordlist by length: the entries covered by
+/// every alphabet of COVERAGE_ALPHABETS and the classes at every position. memory is bounded
+/// by the longest entry
+#[derive(Default)]
+pub struct CharsetStats {
+    total: u64,
+    lengths: BTreeMap<usize, LengthClasses>,
+}
+
+impl CharsetStats {
+    pub fn new() -> CharsetStats {
+        CharsetStats::default()
+    }
+
+    pub fn add(&mut self, entry: &[u8]) {
+        self.total += 1;
+        let stats = self.lengths.entry(entry.len()).or_default();
+        stats.entries += 1;
+        if stats.positions.is_empty() {
+            stats.positions = vec![0; entry.len()];
+        }
+        let mut set = 0;
+        for (position, &ch) in stats.positions.iter_mut().zip(entry) {
+            let bit = class_bit(ch);
+            *position |= bit;
+            set |= bit;
+        }
+        stats.sets[set as usize] += 1;
+    }
+
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// the lengths of the entries, shortest first
+    pub fn lengths(&self) -> impl Iterator<Item = usize> + '_ {
+        self.lengths.keys().copied()
+    }
+
+    /// the number of entries of `len` bytes
+    pub fn entries(&self, len: usize) -> u64 {
+        self.lengths
+            .get(&len)
+            .map(|stats| stats.entries)
+            .unwrap_or(0)
+    }
+
+    /// the number of entries of `len` bytes made of chars of `alphabet` (of COVERAGE_ALPHABETS)
+    pub fn covered(&self, len: usize, alphabet: &str) -> u64 {
+        let bits = alphabet_bits(alphabet);
+        match self.lengths.get(&len) {
+            Some(stats) => (0..stats.sets.len())
+                .filter(|&set| set as u8 & !bits == 0)
+                .map(|set| stats.sets[set])
+                .sum(),
+            None => 0,
+        }
+    }
+
+    /// the smallest mask of builtin charsets generating every entry of `len` bytes, e.g.
+    /// `?u[?l?d]?d`, and its number of words
+    pub fn mask(&self, len: usize) -> (String, BigUint) {
+        let mut mask = String::new();
+        let mut keyspace = 1.to_biguint().unwrap();
+        for &bits in self
+            .lengths
+            .get(&len)
+            .map(|stats| &stats.positions[..])
+            .unwrap_or(&[])
+        {
+            let (alphabet, size) = classes_alphabet(bits);
+            mask.push_str(&alphabet);
+            keyspace *= size;
+        }
+        (mask, keyspace)
+    }
+
+    /// writes a tab separated table of the percent of the entries of every length covered by
+    /// every alphabet, and a table of the smallest mask of every length
+    pub fn write<W: Write>(&self, out: &mut W) -> Result<(), std::io::Error> {
+        writeln!(out, "entries: {}", self.total)?;
+        write!(out, "\nlength\tentries")?;
+        for alphabet in COVERAGE_ALPHABETS {
+            write!(out, "\t{}", alphabet)?;
+        }
+        writeln!(out)?;
+        for len in self.lengths() {
+            let entries = self.entries(len);
+            write!(out, "{}\t{}", len, entries)?;
+            for alphabet in COVERAGE_ALPHABETS {
+                let percent = 100.0 * self.covered(len, alphabet) as f64 / entries as f64;
+                write!(out, "\t{:.2}%", percent)?;
+            }
+            writeln!(out)?;
+        }
+        writeln!(out, "\nlength\tmask\tkeyspace")?;
+        for len in self.lengths() {
+            let (mask, keyspace) = self.mask(len);
+            writeln!(out, "{}\t{}\t{}", len, mask, keyspace)?;
+        }
+        Ok(())
+    }
+
+    /// writes the same tables as a json object, with the covered entries counts
+    pub fn write_json<W: Write>(&self, out: &mut W) -> Result<(), std::io::Error> {
+        let json = CharsetStatsJson {
+            entries: self.total,
+            lengths: self
+                .lengths()
+                .map(|len| {
+                    let (mask, keyspace) = self.mask(len);
+                    CharsetLengthJson {
+                        length: len,
+                        entries: self.entries(len),
+                        covered: JsonObject(
+                            COVERAGE_ALPHABETS
+                                .iter()
+                                .map(|&alphabet| (alphabet, self.covered(len, alphabet)))
+                                .collect(),
+                        ),
+                        mask,
+                        keyspace,
+                    }
+                })
+                .collect(),
+        };
+        serde_json::to_writer(&mut *out, &json)?;
+        writeln!(out)
+    }
+}
+
+/// the json object of `CharsetStats::write_json`
+#[derive(Serialize)]
+struct CharsetStatsJson {
+    entries: u64,
+    lengths: Vec<CharsetLengthJson>,
+}
+
+#[derive(Serialize)]
+struct CharsetLengthJson {
+    length: usize,
+    entries: u64,
+    /// the entries covered by every alphabet of COVERAGE_ALPHABETS
+    covered: JsonObject<&'static str, u64>,
+    mask: String,
+    #[serde(serialize_with = "serialize_str")]
+    keyspace: BigUint,
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::ToBigUint;
+
+    use crate::analyze::{BoundedCounts, CharsetStats, FreqStats, MaskStats};
+    use crate::test_util::wordlist_fname;
+
+    #[test]
+    fn test_mask_stats() {
+        let mut stats = MaskStats::new();
+        for pwd in [
+            "123456",
+            "password",
+            "abc123",
+            "Aa123456!",
+            "qwerty",
+            "654321",
+        ] {
+            stats.add(pwd.as_bytes());
+        }
+        assert_eq!(stats.total(), 6);
+        assert_eq!(
+            stats.top(3),
+            vec![
+                ("?d?d?d?d?d?d", 2),
+                ("?l?l?l?d?d?d", 1),
+                ("?l?l?l?l?l?l", 1)
+            ]
+        );
+
+        let mut out = vec![];
+        stats.write(&mut out, 1).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "?d?d?d?d?d?d\t2\t33.33%\n");
+    }
+
+    #[test]
+    fn test_freq_stats() {
+        let corpus = std::fs::read_to_string(wordlist_fname("passwords-freq.txt")).unwrap();
+        let mut stats = FreqStats::new(3, 100);
+        for pwd in corpus.lines() {
+            stats.add(pwd.as_bytes());
+        }
+        assert_eq!(stats.total(), 9);
+        assert_eq!(
+            stats.lengths().collect::<Vec<_>>(),
+            vec![(6, 4), (8, 2), (9, 3)]
+        );
+        // ?l ?u ?d ?s ?b
+        assert_eq!(stats.positions().len(), 9);
+        assert_eq!(stats.positions()[0], [5, 1, 3, 0, 0]);
+        assert_eq!(stats.positions()[8], [0, 0, 2, 1, 0]);
+        assert_eq!(
+            stats.top_passwords(2),
+            vec![(&b"123456"[..], 3), (&b"password"[..], 2)]
+        );
+        assert_eq!(
+            stats.top_prefixes(2),
+            vec![(&b"123"[..], 3), (&b"pas"[..], 2)]
+        );
+        assert_eq!(
+            stats.top_suffixes(3),
+            vec![(&b"456"[..], 3), (&b"123"[..], 2), (&b"ord"[..], 2)]
+        );
+        assert!(!stats.is_approximate());
+
+        let mut out = vec![];
+        stats.write(&mut out, 1).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.starts_with("passwords: 9\n\nlength\tcount\tpercent\n6\t4\t44.44%\n"),
+            "{}",
+            out
+        );
+        assert!(out
+            .contains("\nposition\t?l\t?u\t?d\t?s\t?b\n1\t55.56%\t11.11%\t33.33%\t0.00%\t0.00%\n"));
+        assert!(
+            out.ends_with("\nsuffix\tcount\tpercent\n456\t3\t33.33%\n"),
+            "{}",
+            out
+        );
+
+        let mut out = vec![];
+        stats.write_json(&mut out, 1).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.starts_with("{\"passwords\":9,\"lengths\":[{\"length\":6,\"count\":4},"),
+            "{}",
+            out
+        );
+        assert!(out.contains("{\"position\":1,\"l\":5,\"u\":1,\"d\":3,\"s\":0,\"b\":0}"));
+        assert!(out.ends_with(
+            "\"top_suffixes\":[{\"value\":\"456\",\"count\":3}],\"approximate\":false}\n"
+        ));
+    }
+
+    #[test]
+    fn test_charset_stats() {
+        let mut stats = CharsetStats::new();
+        for entry in ["abc", "a1c", "123", "Ab!", "ab\x01", "hello", "12345"] {
+            stats.add(entry.as_bytes());
+        }
+        assert_eq!(stats.total(), 7);
+        assert_eq!(stats.lengths().collect::<Vec<_>>(), vec![3, 5]);
+        assert_eq!(stats.entries(3), 5);
+        let covered: Vec<_> = ["?l", "?d", "?l?d", "?a", "?b"]
+            .iter()
+            .map(|alphabet| stats.covered(3, alphabet))
+            .collect();
+        assert_eq!(covered, vec![1, 1, 3, 4, 5]);
+        assert_eq!(stats.covered(5, "?l"), 1);
+        assert_eq!(stats.covered(5, "?l?u?d"), 2);
+        assert_eq!(stats.covered(4, "?b"), 0);
+
+        // the \x01 at the 3rd position needs ?b
+        assert_eq!(
+            stats.mask(3),
+            (
+                "[?l?u?d][?l?d]?b".to_string(),
+                62.to_biguint().unwrap() * 36u32 * 256u32
+            )
+        );
+        assert_eq!(
+            stats.mask(5),
+            (
+                "[?l?d][?l?d][?l?d][?l?d][?l?d]".to_string(),
+                36u64.pow(5).to_biguint().unwrap()
+            )
+        );
+
+        let mut out = vec![];
+        stats.write(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.starts_with(
+                "entries: 7\n\nlength\tentries\t?l\t?u\t?d\t?s\t?l?d\t?u?d\t?l?u\t?l?u?d\t?a\t?b\n\
+             3\t5\t20.00%\t0.00%\t20.00%\t0.00%\t60.00%\t20.00%\t20.00%\t60.00%\t80.00%\t100.00%\n"
+            ),
+            "{}",
+            out
+        );
+        assert!(
+            out.ends_with("\n5\t[?l?d][?l?d][?l?d][?l?d][?l?d]\t60466176\n"),
+            "{}",
+            out
+        );
+
+        let mut out = vec![];
+        stats.write_json(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with(
+            "{\"entries\":7,\"lengths\":[{\"length\":3,\"entries\":5,\"covered\":{\"?l\":1,\"?u\":0,\"?d\":1,"
+        ), "{}", out);
+        assert!(out.ends_with(
+            "\"mask\":\"[?l?d][?l?d][?l?d][?l?d][?l?d]\",\"keyspace\":\"60466176\"}]}\n"
+        ));
+    }
+
+    #[test]
+    fn test_bounded_counts() {
+        let mut counts = BoundedCounts::new(2);
+        for value in ["a", "a", "a", "b", "b", "c"] {
+            counts.add(value.as_bytes());
+        }
+        assert!(!counts.is_approximate());
+        // the 4th distinct value keeps the 2 most common ones
+        counts.add(b"d");
+        assert!(counts.is_approximate());
+        assert_eq!(counts.top(5), vec![(&b"a"[..], 3), (&b"b"[..], 2)]);
+    }
+
+    #[test]
+    fn test_mask_stats_select() {
+        let mut stats = MaskStats::new();
+        for (pwd, count) in [
+            ("123456", 50),
+            ("password", 30),
+            ("abc123", 10),
+            ("Aa123456!", 5),
+            ("123457", 5),
+        ] {
+            for _ in 0..count {
+                stats.add(pwd.as_bytes());
+            }
+        }
+        let masks = |target, max_keyspace: Option<u64>| {
+            let max_keyspace = max_keyspace.map(|max| max.to_biguint().unwrap());
+            let selection = stats.select(target, max_keyspace.as_ref());
+            let masks: Vec<_> = selection.masks.iter().map(|m| m.mask.clone()).collect();
+            (masks, selection)
+        };
+
+        // the smallest keyspace reaching 60% is all the digits and the ?l?l?l?d?d?d words
+        let (selected, selection) = masks(0.6, None);
+        assert_eq!(selected, vec!["?d?d?d?d?d?d", "?l?l?l?d?d?d"]);
+        assert_eq!(selection.hits, 65);
+        assert_eq!(
+            selection.keyspace,
+            (1_000_000u64 + 17_576_000).to_biguint().unwrap()
+        );
+        assert!(selection.reached_target());
+
+        let (selected, selection) = masks(0.9, None);
+        assert_eq!(
+            selected,
+            vec![
+                "?d?d?d?d?d?d",
+                "?l?l?l?d?d?d",
+                "?u?l?d?d?d?d?d?d?s",
+                "?l?l?l?l?l?l?l?l"
+            ]
+        );
+        assert_eq!(selection.coverage(), 1.0);
+
+        // the other masks don't fit in the budget
+        let (selected, selection) = masks(0.6, Some(5_000_000));
+        assert_eq!(selected, vec!["?d?d?d?d?d?d"]);
+        assert!(!selection.reached_target());
+
+        let mut out = vec![];
+        selection.write_report(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "?d?d?d?d?d?d\t55\t55.00%\t1000000\n\
+             1 masks cover 55.00% of 100 passwords with 1000000 words - target coverage 60.00% not reached\n"
+        );
+        let mut out = vec![];
+        selection.write_masks(&mut out).unwrap();
+        assert_eq!(out, b"?d?d?d?d?d?d\n");
+    }
+}
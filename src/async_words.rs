@@ -0,0 +1,146 @@
+//! async output of the generators for tokio runtimes (`async` feature)
+use std::io;
+use std::thread;
+
+use num_bigint::BigUint;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::builder::GeneratorBuilder;
+use crate::error::CrackenError;
+use crate::generators::WordGenerator;
+use crate::words::take_words;
+
+/// number of generated chunks queued before the generating thread blocks
+const CHUNKS_QUEUE_SIZE: usize = 16;
+
+/// number of chunks written between yields to the runtime, when neither the generator nor the
+/// output is ever pending
+const YIELD_CHUNKS: usize = 16;
+
+/// the async counterpart of `Words`, pulling the chunks of a generator running on its own
+/// thread without blocking the runtime. the generator stops at its next write once
+/// `AsyncWords` is dropped
+pub struct AsyncWords {
+    combinations: BigUint,
+    chunks: mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl AsyncWords {
+    /// builds the generator on a new thread, returning once it is ready to generate
+    pub async fn new(builder: GeneratorBuilder<'static>) -> Result<AsyncWords, CrackenError> {
+        AsyncWords::from_generator(move || builder.build()).await
+    }
+
+    /// same as `new` for generators the builder doesn't support, `build` runs on the new thread
+    pub async fn from_generator<F>(build: F) -> Result<AsyncWords, CrackenError>
+    where
+        F: FnOnce() -> Result<Box<dyn WordGenerator>, CrackenError> + Send + 'static,
+    {
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let (chunks_tx, chunks_rx) = mpsc::channel(CHUNKS_QUEUE_SIZE);
+        thread::spawn(move || {
+            // generators share their wordlists with `Rc`, so they must stay on this thread
+            let word_gen = match build() {
+                Ok(word_gen) => word_gen,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            if ready_tx.send(Ok(word_gen.combinations())).is_err() {
+                return;
+            }
+            let mut out: Box<dyn io::Write> = Box::new(ChannelWriter(chunks_tx));
+            // fails only once `AsyncWords` is dropped
+            let _ = word_gen.gen(&mut out);
+        });
+
+        match ready_rx.await {
+            Ok(Ok(combinations)) => Ok(AsyncWords {
+                combinations,
+                chunks: chunks_rx,
+                chunk: vec![],
+                pos: 0,
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(CrackenError::Io(io::Error::other(
+                "the generator thread panicked",
+            ))),
+        }
+    }
+
+    /// number of words of the generator, including the already pulled ones
+    pub fn combinations(&self) -> &BigUint {
+        &self.combinations
+    }
+
+    /// same as `Words::next_chunk`, awaiting the generator
+    pub async fn next_chunk(&mut self, buf: &mut Vec<u8>, max_bytes: usize) -> usize {
+        buf.clear();
+        let mut count = 0;
+        while let Some(len) = self.next_len().await {
+            if count > 0 && buf.len() + len + 1 > max_bytes {
+                break;
+            }
+            let chunk = &self.chunk[self.pos..];
+            let (consumed, words) = take_words(chunk, len, buf, max_bytes, usize::MAX);
+            self.pos += consumed;
+            count += words;
+        }
+        count
+    }
+
+    /// writes the remaining words to `out` and flushes it
+    pub async fn write_to<W: AsyncWrite + Unpin>(mut self, mut out: W) -> Result<(), CrackenError> {
+        out.write_all(&self.chunk[self.pos..]).await?;
+        let mut written = 0;
+        while let Some(chunk) = self.chunks.recv().await {
+            out.write_all(&chunk).await?;
+            written += 1;
+            if written % YIELD_CHUNKS == 0 {
+                tokio::task::yield_now().await;
+            }
+        }
+        out.flush().await?;
+        Ok(())
+    }
+
+    /// length of the next word, receiving the next chunk if the current one is consumed
+    async fn next_len(&mut self) -> Option<usize> {
+        loop {
+            if let Some(len) = self.chunk[self.pos..].iter().position(|&c| c == b'\n') {
+                return Some(len);
+            }
+            // the generators never split a word between writes
+            self.chunk = self.chunks.recv().await?;
+            self.pos = 0;
+        }
+    }
+}
+
+impl GeneratorBuilder<'static> {
+    /// generates the words into `out` like `WordGenerator::gen`, awaiting the writes. the
+    /// generator is built and runs on its own thread
+    pub async fn gen_async<W: AsyncWrite + Unpin>(&self, out: W) -> Result<(), CrackenError> {
+        AsyncWords::new(self.clone()).await?.write_to(out).await
+    }
+}
+
+/// sends every write as a chunk to `AsyncWords`
+struct ChannelWriter(mpsc::Sender<Vec<u8>>);
+
+impl io::Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(buf.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,720 @@
+use std::borrow::Cow;
+use std::cmp::min;
+use std::io::{Error, Write};
+
+use num_bigint::{BigUint, ToBigUint};
+
+use crate::charsets::CustomCharset;
+use crate::error::CrackenError;
+use crate::generators::{
+    get_word_generator_with_options, LengthOrder, RepeatLimits, WordGenerator, WordRank,
+    WordlistGenerator,
+};
+use crate::mask::parse_mask;
+use crate::wordlists::{parse_wordlist_arg, Wordlist, WordlistOptions};
+use crate::{BUFFER_SIZE, MAX_WORD_SIZE};
+
+/// a wordlist of the mask (?w1 to ?w9) given to `GeneratorBuilder`
+#[derive(Clone)]
+enum WordlistSource<'a> {
+    /// a wordlist file, same syntax as -w
+    File(Cow<'a, str>),
+    Memory(Vec<Vec<u8>>),
+}
+
+/// builds a `WordGenerator` option by option, the combination is validated by `build`. the
+/// options are either borrowed or owned strings, a `GeneratorBuilder<'static>` can be kept in
+/// long lived structs and the built generator never borrows them
+///
+/// ```
+/// use std::io::Write;
+///
+/// use cracken::builder::GeneratorBuilder;
+///
+/// let word_gen = GeneratorBuilder::new()
+///     .mask("?w1?d")
+///     .wordlist_in_memory(vec![&b"pass"[..], b"word"])
+///     .skip(2)
+///     .limit(3)
+///     .delimiter(b",")
+///     .build()
+///     .unwrap();
+///
+/// let mut buf = vec![];
+/// {
+///     let mut out: Box<dyn Write> = Box::new(&mut buf);
+///     word_gen.gen(&mut out).unwrap();
+/// }
+/// assert_eq!(buf, b"pass2,pass3,pass4,");
+/// ```
+#[derive(Clone, Default)]
+pub struct GeneratorBuilder<'a> {
+    mask: Option<Cow<'a, str>>,
+    minlen: Option<usize>,
+    maxlen: Option<usize>,
+    custom_charsets: Vec<Cow<'a, str>>,
+    wordlists: Vec<WordlistSource<'a>>,
+    skip: u64,
+    limit: Option<u64>,
+    delimiter: Option<Vec<u8>>,
+    prefix: Vec<u8>,
+    suffix: Vec<u8>,
+}
+
+impl<'a> GeneratorBuilder<'a> {
+    pub fn new() -> GeneratorBuilder<'a> {
+        Self::default()
+    }
+
+    pub fn mask<S: Into<Cow<'a, str>>>(mut self, mask: S) -> Self {
+        self.mask = Some(mask.into());
+        self
+    }
+
+    /// minimum length of the words, only for masks without wordlists
+    pub fn minlen(mut self, minlen: usize) -> Self {
+        self.minlen = Some(minlen);
+        self
+    }
+
+    /// maximum length of the words, only for masks without wordlists
+    pub fn maxlen(mut self, maxlen: usize) -> Self {
+        self.maxlen = Some(maxlen);
+        self
+    }
+
+    /// adds the next custom charset (?1 to ?9)
+    pub fn custom_charset<S: Into<Cow<'a, str>>>(mut self, chars: S) -> Self {
+        self.custom_charsets.push(chars.into());
+        self
+    }
+
+    /// adds the next wordlist (?w1 to ?w9) from a file, same syntax as -w (e.g. `names.txt:latin1`)
+    pub fn wordlist_file<S: Into<Cow<'a, str>>>(mut self, fname: S) -> Self {
+        self.wordlists.push(WordlistSource::File(fname.into()));
+        self
+    }
+
+    /// adds the next wordlist (?w1 to ?w9) from the given words, empty words are skipped
+    pub fn wordlist_in_memory<'w, I: IntoIterator<Item = &'w [u8]>>(mut self, words: I) -> Self {
+        let words = words.into_iter().map(|word| word.to_vec()).collect();
+        self.wordlists.push(WordlistSource::Memory(words));
+        self
+    }
+
+    /// skips the first `skip` generated words
+    pub fn skip(mut self, skip: u64) -> Self {
+        self.skip = skip;
+        self
+    }
+
+    /// stops after `limit` words (after the skipped ones)
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// ends every word with `delimiter` instead of a newline
+    pub fn delimiter(mut self, delimiter: &[u8]) -> Self {
+        self.delimiter = Some(delimiter.to_vec());
+        self
+    }
+
+    /// starts every word with `prefix`, outside of the mask
+    pub fn prefix(mut self, prefix: &[u8]) -> Self {
+        self.prefix = prefix.to_vec();
+        self
+    }
+
+    /// ends every word with `suffix` (before the delimiter), outside of the mask
+    pub fn suffix(mut self, suffix: &[u8]) -> Self {
+        self.suffix = suffix.to_vec();
+        self
+    }
+
+    pub fn build(self) -> Result<Box<dyn WordGenerator>, CrackenError> {
+        let mask = match &self.mask {
+            Some(mask) => mask.as_ref(),
+            None => return Err(CrackenError::Mask("no mask given".to_string())),
+        };
+        if matches!(&self.delimiter, Some(delimiter) if delimiter.is_empty()) {
+            return Err(CrackenError::Unsupported(
+                "the delimiter must not be empty".to_string(),
+            ));
+        }
+        validate_affix(&self.prefix, "prefix")?;
+        validate_affix(&self.suffix, "suffix")?;
+
+        let in_memory = self
+            .wordlists
+            .iter()
+            .any(|wordlist| matches!(wordlist, WordlistSource::Memory(_)));
+        let custom_charsets: Vec<_> = self
+            .custom_charsets
+            .iter()
+            .map(|chars| CustomCharset::Chars(chars))
+            .collect();
+        let generator: Box<dyn WordGenerator> = if in_memory {
+            if self.minlen.is_some() || self.maxlen.is_some() {
+                return Err(CrackenError::Unsupported(
+                    "cannot set minlen or maxlen with wordlists".to_string(),
+                ));
+            }
+            let mask_ops = parse_mask(mask)?;
+            let mut wordlists = vec![];
+            for (idx, source) in self.wordlists.into_iter().enumerate() {
+                wordlists.push(match source {
+                    WordlistSource::File(arg) => {
+                        let (fname, mut options) =
+                            parse_wordlist_arg(&arg, &WordlistOptions::default());
+                        options.max_len = options
+                            .max_len
+                            .min(MAX_WORD_SIZE.saturating_sub(mask_ops.len()));
+                        let wordlist = Wordlist::from_file_with_options(&fname, &options)?;
+                        (fname, wordlist)
+                    }
+                    WordlistSource::Memory(words) => {
+                        let name = format!("?w{}", idx + 1);
+                        match Wordlist::from_words(words.iter().map(|word| word.as_slice())) {
+                            Ok(wordlist) => (name, wordlist),
+                            Err(e) => return Err(CrackenError::wordlist(name, e.to_string())),
+                        }
+                    }
+                });
+            }
+            Box::new(WordlistGenerator::from_wordlists(
+                mask_ops,
+                wordlists,
+                &custom_charsets,
+            )?)
+        } else {
+            let fnames: Vec<_> = self
+                .wordlists
+                .iter()
+                .map(|wordlist| match wordlist {
+                    WordlistSource::File(fname) => fname.as_ref(),
+                    WordlistSource::Memory(_) => unreachable!("in memory wordlists are loaded"),
+                })
+                .collect();
+            get_word_generator_with_options(
+                mask,
+                self.minlen,
+                self.maxlen,
+                &custom_charsets,
+                &fnames,
+                &[],
+            )?
+        };
+
+        if self.skip == 0
+            && self.limit.is_none()
+            && self.delimiter.is_none()
+            && self.prefix.is_empty()
+            && self.suffix.is_empty()
+        {
+            return Ok(generator);
+        }
+        let mut windowed = WindowedGenerator {
+            inner: generator,
+            skip: self.skip,
+            limit: self.limit,
+            delimiter: self.delimiter,
+            prefix: self.prefix,
+            suffix: self.suffix,
+        };
+        windowed.set_buffer_size(BUFFER_SIZE);
+        Ok(Box::new(windowed))
+    }
+}
+
+/// the prefix and suffix are part of the lines, they can't contain a newline
+fn validate_affix(affix: &[u8], name: &str) -> Result<(), CrackenError> {
+    if affix.contains(&b'\n') {
+        return Err(CrackenError::Unsupported(format!(
+            "the {} must not contain a newline",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// limits `generator` to the words `skip..skip + limit`, same as `GeneratorBuilder::skip` and
+/// `GeneratorBuilder::limit` for an already built generator
+pub fn window(
+    generator: Box<dyn WordGenerator>,
+    skip: u64,
+    limit: Option<u64>,
+) -> Box<dyn WordGenerator> {
+    Box::new(WindowedGenerator {
+        inner: generator,
+        skip,
+        limit,
+        delimiter: None,
+        prefix: vec![],
+        suffix: vec![],
+    })
+}
+
+/// wraps every word of `generator` with `prefix` and `suffix`, same as
+/// `GeneratorBuilder::prefix` and `GeneratorBuilder::suffix` for an already built generator
+pub fn affix(
+    generator: Box<dyn WordGenerator>,
+    prefix: &[u8],
+    suffix: &[u8],
+) -> Result<Box<dyn WordGenerator>, CrackenError> {
+    validate_affix(prefix, "prefix")?;
+    validate_affix(suffix, "suffix")?;
+    let mut windowed = WindowedGenerator {
+        inner: generator,
+        skip: 0,
+        limit: None,
+        delimiter: None,
+        prefix: prefix.to_vec(),
+        suffix: suffix.to_vec(),
+    };
+    windowed.set_buffer_size(BUFFER_SIZE);
+    Ok(Box::new(windowed))
+}
+
+/// generates the words `skip..skip + limit` of `inner` between `prefix` and `suffix`, ending
+/// each one with `delimiter`
+struct WindowedGenerator {
+    inner: Box<dyn WordGenerator>,
+    skip: u64,
+    limit: Option<u64>,
+    delimiter: Option<Vec<u8>>,
+    prefix: Vec<u8>,
+    suffix: Vec<u8>,
+}
+
+impl WindowedGenerator {
+    /// number of words out of `combs` left after skip and limit
+    fn window(&self, combs: BigUint) -> BigUint {
+        let skip = self.skip.to_biguint().unwrap();
+        let combs = if combs > skip {
+            combs - skip
+        } else {
+            0.to_biguint().unwrap()
+        };
+        match self.limit {
+            Some(limit) => min(combs, limit.to_biguint().unwrap()),
+            None => combs,
+        }
+    }
+
+    fn affix_len(&self) -> usize {
+        self.prefix.len() + self.suffix.len()
+    }
+
+    fn delimiter_len(&self) -> usize {
+        self.delimiter
+            .as_ref()
+            .map_or(1, |delimiter| delimiter.len())
+    }
+}
+
+impl WordGenerator for WindowedGenerator {
+    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), CrackenError> {
+        self.gen_range(0, u64::MAX, out.as_mut())?;
+        Ok(())
+    }
+
+    /// the range of the words of the window, the inner generator seeks to its start
+    fn gen_range(&self, start: u64, end: u64, out: &mut dyn Write) -> Result<u64, Error> {
+        let window_end = match self.limit {
+            Some(limit) => self.skip.saturating_add(limit),
+            None => u64::MAX,
+        };
+        let start = self.skip.saturating_add(start);
+        let end = min(self.skip.saturating_add(end), window_end);
+        let mut affixed = AffixWriter {
+            out,
+            delimiter: self.delimiter.as_deref(),
+            prefix: &self.prefix,
+            suffix: &self.suffix,
+            word_start: true,
+            buf: vec![],
+        };
+        self.inner.gen_range(start, end, &mut affixed)
+    }
+
+    fn combinations(&self) -> BigUint {
+        self.window(self.inner.combinations())
+    }
+
+    fn length_combinations(&self) -> Vec<(usize, BigUint)> {
+        // the lengths are generated one after the other, in this order
+        let zero = 0.to_biguint().unwrap();
+        let mut skip = self.skip.to_biguint().unwrap();
+        let mut left = self.limit.map(|limit| limit.to_biguint().unwrap());
+        let mut length_combs = vec![];
+        for (len, mut combs) in self.inner.length_combinations() {
+            if combs <= skip {
+                skip -= combs;
+                continue;
+            }
+            combs -= &skip;
+            skip = zero.clone();
+            if let Some(left) = left.as_mut() {
+                combs = min(combs, left.clone());
+                *left -= &combs;
+            }
+            if combs > zero {
+                length_combs.push((len + self.affix_len(), combs));
+            }
+        }
+        length_combs
+    }
+
+    fn output_bytes(&self) -> BigUint {
+        let length_combs = self.length_combinations();
+        if !length_combs.is_empty() {
+            return length_combs
+                .into_iter()
+                .map(|(len, combs)| combs * (len + self.delimiter_len()))
+                .sum();
+        }
+        // the lengths aren't fixed, the words left are assumed of the average length
+        let inner_combs = self.inner.combinations();
+        let words_bytes = self.inner.output_bytes() - &inner_combs;
+        let combs = self.combinations();
+        words_bytes * &combs / inner_combs + combs * (self.affix_len() + self.delimiter_len())
+    }
+
+    fn set_hex_output(&mut self, hex_output: bool) {
+        self.inner.set_hex_output(hex_output);
+    }
+
+    /// the inner generator buffers less with a prefix or suffix, so the words written at once
+    /// are still about `buffer_size` bytes
+    fn set_buffer_size(&mut self, buffer_size: usize) {
+        let combs = self.inner.combinations();
+        if self.affix_len() == 0 || combs.bits() == 0 {
+            self.inner.set_buffer_size(buffer_size);
+            return;
+        }
+        // average line length of the inner words including the newline
+        let line_len = (self.inner.output_bytes() / &combs).to_u64_digits();
+        let line_len = line_len.first().copied().unwrap_or(1).max(1) as usize;
+        let buffer_size = buffer_size * line_len / (line_len + self.affix_len());
+        self.inner.set_buffer_size(buffer_size.max(1));
+    }
+
+    fn set_repeat_limits(&mut self, repeat_limits: RepeatLimits) {
+        self.inner.set_repeat_limits(repeat_limits);
+    }
+
+    fn set_length_order(&mut self, length_order: LengthOrder) {
+        self.inner.set_length_order(length_order);
+    }
+
+    fn index_of(&self, word: &[u8]) -> Option<WordRank> {
+        let rank = self.inner.index_of(word)?;
+        let skip = self.skip.to_biguint().unwrap();
+        if rank.index < skip {
+            return None;
+        }
+        let index = rank.index - skip;
+        if matches!(self.limit, Some(limit) if index >= limit.to_biguint().unwrap()) {
+            return None;
+        }
+        Some(WordRank {
+            index,
+            wordlist_words: rank.wordlist_words,
+        })
+    }
+}
+
+/// writes every word to `out` between `prefix` and `suffix`, ending it with `delimiter`
+struct AffixWriter<'w> {
+    out: &'w mut dyn Write,
+    delimiter: Option<&'w [u8]>,
+    prefix: &'w [u8],
+    suffix: &'w [u8],
+    /// true iff the next byte starts a word, words may be split between writes
+    word_start: bool,
+    buf: Vec<u8>,
+}
+
+impl Write for AffixWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.buf.clear();
+        for line in buf.split_inclusive(|&c| c == b'\n') {
+            let word_end = line.last() == Some(&b'\n');
+            if self.word_start {
+                self.buf.extend_from_slice(self.prefix);
+            }
+            if word_end {
+                self.buf.extend_from_slice(&line[..line.len() - 1]);
+                self.buf.extend_from_slice(self.suffix);
+                self.buf.extend_from_slice(self.delimiter.unwrap_or(b"\n"));
+            } else {
+                self.buf.extend_from_slice(line);
+            }
+            self.word_start = word_end;
+        }
+        self.out.write_all(&self.buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use num_bigint::ToBigUint;
+
+    use super::GeneratorBuilder;
+    use crate::charsets::CustomCharset;
+    use crate::error::CrackenError;
+    use crate::generators::{get_word_generator, WordGenerator};
+    use crate::test_util::wordlist_fname;
+
+    fn gen_to_vec(word_gen: &dyn WordGenerator) -> Vec<u8> {
+        let mut buf = vec![];
+        {
+            let mut out: Box<dyn Write> = Box::new(&mut buf);
+            word_gen.gen(&mut out).unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn test_builder_legacy_equivalence() {
+        let wordlist1 = wordlist_fname("wordlist1.txt");
+        let wordlist2 = wordlist_fname("wordlist2.txt");
+        let wordlists = vec![wordlist1.to_str().unwrap(), wordlist2.to_str().unwrap()];
+        let charsets = vec![CustomCharset::Chars("!@#")];
+        let cases = vec![
+            ("?d?l", None, None),
+            ("?u?l?d?1", Some(2), Some(3)),
+            ("?w1?d?w2?1", None, None),
+            ("?K{3}", None, None),
+        ];
+        for (mask, minlen, maxlen) in cases {
+            let legacy = get_word_generator(mask, minlen, maxlen, &charsets, &wordlists).unwrap();
+            let mut builder = GeneratorBuilder::new()
+                .mask(mask)
+                .custom_charset("!@#")
+                .wordlist_file(wordlists[0])
+                .wordlist_file(wordlists[1]);
+            if let Some(minlen) = minlen {
+                builder = builder.minlen(minlen);
+            }
+            if let Some(maxlen) = maxlen {
+                builder = builder.maxlen(maxlen);
+            }
+            let built = builder.build().unwrap();
+            assert!(
+                gen_to_vec(built.as_ref()) == gen_to_vec(legacy.as_ref()),
+                "{}",
+                mask
+            );
+            assert_eq!(built.combinations(), legacy.combinations(), "{}", mask);
+            assert_eq!(built.output_bytes(), legacy.output_bytes(), "{}", mask);
+        }
+
+        // the same words from memory
+        let words = std::fs::read(&wordlist1).unwrap();
+        let built = GeneratorBuilder::new()
+            .mask("?w1?d")
+            .wordlist_in_memory(words.split(|&c| c == b'\n'))
+            .build()
+            .unwrap();
+        let legacy = get_word_generator("?w1?d", None, None, &[], &wordlists[..1]).unwrap();
+        assert!(gen_to_vec(built.as_ref()) == gen_to_vec(legacy.as_ref()));
+    }
+
+    /// keeps an owned builder and generator around like a long lived struct of a library user
+    struct Session {
+        builder: GeneratorBuilder<'static>,
+        word_gen: Box<dyn WordGenerator>,
+    }
+
+    fn new_session(digits: usize) -> Session {
+        let mask = format!("?1{}", "?d".repeat(digits));
+        let builder = GeneratorBuilder::new()
+            .mask(mask)
+            .custom_charset(String::from("ab"))
+            .limit(3);
+        let word_gen = GeneratorBuilder::new()
+            .mask("?1?d")
+            .custom_charset("ab")
+            .build();
+        Session {
+            builder,
+            word_gen: word_gen.unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_builder_owned() {
+        let session = new_session(2);
+        assert_eq!(gen_to_vec(session.word_gen.as_ref()).len(), 20 * 3);
+        let word_gen = session.builder.build().unwrap();
+        assert_eq!(gen_to_vec(word_gen.as_ref()), b"a00\na01\na02\n");
+    }
+
+    #[test]
+    fn test_builder_window() {
+        let build = |skip: u64, limit: Option<u64>| {
+            let mut builder = GeneratorBuilder::new().mask("?d?d").minlen(1).skip(skip);
+            if let Some(limit) = limit {
+                builder = builder.limit(limit);
+            }
+            builder.build().unwrap()
+        };
+
+        let word_gen = build(8, Some(4));
+        assert_eq!(gen_to_vec(word_gen.as_ref()), b"8\n9\n00\n01\n");
+        assert_eq!(word_gen.combinations(), 4.to_biguint().unwrap());
+        assert_eq!(
+            word_gen.length_combinations(),
+            vec![(1, 2.to_biguint().unwrap()), (2, 2.to_biguint().unwrap())]
+        );
+        assert_eq!(word_gen.output_bytes(), 10.to_biguint().unwrap());
+        assert_eq!(
+            word_gen.index_of(b"9").unwrap().index,
+            1.to_biguint().unwrap()
+        );
+        assert!(word_gen.index_of(b"7").is_none());
+        assert!(word_gen.index_of(b"02").is_none());
+        // the range is within the window
+        let mut buf = vec![];
+        assert_eq!(word_gen.gen_range(1, 10, &mut buf).unwrap(), 3);
+        assert_eq!(buf, b"9\n00\n01\n");
+
+        let word_gen = build(105, None);
+        assert_eq!(gen_to_vec(word_gen.as_ref()), b"95\n96\n97\n98\n99\n");
+        assert_eq!(word_gen.combinations(), 5.to_biguint().unwrap());
+
+        let word_gen = build(200, Some(10));
+        assert_eq!(gen_to_vec(word_gen.as_ref()), b"");
+        assert_eq!(word_gen.combinations(), 0.to_biguint().unwrap());
+
+        // a limit stops the generation, even within a single batch
+        let word_gen = build(0, Some(0));
+        assert_eq!(gen_to_vec(word_gen.as_ref()), b"");
+    }
+
+    #[test]
+    fn test_builder_delimiter() {
+        let word_gen = GeneratorBuilder::new()
+            .mask("?w1")
+            .wordlist_in_memory(vec![&b"ab"[..], b"", b"cde"])
+            .delimiter(b"\0")
+            .build()
+            .unwrap();
+        assert_eq!(gen_to_vec(word_gen.as_ref()), b"ab\0cde\0");
+        assert_eq!(word_gen.output_bytes(), 7.to_biguint().unwrap());
+
+        let word_gen = GeneratorBuilder::new()
+            .mask("?d")
+            .delimiter(b", ")
+            .limit(3)
+            .build()
+            .unwrap();
+        assert_eq!(gen_to_vec(word_gen.as_ref()), b"0, 1, 2, ");
+        assert_eq!(word_gen.output_bytes(), 9.to_biguint().unwrap());
+    }
+
+    #[test]
+    fn test_builder_affix() {
+        let word_gen = GeneratorBuilder::new()
+            .mask("?w1?d")
+            .wordlist_in_memory(vec![&b"ab"[..], b"cde"])
+            .prefix(b"<")
+            .suffix(b"\0>")
+            .delimiter(b", ")
+            .skip(9)
+            .limit(2)
+            .build()
+            .unwrap();
+        assert_eq!(gen_to_vec(word_gen.as_ref()), b"<ab9\0>, <cde0\0>, ");
+        assert_eq!(word_gen.output_bytes(), 17.to_biguint().unwrap());
+        assert_eq!(word_gen.combinations(), 2.to_biguint().unwrap());
+
+        // words split between the writes of a small buffer
+        let mut word_gen = GeneratorBuilder::new()
+            .mask("?l?d?d")
+            .prefix(b"pre")
+            .suffix(b"suf")
+            .build()
+            .unwrap();
+        word_gen.set_buffer_size(7);
+        let out = gen_to_vec(word_gen.as_ref());
+        assert_eq!(out.len(), 26 * 100 * 10);
+        assert!(out.starts_with(b"prea00suf\nprea01suf\n"));
+        assert!(out.ends_with(b"prez99suf\n"));
+        assert_eq!(
+            word_gen.output_bytes(),
+            (out.len() as u32).to_biguint().unwrap()
+        );
+        assert_eq!(
+            word_gen.length_combinations(),
+            vec![(9, 2600.to_biguint().unwrap())]
+        );
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let err = GeneratorBuilder::new().build().err().unwrap();
+        assert!(matches!(err, CrackenError::Mask(_)), "{:?}", err);
+
+        let err = GeneratorBuilder::new()
+            .mask("?d")
+            .delimiter(b"")
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err, CrackenError::Unsupported(_)), "{:?}", err);
+
+        let err = GeneratorBuilder::new()
+            .mask("?d")
+            .suffix(b"a\nb")
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err, CrackenError::Unsupported(_)), "{:?}", err);
+
+        let err = GeneratorBuilder::new()
+            .mask("?w1")
+            .wordlist_in_memory(vec![&b"abc"[..]])
+            .minlen(2)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err, CrackenError::Unsupported(_)), "{:?}", err);
+
+        let err = GeneratorBuilder::new()
+            .mask("?w1?w2")
+            .wordlist_in_memory(vec![&b"abc"[..]])
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err, CrackenError::Mask(_)), "{:?}", err);
+
+        let err = GeneratorBuilder::new()
+            .mask("?w1")
+            .wordlist_in_memory(vec![&b""[..]])
+            .build()
+            .err()
+            .unwrap();
+        match &err {
+            CrackenError::Wordlist { path, .. } => assert_eq!(path.to_str().unwrap(), "?w1"),
+            _ => panic!("unexpected error {:?}", err),
+        }
+
+        let err = GeneratorBuilder::new()
+            .mask("?d?2")
+            .custom_charset("ab")
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err, CrackenError::Charset(_)), "{:?}", err);
+    }
+}
@@ -1,5 +1,8 @@
 use std::ops::Index;
 
+/// separator between tokens of a custom tokens list (e.g. "!|!!|123")
+pub const TOKENS_SEPARATOR: char = '|';
+
 pub struct CharsetSymbol<'a> {
     pub(crate) symbol: char,
     pub(crate) chars: &'a [u8],
@@ -71,6 +74,39 @@ pub const SYMBOL2CHARSET: [CharsetSymbol; 6] = [
     ),
 ];
 
+/// a user defined charset bound to ?1 to ?9 on the mask
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CustomCharset<'a> {
+    /// single chars charset (-c) - each char is a possible value
+    Chars(&'a str),
+    /// multi-char tokens list (--tokens) - separated by `TOKENS_SEPARATOR`
+    Tokens(&'a str),
+}
+
+impl<'a> CustomCharset<'a> {
+    #[inline]
+    pub fn is_tokens(&self) -> bool {
+        matches!(self, CustomCharset::Tokens(_))
+    }
+
+    /// returns the tokens of this charset, each char is a token for `Chars`
+    pub fn tokens(&self) -> Vec<&'a [u8]> {
+        match self {
+            CustomCharset::Chars(chars) => chars.as_bytes().chunks(1).collect(),
+            CustomCharset::Tokens(tokens) => tokens
+                .split(TOKENS_SEPARATOR)
+                .map(|token| token.as_bytes())
+                .collect(),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for CustomCharset<'a> {
+    fn from(chars: &'a str) -> Self {
+        CustomCharset::Chars(chars)
+    }
+}
+
 #[repr(align(64))]
 pub struct Charset {
     pub(crate) jmp_table: [u8; 256],
@@ -104,6 +140,39 @@ impl Charset {
         }
     }
 
+    /// returns the chars in generation order (sorted)
+    pub fn chars(&self) -> Vec<u8> {
+        let mut chars = Vec::with_capacity(self.len);
+        let mut cur = self.min_char;
+        for _ in 0..self.len {
+            chars.push(cur);
+            cur = self.jmp_table[cur as usize];
+        }
+        chars
+    }
+
+    /// returns the char at position `idx` in generation order (sorted), `idx` must be below
+    /// `len`
+    pub fn nth_char(&self, idx: usize) -> u8 {
+        let mut cur = self.min_char;
+        for _ in 0..idx {
+            cur = self.jmp_table[cur as usize];
+        }
+        cur
+    }
+
+    /// returns the position of `chr` in generation order (sorted) or None if not in the charset
+    pub fn index_of(&self, chr: u8) -> Option<usize> {
+        let mut cur = self.min_char;
+        for idx in 0..self.len {
+            if cur == chr {
+                return Some(idx);
+            }
+            cur = self.jmp_table[cur as usize];
+        }
+        None
+    }
+
     pub fn from_symbol(symbol: char) -> Charset {
         for charset in &SYMBOL2CHARSET {
             if charset.symbol == symbol {
@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::helpers::{parse_keyspace, parse_size};
+use crate::wordlists::WordlistEncoding;
+use crate::BoxResult;
+
+/// the profile applied to every run, named profiles (-P) are applied on top of it
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// generate defaults of a config file section, command line flags always override them
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Profile {
+    /// custom charsets, numbered ?1 to ?9 before the tokens
+    pub custom_charsets: Vec<String>,
+    /// multi-char tokens charsets (e.g. "!|!!|123"), numbered after the custom charsets
+    pub tokens: Vec<String>,
+    /// wordlists files ?w1 to ?w9, same syntax as -w
+    pub wordlists: Vec<String>,
+    pub wordlist_encoding: Option<String>,
+    pub output_encoding: Option<String>,
+    pub output_hex: Option<bool>,
+    /// --max-wordlist-memory size (e.g. 2G)
+    pub max_wordlist_memory: Option<String>,
+    /// --max-keyspace words (e.g. 1e13)
+    pub max_keyspace: Option<String>,
+}
+
+impl Profile {
+    /// returns this profile with every key set on `other` replaced by its value
+    pub fn merge(mut self, other: &Profile) -> Profile {
+        if !other.custom_charsets.is_empty() || !other.tokens.is_empty() {
+            self.custom_charsets = other.custom_charsets.clone();
+            self.tokens = other.tokens.clone();
+        }
+        if !other.wordlists.is_empty() {
+            self.wordlists = other.wordlists.clone();
+        }
+        if other.wordlist_encoding.is_some() {
+            self.wordlist_encoding = other.wordlist_encoding.clone();
+        }
+        if other.output_encoding.is_some() {
+            self.output_encoding = other.output_encoding.clone();
+        }
+        if other.output_hex.is_some() {
+            self.output_hex = other.output_hex;
+        }
+        if other.max_wordlist_memory.is_some() {
+            self.max_wordlist_memory = other.max_wordlist_memory.clone();
+        }
+        if other.max_keyspace.is_some() {
+            self.max_keyspace = other.max_keyspace.clone();
+        }
+        self
+    }
+
+    fn validate(&self, name: &str) -> BoxResult<()> {
+        if self.custom_charsets.len() + self.tokens.len() > 9 {
+            bail!(
+                "too many custom charsets for key `{}`: up to 9 are allowed",
+                name
+            );
+        }
+        if self.wordlists.len() > 9 {
+            bail!(
+                "too many wordlists for key `{}.wordlists`: up to 9 are allowed",
+                name
+            );
+        }
+        if let Some(encoding) = &self.wordlist_encoding {
+            if WordlistEncoding::from_name(encoding).is_none() {
+                bail!(
+                    "invalid value '{}' for key `{}.wordlist-encoding`: expected utf8, latin1 or auto",
+                    encoding,
+                    name
+                );
+            }
+        }
+        if let Some(encoding) = &self.output_encoding {
+            if !["utf8", "latin1", "utf16le", "hashcat-hex", "base64"].contains(&encoding.as_str())
+            {
+                bail!(
+                    "invalid value '{}' for key `{}.output-encoding`: expected utf8, latin1, utf16le, hashcat-hex or base64",
+                    encoding,
+                    name
+                );
+            }
+        }
+        if let Some(size) = &self.max_wordlist_memory {
+            if let Err(e) = parse_size(size) {
+                bail!(
+                    "invalid value for key `{}.max-wordlist-memory`: {}",
+                    name,
+                    e
+                );
+            }
+        }
+        if let Some(keyspace) = &self.max_keyspace {
+            if let Err(e) = parse_keyspace(keyspace) {
+                bail!("invalid value for key `{}.max-keyspace`: {}", name, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// a toml config file made of a [default] section and named profiles sections (e.g. [corp])
+#[derive(Debug)]
+pub struct Config {
+    path: PathBuf,
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    /// returns ~/.config/cracken/config.toml or None if the home directory is unknown
+    pub fn default_path() -> Option<PathBuf> {
+        let home = env::var_os("HOME")?;
+        let mut path = PathBuf::from(home);
+        path.extend([".config", "cracken", "config.toml"]);
+        Some(path)
+    }
+
+    /// an empty config, used when the default config file doesn't exist
+    pub fn empty<P: AsRef<Path>>(path: P) -> Config {
+        Config {
+            path: path.as_ref().to_path_buf(),
+            profiles: BTreeMap::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> BoxResult<Config> {
+        let path = path.as_ref();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => bail!("cannot read config file {}: {}", path.display(), e),
+        };
+        Config::parse(&content, path)
+    }
+
+    pub fn parse<P: AsRef<Path>>(content: &str, path: P) -> BoxResult<Config> {
+        let path = path.as_ref();
+        let profiles: BTreeMap<String, Profile> = match toml::from_str(content) {
+            Ok(profiles) => profiles,
+            Err(e) => bail!("invalid config file {}: {}", path.display(), e),
+        };
+        for (name, profile) in profiles.iter() {
+            if let Err(e) = profile.validate(name) {
+                bail!("invalid config file {}: {}", path.display(), e);
+            }
+        }
+        Ok(Config {
+            path: path.to_path_buf(),
+            profiles,
+        })
+    }
+
+    /// returns the [default] section merged with the `name` profile if given
+    pub fn profile(&self, name: Option<&str>) -> BoxResult<Profile> {
+        let default = self
+            .profiles
+            .get(DEFAULT_PROFILE)
+            .cloned()
+            .unwrap_or_default();
+        match name {
+            None => Ok(default),
+            Some(name) => match self.profiles.get(name) {
+                Some(profile) => Ok(default.merge(profile)),
+                None => bail!(
+                    "profile '{}' not found in config file {}",
+                    name,
+                    self.path.display()
+                ),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, Profile};
+    use crate::test_util;
+
+    #[test]
+    fn test_load_config_profiles() {
+        let config = Config::load(test_util::wordlist_fname("config.toml")).unwrap();
+
+        let default = config.profile(None).unwrap();
+        assert_eq!(default.custom_charsets, vec!["!@#$".to_string()]);
+        assert_eq!(default.output_hex, Some(true));
+        assert!(default.wordlists.is_empty());
+
+        let corp = config.profile(Some("corp")).unwrap();
+        assert_eq!(corp.custom_charsets, vec!["0123456789abcdef".to_string()]);
+        assert_eq!(corp.tokens, vec!["!|!!|2024".to_string()]);
+        assert_eq!(
+            corp.wordlists,
+            vec!["test-resources/wordlist1.txt".to_string()]
+        );
+        assert_eq!(corp.output_encoding, Some("latin1".to_string()));
+        // inherited from [default]
+        assert_eq!(corp.output_hex, Some(true));
+    }
+
+    #[test]
+    fn test_config_missing_profile() {
+        let config = Config::load(test_util::wordlist_fname("config.toml")).unwrap();
+        let err = config.profile(Some("nope")).unwrap_err().to_string();
+        assert!(
+            err.starts_with("profile 'nope' not found in config file"),
+            "{}",
+            err
+        );
+
+        let config = Config::empty("/nonexistent/config.toml");
+        assert_eq!(config.profile(None).unwrap(), Profile::default());
+        assert_eq!(
+            config.profile(Some("corp")).unwrap_err().to_string(),
+            "profile 'corp' not found in config file /nonexistent/config.toml"
+        );
+    }
+
+    #[test]
+    fn test_config_bad_keys() {
+        let err = Config::parse("[corp]\ncharsets = [\"abc\"]\n", "bad.toml")
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("invalid config file bad.toml: "), "{}", err);
+        assert!(err.contains("charsets"), "{}", err);
+
+        let err = Config::parse("[corp]\noutput-encoding = \"utf16\"\n", "bad.toml")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "invalid config file bad.toml: invalid value 'utf16' for key `corp.output-encoding`: \
+             expected utf8, latin1, utf16le, hashcat-hex or base64"
+        );
+
+        let err = Config::parse("[corp]\nmax-wordlist-memory = \"2X\"\n", "bad.toml")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with(
+                "invalid config file bad.toml: invalid value for key `corp.max-wordlist-memory`: invalid size '2X'"
+            ),
+            "{}",
+            err
+        );
+        let config = Config::parse("[default]\nmax-wordlist-memory = \"2G\"\n", "ok.toml").unwrap();
+        assert_eq!(
+            config.profile(None).unwrap().max_wordlist_memory.as_deref(),
+            Some("2G")
+        );
+
+        let err = Config::parse("[corp]\nmax-keyspace = \"1e\"\n", "bad.toml")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with(
+                "invalid config file bad.toml: invalid value for key `corp.max-keyspace`: invalid keyspace '1e'"
+            ),
+            "{}",
+            err
+        );
+        let config = Config::parse("[default]\nmax-keyspace = \"1e13\"\n", "ok.toml").unwrap();
+        assert_eq!(
+            config.profile(None).unwrap().max_keyspace.as_deref(),
+            Some("1e13")
+        );
+    }
+
+    #[test]
+    fn test_profile_merge() {
+        let default = Profile {
+            custom_charsets: vec!["ab".to_string()],
+            wordlists: vec!["a.txt".to_string()],
+            output_hex: Some(true),
+            ..Default::default()
+        };
+        let corp = Profile {
+            tokens: vec!["x|y".to_string()],
+            output_hex: Some(false),
+            ..Default::default()
+        };
+        let merged = default.merge(&corp);
+        assert!(merged.custom_charsets.is_empty());
+        assert_eq!(merged.tokens, vec!["x|y".to_string()]);
+        assert_eq!(merged.wordlists, vec!["a.txt".to_string()]);
+        assert_eq!(merged.output_hex, Some(false));
+    }
+}
@@ -275,6 +275,20 @@ mod tests {
     use crate::create_smartlist::{SmartlistBuilder, SmartlistTokenizer};
     use crate::test_util;
 
+    #[test]
+    fn test_sort_vocab() {
+        let fname = test_util::wordlist_fname("smartlist-corpus.txt");
+        let builder = SmartlistBuilder::new().infiles(vec![fname.to_str().unwrap()]);
+        let vocab = ["love", "123", "ab", "you", "!", "zz"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        // ranked by occurrences in the corpus, ties by the word itself
+        let vocab = builder.sort_vocab(vocab).unwrap();
+        assert_eq!(vocab, ["love", "123", "!", "you", "ab", "zz"]);
+    }
+
     #[test]
     fn test_build_vocab() {
         let fname = test_util::wordlist_fname("wordlist1.txt");
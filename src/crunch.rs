@@ -0,0 +1,328 @@
+//! crunch compatibility - `crunch <min> <max> [charset] [-t pattern]` invocations translated
+//! to cracken masks, generating the same words in the same order
+use std::io::{Error, Write};
+
+use crate::error::CrackenError;
+use crate::MAX_WORD_SIZE;
+
+/// crunch's default charsets of the `@`, `,`, `%` and `^` pattern placeholders, in its order
+pub const DEFAULT_CHARSETS: [&str; 4] = [
+    "abcdefghijklmnopqrstuvwxyz",
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+    "0123456789",
+    "!@#$%^&*()-_+=~`[]{}|\\:;\"'<>,.?/ ",
+];
+/// placeholders of the -t pattern, the i-th placeholder is replaced by the i-th charset
+pub const PATTERN_PLACEHOLDERS: [char; 4] = ['@', ',', '%', '^'];
+/// charset argument keeping the default charset of its placeholder (e.g. `+ + 123`)
+pub const DEFAULT_CHARSET_ARG: &str = "+";
+
+/// a crunch invocation as a cracken mask. crunch changes the chars of every position in the
+/// order of its charset while the generators use sorted charsets, so the words are generated
+/// with the sorted charsets and mapped back to the crunch order by `CrunchWriter`
+#[derive(Debug, Clone)]
+pub struct CrunchMask {
+    pub mask: String,
+    pub custom_charsets: Vec<String>,
+    pub minlen: usize,
+    pub maxlen: usize,
+    /// maps every byte of the generated words from the sorted charset to the crunch charset,
+    /// by the position in the word
+    maps: Vec<[u8; 256]>,
+}
+
+impl CrunchMask {
+    /// `charsets` are the crunch charsets arguments - a single one without a pattern, the
+    /// charsets of the `@`, `,`, `%` and `^` placeholders with a pattern
+    pub fn new(
+        minlen: usize,
+        maxlen: usize,
+        charsets: &[&str],
+        pattern: Option<&str>,
+    ) -> Result<CrunchMask, CrackenError> {
+        let invalid_length = |msg: String| Err(CrackenError::InvalidLength(msg));
+        if minlen == 0 || minlen > maxlen {
+            return invalid_length(format!(
+                "crunch min {} must be greater than zero and at most max {}",
+                minlen, maxlen
+            ));
+        }
+        if maxlen >= MAX_WORD_SIZE {
+            return invalid_length(format!(
+                "crunch max {} is longer than the maximum word length {}",
+                maxlen,
+                MAX_WORD_SIZE - 1
+            ));
+        }
+        let max_charsets = if pattern.is_some() { 4 } else { 1 };
+        if charsets.len() > max_charsets {
+            return Err(CrackenError::Charset(format!(
+                "crunch takes {} charsets{}, got {}",
+                max_charsets,
+                if pattern.is_some() { "" } else { " without -t" },
+                charsets.len()
+            )));
+        }
+        let mut placeholder_charsets = DEFAULT_CHARSETS.to_vec();
+        for (idx, &charset) in charsets.iter().enumerate() {
+            if charset != DEFAULT_CHARSET_ARG {
+                validate_charset(charset)?;
+                placeholder_charsets[idx] = charset;
+            }
+        }
+
+        // the crunch charset of every position, None for the literal chars of the pattern
+        let positions: Vec<Option<&str>> = match pattern {
+            None => vec![Some(placeholder_charsets[0]); maxlen],
+            Some(pattern) => {
+                if !pattern.is_ascii() {
+                    return Err(CrackenError::Unsupported(format!(
+                        "crunch pattern {} - only ascii chars are supported",
+                        pattern
+                    )));
+                }
+                if minlen != pattern.len() || maxlen != pattern.len() {
+                    return invalid_length(format!(
+                        "crunch min and max must be the length of the pattern {} ({})",
+                        pattern,
+                        pattern.len()
+                    ));
+                }
+                pattern
+                    .chars()
+                    .map(|ch| {
+                        PATTERN_PLACEHOLDERS
+                            .iter()
+                            .position(|&placeholder| placeholder == ch)
+                            .map(|idx| placeholder_charsets[idx])
+                    })
+                    .collect()
+            }
+        };
+
+        let mut mask = String::new();
+        let mut custom_charsets: Vec<String> = vec![];
+        let mut maps = vec![];
+        for (pos, charset) in positions.into_iter().enumerate() {
+            let mut map = [0; 256];
+            for (i, byte) in map.iter_mut().enumerate() {
+                *byte = i as u8;
+            }
+            match charset {
+                Some(charset) => {
+                    let mut sorted = charset.as_bytes().to_vec();
+                    sorted.sort_unstable();
+                    for (&from, &to) in sorted.iter().zip(charset.as_bytes()) {
+                        map[from as usize] = to;
+                    }
+                    let sorted = String::from_utf8(sorted).unwrap();
+                    let idx = match custom_charsets.iter().position(|c| *c == sorted) {
+                        Some(idx) => idx,
+                        None => {
+                            custom_charsets.push(sorted);
+                            custom_charsets.len() - 1
+                        }
+                    };
+                    mask.push('?');
+                    mask.push_str(&(idx + 1).to_string());
+                }
+                None => {
+                    let ch = pattern.unwrap().as_bytes()[pos] as char;
+                    if ch == '?' || ch == '\\' {
+                        mask.push('\\');
+                    }
+                    mask.push(ch);
+                }
+            }
+            maps.push(map);
+        }
+
+        Ok(CrunchMask {
+            mask,
+            custom_charsets,
+            minlen,
+            maxlen,
+            maps,
+        })
+    }
+
+    /// returns true iff every charset is already sorted, the words need no mapping
+    pub fn is_sorted(&self) -> bool {
+        self.maps
+            .iter()
+            .all(|map| map.iter().enumerate().all(|(i, &byte)| i == byte as usize))
+    }
+
+    /// maps a word in crunch order to the word generated by the mask, e.g. for `index_of`
+    pub fn to_generated(&self, word: &[u8]) -> Vec<u8> {
+        word.iter()
+            .zip(&self.maps)
+            .map(|(&chr, map)| match map.iter().position(|&to| to == chr) {
+                Some(from) => from as u8,
+                None => chr,
+            })
+            .collect()
+    }
+
+    /// wraps `out` so the words generated by the mask are written in crunch order
+    pub fn writer<W: Write>(&self, out: W) -> CrunchWriter<W> {
+        CrunchWriter {
+            inner: out,
+            maps: self.maps.clone(),
+            pos: 0,
+            buf: vec![],
+        }
+    }
+}
+
+/// crunch charsets generate every char once per position, so duplicates are rejected
+fn validate_charset(charset: &str) -> Result<(), CrackenError> {
+    if charset.is_empty() {
+        return Err(CrackenError::Charset("crunch charset is empty".to_string()));
+    }
+    if !charset.is_ascii() {
+        return Err(CrackenError::Unsupported(format!(
+            "crunch charset {} - only ascii chars are supported",
+            charset
+        )));
+    }
+    let bytes = charset.as_bytes();
+    if bytes.contains(&b'\n') {
+        return Err(CrackenError::Charset(
+            "crunch charset must not contain a newline".to_string(),
+        ));
+    }
+    for (i, chr) in bytes.iter().enumerate() {
+        if bytes[..i].contains(chr) {
+            return Err(CrackenError::Charset(format!(
+                "crunch charset {} has {} more than once",
+                charset, *chr as char
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// maps the bytes of the generated newline separated words by their position in the word
+pub struct CrunchWriter<W: Write> {
+    inner: W,
+    maps: Vec<[u8; 256]>,
+    /// position in the current word, words may be split between writes
+    pos: usize,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> Write for CrunchWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.buf.clear();
+        for &chr in buf {
+            if chr == b'\n' {
+                self.pos = 0;
+                self.buf.push(chr);
+            } else {
+                self.buf.push(self.maps[self.pos][chr as usize]);
+                self.pos += 1;
+            }
+        }
+        self.inner.write_all(&self.buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::CrunchMask;
+    use crate::error::CrackenError;
+
+    fn map_words(crunch: &CrunchMask, words: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        {
+            let mut writer = crunch.writer(&mut out);
+            // split in the middle of a word
+            writer.write_all(&words[..words.len() / 2 + 1]).unwrap();
+            writer.write_all(&words[words.len() / 2 + 1..]).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn test_crunch_mask() {
+        let crunch = CrunchMask::new(1, 3, &[], None).unwrap();
+        assert_eq!(crunch.mask, "?1?1?1");
+        assert_eq!(crunch.custom_charsets, vec!["abcdefghijklmnopqrstuvwxyz"]);
+        assert_eq!((crunch.minlen, crunch.maxlen), (1, 3));
+        assert!(crunch.is_sorted());
+
+        let crunch = CrunchMask::new(2, 2, &["cba"], None).unwrap();
+        assert_eq!(crunch.mask, "?1?1");
+        assert_eq!(crunch.custom_charsets, vec!["abc"]);
+        assert!(!crunch.is_sorted());
+        assert_eq!(map_words(&crunch, b"aa\nab\nac\nba\n"), b"cc\ncb\nca\nbc\n");
+        assert_eq!(crunch.to_generated(b"cb"), b"ab");
+
+        // placeholders with default (+) and given charsets, identical sets share a charset
+        let crunch = CrunchMask::new(7, 7, &["+", "+", "10", "01"], Some("a@,?%^\\")).unwrap();
+        assert_eq!(crunch.mask, "a?1?2\\??3?3\\\\");
+        assert_eq!(
+            crunch.custom_charsets,
+            vec![
+                "abcdefghijklmnopqrstuvwxyz",
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+                "01"
+            ]
+        );
+        assert_eq!(map_words(&crunch, b"aaA?00\\\n"), b"aaA?10\\\n");
+        assert_eq!(crunch.to_generated(b"aaA?10\\"), b"aaA?00\\");
+
+        // crunch's symbols order
+        let crunch = CrunchMask::new(1, 1, &["+", "+", "+", "+"], Some("^")).unwrap();
+        assert_eq!(crunch.custom_charsets[0].len(), 33);
+        assert_eq!(map_words(&crunch, b" \n!\n"), b"!\n@\n");
+    }
+
+    #[test]
+    fn test_crunch_mask_errors() {
+        let err = |minlen, maxlen, charsets: &[&str], pattern| {
+            CrunchMask::new(minlen, maxlen, charsets, pattern).unwrap_err()
+        };
+        assert!(matches!(
+            err(0, 2, &[], None),
+            CrackenError::InvalidLength(_)
+        ));
+        assert!(matches!(
+            err(3, 2, &[], None),
+            CrackenError::InvalidLength(_)
+        ));
+        assert!(matches!(
+            err(1, 512, &[], None),
+            CrackenError::InvalidLength(_)
+        ));
+        assert_eq!(
+            err(2, 3, &[], Some("@@@")).to_string(),
+            "crunch min and max must be the length of the pattern @@@ (3)"
+        );
+        assert_eq!(
+            err(1, 2, &["ab", "cd"], None).to_string(),
+            "crunch takes 1 charsets without -t, got 2"
+        );
+        assert_eq!(
+            err(1, 2, &["aba"], None).to_string(),
+            "crunch charset aba has a more than once"
+        );
+        assert!(matches!(
+            err(1, 2, &["é"], None),
+            CrackenError::Unsupported(_)
+        ));
+        assert!(matches!(
+            err(1, 1, &[], Some("é")),
+            CrackenError::Unsupported(_)
+        ));
+        assert!(matches!(err(1, 2, &[""], None), CrackenError::Charset(_)));
+    }
+}
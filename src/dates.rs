@@ -0,0 +1,186 @@
+use std::collections::HashSet;
+
+use crate::BoxResult;
+
+/// separators allowed between the date format fields (e.g. DD-MM-YYYY)
+const DATE_SEPARATORS: &[u8] = b"-./_";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateField {
+    /// 4 digits year (1990)
+    Year,
+    /// 2 digits year (90)
+    ShortYear,
+    /// zero padded month (01-12)
+    Month,
+    /// month without padding (1-12)
+    ShortMonth,
+    /// zero padded day (01-31)
+    Day,
+    /// day without padding (1-31)
+    ShortDay,
+    Separator(u8),
+}
+
+/// a date format like DDMMYYYY, YYYY-MM-DD or DMYY
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateFormat {
+    fields: Vec<DateField>,
+}
+
+impl DateFormat {
+    /// parses a format made of YYYY, YY, MM, M, DD, D fields and `DATE_SEPARATORS`
+    pub fn parse(format: &str) -> BoxResult<DateFormat> {
+        let mut fields = vec![];
+        let mut rest = format.as_bytes();
+
+        while !rest.is_empty() {
+            let (field, len) = if rest.starts_with(b"YYYY") {
+                (DateField::Year, 4)
+            } else if rest.starts_with(b"YY") {
+                (DateField::ShortYear, 2)
+            } else if rest.starts_with(b"MM") {
+                (DateField::Month, 2)
+            } else if rest.starts_with(b"M") {
+                (DateField::ShortMonth, 1)
+            } else if rest.starts_with(b"DD") {
+                (DateField::Day, 2)
+            } else if rest.starts_with(b"D") {
+                (DateField::ShortDay, 1)
+            } else if DATE_SEPARATORS.contains(&rest[0]) {
+                (DateField::Separator(rest[0]), 1)
+            } else {
+                bail!("invalid date format: {}", format);
+            };
+            fields.push(field);
+            rest = &rest[len..];
+        }
+
+        if fields.iter().all(|f| matches!(f, DateField::Separator(_))) {
+            bail!("invalid date format: {}", format);
+        }
+        Ok(DateFormat { fields })
+    }
+
+    fn format(&self, year: u32, month: u32, day: u32, out: &mut Vec<u8>) {
+        out.clear();
+        for field in self.fields.iter() {
+            match field {
+                DateField::Year => out.extend_from_slice(format!("{:04}", year).as_bytes()),
+                DateField::ShortYear => {
+                    out.extend_from_slice(format!("{:02}", year % 100).as_bytes())
+                }
+                DateField::Month => out.extend_from_slice(format!("{:02}", month).as_bytes()),
+                DateField::ShortMonth => out.extend_from_slice(month.to_string().as_bytes()),
+                DateField::Day => out.extend_from_slice(format!("{:02}", day).as_bytes()),
+                DateField::ShortDay => out.extend_from_slice(day.to_string().as_bytes()),
+                DateField::Separator(sep) => out.push(*sep),
+            }
+        }
+    }
+}
+
+#[inline]
+pub fn is_leap_year(year: u32) -> bool {
+    match (year % 4, year % 100, year % 400) {
+        (_, _, 0) => true,
+        (_, 0, _) => false,
+        (0, _, _) => true,
+        _ => false,
+    }
+}
+
+#[inline]
+pub fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// returns all valid calendar dates between `from_year` and `to_year` (inclusive) formatted
+/// by `format`. dates formatting to the same word (e.g. DDMM on multiple years) are deduplicated
+pub fn gen_dates(format: &DateFormat, from_year: u32, to_year: u32) -> Vec<Vec<u8>> {
+    let mut seen = HashSet::new();
+    let mut dates = vec![];
+    let mut date = Vec::with_capacity(16);
+
+    for year in from_year..=to_year {
+        for month in 1..=12 {
+            for day in 1..=days_in_month(year, month) {
+                format.format(year, month, day, &mut date);
+                if seen.insert(date.to_vec()) {
+                    dates.push(date.to_vec());
+                }
+            }
+        }
+    }
+    dates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{days_in_month, gen_dates, is_leap_year, DateFormat};
+
+    #[test]
+    fn test_leap_years() {
+        for year in [1992, 1996, 2000, 2004, 2400] {
+            assert!(is_leap_year(year), "{}", year);
+        }
+        for year in [1900, 1990, 1991, 2100, 2023] {
+            assert!(!is_leap_year(year), "{}", year);
+        }
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(1999, 4), 30);
+        assert_eq!(days_in_month(1999, 12), 31);
+    }
+
+    #[test]
+    fn test_parse_date_format() {
+        for format in [
+            "DDMMYYYY", "MMDDYYYY", "YYYYMMDD", "DDMM", "MMYY", "DD-MM-YY", "DMYY",
+        ] {
+            assert!(DateFormat::parse(format).is_ok(), "{}", format);
+        }
+        for format in ["", "-", "DDMMYYY", "XX", "DD MM"] {
+            assert!(DateFormat::parse(format).is_err(), "{}", format);
+        }
+    }
+
+    #[test]
+    fn test_gen_dates_count() {
+        let cases = vec![
+            ("DDMMYYYY", 1990, 1999, 3652),
+            ("MMDDYYYY", 1990, 1999, 3652),
+            ("YYYYMMDD", 2000, 2000, 366),
+            ("YYYYMMDD", 1900, 1900, 365),
+            ("DDMM", 1990, 1999, 366),
+            ("DDMM", 1991, 1991, 365),
+            ("MMYY", 1990, 1999, 120),
+            ("YY", 1950, 2060, 100),
+        ];
+        for (format, from_year, to_year, count) in cases {
+            let format_ = DateFormat::parse(format).unwrap();
+            let dates = gen_dates(&format_, from_year, to_year);
+            assert_eq!(dates.len(), count, "{} {}-{}", format, from_year, to_year);
+        }
+    }
+
+    #[test]
+    fn test_gen_dates_format() {
+        let format = DateFormat::parse("DD.MM.YYYY").unwrap();
+        let dates = gen_dates(&format, 1996, 1996);
+        assert_eq!(dates[0], b"01.01.1996");
+        assert!(dates.contains(&b"29.02.1996".to_vec()));
+        assert_eq!(dates.last().unwrap(), b"31.12.1996");
+
+        let format = DateFormat::parse("DMYY").unwrap();
+        let dates = gen_dates(&format, 1997, 1997);
+        assert_eq!(dates[0], b"1197");
+        assert!(!dates.contains(&b"29297".to_vec()));
+        assert_eq!(dates.last().unwrap(), b"311297");
+    }
+}
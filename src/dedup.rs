@@ -0,0 +1,459 @@
+//! the words of a mask which none of the earlier masks generates (`--dedup-across-masks`),
+//! every word is checked against the earlier masks by their `index_of` instead of keeping the
+//! generated words
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use num_bigint::{BigUint, ToBigUint};
+
+use crate::error::CrackenError;
+use crate::generators::{LengthOrder, RepeatLimits, WordGenerator, WordRank};
+use crate::helpers::{decode_hex_word, LineFilterWriter, HEX_PREFIX};
+
+/// most words of a mask with wordlists generated to count the words left, the words of the
+/// masks of per-position charsets are counted without generating them
+pub const MAX_ENUMERATED_WORDS: u64 = 100_000_000;
+
+/// most intersections of the earlier masks counted for a length, the masks intersecting each
+/// other have up to 2^n of them
+const MAX_INTERSECTIONS: u64 = 1 << 20;
+
+/// generates the words of `inner` which none of `earlier` generates
+pub struct DedupGenerator {
+    inner: Box<dyn WordGenerator>,
+    /// the generators of the earlier masks sharing a length with `inner`
+    earlier: Vec<Box<dyn WordGenerator>>,
+    hex_output: bool,
+    repeat_limits: RepeatLimits,
+    /// the words left, counted from the charsets of the masks or once by generating them
+    counts: OnceCell<Counts>,
+}
+
+/// the words left of every length and their bytes including the newlines
+#[derive(Default)]
+struct Counts {
+    words: BigUint,
+    lengths: HashMap<usize, BigUint>,
+    bytes: BigUint,
+}
+
+/// skips the words of `generator` which any of `earlier` generates. the earlier generators only
+/// check the words with `index_of`, the ones of other lengths are dropped. the words left are
+/// counted by inclusion-exclusion over the charsets of the masks, the masks with wordlists are
+/// generated to count them and refused over `MAX_ENUMERATED_WORDS` words
+pub fn dedup(
+    generator: Box<dyn WordGenerator>,
+    earlier: Vec<Box<dyn WordGenerator>>,
+) -> Result<Box<dyn WordGenerator>, CrackenError> {
+    let lengths: Vec<_> = generator
+        .length_combinations()
+        .into_iter()
+        .map(|(len, _)| len)
+        .collect();
+    let earlier: Vec<_> = earlier
+        .into_iter()
+        .filter(|other| {
+            let other_lengths = other.length_combinations();
+            lengths.is_empty()
+                || other_lengths.is_empty()
+                || other_lengths.iter().any(|(len, _)| lengths.contains(len))
+        })
+        .collect();
+    let counts = OnceCell::new();
+    if !earlier.is_empty() {
+        match count_by_charsets(generator.as_ref(), &earlier) {
+            Some(charset_counts) => {
+                let _ = counts.set(charset_counts);
+            }
+            None if generator.combinations() > MAX_ENUMERATED_WORDS.to_biguint().unwrap() => {
+                return Err(CrackenError::Unsupported(format!(
+                    "--dedup-across-masks generates the words of the masks with wordlists (or overlapping too many earlier masks) to count them, at most {} words and the mask has {}",
+                    MAX_ENUMERATED_WORDS,
+                    generator.combinations()
+                )));
+            }
+            None => {}
+        }
+    }
+    Ok(Box::new(DedupGenerator {
+        inner: generator,
+        earlier,
+        hex_output: false,
+        repeat_limits: RepeatLimits::default(),
+        counts,
+    }))
+}
+
+/// a set of bytes
+type ByteSet = [u64; 4];
+
+fn byte_set(chars: &[u8]) -> ByteSet {
+    let mut set = [0; 4];
+    for &c in chars {
+        set[c as usize / 64] |= 1 << (c % 64);
+    }
+    set
+}
+
+/// the number of words of the positions of `sets`
+fn set_words(sets: &[ByteSet]) -> BigUint {
+    sets.iter()
+        .map(|set| set.iter().map(|bits| bits.count_ones()).sum::<u32>())
+        .fold(1u32.to_biguint().unwrap(), |words, chars| words * chars)
+}
+
+/// counts the words left of `generator` without generating them, by inclusion-exclusion over
+/// the per-position charsets of the masks of every length. None if a mask has no per-position
+/// charsets (e.g. wordlists) or the masks intersect in too many ways
+fn count_by_charsets(
+    generator: &dyn WordGenerator,
+    earlier: &[Box<dyn WordGenerator>],
+) -> Option<Counts> {
+    let positions: Vec<_> = generator
+        .position_chars()?
+        .iter()
+        .map(|chars| byte_set(chars))
+        .collect();
+    let earlier = earlier
+        .iter()
+        .map(|other| {
+            let positions: Vec<_> = other
+                .position_chars()?
+                .iter()
+                .map(|chars| byte_set(chars))
+                .collect();
+            let lengths: Vec<_> = other
+                .length_combinations()
+                .into_iter()
+                .map(|(len, _)| len)
+                .collect();
+            Some((positions, lengths))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut counts = Counts::default();
+    for (len, _) in generator.length_combinations() {
+        // the words of a length are the first positions of the mask
+        let others: Vec<_> = earlier
+            .iter()
+            .filter(|(_, lengths)| lengths.contains(&len))
+            .map(|(positions, _)| &positions[..len])
+            .collect();
+        let mut intersections = 0;
+        let mut removed = (BigUint::default(), BigUint::default());
+        remove_intersections(
+            &positions[..len],
+            &others,
+            true,
+            &mut removed,
+            &mut intersections,
+        )?;
+        let words = set_words(&positions[..len]) + removed.1 - removed.0;
+        if words.bits() == 0 {
+            continue;
+        }
+        counts.bytes += &words * (len + 1);
+        counts.words += &words;
+        counts.lengths.insert(len, words);
+    }
+    Some(counts)
+}
+
+/// adds the words of the intersections of `sets` with every subset of `others` to `removed`,
+/// the subsets of odd size to `.0` and the others to `.1`. an empty intersection stays empty
+/// with more masks so its subsets are skipped
+fn remove_intersections(
+    sets: &[ByteSet],
+    others: &[&[ByteSet]],
+    odd: bool,
+    removed: &mut (BigUint, BigUint),
+    intersections: &mut u64,
+) -> Option<()> {
+    for (idx, other) in others.iter().enumerate() {
+        let intersection: Vec<ByteSet> = sets
+            .iter()
+            .zip(other.iter())
+            .map(|(set, other)| [0, 1, 2, 3].map(|i| set[i] & other[i]))
+            .collect();
+        if intersection.iter().any(|set| set == &[0; 4]) {
+            continue;
+        }
+        *intersections += 1;
+        if *intersections > MAX_INTERSECTIONS {
+            return None;
+        }
+        match odd {
+            true => removed.0 += set_words(&intersection),
+            false => removed.1 += set_words(&intersection),
+        }
+        remove_intersections(
+            &intersection,
+            &others[idx + 1..],
+            !odd,
+            removed,
+            intersections,
+        )?;
+    }
+    Some(())
+}
+
+impl DedupGenerator {
+    /// the generated line as the word, `$HEX[...]` is decoded
+    fn decoded<'w>(&self, line: &'w [u8]) -> Cow<'w, [u8]> {
+        if self.hex_output && line.starts_with(HEX_PREFIX) {
+            if let Ok(word) = decode_hex_word(line.to_vec()) {
+                return Cow::Owned(word);
+            }
+        }
+        Cow::Borrowed(line)
+    }
+
+    /// true if none of the earlier masks generates `word`
+    fn is_new(&self, word: &[u8]) -> bool {
+        self.earlier
+            .iter()
+            .all(|other| other.index_of(word).is_none())
+    }
+
+    fn counts(&self) -> &Counts {
+        self.counts.get_or_init(|| {
+            let mut counts = Counts::default();
+            let counting = LineFilterWriter::new(io::sink(), |line: &[u8]| {
+                let word = self.decoded(line);
+                if self.is_new(&word) {
+                    counts.words += 1u32;
+                    *counts.lengths.entry(word.len()).or_default() += 1u32;
+                    counts.bytes += word.len() + 1;
+                }
+                false
+            });
+            {
+                let mut counting: Box<dyn Write + '_> = Box::new(counting);
+                // writing to the sink can't fail, the words generated until an error are counted
+                let _ = self.inner.gen(&mut counting);
+            }
+            counts
+        })
+    }
+}
+
+impl WordGenerator for DedupGenerator {
+    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), CrackenError> {
+        if self.earlier.is_empty() && !self.repeat_limits.is_set() {
+            return self.inner.gen(out);
+        }
+        let mut filtered: Box<dyn Write + '_> =
+            Box::new(LineFilterWriter::new(out, |line: &[u8]| {
+                let word = self.decoded(line);
+                self.repeat_limits.allows(&word) && self.is_new(&word)
+            }));
+        self.inner.gen(&mut filtered)?;
+        filtered.flush()?;
+        Ok(())
+    }
+
+    /// the words left are counted once, see `dedup`
+    fn combinations(&self) -> BigUint {
+        if self.earlier.is_empty() {
+            return self.inner.combinations();
+        }
+        self.counts().words.clone()
+    }
+
+    fn length_combinations(&self) -> Vec<(usize, BigUint)> {
+        if self.earlier.is_empty() {
+            return self.inner.length_combinations();
+        }
+        let counts = self.counts();
+        self.inner
+            .length_combinations()
+            .into_iter()
+            .filter_map(|(len, _)| Some((len, counts.lengths.get(&len)?.clone())))
+            .collect()
+    }
+
+    fn output_bytes(&self) -> BigUint {
+        if self.earlier.is_empty() {
+            return self.inner.output_bytes();
+        }
+        self.counts().bytes.clone()
+    }
+
+    fn set_hex_output(&mut self, hex_output: bool) {
+        self.hex_output = hex_output;
+        self.inner.set_hex_output(hex_output);
+    }
+
+    fn set_buffer_size(&mut self, buffer_size: usize) {
+        self.inner.set_buffer_size(buffer_size);
+    }
+
+    /// the words are checked as they are generated, `combinations` still counts them
+    fn set_repeat_limits(&mut self, repeat_limits: RepeatLimits) {
+        self.repeat_limits = repeat_limits;
+    }
+
+    /// the index of `word` in all the words of the mask, the skipped words before it included
+    fn index_of(&self, word: &[u8]) -> Option<WordRank> {
+        if !self.is_new(word) {
+            return None;
+        }
+        self.inner.index_of(word)
+    }
+
+    fn set_length_order(&mut self, length_order: LengthOrder) {
+        self.inner.set_length_order(length_order);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::io::Write;
+
+    use num_bigint::ToBigUint;
+
+    use super::{dedup, MAX_ENUMERATED_WORDS};
+    use crate::charsets::CustomCharset;
+    use crate::generators::{get_word_generator, RepeatLimits, WordGenerator};
+    use crate::test_util::wordlist_fname;
+
+    fn gen_words(word_gen: &dyn WordGenerator) -> Vec<String> {
+        let mut buf = vec![];
+        {
+            let mut out: Box<dyn Write> = Box::new(&mut buf);
+            word_gen.gen(&mut out).unwrap();
+        }
+        String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_dedup() {
+        let charsets = [
+            CustomCharset::Chars("ab"),
+            CustomCharset::Chars("0123456789abc"),
+        ];
+        let build =
+            |mask: &'static str| get_word_generator(mask, None, None, &charsets, &[]).unwrap();
+        let masks = ["?1?1?d", "?l?l?d", "?d?2", "?2?d", "?l?d?d", "?2?2?d"];
+        // the words of every mask which are not in the union of the earlier masks
+        let mut union = HashSet::new();
+        for (idx, &mask) in masks.iter().enumerate() {
+            let expected: Vec<_> = gen_words(build(mask).as_ref())
+                .into_iter()
+                .filter(|word| !union.contains(word))
+                .collect();
+            let earlier = masks[..idx].iter().map(|&mask| build(mask)).collect();
+            let word_gen = dedup(build(mask), earlier).unwrap();
+            assert_eq!(gen_words(word_gen.as_ref()), expected, "{}", mask);
+            assert_eq!(
+                word_gen.combinations(),
+                expected.len().to_biguint().unwrap(),
+                "{}",
+                mask
+            );
+            let bytes: usize = expected.iter().map(|word| word.len() + 1).sum();
+            assert_eq!(word_gen.output_bytes(), bytes.to_biguint().unwrap());
+            union.extend(expected);
+        }
+        // ?l?l?d only adds the words without a and b in the first two chars
+        let word_gen = dedup(build("?l?l?d"), vec![build("?1?1?d")]).unwrap();
+        assert_eq!(
+            word_gen.combinations(),
+            ((26 * 26 - 4) * 10).to_biguint().unwrap()
+        );
+        assert!(word_gen.index_of(b"ab1").is_none());
+        assert!(word_gen.index_of(b"zz1").is_some());
+    }
+
+    #[test]
+    fn test_dedup_lengths() {
+        let build = |mask: &'static str, minlen: Option<usize>| {
+            get_word_generator(mask, minlen, None, &[], &[]).unwrap()
+        };
+        // the masks of other lengths are never checked
+        let word_gen = dedup(build("?d?d", None), vec![build("?d?d?d", None)]).unwrap();
+        assert_eq!(gen_words(word_gen.as_ref()).len(), 100);
+        assert_eq!(word_gen.combinations(), 100.to_biguint().unwrap());
+
+        let word_gen = dedup(build("?d?d?d", Some(1)), vec![build("?d?d", None)]).unwrap();
+        let words = gen_words(word_gen.as_ref());
+        assert_eq!(words.len(), 1010);
+        assert!(words.iter().all(|word| word.len() != 2));
+        assert_eq!(
+            word_gen.length_combinations(),
+            vec![
+                (1, 10.to_biguint().unwrap()),
+                (3, 1000.to_biguint().unwrap())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedup_repeat_limits() {
+        let build = |mask: &'static str| get_word_generator(mask, None, None, &[], &[]).unwrap();
+        let mut word_gen = dedup(build("?d?d"), vec![build("0?d")]).unwrap();
+        word_gen.set_repeat_limits(RepeatLimits {
+            seq_max: Some(1),
+            occurrence_max: None,
+        });
+        let words = gen_words(word_gen.as_ref());
+        assert_eq!(words.len(), 90 - 9);
+        assert!(!words.contains(&"11".to_string()));
+        // the words over the limits are still counted
+        assert_eq!(word_gen.combinations(), 90.to_biguint().unwrap());
+    }
+
+    #[test]
+    fn test_dedup_counts_by_charsets() {
+        let charsets = [CustomCharset::Chars("0123456789abcdef")];
+        let build =
+            |mask: &'static str| get_word_generator(mask, None, None, &charsets, &[]).unwrap();
+        // counted without generating the 2^64 words, ?l is in ?a (94 chars)
+        let earlier = vec![build("?a?a?a?a?a?a?a?a"), build("?l?l?l?l?l?l?l?l")];
+        let word_gen = dedup(build("?b?b?b?b?b?b?b?b"), earlier).unwrap();
+        assert_eq!(
+            word_gen.combinations(),
+            (1u32.to_biguint().unwrap() << 64) - 94u64.pow(8)
+        );
+        // ?1 is in the union of ?l and ?d
+        let earlier = vec![build("?a?a"), build("?l?l"), build("?d?d")];
+        let word_gen = dedup(build("?1?1"), earlier).unwrap();
+        assert_eq!(word_gen.combinations(), 0.to_biguint().unwrap());
+        let word_gen = dedup(build("?l?d"), vec![build("?1?1"), build("?l?l")]).unwrap();
+        assert_eq!(word_gen.combinations(), (20 * 10).to_biguint().unwrap());
+        assert_eq!(word_gen.output_bytes(), (20 * 10 * 3).to_biguint().unwrap());
+    }
+
+    #[test]
+    fn test_dedup_counts_wordlists() {
+        let fname = wordlist_fname("single-digits.txt");
+        let wordlists = [fname.to_str().unwrap()];
+        let word_gen = dedup(
+            get_word_generator("?w1?d", None, None, &[], &wordlists).unwrap(),
+            vec![get_word_generator("?d5", None, None, &[], &[]).unwrap()],
+        )
+        .unwrap();
+        assert_eq!(word_gen.combinations(), 90.to_biguint().unwrap());
+        assert_eq!(gen_words(word_gen.as_ref()).len(), 90);
+
+        // the words of a mask with wordlists are generated to be counted, up to a limit
+        let mask = format!("?w1{}", "?d".repeat(8));
+        let words = 10 * 10u64.pow(8);
+        assert!(words > MAX_ENUMERATED_WORDS);
+        let earlier = vec![get_word_generator("?d?d?d?d?d?d?d?d?d", None, None, &[], &[]).unwrap()];
+        let err = dedup(
+            get_word_generator(&mask, None, None, &[], &wordlists).unwrap(),
+            earlier,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains(&words.to_string()), "{}", err);
+    }
+}
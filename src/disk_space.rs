@@ -0,0 +1,147 @@
+//! the free space of the filesystems of the output files, checked against the estimated size of
+//! the words before writing them
+use std::io;
+use std::path::Path;
+
+/// the space available on the filesystem of a path
+pub trait SpaceCheck {
+    /// the bytes the user can write on the filesystem of `path`, None when the os can't tell
+    fn available_space(&self, path: &Path) -> io::Result<Option<u64>>;
+
+    /// an id of the filesystem of `path`, the paths of the same id share its free space. None
+    /// when the os can't tell
+    fn filesystem(&self, path: &Path) -> Option<u64>;
+}
+
+/// the filesystem space reported by the os, statvfs on unix and GetDiskFreeSpaceEx on windows
+pub struct FsSpace;
+
+/// the directory of `path`, its filesystem is the one queried
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+#[cfg(unix)]
+impl SpaceCheck for FsSpace {
+    fn available_space(&self, path: &Path) -> io::Result<Option<u64>> {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = CString::new(parent_dir(path).as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(dir.as_ptr(), &mut stat) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // f_bavail excludes the blocks reserved for root. their types are u32 on some unixes
+        #[allow(clippy::useless_conversion)]
+        let available = u64::from(stat.f_bavail).saturating_mul(u64::from(stat.f_frsize));
+        Ok(Some(available))
+    }
+
+    fn filesystem(&self, path: &Path) -> Option<u64> {
+        use std::os::unix::fs::MetadataExt;
+
+        std::fs::metadata(parent_dir(path)).ok().map(|m| m.dev())
+    }
+}
+
+#[cfg(windows)]
+impl SpaceCheck for FsSpace {
+    fn available_space(&self, path: &Path) -> io::Result<Option<u64>> {
+        use std::os::windows::ffi::OsStrExt;
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn GetDiskFreeSpaceExW(
+                directory: *const u16,
+                free_to_caller: *mut u64,
+                total: *mut u64,
+                total_free: *mut u64,
+            ) -> i32;
+        }
+
+        let dir: Vec<u16> = parent_dir(path)
+            .as_os_str()
+            .encode_wide()
+            .chain(Some(0))
+            .collect();
+        let mut free_to_caller = 0u64;
+        let ok = unsafe {
+            GetDiskFreeSpaceExW(
+                dir.as_ptr(),
+                &mut free_to_caller,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Some(free_to_caller))
+    }
+
+    /// the volume of the drive or share of `path`, the volumes mounted in a folder are missed
+    fn filesystem(&self, path: &Path) -> Option<u64> {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        use std::path::Component;
+
+        let dir = std::fs::canonicalize(parent_dir(path)).ok()?;
+        match dir.components().next()? {
+            Component::Prefix(prefix) => {
+                let mut hasher = DefaultHasher::new();
+                prefix.as_os_str().hash(&mut hasher);
+                Some(hasher.finish())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+impl SpaceCheck for FsSpace {
+    fn available_space(&self, _path: &Path) -> io::Result<Option<u64>> {
+        Ok(None)
+    }
+
+    fn filesystem(&self, _path: &Path) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{parent_dir, FsSpace, SpaceCheck};
+
+    #[test]
+    fn test_parent_dir() {
+        assert_eq!(parent_dir(Path::new("words.txt")), Path::new("."));
+        assert_eq!(parent_dir(Path::new("out/words.txt")), Path::new("out"));
+        assert_eq!(parent_dir(Path::new("/tmp/words.txt")), Path::new("/tmp"));
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_fs_space() {
+        let available = FsSpace.available_space(Path::new("words.txt")).unwrap();
+        assert!(available.is_some());
+        assert!(FsSpace
+            .available_space(Path::new("no-such-dir/words.txt"))
+            .is_err());
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_fs_filesystem() {
+        let fs = FsSpace.filesystem(Path::new("words.txt"));
+        assert!(fs.is_some());
+        assert_eq!(FsSpace.filesystem(Path::new("./other-words.txt")), fs);
+        assert_eq!(FsSpace.filesystem(Path::new("no-such-dir/words.txt")), None);
+    }
+}
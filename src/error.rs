@@ -0,0 +1,158 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// errors of parsing masks, loading wordlists and generating words
+#[derive(Debug)]
+pub enum CrackenError {
+    /// invalid mask syntax or a mask referencing missing wordlists (e.g. ?w3 with 2 wordlists)
+    Mask(String),
+    /// invalid or missing custom charsets and tokens
+    Charset(String),
+    /// a wordlist which can't be read or has invalid lines
+    Wordlist {
+        path: PathBuf,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// reading or writing failed while generating
+    Io(io::Error),
+    /// invalid minlen / maxlen or words longer than MAX_WORD_SIZE
+    InvalidLength(String),
+    /// an option the generator of the mask doesn't support (e.g. minlen with wordlists)
+    Unsupported(String),
+}
+
+impl CrackenError {
+    pub(crate) fn wordlist<P: Into<PathBuf>, E: Into<Box<dyn Error + Send + Sync>>>(
+        path: P,
+        source: E,
+    ) -> CrackenError {
+        CrackenError::Wordlist {
+            path: path.into(),
+            source: source.into(),
+        }
+    }
+}
+
+impl fmt::Display for CrackenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrackenError::Mask(msg)
+            | CrackenError::Charset(msg)
+            | CrackenError::InvalidLength(msg)
+            | CrackenError::Unsupported(msg) => write!(f, "{}", msg),
+            CrackenError::Wordlist { path, source } => {
+                write!(f, "wordlist {}: {}", path.display(), source)
+            }
+            CrackenError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for CrackenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CrackenError::Wordlist { source, .. } => Some(source.as_ref()),
+            CrackenError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CrackenError {
+    fn from(e: io::Error) -> CrackenError {
+        CrackenError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, ErrorKind, Write};
+
+    use super::CrackenError;
+    use crate::charsets::CustomCharset;
+    use crate::generators::get_word_generator;
+    use crate::mask::parse_mask;
+    use crate::test_util::wordlist_fname;
+
+    fn gen_err(
+        mask: &str,
+        minlen: Option<usize>,
+        custom_charsets: &[CustomCharset],
+        wordlists: &[&str],
+    ) -> CrackenError {
+        get_word_generator(mask, minlen, None, custom_charsets, wordlists)
+            .err()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_error_variants() {
+        assert!(matches!(parse_mask("?d?"), Err(CrackenError::Mask(_))));
+        assert!(matches!(parse_mask("?K{a-b}"), Err(CrackenError::Mask(_))));
+        assert!(matches!(
+            gen_err("?w2", None, &[], &["a.txt"]),
+            CrackenError::Mask(_)
+        ));
+        assert!(matches!(
+            gen_err("?d?1", None, &[], &[]),
+            CrackenError::Charset(_)
+        ));
+        assert!(matches!(
+            gen_err("?d?d", Some(3), &[], &[]),
+            CrackenError::InvalidLength(_)
+        ));
+        let wordlist1 = wordlist_fname("wordlist1.txt");
+        assert!(matches!(
+            gen_err("?w1", Some(1), &[], &[wordlist1.to_str().unwrap()]),
+            CrackenError::Unsupported(_)
+        ));
+    }
+
+    #[test]
+    fn test_error_wordlist_missing() {
+        let err = gen_err("?w1?d", None, &[], &["/tmp/this/dir/not/exisT.txt"]);
+        match &err {
+            CrackenError::Wordlist { path, source } => {
+                assert_eq!(path.to_str().unwrap(), "/tmp/this/dir/not/exisT.txt");
+                let source = source.downcast_ref::<io::Error>().unwrap();
+                assert_eq!(source.kind(), ErrorKind::NotFound);
+            }
+            _ => panic!("unexpected error {:?}", err),
+        }
+        assert!(
+            err.to_string()
+                .starts_with("wordlist /tmp/this/dir/not/exisT.txt: "),
+            "{}",
+            err
+        );
+
+        // the wordlist path is part of the invalid lines errors
+        let fname = wordlist_fname("wordlist-hex-invalid.txt");
+        let err = gen_err("?w1", None, &[], &[fname.to_str().unwrap()]);
+        assert!(matches!(&err, CrackenError::Wordlist { path, .. } if *path == fname));
+    }
+
+    #[test]
+    fn test_error_gen_io() {
+        struct FullWriter;
+
+        impl Write for FullWriter {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("no space left"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let word_gen = get_word_generator("?d?d", None, None, &[], &[]).unwrap();
+        let mut out: Box<dyn Write> = Box::new(FullWriter);
+        match word_gen.gen(&mut out) {
+            Err(CrackenError::Io(e)) => assert_eq!(e.to_string(), "no space left"),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! the words of an `--exclude` file, looked up exactly in a hash set or approximately in a
+//! bloom filter for files too large for memory
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufRead, BufReader, Error};
+use std::path::Path;
+
+/// false positive rate of the bloom filter, the share of the words wrongly excluded
+pub const BLOOM_FALSE_POSITIVE_RATE: f64 = 0.001;
+
+/// a bloom filter of byte strings, `k` bit positions per word by double hashing
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// a filter sized for `items` words at `false_positive_rate`
+    pub fn new(items: u64, false_positive_rate: f64) -> BloomFilter {
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = ((-(items.max(1) as f64) * false_positive_rate.ln() / (ln2 * ln2)).ceil()
+            as u64)
+            .max(64);
+        let num_hashes = ((num_bits as f64 / items.max(1) as f64) * ln2).round() as u32;
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes: num_hashes.clamp(1, 32),
+        }
+    }
+
+    fn positions(&self, word: &[u8]) -> impl Iterator<Item = u64> {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(word);
+        let hash = hasher.finish();
+        let (h1, h2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+
+    pub fn insert(&mut self, word: &[u8]) {
+        for pos in self.positions(word) {
+            self.bits[(pos / 64) as usize] |= 1 << (pos % 64);
+        }
+    }
+
+    /// true if `word` was inserted, or for a false positive
+    pub fn contains(&self, word: &[u8]) -> bool {
+        self.positions(word)
+            .all(|pos| self.bits[(pos / 64) as usize] & (1 << (pos % 64)) != 0)
+    }
+
+    /// memory used by the bits
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+}
+
+/// the excluded words
+pub enum ExcludeSet {
+    Exact(HashSet<Vec<u8>>),
+    Bloom(BloomFilter),
+}
+
+/// `ExcludeSet` with the words lowercased, the ascii letters only
+pub struct CaseInsensitive(pub ExcludeSet);
+
+impl CaseInsensitive {
+    pub fn from_file<P: AsRef<Path>>(fname: P, bloom: bool) -> Result<CaseInsensitive, Error> {
+        let lines = |fname: &Path| -> Result<_, Error> {
+            let lines = read_lines(fname)?;
+            Ok(lines.map(|line| line.map(|line| line.to_ascii_lowercase())))
+        };
+        Ok(CaseInsensitive(ExcludeSet::from_lines(
+            fname.as_ref(),
+            bloom,
+            lines,
+        )?))
+    }
+
+    pub fn contains(&self, word: &[u8]) -> bool {
+        self.0.contains(&word.to_ascii_lowercase())
+    }
+}
+
+/// the lines of `fname` without the newline (and a windows `\r`)
+pub fn read_lines(fname: &Path) -> Result<impl Iterator<Item = Result<Vec<u8>, Error>>, Error> {
+    let fp = BufReader::new(File::open(fname)?);
+    Ok(fp.split(b'\n').map(|line| {
+        let mut line = line?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(line)
+    }))
+}
+
+impl ExcludeSet {
+    /// loads every line of `fname`, a bloom filter reads the file twice to size the filter by
+    /// its lines
+    pub fn from_file<P: AsRef<Path>>(fname: P, bloom: bool) -> Result<ExcludeSet, Error> {
+        ExcludeSet::from_lines(fname.as_ref(), bloom, read_lines)
+    }
+
+    /// loads the words `lines` reads from `fname`
+    fn from_lines<F, I>(fname: &Path, bloom: bool, lines: F) -> Result<ExcludeSet, Error>
+    where
+        F: Fn(&Path) -> Result<I, Error>,
+        I: Iterator<Item = Result<Vec<u8>, Error>>,
+    {
+        if bloom {
+            let mut count = 0;
+            for line in lines(fname)? {
+                line?;
+                count += 1;
+            }
+            let mut filter = BloomFilter::new(count, BLOOM_FALSE_POSITIVE_RATE);
+            for line in lines(fname)? {
+                filter.insert(&line?);
+            }
+            Ok(ExcludeSet::Bloom(filter))
+        } else {
+            let words = lines(fname)?.collect::<Result<HashSet<_>, _>>()?;
+            Ok(ExcludeSet::Exact(words))
+        }
+    }
+
+    pub fn contains(&self, word: &[u8]) -> bool {
+        match self {
+            ExcludeSet::Exact(words) => words.contains(word),
+            ExcludeSet::Bloom(filter) => filter.contains(word),
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, ExcludeSet::Exact(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BloomFilter, CaseInsensitive, ExcludeSet};
+    use crate::test_util::wordlist_fname;
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut filter = BloomFilter::new(1000, 0.01);
+        for i in 0..1000 {
+            filter.insert(format!("word{}", i).as_bytes());
+        }
+        for i in 0..1000 {
+            assert!(filter.contains(format!("word{}", i).as_bytes()));
+        }
+        let false_positives = (0..10000)
+            .filter(|i| filter.contains(format!("other{}", i).as_bytes()))
+            .count();
+        assert!(false_positives < 300, "{}", false_positives);
+        // an empty filter contains nothing
+        assert!(!BloomFilter::new(0, 0.01).contains(b"word"));
+    }
+
+    #[test]
+    fn test_exclude_set() {
+        let fname = wordlist_fname("wordlist1.txt");
+        let lines: Vec<_> = std::fs::read_to_string(&fname)
+            .unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+        for bloom in [false, true] {
+            let excluded = ExcludeSet::from_file(&fname, bloom).unwrap();
+            assert_eq!(excluded.is_exact(), !bloom);
+            for line in lines.iter() {
+                assert!(excluded.contains(line.as_bytes()), "{}", line);
+            }
+            assert!(!excluded.contains(b"not in the wordlist"));
+        }
+        assert!(ExcludeSet::from_file("/no/such/file", false).is_err());
+
+        for bloom in [false, true] {
+            let excluded = CaseInsensitive::from_file(&fname, bloom).unwrap();
+            assert!(excluded.contains(b"PassWord"));
+            assert!(excluded.contains(b"password"));
+            assert!(!excluded.contains(b"Passwords"));
+        }
+    }
+}
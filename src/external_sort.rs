@@ -0,0 +1,266 @@
+//! sorts wordlists larger than the memory: sorted chunks of the lines are written to temporary
+//! files and merged (`wordlist sort`)
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Error, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::CrackenError;
+use crate::helpers::RawFileReader;
+
+/// memory counted for every line besides its bytes, the `Vec` holding it
+const LINE_OVERHEAD: usize = std::mem::size_of::<Vec<u8>>();
+/// most chunks merged at once, more chunks are merged in several passes
+pub const MAX_MERGE_CHUNKS: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct SortOptions {
+    /// memory of the lines of a chunk, sorted in memory before it is written
+    pub memory: usize,
+    /// directory of the temporary chunks files
+    pub tmp_dir: PathBuf,
+    /// write every distinct line once
+    pub unique: bool,
+}
+
+/// the counts of `sort_wordlist`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SortCounts {
+    /// non empty lines of the wordlist
+    pub input_lines: u64,
+    pub output_lines: u64,
+    /// the sorted chunks written to temporary files, 0 if the wordlist was sorted in memory
+    pub chunks: usize,
+}
+
+/// numbers the chunks directories of the sorts of a process
+static NEXT_CHUNKS_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// the temporary chunks files, removed with their directory when dropped
+struct ChunksDir {
+    path: PathBuf,
+    count: usize,
+}
+
+impl ChunksDir {
+    fn create(tmp_dir: &Path) -> Result<ChunksDir, Error> {
+        let id = NEXT_CHUNKS_DIR.fetch_add(1, Ordering::Relaxed);
+        let path = tmp_dir.join(format!("cracken-sort-{}-{}", std::process::id(), id));
+        fs::create_dir_all(&path)?;
+        Ok(ChunksDir { path, count: 0 })
+    }
+
+    fn next_path(&mut self) -> PathBuf {
+        self.count += 1;
+        self.path.join(format!("chunk-{}", self.count))
+    }
+}
+
+impl Drop for ChunksDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// writes the lines in order, every distinct line once if `unique`. returns the lines written
+fn write_lines<W: Write, I: Iterator<Item = Result<Vec<u8>, Error>>>(
+    out: &mut W,
+    lines: I,
+    unique: bool,
+) -> Result<u64, Error> {
+    let mut written = 0;
+    let mut last: Option<Vec<u8>> = None;
+    for line in lines {
+        let line = line?;
+        if unique && last.as_ref() == Some(&line) {
+            continue;
+        }
+        out.write_all(&line)?;
+        out.write_all(b"\n")?;
+        written += 1;
+        if unique {
+            last = Some(line);
+        }
+    }
+    Ok(written)
+}
+
+/// merges the sorted lines of the chunks files
+fn merge_chunks<W: Write>(out: &mut W, chunks: &[PathBuf], unique: bool) -> Result<u64, Error> {
+    let mut readers = chunks
+        .iter()
+        .map(|chunk| Ok(RawFileReader::new(File::open(chunk)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+    // the smallest line first, ties by chunk so the merge is stable
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+    for (idx, reader) in readers.iter_mut().enumerate() {
+        if let Some(line) = reader.next() {
+            heap.push(Reverse((line?, idx)));
+        }
+    }
+    let lines = std::iter::from_fn(|| {
+        let Reverse((line, idx)) = heap.pop()?;
+        match readers[idx].next() {
+            Some(Ok(next)) => heap.push(Reverse((next, idx))),
+            Some(Err(e)) => return Some(Err(e)),
+            None => {}
+        }
+        Some(Ok(line))
+    });
+    write_lines(out, lines, unique)
+}
+
+/// sorts the lines of `fname` byte-wise to `out`. windows line endings are removed and empty
+/// lines skipped. lines which don't fit in `options.memory` are sorted in chunks written to
+/// `options.tmp_dir` and merged, `progress` gets a line for every chunk and merge pass
+pub fn sort_wordlist<P: AsRef<Path>, W: Write, S: Write>(
+    fname: P,
+    out: &mut W,
+    options: &SortOptions,
+    progress: &mut S,
+) -> Result<SortCounts, CrackenError> {
+    let fname = fname.as_ref();
+    let wordlist_error = |e: Error| CrackenError::wordlist(fname, e);
+    let fp = File::open(fname).map_err(wordlist_error)?;
+    let mut counts = SortCounts::default();
+    let mut chunks_dir = None;
+    let mut chunks = vec![];
+    let mut lines = vec![];
+    let mut memory = 0;
+
+    let mut reader = RawFileReader::new(fp);
+    loop {
+        let line = reader.next().transpose().map_err(wordlist_error)?;
+        let end = line.is_none();
+        if let Some(mut line) = line {
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if line.is_empty() {
+                continue;
+            }
+            counts.input_lines += 1;
+            memory += line.len() + LINE_OVERHEAD;
+            lines.push(line);
+            if memory < options.memory {
+                continue;
+            }
+        }
+        // the last lines of a wordlist with chunks are a chunk too
+        if end && (chunks.is_empty() || lines.is_empty()) {
+            break;
+        }
+        lines.sort_unstable();
+        if chunks_dir.is_none() {
+            chunks_dir = Some(ChunksDir::create(&options.tmp_dir)?);
+        }
+        let path = chunks_dir.as_mut().unwrap().next_path();
+        let mut chunk = BufWriter::new(File::create(&path)?);
+        let written = write_lines(&mut chunk, lines.drain(..).map(Ok), options.unique)?;
+        chunk.flush()?;
+        chunks.push(path);
+        memory = 0;
+        writeln!(progress, "sorted chunk {}: {} lines", chunks.len(), written)?;
+        if end {
+            break;
+        }
+    }
+
+    counts.chunks = chunks.len();
+    let dir = match chunks_dir.as_mut() {
+        Some(dir) => dir,
+        None => {
+            lines.sort_unstable();
+            counts.output_lines = write_lines(out, lines.into_iter().map(Ok), options.unique)?;
+            return Ok(counts);
+        }
+    };
+    // merge passes of at most MAX_MERGE_CHUNKS chunks until they can be merged at once
+    while chunks.len() > MAX_MERGE_CHUNKS {
+        let mut merged = Vec::with_capacity(chunks.len().div_ceil(MAX_MERGE_CHUNKS));
+        for group in chunks.chunks(MAX_MERGE_CHUNKS) {
+            let path = dir.next_path();
+            let mut chunk = BufWriter::new(File::create(&path)?);
+            merge_chunks(&mut chunk, group, options.unique)?;
+            chunk.flush()?;
+            for done in group {
+                fs::remove_file(done)?;
+            }
+            merged.push(path);
+        }
+        writeln!(
+            progress,
+            "merged {} chunks into {}",
+            chunks.len(),
+            merged.len()
+        )?;
+        chunks = merged;
+    }
+    counts.output_lines = merge_chunks(out, &chunks, options.unique)?;
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::sink;
+
+    use super::{sort_wordlist, SortCounts, SortOptions, MAX_MERGE_CHUNKS};
+    use crate::helpers::SplitMix64;
+
+    fn sort(fname: &std::path::Path, memory: usize, unique: bool) -> (String, SortCounts) {
+        let options = SortOptions {
+            memory,
+            tmp_dir: std::env::temp_dir(),
+            unique,
+        };
+        let mut out = vec![];
+        let mut progress = vec![];
+        let counts = sort_wordlist(fname, &mut out, &options, &mut progress).unwrap();
+        (String::from_utf8(out).unwrap(), counts)
+    }
+
+    #[test]
+    fn test_sort_wordlist() {
+        let fname = std::env::temp_dir().join("cracken-test-sort.txt");
+        let mut rng = SplitMix64::new(3);
+        let words: Vec<String> = (0..5000)
+            .map(|_| format!("w{}", rng.next_u64() % 2000))
+            .collect();
+        let mut content = words.join("\r\n");
+        content.push_str("\n\n");
+        std::fs::write(&fname, content).unwrap();
+
+        let mut expected = words.clone();
+        expected.sort();
+        let mut expected_unique = expected.clone();
+        expected_unique.dedup();
+        let lines = |words: &[String]| words.iter().map(|w| format!("{}\n", w)).collect::<String>();
+
+        // in memory, in chunks and in chunks merged in several passes
+        for memory in [1 << 20, 4096, 100] {
+            let (sorted, counts) = sort(&fname, memory, false);
+            assert_eq!(sorted, lines(&expected), "{}", memory);
+            assert_eq!(counts.input_lines, 5000);
+            assert_eq!(counts.output_lines, 5000);
+            let (sorted, counts) = sort(&fname, memory, true);
+            assert_eq!(sorted, lines(&expected_unique), "{}", memory);
+            assert_eq!(counts.output_lines, expected_unique.len() as u64);
+            match memory {
+                1048576 => assert_eq!(counts.chunks, 0),
+                4096 => assert!((2..=MAX_MERGE_CHUNKS).contains(&counts.chunks)),
+                _ => assert!(counts.chunks > MAX_MERGE_CHUNKS),
+            }
+        }
+        std::fs::remove_file(fname).unwrap();
+
+        let options = SortOptions {
+            memory: 100,
+            tmp_dir: std::env::temp_dir(),
+            unique: false,
+        };
+        let err = sort_wordlist("/no/such/file", &mut sink(), &options, &mut sink());
+        assert!(err.is_err());
+    }
+}
@@ -0,0 +1,393 @@
+//! C API for pulling the generated words one by one, see `include/cracken.h`.
+//!
+//! error convention:
+//! * functions returning a pointer return NULL on error, and if `err_out` isn't NULL set
+//!   `*err_out` to an error message which must be freed with `cracken_string_free`
+//! * functions returning `isize` return a negative `CRACKEN_ERR_*` code on error
+//! * all strings are NUL terminated UTF-8
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use std::slice;
+
+use crate::builder::GeneratorBuilder;
+use crate::words::Words;
+
+/// a NULL pointer was passed for a required argument
+pub const CRACKEN_ERR_NULL: isize = -1;
+/// the next word is longer than the buffer, it is kept for the next call
+pub const CRACKEN_ERR_BUF_TOO_SMALL: isize = -2;
+/// buffers of this size can hold any generated word (a literal for cbindgen)
+pub const CRACKEN_MAX_WORD_SIZE: usize = 512;
+
+/// a generator running on its own thread, pulled by `cracken_generator_next`
+pub struct CrackenGenerator(Words);
+
+/// reads `n` strings of `strs` into owned strings
+unsafe fn read_strs(
+    strs: *const *const c_char,
+    n: usize,
+    what: &str,
+) -> Result<Vec<String>, String> {
+    if n == 0 {
+        return Ok(vec![]);
+    }
+    if strs.is_null() {
+        return Err(format!("{} is NULL", what));
+    }
+    slice::from_raw_parts(strs, n)
+        .iter()
+        .enumerate()
+        .map(|(i, s)| read_str(*s, &format!("{}[{}]", what, i)))
+        .collect()
+}
+
+unsafe fn read_str(s: *const c_char, what: &str) -> Result<String, String> {
+    if s.is_null() {
+        return Err(format!("{} is NULL", what));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map(|s| s.to_string())
+        .map_err(|e| format!("{} is not valid UTF-8: {}", what, e))
+}
+
+unsafe fn set_error(err_out: *mut *mut c_char, msg: String) {
+    if err_out.is_null() {
+        return;
+    }
+    // the messages never contain NUL bytes, the paths and masks come from C strings
+    let msg = CString::new(msg).unwrap_or_default();
+    *err_out = msg.into_raw();
+}
+
+/// creates a generator of `mask` with the custom charsets ?1 to ?9 and the wordlist files
+/// ?w1 to ?w9 (same syntax as the -c and -w arguments). returns NULL on error
+///
+/// # Safety
+///
+/// `mask` must be a NUL terminated string, `charsets` and `wordlists` arrays of
+/// `n_charsets` and `n_wordlists` NUL terminated strings (may be NULL if empty) and
+/// `err_out` NULL or a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn cracken_generator_new(
+    mask: *const c_char,
+    charsets: *const *const c_char,
+    n_charsets: usize,
+    wordlists: *const *const c_char,
+    n_wordlists: usize,
+    err_out: *mut *mut c_char,
+) -> *mut CrackenGenerator {
+    let res = (|| {
+        let mut builder = GeneratorBuilder::new().mask(read_str(mask, "mask")?);
+        for charset in read_strs(charsets, n_charsets, "charsets")? {
+            builder = builder.custom_charset(charset);
+        }
+        for fname in read_strs(wordlists, n_wordlists, "wordlists")? {
+            builder = builder.wordlist_file(fname);
+        }
+        Words::new(builder).map_err(|e| e.to_string())
+    })();
+
+    match res {
+        Ok(words) => Box::into_raw(Box::new(CrackenGenerator(words))),
+        Err(e) => {
+            set_error(err_out, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// copies the next word (without a newline or a NUL) to `buf` and returns its length,
+/// or 0 after the last word. returns `CRACKEN_ERR_BUF_TOO_SMALL` if the word is longer
+/// than `buf_len`, a buffer of `CRACKEN_MAX_WORD_SIZE` bytes always fits
+///
+/// # Safety
+///
+/// `gen` must be returned by `cracken_generator_new` and `buf` point to `buf_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn cracken_generator_next(
+    gen: *mut CrackenGenerator,
+    buf: *mut u8,
+    buf_len: usize,
+) -> isize {
+    if gen.is_null() || buf.is_null() {
+        return CRACKEN_ERR_NULL;
+    }
+    let words = &mut (*gen).0;
+    match words.peek_word() {
+        Some(word) if word.len() > buf_len => return CRACKEN_ERR_BUF_TOO_SMALL,
+        Some(_) => {}
+        None => return 0,
+    }
+    let word = words.next_word().unwrap();
+    ptr::copy_nonoverlapping(word.as_ptr(), buf, word.len());
+    word.len() as isize
+}
+
+/// copies as many of the next words as fit in `buf`, every one followed by a newline (without
+/// a NUL), and returns the number of bytes copied, or 0 after the last word. returns
+/// `CRACKEN_ERR_BUF_TOO_SMALL` if the next word and its newline are longer than `buf_len`.
+/// pulls many words per call, faster than `cracken_generator_next` for large keyspaces
+///
+/// # Safety
+///
+/// `gen` must be returned by `cracken_generator_new` and `buf` point to `buf_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn cracken_generator_next_chunk(
+    gen: *mut CrackenGenerator,
+    buf: *mut u8,
+    buf_len: usize,
+) -> isize {
+    if gen.is_null() || buf.is_null() {
+        return CRACKEN_ERR_NULL;
+    }
+    let words = &mut (*gen).0;
+    match words.peek_word() {
+        Some(word) if word.len() + 1 > buf_len => return CRACKEN_ERR_BUF_TOO_SMALL,
+        Some(_) => {}
+        None => return 0,
+    }
+    let mut chunk = Vec::with_capacity(buf_len);
+    words.next_chunk(&mut chunk, buf_len);
+    ptr::copy_nonoverlapping(chunk.as_ptr(), buf, chunk.len());
+    chunk.len() as isize
+}
+
+/// returns the number of words of the generator, UINT64_MAX if it doesn't fit
+///
+/// # Safety
+///
+/// `gen` must be returned by `cracken_generator_new`
+#[no_mangle]
+pub unsafe extern "C" fn cracken_generator_combinations(gen: *const CrackenGenerator) -> u64 {
+    if gen.is_null() {
+        return 0;
+    }
+    match (*gen).0.combinations().to_u64_digits()[..] {
+        [] => 0,
+        [combs] => combs,
+        _ => u64::MAX,
+    }
+}
+
+/// skips the next `n` words and returns the number of skipped words, less than `n` only
+/// after the last word
+///
+/// # Safety
+///
+/// `gen` must be returned by `cracken_generator_new`
+#[no_mangle]
+pub unsafe extern "C" fn cracken_generator_skip(gen: *mut CrackenGenerator, n: u64) -> u64 {
+    if gen.is_null() {
+        return 0;
+    }
+    (*gen).0.skip_words(n)
+}
+
+/// stops the generator and frees it, NULL is ignored
+///
+/// # Safety
+///
+/// `gen` must be returned by `cracken_generator_new` and not used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn cracken_generator_free(gen: *mut CrackenGenerator) {
+    if !gen.is_null() {
+        drop(Box::from_raw(gen));
+    }
+}
+
+/// frees an error message returned by the cracken functions, NULL is ignored
+///
+/// # Safety
+///
+/// `s` must be returned by a cracken function and not used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn cracken_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_char;
+    use std::ptr;
+
+    use super::*;
+    use crate::test_util::wordlist_fname;
+
+    fn new_gen(
+        mask: &str,
+        charsets: &[&str],
+        wordlists: &[&str],
+    ) -> Result<*mut CrackenGenerator, String> {
+        let mask = CString::new(mask).unwrap();
+        let charsets: Vec<_> = charsets.iter().map(|s| CString::new(*s).unwrap()).collect();
+        let charsets: Vec<_> = charsets.iter().map(|s| s.as_ptr()).collect();
+        let wordlists: Vec<_> = wordlists
+            .iter()
+            .map(|s| CString::new(*s).unwrap())
+            .collect();
+        let wordlists: Vec<_> = wordlists.iter().map(|s| s.as_ptr()).collect();
+        let mut err: *mut c_char = ptr::null_mut();
+        unsafe {
+            let gen = cracken_generator_new(
+                mask.as_ptr(),
+                charsets.as_ptr(),
+                charsets.len(),
+                wordlists.as_ptr(),
+                wordlists.len(),
+                &mut err,
+            );
+            if gen.is_null() {
+                let msg = CStr::from_ptr(err).to_str().unwrap().to_string();
+                cracken_string_free(err);
+                return Err(msg);
+            }
+            assert!(err.is_null());
+            Ok(gen)
+        }
+    }
+
+    fn next_words(gen: *mut CrackenGenerator) -> Vec<String> {
+        let mut buf = [0u8; CRACKEN_MAX_WORD_SIZE];
+        let mut words = vec![];
+        loop {
+            let len = unsafe { cracken_generator_next(gen, buf.as_mut_ptr(), buf.len()) };
+            assert!(len >= 0);
+            if len == 0 {
+                return words;
+            }
+            words.push(String::from_utf8(buf[..len as usize].to_vec()).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_ffi_max_word_size() {
+        assert_eq!(CRACKEN_MAX_WORD_SIZE, crate::MAX_WORD_SIZE);
+    }
+
+    #[test]
+    fn test_ffi_generate() {
+        let wordlist = wordlist_fname("wordlist-no-newline.txt");
+        let gen = new_gen("?w1?1", &["ab"], &[wordlist.to_str().unwrap()]).unwrap();
+        unsafe {
+            assert_eq!(cracken_generator_combinations(gen), 6);
+            let words = next_words(gen);
+            assert_eq!(
+                words,
+                vec![
+                    "johna",
+                    "johnb",
+                    "emilea",
+                    "emileb",
+                    "McDonalda",
+                    "McDonaldb"
+                ]
+            );
+            cracken_generator_free(gen);
+        }
+    }
+
+    #[test]
+    fn test_ffi_skip_and_buffer() {
+        let gen = new_gen("?d?d?d?d", &[], &[]).unwrap();
+        unsafe {
+            assert_eq!(cracken_generator_combinations(gen), 10000);
+            assert_eq!(cracken_generator_skip(gen, 9997), 9997);
+
+            let mut buf = [0u8; 4];
+            assert_eq!(
+                cracken_generator_next(gen, buf.as_mut_ptr(), 3),
+                CRACKEN_ERR_BUF_TOO_SMALL
+            );
+            assert_eq!(cracken_generator_next(gen, buf.as_mut_ptr(), 4), 4);
+            assert_eq!(&buf, b"9997");
+
+            assert_eq!(cracken_generator_skip(gen, 5), 2);
+            assert_eq!(cracken_generator_next(gen, buf.as_mut_ptr(), 4), 0);
+            assert_eq!(
+                cracken_generator_next(ptr::null_mut(), buf.as_mut_ptr(), 4),
+                CRACKEN_ERR_NULL
+            );
+            cracken_generator_free(gen);
+        }
+
+        // freeing stops a generator which didn't finish
+        let gen = new_gen("?d?d?d?d?d?d?d?d", &[], &[]).unwrap();
+        assert_eq!(unsafe { cracken_generator_skip(gen, 10) }, 10);
+        unsafe { cracken_generator_free(gen) };
+    }
+
+    #[test]
+    fn test_ffi_next_chunk() {
+        let gen = new_gen("?d?d", &[], &[]).unwrap();
+        unsafe {
+            let mut buf = [0u8; 10];
+            assert_eq!(
+                cracken_generator_next_chunk(gen, buf.as_mut_ptr(), 2),
+                CRACKEN_ERR_BUF_TOO_SMALL
+            );
+            assert_eq!(cracken_generator_next_chunk(gen, buf.as_mut_ptr(), 10), 9);
+            assert_eq!(&buf[..9], b"00\n01\n02\n");
+            assert_eq!(cracken_generator_next(gen, buf.as_mut_ptr(), 2), 2);
+            assert_eq!(&buf[..2], b"03");
+
+            let mut out = vec![];
+            loop {
+                let n = cracken_generator_next_chunk(gen, buf.as_mut_ptr(), buf.len());
+                assert!(n >= 0);
+                if n == 0 {
+                    break;
+                }
+                out.extend_from_slice(&buf[..n as usize]);
+            }
+            assert_eq!(out.len(), 96 * 3);
+            assert!(out.starts_with(b"04\n05\n"));
+            assert!(out.ends_with(b"98\n99\n"));
+            assert_eq!(
+                cracken_generator_next_chunk(ptr::null_mut(), buf.as_mut_ptr(), 10),
+                CRACKEN_ERR_NULL
+            );
+            cracken_generator_free(gen);
+        }
+    }
+
+    #[test]
+    fn test_ffi_errors() {
+        assert_eq!(new_gen("?d?", &[], &[]).err().unwrap(), "Invalid mask");
+        assert!(new_gen("?w1", &[], &["/tmp/this/dir/not/exisT.txt"])
+            .err()
+            .unwrap()
+            .starts_with("wordlist /tmp/this/dir/not/exisT.txt: "));
+
+        let mut err: *mut c_char = ptr::null_mut();
+        unsafe {
+            let gen = cracken_generator_new(ptr::null(), ptr::null(), 0, ptr::null(), 0, &mut err);
+            assert!(gen.is_null());
+            assert_eq!(CStr::from_ptr(err).to_str().unwrap(), "mask is NULL");
+            cracken_string_free(err);
+
+            let mask = CString::new("?1").unwrap();
+            let gen =
+                cracken_generator_new(mask.as_ptr(), ptr::null(), 1, ptr::null(), 0, &mut err);
+            assert!(gen.is_null());
+            assert_eq!(CStr::from_ptr(err).to_str().unwrap(), "charsets is NULL");
+            cracken_string_free(err);
+
+            // err_out is optional
+            let gen = cracken_generator_new(
+                mask.as_ptr(),
+                ptr::null(),
+                0,
+                ptr::null(),
+                0,
+                ptr::null_mut(),
+            );
+            assert!(gen.is_null());
+        }
+    }
+}
@@ -0,0 +1,1194 @@
+//! the options of the generate subcommand, its args parsed and checked against each other
+//! before anything is generated or written
+use std::env;
+use std::io::{stderr, IsTerminal};
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use num_bigint::BigUint;
+
+use crate::charsets::CustomCharset;
+use crate::generators::{LengthOrder, RepeatLimits};
+use crate::helpers::{parse_duration, parse_escaped_bytes, parse_rate, parse_size, IndexedFormat};
+use crate::io_backend::IoBackend;
+use crate::mask::{JoinMode, MaskSyntax};
+use crate::model::{Model, DEFAULT_BEAM};
+use crate::runner::{
+    get_config_profile, get_custom_charsets, get_io_buffer, get_length_arg, get_max_keyspace,
+    get_rates, get_wordlists_options, optional_value_t, parse_slice, read_length_histogram,
+    read_mask_arg, read_masks_file, read_model, with_wordlist_dir, MaskLine, RunError,
+    DEFAULT_VERIFY_MAX, ENV_BUFFER_SIZE, ENV_MAX_KEYSPACE, ENV_OUTPUT, ENV_WORDLIST_DIR,
+};
+use crate::wordlists::WordlistOptions;
+use crate::writer_chain::OutputOptions;
+use crate::BoxResult;
+
+/// the generate subcommand and its args
+pub fn generate_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("generate")
+        .about("(default) - Generates newline separated words according to given mask and wordlist files")
+        .display_order(0)
+    .arg(
+        Arg::with_name("mask")
+            .long_help(
+                r#"the wordlist mask to generate.
+@file reads the mask from a file and @- from stdin: a single mask line, the empty and # comment
+lines are skipped (use -i for a file of several masks). a mask starting with a literal @ is
+written \@.
+available masks are:
+    builtin charsets:
+    ?d - digits: "0123456789"
+    ?l - lowercase: "abcdefghijklmnopqrstuvwxyz"
+    ?u - uppercase: "ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+    ?s - symbols: " !\"\#$%&'()*+,-./:;<=>?@[\\]^_`{|}~"
+    ?a - all characters: ?d + ?l + ?u + ?s
+    ?b - all binary values: (0-255)
+
+    custom charsets ?1 to ?9:
+    ?1 - first custom charset specified by --charset 'mychars'
+         or by --tokens 'token1|token2' (numbered by order of appearance)
+
+    wordlists ?w1 to ?w9:
+    ?w1 - first wordlist specified by --wordlist 'my-wordlist.txt'
+    ?w1:rev - first wordlist with every word reversed (e.g. drowssap)
+    ?w1:shift - first wordlist typed with shift held on a qwerty keyboard (e.g. PASS!)
+    ?w1:adjacent - first wordlist with a key replaced by its left or right qwerty neighbour
+                   (e.g. [assword), up to 32 words of every word
+    ?w1[:4] - first 4 chars of every word of the first wordlist (deduplicated),
+              slices like [2:] and [1:5] are also supported
+
+    keyboard walks (adjacent qwerty keys, e.g. qwerty, 1qaz, zaq1):
+    ?K{4} - all keyboard walks of length 4
+    ?K{3-6} - all keyboard walks of length 3 to 6
+    ?K{3-6,shift} - same as above including walks on the shifted keys (e.g. !QAZ)
+    the walks are generated upfront and limited to 10M (about length 8)
+
+    calendar dates (only valid dates, leap years included):
+    ?D{DDMMYYYY,1950-2010} - all dates from 01011950 to 31122010
+    ?D{YYYY-MM-DD,1990-1999} - formats are composed from YYYY, YY, MM, M, DD, D and - . / _
+
+    backreferences to an earlier position of the mask (positions are numbered from 1):
+    ?r1 - the same value as the first position, e.g. ?d?l?r1 generates 0a0 to 9z9
+          (all the digits are the position, ?r1\2 is ?r1 followed by 2)
+"#,
+            )
+            .takes_value(true)
+            .required_unless("masks-file"),
+    )
+    .arg(Arg::with_name("masks-file")
+            .short("i")
+            .long("masks-file")
+            .help("a file containing masks to generate, a mask per line. the lines of a .hcmask file are hashcat hcmask lines, up to 4 comma separated custom charsets before the mask (e.g. ?l?d,?1?1?1). with --stats, prints the keyspace of every mask and the total")
+            .takes_value(true)
+            .required_unless("mask"),
+    )
+    .arg(
+        Arg::with_name("min-length")
+            .short("m")
+            .long("minlen")
+            .help("minimum length of the mask to start from")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("max-length")
+            .short("x")
+            .long("maxlen")
+            .help("maximum length of the mask to start from")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("stats")
+            .short("s")
+            .long("stats")
+            .help("prints the number of words this command will generate and exits")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("rate")
+            .long("rate")
+            .help("with --stats, prints how long the downstream cracker takes to try all the words at this many candidates per second. accepts k, M, G and T suffixes (e.g. 50GH)")
+            .takes_value(true)
+            .required(false)
+            .requires("stats"),
+    )
+    .arg(
+        Arg::with_name("keyspace-hashcat")
+            .long("keyspace-hashcat")
+            .help("with --stats, also prints the keyspace hashcat --keyspace reports for the mask with a fast hash mode (e.g. -m 0). hashcat doesn't count the last 1 to 4 positions it amplifies, its -s and -l are in these base words")
+            .takes_value(false)
+            .required(false)
+            .requires("stats"),
+    )
+    .arg(
+        Arg::with_name("sort-keyspace")
+            .long("sort-keyspace")
+            .help("with --stats and a masks file, prints the masks by keyspace, the largest first")
+            .takes_value(false)
+            .required(false)
+            .requires("stats"),
+    )
+    .arg(
+        Arg::with_name("rate-file")
+            .long("rate-file")
+            .help("with --stats, prints the time for every rate in this file of '<hash mode> <rate>' lines (e.g. 'NTLM 50GH')")
+            .takes_value(true)
+            .required(false)
+            .requires("stats"),
+    )
+    .arg(
+        Arg::with_name("verify")
+            .long("verify")
+            .help("generates the words of every mask without writing them and checks that every word is generated once and their number is the --stats combinations, prints PASS or FAIL with the numbers and fails on a mismatch. uses 16 bytes of memory per word")
+            .takes_value(false)
+            .required(false)
+            .conflicts_with_all(&["stats", "distribute", "slice", "skip", "start-from", "end-at", "seq-max", "occurrence-max"]),
+    )
+    .arg(
+        Arg::with_name("verify-max")
+            .long("verify-max")
+            .help("with --verify, refuses the masks of more words than this in total (default: 10000000)")
+            .takes_value(true)
+            .required(false)
+            .requires("verify"),
+    ).arg(
+        Arg::with_name("custom-charset")
+            .short("c")
+            .long("custom-charset")
+            .help("custom charset (string of chars). up to 9 custom charsets - ?1 to ?9. use ?1 on the mask for the first charset")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .max_values(9),
+    )
+    .arg(
+        Arg::with_name("tokens")
+            .long("tokens")
+            .help("custom charset of multi-char tokens separated by '|' (e.g. '!|!!|123'). shares the ?1 to ?9 numbering with --custom-charset by order of appearance")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .max_values(9),
+    )
+    .arg(
+        Arg::with_name("wordlist")
+            .short("w")
+            .long("wordlist")
+            .help("filename containing newline (0xA) separated words, optionally followed by an encoding :utf8, :latin1 or :auto and a case transform :lower, :upper, :capitalize or :invert (escape colons in filename with \\:). note: currently all wordlists loaded to memory")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .max_values(9),
+    )
+    .arg(
+        Arg::with_name("wordlist-filter")
+            .long("wordlist-filter")
+            .help("load only the words matching the given regex from the wordlist preceding this option (e.g. -w big.txt --wordlist-filter '^[a-z]{4,8}$')")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .max_values(9),
+    )
+    .arg(
+        Arg::with_name("wordlist-sample")
+            .long("wordlist-sample")
+            .help("load only a random fraction of every wordlist (e.g. 0.01 for 1%), useful for quick dry runs")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("wordlist-sample-n"),
+    )
+    .arg(
+        Arg::with_name("wordlist-sample-n")
+            .long("wordlist-sample-n")
+            .help("load only N random words of every wordlist")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("max-wordlist-memory")
+            .long("max-wordlist-memory")
+            .help("fail loading a wordlist whose words need more memory than SIZE (e.g. 2G) instead of running out of memory. unlimited by default")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("seed")
+            .long("seed")
+            .help("seed of the random wordlists sample and of --shuffle, makes them reproducible")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("trim-wordlist")
+            .long("trim-wordlist")
+            .help("trim trailing spaces and tabs of wordlists words (a trailing \\r is always removed)")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("wordlist-comments")
+            .long("wordlist-comments")
+            .help("skip wordlists lines starting with the given prefix (e.g. '#')")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("keep-blank-lines")
+            .long("keep-blank-lines")
+            .help("load wordlists lines containing only spaces and tabs as words (empty lines are always skipped)")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("skip-invalid-lines")
+            .long("skip-invalid-lines")
+            .help("skip wordlists lines which are too long for the mask or contain NUL bytes instead of failing")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("wordlist-encoding")
+            .long("wordlist-encoding")
+            .help("encoding of the wordlists, words are converted to utf-8 when loaded. auto decodes words which are not valid utf-8 as latin1. can be overridden per wordlist with -w <file>:<encoding>")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["utf8", "latin1", "auto"])
+            .default_value("utf8"),
+    )
+    .arg(
+        Arg::with_name("output-encoding")
+            .long("output-encoding")
+            .help("encoding of the generated words, latin1 converts the words back to single byte latin1 characters. utf16le encodes the words and their newlines in 2 or 4 bytes per char (e.g. for NTLM), words which are not valid utf-8 fail unless --lossy. hashcat-hex writes the words with bytes outside of printable ascii as $HEX[...] like hashcat potfiles. base64 writes every word in standard base64, padded unless --base64-no-pad")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["utf8", "latin1", "utf16le", "hashcat-hex", "base64"])
+            .default_value("utf8"),
+    )
+    .arg(
+        Arg::with_name("base64-no-pad")
+            .long("base64-no-pad")
+            .help("with --output-encoding base64, writes the words without the = padding")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("lossy")
+            .long("lossy")
+            .help("with --output-encoding utf16le, writes the bytes of the words which are not valid utf-8 as U+FFFD instead of failing")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("no-wordlist-hex")
+            .long("no-wordlist-hex")
+            .help("load $HEX[...] wordlists lines as is instead of decoding them to raw bytes")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("output-hex")
+            .long("output-hex")
+            .help("write words containing control chars (e.g. newline) or invalid utf-8 as $HEX[...]")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("format")
+            .long("format")
+            .help("format of the generated words. jsonl writes a json object per word with the word (or hex for invalid utf-8), its length in bytes and its index in the generation order of its mask. every word is encoded on its own which makes jsonl several times slower than plain lines (default: plain). json is the format of --distribute and --stats")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["plain", "jsonl", "json"]),
+    )
+    .arg(
+        Arg::with_name("with-index")
+            .long("with-index")
+            .help("write the index of every word in the generation order of its mask and a tab before it (e.g. 42<TAB>0042), --skip and --start-from words are counted")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("min-entropy")
+            .long("min-entropy")
+            .help("skip generated words with a charset entropy (bits) lower than this. the entropy is computed for every word which slows down the generation")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("max-entropy")
+            .long("max-entropy")
+            .help("skip generated words with a charset entropy (bits) higher than this. the entropy is computed for every word which slows down the generation")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("exclude")
+            .long("exclude")
+            .help("skip generated words found in this file of newline separated words, e.g. the words already tried. the file is loaded to memory, see --exclude-bloom for huge files")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("exclude-bloom")
+            .long("exclude-bloom")
+            .help("load --exclude to a bloom filter of about 2 bytes per word instead of all the words. about 1 in 1000 of the other words is skipped too")
+            .takes_value(false)
+            .required(false)
+            .requires("exclude"),
+    )
+    .arg(
+        Arg::with_name("seq-max")
+            .long("seq-max")
+            .help("skip words with more than N identical consecutive chars (e.g. 2 skips aaa), same as maskprocessor -q. the skipped charset words are never generated")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("occurrence-max")
+            .short("r")
+            .long("occurrence-max")
+            .help("skip words with any char occurring more than N times (e.g. 1 skips aba), same as maskprocessor -r. the skipped charset words are never generated")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("syntax")
+            .long("syntax")
+            .help("syntax of the masks. john accepts John the Ripper masks (john --mask=) - ?w is the ?w1 wordlist, [a-z] ranges and ?h/?H become custom charsets after the -c ones, the 8-bit placeholders are not supported. the words are the same as john's in cracken's order (the last position changes first)")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["hashcat", "john"])
+            .default_value("hashcat"),
+    )
+    .arg(
+        Arg::with_name("output-file")
+            .short("o")
+            .long("output-file")
+            .help("output file to write the wordlist to, defaults to $CRACKEN_OUTPUT or stdout. repeat it (and add --pipe-to) to write the same words to several outputs at once")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("skip")
+            .long("skip")
+            .help("skip the first N generated words, e.g. to resume a run interrupted with ctrl-c. the masks seek to the next word without generating the skipped ones, except with --seq-max, --occurrence-max and --interleave")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("max-bytes")
+            .long("max-bytes")
+            .help("stop once the next word would write more than this many bytes (newlines, --prefix and --suffix included), with an optional k, M, G or T suffix (e.g. 10G). the number of words generated is printed to resume with --skip")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("max-keyspace")
+            .long("max-keyspace")
+            .help("refuse to write more words than this to an output file, optionally in scientific notation (e.g. 1e13). stdout, --pipe-to, devices like /dev/null and --max-bytes runs are not limited (default: 1e12, also set by $CRACKEN_MAX_KEYSPACE or max-keyspace in the config file)")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("yes-i-know")
+            .long("yes-i-know")
+            .help("write the words to the output file even if they are more than --max-keyspace")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("checksum")
+            .long("checksum")
+            .help("print the checksum of all the bytes written and their number to stderr when done, e.g. to compare a resumed run with a clean one without keeping the words")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["sha256"]),
+    )
+    .arg(
+        Arg::with_name("max-rate")
+            .long("max-rate")
+            .help("write at most this many words per second, with an optional k, M, G or T suffix (e.g. 500k), to not overwhelm a slow consumer. whole buffers of words wait, see --io-buffer for smaller ones")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("status")
+            .long("status")
+            .help("print the progress to stderr every interval (e.g. --status=30s), by default every second on a terminal and every 10 seconds otherwise: the words generated out of the total, percent, current and average rate, eta and the last word generated. a single line is updated on a terminal, a line is printed every interval otherwise. on unix a status line is also printed on SIGUSR1 (kill -USR1), with or without --status")
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("timeout")
+            .long("timeout")
+            .help("stop cleanly after this long with an optional ms, s, m, h or d suffix (e.g. 10m), checked before every write of --io-buffer bytes. exits 0 and prints the number of words generated to resume with --skip")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("start-from")
+            .long("start-from")
+            .help("resume after this word (e.g. the last one tried according to the cracker log), generating from the next word. the masks before the first one generating it are skipped. accepts $HEX[...] words")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("skip"),
+    )
+    .arg(
+        Arg::with_name("end-at")
+            .long("end-at")
+            .help("stop after generating this word, e.g. to regenerate a range between two words of a cracker log with --start-from. with --stats prints the number of words of the range. accepts $HEX[...] words")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("slice")
+            .long("slice")
+            .help("generate only the i-th of n equal shares of the words of all the masks (e.g. 3/8), starting at the share without generating the words before it. the shares 1/n to n/n generate every word once, see --distribute")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with_all(&["start-from", "end-at"]),
+    )
+    .arg(
+        Arg::with_name("distribute")
+            .long("distribute")
+            .help("print the commands generating the words in n shares instead of generating them, e.g. to run on n machines: this command with --slice 1/n to --slice n/n. with --format json prints a json array of the shares, their first word and number of words")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with_all(&["slice", "start-from", "end-at", "stats", "interleave", "order"]),
+    )
+    .arg(
+        Arg::with_name("prefix")
+            .long("prefix")
+            .help("write this before every generated word, e.g. a known part of the password. \\xNN escapes any byte and \\\\ is a backslash. unlike a mask prefix it doesn't change the number of words")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("output-hex"),
+    )
+    .arg(
+        Arg::with_name("join")
+            .long("join")
+            .help("insert this separator between the items of the mask (not inside them and not at its ends), e.g. -w a.txt -w b.txt --join - '?w1?w2?d?d' generates alpha-beta-42. the words are longer by the separators. \\xNN escapes any byte and \\\\ is a backslash")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("join-mode")
+            .long("join-mode")
+            .help("where --join inserts the separator, between all the items of the mask or only next to its wordlists (?w1-?w2-?d?d) (default: wordlists)")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["all", "wordlists"])
+            .requires("join"),
+    )
+    .arg(
+        Arg::with_name("palindrome")
+            .long("palindrome")
+            .help("generate only the words which read the same forwards and backwards, the first half of the mask is enumerated and mirrored (e.g. ?d?d?d?d generates the 100 words 0000 to 9999 like 1221). the mirrored positions must have the same chars")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("suffix")
+            .long("suffix")
+            .help("write this after every generated word, with the same escapes as --prefix")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("output-hex"),
+    )
+    .arg(
+        Arg::with_name("interleave")
+            .long("interleave")
+            .help("with several masks, write --interleave-chunk words of every mask in turn instead of one mask after the other, so the likely words of every mask come early. the words are the same")
+            .takes_value(false)
+            .required(false)
+            .conflicts_with_all(&["start-from", "end-at", "slice"]),
+    )
+    .arg(
+        Arg::with_name("interleave-chunk")
+            .long("interleave-chunk")
+            .help("words of a mask written before moving to the next one with --interleave, and of a length before the next one with --length-order interleave")
+            .takes_value(true)
+            .required(false)
+            .default_value("65536"),
+    )
+    .arg(
+        Arg::with_name("dedup-across-masks")
+            .long("dedup-across-masks")
+            .help("with a masks file, skip the words of a mask which an earlier mask generates, so every word is written once (e.g. ?l?l?l after ?a?a?a). every word is looked up in the earlier masks of its length. the words left are counted from the charsets of the masks, the masks with wordlists are generated to count them (at most 10^8 words)")
+            .takes_value(false)
+            .required(false)
+            .requires("masks-file")
+            .conflicts_with_all(&["start-from", "end-at", "slice", "distribute"]),
+    )
+    .arg(
+        Arg::with_name("length-order")
+            .long("length-order")
+            .help("order of the lengths between --minlen and --maxlen. interleave writes --interleave-chunk words of every length in turn, likely orders the lengths by the --length-histogram counts. the words are the same")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["asc", "desc", "interleave", "likely"])
+            .default_value("asc"),
+    )
+    .arg(
+        Arg::with_name("length-histogram")
+            .long("length-histogram")
+            .help("file of 'length count' lines (e.g. of the cracked passwords) for --length-order likely, the most common lengths first")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("order")
+            .long("order")
+            .help("generate the words of charset masks from the most likely according to this model (see cracken train). the order is approximate, the partial words are expanded best first keeping at most --beam of them")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with_all(&["start-from", "end-at", "slice"]),
+    )
+    .arg(
+        Arg::with_name("shuffle")
+            .long("shuffle")
+            .help("generate the words of every charsets or wordlists mask in a pseudo random order given by --seed, every word once. the same seed gives the same order, so --skip resumes it")
+            .takes_value(false)
+            .required(false)
+            .requires("seed")
+            .conflicts_with("order"),
+    )
+    .arg(
+        Arg::with_name("beam")
+            .long("beam")
+            .help("partial words kept by --order, the least likely words are missing once they don't fit. memory grows with it (default: 1000000)")
+            .takes_value(true)
+            .required(false)
+            .requires("order"),
+    )
+    .arg(
+        Arg::with_name("io-buffer")
+            .long("io-buffer")
+            .help("bytes of words generated before every write to the output and size of the output buffer, with an optional k, M or G suffix (e.g. 4M for fast disks, 64 for interactive consumers). defaults to $CRACKEN_BUFFER_SIZE or 8k")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("io-backend")
+            .long("io-backend")
+            .help("how the words are written to the output - buffered write() calls (std), vmsplice of the buffers into a pipe or io_uring writes, the last two on linux only. falls back to std with a note when unsupported")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["std", "vmsplice", "uring"])
+            .default_value("std"),
+    )
+    .arg(
+        Arg::with_name("pipe-to")
+            .long("pipe-to")
+            .help("run this shell command and write the words to its stdin instead of stdout (e.g. 'hashcat -m 1000 hashes.txt'), exits with the command exit code. with -o the words are written to both")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("tee-best-effort")
+            .long("tee-best-effort")
+            .help("with several outputs, stop writing to an output which fails (with a warning) and go on with the others instead of stopping")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("profile")
+            .short("P")
+            .long("profile")
+            .help("apply the defaults of the given profile section of the config file on top of its [default] section")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("config")
+            .long("config")
+            .help("config file with default charsets, wordlists and output options, defaults to ~/.config/cracken/config.toml. command line flags always override it")
+            .takes_value(true)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("no-config")
+            .long("no-config")
+            .help("ignore the config file")
+            .takes_value(false)
+            .required(false)
+            .conflicts_with_all(&["profile", "config"]),
+    )
+    .arg(
+        Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .help("print the mask, keyspace and output before generating and a summary (words, bytes, time and rate) when done to stderr")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .help("don't print anything to stderr, including warnings")
+            .takes_value(false)
+            .required(false)
+            .conflicts_with("verbose"),
+    )
+    .arg(
+        Arg::with_name("append")
+            .long("append")
+            .help("append the words to the output file instead of truncating it")
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("atomic")
+            .long("atomic")
+            .help("write every output file to FILE.partial and rename it to FILE once the run succeeds, so a failed or interrupted run never leaves a file looking complete. the partial file is kept for inspection unless --no-keep-partial is given")
+            .takes_value(false)
+            .required(false)
+            .conflicts_with_all(&["append", "stats"]),
+    )
+    .arg(
+        Arg::with_name("no-keep-partial")
+            .long("no-keep-partial")
+            .help("remove the partial files of --atomic when the run fails or is interrupted")
+            .takes_value(false)
+            .required(false)
+            .requires("atomic"),
+    )
+    .arg(
+        Arg::with_name("force")
+            .long("force")
+            .help("overwrite the output file if it already exists, and write it even when its filesystem has less free space than the estimated size of the words")
+            .takes_value(false)
+            .required(false)
+            .conflicts_with("append"),
+    )
+    .arg(
+        Arg::with_name("compression-ratio")
+            .long("compression-ratio")
+            .help("divide the estimated size of the words by this before checking the free space of the output files, for filesystems compressing them (e.g. 3 for plain words on btrfs or zfs with zstd). the output files of a filesystem are checked together against its free space (default: 1)")
+            .takes_value(true)
+            .required(false),
+    )
+}
+
+/// the options of a generate run, parsed from its args and the config profile. every check
+/// of the args is done here, before the outputs are created
+pub struct GenerateOptions {
+    /// the mask argument or the masks of the masks file (-i)
+    pub masks: Vec<MaskLine>,
+    /// the mask was read from stdin (`@-`), the --distribute commands are given the mask read
+    pub stdin_mask: bool,
+    pub masks_file: bool,
+    /// --minlen and --maxlen
+    pub lengths: (Option<usize>, Option<usize>),
+    pub outputs: OutputOptions,
+    /// the share i of n of --slice
+    pub slice: Option<(u64, u64)>,
+    /// the shares of --distribute
+    pub distribute: Option<u64>,
+    /// --format json, of --distribute and --stats
+    pub json: bool,
+    /// the --rate and --rate-file rates of --stats, None without --stats
+    pub stats: Option<Vec<(String, u64)>>,
+    pub keyspace_hashcat: bool,
+    pub sort_keyspace: bool,
+    /// the --verify-max words of --verify, None without --verify
+    pub verify: Option<u64>,
+    /// the --checksum algorithm
+    pub checksum: Option<String>,
+    pub verbose: bool,
+    pub quiet: bool,
+    pub output_encoding: String,
+    /// --lossy utf16le
+    pub lossy: bool,
+    /// base64 words are padded, unless --base64-no-pad
+    pub base64_pad: bool,
+    /// the words are written as $HEX[...] by the generator, with --output-hex or to be
+    /// encoded by --output-encoding hashcat-hex and base64
+    pub output_hex: bool,
+    pub prefix: Vec<u8>,
+    pub suffix: Vec<u8>,
+    /// the --min-entropy and --max-entropy bits of the words written
+    pub entropy: Option<(f64, f64)>,
+    /// the --exclude file and whether it is loaded to a bloom filter (--exclude-bloom)
+    pub exclude: Option<(String, bool)>,
+    pub skip: u64,
+    /// the masks seek to the first word after the --skip words instead of generating them
+    pub seek_skip: bool,
+    pub timeout: Option<Duration>,
+    pub max_bytes: Option<u64>,
+    pub max_rate: Option<u64>,
+    /// the --status interval
+    pub status: Option<Duration>,
+    /// the custom charsets, true for --tokens
+    pub custom_charsets: Vec<(String, bool)>,
+    pub wordlists: Vec<String>,
+    pub wordlists_options: Vec<WordlistOptions>,
+    pub syntax: MaskSyntax,
+    pub repeat_limits: RepeatLimits,
+    /// the --interleave-chunk of --interleave, None without --interleave
+    pub interleave_chunk: Option<u64>,
+    pub length_order: LengthOrder,
+    /// the --order model and beam
+    pub order: Option<(Arc<Model>, usize)>,
+    /// the --shuffle seed
+    pub shuffle: Option<u64>,
+    /// --format jsonl or --with-index
+    pub indexed_format: Option<IndexedFormat>,
+    /// the --join separator and mode
+    pub join: Option<(String, JoinMode)>,
+    pub palindrome: bool,
+    pub dedup_across_masks: bool,
+    /// the --start-from and --end-at words, as given
+    pub start_from: Option<String>,
+    pub end_at: Option<String>,
+    pub max_keyspace: BigUint,
+    /// --yes-i-know, the --max-keyspace guard is off
+    pub yes_i_know: bool,
+    /// --compression-ratio of the output files filesystems
+    pub compression_ratio: f64,
+}
+
+impl GenerateOptions {
+    pub fn from_args(args: &ArgMatches) -> BoxResult<GenerateOptions> {
+        let masks = match args.value_of("mask") {
+            Some(mask) => vec![MaskLine {
+                line: 1,
+                mask: read_mask_arg(mask)?,
+                charsets: vec![],
+            }],
+            None => read_masks_file(args.value_of("masks-file").unwrap())?,
+        };
+
+        let minlen = get_length_arg(args, "min-length", "--minlen")?;
+        let maxlen = get_length_arg(args, "max-length", "--maxlen")?;
+        // flags take precedence over the environment, e.g. --pipe-to ignores $CRACKEN_OUTPUT
+        let env_outfile = env::var(ENV_OUTPUT).ok().filter(|fname| !fname.is_empty());
+        let mut files: Vec<String> = match args.values_of("output-file") {
+            Some(fnames) => fnames.map(|fname| fname.to_string()).collect(),
+            None => vec![],
+        };
+        if files.is_empty() && !args.is_present("pipe-to") {
+            files.extend(env_outfile);
+        }
+        for (idx, fname) in files.iter().enumerate() {
+            if files[..idx].contains(fname) {
+                let msg = format!("output file {} is given more than once", fname);
+                return Err(RunError::Usage(msg).into());
+            }
+        }
+        if files.is_empty() {
+            for flag in ["append", "force"] {
+                if args.is_present(flag) {
+                    let msg = format!("--{} requires an output file (-o or ${})", flag, ENV_OUTPUT);
+                    return Err(RunError::Usage(msg).into());
+                }
+            }
+        }
+        let io_buffer = get_io_buffer(args.value_of("io-buffer"), env::var(ENV_BUFFER_SIZE).ok())?;
+        let io_backend: IoBackend = args.value_of("io-backend").unwrap().parse()?;
+        let outputs = OutputOptions {
+            files,
+            append: args.is_present("append"),
+            force: args.is_present("force"),
+            atomic: args.is_present("atomic"),
+            keep_partial: !args.is_present("no-keep-partial"),
+            pipe_to: args.value_of("pipe-to").map(|command| command.to_string()),
+            tee_best_effort: args.is_present("tee-best-effort"),
+            io_buffer,
+            io_backend,
+        };
+        let profile = get_config_profile(args)?;
+        let slice = match args.value_of("slice") {
+            Some(slice) => Some(parse_slice(slice)?),
+            None => None,
+        };
+        let distribute = match optional_value_t!(args, "distribute", u64) {
+            Some(0) => {
+                return Err(RunError::Usage("--distribute must be at least 1".to_string()).into())
+            }
+            distribute => distribute,
+        };
+        let json = args.value_of("format") == Some("json");
+        if json && distribute.is_none() && !args.is_present("stats") {
+            bail!("--format json is the format of --distribute and --stats, the words are written in jsonl");
+        }
+
+        let output_encoding = match profile.output_encoding.as_deref() {
+            Some(encoding) if args.occurrences_of("output-encoding") == 0 => encoding,
+            _ => args.value_of("output-encoding").unwrap(),
+        };
+        if args.is_present("base64-no-pad") && output_encoding != "base64" {
+            bail!("--base64-no-pad requires --output-encoding base64");
+        }
+        if args.is_present("lossy") && output_encoding != "utf16le" {
+            bail!("--lossy requires --output-encoding utf16le");
+        }
+        // the generator encodes the words with newlines or invalid utf-8 of hashcat-hex and
+        // base64, the writers encode the other words
+        let word_encoding = matches!(output_encoding, "hashcat-hex" | "base64");
+        let output_hex = args.is_present("output-hex") || profile.output_hex == Some(true);
+        let hex_flag = match output_hex {
+            true => "--output-hex".to_string(),
+            false => format!("--output-encoding {}", output_encoding),
+        };
+        let output_hex = output_hex || word_encoding;
+        let prefix = match args.value_of("prefix") {
+            Some(prefix) => parse_escaped_bytes(prefix)?,
+            None => vec![],
+        };
+        let suffix = match args.value_of("suffix") {
+            Some(suffix) => parse_escaped_bytes(suffix)?,
+            None => vec![],
+        };
+        if output_hex && !(prefix.is_empty() && suffix.is_empty()) {
+            bail!(
+                "--prefix and --suffix don't support {}, the words are encoded without them",
+                hex_flag
+            );
+        }
+        let min_entropy = optional_value_t!(args, "min-entropy", f64);
+        let max_entropy = optional_value_t!(args, "max-entropy", f64);
+        let entropy = match (min_entropy, max_entropy) {
+            (None, None) => None,
+            (min_entropy, max_entropy) => Some((
+                min_entropy.unwrap_or(f64::NEG_INFINITY),
+                max_entropy.unwrap_or(f64::INFINITY),
+            )),
+        };
+        if let Some((min_entropy, max_entropy)) = entropy {
+            if min_entropy > max_entropy {
+                bail!(
+                    "--min-entropy {} is greater than --max-entropy {}",
+                    min_entropy,
+                    max_entropy
+                );
+            }
+        }
+
+        let skip = optional_value_t!(args, "skip", u64).unwrap_or(0);
+        // the masks seek to the first word after the skipped ones. the words filtered by the
+        // repeat limits or --dedup-across-masks or mixed by --interleave are counted as written,
+        // and a --slice or --end-at range skips its own first words, so these are generated and
+        // discarded
+        let seek_skip = skip > 0
+            && ![
+                "seq-max",
+                "occurrence-max",
+                "slice",
+                "end-at",
+                "interleave",
+                "stats",
+                "dedup-across-masks",
+            ]
+            .iter()
+            .any(|arg| args.is_present(arg));
+        let timeout = match args.value_of("timeout") {
+            Some(timeout) => match parse_duration(timeout) {
+                Ok(timeout) => Some(timeout),
+                Err(e) => return Err(RunError::Usage(format!("--timeout: {}", e)).into()),
+            },
+            None => None,
+        };
+        let max_bytes = match args.value_of("max-bytes") {
+            Some(max_bytes) => match parse_size(max_bytes) {
+                Ok(size) => Some(size as u64),
+                Err(e) => return Err(RunError::Usage(format!("--max-bytes: {}", e)).into()),
+            },
+            None => None,
+        };
+        if max_bytes.is_some() {
+            if entropy.is_some() {
+                bail!("--max-bytes doesn't support --min-entropy and --max-entropy, the bytes are counted as generated");
+            }
+            if output_encoding != "utf8" {
+                bail!("--max-bytes doesn't support --output-encoding {}, the bytes are counted as generated", output_encoding);
+            }
+        }
+        let max_rate = match args.value_of("max-rate") {
+            Some(rate) => match parse_rate(rate) {
+                Ok(rate) => Some(rate),
+                Err(e) => return Err(RunError::Usage(format!("--max-rate: {}", e)).into()),
+            },
+            None => None,
+        };
+
+        let mut custom_charsets = get_custom_charsets(args);
+        if custom_charsets.is_empty() {
+            let chars = profile
+                .custom_charsets
+                .iter()
+                .map(|c| CustomCharset::Chars(c));
+            let tokens = profile.tokens.iter().map(|t| CustomCharset::Tokens(t));
+            custom_charsets = chars.chain(tokens).collect();
+        }
+        let custom_charsets = custom_charsets
+            .iter()
+            .map(|charset| match charset {
+                CustomCharset::Chars(chars) => (chars.to_string(), false),
+                CustomCharset::Tokens(tokens) => (tokens.to_string(), true),
+            })
+            .collect();
+
+        let wordlists: Vec<&str> = match args.values_of("wordlist") {
+            Some(values) => values.collect(),
+            None => profile.wordlists.iter().map(|w| w.as_str()).collect(),
+        };
+        let wordlists = with_wordlist_dir(wordlists, env::var_os(ENV_WORDLIST_DIR));
+        let wordlists_options = get_wordlists_options(args, &profile)?;
+
+        let syntax = MaskSyntax::from_name(args.value_of("syntax").unwrap()).unwrap();
+        let repeat_limits = RepeatLimits {
+            seq_max: get_length_arg(args, "seq-max", "--seq-max")?,
+            occurrence_max: get_length_arg(args, "occurrence-max", "--occurrence-max")?,
+        };
+        let chunk = match optional_value_t!(args, "interleave-chunk", u64).unwrap() {
+            0 => bail!("--interleave-chunk must be greater than zero"),
+            chunk => chunk,
+        };
+        let interleave_chunk = if args.is_present("interleave") {
+            Some(chunk)
+        } else {
+            None
+        };
+        let length_order = match (
+            args.value_of("length-order"),
+            args.value_of("length-histogram"),
+        ) {
+            (Some("likely"), Some(fname)) => LengthOrder::Likely(read_length_histogram(fname)?),
+            (Some("likely"), None) => {
+                bail!("--length-order likely requires a --length-histogram")
+            }
+            (_, Some(_)) => bail!("--length-histogram requires --length-order likely"),
+            (Some("desc"), None) => LengthOrder::Descending,
+            (Some("interleave"), None) => LengthOrder::Interleave(chunk),
+            _ => LengthOrder::Ascending,
+        };
+        if length_order.chunk().is_some()
+            && (args.is_present("start-from") || args.is_present("end-at"))
+        {
+            bail!("--length-order interleave doesn't support --start-from and --end-at");
+        }
+        if length_order.chunk().is_some() && (slice.is_some() || distribute.is_some()) {
+            bail!("--length-order interleave doesn't support --slice and --distribute");
+        }
+        let order = match args.value_of("order") {
+            Some(_) if args.occurrences_of("length-order") > 0 => {
+                bail!(
+                    "--order orders the lengths with the words, it doesn't support --length-order"
+                )
+            }
+            Some(fname) => {
+                let beam = optional_value_t!(args, "beam", usize).unwrap_or(DEFAULT_BEAM);
+                Some((Arc::new(read_model(fname)?), beam))
+            }
+            None => None,
+        };
+        let shuffle = match args.is_present("shuffle") {
+            true if args.occurrences_of("length-order") > 0 => {
+                bail!("--shuffle mixes the lengths, it doesn't support --length-order")
+            }
+            true => optional_value_t!(args, "seed", u64),
+            false => None,
+        };
+        let indexed_format = match (args.value_of("format"), args.is_present("with-index")) {
+            (Some("jsonl"), true) => {
+                bail!("--with-index doesn't support --format jsonl, its lines have the index")
+            }
+            (Some("jsonl"), false) => Some(IndexedFormat::Jsonl),
+            (_, true) => Some(IndexedFormat::Tab),
+            _ => None,
+        };
+        if let Some(format) = indexed_format {
+            let jsonl = format == IndexedFormat::Jsonl;
+            let unsupported = [
+                (word_encoding, hex_flag.as_str()),
+                (jsonl && output_hex, "--output-hex"),
+                (entropy.is_some(), "--min-entropy and --max-entropy"),
+                (repeat_limits.is_set(), "--seq-max and --occurrence-max"),
+                (interleave_chunk.is_some(), "--interleave"),
+                (
+                    jsonl && output_encoding == "latin1",
+                    "--output-encoding latin1",
+                ),
+            ];
+            if let Some((_, flags)) = unsupported.iter().find(|(used, _)| *used) {
+                bail!(
+                    "{} doesn't support {}, every word is written with its index in the generation order",
+                    if jsonl { "--format jsonl" } else { "--with-index" },
+                    flags
+                );
+            }
+        }
+        let join = match args.value_of("join") {
+            Some(separator) => match String::from_utf8(parse_escaped_bytes(separator)?) {
+                Ok(separator) => {
+                    let mode = args.value_of("join-mode").unwrap_or("wordlists");
+                    Some((separator, JoinMode::from_name(mode).unwrap()))
+                }
+                Err(_) => bail!("--join must be valid utf-8, the separator is a part of the mask"),
+            },
+            None => None,
+        };
+        let palindrome = args.is_present("palindrome");
+        if palindrome && (minlen.is_some() || maxlen.is_some()) {
+            bail!("--palindrome doesn't support --minlen and --maxlen, the mirrored positions depend on the length of the mask");
+        }
+        if syntax == MaskSyntax::John && masks.iter().any(|mask| !mask.charsets.is_empty()) {
+            bail!("--syntax john doesn't support the custom charsets of hcmask lines")
+        }
+        let dedup_across_masks = args.is_present("dedup-across-masks");
+        if dedup_across_masks {
+            let sampled = wordlists_options
+                .iter()
+                .any(|options| options.sample.is_some() && options.seed.is_none());
+            if sampled {
+                bail!("--dedup-across-masks requires a --seed with --wordlist-sample, the masks look up their words in the same sample");
+            }
+        }
+        // --slice, --start-from and --end-at ranges are located in the unfiltered words
+        if repeat_limits.is_set() && slice.is_some() {
+            bail!("--slice doesn't support --seq-max and --occurrence-max, the words of the masks are counted unfiltered");
+        }
+        if repeat_limits.is_set() && (args.is_present("start-from") || args.is_present("end-at")) {
+            bail!("--start-from and --end-at don't support --seq-max and --occurrence-max, the skipped words are not counted");
+        }
+
+        let ranged = ["slice", "start-from", "end-at"]
+            .iter()
+            .any(|arg| args.is_present(arg));
+        let stats = match args.is_present("stats") {
+            true => Some(get_rates(args)?),
+            false => None,
+        };
+        let masks_file = args.is_present("masks-file");
+        let keyspace_hashcat = args.is_present("keyspace-hashcat");
+        // the stats of a range count its words without the keyspace of hashcat
+        if keyspace_hashcat && !ranged {
+            if masks_file {
+                bail!("--keyspace-hashcat reports a single mask, it doesn't support --masks-file");
+            }
+            if json {
+                bail!("--keyspace-hashcat doesn't support --format json");
+            }
+            if minlen.is_some() || maxlen.is_some() {
+                bail!("--keyspace-hashcat doesn't support --minlen and --maxlen, hashcat --increment reports every length on its own");
+            }
+        }
+        let sort_keyspace = args.is_present("sort-keyspace");
+        if sort_keyspace && (!masks_file || ranged) {
+            bail!("--sort-keyspace sorts the masks of a masks file (-i)");
+        }
+        let verify = match args.is_present("verify") {
+            true => Some(optional_value_t!(args, "verify-max", u64).unwrap_or(DEFAULT_VERIFY_MAX)),
+            false => None,
+        };
+
+        let max_keyspace = get_max_keyspace(
+            args.value_of("max-keyspace"),
+            env::var(ENV_MAX_KEYSPACE).ok(),
+            &profile,
+        )?;
+        let compression_ratio = match optional_value_t!(args, "compression-ratio", f64) {
+            Some(ratio) if !(ratio.is_finite() && ratio > 0.0) => {
+                let msg = format!("--compression-ratio must be positive, got {}", ratio);
+                return Err(RunError::Usage(msg).into());
+            }
+            ratio => ratio.unwrap_or(1.0),
+        };
+        let status = match args.value_of("status") {
+            Some(interval) => match parse_duration(interval) {
+                Ok(interval) => Some(interval),
+                Err(e) => return Err(RunError::Usage(format!("--status: {}", e)).into()),
+            },
+            None if !args.is_present("status") => None,
+            None if stderr().is_terminal() => Some(Duration::from_secs(1)),
+            None => Some(Duration::from_secs(10)),
+        };
+
+        Ok(GenerateOptions {
+            masks,
+            stdin_mask: args.value_of("mask") == Some("@-"),
+            masks_file,
+            lengths: (minlen, maxlen),
+            outputs,
+            slice,
+            distribute,
+            json,
+            stats,
+            keyspace_hashcat,
+            sort_keyspace,
+            verify,
+            checksum: args
+                .value_of("checksum")
+                .map(|algorithm| algorithm.to_string()),
+            verbose: args.is_present("verbose"),
+            quiet: args.is_present("quiet"),
+            output_encoding: output_encoding.to_string(),
+            lossy: args.is_present("lossy"),
+            base64_pad: !args.is_present("base64-no-pad"),
+            output_hex,
+            prefix,
+            suffix,
+            entropy,
+            exclude: args
+                .value_of("exclude")
+                .map(|fname| (fname.to_string(), args.is_present("exclude-bloom"))),
+            skip,
+            seek_skip,
+            timeout,
+            max_bytes,
+            max_rate,
+            status,
+            custom_charsets,
+            wordlists,
+            wordlists_options,
+            syntax,
+            repeat_limits,
+            interleave_chunk,
+            length_order,
+            order,
+            shuffle,
+            indexed_format,
+            join,
+            palindrome,
+            dedup_across_masks,
+            start_from: args.value_of("start-from").map(|word| word.to_string()),
+            end_at: args.value_of("end-at").map(|word| word.to_string()),
+            max_keyspace,
+            yes_i_know: args.is_present("yes-i-know"),
+            compression_ratio,
+        })
+    }
+
+    /// the size of `bytes` of words once encoded. utf-16 takes up to twice the bytes of utf-8,
+    /// exactly twice for ascii words. base64 takes 4 bytes for every 3, about 4/3 with the
+    /// padding
+    pub fn encoded_size(&self, bytes: BigUint) -> BigUint {
+        match self.output_encoding.as_str() {
+            "utf16le" => bytes * 2u32,
+            "base64" => (bytes * 4u32 + 2u32) / 3u32,
+            _ => bytes,
+        }
+    }
+
+    /// the skipped words which are generated and discarded, the masks seek past the others
+    pub fn discarded(&self) -> u64 {
+        if self.seek_skip {
+            0
+        } else {
+            self.skip
+        }
+    }
+}
@@ -1,17 +1,250 @@
-use std::io::Write;
+use std::cell::Cell;
+use std::cmp::min;
+use std::io::{IoSlice, Write};
 use std::rc::Rc;
 
 use num_bigint::{BigUint, ToBigUint};
 
-use crate::charsets::Charset;
+use crate::charsets::{Charset, CustomCharset};
+use crate::dates::gen_dates;
+use crate::error::CrackenError;
+use crate::helpers::{
+    encode_hex_word, hex_word_len, needs_hex, write_all_vectored, HexWords, HEX_WORD_SIZE,
+};
+use crate::keyboard::KeyboardGraph;
 use crate::mask::{parse_mask, validate_charsets, validate_wordlists, MaskOp};
 use crate::stackbuf::StackBuf;
-use crate::wordlists::{Wordlist, WordlistIterator};
-use crate::{BoxResult, MAX_WORD_SIZE};
+use crate::wordlists::{parse_wordlist_arg, Wordlist, WordlistIterator, WordlistOptions};
+use crate::{BUFFER_SIZE, MAX_WORD_SIZE};
+
+/// maximum number of walks of a single ?K op, they are generated upfront in memory
+const MAX_KEYWALKS: u64 = 10_000_000;
 
 pub trait WordGenerator {
-    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), std::io::Error>;
+    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), CrackenError>;
     fn combinations(&self) -> BigUint;
+    /// number of words generated of every length in the order the lengths are generated, empty
+    /// if the words lengths are not fixed
+    fn length_combinations(&self) -> Vec<(usize, BigUint)>;
+    /// number of bytes generated including the newlines (without hex encoding)
+    fn output_bytes(&self) -> BigUint;
+    /// writes words which can't be written as plain lines as `$HEX[...]`
+    fn set_hex_output(&mut self, hex_output: bool);
+    /// number of bytes generated before every write to the output, at least one word
+    fn set_buffer_size(&mut self, buffer_size: usize);
+    /// skips the words with more repeated chars than `repeat_limits`, `combinations` still
+    /// counts them
+    fn set_repeat_limits(&mut self, repeat_limits: RepeatLimits);
+    /// returns the position of the first occurrence of `word` in generation order, or None
+    /// if this generator never generates it
+    fn index_of(&self, word: &[u8]) -> Option<WordRank>;
+    /// generates the lengths between the min and max length in this order, the masks with
+    /// wordlists have no min and max length and ignore it
+    fn set_length_order(&mut self, length_order: LengthOrder);
+    /// returns the word at `index` in generation order (without the repeat limits, hex
+    /// encoding and newline), None past the last word or if this generator can't find a word
+    /// by its index
+    fn word_at(&self, _index: u64) -> Option<Vec<u8>> {
+        None
+    }
+    /// the chars of every position of the mask if the words of every length are all the
+    /// combinations of the chars of its first positions, None for the other generators (e.g.
+    /// with wordlists)
+    fn position_chars(&self) -> Option<Vec<Vec<u8>>> {
+        None
+    }
+    /// writes the words `start..end` in generation order to `out`, returns the number of words
+    /// written, fewer than `end - start` past the last word. the words exceeding the repeat
+    /// limits are not counted. generators which can't find a word by its index generate the
+    /// words before `start` and discard them
+    fn gen_range(&self, start: u64, end: u64, out: &mut dyn Write) -> Result<u64, std::io::Error> {
+        gen_range_discarding(self, start, end, out)
+    }
+}
+
+/// `WordGenerator::gen_range` by generating all the words from the first one, the words
+/// before `start` are discarded and the generation is stopped by refusing its writes at `end`
+pub fn gen_range_discarding<G: WordGenerator + ?Sized>(
+    word_gen: &G,
+    start: u64,
+    end: u64,
+    out: &mut dyn Write,
+) -> Result<u64, std::io::Error> {
+    if start >= end {
+        return Ok(0);
+    }
+    let word = Cell::new(0);
+    let res = {
+        let mut range: Box<dyn Write + '_> = Box::new(RangeWriter {
+            out,
+            start,
+            end,
+            word: &word,
+            buf: vec![],
+        });
+        word_gen.gen(&mut range)
+    };
+    match res {
+        Err(_) if word.get() >= end => {}
+        Err(CrackenError::Io(e)) => return Err(e),
+        Err(e) => return Err(std::io::Error::other(e.to_string())),
+        Ok(()) => {}
+    }
+    Ok(min(word.get(), end).saturating_sub(start))
+}
+
+/// writes the words `start..end` to `out` and refuses the next writes once `end` is reached
+struct RangeWriter<'w> {
+    out: &'w mut dyn Write,
+    start: u64,
+    end: u64,
+    /// index of the current word in the generation order, words may be split between writes.
+    /// only the words written are counted, so a failed write isn't taken for the end
+    word: &'w Cell<u64>,
+    buf: Vec<u8>,
+}
+
+impl Write for RangeWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, std::io::Error> {
+        if self.word.get() >= self.end {
+            return Err(std::io::Error::other("range end reached"));
+        }
+        self.buf.clear();
+        let mut word = self.word.get();
+        for line in buf.split_inclusive(|&c| c == b'\n') {
+            if word >= self.end {
+                break;
+            }
+            if word >= self.start {
+                self.buf.extend_from_slice(line);
+            }
+            if line.last() == Some(&b'\n') {
+                word += 1;
+            }
+        }
+        self.out.write_all(&self.buf)?;
+        self.word.set(word);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.out.flush()
+    }
+}
+
+/// order of the lengths of the words of a mask with a min and max length
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum LengthOrder {
+    /// the shortest words first
+    #[default]
+    Ascending,
+    /// the longest words first
+    Descending,
+    /// this many words of every length in turn, from the shortest
+    Interleave(u64),
+    /// the lengths from the most likely, the lengths missing from it follow in ascending order
+    Likely(Vec<usize>),
+}
+
+impl LengthOrder {
+    /// words of a length generated before moving to the next length, None for all of them
+    pub fn chunk(&self) -> Option<u64> {
+        match self {
+            LengthOrder::Interleave(chunk) => Some(*chunk),
+            _ => None,
+        }
+    }
+}
+
+/// limits of the repeated chars of the generated words, same as the maskprocessor -q and -r
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RepeatLimits {
+    /// maximum number of identical consecutive chars (e.g. 2 skips aaa but not aab)
+    pub seq_max: Option<usize>,
+    /// maximum number of occurrences of every char in the word (e.g. 1 skips aba)
+    pub occurrence_max: Option<usize>,
+}
+
+impl RepeatLimits {
+    pub fn is_set(&self) -> bool {
+        self.seq_max.is_some() || self.occurrence_max.is_some()
+    }
+
+    /// returns true iff `word` is within the limits
+    pub fn allows(&self, word: &[u8]) -> bool {
+        if let Some(seq_max) = self.seq_max {
+            let mut run = 0;
+            for (i, &chr) in word.iter().enumerate() {
+                run = if i > 0 && word[i - 1] == chr {
+                    run + 1
+                } else {
+                    1
+                };
+                if run > seq_max {
+                    return false;
+                }
+            }
+        }
+        if let Some(occurrence_max) = self.occurrence_max {
+            let mut occurrences = [0; 256];
+            for &chr in word {
+                occurrences[chr as usize] += 1;
+                if occurrences[chr as usize] > occurrence_max {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// position of a word in the generation order of a `WordGenerator`
+#[derive(Debug, PartialEq)]
+pub struct WordRank {
+    pub index: BigUint,
+    /// the words matched by each wordlist of the mask (in mask order)
+    pub wordlist_words: Vec<Vec<u8>>,
+}
+
+/// writes `word` (ending with a newline) to `buf`, hex encoded if needed
+#[inline]
+pub(crate) fn write_word(buf: &mut StackBuf, word: &[u8], hex_output: bool) {
+    if hex_output && needs_hex(&word[..word.len() - 1], HexWords::Unprintable) {
+        let mut hex_word = [0; HEX_WORD_SIZE];
+        let len = encode_hex_word(&word[..word.len() - 1], &mut hex_word);
+        buf.write(&hex_word[..len]);
+    } else {
+        buf.write(word);
+    }
+}
+
+/// returns the maximum number of bytes written by `write_word` for a word of `len` bytes
+#[inline]
+pub(crate) fn max_written_len(len: usize, hex_output: bool) -> usize {
+    if hex_output {
+        hex_word_len(len)
+    } else {
+        len
+    }
+}
+
+/// writes the `pending` buffers followed by `data` with a single vectored write
+fn write_pending(
+    out: &mut dyn Write,
+    pending: &mut Vec<StackBuf>,
+    data: &[u8],
+) -> Result<(), std::io::Error> {
+    if pending.is_empty() {
+        return out.write_all(data);
+    }
+    let mut slices: Vec<_> = pending
+        .iter()
+        .map(|buf| IoSlice::new(buf.getdata()))
+        .collect();
+    slices.push(IoSlice::new(data));
+    write_all_vectored(out, &mut slices)?;
+    pending.clear();
+    Ok(())
 }
 
 /// Generator optimized for charsets only
@@ -21,18 +254,38 @@ pub struct CharsetGenerator {
     pub maxlen: usize,
     charsets: Vec<Charset>,
     min_word: Vec<u8>,
+    hex_output: bool,
+    buffer_size: usize,
+    repeat_limits: RepeatLimits,
+    length_order: LengthOrder,
 }
 
 /// Wordlist Generator for both charsets and wordlists
 pub struct WordlistGenerator {
     pub mask: Vec<MaskOp>,
     items: Vec<WordlistItem>,
+    hex_output: bool,
+    buffer_size: usize,
+    repeat_limits: RepeatLimits,
 }
 
 #[allow(clippy::large_enum_variant)]
 enum WordlistItem {
     Charset(Charset),
     Wordlist(Rc<Wordlist>),
+    /// the value of an earlier item
+    Backref(usize),
+}
+
+impl WordlistItem {
+    /// number of distinct values of this mask position, a backreference has a single one
+    fn len(&self) -> usize {
+        match self {
+            WordlistItem::Wordlist(wl) => wl.len(),
+            WordlistItem::Charset(c) => c.len,
+            WordlistItem::Backref(_) => 1,
+        }
+    }
 }
 
 enum Position<'a> {
@@ -46,19 +299,45 @@ enum Position<'a> {
     },
 }
 
-/// returns the correct word generator based on the args provided
+/// returns the correct word generator based on the args provided. the generator owns all its
+/// data, the args can be dropped once it is created
 pub fn get_word_generator<'a>(
     mask: &'a str,
     minlen: Option<usize>,
     maxlen: Option<usize>,
-    custom_charsets: &[&'a str],
+    custom_charsets: &[CustomCharset<'a>],
     wordlists_fnames: &[&'a str],
-) -> BoxResult<Box<dyn WordGenerator + 'a>> {
+) -> Result<Box<dyn WordGenerator>, CrackenError> {
+    get_word_generator_with_options(mask, minlen, maxlen, custom_charsets, wordlists_fnames, &[])
+}
+
+/// same as `get_word_generator` with `wordlists_options[i]` applied when loading the i-th
+/// wordlist, wordlists without options are loaded with the default options
+pub fn get_word_generator_with_options<'a>(
+    mask: &'a str,
+    minlen: Option<usize>,
+    maxlen: Option<usize>,
+    custom_charsets: &[CustomCharset<'a>],
+    wordlists_fnames: &[&'a str],
+    wordlists_options: &[WordlistOptions],
+) -> Result<Box<dyn WordGenerator>, CrackenError> {
     let mask_ops = parse_mask(mask)?;
     validate_charsets(&mask_ops, custom_charsets.len())?;
     validate_wordlists(&mask_ops, wordlists_fnames.len())?;
 
-    if mask_ops.iter().all(|op| !matches!(op, MaskOp::Wordlist(_))) {
+    // custom tokens, keyboard walks and dates have variable length so they are handled as wordlists
+    let has_wordlists = mask_ops.iter().any(|op| match op {
+        MaskOp::Wordlist(_)
+        | MaskOp::TransformedWordlist { .. }
+        | MaskOp::Keywalk { .. }
+        | MaskOp::Date { .. } => true,
+        MaskOp::CustomCharset(idx) => custom_charsets[*idx].is_tokens(),
+        _ => false,
+    });
+    // the words with backreferences are assembled from the values of their positions
+    let has_backrefs = mask_ops.iter().any(|op| matches!(op, MaskOp::Backref(_)));
+
+    if !has_wordlists && !has_backrefs {
         Ok(Box::new(CharsetGenerator::new(
             mask_ops,
             minlen,
@@ -66,30 +345,98 @@ pub fn get_word_generator<'a>(
             custom_charsets,
         )?))
     } else if minlen.is_some() || maxlen.is_some() {
-        bail!("cannot set minlen or maxlen with wordlists")
+        Err(CrackenError::Unsupported(format!(
+            "cannot set minlen or maxlen with {}",
+            if has_wordlists {
+                "wordlists"
+            } else {
+                "backreferences"
+            }
+        )))
     } else {
-        Ok(Box::new(WordlistGenerator::new(
+        Ok(Box::new(WordlistGenerator::with_options(
             mask_ops,
             wordlists_fnames,
             custom_charsets,
+            wordlists_options,
         )?))
     }
 }
 
+/// returns the keyspace `hashcat --keyspace -a 3` reports for the mask with a fast hash mode
+/// (e.g. -m 0). hashcat amplifies the last 1 to 4 positions of the mask inside the kernel and
+/// only counts the base words of the other positions, so its -s/-l are in base words
+pub fn hashcat_keyspace(
+    mask: &str,
+    custom_charsets: &[CustomCharset],
+) -> Result<BigUint, CrackenError> {
+    let mask_ops = parse_mask(mask)?;
+    validate_charsets(&mask_ops, custom_charsets.len())?;
+    let mut lens = vec![];
+    for op in mask_ops.iter() {
+        lens.push(match op {
+            MaskOp::Char(_) => 1,
+            // hashcat's ?a includes the space, unlike cracken's
+            MaskOp::BuiltinCharset('a') => 95,
+            MaskOp::BuiltinCharset(ch) => Charset::from_symbol(*ch).len,
+            MaskOp::CustomCharset(idx) => match custom_charsets[*idx] {
+                CustomCharset::Chars(chars) => Charset::from_chars(chars.as_bytes()).len,
+                CustomCharset::Tokens(_) => {
+                    return Err(CrackenError::Unsupported(
+                        "hashcat keyspace of a mask with custom tokens".to_string(),
+                    ))
+                }
+            },
+            MaskOp::Backref(_) => {
+                return Err(CrackenError::Unsupported(
+                    "hashcat keyspace of a mask with backreferences".to_string(),
+                ))
+            }
+            _ => {
+                return Err(CrackenError::Unsupported(
+                    "hashcat keyspace of a mask with wordlists, keyboard walks or dates"
+                        .to_string(),
+                ))
+            }
+        });
+    }
+
+    // same split as hashcat's mask_ctx_update_loop
+    let amplified = match lens.len() {
+        0..=5 => 1,
+        6 => 2,
+        _ if lens[0] * lens[1] > 256 => 3,
+        _ => 4,
+    };
+    Ok(lens
+        .iter()
+        .take(lens.len().saturating_sub(amplified))
+        .fold(1.to_biguint().unwrap(), |acc, &len| acc * len))
+}
+
 impl<'a> CharsetGenerator {
     pub fn new(
         mask: Vec<MaskOp>,
         minlen: Option<usize>,
         maxlen: Option<usize>,
-        custom_charsets: &[&'a str],
-    ) -> BoxResult<CharsetGenerator> {
+        custom_charsets: &[CustomCharset<'a>],
+    ) -> Result<CharsetGenerator, CrackenError> {
+        validate_charsets(&mask, custom_charsets.len())?;
+
         let charsets: Vec<_> = mask
             .iter()
             .map(|op| match op {
                 MaskOp::Char(ch) => Charset::from_chars(vec![*ch as u8].as_ref()),
                 MaskOp::BuiltinCharset(ch) => Charset::from_symbol(*ch),
-                MaskOp::CustomCharset(idx) => Charset::from_chars(custom_charsets[*idx].as_bytes()),
-                MaskOp::Wordlist(_) => unreachable!("cant handle wordlists"),
+                MaskOp::CustomCharset(idx) => match custom_charsets[*idx] {
+                    CustomCharset::Chars(chars) => Charset::from_chars(chars.as_bytes()),
+                    CustomCharset::Tokens(_) => unreachable!("cant handle custom tokens"),
+                },
+                MaskOp::Wordlist(_)
+                | MaskOp::TransformedWordlist { .. }
+                | MaskOp::Keywalk { .. }
+                | MaskOp::Date { .. } => unreachable!("cant handle wordlists"),
+                MaskOp::Backref(_) => unreachable!("cant handle backreferences"),
             })
             .collect();
 
@@ -97,12 +444,29 @@ impl<'a> CharsetGenerator {
         let minlen = minlen.unwrap_or_else(|| charsets.len());
         let maxlen = maxlen.unwrap_or_else(|| charsets.len());
 
-        // validate minlen
-        if !(0 < minlen && minlen <= maxlen && minlen <= charsets.len()) {
-            bail!("minlen is invalid");
+        let invalid_length = |msg: String| Err(CrackenError::InvalidLength(msg));
+        if minlen == 0 || maxlen == 0 {
+            return invalid_length("minlen and maxlen must be greater than zero".to_string());
+        }
+        if minlen > charsets.len() {
+            return invalid_length(format!(
+                "minlen {} is longer than the mask length {}",
+                minlen,
+                charsets.len()
+            ));
         }
         if maxlen > charsets.len() {
-            bail!("maxlen is invalid");
+            return invalid_length(format!(
+                "maxlen {} is longer than the mask length {}",
+                maxlen,
+                charsets.len()
+            ));
+        }
+        if minlen > maxlen {
+            return invalid_length(format!(
+                "minlen {} is greater than maxlen {}",
+                minlen, maxlen
+            ));
         }
 
         // prepare min word - the longest first word
@@ -114,24 +478,88 @@ impl<'a> CharsetGenerator {
             maxlen,
             charsets,
             min_word,
+            hex_output: false,
+            buffer_size: BUFFER_SIZE,
+            repeat_limits: RepeatLimits::default(),
+            length_order: LengthOrder::default(),
         })
     }
 
+    /// the charset of every position of the mask
+    pub(crate) fn charsets(&self) -> &[Charset] {
+        &self.charsets
+    }
+
+    /// the lengths in generation order
+    fn ordered_lengths(&self) -> Vec<usize> {
+        let mut lengths: Vec<usize> = match &self.length_order {
+            LengthOrder::Descending => (self.minlen..=self.maxlen).rev().collect(),
+            LengthOrder::Likely(likely) => likely
+                .iter()
+                .copied()
+                .filter(|len| (self.minlen..=self.maxlen).contains(len))
+                .collect(),
+            _ => vec![],
+        };
+        // the lengths missing from the likely ones follow in ascending order
+        for len in self.minlen..=self.maxlen {
+            if !lengths.contains(&len) {
+                lengths.push(len);
+            }
+        }
+        lengths
+    }
+
+    fn length_state(&self, pwdlen: usize) -> LengthState {
+        let mut word = [b'\n'; MAX_WORD_SIZE];
+        word[..pwdlen].copy_from_slice(&self.min_word[..pwdlen]);
+        let mut next_chars = vec![None; pwdlen];
+        next_chars[0] = Some(self.charsets[0].min_char);
+        LengthState {
+            pwdlen,
+            word,
+            done: false,
+            occurrences: [0; 256],
+            runs: [0; MAX_WORD_SIZE],
+            next_chars,
+            pos: 0,
+        }
+    }
+
+    /// moves the next word of `state` to the word at `offset` among the words of its length,
+    /// the rightmost position changes first
+    fn seek(&self, state: &mut LengthState, offset: u64) {
+        let mut offset = offset;
+        for (pos, charset) in self.charsets[..state.pwdlen].iter().enumerate().rev() {
+            state.word[pos] = charset.nth_char((offset % charset.len as u64) as usize);
+            offset /= charset.len as u64;
+        }
+    }
+
+    /// generates the next `max_words` words of a length, all of them if None. the last partial
+    /// batch is added to `pending` and written with the next full batch, short lengths which
+    /// don't fill a batch don't cost a write each
     #[allow(clippy::borrowed_box)]
     fn gen_by_length<'b>(
         &self,
-        pwdlen: usize,
+        state: &mut LengthState,
+        max_words: Option<u64>,
         out: &mut Box<dyn Write + 'b>,
+        pending: &mut Vec<StackBuf>,
     ) -> Result<(), std::io::Error> {
-        let mut buf = StackBuf::new();
-        let batch_size = buf.len() / (pwdlen + 1);
+        let pwdlen = state.pwdlen;
+        let word_len = max_written_len(pwdlen + 1, self.hex_output);
+        let mut buf = StackBuf::with_size(self.buffer_size.max(word_len));
+        // every word of a length takes the same space, the batch is checked once
+        let batch_size = (buf.remaining() / word_len) as u64;
+        let mut left = max_words;
 
-        let word = &mut [b'\n'; MAX_WORD_SIZE][..=pwdlen];
-        word[..pwdlen].copy_from_slice(&self.min_word[..pwdlen]);
+        let word = &mut state.word[..=pwdlen];
 
         'outer_loop: loop {
-            'batch_for: for _ in 0..batch_size {
-                buf.write(word);
+            let batch = left.map_or(batch_size, |left| min(left, batch_size));
+            'batch_for: for _ in 0..batch {
+                write_word(&mut buf, word, self.hex_output);
                 for pos in (0..pwdlen).rev() {
                     let chr = word[pos];
                     let next_chr = self.charsets[pos][chr as usize];
@@ -141,39 +569,288 @@ impl<'a> CharsetGenerator {
                         continue 'batch_for;
                     }
                 }
+                state.done = true;
                 break 'outer_loop;
             }
+            if let Some(left) = left.as_mut() {
+                *left -= batch;
+                if *left == 0 {
+                    break;
+                }
+            }
 
-            out.write_all(buf.getdata())?;
+            write_pending(out.as_mut(), pending, buf.getdata())?;
             buf.clear();
         }
-        out.write_all(buf.getdata())?;
+        pending.push(buf);
+        Ok(())
+    }
+
+    /// same as `gen_by_length` without the words exceeding the repeat limits. the chars are
+    /// checked as they are added, so the words starting with a prefix exceeding the limits
+    /// are never enumerated
+    #[allow(clippy::borrowed_box)]
+    fn gen_by_length_limited<'b>(
+        &self,
+        state: &mut LengthState,
+        max_words: Option<u64>,
+        out: &mut Box<dyn Write + 'b>,
+        pending: &mut Vec<StackBuf>,
+    ) -> Result<(), std::io::Error> {
+        let pwdlen = state.pwdlen;
+        let word_len = max_written_len(pwdlen + 1, self.hex_output);
+        let mut buf = StackBuf::with_size(self.buffer_size.max(word_len));
+        let seq_max = self.repeat_limits.seq_max.unwrap_or(usize::MAX);
+        let occurrence_max = self.repeat_limits.occurrence_max.unwrap_or(usize::MAX);
+        let mut left = max_words.unwrap_or(u64::MAX);
+
+        let LengthState {
+            word,
+            occurrences,
+            runs,
+            next_chars,
+            pos,
+            ..
+        } = state;
+        let word = &mut word[..=pwdlen];
+
+        while left > 0 {
+            let chr = match next_chars[*pos] {
+                Some(chr) => chr,
+                // all the chars of this position were tried, back to the previous one
+                None if *pos == 0 => {
+                    state.done = true;
+                    break;
+                }
+                None => {
+                    *pos -= 1;
+                    occurrences[word[*pos] as usize] -= 1;
+                    continue;
+                }
+            };
+            let next_chr = self.charsets[*pos][chr as usize];
+            next_chars[*pos] = if chr < next_chr { Some(next_chr) } else { None };
+
+            let run = if *pos > 0 && word[*pos - 1] == chr {
+                runs[*pos - 1] + 1
+            } else {
+                1
+            };
+            if run > seq_max || occurrences[chr as usize] >= occurrence_max {
+                continue;
+            }
+            word[*pos] = chr;
+
+            if *pos + 1 == pwdlen {
+                if buf.remaining() < word_len {
+                    write_pending(out.as_mut(), pending, buf.getdata())?;
+                    buf.clear();
+                }
+                write_word(&mut buf, word, self.hex_output);
+                left -= 1;
+                continue;
+            }
+            occurrences[chr as usize] += 1;
+            runs[*pos] = run;
+            *pos += 1;
+            next_chars[*pos] = Some(self.charsets[*pos].min_char);
+        }
+        pending.push(buf);
         Ok(())
     }
 }
 
-impl<'a> WordGenerator for CharsetGenerator {
+/// the position of the generation of the words of a length, so the generation of a length can
+/// be resumed after generating words of the other lengths
+struct LengthState {
+    pwdlen: usize,
+    /// the next word, followed by a newline
+    word: [u8; MAX_WORD_SIZE],
+    done: bool,
+    // the depth first state of `gen_by_length_limited`
+    /// occurrences of every char in the prefix before the current position
+    occurrences: [usize; 256],
+    /// length of the run of identical chars ending at every position of the prefix
+    runs: [usize; MAX_WORD_SIZE],
+    /// the next char to try at every position, None once all its chars were tried
+    next_chars: Vec<Option<u8>>,
+    pos: usize,
+}
+
+impl WordGenerator for CharsetGenerator {
     /// generates all words into the output buffer `out`
-    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), std::io::Error> {
-        for pwdlen in self.minlen..=self.maxlen {
-            self.gen_by_length(pwdlen, out)?;
+    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), CrackenError> {
+        let mut pending: Vec<StackBuf> = vec![];
+        let chunk = self.length_order.chunk();
+        let mut states: Vec<_> = self
+            .ordered_lengths()
+            .into_iter()
+            .map(|len| self.length_state(len))
+            .collect();
+        // every length in turn, a chunk of words each time
+        while !states.is_empty() {
+            for state in states.iter_mut() {
+                if self.repeat_limits.is_set() {
+                    self.gen_by_length_limited(state, chunk, out, &mut pending)?;
+                } else {
+                    self.gen_by_length(state, chunk, out, &mut pending)?;
+                }
+                // chunks shorter than a batch never fill one, the pending ones are written
+                let pending_bytes: usize = pending.iter().map(|buf| buf.getdata().len()).sum();
+                if chunk.is_some() && pending_bytes >= self.buffer_size {
+                    write_pending(out.as_mut(), &mut pending, &[])?;
+                }
+            }
+            states.retain(|state| !state.done);
         }
+        write_pending(out.as_mut(), &mut pending, &[])?;
         Ok(())
     }
 
-    /// calculates number of words to be generated by this WordGenerator
-    fn combinations(&self) -> BigUint {
-        let mut combs: BigUint = 0.to_biguint().unwrap();
-        for i in self.minlen..=self.maxlen {
-            combs += self
-                .charsets
+    fn set_hex_output(&mut self, hex_output: bool) {
+        self.hex_output = hex_output;
+    }
+
+    fn set_buffer_size(&mut self, buffer_size: usize) {
+        self.buffer_size = buffer_size;
+    }
+
+    fn set_repeat_limits(&mut self, repeat_limits: RepeatLimits) {
+        self.repeat_limits = repeat_limits;
+    }
+
+    fn set_length_order(&mut self, length_order: LengthOrder) {
+        self.length_order = length_order;
+    }
+
+    fn position_chars(&self) -> Option<Vec<Vec<u8>>> {
+        Some(
+            self.charsets
                 .iter()
-                .take(i)
-                .fold(1.to_biguint().unwrap(), |acc, x| {
-                    (acc * x.len).to_biguint().unwrap()
-                });
+                .map(|charset| charset.chars())
+                .collect(),
+        )
+    }
+
+    /// the words of the lengths generated before are skipped, the rightmost position of the
+    /// word changes first. the interleaved lengths are not supported
+    fn word_at(&self, index: u64) -> Option<Vec<u8>> {
+        if self.length_order.chunk().is_some() {
+            return None;
+        }
+        let mut offset = index;
+        for (len, combs) in self.length_combinations() {
+            match u64::try_from(&combs) {
+                Ok(combs) if offset >= combs => {
+                    offset -= combs;
+                    continue;
+                }
+                _ => {}
+            }
+            let mut word = vec![0; len];
+            for (pos, charset) in self.charsets[..len].iter().enumerate().rev() {
+                word[pos] = charset.nth_char((offset % charset.len as u64) as usize);
+                offset /= charset.len as u64;
+            }
+            return Some(word);
+        }
+        None
+    }
+
+    /// seeks to the first word of the range, the lengths generated before are skipped. the
+    /// repeat limits and the interleaved lengths generate the words before the range
+    fn gen_range(&self, start: u64, end: u64, out: &mut dyn Write) -> Result<u64, std::io::Error> {
+        if self.repeat_limits.is_set() || self.length_order.chunk().is_some() {
+            return gen_range_discarding(self, start, end, out);
+        }
+        let mut out: Box<dyn Write + '_> = Box::new(out);
+        let mut pending: Vec<StackBuf> = vec![];
+        let mut offset = start;
+        let mut written = 0;
+        for (len, combs) in self.length_combinations() {
+            let left = end.saturating_sub(start) - written;
+            if left == 0 {
+                break;
+            }
+            let combs = u64::try_from(&combs).unwrap_or(u64::MAX);
+            if offset >= combs {
+                offset -= combs;
+                continue;
+            }
+            let count = min(combs - offset, left);
+            let mut state = self.length_state(len);
+            self.seek(&mut state, offset);
+            self.gen_by_length(&mut state, Some(count), &mut out, &mut pending)?;
+            written += count;
+            offset = 0;
+        }
+        write_pending(out.as_mut(), &mut pending, &[])?;
+        Ok(written)
+    }
+
+    fn index_of(&self, word: &[u8]) -> Option<WordRank> {
+        if !(self.minlen..=self.maxlen).contains(&word.len()) {
+            return None;
         }
-        combs
+
+        // the rightmost position changes first
+        let mut offset: BigUint = 0.to_biguint().unwrap();
+        for (charset, &chr) in self.charsets.iter().zip(word) {
+            offset = offset * charset.len + charset.index_of(chr)?;
+        }
+
+        // the words of the lengths generated before, with interleaved lengths the lengths
+        // before this one in a turn generated one more chunk
+        let chunk = self.length_order.chunk();
+        let mut index = offset.clone();
+        let mut before = true;
+        for (len, combs) in self.length_combinations() {
+            if len == word.len() {
+                before = false;
+                continue;
+            }
+            index += match chunk {
+                None if before => combs,
+                None => continue,
+                Some(chunk) => {
+                    let turns = &offset / chunk + before as u64;
+                    min(combs, turns * chunk)
+                }
+            };
+        }
+        Some(WordRank {
+            index,
+            wordlist_words: vec![],
+        })
+    }
+
+    /// calculates number of words to be generated by this WordGenerator
+    fn combinations(&self) -> BigUint {
+        self.length_combinations()
+            .into_iter()
+            .map(|(_, combs)| combs)
+            .sum()
+    }
+
+    fn output_bytes(&self) -> BigUint {
+        self.length_combinations()
+            .into_iter()
+            .map(|(len, combs)| combs * (len + 1))
+            .sum()
+    }
+
+    fn length_combinations(&self) -> Vec<(usize, BigUint)> {
+        self.ordered_lengths()
+            .into_iter()
+            .map(|len| {
+                let combs = self
+                    .charsets
+                    .iter()
+                    .take(len)
+                    .fold(1.to_biguint().unwrap(), |acc, x| acc * x.len);
+                (len, combs)
+            })
+            .collect()
     }
 }
 
@@ -181,72 +858,299 @@ impl<'a> WordlistGenerator {
     pub fn new(
         mask: Vec<MaskOp>,
         wordlists_fnames: &[&'a str],
-        custom_charsets: &[&'a str],
-    ) -> BoxResult<WordlistGenerator> {
-        let mut wordlists_data = vec![];
-        for fname in wordlists_fnames.iter() {
-            wordlists_data.push(Rc::new(Wordlist::from_file(fname)?));
+        custom_charsets: &[CustomCharset<'a>],
+    ) -> Result<WordlistGenerator, CrackenError> {
+        Self::with_options(mask, wordlists_fnames, custom_charsets, &[])
+    }
+
+    pub fn with_options(
+        mask: Vec<MaskOp>,
+        wordlists_fnames: &[&'a str],
+        custom_charsets: &[CustomCharset<'a>],
+        wordlists_options: &[WordlistOptions],
+    ) -> Result<WordlistGenerator, CrackenError> {
+        validate_charsets(&mask, custom_charsets.len())?;
+        validate_wordlists(&mask, wordlists_fnames.len())?;
+
+        let default_options = WordlistOptions::default();
+        let mut wordlists = vec![];
+        for (idx, arg) in wordlists_fnames.iter().enumerate() {
+            let wordlist_options = wordlists_options.get(idx).unwrap_or(&default_options);
+            let (fname, mut options) = parse_wordlist_arg(arg, wordlist_options);
+            // every other position of the mask takes at least one byte of the candidate
+            options.max_len = options
+                .max_len
+                .min(MAX_WORD_SIZE.saturating_sub(mask.len()));
+            let wordlist = Wordlist::from_file_with_options(&fname, &options)?;
+            wordlists.push((fname, wordlist));
         }
+        Self::from_wordlists(mask, wordlists, custom_charsets)
+    }
 
-        let items: Vec<WordlistItem> = mask
+    /// same as `with_options` with the wordlists ?w1 to ?w9 already loaded, each with the name
+    /// reported in its errors (e.g. its path)
+    pub fn from_wordlists(
+        mask: Vec<MaskOp>,
+        wordlists: Vec<(String, Wordlist)>,
+        custom_charsets: &[CustomCharset<'a>],
+    ) -> Result<WordlistGenerator, CrackenError> {
+        validate_charsets(&mask, custom_charsets.len())?;
+        validate_wordlists(&mask, wordlists.len())?;
+        let (wordlists_paths, wordlists_data): (Vec<_>, Vec<_>) = wordlists
+            .into_iter()
+            .map(|(name, wordlist)| (name, Rc::new(wordlist)))
+            .unzip();
+
+        // custom tokens are loaded as tiny in-memory wordlists
+        let mut tokens_data = vec![];
+        for charset in custom_charsets.iter() {
+            tokens_data.push(match charset {
+                CustomCharset::Tokens(_) => match Wordlist::from_words(charset.tokens()) {
+                    Ok(tokens) => Some(Rc::new(tokens)),
+                    Err(e) => return Err(CrackenError::Charset(format!("invalid tokens: {}", e))),
+                },
+                CustomCharset::Chars(_) => None,
+            });
+        }
+
+        let items = mask
             .iter()
-            .map(|op| match op {
-                MaskOp::Char(ch) => {
-                    WordlistItem::Charset(Charset::from_chars(vec![*ch as u8].as_ref()))
+            .map(|op| {
+                Ok(match op {
+                    MaskOp::Char(ch) => {
+                        WordlistItem::Charset(Charset::from_chars(vec![*ch as u8].as_ref()))
+                    }
+                    MaskOp::BuiltinCharset(ch) => WordlistItem::Charset(Charset::from_symbol(*ch)),
+                    MaskOp::CustomCharset(idx) => match custom_charsets[*idx] {
+                        CustomCharset::Chars(chars) => {
+                            WordlistItem::Charset(Charset::from_chars(chars.as_bytes()))
+                        }
+                        CustomCharset::Tokens(_) => {
+                            WordlistItem::Wordlist(Rc::clone(tokens_data[*idx].as_ref().unwrap()))
+                        }
+                    },
+                    MaskOp::Wordlist(idx) => {
+                        WordlistItem::Wordlist(Rc::clone(&wordlists_data[*idx]))
+                    }
+                    MaskOp::TransformedWordlist { idx, transform } => {
+                        match wordlists_data[*idx].transformed(*transform) {
+                            Ok(wordlist) => WordlistItem::Wordlist(Rc::new(wordlist)),
+                            Err(e) => {
+                                return Err(CrackenError::wordlist(
+                                    &wordlists_paths[*idx],
+                                    e.to_string(),
+                                ))
+                            }
+                        }
+                    }
+                    // keyboard walks are generated upfront as an in-memory wordlist, their number
+                    // is counted first without generating them
+                    MaskOp::Keywalk {
+                        minlen,
+                        maxlen,
+                        shifted,
+                    } => {
+                        let graph = KeyboardGraph::qwerty(*shifted);
+                        let count = graph.count_walks(*minlen, *maxlen);
+                        if count > MAX_KEYWALKS {
+                            return Err(CrackenError::Mask(format!(
+                                "keyboard walks of length {}-{} are {} words, the maximum is {}",
+                                minlen, maxlen, count, MAX_KEYWALKS
+                            )));
+                        }
+                        let walks = Wordlist::from_words(graph.walks(*minlen, *maxlen))
+                            .map_err(|e| CrackenError::Mask(e.to_string()))?;
+                        WordlistItem::Wordlist(Rc::new(walks))
+                    }
+                    // valid calendar dates are generated upfront as an in-memory wordlist
+                    MaskOp::Date {
+                        format,
+                        from_year,
+                        to_year,
+                    } => {
+                        let dates = gen_dates(format, *from_year, *to_year);
+                        let dates = Wordlist::from_words(dates.iter().map(|d| d.as_slice()))
+                            .map_err(|e| CrackenError::Mask(e.to_string()))?;
+                        WordlistItem::Wordlist(Rc::new(dates))
+                    }
+                    MaskOp::Backref(pos) => WordlistItem::Backref(*pos),
+                })
+            })
+            .collect::<Result<Vec<_>, CrackenError>>()?;
+
+        let max_len = |item: &WordlistItem| match item {
+            WordlistItem::Wordlist(wordlist) => wordlist.max_len(),
+            _ => 1,
+        };
+        let max_word_len: usize = items
+            .iter()
+            .map(|item| match item {
+                WordlistItem::Backref(pos) => max_len(&items[*pos]),
+                _ => max_len(item),
+            })
+            .sum();
+        if max_word_len >= MAX_WORD_SIZE {
+            return Err(CrackenError::InvalidLength(format!(
+                "mask generates words of up to {} bytes, the maximum is {}",
+                max_word_len,
+                MAX_WORD_SIZE - 1
+            )));
+        }
+
+        Ok(WordlistGenerator {
+            mask,
+            items,
+            hex_output: false,
+            buffer_size: BUFFER_SIZE,
+            repeat_limits: RepeatLimits::default(),
+        })
+    }
+
+    /// finds the lowest index of all the splits of `word` into the remaining mask items.
+    /// `digits` are the indices (and matched values) of the items matched so far
+    fn match_items<'w>(
+        &self,
+        word: &'w [u8],
+        digits: &mut Vec<(usize, &'w [u8])>,
+        best: &mut Option<WordRank>,
+    ) {
+        let item = match self.items.get(digits.len()) {
+            Some(item) => item,
+            None => {
+                if word.is_empty() {
+                    let index = self
+                        .items
+                        .iter()
+                        .zip(digits.iter())
+                        .fold(0.to_biguint().unwrap(), |acc, (item, (digit, _))| {
+                            acc * item.len() + *digit
+                        });
+                    let is_better = match best {
+                        Some(best) => index < best.index,
+                        None => true,
+                    };
+                    if is_better {
+                        *best = Some(WordRank {
+                            index,
+                            wordlist_words: self
+                                .items
+                                .iter()
+                                .zip(digits.iter())
+                                .filter(|(item, _)| matches!(item, WordlistItem::Wordlist(_)))
+                                .map(|(_, (_, w))| w.to_vec())
+                                .collect(),
+                        });
+                    }
                 }
-                MaskOp::BuiltinCharset(ch) => WordlistItem::Charset(Charset::from_symbol(*ch)),
-                MaskOp::CustomCharset(idx) => {
-                    WordlistItem::Charset(Charset::from_chars(custom_charsets[*idx].as_bytes()))
+                return;
+            }
+        };
+
+        match item {
+            WordlistItem::Charset(charset) => {
+                if let Some(digit) = word.first().and_then(|&chr| charset.index_of(chr)) {
+                    digits.push((digit, &word[..1]));
+                    self.match_items(&word[1..], digits, best);
+                    digits.pop();
                 }
-                MaskOp::Wordlist(idx) => WordlistItem::Wordlist(Rc::clone(&wordlists_data[*idx])),
-            })
-            .collect();
+            }
+            WordlistItem::Wordlist(wordlist) => {
+                for len in wordlist.word_lens().take_while(|&len| len <= word.len()) {
+                    if let Some(digit) = wordlist.index_of(&word[..len]) {
+                        digits.push((digit, &word[..len]));
+                        self.match_items(&word[len..], digits, best);
+                        digits.pop();
+                    }
+                }
+            }
+            WordlistItem::Backref(pos) => {
+                let value = digits[*pos].1;
+                if word.starts_with(value) {
+                    digits.push((0, &word[..value.len()]));
+                    self.match_items(&word[value.len()..], digits, best);
+                    digits.pop();
+                }
+            }
+        }
+    }
 
-        Ok(WordlistGenerator { mask, items })
+    fn has_backrefs(&self) -> bool {
+        self.items
+            .iter()
+            .any(|item| matches!(item, WordlistItem::Backref(_)))
     }
 
+    /// the index of the value of every item of the word at `index`, the last item changes first
+    fn digits_at(&self, index: u64) -> Vec<usize> {
+        let mut offset = index;
+        let mut digits = vec![0; self.items.len()];
+        for (item, digit) in self.items.iter().zip(digits.iter_mut()).rev() {
+            *digit = (offset % item.len() as u64) as usize;
+            offset /= item.len() as u64;
+        }
+        digits
+    }
+
+    /// generates `count` words from the word at `start`, all of them if None
     #[allow(clippy::borrowed_box)]
-    fn gen_words<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), std::io::Error> {
-        let mut buf = StackBuf::new();
+    fn gen_words<'b>(
+        &self,
+        start: u64,
+        count: Option<u64>,
+        out: &mut Box<dyn Write + 'b>,
+    ) -> Result<(), std::io::Error> {
+        let max_len = max_written_len(MAX_WORD_SIZE, self.hex_output);
+        let mut buf = StackBuf::with_size(self.buffer_size.max(max_len));
 
         let mut word_buf = [b'\n'; MAX_WORD_SIZE];
         let word = &mut word_buf[..];
         let mut positions: Vec<_> = self
             .items
             .iter()
-            .map(|item| match item {
+            .zip(self.digits_at(start))
+            .map(|(item, digit)| match item {
                 WordlistItem::Charset(charset) => Position::CharsetPos {
                     charset,
-                    chr: charset.min_char,
+                    chr: charset.nth_char(digit),
                 },
                 WordlistItem::Wordlist(wordlist) => Position::WordlistPos {
                     wordlist,
-                    idx: wordlist.iter(),
+                    idx: wordlist.iter_from(digit),
                 },
+                WordlistItem::Backref(_) => unreachable!("backreferences are generated by value"),
             })
             .collect();
 
-        let mut min_word = vec![];
+        let mut first_word = vec![];
         for pos in positions.iter_mut() {
             match pos {
-                Position::CharsetPos { chr, .. } => min_word.push(*chr),
+                Position::CharsetPos { chr, .. } => first_word.push(*chr),
                 Position::WordlistPos { idx, .. } => {
-                    min_word.extend_from_slice(idx.next().unwrap())
+                    first_word.extend_from_slice(idx.next().unwrap())
                 }
             }
         }
-        min_word.push(b'\n');
-        let min_word = min_word;
-        let mut word_len = min_word.len();
+        first_word.push(b'\n');
+        let first_word = first_word;
+        let mut word_len = first_word.len();
 
-        word[..word_len].copy_from_slice(&min_word);
+        word[..word_len].copy_from_slice(&first_word);
+        // wordlists words have any length, the words are filtered after they are generated
+        let limited = self.repeat_limits.is_set();
+        let mut left = count.unwrap_or(u64::MAX);
 
         'outer_loop: loop {
-            if buf.pos() + word_len >= buf.len() {
+            if buf.remaining() < max_written_len(word_len, self.hex_output) {
                 out.write_all(buf.getdata())?;
                 buf.clear();
             }
-            buf.write(&word[..word_len]);
+            if !limited || self.repeat_limits.allows(&word[..word_len - 1]) {
+                write_word(&mut buf, &word[..word_len], self.hex_output);
+            }
+            left -= 1;
+            if left == 0 {
+                break;
+            }
 
             let mut pos = word_len - 2;
 
@@ -331,38 +1235,211 @@ impl<'a> WordlistGenerator {
         out.write_all(buf.getdata())?;
         Ok(())
     }
+
+    /// same as `gen_words` for the masks with backreferences, which can't move a single
+    /// position in place. every word is assembled from the values of its items, the
+    /// backreferences copy the value of the item they repeat
+    #[allow(clippy::borrowed_box)]
+    fn gen_words_with_backrefs<'b>(
+        &self,
+        start: u64,
+        count: Option<u64>,
+        out: &mut Box<dyn Write + 'b>,
+    ) -> Result<(), std::io::Error> {
+        let max_len = max_written_len(MAX_WORD_SIZE, self.hex_output);
+        let mut buf = StackBuf::with_size(self.buffer_size.max(max_len));
+        let limited = self.repeat_limits.is_set();
+
+        // the chars of the charsets in order, `Charset::nth_char` walks the charset
+        let chars: Vec<Vec<u8>> = self
+            .items
+            .iter()
+            .map(|item| match item {
+                WordlistItem::Charset(charset) => {
+                    (0..charset.len).map(|idx| charset.nth_char(idx)).collect()
+                }
+                _ => vec![],
+            })
+            .collect();
+        let mut digits = self.digits_at(start);
+        // the range of the value of every item in the word
+        let mut ranges = vec![(0, 0); self.items.len()];
+        let mut word = Vec::with_capacity(MAX_WORD_SIZE);
+        let mut left = count.unwrap_or(u64::MAX);
+        loop {
+            word.clear();
+            for (idx, item) in self.items.iter().enumerate() {
+                let start = word.len();
+                match item {
+                    WordlistItem::Charset(_) => word.push(chars[idx][digits[idx]]),
+                    WordlistItem::Wordlist(wordlist) => {
+                        word.extend_from_slice(wordlist.get(digits[idx]).unwrap())
+                    }
+                    WordlistItem::Backref(pos) => {
+                        let (value_start, value_end) = ranges[*pos];
+                        word.extend_from_within(value_start..value_end);
+                    }
+                }
+                ranges[idx] = (start, word.len());
+            }
+            word.push(b'\n');
+
+            if buf.remaining() < max_written_len(word.len(), self.hex_output) {
+                out.write_all(buf.getdata())?;
+                buf.clear();
+            }
+            if !limited || self.repeat_limits.allows(&word[..word.len() - 1]) {
+                write_word(&mut buf, &word, self.hex_output);
+            }
+            left -= 1;
+
+            // the last item changes first
+            let mut idx = self.items.len();
+            loop {
+                if idx == 0 || left == 0 {
+                    out.write_all(buf.getdata())?;
+                    return Ok(());
+                }
+                idx -= 1;
+                digits[idx] += 1;
+                if digits[idx] < self.items[idx].len() {
+                    break;
+                }
+                digits[idx] = 0;
+            }
+        }
+    }
 }
 
-impl<'a> WordGenerator for WordlistGenerator {
+impl WordGenerator for WordlistGenerator {
     /// generates all words into the output buffer `out`
-    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), std::io::Error> {
-        self.gen_words(out)?;
+    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), CrackenError> {
+        if self.has_backrefs() {
+            self.gen_words_with_backrefs(0, None, out)?;
+        } else {
+            self.gen_words(0, None, out)?;
+        }
         Ok(())
     }
 
+    /// seeks every item to its value in the first word of the range. the repeat limits
+    /// generate the words before the range
+    fn gen_range(&self, start: u64, end: u64, out: &mut dyn Write) -> Result<u64, std::io::Error> {
+        if self.repeat_limits.is_set() {
+            return gen_range_discarding(self, start, end, out);
+        }
+        let combs = u64::try_from(&self.combinations()).unwrap_or(u64::MAX);
+        let count = min(end, combs).saturating_sub(start);
+        if count == 0 {
+            return Ok(0);
+        }
+        let mut out: Box<dyn Write + '_> = Box::new(out);
+        if self.has_backrefs() {
+            self.gen_words_with_backrefs(start, Some(count), &mut out)?;
+        } else {
+            self.gen_words(start, Some(count), &mut out)?;
+        }
+        Ok(count)
+    }
+
+    fn set_hex_output(&mut self, hex_output: bool) {
+        self.hex_output = hex_output;
+    }
+
+    fn set_buffer_size(&mut self, buffer_size: usize) {
+        self.buffer_size = buffer_size;
+    }
+
+    fn set_repeat_limits(&mut self, repeat_limits: RepeatLimits) {
+        self.repeat_limits = repeat_limits;
+    }
+
+    /// the words of wordlist masks have a single length range, there is no order to change
+    fn set_length_order(&mut self, _length_order: LengthOrder) {}
+
+    /// the first item of the mask changes last
+    fn word_at(&self, index: u64) -> Option<Vec<u8>> {
+        if index.to_biguint().unwrap() >= self.combinations() {
+            return None;
+        }
+        let digits = self.digits_at(index);
+        // the backreferences copy the value of an earlier item
+        let mut parts: Vec<Vec<u8>> = Vec::with_capacity(self.items.len());
+        for (item, digit) in self.items.iter().zip(digits) {
+            let part = match item {
+                WordlistItem::Charset(charset) => vec![charset.nth_char(digit)],
+                WordlistItem::Wordlist(wordlist) => wordlist.get(digit)?.to_vec(),
+                WordlistItem::Backref(pos) => parts[*pos].clone(),
+            };
+            parts.push(part);
+        }
+        Some(parts.into_iter().flatten().collect())
+    }
+
+    fn index_of(&self, word: &[u8]) -> Option<WordRank> {
+        let mut digits = Vec::with_capacity(self.items.len());
+        let mut best = None;
+        self.match_items(word, &mut digits, &mut best);
+        best
+    }
+
     fn combinations(&self) -> BigUint {
         self.items
             .iter()
-            .map(|item| match item {
-                WordlistItem::Wordlist(wl) => wl.len().to_biguint().unwrap(),
-                WordlistItem::Charset(c) => c.len.to_biguint().unwrap(),
-            })
+            .map(|item| item.len().to_biguint().unwrap())
             .product()
     }
+
+    fn length_combinations(&self) -> Vec<(usize, BigUint)> {
+        vec![]
+    }
+
+    fn output_bytes(&self) -> BigUint {
+        // every word of an item is combined with all the words of the other items, a
+        // backreference repeats the bytes of its item
+        let combs = self.combinations();
+        let words_bytes: BigUint = self
+            .items
+            .iter()
+            .map(|item| {
+                let item = match item {
+                    WordlistItem::Backref(pos) => &self.items[*pos],
+                    _ => item,
+                };
+                let item_bytes = match item {
+                    WordlistItem::Wordlist(wl) => wl.total_bytes(),
+                    WordlistItem::Charset(c) => c.len,
+                    WordlistItem::Backref(_) => unreachable!("backreferences repeat an item"),
+                };
+                &combs / item.len() * item_bytes
+            })
+            .sum();
+        words_bytes + combs
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cmp::min;
+    use std::collections::HashMap;
     use std::fs;
-    use std::io::{Cursor, Write};
+    use std::io::{Cursor, Error, IoSlice, Write};
 
     use num_bigint::{BigUint, ToBigUint};
-
-    use crate::generators::get_word_generator;
+    use regex::bytes::Regex;
+
+    use crate::charsets::CustomCharset;
+    use crate::error::CrackenError;
+    use crate::generators::{
+        gen_range_discarding, get_word_generator, get_word_generator_with_options, hashcat_keyspace,
+    };
+    use crate::helpers::decode_hex_word;
+    use crate::keyboard::KeyboardGraph;
     use crate::mask::parse_mask;
     use crate::test_util::wordlist_fname;
+    use crate::wordlists::{Wordlist, WordlistOptions};
 
-    use super::{CharsetGenerator, WordGenerator};
+    use super::{CharsetGenerator, LengthOrder, RepeatLimits, WordGenerator, WordlistGenerator};
 
     #[test]
     fn test_gen_words_single_digit() {
@@ -398,7 +1475,8 @@ mod tests {
     #[test]
     fn test_gen_pwd_upper_lower_year_1_4() {
         let mask = parse_mask("pwd?u?l201?1").unwrap();
-        let word_gen = CharsetGenerator::new(mask.to_vec(), Some(1), None, &vec!["56789"]).unwrap();
+        let word_gen =
+            CharsetGenerator::new(mask.to_vec(), Some(1), None, &vec!["56789".into()]).unwrap();
 
         assert_eq!(word_gen.mask, mask);
         assert_eq!(word_gen.minlen, 1);
@@ -409,6 +1487,216 @@ mod tests {
         assert_gen(Box::new(word_gen), "upper-lower-year-1-4.txt");
     }
 
+    #[test]
+    fn test_gen_repeat_limits() {
+        let limits = |seq_max, occurrence_max| RepeatLimits {
+            seq_max,
+            occurrence_max,
+        };
+        // maskprocessor -q 2 and -r 1
+        let mask = parse_mask("?1?1?1?1").unwrap();
+        let mut word_gen =
+            CharsetGenerator::new(mask.clone(), None, None, &["abc".into()]).unwrap();
+        word_gen.set_repeat_limits(limits(Some(2), None));
+        assert_gen(Box::new(word_gen), "mp-seq-max-2.txt");
+        let mut word_gen = CharsetGenerator::new(mask, None, None, &["abcd".into()]).unwrap();
+        word_gen.set_repeat_limits(limits(None, Some(1)));
+        assert_gen(Box::new(word_gen), "mp-occurrence-max-1.txt");
+
+        // the pruned and the filtered wordlists generation are the same as filtering all words
+        let wordlist = wordlist_fname("wordlist1.txt");
+        let wordlists = [wordlist.to_str().unwrap()];
+        for (mask, minlen, limits) in [
+            ("?l?l?l?l", None, limits(Some(2), None)),
+            ("?l?l?l?l", Some(1), limits(Some(1), Some(2))),
+            ("?d?d?d?d", Some(2), limits(None, Some(1))),
+            ("a?da?d?d", None, limits(Some(1), Some(2))),
+            ("?w1?d?d", None, limits(Some(2), Some(2))),
+        ] {
+            let gen = |limits: Option<RepeatLimits>, buffer_size: usize| {
+                let mut word_gen = get_word_generator(mask, minlen, None, &[], &wordlists).unwrap();
+                word_gen.set_buffer_size(buffer_size);
+                if let Some(limits) = limits {
+                    word_gen.set_repeat_limits(limits);
+                }
+                let mut buf: Vec<u8> = Vec::new();
+                {
+                    let mut cur: Box<dyn Write> = Box::new(Cursor::new(&mut buf));
+                    word_gen.gen(&mut cur).unwrap();
+                }
+                buf
+            };
+            let expected: Vec<u8> = gen(None, 8192)
+                .split(|&c| c == b'\n')
+                .filter(|word| !word.is_empty() && limits.allows(word))
+                .flat_map(|word| [word, b"\n"].concat())
+                .collect();
+            assert!(!expected.is_empty());
+            for buffer_size in [1, 100, 8192] {
+                assert!(gen(Some(limits), buffer_size) == expected, "{}", mask);
+            }
+        }
+    }
+
+    #[test]
+    fn test_gen_length_order() {
+        let gen = |order: LengthOrder, limits: RepeatLimits, buffer_size: usize| {
+            let mut word_gen = get_word_generator("?d?d?d", Some(1), None, &[], &[]).unwrap();
+            word_gen.set_length_order(order);
+            word_gen.set_repeat_limits(limits);
+            word_gen.set_buffer_size(buffer_size);
+            let mut buf: Vec<u8> = Vec::new();
+            {
+                let mut cur: Box<dyn Write> = Box::new(Cursor::new(&mut buf));
+                word_gen.gen(&mut cur).unwrap();
+            }
+            let words: Vec<String> = String::from_utf8(buf)
+                .unwrap()
+                .lines()
+                .map(|w| w.to_string())
+                .collect();
+            (words, word_gen)
+        };
+        let seq_max = RepeatLimits {
+            seq_max: Some(1),
+            occurrence_max: None,
+        };
+        for limits in [RepeatLimits::default(), seq_max] {
+            let (mut ascending, _) = gen(LengthOrder::Ascending, limits, 8192);
+            ascending.sort_unstable();
+            for order in [
+                LengthOrder::Descending,
+                LengthOrder::Interleave(7),
+                LengthOrder::Interleave(100),
+                LengthOrder::Likely(vec![2, 5, 1]),
+            ] {
+                for buffer_size in [1, 10, 8192] {
+                    let (mut words, _) = gen(order.clone(), limits, buffer_size);
+                    words.sort_unstable();
+                    assert_eq!(words, ascending, "{:?} {}", order, buffer_size);
+                }
+            }
+        }
+
+        let (words, word_gen) = gen(LengthOrder::Descending, RepeatLimits::default(), 8192);
+        assert_eq!((&words[0][..], &words[999][..]), ("000", "999"));
+        assert_eq!((&words[1000][..], &words[1099][..]), ("00", "99"));
+        assert_eq!(words[1100], "0");
+        assert_eq!(assert_index_of_brute_force(word_gen.as_ref()), 1110);
+        assert_eq!(
+            word_gen.length_combinations()[0],
+            (3, 1000.to_biguint().unwrap())
+        );
+
+        // 7 words of every length in turn, the exhausted lengths are left out
+        let (words, word_gen) = gen(LengthOrder::Interleave(7), RepeatLimits::default(), 10);
+        assert_eq!(words[..8], ["0", "1", "2", "3", "4", "5", "6", "00"]);
+        assert_eq!(words[14..17], ["000", "001", "002"]);
+        assert_eq!(words[21..25], ["7", "8", "9", "07"]);
+        assert_eq!(words[30..32], ["13", "007"]);
+        assert_eq!(words[37..39], ["013", "14"]);
+        assert_eq!(assert_index_of_brute_force(word_gen.as_ref()), 1110);
+
+        // the likely lengths first, then the missing ones in ascending order
+        let (words, word_gen) = gen(
+            LengthOrder::Likely(vec![2, 5]),
+            RepeatLimits::default(),
+            8192,
+        );
+        assert_eq!(
+            (&words[0][..], &words[100][..], &words[110][..]),
+            ("00", "0", "000")
+        );
+        assert_eq!(assert_index_of_brute_force(word_gen.as_ref()), 1110);
+    }
+
+    #[test]
+    fn test_gen_range() {
+        // adjacent ranges together are all the words, the last one ends early
+        let assert_ranges = |word_gen: &dyn WordGenerator, name: &str| {
+            let mut expected: Vec<u8> = Vec::new();
+            {
+                let mut cur: Box<dyn Write> = Box::new(Cursor::new(&mut expected));
+                word_gen.gen(&mut cur).unwrap();
+            }
+            let words = expected.iter().filter(|&&c| c == b'\n').count() as u64;
+            for step in [1, 7, 100] {
+                let mut buf = vec![];
+                let mut start = 0;
+                loop {
+                    let written = word_gen.gen_range(start, start + step, &mut buf).unwrap();
+                    assert_eq!(written, min(step, words.saturating_sub(start)), "{}", name);
+                    if written == 0 {
+                        break;
+                    }
+                    start += step;
+                }
+                assert!(buf == expected, "{} {}", name, step);
+            }
+            let mut seeked = vec![];
+            let mut discarded = vec![];
+            assert_eq!(word_gen.gen_range(5, 12, &mut seeked).unwrap(), 7);
+            gen_range_discarding(word_gen, 5, 12, &mut discarded).unwrap();
+            assert!(seeked == discarded, "{}", name);
+            assert_eq!(word_gen.gen_range(9, 9, &mut seeked).unwrap(), 0);
+            assert_eq!(word_gen.gen_range(words, u64::MAX, &mut seeked).unwrap(), 0);
+        };
+
+        let wordlist = wordlist_fname("wordlist1.txt");
+        let wordlists = [wordlist.to_str().unwrap()];
+        for (mask, minlen) in [
+            ("?d?d", Some(1)),
+            ("?u?l?d", None),
+            ("?1?l?d", Some(2)),
+            ("?w1?d", None),
+            ("?d?w1?1", None),
+            ("?d?l?r1", None),
+        ] {
+            let word_gen =
+                get_word_generator(mask, minlen, None, &["!@#".into()], &wordlists).unwrap();
+            assert_ranges(word_gen.as_ref(), mask);
+        }
+
+        // the words before the range are generated with the repeat limits and the interleaved
+        // lengths
+        let seq_max = RepeatLimits {
+            seq_max: Some(1),
+            occurrence_max: None,
+        };
+        for mask in ["?d?d?d", "?w1?d?d"] {
+            let minlen = if mask == "?d?d?d" { Some(1) } else { None };
+            let mut word_gen = get_word_generator(mask, minlen, None, &[], &wordlists).unwrap();
+            word_gen.set_repeat_limits(seq_max);
+            assert_ranges(word_gen.as_ref(), mask);
+        }
+        let mut word_gen = get_word_generator("?d?d?d", Some(1), None, &[], &[]).unwrap();
+        word_gen.set_length_order(LengthOrder::Interleave(7));
+        assert_ranges(word_gen.as_ref(), "interleave");
+        word_gen.set_length_order(LengthOrder::Descending);
+        assert_ranges(word_gen.as_ref(), "descending");
+    }
+
+    #[test]
+    fn test_repeat_limits_allows() {
+        let limits = RepeatLimits {
+            seq_max: Some(2),
+            occurrence_max: Some(3),
+        };
+        for (word, allowed) in [
+            (&b""[..], true),
+            (b"aab", true),
+            (b"aaab", false),
+            (b"baaa", false),
+            (b"aabaa", false),
+            (b"aabab", true),
+            (b"abab", true),
+        ] {
+            assert_eq!(limits.allows(word), allowed, "{:?}", word);
+        }
+        assert!(RepeatLimits::default().allows(b"aaaaaa"));
+        assert!(!RepeatLimits::default().is_set());
+    }
+
     #[test]
     fn test_get_word_generator_charset() {
         let mask = "?d?d?d?d";
@@ -444,7 +1732,7 @@ mod tests {
         let mask = "?w1?d?w2?l?w1?1";
         let wordlist1 = wordlist_fname("wordlist1.txt");
         let wordlist2 = wordlist_fname("wordlist2.txt");
-        let charsets = vec!["!@#"];
+        let charsets = vec![CustomCharset::Chars("!@#")];
         let wordlists = vec![wordlist1.to_str().unwrap(), wordlist2.to_str().unwrap()];
         let word_gen =
             get_word_generator(mask, None, None, charsets.as_ref(), wordlists.as_ref()).unwrap();
@@ -460,7 +1748,7 @@ mod tests {
     fn test_word_generator_invalid_wordlist_mask() {
         let mask = "?w1?d?w2?l?w1?1";
         let wordlist1 = wordlist_fname("wordlist1.txt");
-        let charsets = vec!["!@#"];
+        let charsets = vec![CustomCharset::Chars("!@#")];
         let wordlists = vec![wordlist1.to_str().unwrap()];
 
         let word_gen = get_word_generator(mask, None, None, charsets.as_ref(), wordlists.as_ref());
@@ -470,12 +1758,642 @@ mod tests {
     #[test]
     fn test_word_generator_invalid_custom_charset_mask() {
         let mask = "a?1?2?l?3";
-        let charsets = vec!["!@#", "abc"];
+        let charsets = vec![CustomCharset::Chars("!@#"), CustomCharset::Chars("abc")];
 
         let word_gen = get_word_generator(mask, None, None, charsets.as_ref(), vec![].as_ref());
         assert!(word_gen.is_err());
     }
 
+    #[test]
+    fn test_generators_out_of_range_references() {
+        let charsets = vec![CustomCharset::Chars("ab"), CustomCharset::Chars("cd")];
+        let mask = parse_mask("?1?2?3").unwrap();
+        let err = CharsetGenerator::new(mask.to_vec(), None, None, &charsets)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "mask uses ?3 but only 2 custom charsets were provided (-c or --tokens)"
+        );
+        assert!(WordlistGenerator::new(mask, &[], &charsets).is_err());
+
+        let wordlist1 = wordlist_fname("wordlist1.txt");
+        let wordlists = vec![wordlist1.to_str().unwrap()];
+        let mask = parse_mask("?w1?w2").unwrap();
+        let err = WordlistGenerator::new(mask, &wordlists, &charsets)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "mask uses ?w2 but only 1 wordlists were provided (-w)"
+        );
+    }
+
+    #[test]
+    fn test_word_generator_keywalk() {
+        let word_gen = get_word_generator("?K{4}?d", None, None, &[], &[]).unwrap();
+        let walks: Vec<_> = KeyboardGraph::qwerty(false).walks(4, 4).collect();
+        assert_eq!(
+            word_gen.combinations(),
+            (walks.len() * 10).to_biguint().unwrap()
+        );
+
+        let output = gen_to_string(word_gen);
+        let words: Vec<_> = output.lines().collect();
+        assert_eq!(words.len(), walks.len() * 10);
+        assert_eq!(words[0], format!("{}0", String::from_utf8_lossy(&walks[0])));
+        assert!(words.contains(&"qwer5"));
+        assert!(words.contains(&"1qaz9"));
+
+        let word_gen = get_word_generator("?K{1-2,shift}", None, None, &[], &[]).unwrap();
+        let walks: Vec<_> = KeyboardGraph::qwerty(true).walks(1, 2).collect();
+        assert_eq!(word_gen.combinations(), walks.len().to_biguint().unwrap());
+
+        // long walks are rejected before generating them
+        for mask in ["?K{40}", "?K{255}?K{255}", "?K{1-500,shift}"] {
+            match get_word_generator(mask, None, None, &[], &[]) {
+                Err(CrackenError::Mask(msg)) => assert!(msg.contains("the maximum is 10000000")),
+                res => panic!("unexpected result for {}: {:?}", mask, res.err()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_word_generator_utf8_chars() {
+        let word_gen = get_word_generator("é?d€", None, None, &[], &[]).unwrap();
+        let output = gen_to_string(word_gen);
+        assert_eq!(output.lines().next(), Some("é0€"));
+        assert_eq!(output.lines().count(), 10);
+
+        let wordlist = wordlist_fname("wordlist-no-newline.txt");
+        let wordlists = [wordlist.to_str().unwrap()];
+        let word_gen = get_word_generator("?w1→", None, None, &[], &wordlists).unwrap();
+        assert_eq!(gen_to_string(word_gen), "john→\nemile→\nMcDonald→\n");
+    }
+
+    #[test]
+    fn test_word_generator_dates() {
+        let word_gen = get_word_generator("?D{DDMMYYYY,1990-1999}", None, None, &[], &[]).unwrap();
+        assert_eq!(word_gen.combinations(), 3652.to_biguint().unwrap());
+
+        let word_gen = get_word_generator("?l?D{DDMM,2000-2000}!", None, None, &[], &[]).unwrap();
+        assert_eq!(word_gen.combinations(), (26 * 366).to_biguint().unwrap());
+        let output = gen_to_string(word_gen);
+        let words: Vec<_> = output.lines().collect();
+        assert_eq!(words.len(), 26 * 366);
+        assert_eq!(words[0], "a0101!");
+        assert!(words.contains(&"m2902!"));
+        assert!(!words.contains(&"m3002!"));
+        assert_eq!(words.last().unwrap(), &"z3112!");
+    }
+
+    #[test]
+    fn test_word_generator_custom_tokens_only() {
+        let charsets = vec![CustomCharset::Tokens("2024|!|123|!!")];
+        let word_gen = get_word_generator("?d?1", None, None, &charsets, &[]).unwrap();
+        assert_eq!(word_gen.combinations(), 40.to_biguint().unwrap());
+
+        let mut expected = String::new();
+        for d in 0..10 {
+            for token in ["!", "!!", "123", "2024"] {
+                expected.push_str(&format!("{}{}\n", d, token));
+            }
+        }
+        assert_eq!(gen_to_string(word_gen), expected);
+    }
+
+    #[test]
+    fn test_word_generator_custom_tokens_and_wordlist() {
+        let mask = "?1?w1?2-?3";
+        let wordlist1 = wordlist_fname("wordlist1.txt");
+        let charsets = vec![
+            CustomCharset::Tokens("!|!!|123|2024"),
+            CustomCharset::Tokens("a|bcd|ef"),
+            CustomCharset::Chars("xy"),
+        ];
+        let wordlists = vec![wordlist1.to_str().unwrap()];
+        let word_gen = get_word_generator(mask, None, None, &charsets, &wordlists).unwrap();
+        assert_eq!(
+            word_gen.combinations(),
+            (4 * 10 * 3 * 2).to_biguint().unwrap()
+        );
+
+        let words = Wordlist::from_file(&wordlist1).unwrap();
+        let mut expected = String::new();
+        for t1 in ["!", "!!", "123", "2024"] {
+            for w in words.iter() {
+                for t2 in ["a", "ef", "bcd"] {
+                    for c in ["x", "y"] {
+                        let w = String::from_utf8(w.to_vec()).unwrap();
+                        expected.push_str(&format!("{}{}{}-{}\n", t1, w, t2, c));
+                    }
+                }
+            }
+        }
+        assert_eq!(gen_to_string(word_gen), expected);
+    }
+
+    #[test]
+    fn test_word_generator_custom_tokens_minlen() {
+        let charsets = vec![CustomCharset::Tokens("a|bb")];
+        let word_gen = get_word_generator("?d?1", Some(1), None, &charsets, &[]);
+        assert!(word_gen.is_err());
+    }
+
+    #[test]
+    fn test_word_generator_wordlist_transform() {
+        let wordlist = wordlist_fname("wordlist-utf8.txt");
+        let arg = format!("{}:capitalize", wordlist.to_str().unwrap());
+        let word_gen = get_word_generator("?w1?d", None, None, &[], &[arg.as_str()]).unwrap();
+        assert_eq!(word_gen.combinations(), (4 * 10).to_biguint().unwrap());
+
+        let output = gen_to_string(word_gen);
+        let words: Vec<_> = output.lines().collect();
+        assert_eq!(words.len(), 40);
+        assert_eq!(words[0], "John0");
+        assert!(words.contains(&"Zoë5"));
+        assert!(words.contains(&"émile9"));
+        assert_eq!(words[39], "Mcdonald9");
+    }
+
+    #[test]
+    fn test_word_generator_backref() {
+        let word_gen = get_word_generator("?d?l?r1", None, None, &[], &[]).unwrap();
+        assert_eq!(word_gen.combinations(), 260.to_biguint().unwrap());
+        assert_eq!(word_gen.output_bytes(), (260 * 4).to_biguint().unwrap());
+        let words = assert_gen(word_gen, "backref-digit-lower.txt");
+        assert!(words.lines().all(|word| word[..1] == word[2..]));
+
+        // the variable length wordlist words are copied whole
+        let wordlist1 = wordlist_fname("wordlist1.txt");
+        let wordlists = vec![wordlist1.to_str().unwrap()];
+        let word_gen = get_word_generator("?w1-?r1", None, None, &[], &wordlists).unwrap();
+        assert_eq!(word_gen.combinations(), 10.to_biguint().unwrap());
+        let words = gen_to_string(word_gen);
+        // the words are generated by length, the shortest wordlist words first
+        assert!(words.starts_with("12345-12345\n123456-123456\nqwerty-qwerty\n"));
+        assert!(words.ends_with("12345678-12345678\n123456789-123456789\n"));
+        assert!(words.lines().all(|word| {
+            let (first, second) = word.split_once('-').unwrap();
+            first == second
+        }));
+        let word_gen = get_word_generator("?w1?d?r1", None, None, &[], &wordlists).unwrap();
+        assert_eq!(assert_index_of_brute_force(word_gen.as_ref()), 100);
+        assert_eq!(word_gen.index_of(b"qwerty7qwertz"), None);
+        let rank = word_gen.index_of(b"qwerty7qwerty").unwrap();
+        assert_eq!(rank.wordlist_words, vec![b"qwerty".to_vec()]);
+
+        // the repeat limits see the copied values
+        let mut word_gen = get_word_generator("?d?r1", None, None, &[], &[]).unwrap();
+        word_gen.set_repeat_limits(RepeatLimits {
+            seq_max: Some(1),
+            occurrence_max: None,
+        });
+        assert_eq!(gen_to_string(word_gen), "");
+
+        assert!(matches!(
+            get_word_generator("?d?r1", Some(1), None, &[], &[]),
+            Err(CrackenError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_word_generator_wordlist_crlf() {
+        let wordlist = wordlist_fname("wordlist-crlf.txt");
+        let wordlists = vec![wordlist.to_str().unwrap()];
+        let word_gen = get_word_generator("?w1?d", None, None, &[], &wordlists).unwrap();
+        let output = gen_to_string(word_gen);
+        assert_eq!(output.lines().count(), 40);
+        assert!(!output.contains('\r'));
+        assert!(output.contains("emile 5\n"));
+
+        let options = WordlistOptions {
+            trim: true,
+            ..Default::default()
+        };
+        let word_gen =
+            get_word_generator_with_options("?w1?d", None, None, &[], &wordlists, &[options])
+                .unwrap();
+        let output = gen_to_string(word_gen);
+        assert_eq!(output.lines().count(), 40);
+        assert!(output
+            .lines()
+            .all(|w| !w.contains(|c| c == '\r' || c == ' ' || c == '\t')));
+    }
+
+    #[test]
+    fn test_word_generator_wordlist_blank_lines() {
+        // blank lines used to be loaded as zero/whitespace length words
+        let wordlist = wordlist_fname("wordlist-comments.txt");
+        let wordlists = vec![wordlist.to_str().unwrap()];
+        let options = WordlistOptions {
+            comment_prefix: Some("#".to_owned()),
+            ..Default::default()
+        };
+        let word_gen =
+            get_word_generator_with_options("?w1-?d", None, None, &[], &wordlists, &[options])
+                .unwrap();
+        assert_eq!(word_gen.combinations(), (3 * 10).to_biguint().unwrap());
+
+        let output = gen_to_string(word_gen);
+        assert_eq!(output.lines().count(), 30);
+        assert!(output
+            .lines()
+            .all(|w| !w.starts_with('-') && !w.starts_with('#')));
+        assert!(output.starts_with("john-0\njohn-1\n"));
+        assert!(output.ends_with("McDonald-8\nMcDonald-9\n"));
+    }
+
+    #[test]
+    fn test_word_generator_wordlist_invalid_lines() {
+        let wordlist = wordlist_fname("wordlist-invalid-lines.txt");
+        let wordlists = vec![wordlist.to_str().unwrap()];
+
+        // 500 bytes line + 12 digits exceeds the maximum word size
+        let mask = "?w1?d?d?d?d?d?d?d?d?d?d?d?d";
+        let err = get_word_generator(mask, None, None, &[], &wordlists)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("longer than 499 bytes"), "{}", err);
+        assert!(
+            err.contains("line 3 (500 bytes), line 5 (8 bytes, NUL byte)"),
+            "{}",
+            err
+        );
+
+        let options = WordlistOptions {
+            skip_invalid: true,
+            ..Default::default()
+        };
+        let word_gen =
+            get_word_generator_with_options("?w1", None, None, &[], &wordlists, &[options.clone()])
+                .unwrap();
+        assert_eq!(word_gen.combinations(), 4u32.to_biguint().unwrap());
+        let word_gen =
+            get_word_generator_with_options(mask, None, None, &[], &wordlists, &[options.clone()])
+                .unwrap();
+        assert_eq!(
+            word_gen.combinations(),
+            (3 * 10u128.pow(12)).to_biguint().unwrap()
+        );
+
+        // multiple wordlists together can still exceed the maximum word size
+        let wordlists = vec![wordlist.to_str().unwrap(), wordlist.to_str().unwrap()];
+        let err = get_word_generator_with_options(
+            "?w1?w2",
+            None,
+            None,
+            &[],
+            &wordlists,
+            &[options.clone(), options],
+        )
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(err.contains("up to 1000 bytes"), "{}", err);
+    }
+
+    #[test]
+    fn test_word_generator_hex_round_trip() {
+        let wordlist = wordlist_fname("wordlist-hex.txt");
+        let wordlists = vec![wordlist.to_str().unwrap()];
+        let mut word_gen = get_word_generator("?w1", None, None, &[], &wordlists).unwrap();
+        word_gen.set_hex_output(true);
+        let output = gen_to_string(word_gen);
+        assert_eq!(
+            output,
+            "$HEX[0a]\n$HEX[00ff]\nABC\n$HEX[6162630a]\nplain\n$HEX[244845585b5d]\n"
+        );
+
+        let decoded: Vec<_> = output
+            .lines()
+            .map(|w| decode_hex_word(w.as_bytes().to_vec()).unwrap())
+            .collect();
+        let words: Vec<_> = Wordlist::from_file(&wordlist)
+            .unwrap()
+            .iter()
+            .map(|w| w.to_vec())
+            .collect();
+        assert_eq!(decoded, words);
+
+        // every byte of ?b is written as a separate line
+        let mut word_gen = get_word_generator("?b", None, None, &[], &[]).unwrap();
+        word_gen.set_hex_output(true);
+        let output = gen_to_string(word_gen);
+        let decoded: Vec<_> = output
+            .lines()
+            .map(|w| decode_hex_word(w.as_bytes().to_vec()).unwrap())
+            .collect();
+        let expected: Vec<_> = (0..=255u8).map(|c| vec![c]).collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_word_generator_wordlist_filter() {
+        let wordlist = wordlist_fname("wordlist1.txt");
+        let wordlists = vec![wordlist.to_str().unwrap(), wordlist.to_str().unwrap()];
+        let options = WordlistOptions {
+            filter: Some(Regex::new("^[a-z]+[0-9]*$").unwrap()),
+            ..Default::default()
+        };
+        let word_gen =
+            get_word_generator_with_options("?w1-?w2", None, None, &[], &wordlists, &[options])
+                .unwrap();
+        assert_eq!(word_gen.combinations(), (3 * 10).to_biguint().unwrap());
+
+        let output = gen_to_string(word_gen);
+        assert_eq!(output.lines().count(), 30);
+        for word in output.lines() {
+            let stem = word.split('-').next().unwrap();
+            assert!(["qwerty", "abc123", "password"].contains(&stem), "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_word_generator_wordlist_reverse() {
+        let wordlist = wordlist_fname("wordlist-utf8.txt");
+        let wordlists = vec![wordlist.to_str().unwrap()];
+        let word_gen = get_word_generator("?w1:rev?w1", None, None, &[], &wordlists).unwrap();
+        assert_eq!(word_gen.combinations(), (4 * 4).to_biguint().unwrap());
+
+        let output = gen_to_string(word_gen);
+        let words: Vec<_> = output.lines().collect();
+        assert_eq!(words.len(), 16);
+        assert_eq!(words[0], "nhojjohn");
+        assert!(words.contains(&"elimézoë"));
+        assert!(words.contains(&"dlanoDcMMcDonald"));
+    }
+
+    #[test]
+    fn test_word_generator_wordlist_slice() {
+        let wordlist = wordlist_fname("names.txt");
+        let wordlists = vec![wordlist.to_str().unwrap()];
+        let word_gen = get_word_generator("?w1[:4]?d?d", None, None, &[], &wordlists).unwrap();
+        // michael, michelle, michal and michaela share the same prefix
+        assert_eq!(word_gen.combinations(), (3 * 100).to_biguint().unwrap());
+
+        let output = gen_to_string(word_gen);
+        assert_eq!(output.lines().count(), 300);
+        assert!(output.starts_with("john00\n"));
+        assert!(output.ends_with("mich99\n"));
+    }
+
+    #[test]
+    fn test_word_generator_wordlist_keyboard_transforms() {
+        let wordlist = wordlist_fname("wordlist1.txt");
+        let wordlists = vec![wordlist.to_str().unwrap()];
+        let word_gen = get_word_generator("?w1:shift?d", None, None, &[], &wordlists).unwrap();
+        assert_eq!(word_gen.combinations(), (10 * 10).to_biguint().unwrap());
+        let output = gen_to_string(word_gen);
+        assert!(output.contains("PASSWORD7\n"));
+        assert!(output.starts_with("!@#$%0\n"));
+
+        // the adjacent keys words are expanded when the wordlist is loaded
+        let word_gen = get_word_generator("?w1:adjacent", None, None, &[], &wordlists).unwrap();
+        assert_eq!(word_gen.combinations(), 131.to_biguint().unwrap());
+        let output = gen_to_string(word_gen);
+        assert_eq!(output.lines().count(), 131);
+        assert!(output.contains("\n[assword\n"));
+    }
+
+    #[test]
+    fn test_gen_buffer_sizes() {
+        let wordlist1 = wordlist_fname("wordlist1.txt");
+        let wordlist_hex = wordlist_fname("wordlist-hex.txt");
+        let wordlists = vec![wordlist1.to_str().unwrap(), wordlist_hex.to_str().unwrap()];
+        for (mask, minlen, hex_output) in [
+            ("?u?l?d?d", Some(1), false),
+            ("?b?d", None, true),
+            ("?w1?d?w2", None, false),
+            ("?w2?s", None, true),
+        ] {
+            let new_gen = |buffer_size: Option<usize>| {
+                let mut word_gen = get_word_generator(mask, minlen, None, &[], &wordlists).unwrap();
+                word_gen.set_hex_output(hex_output);
+                if let Some(buffer_size) = buffer_size {
+                    word_gen.set_buffer_size(buffer_size);
+                }
+                let mut buf: Vec<u8> = Vec::new();
+                {
+                    let mut cur: Box<dyn Write> = Box::new(Cursor::new(&mut buf));
+                    word_gen.gen(&mut cur).unwrap();
+                }
+                buf
+            };
+            let expected = new_gen(None);
+            // smaller than a word, not a multiple of the words length and heap allocated
+            for buffer_size in [1, 7, 100, 8191, 8193, 1 << 20] {
+                assert!(
+                    new_gen(Some(buffer_size)) == expected,
+                    "{} {}",
+                    mask,
+                    buffer_size
+                );
+            }
+        }
+    }
+
+    /// counts the write calls reaching the output
+    struct CallsWriter<'a> {
+        out: &'a mut Vec<u8>,
+        calls: &'a mut usize,
+    }
+
+    impl Write for CallsWriter<'_> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            *self.calls += 1;
+            self.out.write(buf)
+        }
+
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize, Error> {
+            *self.calls += 1;
+            self.out.write_vectored(bufs)
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_gen_vectored_lengths() {
+        for (mask, buffer_size, expected_calls) in [
+            // every length fits a single batch, all written at the end
+            ("?d?d?d", 8192, 1),
+            // the lengths 1 and 2 are written with the first batch of length 3
+            ("?d?d?d", 1000, 4),
+            // a write per word, the last word of every length waits for the next write
+            ("?d?d?d", 1, 9 + 99 + 999 + 1),
+        ] {
+            let mut word_gen = get_word_generator(mask, Some(1), None, &[], &[]).unwrap();
+            let expected =
+                gen_to_string(get_word_generator(mask, Some(1), None, &[], &[]).unwrap());
+            word_gen.set_buffer_size(buffer_size);
+            let mut buf = vec![];
+            let mut calls = 0;
+            {
+                let mut out: Box<dyn Write> = Box::new(CallsWriter {
+                    out: &mut buf,
+                    calls: &mut calls,
+                });
+                word_gen.gen(&mut out).unwrap();
+            }
+            assert_eq!(String::from_utf8(buf).unwrap(), expected);
+            assert_eq!(calls, expected_calls, "{} {}", mask, buffer_size);
+        }
+    }
+
+    /// the mask and the wordlists paths are dropped when returning
+    fn names_digits_generator(digits: usize) -> Box<dyn WordGenerator> {
+        let mask = format!("?w1{}", "?d".repeat(digits));
+        let wordlist = wordlist_fname("wordlist1.txt")
+            .to_str()
+            .unwrap()
+            .to_string();
+        get_word_generator(&mask, None, None, &[], &[wordlist.as_str()]).unwrap()
+    }
+
+    #[test]
+    fn test_generator_returned_from_function() {
+        let wordlist = wordlist_fname("wordlist1.txt");
+        let wordlists = vec![wordlist.to_str().unwrap()];
+        let generators = vec![names_digits_generator(1), names_digits_generator(2)];
+        for (word_gen, mask) in generators.into_iter().zip(["?w1?d", "?w1?d?d"]) {
+            let expected = get_word_generator(mask, None, None, &[], &wordlists).unwrap();
+            assert_eq!(gen_to_string(word_gen), gen_to_string(expected));
+        }
+    }
+
+    fn gen_to_string<'a>(w: Box<dyn WordGenerator + 'a>) -> String {
+        let mut buf: Vec<u8> = Vec::new();
+        {
+            let mut cur: Box<dyn Write> = Box::new(Cursor::new(&mut buf));
+            w.gen(&mut cur).unwrap();
+        }
+        String::from_utf8(buf).unwrap()
+    }
+
+    /// asserts `index_of` of every generated word is the position of its first occurrence
+    fn assert_index_of_brute_force(word_gen: &dyn WordGenerator) -> usize {
+        let mut buf: Vec<u8> = Vec::new();
+        {
+            let mut cur: Box<dyn Write> = Box::new(Cursor::new(&mut buf));
+            word_gen.gen(&mut cur).unwrap();
+        }
+        let mut first_index = HashMap::new();
+        for (idx, word) in buf
+            .split(|&c| c == b'\n')
+            .filter(|w| !w.is_empty())
+            .enumerate()
+        {
+            first_index.entry(word).or_insert(idx);
+        }
+        for (word, idx) in first_index.iter() {
+            let rank = word_gen.index_of(word).unwrap();
+            assert_eq!(rank.index, idx.to_biguint().unwrap(), "{:?}", word);
+        }
+        first_index.len()
+    }
+
+    #[test]
+    fn test_output_bytes() {
+        let wordlist1 = wordlist_fname("wordlist1.txt");
+        let wordlists = vec![wordlist1.to_str().unwrap()];
+        let charsets = vec![
+            CustomCharset::Tokens("a|bcd|ef"),
+            CustomCharset::Chars("xy"),
+        ];
+        let cases = vec![
+            ("?d?l", None),
+            ("?d?l?2", Some(1)),
+            ("?w1?d", None),
+            ("?1-?w1?2", None),
+            ("?w1?d?r1", None),
+            ("?1?2?r1?r2", None),
+        ];
+        for (mask, minlen) in cases {
+            let word_gen = get_word_generator(mask, minlen, None, &charsets, &wordlists).unwrap();
+            let output_bytes = word_gen.output_bytes();
+            let out_len = gen_to_string(word_gen).len();
+            assert_eq!(output_bytes, out_len.to_biguint().unwrap(), "{}", mask);
+        }
+    }
+
+    #[test]
+    fn test_index_of_charsets() {
+        let charsets = vec![CustomCharset::Chars("ba")];
+        let word_gen = get_word_generator("?1?d?1", Some(1), None, &charsets, &[]).unwrap();
+        assert_eq!(assert_index_of_brute_force(word_gen.as_ref()), 2 + 20 + 40);
+        assert_eq!(
+            word_gen.index_of(b"b0a").unwrap().index,
+            (2 + 20 + 20).to_biguint().unwrap()
+        );
+        for word in [&b""[..], b"c", b"a0c", b"a0a0", b"aa"] {
+            assert_eq!(word_gen.index_of(word), None, "{:?}", word);
+        }
+    }
+
+    #[test]
+    fn test_index_of_wordlists() {
+        let wordlist1 = wordlist_fname("wordlist1.txt");
+        let wordlists = vec![wordlist1.to_str().unwrap()];
+        let word_gen = get_word_generator("?w1?d", None, None, &[], &wordlists).unwrap();
+        assert_eq!(assert_index_of_brute_force(word_gen.as_ref()), 100);
+        let rank = word_gen.index_of(b"qwerty7").unwrap();
+        assert_eq!(rank.wordlist_words, vec![b"qwerty".to_vec()]);
+        assert_eq!(word_gen.index_of(b"qwerty"), None);
+
+        // abb is generated twice - a|bb and ab|b
+        let charsets = vec![CustomCharset::Tokens("a|ab"), CustomCharset::Tokens("b|bb")];
+        let word_gen = get_word_generator("?1?2?d", None, None, &charsets, &[]).unwrap();
+        assert_eq!(assert_index_of_brute_force(word_gen.as_ref()), 30);
+        let rank = word_gen.index_of(b"abb3").unwrap();
+        assert_eq!(rank.index, 13.to_biguint().unwrap());
+        assert_eq!(rank.wordlist_words, vec![b"a".to_vec(), b"bb".to_vec()]);
+    }
+
+    #[test]
+    fn test_word_at() {
+        let wordlist1 = wordlist_fname("wordlist1.txt");
+        let wordlists = vec![wordlist1.to_str().unwrap()];
+        let charsets = vec![CustomCharset::Chars("ba"), CustomCharset::Tokens("a|ab")];
+        for (mask, minlen, wordlists) in [
+            ("?1?d?1", Some(1), &[][..]),
+            ("?w1?d", None, &wordlists[..]),
+            ("?2x?1", None, &[][..]),
+            ("?w1?d?r1?r2", None, &wordlists[..]),
+        ] {
+            let word_gen = get_word_generator(mask, minlen, None, &charsets, wordlists).unwrap();
+            let mut buf: Vec<u8> = Vec::new();
+            {
+                let mut cur: Box<dyn Write> = Box::new(Cursor::new(&mut buf));
+                word_gen.gen(&mut cur).unwrap();
+            }
+            let mut count = 0;
+            for (idx, word) in buf
+                .split(|&c| c == b'\n')
+                .filter(|w| !w.is_empty())
+                .enumerate()
+            {
+                assert_eq!(
+                    word_gen.word_at(idx as u64).unwrap(),
+                    word,
+                    "{} {}",
+                    mask,
+                    idx
+                );
+                count += 1;
+            }
+            assert_eq!(word_gen.word_at(count), None, "{}", mask);
+        }
+
+        // the interleaved lengths aren't supported
+        let mut word_gen = get_word_generator("?d?d", Some(1), None, &[], &[]).unwrap();
+        word_gen.set_length_order(LengthOrder::Interleave(3));
+        assert_eq!(word_gen.word_at(0), None);
+    }
+
     fn assert_gen<'a>(w: Box<dyn WordGenerator + 'a>, fname: &str) -> String {
         let mut buf: Vec<u8> = Vec::new();
         {
@@ -495,7 +2413,7 @@ mod tests {
 
     #[test]
     fn test_gen_stats() {
-        let custom_charsets = vec!["abcd", "01"];
+        let custom_charsets = vec![CustomCharset::Chars("abcd"), CustomCharset::Chars("01")];
         let combinations = vec![
             ("?d?s?u?l?a?b", "5368197120", None, None),
             ("?d?d?d?d?d?d?d?d", "111111110", Some(1), Some(8)),
@@ -523,4 +2441,43 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_hashcat_keyspace() {
+        // as reported by `hashcat -m 0 -a 3 --keyspace` (-1 0123456789abcdef for ?1)
+        let hex = [CustomCharset::Chars("0123456789abcdef")];
+        let cases: [(&str, u64); 10] = [
+            ("?d", 1),
+            ("?a?a?a?a", 857375),
+            ("?l?l?l?l?l?l", 456976),
+            ("?d?d?l?l?l?l?l", 2600),
+            ("?d?d?d?d?d?d?d?d", 10000),
+            ("?u?l?l?l?l?l?d?d", 11881376),
+            ("?a?a?a?a?a?a?a?a", 7737809375),
+            ("pass?d?d?d?d", 1),
+            ("?1?1?1?1?1?1?1", 4096),
+            ("?b?b?b?b?b?b?b", 4294967296),
+        ];
+        for (mask, expected) in cases {
+            assert_eq!(
+                hashcat_keyspace(mask, &hex).unwrap(),
+                expected.to_biguint().unwrap(),
+                "{}",
+                mask
+            );
+        }
+        assert!(matches!(
+            hashcat_keyspace("?w1?d", &[]),
+            Err(CrackenError::Unsupported(_))
+        ));
+        assert!(matches!(
+            hashcat_keyspace("?d?r1", &[]),
+            Err(CrackenError::Unsupported(_))
+        ));
+        assert!(matches!(
+            hashcat_keyspace("?1?d", &[CustomCharset::Tokens("ab|cd")]),
+            Err(CrackenError::Unsupported(_))
+        ));
+        assert!(hashcat_keyspace("?1", &[]).is_err());
+    }
 }
@@ -1,4 +1,30 @@
-use std::io::{BufRead, BufReader, Error, Read};
+use std::cell::Cell;
+use std::fmt::Display;
+use std::io::{BufRead, BufReader, Error, ErrorKind, IoSlice, Read, Write};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use num_bigint::BigUint;
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+use crate::{BoxResult, BUFFER_SIZE, MAX_WORD_SIZE};
+
+/// prefix of hashcat style hex encoded words (`$HEX[6162630a]`)
+pub const HEX_PREFIX: &[u8] = b"$HEX[";
+const HEX_SUFFIX: &[u8] = b"]";
+const HEX_DIGITS: &[u8] = b"0123456789abcdef";
+
+/// size of the buffer needed by `encode_hex_word` for any word
+pub const HEX_WORD_SIZE: usize = hex_word_len(MAX_WORD_SIZE);
+
+/// returns the length `encode_hex_word` encodes a word of `len` bytes to, with the newline
+pub const fn hex_word_len(len: usize) -> usize {
+    HEX_PREFIX.len() + 2 * len + HEX_SUFFIX.len() + 1
+}
 
 pub struct RawFileReader<R> {
     reader: BufReader<R>,
@@ -22,7 +48,10 @@ impl<R: Read> Iterator for RawFileReader<R> {
         match self.reader.read_until(b'\n', &mut self.buffer) {
             Ok(0) => None,
             Ok(_) => {
-                self.buffer.pop();
+                // the last line may have no newline
+                if self.buffer.last() == Some(&b'\n') {
+                    self.buffer.pop();
+                }
                 Some(Ok(self.buffer.to_vec()))
             }
             Err(e) => Some(Err(e)),
@@ -30,11 +59,1094 @@ impl<R: Read> Iterator for RawFileReader<R> {
     }
 }
 
+/// writes only the lines for which `predicate` returns true, lines are passed to the
+/// predicate without the newline
+pub struct LineFilterWriter<W: Write, F: FnMut(&[u8]) -> bool> {
+    inner: W,
+    predicate: F,
+    /// incomplete line at the end of the last write
+    partial: Vec<u8>,
+    out: Vec<u8>,
+}
+
+impl<W: Write, F: FnMut(&[u8]) -> bool> LineFilterWriter<W, F> {
+    pub fn new(inner: W, predicate: F) -> LineFilterWriter<W, F> {
+        LineFilterWriter {
+            inner,
+            predicate,
+            partial: vec![],
+            out: Vec::with_capacity(BUFFER_SIZE),
+        }
+    }
+}
+
+impl<W: Write, F: FnMut(&[u8]) -> bool> Write for LineFilterWriter<W, F> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let mut rest = buf;
+        while let Some(pos) = rest.iter().position(|&c| c == b'\n') {
+            let line = if self.partial.is_empty() {
+                &rest[..pos]
+            } else {
+                self.partial.extend_from_slice(&rest[..pos]);
+                &self.partial[..]
+            };
+            if (self.predicate)(line) {
+                self.out.extend_from_slice(line);
+                self.out.push(b'\n');
+            }
+            self.partial.clear();
+            rest = &rest[pos + 1..];
+        }
+        self.partial.extend_from_slice(rest);
+
+        self.inner.write_all(&self.out)?;
+        self.out.clear();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if !self.partial.is_empty() {
+            if (self.predicate)(&self.partial) {
+                self.inner.write_all(&self.partial)?;
+            }
+            self.partial.clear();
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write, F: FnMut(&[u8]) -> bool> Drop for LineFilterWriter<W, F> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// how an `IndexedWriter` writes every word with its index in the generation order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexedFormat {
+    /// a json line `{"pw":"abc12","len":5,"index":1234}` of the word (under `hex` instead of
+    /// `pw` if it isn't valid utf-8), its length in bytes and its index
+    Jsonl,
+    /// the decimal index and a tab before the word, `1234<TAB>abc12`
+    Tab,
+}
+
+/// writes every line with its index in the generation order. `index` is the index of the next
+/// word, shared so it can be set when a new mask starts
+pub struct IndexedWriter<W: Write> {
+    inner: W,
+    format: IndexedFormat,
+    index: Rc<Cell<u64>>,
+    /// incomplete line at the end of the last write
+    partial: Vec<u8>,
+    out: Vec<u8>,
+}
+
+impl<W: Write> IndexedWriter<W> {
+    pub fn new(inner: W, format: IndexedFormat, index: Rc<Cell<u64>>) -> IndexedWriter<W> {
+        IndexedWriter {
+            inner,
+            format,
+            index,
+            partial: vec![],
+            out: Vec::with_capacity(BUFFER_SIZE),
+        }
+    }
+}
+
+/// a `--format jsonl` line, the word is written as `hex` if it isn't valid utf-8
+#[derive(Serialize)]
+struct JsonlWord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pw: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hex: Option<String>,
+    len: usize,
+    index: u64,
+}
+
+/// writes the line of `word` and its index, see `IndexedFormat`
+fn write_indexed_word<W: Write>(
+    out: &mut W,
+    word: &[u8],
+    index: u64,
+    format: IndexedFormat,
+) -> Result<(), Error> {
+    if format == IndexedFormat::Tab {
+        write!(out, "{}\t", index)?;
+        out.write_all(word)?;
+        return out.write_all(b"\n");
+    }
+    let pw = std::str::from_utf8(word).ok();
+    let line = JsonlWord {
+        pw,
+        hex: pw.is_none().then(|| to_hex(word)),
+        len: word.len(),
+        index,
+    };
+    serde_json::to_writer(&mut *out, &line)?;
+    out.write_all(b"\n")
+}
+
+impl<W: Write> Write for IndexedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let mut rest = buf;
+        while let Some(pos) = rest.iter().position(|&c| c == b'\n') {
+            let line = if self.partial.is_empty() {
+                &rest[..pos]
+            } else {
+                self.partial.extend_from_slice(&rest[..pos]);
+                &self.partial[..]
+            };
+            write_indexed_word(&mut self.out, line, self.index.get(), self.format)?;
+            self.index.set(self.index.get() + 1);
+            self.partial.clear();
+            rest = &rest[pos + 1..];
+        }
+        self.partial.extend_from_slice(rest);
+
+        self.inner.write_all(&self.out)?;
+        self.out.clear();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if !self.partial.is_empty() {
+            write_indexed_word(
+                &mut self.inner,
+                &self.partial,
+                self.index.get(),
+                self.format,
+            )?;
+            self.index.set(self.index.get() + 1);
+            self.partial.clear();
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for IndexedWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// number of bytes of the `IndexedFormat::Tab` indices of `count` words from index `start`,
+/// tabs included
+pub fn index_bytes(start: u64, count: &BigUint) -> BigUint {
+    let end = BigUint::from(start) + count;
+    let mut total = count.clone();
+    let mut from = BigUint::from(start);
+    // the indices of every number of digits, 0 to 9, 10 to 99...
+    let mut digits_end = BigUint::from(10u32);
+    let mut digits = 1u64;
+    while from < end {
+        if from < digits_end {
+            let to = if end < digits_end {
+                end.clone()
+            } else {
+                digits_end.clone()
+            };
+            total += (to.clone() - &from) * digits;
+            from = to;
+        }
+        digits_end *= 10u32;
+        digits += 1;
+    }
+    total
+}
+
+/// number of bytes and lines written through a `CountingWriter`
+#[derive(Debug, Default)]
+pub struct WriteCounts {
+    pub bytes: Cell<u64>,
+    pub lines: Cell<u64>,
+}
+
+/// counts the bytes and lines actually written to the inner writer
+pub struct CountingWriter<W: Write> {
+    inner: W,
+    counts: Rc<WriteCounts>,
+}
+
+impl<W: Write> CountingWriter<W> {
+    pub fn new(inner: W, counts: Rc<WriteCounts>) -> CountingWriter<W> {
+        CountingWriter { inner, counts }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let written = self.inner.write(buf)?;
+        let lines = buf[..written].iter().filter(|&&c| c == b'\n').count();
+        self.counts
+            .bytes
+            .set(self.counts.bytes.get() + written as u64);
+        self.counts
+            .lines
+            .set(self.counts.lines.get() + lines as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+/// writes whole lines until the next one would exceed `max_bytes`, then sets `exhausted` and
+/// fails every write. the first `free_lines` lines (e.g. the skipped ones) are not counted
+pub struct BudgetWriter<W: Write> {
+    inner: W,
+    remaining: u64,
+    free_lines: u64,
+    exhausted: Rc<Cell<bool>>,
+}
+
+impl<W: Write> BudgetWriter<W> {
+    pub fn new(
+        inner: W,
+        max_bytes: u64,
+        free_lines: u64,
+        exhausted: Rc<Cell<bool>>,
+    ) -> BudgetWriter<W> {
+        BudgetWriter {
+            inner,
+            remaining: max_bytes,
+            free_lines,
+            exhausted,
+        }
+    }
+}
+
+impl<W: Write> Write for BudgetWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if self.exhausted.get() {
+            return Err(Error::other("byte budget exhausted"));
+        }
+        let mut counted_from = 0;
+        while self.free_lines > 0 && counted_from < buf.len() {
+            match buf[counted_from..].iter().position(|&c| c == b'\n') {
+                Some(pos) => {
+                    counted_from += pos + 1;
+                    self.free_lines -= 1;
+                }
+                None => counted_from = buf.len(),
+            }
+        }
+        let counted = &buf[counted_from..];
+        if counted.len() as u64 <= self.remaining {
+            self.inner.write_all(buf)?;
+            self.remaining -= counted.len() as u64;
+            return Ok(buf.len());
+        }
+        // the lines which fit, the rest of the buffer is dropped
+        let fit = &counted[..self.remaining as usize];
+        let end = match fit.iter().rposition(|&c| c == b'\n') {
+            Some(pos) => counted_from + pos + 1,
+            None => counted_from,
+        };
+        self.inner.write_all(&buf[..end])?;
+        self.remaining = 0;
+        self.exhausted.set(true);
+        Err(Error::other("byte budget exhausted"))
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+/// fails every write once `stop` is set, whole buffers are either written or refused
+pub struct StopWriter<W: Write> {
+    inner: W,
+    stop: &'static AtomicBool,
+}
+
+impl<W: Write> StopWriter<W> {
+    pub fn new(inner: W, stop: &'static AtomicBool) -> StopWriter<W> {
+        StopWriter { inner, stop }
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        if self.stop.load(Ordering::SeqCst) {
+            return Err(Error::other("stopped"));
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for StopWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.check()?;
+        self.inner.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.check()?;
+        self.inner.write_all(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize, Error> {
+        self.check()?;
+        self.inner.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+/// fails every write once `deadline` passed and sets `expired`, whole buffers are either
+/// written or refused
+pub struct TimeoutWriter<W: Write> {
+    inner: W,
+    deadline: Instant,
+    expired: Rc<Cell<bool>>,
+}
+
+impl<W: Write> TimeoutWriter<W> {
+    pub fn new(inner: W, deadline: Instant, expired: Rc<Cell<bool>>) -> TimeoutWriter<W> {
+        TimeoutWriter {
+            inner,
+            deadline,
+            expired,
+        }
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        if self.expired.get() || Instant::now() >= self.deadline {
+            self.expired.set(true);
+            return Err(Error::other("timed out"));
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for TimeoutWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.check()?;
+        self.inner.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.check()?;
+        self.inner.write_all(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize, Error> {
+        self.check()?;
+        self.inner.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+/// writes at most `rate` lines per second (`--max-rate`) with a token bucket holding up to a
+/// second of lines. a whole buffer waits for the tokens of its lines before it is written, so
+/// the generation isn't slowed down word by word. the first `free_lines` lines (e.g. the
+/// skipped ones) are not counted and a wait ends early once `stop` is set or `deadline` passed,
+/// for the next writer to refuse the buffer
+pub struct RateLimitWriter<W: Write> {
+    inner: W,
+    rate: u64,
+    free_lines: u64,
+    stop: &'static AtomicBool,
+    deadline: Option<Instant>,
+    tokens: f64,
+    /// when the tokens were last added, the bucket starts empty at the first write
+    refilled: Option<Instant>,
+}
+
+impl<W: Write> RateLimitWriter<W> {
+    pub fn new(
+        inner: W,
+        rate: u64,
+        free_lines: u64,
+        stop: &'static AtomicBool,
+        deadline: Option<Instant>,
+    ) -> RateLimitWriter<W> {
+        RateLimitWriter {
+            inner,
+            rate,
+            free_lines,
+            stop,
+            deadline,
+            tokens: 0.0,
+            refilled: None,
+        }
+    }
+
+    /// waits until the bucket has a token for every counted line of `buf` and takes them
+    fn wait(&mut self, buf: &[u8]) {
+        let lines = buf.iter().filter(|&&c| c == b'\n').count() as u64;
+        let free = lines.min(self.free_lines);
+        self.free_lines -= free;
+        let lines = (lines - free) as f64;
+        if lines == 0.0 {
+            return;
+        }
+        let rate = self.rate as f64;
+        let now = Instant::now();
+        let refilled = *self.refilled.get_or_insert(now);
+        // a buffer of more than a second of lines waits for all of them
+        let capacity = rate.max(lines);
+        self.tokens = (self.tokens + (now - refilled).as_secs_f64() * rate).min(capacity);
+        self.refilled = Some(now);
+        if self.tokens >= lines {
+            self.tokens -= lines;
+            return;
+        }
+        let mut until = now + Duration::from_secs_f64((lines - self.tokens) / rate);
+        if let Some(deadline) = self.deadline {
+            until = until.min(deadline);
+        }
+        loop {
+            let now = Instant::now();
+            if now >= until || self.stop.load(Ordering::SeqCst) {
+                break;
+            }
+            // short sleeps so ctrl-c isn't delayed by a long wait
+            thread::sleep((until - now).min(Duration::from_millis(100)));
+        }
+        // the tokens added while waiting are all taken by the buffer
+        self.tokens = 0.0;
+        self.refilled = Some(Instant::now());
+    }
+}
+
+impl<W: Write> Write for RateLimitWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        // the lines are counted once, so the buffer is written whole
+        self.wait(buf);
+        self.inner.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+/// writes every buffer to all the named `sinks`, a failing sink fails the write with its name.
+/// with `best_effort` a failing sink is dropped instead with a warning to `warnings`, while
+/// another sink is left
+pub struct TeeWriter<S: Write> {
+    sinks: Vec<(String, Box<dyn Write>)>,
+    best_effort: bool,
+    warnings: S,
+}
+
+impl<S: Write> TeeWriter<S> {
+    pub fn new(sinks: Vec<(String, Box<dyn Write>)>, best_effort: bool, warnings: S) -> Self {
+        TeeWriter {
+            sinks,
+            best_effort,
+            warnings,
+        }
+    }
+
+    /// runs `op` on every sink in turn
+    fn each<F>(&mut self, mut op: F) -> Result<(), Error>
+    where
+        F: FnMut(&mut dyn Write) -> Result<(), Error>,
+    {
+        let mut idx = 0;
+        while idx < self.sinks.len() {
+            let e = match op(self.sinks[idx].1.as_mut()) {
+                Ok(()) => {
+                    idx += 1;
+                    continue;
+                }
+                Err(e) => e,
+            };
+            let name = &self.sinks[idx].0;
+            if !self.best_effort || self.sinks.len() == 1 {
+                // even a broken pipe, the other sinks would miss the rest of the words
+                return Err(Error::other(format!("{}: {}", name, e)));
+            }
+            let _ = writeln!(self.warnings, "stopped writing to {}: {}", name, e);
+            self.sinks.remove(idx);
+        }
+        Ok(())
+    }
+}
+
+impl<S: Write> Write for TeeWriter<S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        // every sink gets the whole buffer, a partial write can't be repeated to only some
+        self.each(|sink| sink.write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.each(|sink| sink.flush())
+    }
+}
+
+/// prints the progress of the generation to `status` every `interval` (`--status`), updating a
+/// single line on a terminal. the last word is taken from every written buffer, never copied
+/// word by word, and a last status is printed when dropped
+pub struct StatusWriter<W: Write, S: Write> {
+    inner: W,
+    status: S,
+    tty: bool,
+    /// none when the status is only printed on request
+    interval: Option<Duration>,
+    total: BigUint,
+    /// counts the total at the first status when it isn't known upfront
+    count_total: Option<Box<dyn FnOnce() -> BigUint>>,
+    /// set (e.g. by a signal handler) to print a status at the next write
+    requested: Option<Arc<AtomicBool>>,
+    /// the words generated so far, including the skipped ones
+    done: Rc<WriteCounts>,
+    /// when the first buffer was written and the words done then
+    started: Option<(Instant, u64)>,
+    /// when the previous status was printed and the words done then
+    previous: Option<(Instant, u64)>,
+    last_word: Vec<u8>,
+}
+
+impl<W: Write, S: Write> StatusWriter<W, S> {
+    pub fn new(
+        inner: W,
+        status: S,
+        tty: bool,
+        interval: Duration,
+        total: BigUint,
+        done: Rc<WriteCounts>,
+    ) -> StatusWriter<W, S> {
+        StatusWriter {
+            inner,
+            status,
+            tty,
+            interval: Some(interval),
+            total,
+            count_total: None,
+            requested: None,
+            done,
+            started: None,
+            previous: None,
+            last_word: vec![],
+        }
+    }
+
+    /// prints a status line only when `requested` is set and no last status, the total is
+    /// counted by `count_total` at the first status so a run never asked doesn't count it
+    pub fn on_request(
+        inner: W,
+        status: S,
+        requested: Arc<AtomicBool>,
+        count_total: Box<dyn FnOnce() -> BigUint>,
+        done: Rc<WriteCounts>,
+    ) -> StatusWriter<W, S> {
+        StatusWriter {
+            inner,
+            status,
+            tty: false,
+            interval: None,
+            total: BigUint::default(),
+            count_total: Some(count_total),
+            requested: Some(requested),
+            done,
+            started: None,
+            previous: None,
+            last_word: vec![],
+        }
+    }
+
+    /// also prints a status at the next write once `requested` is set
+    pub fn with_request(mut self, requested: Arc<AtomicBool>) -> StatusWriter<W, S> {
+        self.requested = Some(requested);
+        self
+    }
+
+    fn print_status(&mut self, now: Instant) -> Result<(), Error> {
+        if let Some(count_total) = self.count_total.take() {
+            self.total = count_total();
+        }
+        let done = self.done.lines.get();
+        let (started, started_done) = self.started.unwrap_or((now, done));
+        let (previous, previous_done) = self.previous.unwrap_or((started, started_done));
+        let line = format_status(
+            done,
+            &self.total,
+            words_rate(done - previous_done, now - previous),
+            words_rate(done - started_done, now - started),
+            &self.last_word,
+        );
+        if self.tty {
+            // back to the start of the line, clearing the rest of the previous status
+            write!(self.status, "\r{}\x1b[K", line)?;
+        } else {
+            writeln!(self.status, "{}", line)?;
+        }
+        self.status.flush()?;
+        self.previous = Some((now, done));
+        Ok(())
+    }
+}
+
+impl<W: Write, S: Write> Write for StatusWriter<W, S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let written = self.inner.write(buf)?;
+        let words = &buf[..written];
+        if let Some(end) = words.iter().rposition(|&c| c == b'\n') {
+            let start = words[..end].iter().rposition(|&c| c == b'\n');
+            self.last_word.clear();
+            self.last_word
+                .extend_from_slice(&words[start.map_or(0, |pos| pos + 1)..end]);
+        }
+        let now = Instant::now();
+        let since = match (self.started, self.previous) {
+            (_, Some((previous, _))) => previous,
+            (Some((started, _)), None) => started,
+            (None, None) => {
+                // the rates count from the first write, after the wordlists are loaded
+                self.started = Some((now, self.done.lines.get()));
+                return Ok(written);
+            }
+        };
+        let requested = match &self.requested {
+            Some(requested) => requested.swap(false, Ordering::SeqCst),
+            None => false,
+        };
+        if requested || matches!(self.interval, Some(interval) if now - since >= interval) {
+            // a status which can't be printed doesn't stop the generation
+            let _ = self.print_status(now);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write, S: Write> Drop for StatusWriter<W, S> {
+    fn drop(&mut self) {
+        if self.started.is_some() && self.interval.is_some() {
+            let _ = self.print_status(Instant::now());
+            if self.tty {
+                let _ = writeln!(self.status);
+            }
+        }
+    }
+}
+
+/// words per second, 0 for an empty duration
+fn words_rate(words: u64, elapsed: Duration) -> f64 {
+    match elapsed.as_secs_f64() {
+        secs if secs > 0.0 => words as f64 / secs,
+        _ => 0.0,
+    }
+}
+
+/// formats a rate with a k, M, G or T suffix like the rates `parse_rate` reads (e.g. 1.25M)
+fn format_rate(rate: f64) -> String {
+    let units = [(1e12, "T"), (1e9, "G"), (1e6, "M"), (1e3, "k")];
+    match units.iter().find(|(unit, _)| rate >= *unit) {
+        Some((unit, suffix)) => format!("{:.2}{}", rate / unit, suffix),
+        None => format!("{:.0}", rate),
+    }
+}
+
+/// the `--status` line, e.g.
+/// `progress: 2500/10000 (25.00%), 1.20k/s (avg 1.00k/s), eta 6.2 seconds, last: 2499`
+pub fn format_status(done: u64, total: &BigUint, rate: f64, avg: f64, last_word: &[u8]) -> String {
+    let total_f64 = total.to_string().parse::<f64>().unwrap_or(f64::INFINITY);
+    let percent = if total_f64 > 0.0 {
+        (done as f64 / total_f64 * 100.0).min(100.0)
+    } else {
+        100.0
+    };
+    let remaining = total_f64 - done as f64;
+    let eta = if remaining <= 0.0 {
+        "done".to_string()
+    } else if rate > 0.0 && (remaining / rate) < u64::MAX as f64 {
+        format!(
+            "eta {}",
+            format_duration(&BigUint::from((remaining / rate) as u64))
+        )
+    } else {
+        "eta unknown".to_string()
+    };
+    format!(
+        "progress: {}/{} ({:.2}%), {}/s (avg {}/s), {}, last: {}",
+        done,
+        total,
+        percent,
+        format_rate(rate),
+        format_rate(avg),
+        eta,
+        String::from_utf8_lossy(last_word)
+    )
+}
+
+/// writes all the `bufs` with as few `write_vectored` calls as `out` allows. writers without
+/// vectored support write the first non empty slice of every call, which still ends up writing
+/// everything one slice at a time
+pub fn write_all_vectored<W: Write + ?Sized>(
+    out: &mut W,
+    mut bufs: &mut [IoSlice<'_>],
+) -> Result<(), Error> {
+    // skip the leading empty slices so a zero length write is an error
+    IoSlice::advance_slices(&mut bufs, 0);
+    while !bufs.is_empty() {
+        match out.write_vectored(bufs) {
+            Ok(0) => {
+                return Err(Error::new(
+                    ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ))
+            }
+            Ok(written) => IoSlice::advance_slices(&mut bufs, written),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// small deterministic pseudo random generator (splitmix64) - the same seed always gives the
+/// same sequence across platforms and versions
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    /// seeds the generator from the current time
+    pub fn from_time() -> SplitMix64 {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        SplitMix64::new(nanos)
+    }
+
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// returns a uniform float in [0, 1)
+    #[inline]
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// which words are written as `$HEX[...]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HexWords {
+    /// the words which can't be written as a plain line - they contain control chars (e.g.
+    /// newline), are not valid utf-8 or look like a hex encoded word themselves
+    Unprintable,
+    /// the words with bytes outside of printable ascii, like hashcat writes them in its
+    /// potfiles. words already encoded are left as they are
+    Hashcat,
+}
+
+/// returns true if `word` is written as `$HEX[...]` (see `HexWords`)
+pub fn needs_hex(word: &[u8], words: HexWords) -> bool {
+    match words {
+        HexWords::Unprintable => {
+            word.iter().any(|&c| c < 0x20 || c == 0x7f)
+                || word.starts_with(HEX_PREFIX)
+                || std::str::from_utf8(word).is_err()
+        }
+        HexWords::Hashcat => word.iter().any(|&c| !(0x20..0x7f).contains(&c)),
+    }
+}
+
+/// encodes `word` as `$HEX[...]` followed by a newline into `out`, returns the encoded length
+pub fn encode_hex_word(word: &[u8], out: &mut [u8]) -> usize {
+    out[..HEX_PREFIX.len()].copy_from_slice(HEX_PREFIX);
+    let mut pos = HEX_PREFIX.len();
+    for &c in word {
+        out[pos] = HEX_DIGITS[(c >> 4) as usize];
+        out[pos + 1] = HEX_DIGITS[(c & 0xf) as usize];
+        pos += 2;
+    }
+    out[pos] = HEX_SUFFIX[0];
+    out[pos + 1] = b'\n';
+    pos + 2
+}
+
+/// decodes a `$HEX[...]` word to its raw bytes, other words are returned as is
+pub fn decode_hex_word(word: Vec<u8>) -> Result<Vec<u8>, String> {
+    if !word.starts_with(HEX_PREFIX) || !word.ends_with(HEX_SUFFIX) {
+        return Ok(word);
+    }
+
+    let hex = &word[HEX_PREFIX.len()..word.len() - HEX_SUFFIX.len()];
+    if hex.len() & 1 != 0 {
+        return Err(format!(
+            "odd number of hex digits in {}",
+            String::from_utf8_lossy(&word)
+        ));
+    }
+
+    let digit = |c: u8| match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(format!(
+            "invalid hex digit '{}' in {}",
+            c as char,
+            String::from_utf8_lossy(&word)
+        )),
+    };
+    hex.chunks(2)
+        .map(|pair| Ok(digit(pair[0])? << 4 | digit(pair[1])?))
+        .collect()
+}
+
+/// returns log2 of `n`, precise for numbers larger than u64 too
+pub fn log2_biguint(n: &BigUint) -> f64 {
+    // keep the 64 most significant bits, the rest is below f64 precision anyway
+    let shift = n.bits().saturating_sub(64);
+    let top = (n >> shift).to_u64_digits().first().copied().unwrap_or(0);
+    (top as f64).log2() + shift as f64
+}
+
+const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// parses a rate of candidates per second with an optional k, M, G or T suffix, optionally
+/// followed by H or H/s (e.g. 50GH, 1.5MH/s or 200000)
+pub fn parse_rate(rate: &str) -> BoxResult<u64> {
+    let value = rate.strip_suffix("/s").unwrap_or(rate);
+    let value = value.strip_suffix('H').unwrap_or(value);
+    let (value, multiplier) = match value.as_bytes().last() {
+        Some(b'k') | Some(b'K') => (&value[..value.len() - 1], 1e3),
+        Some(b'M') => (&value[..value.len() - 1], 1e6),
+        Some(b'G') => (&value[..value.len() - 1], 1e9),
+        Some(b'T') => (&value[..value.len() - 1], 1e12),
+        _ => (value, 1.0),
+    };
+    match value.parse::<f64>() {
+        Ok(value) if value.is_finite() && value * multiplier >= 1.0 => {
+            Ok((value * multiplier).round() as u64)
+        }
+        _ => bail!(
+            "invalid rate '{}': expected candidates per second with an optional k, M, G or T suffix (e.g. 50GH)",
+            rate
+        ),
+    }
+}
+
+/// parses a number of words, exact even in scientific notation (e.g. 1e13 or 2.5e12)
+pub fn parse_keyspace(keyspace: &str) -> BoxResult<BigUint> {
+    let invalid = || -> BoxResult<BigUint> {
+        bail!(
+            "invalid keyspace '{}': expected a positive integer, optionally in scientific notation (e.g. 1e13)",
+            keyspace
+        )
+    };
+    let (mantissa, exp) = match keyspace.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => match exp.parse::<u32>() {
+            Ok(exp) => (mantissa, exp as usize),
+            Err(_) => return invalid(),
+        },
+        None => (keyspace, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let mut digits = format!("{}{}", int, frac);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return invalid();
+    }
+    // the fraction digits beyond the exponent must be zeros
+    let mut zeros = exp;
+    if frac.len() > exp {
+        let extra = frac.len() - exp;
+        if digits.bytes().rev().take(extra).any(|b| b != b'0') {
+            return invalid();
+        }
+        digits.truncate(digits.len() - extra);
+        zeros = 0;
+    } else {
+        zeros -= frac.len();
+    }
+    digits.push_str(&"0".repeat(zeros));
+    match BigUint::parse_bytes(digits.as_bytes(), 10) {
+        Some(value) if value.bits() > 0 => Ok(value),
+        _ => invalid(),
+    }
+}
+
+/// parses a size in bytes with an optional binary k, M, G or T suffix (e.g. 64k = 65536)
+pub fn parse_size(size: &str) -> BoxResult<usize> {
+    let (value, shift) = match size.as_bytes().last() {
+        Some(b'k') | Some(b'K') => (&size[..size.len() - 1], 10),
+        Some(b'M') => (&size[..size.len() - 1], 20),
+        Some(b'G') => (&size[..size.len() - 1], 30),
+        Some(b'T') => (&size[..size.len() - 1], 40),
+        _ => (size, 0),
+    };
+    match value.parse::<usize>() {
+        Ok(value) if value > 0 && value.leading_zeros() >= shift => Ok(value << shift),
+        _ => bail!(
+            "invalid size '{}': expected a number of bytes with an optional k, M, G or T suffix (e.g. 64k)",
+            size
+        ),
+    }
+}
+
+/// parses a duration with an optional ms, s, m, h or d suffix, seconds without one (e.g. 10m)
+pub fn parse_duration(duration: &str) -> BoxResult<Duration> {
+    let (value, unit) = match duration.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(pos) => duration.split_at(pos),
+        None => (duration, "s"),
+    };
+    let multiplier = match unit {
+        "ms" => 0.001,
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        "d" => 86400.0,
+        _ => 0.0,
+    };
+    match value.parse::<f64>() {
+        Ok(value) if multiplier > 0.0 && value > 0.0 && (value * multiplier).is_finite() => {
+            Ok(Duration::from_secs_f64(value * multiplier))
+        }
+        _ => bail!(
+            "invalid duration '{}': expected a number with an optional ms, s, m, h or d suffix (e.g. 10m)",
+            duration
+        ),
+    }
+}
+
+/// parses raw bytes with `\xNN` escapes for any byte and `\\` for a backslash (e.g. `pwd\x00`)
+pub fn parse_escaped_bytes(s: &str) -> BoxResult<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        match rest {
+            [b'\\', tail @ ..] => {
+                bytes.push(b'\\');
+                rest = tail;
+            }
+            [b'x', hi, lo, tail @ ..] if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                let hex = [*hi, *lo];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex)?, 16)?);
+                rest = tail;
+            }
+            _ => bail!(
+                "invalid escape in '{}': expected \\xNN with 2 hex digits or \\\\",
+                s
+            ),
+        }
+    }
+    Ok(bytes)
+}
+
+/// formats a duration of `secs` seconds in the largest unit up to years, e.g. 2.5 hours
+pub fn format_duration(secs: &BigUint) -> String {
+    if secs.bits() > 64 {
+        return format!("{} years", secs / SECONDS_PER_YEAR);
+    }
+    let secs = secs.to_u64_digits().first().copied().unwrap_or(0);
+    // truncated to tenths so a value never rounds up to the next unit (e.g. 60.0 minutes)
+    let tenths = |unit: u64, name: &str| {
+        let tenths = secs as u128 * 10 / unit as u128;
+        format!("{}.{} {}", tenths / 10, tenths % 10, name)
+    };
+    match secs {
+        0 => "less than a second".to_string(),
+        1 => "1 second".to_string(),
+        2..=59 => format!("{} seconds", secs),
+        60..=3599 => tenths(60, "minutes"),
+        3600..=86399 => tenths(3600, "hours"),
+        86400..=31535999 => tenths(86400, "days"),
+        _ => tenths(SECONDS_PER_YEAR, "years"),
+    }
+}
+
+/// formats a number of bytes in the largest binary unit up to TiB, e.g. 3.2 GiB
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let exp = ((63 - bytes.leading_zeros()) / 10).min(UNITS.len() as u32);
+    let unit = 1u64 << (10 * exp);
+    let name = UNITS[exp as usize - 1];
+    match bytes % unit {
+        0 => format!("{} {}", bytes / unit, name),
+        _ => format!("{:.1} {}", bytes as f64 / unit as f64, name),
+    }
+}
+
+/// returns `word` as lowercase hex digits
+pub fn to_hex(word: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 * word.len());
+    for &c in word {
+        hex.push(HEX_DIGITS[(c >> 4) as usize] as char);
+        hex.push(HEX_DIGITS[(c & 0xf) as usize] as char);
+    }
+    hex
+}
+
+/// rounds `x` to the 2 decimals the json reports print
+pub fn round2(x: f64) -> f64 {
+    (x * 100.0).round() / 100.0
+}
+
+/// serializes `value` as a string, for the numbers of words which may exceed the json numbers
+pub fn serialize_str<T: Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// a json object whose keys are only known at runtime, written in the order of `.0`
+pub struct JsonObject<K, V>(pub Vec<(K, V)>);
+
+impl<K: Serialize, V: Serialize> Serialize for JsonObject<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
     use std::fs::File;
+    use std::io::{Error, ErrorKind, IoSlice, Write};
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use num_bigint::BigUint;
 
-    use crate::helpers::RawFileReader;
+    use crate::helpers::{
+        decode_hex_word, encode_hex_word, format_bytes, format_duration, format_status,
+        index_bytes, log2_biguint, needs_hex, parse_duration, parse_escaped_bytes, parse_keyspace,
+        parse_rate, parse_size, round2, to_hex, write_all_vectored, BudgetWriter, CountingWriter,
+        HexWords, IndexedFormat, IndexedWriter, JsonObject, LineFilterWriter, RateLimitWriter,
+        RawFileReader, SplitMix64, StatusWriter, StopWriter, TeeWriter, TimeoutWriter, WriteCounts,
+        HEX_WORD_SIZE,
+    };
     use crate::test_util::wordlist_fname;
 
     #[test]
@@ -47,5 +1159,593 @@ mod tests {
             .collect();
         let lines = reader.take(5).map(|s| s.unwrap()).collect::<Vec<_>>();
         assert_eq!(lines, expected);
+
+        let reader = RawFileReader::new(&b"qwerty\n\nletmein"[..]);
+        let lines = reader.map(|s| s.unwrap()).collect::<Vec<_>>();
+        assert_eq!(lines, vec![&b"qwerty"[..], b"", b"letmein"]);
+    }
+
+    #[test]
+    fn test_hex_words_round_trip() {
+        let words: Vec<&[u8]> = vec![b"abc\n", b"\x00", b"\xff\xfe", b"a\rb", b"$HEX[00]", b""];
+        for word in words {
+            assert!(
+                word.is_empty() || needs_hex(word, HexWords::Unprintable),
+                "{:?}",
+                word
+            );
+            let mut hex_word = [0; HEX_WORD_SIZE];
+            let len = encode_hex_word(word, &mut hex_word);
+            assert_eq!(hex_word[len - 1], b'\n');
+            assert!(hex_word[..len - 1].iter().all(|c| c.is_ascii_graphic()));
+
+            let decoded = decode_hex_word(hex_word[..len - 1].to_vec()).unwrap();
+            assert_eq!(decoded, word);
+        }
+
+        let mut hex_word = [0; HEX_WORD_SIZE];
+        let len = encode_hex_word(b"abc\n", &mut hex_word);
+        assert_eq!(&hex_word[..len], b"$HEX[6162630a]\n");
+        assert!(!needs_hex("émile".as_bytes(), HexWords::Unprintable));
+        assert!(!needs_hex(b"password1", HexWords::Unprintable));
+    }
+
+    #[test]
+    fn test_decode_hex_word() {
+        assert_eq!(decode_hex_word(b"$HEX[41Ff]".to_vec()).unwrap(), b"A\xff");
+        assert_eq!(decode_hex_word(b"$HEX[41".to_vec()).unwrap(), b"$HEX[41");
+        assert_eq!(decode_hex_word(b"plain".to_vec()).unwrap(), b"plain");
+        assert!(decode_hex_word(b"$HEX[414]".to_vec())
+            .unwrap_err()
+            .contains("odd number"));
+        assert!(decode_hex_word(b"$HEX[41zz]".to_vec())
+            .unwrap_err()
+            .contains("invalid hex digit 'z'"));
+    }
+
+    #[test]
+    fn test_splitmix64() {
+        let mut rng = SplitMix64::new(1234567);
+        // reference values of splitmix64
+        assert_eq!(rng.next_u64(), 6457827717110365317);
+        assert_eq!(rng.next_u64(), 3203168211198807973);
+
+        let mut rng = SplitMix64::new(42);
+        for _ in 0..1000 {
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+        }
+    }
+
+    #[test]
+    fn test_counting_writer() {
+        let counts = Rc::new(WriteCounts::default());
+        let mut out = vec![];
+        {
+            let mut writer = CountingWriter::new(&mut out, Rc::clone(&counts));
+            writer.write_all(b"abc\nd").unwrap();
+            writer.write_all(b"e\n\n").unwrap();
+        }
+        assert_eq!(counts.bytes.get(), 8);
+        assert_eq!(counts.lines.get(), 3);
+    }
+
+    #[test]
+    fn test_stop_writer() {
+        static STOP: AtomicBool = AtomicBool::new(false);
+        let mut out = vec![];
+        {
+            let mut writer = StopWriter::new(&mut out, &STOP);
+            writer.write_all(b"abc\n").unwrap();
+            STOP.store(true, Ordering::SeqCst);
+            assert!(writer.write_all(b"def\n").is_err());
+            assert!(writer.write(b"def\n").is_err());
+            assert!(writer.write_vectored(&[IoSlice::new(b"def\n")]).is_err());
+            writer.flush().unwrap();
+        }
+        assert_eq!(out, b"abc\n");
+    }
+
+    #[test]
+    fn test_rate_limit_writer() {
+        static STOP: AtomicBool = AtomicBool::new(false);
+        let mut out = vec![];
+        let started = Instant::now();
+        {
+            let mut writer = RateLimitWriter::new(&mut out, 200, 5, &STOP, None);
+            // the free lines don't wait, the next 20 wait for 100ms of tokens
+            writer.write_all(b"a\nb\nc\nd\ne\n").unwrap();
+            assert!(started.elapsed() < Duration::from_millis(50));
+            writer.write_all(&b"f\n".repeat(20)).unwrap();
+            writer.flush().unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(95));
+        assert_eq!(out.len(), 2 * 25);
+
+        // a wait ends at the deadline or once stopped, the words are still written
+        let started = Instant::now();
+        let deadline = Some(started + Duration::from_millis(50));
+        let mut writer = RateLimitWriter::new(vec![], 1, 0, &STOP, deadline);
+        writer.write_all(&b"g\n".repeat(100)).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        STOP.store(true, Ordering::SeqCst);
+        let mut writer = RateLimitWriter::new(vec![], 1, 0, &STOP, None);
+        writer.write_all(&b"h\n".repeat(100)).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    /// fails every write and flush
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> Result<usize, Error> {
+            Err(Error::new(ErrorKind::PermissionDenied, "denied"))
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tee_writer() {
+        let counts = Rc::new(WriteCounts::default());
+        let mut warnings = vec![];
+        {
+            let sinks: Vec<(String, Box<dyn Write>)> = vec![
+                ("a".to_string(), Box::new(vec![])),
+                ("b".to_string(), Box::new(FailingWriter)),
+                (
+                    "c".to_string(),
+                    Box::new(CountingWriter::new(std::io::sink(), Rc::clone(&counts))),
+                ),
+            ];
+            let mut writer = TeeWriter::new(sinks, false, &mut warnings);
+            let e = writer.write_all(b"ab\n").unwrap_err();
+            assert_eq!(e.to_string(), "b: denied");
+        }
+        // the write stops at the failing sink
+        assert_eq!(counts.lines.get(), 0);
+
+        {
+            let sinks: Vec<(String, Box<dyn Write>)> = vec![
+                ("b".to_string(), Box::new(FailingWriter)),
+                (
+                    "c".to_string(),
+                    Box::new(CountingWriter::new(std::io::sink(), Rc::clone(&counts))),
+                ),
+            ];
+            let mut writer = TeeWriter::new(sinks, true, &mut warnings);
+            writer.write_all(b"ab\n").unwrap();
+            writer.write_all(b"cd\nef\n").unwrap();
+            writer.flush().unwrap();
+        }
+        assert_eq!(counts.lines.get(), 3);
+        assert_eq!(
+            String::from_utf8(warnings).unwrap(),
+            "stopped writing to b: denied\n"
+        );
+
+        // the last sink left fails the write even at best effort
+        let sinks: Vec<(String, Box<dyn Write>)> = vec![("b".to_string(), Box::new(FailingWriter))];
+        let mut writer = TeeWriter::new(sinks, true, std::io::sink());
+        assert_eq!(
+            writer.write_all(b"ab\n").unwrap_err().to_string(),
+            "b: denied"
+        );
+    }
+
+    /// writes up to `max` bytes of the first non empty slice per call, like a pipe without
+    /// vectored support
+    struct ShortWriter {
+        out: Vec<u8>,
+        max: usize,
+        calls: usize,
+    }
+
+    impl Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            self.calls += 1;
+            let len = buf.len().min(self.max);
+            self.out.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_all_vectored() {
+        let bufs: [&[u8]; 4] = [b"", b"abc\n", b"", b"de\nfghij\n"];
+        let mut slices: Vec<_> = bufs.iter().map(|b| IoSlice::new(b)).collect();
+        let mut out = vec![];
+        write_all_vectored(&mut out, &mut slices).unwrap();
+        assert_eq!(out, b"abc\nde\nfghij\n");
+
+        for (max, calls) in [(1, 13), (3, 5), (100, 2)] {
+            let mut slices: Vec<_> = bufs.iter().map(|b| IoSlice::new(b)).collect();
+            let mut out = ShortWriter {
+                out: vec![],
+                max,
+                calls: 0,
+            };
+            write_all_vectored(&mut out, &mut slices).unwrap();
+            assert_eq!(out.out, b"abc\nde\nfghij\n", "{}", max);
+            assert_eq!(out.calls, calls, "{}", max);
+        }
+
+        let mut slices = vec![IoSlice::new(b"abc")];
+        let mut out = ShortWriter {
+            out: vec![],
+            max: 0,
+            calls: 0,
+        };
+        let err = write_all_vectored(&mut out, &mut slices).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+
+        // nothing to write
+        write_all_vectored(&mut out, &mut []).unwrap();
+        write_all_vectored(&mut out, &mut [IoSlice::new(b"")]).unwrap();
+        assert_eq!(out.calls, 1);
+    }
+
+    #[test]
+    fn test_budget_writer() {
+        let mut out = vec![];
+        let exhausted = Rc::new(Cell::new(false));
+        {
+            let mut writer = BudgetWriter::new(&mut out, 10, 1, Rc::clone(&exhausted));
+            writer.write_all(b"skipped\nab\ncd\n").unwrap();
+            assert!(!exhausted.get());
+            assert!(writer.write_all(b"ef\ngh\nij\n").is_err());
+            assert!(exhausted.get());
+            assert!(writer.write_all(b"k\n").is_err());
+        }
+        // 9 bytes counted, the next word would exceed the budget
+        assert_eq!(out, b"skipped\nab\ncd\nef\n");
+    }
+
+    #[test]
+    fn test_timeout_writer() {
+        let mut out = vec![];
+        let expired = Rc::new(Cell::new(false));
+        {
+            let deadline = Instant::now() + Duration::from_secs(3600);
+            let mut writer = TimeoutWriter::new(&mut out, deadline, Rc::clone(&expired));
+            writer.write_all(b"ab\ncd\n").unwrap();
+            assert!(!expired.get());
+        }
+        {
+            let mut writer = TimeoutWriter::new(&mut out, Instant::now(), Rc::clone(&expired));
+            assert!(writer.write_all(b"ef\n").is_err());
+            assert!(expired.get());
+            writer.flush().unwrap();
+        }
+        assert_eq!(out, b"ab\ncd\n");
+    }
+
+    #[test]
+    fn test_format_status() {
+        let total = BigUint::from(10000u32);
+        assert_eq!(
+            format_status(2500, &total, 1200.0, 1000.0, b"2499"),
+            "progress: 2500/10000 (25.00%), 1.20k/s (avg 1.00k/s), eta 6 seconds, last: 2499"
+        );
+        assert_eq!(
+            format_status(0, &total, 0.0, 0.0, b""),
+            "progress: 0/10000 (0.00%), 0/s (avg 0/s), eta unknown, last: "
+        );
+        assert_eq!(
+            format_status(10000, &total, 5e9, 2.5e12, b"\xff"),
+            "progress: 10000/10000 (100.00%), 5.00G/s (avg 2.50T/s), done, last: \u{fffd}"
+        );
+    }
+
+    #[test]
+    fn test_status_writer() {
+        for tty in [false, true] {
+            let mut out = vec![];
+            let mut status = vec![];
+            let done = Rc::new(WriteCounts::default());
+            {
+                let counting = CountingWriter::new(&mut out, Rc::clone(&done));
+                let total = BigUint::from(6u8);
+                let mut writer =
+                    StatusWriter::new(counting, &mut status, tty, Duration::ZERO, total, done);
+                writer.write_all(b"a\nb\n").unwrap();
+                writer.write_all(b"c\nd\ne\n").unwrap();
+                writer.write_all(b"f\n").unwrap();
+            }
+            assert_eq!(out, b"a\nb\nc\nd\ne\nf\n");
+            let status = String::from_utf8(status).unwrap();
+            // the first write starts the rates, every later one prints with a zero interval
+            let lines: Vec<_> = if tty {
+                assert!(status.starts_with('\r') && status.ends_with("\x1b[K\n"));
+                status.trim_end().split('\r').skip(1).collect()
+            } else {
+                status.lines().collect()
+            };
+            assert_eq!(lines.len(), 3, "{:?}", status);
+            assert!(lines[0].starts_with("progress: 5/6 (83.33%), "));
+            assert!(lines[0].contains(", last: e"));
+            assert!(lines[2].starts_with("progress: 6/6 (100.00%), "));
+            assert!(lines[2].contains(", done, last: f"));
+        }
+    }
+
+    #[test]
+    fn test_status_writer_on_request() {
+        let mut status = vec![];
+        let done = Rc::new(WriteCounts::default());
+        let requested = Arc::new(AtomicBool::new(false));
+        let counted = Rc::new(Cell::new(0));
+        {
+            let counting = CountingWriter::new(std::io::sink(), Rc::clone(&done));
+            let count_total = {
+                let counted = Rc::clone(&counted);
+                Box::new(move || {
+                    counted.set(counted.get() + 1);
+                    BigUint::from(4u8)
+                })
+            };
+            let mut writer = StatusWriter::on_request(
+                counting,
+                &mut status,
+                Arc::clone(&requested),
+                count_total,
+                done,
+            );
+            writer
+                .write_all(
+                    b"a
+",
+                )
+                .unwrap();
+            writer
+                .write_all(
+                    b"b
+",
+                )
+                .unwrap();
+            assert_eq!(counted.get(), 0);
+            requested.store(true, Ordering::SeqCst);
+            writer
+                .write_all(
+                    b"c
+",
+                )
+                .unwrap();
+            writer
+                .write_all(
+                    b"d
+",
+                )
+                .unwrap();
+        }
+        // a line per request and none when dropped, the total is counted once
+        let status = String::from_utf8(status).unwrap();
+        assert!(!requested.load(Ordering::SeqCst));
+        assert_eq!(counted.get(), 1);
+        assert_eq!(status.lines().count(), 1, "{:?}", status);
+        assert!(status.starts_with("progress: 3/4 (75.00%), "));
+        assert!(status.ends_with(", last: c\n"));
+    }
+
+    #[test]
+    fn test_line_filter_writer() {
+        let mut out = vec![];
+        {
+            let mut writer = LineFilterWriter::new(&mut out, |line: &[u8]| line.len() > 2);
+            writer.write_all(b"a\nabc\nab").unwrap();
+            writer.write_all(b"cd\n").unwrap();
+            writer.write_all(b"x").unwrap();
+            writer.write_all(b"\n\nlast").unwrap();
+        }
+        assert_eq!(out, b"abc\nabcd\nlast");
+    }
+
+    #[test]
+    fn test_indexed_writer() {
+        let mut out = vec![];
+        let index = Rc::new(Cell::new(7));
+        {
+            let mut writer = IndexedWriter::new(&mut out, IndexedFormat::Jsonl, Rc::clone(&index));
+            writer.write_all(b"ab\nc\"").unwrap();
+            writer.write_all(b"d\n\xff\x00\n").unwrap();
+            index.set(0);
+            writer.write_all(b"last").unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "{\"pw\":\"ab\",\"len\":2,\"index\":7}\n",
+                "{\"pw\":\"c\\\"d\",\"len\":3,\"index\":8}\n",
+                "{\"hex\":\"ff00\",\"len\":2,\"index\":9}\n",
+                "{\"pw\":\"last\",\"len\":4,\"index\":0}\n",
+            )
+        );
+        assert_eq!(index.get(), 1);
+
+        let mut out = vec![];
+        {
+            let mut writer = IndexedWriter::new(&mut out, IndexedFormat::Tab, Rc::clone(&index));
+            writer.write_all(b"0042\n\xff\n9").unwrap();
+            writer.write_all(b"9\n").unwrap();
+        }
+        assert_eq!(out, b"1\t0042\n2\t\xff\n3\t99\n");
+        assert_eq!(index_bytes(0, &BigUint::from(12u32)), BigUint::from(26u32));
+        assert_eq!(index_bytes(98, &BigUint::from(3u32)), BigUint::from(10u32));
+        assert_eq!(index_bytes(5, &BigUint::from(0u32)), BigUint::from(0u32));
+    }
+
+    #[test]
+    fn test_to_hex() {
+        assert_eq!(to_hex(b"\xff\x00a"), "ff0061");
+        assert_eq!(to_hex(b""), "");
+        assert_eq!(round2(32.3249), 32.32);
+        assert_eq!(round2(7.0), 7.0);
+    }
+
+    #[test]
+    fn test_json_object() {
+        let object = JsonObject(vec![("b", 1), ("a", 2)]);
+        assert_eq!(serde_json::to_string(&object).unwrap(), r#"{"b":1,"a":2}"#);
+    }
+
+    #[test]
+    fn test_log2_biguint() {
+        assert_eq!(log2_biguint(&BigUint::from(1u32)), 0.0);
+        assert_eq!(log2_biguint(&BigUint::from(1024u32)), 10.0);
+        assert_eq!(log2_biguint(&(BigUint::from(1u32) << 100)), 100.0);
+        assert!((log2_biguint(&BigUint::from(1000u32)) - 1000f64.log2()).abs() < 1e-12);
+        let big = BigUint::from(3u32) << 90;
+        assert!((log2_biguint(&big) - (90.0 + 3f64.log2())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        let cases = vec![
+            ("1", 1),
+            ("200000", 200_000),
+            ("50GH", 50_000_000_000),
+            ("50G", 50_000_000_000),
+            ("1.5MH/s", 1_500_000),
+            ("10k", 10_000),
+            ("10KH", 10_000),
+            ("2T/s", 2_000_000_000_000),
+        ];
+        for (rate, expected) in cases {
+            assert_eq!(parse_rate(rate).unwrap(), expected, "{}", rate);
+        }
+        for rate in ["", "G", "abc", "0", "0.1", "-5G", "5X", "inf", "NaNG"] {
+            assert!(parse_rate(rate).is_err(), "{}", rate);
+        }
+    }
+
+    #[test]
+    fn test_parse_keyspace() {
+        let cases = vec![
+            ("1", "1"),
+            ("1000000", "1000000"),
+            ("1e13", "10000000000000"),
+            ("2.5e3", "2500"),
+            ("2.50E1", "25"),
+            ("1e30", "1000000000000000000000000000000"),
+        ];
+        for (keyspace, expected) in cases {
+            assert_eq!(
+                parse_keyspace(keyspace).unwrap().to_string(),
+                expected,
+                "{}",
+                keyspace
+            );
+        }
+        for keyspace in [
+            "", "e5", "abc", "0", "0e5", "-5", "2.55e1", "1e-3", "1.5", "1e",
+        ] {
+            assert!(parse_keyspace(keyspace).is_err(), "{}", keyspace);
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        let cases = vec![
+            ("1", 1),
+            ("8192", 8192),
+            ("64k", 65536),
+            ("64K", 65536),
+            ("4M", 4 << 20),
+            ("1G", 1 << 30),
+            ("10T", 10 << 40),
+        ];
+        for (size, expected) in cases {
+            assert_eq!(parse_size(size).unwrap(), expected, "{}", size);
+        }
+        for size in ["", "k", "abc", "0", "0k", "-5k", "1.5M", "5X", "4m"] {
+            assert!(parse_size(size).is_err(), "{}", size);
+        }
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let cases = vec![
+            ("90", Duration::from_secs(90)),
+            ("500ms", Duration::from_millis(500)),
+            ("30s", Duration::from_secs(30)),
+            ("10m", Duration::from_secs(600)),
+            ("1.5h", Duration::from_secs(5400)),
+            ("2d", Duration::from_secs(172800)),
+        ];
+        for (duration, expected) in cases {
+            assert_eq!(parse_duration(duration).unwrap(), expected, "{}", duration);
+        }
+        for duration in [
+            "", "m", "abc", "0", "0s", "-5m", "5x", "5 m", "1e400s", "10M",
+        ] {
+            assert!(parse_duration(duration).is_err(), "{}", duration);
+        }
+    }
+
+    #[test]
+    fn test_parse_escaped_bytes() {
+        let cases: Vec<(&str, &[u8])> = vec![
+            ("", b""),
+            ("pwd", b"pwd"),
+            ("a\\x00b", b"a\x00b"),
+            ("\\xffé", b"\xff\xc3\xa9"),
+            ("\\xAb", b"\xab"),
+            ("c:\\\\", b"c:\\"),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(parse_escaped_bytes(s).unwrap(), expected, "{}", s);
+        }
+        for s in ["\\", "\\x", "\\x1", "\\xg0", "\\n", "a\\"] {
+            assert!(parse_escaped_bytes(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        let cases = vec![
+            (0u64, "less than a second"),
+            (1, "1 second"),
+            (59, "59 seconds"),
+            (60, "1.0 minutes"),
+            (90, "1.5 minutes"),
+            (3599, "59.9 minutes"),
+            (3600, "1.0 hours"),
+            (86399, "23.9 hours"),
+            (86400, "1.0 days"),
+            (31535999, "364.9 days"),
+            (31536000, "1.0 years"),
+            (u64::MAX, "584942417355.0 years"),
+        ];
+        for (secs, expected) in cases {
+            assert_eq!(format_duration(&BigUint::from(secs)), expected, "{}", secs);
+        }
+        // 2^100 seconds does not fit a u64
+        assert_eq!(
+            format_duration(&(BigUint::from(1u32) << 100)),
+            "40196936841331475186983 years"
+        );
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        let cases = vec![
+            (0u64, "0 B"),
+            (1023, "1023 B"),
+            (1024, "1 KiB"),
+            (1536, "1.5 KiB"),
+            (2 << 30, "2 GiB"),
+            (3435973837, "3.2 GiB"),
+            (5 << 40, "5 TiB"),
+            (u64::MAX, "16777216.0 TiB"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(format_bytes(bytes), expected, "{}", bytes);
+        }
     }
 }
@@ -0,0 +1,392 @@
+//! output backends of `--io-backend`: buffered write() calls everywhere, or on linux vmsplice
+//! of the filled buffers into a pipe and io_uring writes from registered buffers
+use std::fmt;
+use std::io::{BufWriter, Write};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoBackend {
+    /// a `BufWriter`, one write() per full buffer
+    Std,
+    /// gives the pages of the full buffers to the pipe instead of copying them, pipes only
+    Vmsplice,
+    /// io_uring writes from two registered buffers, one filled while the other is written
+    Uring,
+}
+
+impl FromStr for IoBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<IoBackend, String> {
+        match s {
+            "std" => Ok(IoBackend::Std),
+            "vmsplice" => Ok(IoBackend::Vmsplice),
+            "uring" => Ok(IoBackend::Uring),
+            _ => Err(format!("unknown io backend {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for IoBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            IoBackend::Std => "std",
+            IoBackend::Vmsplice => "vmsplice",
+            IoBackend::Uring => "uring",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// outputs the backends can write to - anything with a file descriptor on linux
+#[cfg(target_os = "linux")]
+pub trait BackendOutput: Write + std::os::unix::io::AsRawFd {}
+#[cfg(target_os = "linux")]
+impl<W: Write + std::os::unix::io::AsRawFd> BackendOutput for W {}
+
+#[cfg(not(target_os = "linux"))]
+pub trait BackendOutput: Write {}
+#[cfg(not(target_os = "linux"))]
+impl<W: Write> BackendOutput for W {}
+
+/// wraps `out` in the writer of `backend` buffering `buffer_size` bytes. returns `out` back
+/// with the reason when the os, the kernel or the kind of output doesn't support the backend
+pub fn backend_writer<W: BackendOutput + 'static>(
+    out: W,
+    backend: IoBackend,
+    buffer_size: usize,
+) -> Result<Box<dyn Write>, (W, String)> {
+    match backend {
+        IoBackend::Std => Ok(Box::new(BufWriter::with_capacity(buffer_size, out))),
+        #[cfg(target_os = "linux")]
+        IoBackend::Vmsplice => match linux::VmspliceWriter::new(out, buffer_size) {
+            Ok(writer) => Ok(Box::new(writer)),
+            Err((out, reason)) => Err((out, reason)),
+        },
+        #[cfg(target_os = "linux")]
+        IoBackend::Uring => match linux::UringWriter::new(out, buffer_size) {
+            Ok(writer) => Ok(Box::new(writer)),
+            Err((out, reason)) => Err((out, reason)),
+        },
+        #[cfg(not(target_os = "linux"))]
+        _ => Err((out, "only supported on linux".to_string())),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::fs::File;
+    use std::io::{self, Write};
+    use std::mem::ManuallyDrop;
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    use io_uring::{opcode, types, IoUring};
+
+    /// a zeroed buffer of `size` bytes starting at a page boundary, so vmsplice gives whole
+    /// pages to the pipe and every page takes a single pipe slot
+    #[derive(Default)]
+    struct PageBuf {
+        data: Vec<u8>,
+        start: usize,
+        size: usize,
+    }
+
+    impl PageBuf {
+        fn new(size: usize, page_size: usize) -> PageBuf {
+            let data = vec![0; size + page_size];
+            let start = data.as_ptr().align_offset(page_size);
+            PageBuf { data, start, size }
+        }
+
+        fn get(&self) -> &[u8] {
+            &self.data[self.start..self.start + self.size]
+        }
+
+        fn get_mut(&mut self) -> &mut [u8] {
+            &mut self.data[self.start..self.start + self.size]
+        }
+    }
+
+    fn page_size() -> usize {
+        match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+            size if size > 0 => size as usize,
+            _ => 4096,
+        }
+    }
+
+    /// copies as much of `buf` as fits after the first `len` bytes of `dest`
+    fn fill(dest: &mut [u8], len: &mut usize, buf: &[u8]) -> usize {
+        let n = buf.len().min(dest.len() - *len);
+        dest[*len..*len + n].copy_from_slice(&buf[..n]);
+        *len += n;
+        n
+    }
+
+    /// vmsplices two page aligned buffers of the pipe size into a pipe, one after the other.
+    /// the pipe keeps referencing the pages until they are read, so a buffer is refilled only
+    /// after the other one filled the whole pipe - which the reader must have emptied of it
+    pub struct VmspliceWriter<W: AsRawFd + Write> {
+        out: W,
+        bufs: [PageBuf; 2],
+        current: usize,
+        len: usize,
+    }
+
+    impl<W: AsRawFd + Write> VmspliceWriter<W> {
+        pub fn new(out: W, buffer_size: usize) -> Result<VmspliceWriter<W>, (W, String)> {
+            let fd = out.as_raw_fd();
+            // borrows the fd without closing it
+            let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+            match file.metadata() {
+                Ok(meta) if meta.file_type().is_fifo() => {}
+                Ok(_) => return Err((out, "the output is not a pipe".to_string())),
+                Err(e) => return Err((out, e.to_string())),
+            }
+            let mut pipe_size = unsafe { libc::fcntl(fd, libc::F_GETPIPE_SZ) };
+            if pipe_size <= 0 {
+                return Err((out, io::Error::last_os_error().to_string()));
+            }
+            // grows the pipe to the buffer size. the kernel rounds it up to a power of 2 pages
+            // and refuses sizes above /proc/sys/fs/pipe-max-size to unprivileged users, the
+            // current size is kept then
+            if buffer_size > pipe_size as usize {
+                let wanted = buffer_size.min(i32::MAX as usize) as libc::c_int;
+                let grown = unsafe { libc::fcntl(fd, libc::F_SETPIPE_SZ, wanted) };
+                if grown > 0 {
+                    pipe_size = grown;
+                }
+            }
+            let size = pipe_size as usize;
+            let page_size = page_size();
+            Ok(VmspliceWriter {
+                out,
+                bufs: [PageBuf::new(size, page_size), PageBuf::new(size, page_size)],
+                current: 0,
+                len: 0,
+            })
+        }
+
+        /// gives the full current buffer to the pipe and switches to the other one
+        fn splice_current(&mut self) -> io::Result<()> {
+            let data = self.bufs[self.current].get();
+            let mut pos = 0;
+            while pos < data.len() {
+                let iov = libc::iovec {
+                    iov_base: data[pos..].as_ptr() as *mut libc::c_void,
+                    iov_len: data.len() - pos,
+                };
+                let spliced = unsafe { libc::vmsplice(self.out.as_raw_fd(), &iov, 1, 0) };
+                if spliced < 0 {
+                    let e = io::Error::last_os_error();
+                    if e.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(e);
+                }
+                pos += spliced as usize;
+            }
+            self.current = 1 - self.current;
+            self.len = 0;
+            Ok(())
+        }
+    }
+
+    impl<W: AsRawFd + Write> Write for VmspliceWriter<W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.len == self.bufs[self.current].size {
+                self.splice_current()?;
+            }
+            let n = fill(self.bufs[self.current].get_mut(), &mut self.len, buf);
+            if self.len == self.bufs[self.current].size {
+                self.splice_current()?;
+            }
+            Ok(n)
+        }
+
+        /// a partly filled buffer doesn't fill the pipe, so it is copied with write() and
+        /// kept as the current buffer
+        fn flush(&mut self) -> io::Result<()> {
+            let len = self.len;
+            self.len = 0;
+            self.out.write_all(&self.bufs[self.current].get()[..len])?;
+            self.out.flush()
+        }
+    }
+
+    impl<W: AsRawFd + Write> Drop for VmspliceWriter<W> {
+        fn drop(&mut self) {
+            // like BufWriter, errors writing the rest are ignored
+            let _ = self.flush();
+            // the pipe may still reference the pages until they are read, so they are leaked
+            // rather than reused by the allocator
+            std::mem::forget(std::mem::take(&mut self.bufs));
+        }
+    }
+
+    /// writes a registered buffer with io_uring while the other one is filled. short writes
+    /// are resubmitted, so the output gets every byte in order
+    pub struct UringWriter<W: AsRawFd + Write> {
+        /// dropped before the registered buffers
+        ring: IoUring,
+        bufs: [Vec<u8>; 2],
+        out: W,
+        current: usize,
+        len: usize,
+        /// the buffer the kernel is writing and the range of it not written yet
+        in_flight: Option<(usize, usize, usize)>,
+    }
+
+    impl<W: AsRawFd + Write> UringWriter<W> {
+        pub fn new(out: W, buffer_size: usize) -> Result<UringWriter<W>, (W, String)> {
+            let ring = match IoUring::new(2) {
+                Ok(ring) => ring,
+                Err(e) => return Err((out, format!("io_uring: {}", e))),
+            };
+            // the length of a write is a u32
+            let size = buffer_size.clamp(1, 1 << 30);
+            let bufs = [vec![0; size], vec![0; size]];
+            let iovecs: Vec<_> = bufs
+                .iter()
+                .map(|buf| libc::iovec {
+                    iov_base: buf.as_ptr() as *mut libc::c_void,
+                    iov_len: buf.len(),
+                })
+                .collect();
+            // the buffers are never reallocated and outlive the ring
+            if let Err(e) = unsafe { ring.submitter().register_buffers(&iovecs) } {
+                return Err((out, format!("registering io_uring buffers: {}", e)));
+            }
+            Ok(UringWriter {
+                ring,
+                bufs,
+                out,
+                current: 0,
+                len: 0,
+                in_flight: None,
+            })
+        }
+
+        /// submits the write of `bufs[index][start..end]` at the current file position
+        fn submit(&mut self, index: usize, start: usize, end: usize) -> io::Result<()> {
+            let entry = opcode::WriteFixed::new(
+                types::Fd(self.out.as_raw_fd()),
+                self.bufs[index][start..].as_ptr(),
+                (end - start) as u32,
+                index as u16,
+            )
+            .offset(u64::MAX)
+            .build();
+            // the submission queue has room for both buffers and at most one is in flight
+            unsafe { self.ring.submission().push(&entry) }
+                .map_err(|_| io::Error::other("the io_uring submission queue is full"))?;
+            self.ring.submit()?;
+            self.in_flight = Some((index, start, end));
+            Ok(())
+        }
+
+        /// waits until the buffer in flight is written completely
+        fn wait(&mut self) -> io::Result<()> {
+            while let Some((index, start, end)) = self.in_flight {
+                self.ring.submit_and_wait(1)?;
+                let written = match self.ring.completion().next() {
+                    Some(cqe) => cqe.result(),
+                    None => continue,
+                };
+                self.in_flight = None;
+                if written < 0 {
+                    return Err(io::Error::from_raw_os_error(-written));
+                }
+                if written == 0 {
+                    return Err(io::ErrorKind::WriteZero.into());
+                }
+                let start = start + written as usize;
+                if start < end {
+                    self.submit(index, start, end)?;
+                }
+            }
+            Ok(())
+        }
+
+        /// writes the current buffer once the previous write is done and switches buffers
+        fn submit_current(&mut self) -> io::Result<()> {
+            self.wait()?;
+            if self.len > 0 {
+                self.submit(self.current, 0, self.len)?;
+                self.current = 1 - self.current;
+                self.len = 0;
+            }
+            Ok(())
+        }
+    }
+
+    impl<W: AsRawFd + Write> Write for UringWriter<W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.len == self.bufs[self.current].len() {
+                self.submit_current()?;
+            }
+            let n = fill(&mut self.bufs[self.current], &mut self.len, buf);
+            if self.len == self.bufs[self.current].len() {
+                self.submit_current()?;
+            }
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.submit_current()?;
+            self.wait()?;
+            self.out.flush()
+        }
+    }
+
+    impl<W: AsRawFd + Write> Drop for UringWriter<W> {
+        fn drop(&mut self) {
+            // like BufWriter, errors writing the rest are ignored
+            let _ = self.flush();
+            if self.in_flight.is_some() {
+                // the kernel may still write from the buffers
+                std::mem::forget(std::mem::take(&mut self.bufs));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{backend_writer, IoBackend};
+
+    #[test]
+    fn test_io_backend_names() {
+        for name in ["std", "vmsplice", "uring"] {
+            let backend: IoBackend = name.parse().unwrap();
+            assert_eq!(backend.to_string(), name);
+        }
+        assert!("mmap".parse::<IoBackend>().is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_backend_writer_file() {
+        let fname = std::env::temp_dir().join("cracken-test-backend-writer.txt");
+        let expected: Vec<u8> = (0..100_000u32).flat_map(|i| i.to_le_bytes()).collect();
+        let file = std::fs::File::create(&fname).unwrap();
+        // io_uring may be unavailable, the file is returned then
+        if let Ok(mut out) = backend_writer(file, IoBackend::Uring, 1000) {
+            for chunk in expected.chunks(777) {
+                out.write_all(chunk).unwrap();
+            }
+            out.flush().unwrap();
+            drop(out);
+            assert!(std::fs::read(&fname).unwrap() == expected);
+        }
+
+        let file = std::fs::File::create(&fname).unwrap();
+        let (_, reason) = backend_writer(file, IoBackend::Vmsplice, 1000)
+            .err()
+            .unwrap();
+        assert_eq!(reason, "the output is not a pipe");
+        std::fs::remove_file(&fname).unwrap();
+    }
+}
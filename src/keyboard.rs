@@ -0,0 +1,383 @@
+/// a single keyboard row, `offset` is the horizontal shift of the row in quarter keys
+pub struct KeyboardRow<'a> {
+    pub(crate) offset: usize,
+    pub(crate) keys: &'a [u8],
+}
+
+impl<'a> KeyboardRow<'a> {
+    pub const fn new(offset: usize, keys: &'a [u8]) -> KeyboardRow<'a> {
+        KeyboardRow { offset, keys }
+    }
+}
+
+/// width of a single key in quarter keys
+const KEY_WIDTH: usize = 4;
+
+pub const QWERTY: [KeyboardRow; 4] = [
+    KeyboardRow::new(0, b"`1234567890-="),
+    KeyboardRow::new(6, b"qwertyuiop[]\\"),
+    KeyboardRow::new(7, b"asdfghjkl;'"),
+    KeyboardRow::new(9, b"zxcvbnm,./"),
+];
+
+pub const QWERTY_SHIFTED: [KeyboardRow; 4] = [
+    KeyboardRow::new(0, b"~!@#$%^&*()_+"),
+    KeyboardRow::new(6, b"QWERTYUIOP{}|"),
+    KeyboardRow::new(7, b"ASDFGHJKL:\""),
+    KeyboardRow::new(9, b"ZXCVBNM<>?"),
+];
+
+lazy_static! {
+    /// `shift_table` of the qwerty layout, used by the wordlist transform ?w1:shift
+    pub static ref QWERTY_SHIFT_TABLE: [u8; 256] = shift_table(&QWERTY, &QWERTY_SHIFTED);
+    /// `row_neighbours` of both qwerty layers, used by the wordlist transform ?w1:adjacent
+    pub static ref QWERTY_ROW_NEIGHBOURS: Vec<Vec<u8>> =
+        row_neighbours(&[&QWERTY, &QWERTY_SHIFTED]);
+}
+
+/// returns the key typed with shift held for every byte, e.g. 1 is ! and a is A. the rows of
+/// the layouts are paired by position, the bytes which are not an unshifted key are unchanged
+pub fn shift_table(unshifted: &[KeyboardRow], shifted: &[KeyboardRow]) -> [u8; 256] {
+    let mut table = [0; 256];
+    for (byte, key) in table.iter_mut().enumerate() {
+        *key = byte as u8;
+    }
+    for (row, shifted_row) in unshifted.iter().zip(shifted) {
+        for (&key, &shifted_key) in row.keys.iter().zip(shifted_row.keys) {
+            table[key as usize] = shifted_key;
+        }
+    }
+    table
+}
+
+/// returns the keys left and right of every key on its row (in this order), keys of several
+/// layers have the neighbours of the first layer they are on
+pub fn row_neighbours(layers: &[&[KeyboardRow]]) -> Vec<Vec<u8>> {
+    let mut neighbours = vec![vec![]; 256];
+    for rows in layers.iter().rev() {
+        for row in rows.iter() {
+            for (col, &key) in row.keys.iter().enumerate() {
+                let left = col.checked_sub(1).map(|left| row.keys[left]);
+                let right = row.keys.get(col + 1).copied();
+                neighbours[key as usize] = left.into_iter().chain(right).collect();
+            }
+        }
+    }
+    neighbours
+}
+
+/// adjacency graph of keyboard keys - two keys are adjacent if they touch on the keyboard
+pub struct KeyboardGraph {
+    keys: Vec<u8>,
+    neighbours: Vec<Vec<u8>>,
+}
+
+impl KeyboardGraph {
+    /// builds the graph from keyboard layers (e.g. unshifted and shifted), keys of
+    /// different layers are never adjacent to each other
+    pub fn from_layers(layers: &[&[KeyboardRow]]) -> KeyboardGraph {
+        let mut keys = vec![];
+        let mut neighbours = vec![vec![]; 256];
+
+        for rows in layers {
+            for (row_idx, row) in rows.iter().enumerate() {
+                for (col, &key) in row.keys.iter().enumerate() {
+                    if !keys.contains(&key) {
+                        keys.push(key);
+                    }
+                    let key_x = row.offset + col * KEY_WIDTH;
+
+                    // adjacent rows (including same row) keys touching this key
+                    let from_row = row_idx.saturating_sub(1);
+                    let to_row = (row_idx + 1).min(rows.len() - 1);
+                    for (other_idx, other) in
+                        rows.iter().enumerate().take(to_row + 1).skip(from_row)
+                    {
+                        for (other_col, &other_key) in other.keys.iter().enumerate() {
+                            let other_x = other.offset + other_col * KEY_WIDTH;
+                            let dist = (key_x as isize - other_x as isize).unsigned_abs();
+
+                            let is_adjacent = if other_idx == row_idx {
+                                dist == KEY_WIDTH
+                            } else {
+                                dist < KEY_WIDTH
+                            };
+
+                            let key_neighbours: &mut Vec<u8> = &mut neighbours[key as usize];
+                            if is_adjacent && !key_neighbours.contains(&other_key) {
+                                key_neighbours.push(other_key);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        KeyboardGraph { keys, neighbours }
+    }
+
+    /// builtin qwerty keyboard graph, optionally with the shifted keys layer
+    pub fn qwerty(shifted: bool) -> KeyboardGraph {
+        if shifted {
+            KeyboardGraph::from_layers(&[&QWERTY, &QWERTY_SHIFTED])
+        } else {
+            KeyboardGraph::from_layers(&[&QWERTY])
+        }
+    }
+
+    #[inline]
+    pub fn keys(&self) -> &[u8] {
+        &self.keys
+    }
+
+    #[inline]
+    pub fn neighbours(&self, key: u8) -> &[u8] {
+        &self.neighbours[key as usize]
+    }
+
+    #[inline]
+    pub fn is_adjacent(&self, a: u8, b: u8) -> bool {
+        self.neighbours(a).contains(&b)
+    }
+
+    /// returns the walks with length between `minlen` and `maxlen` (inclusive), generated one
+    /// at a time. a walk is a sequence of keys where each key is adjacent to the previous one.
+    /// the keys and the neighbours of every key are distinct, so are the walks
+    pub fn walks(&self, minlen: usize, maxlen: usize) -> Walks<'_> {
+        Walks {
+            graph: self,
+            len: minlen.max(1),
+            maxlen,
+            walk: Vec::with_capacity(maxlen),
+            next: vec![0; maxlen + 1],
+        }
+    }
+
+    /// returns the number of walks `walks(minlen, maxlen)` returns (saturating), without
+    /// generating them - their number grows exponentially with the length
+    pub fn count_walks(&self, minlen: usize, maxlen: usize) -> u64 {
+        // walks of the current length ending at each key
+        let mut counts = vec![0u64; 256];
+        for &key in self.keys.iter() {
+            counts[key as usize] = 1;
+        }
+
+        let mut total = 0u64;
+        for len in 1..=maxlen {
+            if len >= minlen {
+                total = counts
+                    .iter()
+                    .fold(total, |total, &c| total.saturating_add(c));
+            }
+            let mut next_counts = vec![0u64; 256];
+            for &key in self.keys.iter() {
+                for &next in self.neighbours(key) {
+                    next_counts[next as usize] =
+                        next_counts[next as usize].saturating_add(counts[key as usize]);
+                }
+            }
+            counts = next_counts;
+        }
+        total
+    }
+}
+
+/// the walks of `KeyboardGraph::walks`, shortest first and then depth first from every key
+pub struct Walks<'g> {
+    graph: &'g KeyboardGraph,
+    /// length of the walks generated
+    len: usize,
+    maxlen: usize,
+    walk: Vec<u8>,
+    /// index of the next key tried at every position of the walk, in the keys of the graph for
+    /// the first position and in the neighbours of the previous key for the others
+    next: Vec<usize>,
+}
+
+impl Iterator for Walks<'_> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let graph = self.graph;
+        while self.len <= self.maxlen {
+            let depth = self.walk.len();
+            let candidates = match self.walk.last() {
+                Some(&last) => graph.neighbours(last),
+                None => graph.keys(),
+            };
+            match candidates.get(self.next[depth]) {
+                Some(&key) => {
+                    self.next[depth] += 1;
+                    self.walk.push(key);
+                    if self.walk.len() == self.len {
+                        let walk = self.walk.clone();
+                        self.walk.pop();
+                        return Some(walk);
+                    }
+                    self.next[depth + 1] = 0;
+                }
+                // every walk of this length starting with all the keys was generated
+                None if depth == 0 => {
+                    self.len += 1;
+                    self.next[0] = 0;
+                }
+                None => {
+                    self.walk.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        row_neighbours, shift_table, KeyboardGraph, KeyboardRow, QWERTY_ROW_NEIGHBOURS,
+        QWERTY_SHIFT_TABLE,
+    };
+
+    fn sorted(keys: &[u8]) -> Vec<u8> {
+        let mut keys = keys.to_vec();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn test_qwerty_neighbours() {
+        let graph = KeyboardGraph::qwerty(false);
+        assert_eq!(graph.keys().len(), 47);
+        assert_eq!(sorted(graph.neighbours(b'q')), b"12aw");
+        assert_eq!(sorted(graph.neighbours(b'g')), b"bfhtvy");
+        assert_eq!(sorted(graph.neighbours(b'1')), b"2`q");
+        assert_eq!(sorted(graph.neighbours(b'z')), b"asx");
+        assert_eq!(sorted(graph.neighbours(b'/')), b"'.;");
+        assert!(graph.neighbours(b'Q').is_empty());
+
+        // adjacency is symmetric
+        for &a in graph.keys() {
+            for &b in graph.neighbours(a) {
+                assert!(graph.is_adjacent(b, a));
+            }
+        }
+    }
+
+    #[test]
+    fn test_qwerty_shifted_neighbours() {
+        let graph = KeyboardGraph::qwerty(true);
+        assert_eq!(graph.keys().len(), 94);
+        assert_eq!(sorted(graph.neighbours(b'Q')), b"!@AW");
+        assert_eq!(sorted(graph.neighbours(b'q')), b"12aw");
+        assert!(!graph.is_adjacent(b'q', b'Q'));
+    }
+
+    #[test]
+    fn test_shift_table() {
+        let table = &*QWERTY_SHIFT_TABLE;
+        for (key, shifted) in [(b'1', b'!'), (b'a', b'A'), (b'[', b'{'), (b'/', b'?')] {
+            assert_eq!(table[key as usize], shifted);
+        }
+        // the shifted keys and the bytes which are not keys are unchanged
+        for byte in [b'A', b'!', b' ', b'\t', 0xe9] {
+            assert_eq!(table[byte as usize], byte);
+        }
+
+        let rows = [KeyboardRow::new(0, b"ab")];
+        let shifted = [KeyboardRow::new(0, b"#")];
+        let table = shift_table(&rows, &shifted);
+        assert_eq!((table[b'a' as usize], table[b'b' as usize]), (b'#', b'b'));
+    }
+
+    #[test]
+    fn test_row_neighbours() {
+        let neighbours = &*QWERTY_ROW_NEIGHBOURS;
+        assert_eq!(neighbours[b'p' as usize], b"o[");
+        assert_eq!(neighbours[b'a' as usize], b"s");
+        assert_eq!(neighbours[b'=' as usize], b"-");
+        assert_eq!(neighbours[b'S' as usize], b"AD");
+        assert_eq!(neighbours[b'!' as usize], b"~@");
+        assert!(neighbours[b' ' as usize].is_empty());
+
+        // the first layer of a key wins
+        let first = [KeyboardRow::new(0, b"abc")];
+        let second = [KeyboardRow::new(0, b"xbz")];
+        let neighbours = row_neighbours(&[&first, &second]);
+        assert_eq!(neighbours[b'b' as usize], b"ac");
+        assert_eq!(neighbours[b'x' as usize], b"b");
+    }
+
+    #[test]
+    fn test_walks_examples() {
+        let walks: Vec<_> = KeyboardGraph::qwerty(false).walks(3, 6).collect();
+        for walk in ["qwerty", "1qaz", "asdf", "zaq1", "qaz", "wsx", "poiu"] {
+            assert!(walks.contains(&walk.as_bytes().to_vec()), "{}", walk);
+        }
+        assert!(!walks.contains(&b"qazwsx".to_vec()));
+        assert!(walks.iter().all(|w| (3..=6).contains(&w.len())));
+    }
+
+    #[test]
+    fn test_walks_len4_brute_force() {
+        for shifted in [false, true] {
+            let graph = KeyboardGraph::qwerty(shifted);
+            let keys = graph.keys();
+
+            let mut expected = vec![];
+            for &a in keys {
+                for &b in keys.iter().filter(|&&b| graph.is_adjacent(a, b)) {
+                    for &c in keys.iter().filter(|&&c| graph.is_adjacent(b, c)) {
+                        for &d in keys.iter().filter(|&&d| graph.is_adjacent(c, d)) {
+                            expected.push(vec![a, b, c, d]);
+                        }
+                    }
+                }
+            }
+            expected.sort_unstable();
+
+            let mut walks: Vec<_> = graph.walks(4, 4).collect();
+            let walks_len = walks.len();
+            walks.sort_unstable();
+            walks.dedup();
+            assert_eq!(walks.len(), walks_len);
+            assert_eq!(walks, expected);
+        }
+    }
+
+    #[test]
+    fn test_walks_dedup_custom_layers() {
+        let layer = [KeyboardRow::new(0, b"ab"), KeyboardRow::new(2, b"cd")];
+        let graph = KeyboardGraph::from_layers(&[&layer, &layer]);
+        assert_eq!(graph.keys(), b"abcd");
+        assert_eq!(sorted(graph.neighbours(b'a')), b"bc");
+        assert_eq!(sorted(graph.neighbours(b'd')), b"bc");
+
+        let walks: Vec<_> = graph.walks(1, 2).collect();
+        assert_eq!(walks.len(), 4 + 10);
+        // the shortest walks first, from every key in order
+        assert_eq!(
+            walks[..5],
+            [
+                b"a".to_vec(),
+                b"b".to_vec(),
+                b"c".to_vec(),
+                b"d".to_vec(),
+                b"ab".to_vec()
+            ]
+        );
+        assert_eq!(graph.count_walks(1, 2), 4 + 10);
+    }
+
+    #[test]
+    fn test_count_walks() {
+        for shifted in [false, true] {
+            let graph = KeyboardGraph::qwerty(shifted);
+            for (minlen, maxlen) in [(1, 1), (3, 3), (2, 5), (5, 4)] {
+                assert_eq!(
+                    graph.count_walks(minlen, maxlen),
+                    graph.walks(minlen, maxlen).count() as u64
+                );
+            }
+            assert_eq!(graph.count_walks(1, 500), u64::MAX);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+#[cfg(feature = "cli")]
 extern crate clap;
 #[macro_use(lazy_static)]
 extern crate lazy_static;
@@ -7,16 +8,52 @@ extern crate regex;
 
 use std::error::Error;
 
+pub mod anagram;
+pub mod analyze;
+#[cfg(feature = "async")]
+pub mod async_words;
+pub mod builder;
 pub mod charsets;
+#[cfg(feature = "cli")]
+pub mod config;
+#[cfg(feature = "cli")]
 pub mod create_smartlist;
+pub mod crunch;
+pub mod dates;
+pub mod dedup;
+#[cfg(feature = "cli")]
+pub mod disk_space;
+pub mod error;
+pub mod exclude;
+pub mod external_sort;
+pub mod ffi;
+#[cfg(feature = "cli")]
+mod generate_options;
 pub mod generators;
 pub mod helpers;
+pub mod io_backend;
+pub mod keyboard;
 pub mod mask;
+pub mod model;
+pub mod output_encoding;
 pub mod password_entropy;
+pub mod policygen;
+#[cfg(feature = "python")]
+mod python;
+pub mod regex_mask;
+#[cfg(feature = "cli")]
 pub mod runner;
+pub mod shuffle;
 pub mod stackbuf;
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 pub mod wordlists;
+pub mod words;
+#[cfg(feature = "cli")]
+mod writer_chain;
 
+#[cfg(feature = "cli")]
 pub mod built_info {
     // The file has been placed there by the build script.
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
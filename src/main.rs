@@ -3,6 +3,6 @@ extern crate cracken;
 fn main() {
     if let Err(e) = cracken::runner::run(None) {
         eprintln!("{}", &e);
-        std::process::exit(2);
+        std::process::exit(e.exit_code());
     }
 }
@@ -1,3 +1,7 @@
+use crate::charsets::{Charset, CustomCharset, SYMBOL2CHARSET};
+use crate::dates::DateFormat;
+use crate::error::CrackenError;
+use crate::wordlists::TokenTransform;
 use crate::{BoxResult, MAX_WORD_SIZE};
 use regex::Regex;
 
@@ -7,6 +11,23 @@ pub enum MaskOp {
     BuiltinCharset(char),
     CustomCharset(usize),
     Wordlist(usize),
+    /// wordlist with its words transformed (like ?w1:rev)
+    TransformedWordlist {
+        idx: usize,
+        transform: TokenTransform,
+    },
+    Keywalk {
+        minlen: usize,
+        maxlen: usize,
+        shifted: bool,
+    },
+    Date {
+        format: DateFormat,
+        from_year: u32,
+        to_year: u32,
+    },
+    /// the value chosen at an earlier position of the mask (0-based, never a backreference)
+    Backref(usize),
 }
 
 impl Clone for MaskOp {
@@ -16,14 +37,37 @@ impl Clone for MaskOp {
             MaskOp::BuiltinCharset(ch) => MaskOp::BuiltinCharset(*ch),
             MaskOp::CustomCharset(idx) => MaskOp::CustomCharset(*idx),
             MaskOp::Wordlist(idx) => MaskOp::Wordlist(*idx),
+            MaskOp::TransformedWordlist { idx, transform } => MaskOp::TransformedWordlist {
+                idx: *idx,
+                transform: *transform,
+            },
+            MaskOp::Keywalk {
+                minlen,
+                maxlen,
+                shifted,
+            } => MaskOp::Keywalk {
+                minlen: *minlen,
+                maxlen: *maxlen,
+                shifted: *shifted,
+            },
+            MaskOp::Date {
+                format,
+                from_year,
+                to_year,
+            } => MaskOp::Date {
+                format: format.clone(),
+                from_year: *from_year,
+                to_year: *to_year,
+            },
+            MaskOp::Backref(pos) => MaskOp::Backref(*pos),
         }
     }
 }
 
 /// parses `mask` string into the operations it means
-pub fn parse_mask(mask: &str) -> BoxResult<Vec<MaskOp>> {
+pub fn parse_mask(mask: &str) -> Result<Vec<MaskOp>, CrackenError> {
     if !is_valid_mask(mask) {
-        bail!("Invalid mask");
+        return Err(CrackenError::Mask("Invalid mask".to_string()));
     }
 
     let mut mask_ops = vec![];
@@ -34,7 +78,7 @@ pub fn parse_mask(mask: &str) -> BoxResult<Vec<MaskOp>> {
         let ch = next.unwrap();
         match ch {
             // 1. escaped char (like \?)
-            '\\' => mask_ops.push(MaskOp::Char(chars.next().unwrap())),
+            '\\' => push_char(&mut mask_ops, chars.next().unwrap()),
             // 2. charsets (like ?d)
             '?' => {
                 let next_chr = chars.next().unwrap();
@@ -43,25 +87,657 @@ pub fn parse_mask(mask: &str) -> BoxResult<Vec<MaskOp>> {
                 if next_chr.is_digit(10) {
                     mask_ops.push(MaskOp::CustomCharset(((next_chr as u8) - b'1') as usize))
 
-                // 2.2 wordlist
+                // 2.2 wordlist with optional transform (like ?w1:rev)
                 } else if next_chr == 'w' {
-                    let idx = chars.next().unwrap();
-                    mask_ops.push(MaskOp::Wordlist(((idx as u8) - b'1') as usize));
+                    let idx = ((chars.next().unwrap() as u8) - b'1') as usize;
+                    match parse_token_transform(chars.as_str()) {
+                        Some((transform, len)) => {
+                            chars.nth(len - 1);
+                            mask_ops.push(MaskOp::TransformedWordlist { idx, transform });
+                        }
+                        None => mask_ops.push(MaskOp::Wordlist(idx)),
+                    }
 
-                // 2.3 builtin charset
+                // 2.3 keyboard walk (like ?K{3-5,shift})
+                } else if next_chr == 'K' {
+                    let args: String = chars.by_ref().skip(1).take_while(|&c| c != '}').collect();
+                    let op = parse_keywalk(&args).map_err(|e| CrackenError::Mask(e.to_string()))?;
+                    mask_ops.push(op);
+
+                // 2.4 calendar dates (like ?D{DDMMYYYY,1950-2010})
+                } else if next_chr == 'D' {
+                    let args: String = chars.by_ref().skip(1).take_while(|&c| c != '}').collect();
+                    let op = parse_date(&args).map_err(|e| CrackenError::Mask(e.to_string()))?;
+                    mask_ops.push(op);
+
+                // 2.5 backreference to an earlier position (like ?r1)
+                } else if next_chr == 'r' {
+                    let digits: String = chars
+                        .as_str()
+                        .chars()
+                        .take_while(|c| c.is_ascii_digit())
+                        .collect();
+                    chars.nth(digits.len() - 1);
+                    mask_ops.push(parse_backref(&digits, &mask_ops)?);
+
+                // 2.6 builtin charset
                 } else {
                     mask_ops.push(MaskOp::BuiltinCharset(next_chr))
                 }
             }
             // 3. single char
-            _ => mask_ops.push(MaskOp::Char(ch)),
+            _ => push_char(&mut mask_ops, ch),
         }
         next = chars.next();
     }
+
+    // non ascii chars are longer than a single op
+    if mask_ops.len() >= MAX_WORD_SIZE {
+        return Err(CrackenError::Mask(format!(
+            "mask generates words of {} bytes, the maximum is {}",
+            mask_ops.len(),
+            MAX_WORD_SIZE - 1
+        )));
+    }
     Ok(mask_ops)
 }
 
-pub fn validate_charsets(mask: &[MaskOp], customer_charests_len: usize) -> BoxResult<()> {
+/// parses the 1-based position of a backreference, a backreference to a backreference refers
+/// to the position it repeats
+fn parse_backref(digits: &str, mask_ops: &[MaskOp]) -> Result<MaskOp, CrackenError> {
+    let pos = digits.parse::<usize>().unwrap_or(usize::MAX);
+    match mask_ops.get(pos - 1) {
+        Some(MaskOp::Backref(target)) => Ok(MaskOp::Backref(*target)),
+        Some(_) => Ok(MaskOp::Backref(pos - 1)),
+        None => Err(CrackenError::Mask(format!(
+            "?r{} refers to position {} but it is at position {}, it must refer to an earlier \
+             position",
+            digits,
+            pos,
+            mask_ops.len() + 1
+        ))),
+    }
+}
+
+/// pushes a literal char of the mask as a `MaskOp::Char` for every byte of its utf-8
+/// encoding, since every op generates a single byte
+fn push_char(mask_ops: &mut Vec<MaskOp>, ch: char) {
+    let mut buf = [0; 4];
+    for &c in ch.encode_utf8(&mut buf).as_bytes() {
+        mask_ops.push(MaskOp::Char(c as char));
+    }
+}
+
+/// parses a wordlist token transform (`:rev`, `:shift`, `:adjacent` or a slice like `[1:5]`)
+/// at the start of `rest`, returns the transform and its length in the mask
+fn parse_token_transform(rest: &str) -> Option<(TokenTransform, usize)> {
+    for (name, transform) in [
+        (":rev", TokenTransform::Reverse),
+        (":shift", TokenTransform::Shift),
+        (":adjacent", TokenTransform::Adjacent),
+    ] {
+        if rest.starts_with(name) {
+            return Some((transform, name.len()));
+        }
+    }
+
+    let slice = &rest[..rest.find(']')? + 1];
+    let (start, end) = slice
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .split_once(':')?;
+    let parse_index = |index: &str| -> Option<Option<usize>> {
+        if index.is_empty() {
+            Some(None)
+        } else if index.bytes().all(|c| c.is_ascii_digit()) {
+            index.parse().ok().map(Some)
+        } else {
+            None
+        }
+    };
+    let transform = TokenTransform::Slice {
+        start: parse_index(start)?.unwrap_or(0),
+        end: parse_index(end)?,
+    };
+    Some((transform, slice.len()))
+}
+
+/// parses the args of keyboard walk op - `<len>` or `<minlen>-<maxlen>` with optional `,shift`
+fn parse_keywalk(args: &str) -> BoxResult<MaskOp> {
+    let (lens, shifted) = match args.strip_suffix(",shift") {
+        Some(lens) => (lens, true),
+        None => (args, false),
+    };
+    let (minlen, maxlen) = match lens.split_once('-') {
+        Some((minlen, maxlen)) => (minlen.parse()?, maxlen.parse()?),
+        None => (lens.parse()?, lens.parse()?),
+    };
+    if !(0 < minlen && minlen <= maxlen && maxlen < MAX_WORD_SIZE) {
+        bail!("invalid keyboard walk length: ?K{{{}}}", args);
+    }
+    Ok(MaskOp::Keywalk {
+        minlen,
+        maxlen,
+        shifted,
+    })
+}
+
+/// parses the args of date op - `<format>,<from_year>-<to_year>` (e.g. DDMMYYYY,1950-2010)
+fn parse_date(args: &str) -> BoxResult<MaskOp> {
+    let (format, years) = match args.split_once(',') {
+        Some(v) => v,
+        None => bail!("invalid date: ?D{{{}}} - year range is missing", args),
+    };
+    let (from_year, to_year) = match years.split_once('-') {
+        Some((from_year, to_year)) => (from_year.parse()?, to_year.parse()?),
+        None => bail!("invalid date: ?D{{{}}} - invalid year range", args),
+    };
+    if from_year > to_year {
+        bail!("invalid date: ?D{{{}}} - invalid year range", args);
+    }
+    Ok(MaskOp::Date {
+        format: DateFormat::parse(format)?,
+        from_year,
+        to_year,
+    })
+}
+
+/// syntax of the masks given to generate (--syntax)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaskSyntax {
+    /// cracken's own syntax, a superset of hashcat masks
+    Hashcat,
+    /// John the Ripper mask mode (`john --mask=`), translated by `translate_john_mask`
+    John,
+}
+
+impl MaskSyntax {
+    pub fn from_name(name: &str) -> Option<MaskSyntax> {
+        match name {
+            "hashcat" => Some(MaskSyntax::Hashcat),
+            "john" => Some(MaskSyntax::John),
+            _ => None,
+        }
+    }
+}
+
+/// translates a John the Ripper mask into a cracken mask. the bracket ranges (like `[a-f]`)
+/// and the hex placeholders are returned as charsets which must be added as the custom
+/// charsets following the `n_custom_charsets` given ones, e.g. `[a-c]?d?1` with a single
+/// custom charset is `?2?d?1` with the range charset `abc`
+pub fn translate_john_mask(
+    mask: &str,
+    n_custom_charsets: usize,
+) -> Result<(String, Vec<String>), CrackenError> {
+    let mut translated = String::with_capacity(mask.len());
+    let mut ranges: Vec<String> = vec![];
+    // identical ranges share a custom charset
+    let mut push_range = |translated: &mut String, chars: Vec<u8>| {
+        if let [ch] = chars[..] {
+            push_john_literal(translated, ch as char);
+            return Ok(());
+        }
+        let chars = String::from_utf8(chars).unwrap();
+        let idx = match ranges.iter().position(|range| *range == chars) {
+            Some(pos) => pos,
+            None => {
+                ranges.push(chars);
+                ranges.len() - 1
+            }
+        };
+        if n_custom_charsets + idx >= 9 {
+            return Err(CrackenError::Mask(format!(
+                "john mask {} uses too many ranges, the custom charsets and ranges are limited to 9",
+                mask
+            )));
+        }
+        translated.push('?');
+        translated.push_str(&(n_custom_charsets + idx + 1).to_string());
+        Ok(())
+    };
+
+    let mut chars = mask.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                let ch = parse_john_escape(mask, &mut chars)?;
+                push_john_literal(&mut translated, ch);
+            }
+            '?' => match chars.next() {
+                Some(symbol @ ('l' | 'u' | 'd' | 's' | 'a')) => {
+                    translated.push('?');
+                    translated.push(symbol);
+                }
+                Some('w') => translated.push_str("?w1"),
+                Some(idx @ '1'..='9') => {
+                    let idx = idx as usize - '0' as usize;
+                    if idx > n_custom_charsets {
+                        return Err(CrackenError::Charset(format!(
+                            "mask uses ?{} but only {} custom charsets were provided (-c or --tokens)",
+                            idx, n_custom_charsets
+                        )));
+                    }
+                    translated.push('?');
+                    translated.push_str(&idx.to_string());
+                }
+                Some(symbol) => push_range(&mut translated, john_placeholder(mask, symbol)?)?,
+                None => {
+                    return Err(CrackenError::Mask(format!(
+                        "john mask {} ends with a lone ?",
+                        mask
+                    )))
+                }
+            },
+            '[' => {
+                let range = parse_john_range(mask, &mut chars)?;
+                push_range(&mut translated, range)?;
+            }
+            _ => push_john_literal(&mut translated, ch),
+        }
+    }
+    Ok((translated, ranges))
+}
+
+/// the chars of a john placeholder, the builtin ones are only expanded inside ranges
+fn john_placeholder(mask: &str, symbol: char) -> Result<Vec<u8>, CrackenError> {
+    match symbol {
+        'l' | 'u' | 'd' | 's' | 'a' => Ok(SYMBOL2CHARSET
+            .iter()
+            .find(|charset| charset.symbol == symbol)
+            .unwrap()
+            .chars
+            .to_vec()),
+        'h' => Ok(b"0123456789abcdef".to_vec()),
+        'H' => Ok(b"0123456789ABCDEF".to_vec()),
+        // 8-bit and codepage dependent placeholders
+        'b' | 'B' | 'L' | 'U' | 'D' | 'S' | 'A' | 'x' | 'X' | 'y' | 'Y' | 'o' | 'O' | '0' => {
+            Err(CrackenError::Unsupported(format!(
+                "john mask {} - placeholder ?{} is not supported, use a range (e.g. [\\x21-\\x7e]) instead",
+                mask, symbol
+            )))
+        }
+        _ => Err(CrackenError::Mask(format!(
+            "john mask {} - unknown placeholder ?{} (escape a literal ? as \\?)",
+            mask, symbol
+        ))),
+    }
+}
+
+/// parses the escaped char following a backslash - a literal char or a `\xHH` ascii char
+fn parse_john_escape(mask: &str, chars: &mut std::str::Chars) -> Result<char, CrackenError> {
+    match chars.next() {
+        Some('x') => {
+            let hex: String = chars.by_ref().take(2).collect();
+            match u8::from_str_radix(&hex, 16) {
+                Ok(ch) if hex.len() == 2 && ch.is_ascii() && ch != 0 => Ok(ch as char),
+                Ok(_) if hex.len() == 2 => Err(CrackenError::Unsupported(format!(
+                    "john mask {} - only ascii chars except NUL are supported, got \\x{}",
+                    mask, hex
+                ))),
+                _ => Err(CrackenError::Mask(format!(
+                    "john mask {} - invalid escape \\x{}",
+                    mask, hex
+                ))),
+            }
+        }
+        Some(ch) => Ok(ch),
+        None => Err(CrackenError::Mask(format!(
+            "john mask {} ends with a lone \\",
+            mask
+        ))),
+    }
+}
+
+/// parses a bracket range following `[` (e.g. `[a-f0-9?s]`), returns its chars deduplicated
+/// in order of appearance
+fn parse_john_range(mask: &str, chars: &mut std::str::Chars) -> Result<Vec<u8>, CrackenError> {
+    let mut range: Vec<char> = vec![];
+    let mut closed = false;
+    while let Some(ch) = chars.next() {
+        match ch {
+            ']' => {
+                closed = true;
+                break;
+            }
+            '?' => {
+                let symbol = chars.next().unwrap_or('?');
+                range.extend(john_placeholder(mask, symbol)?.into_iter().map(char::from));
+            }
+            // a dash between two chars (like a-z), a leading or trailing dash is a literal
+            '-' if !range.is_empty() && !chars.as_str().starts_with(']') => {
+                let from = *range.last().unwrap();
+                let to = match chars.next() {
+                    Some('\\') => parse_john_escape(mask, chars)?,
+                    Some(to) => to,
+                    None => break,
+                };
+                if from <= to {
+                    range.extend((from..=to).skip(1));
+                } else {
+                    range.extend((to..from).rev());
+                }
+            }
+            '\\' => range.push(parse_john_escape(mask, chars)?),
+            _ => range.push(ch),
+        }
+    }
+    if !closed {
+        return Err(CrackenError::Mask(format!(
+            "john mask {} has an unclosed [",
+            mask
+        )));
+    }
+    if range.iter().any(|ch| !ch.is_ascii()) {
+        return Err(CrackenError::Unsupported(format!(
+            "john mask {} - only ascii chars are supported in ranges",
+            mask
+        )));
+    }
+    let mut chars = vec![];
+    for ch in range {
+        if !chars.contains(&(ch as u8)) {
+            chars.push(ch as u8);
+        }
+    }
+    if chars.is_empty() {
+        return Err(CrackenError::Mask(format!(
+            "john mask {} has an empty []",
+            mask
+        )));
+    }
+    Ok(chars)
+}
+
+/// pushes a literal char escaped for the cracken mask
+fn push_john_literal(translated: &mut String, ch: char) {
+    if ch == '?' || ch == '\\' {
+        translated.push('\\');
+    }
+    translated.push(ch);
+}
+
+/// where `--join` inserts its separator in a mask
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinMode {
+    /// between all the items of the mask
+    All,
+    /// only next to the wordlist items, e.g. `?w1?w2?d?d` joined with `-` is `?w1-?w2-?d?d`
+    Wordlists,
+}
+
+impl JoinMode {
+    pub fn from_name(name: &str) -> Option<JoinMode> {
+        match name {
+            "all" => Some(JoinMode::All),
+            "wordlists" => Some(JoinMode::Wordlists),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ItemKind {
+    /// literal chars, consecutive ones are a single item
+    Literal,
+    Wordlist,
+    Backref,
+    /// charsets, keyboard walks and dates
+    Other,
+}
+
+/// splits a valid mask into its top level items, returns their kind, range in the mask and
+/// number of mask ops (positions)
+fn mask_items(mask: &str) -> Vec<(ItemKind, usize, usize, usize)> {
+    let mut items: Vec<(ItemKind, usize, usize, usize)> = vec![];
+    let mut pos = 0;
+    while let Some(ch) = mask[pos..].chars().next() {
+        let start = pos;
+        let mut rest = mask[pos + ch.len_utf8()..].chars();
+        let (kind, len, ops) = match (ch, rest.next()) {
+            ('\\', Some(escaped)) => (
+                ItemKind::Literal,
+                1 + escaped.len_utf8(),
+                escaped.len_utf8(),
+            ),
+            ('?', Some('w')) => {
+                let len = "?w1".len();
+                let transform = parse_token_transform(&mask[pos + len..]).map_or(0, |(_, len)| len);
+                (ItemKind::Wordlist, len + transform, 1)
+            }
+            ('?', Some('K' | 'D')) => (ItemKind::Other, mask[pos..].find('}').unwrap() + 1, 1),
+            ('?', Some('r')) => {
+                let digits = rest.take_while(|c| c.is_ascii_digit()).count();
+                (ItemKind::Backref, 2 + digits, 1)
+            }
+            ('?', Some(symbol)) => (ItemKind::Other, 1 + symbol.len_utf8(), 1),
+            _ => (ItemKind::Literal, ch.len_utf8(), ch.len_utf8()),
+        };
+        pos += len;
+        match items.last_mut() {
+            Some((ItemKind::Literal, _, end, item_ops)) if kind == ItemKind::Literal => {
+                *end = pos;
+                *item_ops += ops;
+            }
+            _ => items.push((kind, start, pos, ops)),
+        }
+    }
+    items
+}
+
+/// inserts `separator` between the top level items of `mask` (not inside them and not at its
+/// ends), e.g. `?w1?w2?d?d` joined with `-` is `?w1-?w2-?d?d` with `JoinMode::Wordlists` and
+/// `?w1-?w2-?d-?d` with `JoinMode::All`. consecutive literal chars are a single item. the
+/// backreferences are renumbered to the positions they repeat
+pub fn join_mask(mask: &str, separator: &str, mode: JoinMode) -> Result<String, CrackenError> {
+    let mask_ops = parse_mask(mask)?;
+    let escaped: String = separator.chars().flat_map(|ch| ['\\', ch]).collect();
+    let separator_ops = separator.len();
+    let mut joined = String::with_capacity(mask.len());
+    let mut last: Option<ItemKind> = None;
+    // the joined position of every position of the mask
+    let mut positions = Vec::with_capacity(mask_ops.len());
+    for (kind, start, end, ops) in mask_items(mask) {
+        // a backreference to a wordlist is joined as the wordlist
+        let (kind, item) = match (kind, &mask_ops[positions.len()]) {
+            (ItemKind::Backref, MaskOp::Backref(target)) => {
+                let kind = match mask_ops[*target] {
+                    MaskOp::Wordlist(_) | MaskOp::TransformedWordlist { .. } => ItemKind::Wordlist,
+                    _ => ItemKind::Other,
+                };
+                (kind, format!("?r{}", positions[*target] + 1))
+            }
+            _ => (kind, mask[start..end].to_string()),
+        };
+        let join = match (last, mode) {
+            (None, _) => false,
+            (Some(_), JoinMode::All) => true,
+            (Some(last), JoinMode::Wordlists) => {
+                last == ItemKind::Wordlist || kind == ItemKind::Wordlist
+            }
+        };
+        let mut joined_pos = positions.last().map_or(0, |&pos| pos + 1);
+        if join {
+            joined.push_str(&escaped);
+            joined_pos += separator_ops;
+        }
+        joined.push_str(&item);
+        positions.extend(joined_pos..joined_pos + ops);
+        last = Some(kind);
+    }
+    Ok(joined)
+}
+
+/// the chars of a literal item of a mask with the number of mask ops of each (the bytes of
+/// its utf-8 encoding), an escaped char includes its backslash
+fn literal_chars(literal: &str) -> Vec<(&str, usize)> {
+    let mut chars = vec![];
+    let mut idx = 0;
+    while let Some(ch) = literal[idx..].chars().next() {
+        let (len, ops) = match ch {
+            '\\' => {
+                let escaped = literal[idx + 1..].chars().next().unwrap().len_utf8();
+                (1 + escaped, escaped)
+            }
+            _ => (ch.len_utf8(), ch.len_utf8()),
+        };
+        chars.push((&literal[idx..idx + len], ops));
+        idx += len;
+    }
+    chars
+}
+
+/// rewrites `mask` so its words read the same forwards and backwards, the positions of its
+/// second half are backreferences to the mirrored positions of its first half, e.g.
+/// `?d?d?d?d` is `?d?d?r2?r1`. the mirrored positions must have the same chars
+pub fn palindrome_mask(
+    mask: &str,
+    custom_charsets: &[CustomCharset],
+) -> Result<String, CrackenError> {
+    let mask_ops = parse_mask(mask)?;
+    validate_charsets(&mask_ops, custom_charsets.len())?;
+    let unsupported = || {
+        Err(CrackenError::Unsupported(format!(
+            "mask {} - palindromes support charset masks only, without wordlists, tokens, \
+             keyboard walks, dates and backreferences",
+            mask
+        )))
+    };
+    let mut positions = Vec::with_capacity(mask_ops.len());
+    for op in mask_ops.iter() {
+        positions.push(match op {
+            MaskOp::Char(ch) => vec![*ch as u8],
+            MaskOp::BuiltinCharset(symbol) => Charset::from_symbol(*symbol).chars(),
+            MaskOp::CustomCharset(idx) => match custom_charsets[*idx] {
+                CustomCharset::Chars(chars) => Charset::from_chars(chars.as_bytes()).chars(),
+                CustomCharset::Tokens(_) => return unsupported(),
+            },
+            _ => return unsupported(),
+        });
+    }
+    let len = positions.len();
+    for pos in 0..len / 2 {
+        if positions[pos] != positions[len - 1 - pos] {
+            return Err(CrackenError::Mask(format!(
+                "mask {} can't be a palindrome, position {} and its mirrored position {} have \
+                 different chars",
+                mask,
+                pos + 1,
+                len - pos
+            )));
+        }
+    }
+
+    let half = len.div_ceil(2);
+    let mut palindrome = String::with_capacity(mask.len());
+    let mut pos = 0;
+    for (kind, start, end, ops) in mask_items(mask) {
+        // the chars of a literal item can be on both halves
+        let tokens = match kind {
+            ItemKind::Literal => literal_chars(&mask[start..end]),
+            _ => vec![(&mask[start..end], ops)],
+        };
+        for (token, ops) in tokens {
+            if pos < half {
+                palindrome.push_str(token);
+            } else {
+                for op in pos..pos + ops {
+                    palindrome.push_str(&format!("?r{}", len - op));
+                }
+            }
+            pos += ops;
+        }
+    }
+    Ok(palindrome)
+}
+
+/// most custom charsets before the mask of a hcmask line, hashcat's -1 to -4
+pub const HCMASK_MAX_CHARSETS: usize = 4;
+
+/// parses a line of a hashcat `.hcmask` file, up to 4 comma separated custom charsets and the
+/// mask, e.g. `?l?d,?1?1?d`. `\,` is a literal comma. returns the resolved charsets and the mask
+pub fn parse_hcmask_line(line: &str) -> Result<(Vec<String>, String), CrackenError> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&',') => {
+                fields.last_mut().unwrap().push(chars.next().unwrap());
+            }
+            ',' => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(ch),
+        }
+    }
+    let mask = fields.pop().unwrap();
+    if fields.len() > HCMASK_MAX_CHARSETS {
+        return Err(CrackenError::Charset(format!(
+            "hcmask line {} has {} custom charsets, at most {} are supported",
+            line,
+            fields.len(),
+            HCMASK_MAX_CHARSETS
+        )));
+    }
+    let mut charsets: Vec<String> = vec![];
+    for field in fields.iter() {
+        let charset = resolve_hcmask_charset(field, &charsets)?;
+        charsets.push(charset);
+    }
+    Ok((charsets, mask))
+}
+
+/// the chars of a hcmask custom charset, the builtin charsets (e.g. `?l?d`) and the previous
+/// custom charsets of the line (`?1`) are expanded, every char is kept once
+fn resolve_hcmask_charset(charset: &str, previous: &[String]) -> Result<String, CrackenError> {
+    let mut resolved = String::new();
+    let mut push = |ch: char| {
+        if !resolved.contains(ch) {
+            resolved.push(ch);
+        }
+    };
+    let mut chars = charset.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '?' {
+            push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('?') => push('?'),
+            Some(symbol @ ('l' | 'u' | 'd' | 's' | 'a')) => SYMBOL2CHARSET
+                .iter()
+                .find(|charset| charset.symbol == symbol)
+                .unwrap()
+                .chars
+                .iter()
+                .for_each(|&ch| push(ch as char)),
+            Some(idx @ '1'..='9') if (idx as usize - '1' as usize) < previous.len() => {
+                previous[idx as usize - '1' as usize].chars().for_each(&mut push)
+            }
+            Some(symbol) => {
+                return Err(CrackenError::Charset(format!(
+                    "hcmask charset {} uses ?{} which is not a builtin charset or a previous custom charset",
+                    charset, symbol
+                )))
+            }
+            None => {
+                return Err(CrackenError::Charset(format!(
+                    "hcmask charset {} ends with a lone ?",
+                    charset
+                )))
+            }
+        }
+    }
+    if resolved.is_empty() {
+        return Err(CrackenError::Charset(
+            "hcmask line has an empty custom charset".to_string(),
+        ));
+    }
+    Ok(resolved)
+}
+
+/// ensures all custom charsets (?1 to ?9) on the mask were provided
+pub fn validate_charsets(
+    mask: &[MaskOp],
+    customer_charests_len: usize,
+) -> Result<(), CrackenError> {
     let max_charset_len = mask
         .iter()
         .filter_map(|op| match op {
@@ -73,21 +749,23 @@ pub fn validate_charsets(mask: &[MaskOp], customer_charests_len: usize) -> BoxRe
         None => {}
         Some(&n) => {
             if n >= customer_charests_len {
-                bail!(
-                    "mask contains unspecified custom charset: ?{} - please add use -c \"<chars>\"",
-                    n + 1
-                );
+                return Err(CrackenError::Charset(format!(
+                    "mask uses ?{} but only {} custom charsets were provided (-c or --tokens)",
+                    n + 1,
+                    customer_charests_len
+                )));
             }
         }
     }
     Ok(())
 }
 
-pub fn validate_wordlists(mask: &[MaskOp], wordlists_len: usize) -> BoxResult<()> {
+/// ensures all wordlists (?w1 to ?w9) on the mask were provided
+pub fn validate_wordlists(mask: &[MaskOp], wordlists_len: usize) -> Result<(), CrackenError> {
     let max_wordlist_len = mask
         .iter()
         .filter_map(|op| match op {
-            MaskOp::Wordlist(idx) => Some(idx),
+            MaskOp::Wordlist(idx) | MaskOp::TransformedWordlist { idx, .. } => Some(idx),
             _ => None,
         })
         .max();
@@ -95,10 +773,11 @@ pub fn validate_wordlists(mask: &[MaskOp], wordlists_len: usize) -> BoxResult<()
         None => {}
         Some(&n) => {
             if n >= wordlists_len {
-                bail!(
-                    "mask contains unspecified wordlist: ?w{} - please add -w <wordlist_file>",
-                    n + 1
-                );
+                return Err(CrackenError::Mask(format!(
+                    "mask uses ?w{} but only {} wordlists were provided (-w)",
+                    n + 1,
+                    wordlists_len
+                )));
             }
         }
     }
@@ -110,7 +789,7 @@ fn is_valid_mask(mask: &str) -> bool {
     lazy_static! {
         static ref RE: Regex = Regex::new(
             format!(
-                r"^(\?[ludsab1-9]|\?w[1-9]|\\.|[^?\\]){{1,{}}}$",
+                r"^(\?[ludsab1-9]|\?r[1-9][0-9]*|\?w[1-9](:rev|:shift|:adjacent|\[[0-9]*:[0-9]*\])?|\?K\{{[0-9]+(-[0-9]+)?(,shift)?\}}|\?D\{{[DMY./_-]+,[0-9]{{4}}-[0-9]{{4}}\}}|\\.|[^?\\]){{1,{}}}$",
                 MAX_WORD_SIZE - 1
             )
             .as_str()
@@ -122,7 +801,14 @@ fn is_valid_mask(mask: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::{is_valid_mask, parse_mask, MaskOp};
+    use super::{
+        is_valid_mask, join_mask, palindrome_mask, parse_hcmask_line, parse_mask,
+        translate_john_mask, validate_charsets, validate_wordlists, JoinMode, MaskOp,
+    };
+    use crate::charsets::CustomCharset;
+    use crate::dates::DateFormat;
+    use crate::error::CrackenError;
+    use crate::wordlists::TokenTransform;
 
     #[test]
     fn test_is_valid_mask() {
@@ -131,12 +817,44 @@ mod tests {
             "?l?u?a?b?s",
             "abc?l?u?a?b?sdef?1?2?3",
             "?a?b\\?",
+            "?K{4}?d",
+            "?K{3-5,shift}!",
+            "?D{DDMMYYYY,1950-2010}",
+            "?D{DD-MM-YY,1950-2010}?d",
+            "?w1:rev?d",
+            "?w1?w1:rev",
+            "?w1:shift?w2:adjacent",
+            "?w1:re",
+            "?w1[:4]?d?d?d?d",
+            "?w1[2:]",
+            "?w1[1:5]?w1[:]",
+            "?w1[a]",
+            "?d?r1",
+            "?w1?d?r12",
         ];
         for mask in valid_masks {
             assert!(is_valid_mask(mask));
         }
 
-        let invalid_masks = vec!["", "?", "?x", "??", "?"];
+        let invalid_masks = vec![
+            "",
+            "?",
+            "?x",
+            "??",
+            "?",
+            "?K",
+            "?K{}",
+            "?K{a}",
+            "?K{3-}",
+            "?K{3,x}",
+            "?D{}",
+            "?D{DDMM}",
+            "?D{DDMM,1990}",
+            "?D{DDMM,90-99}",
+            "?D{AA,1990-1999}",
+            "?r",
+            "?d?r0",
+        ];
         for mask in invalid_masks {
             assert!(!is_valid_mask(mask));
         }
@@ -182,4 +900,384 @@ mod tests {
             assert_eq!(mask_ops, expected);
         }
     }
+
+    #[test]
+    fn test_parse_mask_utf8_chars() {
+        assert_eq!(
+            parse_mask("é?d\\€").unwrap(),
+            vec![
+                MaskOp::Char('\u{c3}'),
+                MaskOp::Char('\u{a9}'),
+                MaskOp::BuiltinCharset('d'),
+                MaskOp::Char('\u{e2}'),
+                MaskOp::Char('\u{82}'),
+                MaskOp::Char('\u{ac}'),
+            ]
+        );
+        assert!(parse_mask(&"é".repeat(255)).is_ok());
+        // 256 chars but 512 bytes
+        assert!(matches!(
+            parse_mask(&"é".repeat(256)),
+            Err(CrackenError::Mask(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_mask_keywalk() {
+        assert_eq!(
+            parse_mask("?K{4}?d?K{2-6,shift}").unwrap(),
+            vec![
+                MaskOp::Keywalk {
+                    minlen: 4,
+                    maxlen: 4,
+                    shifted: false
+                },
+                MaskOp::BuiltinCharset('d'),
+                MaskOp::Keywalk {
+                    minlen: 2,
+                    maxlen: 6,
+                    shifted: true
+                },
+            ]
+        );
+        assert!(parse_mask("?K{0}").is_err());
+        assert!(parse_mask("?K{5-3}").is_err());
+    }
+
+    #[test]
+    fn test_parse_mask_date() {
+        assert_eq!(
+            parse_mask("?D{DDMMYYYY,1950-2010}!").unwrap(),
+            vec![
+                MaskOp::Date {
+                    format: DateFormat::parse("DDMMYYYY").unwrap(),
+                    from_year: 1950,
+                    to_year: 2010,
+                },
+                MaskOp::Char('!'),
+            ]
+        );
+        assert!(parse_mask("?D{DDMMYYYY,2010-1950}").is_err());
+        assert!(parse_mask("?D{YYY,1950-2010}").is_err());
+    }
+
+    #[test]
+    fn test_validate_charsets() {
+        let mask = parse_mask("?1?d?2?w1").unwrap();
+        assert!(validate_charsets(&mask, 2).is_ok());
+        assert!(validate_charsets(&mask, 3).is_ok());
+        assert_eq!(
+            validate_charsets(&mask, 1).unwrap_err().to_string(),
+            "mask uses ?2 but only 1 custom charsets were provided (-c or --tokens)"
+        );
+        assert!(validate_charsets(&parse_mask("?d").unwrap(), 0).is_ok());
+    }
+
+    #[test]
+    fn test_parse_mask_transformed_wordlist() {
+        assert_eq!(
+            parse_mask("?w1?w1:rev?d:re\\:rev").unwrap(),
+            vec![
+                MaskOp::Wordlist(0),
+                MaskOp::TransformedWordlist {
+                    idx: 0,
+                    transform: TokenTransform::Reverse
+                },
+                MaskOp::BuiltinCharset('d'),
+                MaskOp::Char(':'),
+                MaskOp::Char('r'),
+                MaskOp::Char('e'),
+                MaskOp::Char(':'),
+                MaskOp::Char('r'),
+                MaskOp::Char('e'),
+                MaskOp::Char('v'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_mask_keyboard_transforms() {
+        let transformed = |transform| MaskOp::TransformedWordlist { idx: 0, transform };
+        assert_eq!(
+            parse_mask("?w1:shift?w1:adjacent?w1:shifted").unwrap(),
+            vec![
+                transformed(TokenTransform::Shift),
+                transformed(TokenTransform::Adjacent),
+                transformed(TokenTransform::Shift),
+                MaskOp::Char('e'),
+                MaskOp::Char('d'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_mask_sliced_wordlist() {
+        let slice = |start, end| MaskOp::TransformedWordlist {
+            idx: 0,
+            transform: TokenTransform::Slice { start, end },
+        };
+        assert_eq!(
+            parse_mask("?w1[:4]?d").unwrap(),
+            vec![slice(0, Some(4)), MaskOp::BuiltinCharset('d')]
+        );
+        assert_eq!(parse_mask("?w1[2:]").unwrap(), vec![slice(2, None)]);
+        assert_eq!(
+            parse_mask("?w1[1:5]?w1[:]").unwrap(),
+            vec![slice(1, Some(5)), slice(0, None)]
+        );
+        assert_eq!(
+            parse_mask("?w1[a]").unwrap(),
+            vec![
+                MaskOp::Wordlist(0),
+                MaskOp::Char('['),
+                MaskOp::Char('a'),
+                MaskOp::Char(']')
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_mask_backref() {
+        assert_eq!(
+            parse_mask("?d?l?r1").unwrap(),
+            vec![
+                MaskOp::BuiltinCharset('d'),
+                MaskOp::BuiltinCharset('l'),
+                MaskOp::Backref(0)
+            ]
+        );
+        // all the digits are the position, a backreference to a backreference repeats its position
+        let mask_ops = parse_mask("abcdefghij?w1?r11?r12x").unwrap();
+        assert_eq!(mask_ops[11], MaskOp::Backref(10));
+        assert_eq!(mask_ops[12], MaskOp::Backref(10));
+        assert_eq!(mask_ops[13], MaskOp::Char('x'));
+        assert_eq!(parse_mask("?d?r1\\2").unwrap()[2], MaskOp::Char('2'));
+        // only the earlier positions
+        for mask in ["?r1", "?d?r2", "?d?r3", "?d?r99999999999999999999999"] {
+            assert!(
+                matches!(parse_mask(mask), Err(CrackenError::Mask(_))),
+                "{}",
+                mask
+            );
+        }
+    }
+
+    #[test]
+    fn test_join_mask() {
+        for (mask, all, wordlists) in [
+            ("?w1?w2?d?d", "?w1+?w2+?d+?d", "?w1+?w2+?d?d"),
+            (
+                "?d?w1:rev?w2[1:3]",
+                "?d+?w1:rev+?w2[1:3]",
+                "?d+?w1:rev+?w2[1:3]",
+            ),
+            (
+                "ab\\?c?w1?K{3-4}",
+                "ab\\?c+?w1+?K{3-4}",
+                "ab\\?c+?w1+?K{3-4}",
+            ),
+            (
+                "?D{YYYY,1990-2000}?1é",
+                "?D{YYYY,1990-2000}+?1+é",
+                "?D{YYYY,1990-2000}?1é",
+            ),
+            ("?w1", "?w1", "?w1"),
+            // the backreferences are renumbered, a repeated wordlist is joined as a wordlist
+            ("ab?d?r3", "ab+?d+?r4", "ab?d?r3"),
+            ("?w1?l?r1", "?w1+?l+?r1", "?w1+?l+?r1"),
+            ("?d?r1?r2", "?d+?r1+?r1", "?d?r1?r1"),
+        ] {
+            let escaped = |joined: &str| joined.replace('+', "\\+");
+            assert_eq!(join_mask(mask, "+", JoinMode::All).unwrap(), escaped(all));
+            assert_eq!(
+                join_mask(mask, "+", JoinMode::Wordlists).unwrap(),
+                escaped(wordlists)
+            );
+        }
+        // the separator is literal, even a mask placeholder
+        assert_eq!(
+            join_mask("?w1?w2", "?d", JoinMode::All).unwrap(),
+            "?w1\\?\\d?w2"
+        );
+        assert!(parse_mask(&join_mask("?w1?w2", "?d", JoinMode::All).unwrap()).is_ok());
+        assert!(join_mask("?w1?", "-", JoinMode::All).is_err());
+    }
+
+    #[test]
+    fn test_palindrome_mask() {
+        let letters = CustomCharset::Chars("zyxwvutsrqponmlkjihgfedcba");
+        let charsets = [CustomCharset::Chars("abc"), letters];
+        for (mask, palindrome) in [
+            ("?d?d?d?d", "?d?d?r2?r1"),
+            ("?l?d?l", "?l?d?r1"),
+            ("ab?dba", "ab?d?r2?r1"),
+            ("a\\?\\?a", "a\\??r2?r1"),
+            ("?1?d?1", "?1?d?r1"),
+            // the same chars in another charset
+            ("?2?l", "?2?r1"),
+            ("x", "x"),
+        ] {
+            assert_eq!(
+                palindrome_mask(mask, &charsets).unwrap(),
+                palindrome,
+                "{}",
+                mask
+            );
+            assert!(parse_mask(palindrome).is_ok());
+        }
+        for mask in ["?d?l", "ab?db", "?1?d?2", "é?dé"] {
+            assert!(
+                matches!(palindrome_mask(mask, &charsets), Err(CrackenError::Mask(_))),
+                "{}",
+                mask
+            );
+        }
+        for mask in ["?w1?w1", "?d?r1", "?K{3}"] {
+            assert!(
+                matches!(
+                    palindrome_mask(mask, &charsets),
+                    Err(CrackenError::Unsupported(_))
+                ),
+                "{}",
+                mask
+            );
+        }
+        let tokens = [CustomCharset::Tokens("ab|c")];
+        assert!(palindrome_mask("?1?1", &tokens).is_err());
+        assert!(palindrome_mask("?3", &charsets).is_err());
+    }
+
+    #[test]
+    fn test_parse_hcmask_line() {
+        let line = |charsets: &[&str], mask: &str| {
+            let charsets = charsets.iter().map(|c| c.to_string()).collect();
+            (charsets, mask.to_string())
+        };
+        assert_eq!(parse_hcmask_line("?d?d").unwrap(), line(&[], "?d?d"));
+        assert_eq!(
+            parse_hcmask_line("?d?u,ab,?1?2").unwrap(),
+            line(&["0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ", "ab"], "?1?2")
+        );
+        // ?2 includes ?1, the repeated chars are kept once
+        assert_eq!(
+            parse_hcmask_line("xy,?1z?1,??\\,,?2?3").unwrap(),
+            line(&["xy", "xyz", "?,"], "?2?3")
+        );
+        assert_eq!(
+            parse_hcmask_line("\\,.,x?1\\,").unwrap(),
+            line(&[",."], "x?1,")
+        );
+        assert_eq!(parse_hcmask_line("").unwrap(), line(&[], ""));
+
+        for (bad, err) in [
+            (
+                "a,b,c,d,e,?1",
+                "hcmask line a,b,c,d,e,?1 has 5 custom charsets, at most 4 are supported",
+            ),
+            (
+                "?2,?1",
+                "hcmask charset ?2 uses ?2 which is not a builtin charset or a previous custom charset",
+            ),
+            ("?b,?1", "hcmask charset ?b uses ?b which is not a builtin charset or a previous custom charset"),
+            ("a?,?1", "hcmask charset a? ends with a lone ?"),
+            (",?1", "hcmask line has an empty custom charset"),
+        ] {
+            let e = parse_hcmask_line(bad).unwrap_err();
+            assert!(matches!(e, CrackenError::Charset(_)));
+            assert_eq!(e.to_string(), err);
+        }
+    }
+
+    #[test]
+    fn test_validate_wordlists() {
+        let mask = parse_mask("?w1?d?w3:rev?1").unwrap();
+        assert!(validate_wordlists(&mask, 3).is_ok());
+        assert_eq!(
+            validate_wordlists(&mask, 2).unwrap_err().to_string(),
+            "mask uses ?w3 but only 2 wordlists were provided (-w)"
+        );
+        assert_eq!(
+            validate_wordlists(&mask, 0).unwrap_err().to_string(),
+            "mask uses ?w3 but only 0 wordlists were provided (-w)"
+        );
+    }
+
+    #[test]
+    fn test_translate_john_mask() {
+        let translate = |mask: &str, n_custom_charsets: usize| {
+            let (mask, ranges) = translate_john_mask(mask, n_custom_charsets).unwrap();
+            let ranges: Vec<&str> = ranges.iter().map(|r| r.as_str()).collect();
+            (mask, ranges.join(","))
+        };
+        // placeholders shared with cracken and ?w
+        assert_eq!(translate("?u?l?d?s?a", 0), ("?u?l?d?s?a".into(), "".into()));
+        assert_eq!(translate("?w?d?d", 0), ("?w1?d?d".into(), "".into()));
+        // ranges follow the given custom charsets, identical ranges share one
+        assert_eq!(
+            translate("[Pp]ass[a-c]?1[pP]", 1),
+            ("?2ass?3?1?4".into(), "Pp,abc,pP".into())
+        );
+        assert_eq!(translate("[ab][ab]", 0), ("?1?1".into(), "ab".into()));
+        assert_eq!(
+            translate("?h?H", 0),
+            ("?1?2".into(), "0123456789abcdef,0123456789ABCDEF".into())
+        );
+        // escapes, placeholders and dashes in ranges, duplicates are removed
+        assert_eq!(
+            translate("[\\x41-\\x43][?dx][-a-b-][c-a][a-ca]", 0),
+            ("?1?2?3?4?5".into(), "ABC,0123456789x,-ab,cba,abc".into())
+        );
+        // single char ranges and escaped chars are literals, cracken's escapes are added
+        assert_eq!(
+            translate("[\\?]\\x3f\\?\\[[\\\\]", 0),
+            ("\\?\\?\\?[\\\\".into(), "".into())
+        );
+
+        // the translated masks are valid
+        let (mask, ranges) = translate_john_mask("[a-c]\\x2a?w?d?h", 0).unwrap();
+        assert_eq!(mask, "?1*?w1?d?2");
+        assert!(parse_mask(&mask).is_ok());
+        assert!(validate_charsets(&parse_mask(&mask).unwrap(), ranges.len()).is_ok());
+    }
+
+    #[test]
+    fn test_translate_john_mask_errors() {
+        let err = |mask: &str, n_custom_charsets: usize| {
+            translate_john_mask(mask, n_custom_charsets).unwrap_err()
+        };
+        assert!(matches!(err("?b?d", 0), CrackenError::Unsupported(_)));
+        assert!(matches!(err("[?B]", 0), CrackenError::Unsupported(_)));
+        assert!(matches!(err("[é]", 0), CrackenError::Unsupported(_)));
+        assert!(matches!(err("\\xe9", 0), CrackenError::Unsupported(_)));
+        assert!(matches!(err("?2", 1), CrackenError::Charset(_)));
+        assert_eq!(
+            err("?b", 0).to_string(),
+            "john mask ?b - placeholder ?b is not supported, use a range (e.g. [\\x21-\\x7e]) instead"
+        );
+        assert_eq!(
+            err("?d?", 0).to_string(),
+            "john mask ?d? ends with a lone ?"
+        );
+        assert_eq!(
+            err("?d\\", 0).to_string(),
+            "john mask ?d\\ ends with a lone \\"
+        );
+        assert_eq!(
+            err("[a-c?d", 0).to_string(),
+            "john mask [a-c?d has an unclosed ["
+        );
+        assert_eq!(err("a[]", 0).to_string(), "john mask a[] has an empty []");
+        assert_eq!(
+            err("\\x4", 0).to_string(),
+            "john mask \\x4 - invalid escape \\x4"
+        );
+        assert_eq!(
+            err("?q", 0).to_string(),
+            "john mask ?q - unknown placeholder ?q (escape a literal ? as \\?)"
+        );
+        assert_eq!(
+            err("[ab][cd][ef]", 7).to_string(),
+            "john mask [ab][cd][ef] uses too many ranges, the custom charsets and ranges are limited to 9"
+        );
+    }
 }
@@ -0,0 +1,435 @@
+//! a model of the chars at every position of the passwords of a corpus (`cracken train`),
+//! orders the words of a charset mask from the most likely (`--order`)
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::io::{self, Read, Write};
+
+use num_bigint::BigUint;
+
+use crate::charsets::CustomCharset;
+use crate::error::CrackenError;
+use crate::generators::{
+    max_written_len, write_word, CharsetGenerator, LengthOrder, RepeatLimits, WordGenerator,
+    WordRank,
+};
+use crate::mask::{parse_mask, validate_charsets, MaskOp};
+use crate::stackbuf::StackBuf;
+use crate::{BUFFER_SIZE, MAX_WORD_SIZE};
+
+/// first bytes of the model files
+pub const MODEL_MAGIC: &[u8] = b"CRACKEN-MODEL 1\n";
+/// default maximum number of partial words kept by the ordered generation
+pub const DEFAULT_BEAM: usize = 1_000_000;
+
+/// occurrences of every byte at every position and of every length in a passwords corpus
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Model {
+    /// number of passwords of every length
+    length_counts: Vec<u64>,
+    /// occurrences of every byte at every position
+    position_counts: Vec<[u64; 256]>,
+}
+
+impl Model {
+    pub fn new() -> Model {
+        Model::default()
+    }
+
+    /// counts the chars of `pwd`, passwords longer than the maximum word length are skipped
+    pub fn add(&mut self, pwd: &[u8]) {
+        if pwd.is_empty() || pwd.len() >= MAX_WORD_SIZE {
+            return;
+        }
+        if self.length_counts.len() <= pwd.len() {
+            self.length_counts.resize(pwd.len() + 1, 0);
+            self.position_counts.resize(pwd.len(), [0; 256]);
+        }
+        self.length_counts[pwd.len()] += 1;
+        for (counts, &chr) in self.position_counts.iter_mut().zip(pwd) {
+            counts[chr as usize] += 1;
+        }
+    }
+
+    /// number of passwords counted
+    pub fn passwords(&self) -> u64 {
+        self.length_counts.iter().sum()
+    }
+
+    /// log2 probability of `chr` at `pos`, unseen chars and positions keep a small probability
+    pub fn char_score(&self, pos: usize, chr: u8) -> f64 {
+        let (count, total) = match self.position_counts.get(pos) {
+            Some(counts) => (counts[chr as usize], counts.iter().sum()),
+            None => (0, 0),
+        };
+        ((count + 1) as f64 / (total + 256) as f64).log2()
+    }
+
+    /// log2 probability of a password of `len` chars
+    pub fn length_score(&self, len: usize) -> f64 {
+        let count = self.length_counts.get(len).copied().unwrap_or(0);
+        ((count + 1) as f64 / (self.passwords() + MAX_WORD_SIZE as u64) as f64).log2()
+    }
+
+    /// log2 probability of `word`, the score the words are ordered by
+    pub fn score(&self, word: &[u8]) -> f64 {
+        let chars: f64 = word
+            .iter()
+            .enumerate()
+            .map(|(pos, &chr)| self.char_score(pos, chr))
+            .sum();
+        self.length_score(word.len()) + chars
+    }
+
+    /// writes the model as `MODEL_MAGIC`, the longest length (u32) and the length and position
+    /// counts (u64), little endian
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(MODEL_MAGIC)?;
+        let maxlen = self.position_counts.len();
+        out.write_all(&(maxlen as u32).to_le_bytes())?;
+        let length_counts =
+            (0..=maxlen).map(|len| self.length_counts.get(len).copied().unwrap_or(0));
+        let counts = length_counts.chain(self.position_counts.iter().flatten().copied());
+        for count in counts {
+            out.write_all(&count.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// reads a model written by `write`
+    pub fn read<R: Read>(input: &mut R) -> io::Result<Model> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut magic = [0; MODEL_MAGIC.len()];
+        input
+            .read_exact(&mut magic)
+            .map_err(|_| invalid("not a cracken model"))?;
+        if magic != MODEL_MAGIC {
+            return Err(invalid("not a cracken model"));
+        }
+        let mut maxlen = [0; 4];
+        input.read_exact(&mut maxlen)?;
+        let maxlen = u32::from_le_bytes(maxlen) as usize;
+        if maxlen >= MAX_WORD_SIZE {
+            return Err(invalid("the model is longer than the maximum word length"));
+        }
+        let mut read_count = || -> io::Result<u64> {
+            let mut count = [0; 8];
+            input
+                .read_exact(&mut count)
+                .map_err(|_| invalid("the model is truncated"))?;
+            Ok(u64::from_le_bytes(count))
+        };
+        let mut model = Model::new();
+        for _ in 0..=maxlen {
+            model.length_counts.push(read_count()?);
+        }
+        for _ in 0..maxlen {
+            let mut counts = [0; 256];
+            for count in counts.iter_mut() {
+                *count = read_count()?;
+            }
+            model.position_counts.push(counts);
+        }
+        if maxlen == 0 {
+            model.length_counts.clear();
+        }
+        Ok(model)
+    }
+}
+
+/// returns a generator of the words of a charset mask from the highest `Model::score`. the
+/// order is approximate - the partial words are expanded best first, keeping at most `beam`
+/// of them, so with a beam smaller than the keyspace the least likely words may be missing
+pub fn get_model_generator<'a>(
+    mask: &'a str,
+    minlen: Option<usize>,
+    maxlen: Option<usize>,
+    custom_charsets: &[CustomCharset<'a>],
+    model: &Model,
+    beam: usize,
+) -> Result<Box<dyn WordGenerator>, CrackenError> {
+    let mask_ops = parse_mask(mask)?;
+    validate_charsets(&mask_ops, custom_charsets.len())?;
+    let charsets_only = mask_ops.iter().all(|op| match op {
+        MaskOp::Char(_) | MaskOp::BuiltinCharset(_) => true,
+        MaskOp::CustomCharset(idx) => !custom_charsets[*idx].is_tokens(),
+        _ => false,
+    });
+    if !charsets_only {
+        return Err(CrackenError::Unsupported(format!(
+            "mask {} - model ordering supports charset masks only, without wordlists and tokens",
+            mask
+        )));
+    }
+    if beam == 0 {
+        return Err(CrackenError::Unsupported(
+            "the beam must be greater than zero".to_string(),
+        ));
+    }
+    let inner = CharsetGenerator::new(mask_ops, minlen, maxlen, custom_charsets)?;
+    // the chars of every position from the most likely
+    let positions = inner
+        .charsets()
+        .iter()
+        .enumerate()
+        .map(|(pos, charset)| {
+            let mut chars: Vec<_> = charset
+                .chars()
+                .into_iter()
+                .map(|chr| (chr, model.char_score(pos, chr)))
+                .collect();
+            chars.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            chars
+        })
+        .collect();
+    let length_scores = (0..=inner.maxlen)
+        .map(|len| model.length_score(len))
+        .collect();
+    Ok(Box::new(ModelGenerator {
+        inner,
+        positions,
+        length_scores,
+        beam,
+        hex_output: false,
+        buffer_size: BUFFER_SIZE,
+        repeat_limits: RepeatLimits::default(),
+    }))
+}
+
+/// generates the words of `inner` from the most likely, see `get_model_generator`
+struct ModelGenerator {
+    inner: CharsetGenerator,
+    /// the chars of every position and their scores, from the most likely
+    positions: Vec<Vec<(u8, f64)>>,
+    length_scores: Vec<f64>,
+    beam: usize,
+    hex_output: bool,
+    buffer_size: usize,
+    repeat_limits: RepeatLimits,
+}
+
+/// a word of the frontier, as the index of its char in the sorted chars of every position
+struct Candidate {
+    score: f64,
+    chars: Vec<u8>,
+    /// only the positions from this one are incremented, so every word has a single parent
+    last: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    /// the highest score first, ties by the shortest and the first in the chars order
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.chars.len().cmp(&self.chars.len()))
+            .then_with(|| other.chars.cmp(&self.chars))
+    }
+}
+
+impl ModelGenerator {
+    fn candidate(&self, chars: Vec<u8>, last: usize) -> Candidate {
+        let score = chars
+            .iter()
+            .enumerate()
+            .map(|(pos, &idx)| self.positions[pos][idx as usize].1)
+            .sum::<f64>()
+            + self.length_scores[chars.len()];
+        Candidate { score, chars, last }
+    }
+}
+
+impl WordGenerator for ModelGenerator {
+    /// best first over the frontier of words, every word popped is the most likely left as the
+    /// chars of every position are sorted from the most likely
+    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), CrackenError> {
+        let max_word_len = max_written_len(self.inner.maxlen + 1, self.hex_output);
+        let mut buf = StackBuf::with_size(self.buffer_size.max(max_word_len));
+        let mut frontier = BinaryHeap::new();
+        for len in self.inner.minlen..=self.inner.maxlen {
+            frontier.push(self.candidate(vec![0; len], 0));
+        }
+        let mut word = [b'\n'; MAX_WORD_SIZE];
+        while let Some(candidate) = frontier.pop() {
+            let len = candidate.chars.len();
+            for (pos, &idx) in candidate.chars.iter().enumerate() {
+                word[pos] = self.positions[pos][idx as usize].0;
+            }
+            word[len] = b'\n';
+            if !self.repeat_limits.is_set() || self.repeat_limits.allows(&word[..len]) {
+                if buf.remaining() < max_word_len {
+                    out.write_all(buf.getdata())?;
+                    buf.clear();
+                }
+                write_word(&mut buf, &word[..=len], self.hex_output);
+            }
+
+            for pos in candidate.last..len {
+                if candidate.chars[pos] as usize + 1 < self.positions[pos].len() {
+                    let mut chars = candidate.chars.clone();
+                    chars[pos] += 1;
+                    frontier.push(self.candidate(chars, pos));
+                }
+            }
+            // keeps the best `beam` words, the others and the words following them are lost
+            if frontier.len() > 2 * self.beam {
+                let mut best = frontier.into_sorted_vec();
+                best.drain(..best.len() - self.beam);
+                frontier = best.into();
+            }
+        }
+        out.write_all(buf.getdata())?;
+        Ok(())
+    }
+
+    fn combinations(&self) -> BigUint {
+        self.inner.combinations()
+    }
+
+    fn length_combinations(&self) -> Vec<(usize, BigUint)> {
+        self.inner.length_combinations()
+    }
+
+    fn output_bytes(&self) -> BigUint {
+        self.inner.output_bytes()
+    }
+
+    fn set_hex_output(&mut self, hex_output: bool) {
+        self.hex_output = hex_output;
+    }
+
+    fn set_buffer_size(&mut self, buffer_size: usize) {
+        self.buffer_size = buffer_size;
+    }
+
+    fn set_repeat_limits(&mut self, repeat_limits: RepeatLimits) {
+        self.repeat_limits = repeat_limits;
+    }
+
+    /// the position of a word depends on the beam, it isn't computed
+    fn index_of(&self, _word: &[u8]) -> Option<WordRank> {
+        None
+    }
+
+    /// the lengths are ordered by the model with the words
+    fn set_length_order(&mut self, _length_order: LengthOrder) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::io::Write;
+
+    use super::{get_model_generator, Model};
+    use crate::charsets::CustomCharset;
+    use crate::error::CrackenError;
+    use crate::generators::{get_word_generator, WordGenerator};
+
+    fn gen_words(word_gen: &dyn WordGenerator) -> Vec<Vec<u8>> {
+        let mut buf = vec![];
+        {
+            let mut out: Box<dyn Write> = Box::new(&mut buf);
+            word_gen.gen(&mut out).unwrap();
+        }
+        buf.split(|&c| c == b'\n')
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_vec())
+            .collect()
+    }
+
+    fn train(passwords: &[&str]) -> Model {
+        let mut model = Model::new();
+        for pwd in passwords {
+            model.add(pwd.as_bytes());
+        }
+        model
+    }
+
+    #[test]
+    fn test_model_train() {
+        let model = train(&["aaa", "aab", "ab1", "b1", ""]);
+        assert_eq!(model.passwords(), 4);
+        assert!(model.char_score(0, b'a') > model.char_score(0, b'b'));
+        assert!(model.char_score(1, b'a') > model.char_score(1, b'b'));
+        assert!(model.char_score(1, b'c') == model.char_score(1, b'd'));
+        assert!(model.char_score(2, b'b') > model.char_score(2, b'z'));
+        assert!(model.length_score(3) > model.length_score(2));
+        assert!(model.length_score(2) > model.length_score(4));
+        assert!(model.score(b"aab") > model.score(b"bab"));
+
+        let mut buf = vec![];
+        model.write(&mut buf).unwrap();
+        assert_eq!(buf.len(), 16 + 4 + 4 * 8 + 3 * 256 * 8);
+        assert_eq!(Model::read(&mut buf.as_slice()).unwrap(), model);
+        let mut empty = vec![];
+        Model::new().write(&mut empty).unwrap();
+        assert_eq!(Model::read(&mut empty.as_slice()).unwrap(), Model::new());
+
+        assert!(Model::read(&mut &b"CRACKEN-MODEL 2\n"[..]).is_err());
+        assert!(Model::read(&mut &buf[..buf.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_model_generator() {
+        let model = train(&["b2", "b2", "b1", "a1", "c", "c9x"]);
+        let charsets = vec![CustomCharset::Chars("abc")];
+        let word_gen = get_model_generator("?1?d", Some(1), None, &charsets, &model, 100).unwrap();
+        let words = gen_words(word_gen.as_ref());
+        // the short words first as every char lowers the score, ties by the chars order
+        let first: Vec<_> = words[..5].iter().map(|w| w.as_slice()).collect();
+        assert_eq!(first, [&b"b"[..], b"c", b"a", b"b1", b"b2"]);
+        // the words from the most likely
+        for pair in words.windows(2) {
+            assert!(model.score(&pair[0]) >= model.score(&pair[1]), "{:?}", pair);
+        }
+        // the same words as the plain generation when the beam fits the frontier
+        let plain = get_word_generator("?1?d", Some(1), None, &charsets, &[]).unwrap();
+        let mut expected = gen_words(plain.as_ref());
+        let mut sorted = words.clone();
+        expected.sort_unstable();
+        sorted.sort_unstable();
+        assert_eq!(sorted, expected);
+        assert_eq!(word_gen.combinations(), plain.combinations());
+
+        // a small beam keeps the most likely words, without duplicates
+        let word_gen = get_model_generator("?l?l?l", None, None, &[], &model, 5).unwrap();
+        let words = gen_words(word_gen.as_ref());
+        assert!(words.len() < 26 * 26 * 26);
+        assert_eq!(words.iter().collect::<HashSet<_>>().len(), words.len());
+        assert_eq!(words[0], b"bax");
+        let word_gen = get_model_generator("?l?l?l", None, None, &[], &model, 10_000).unwrap();
+        assert_eq!(gen_words(word_gen.as_ref()).len(), 26 * 26 * 26);
+    }
+
+    #[test]
+    fn test_model_generator_errors() {
+        let model = Model::new();
+        let charsets = vec![CustomCharset::Tokens("ab|cd")];
+        for (mask, charsets) in [("?w1?d", &[][..]), ("?1?d", &charsets[..])] {
+            let err = get_model_generator(mask, None, None, charsets, &model, 10)
+                .err()
+                .unwrap();
+            assert!(matches!(err, CrackenError::Unsupported(_)), "{:?}", err);
+        }
+        assert!(matches!(
+            get_model_generator("?d", None, None, &[], &model, 0),
+            Err(CrackenError::Unsupported(_))
+        ));
+        assert!(matches!(
+            get_model_generator("?d?", None, None, &[], &model, 10),
+            Err(CrackenError::Mask(_))
+        ));
+    }
+}
@@ -0,0 +1,490 @@
+//! the encodings of the generated words written by `--output-encoding`, every writer converts
+//! the utf-8 lines of the generator as they are written
+use std::io::{Error, ErrorKind, Write};
+
+use crate::helpers::{
+    decode_hex_word, encode_hex_word, hex_word_len, needs_hex, HexWords, HEX_PREFIX,
+};
+use crate::BUFFER_SIZE;
+
+/// encodes `s` as latin-1 into `out`, fails on characters outside of latin-1
+fn encode_latin1(s: &str, out: &mut Vec<u8>) -> Result<(), Error> {
+    for ch in s.chars() {
+        if ch as u32 > 0xff {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("cannot encode '{}' as latin1", ch),
+            ));
+        }
+        out.push(ch as u8);
+    }
+    Ok(())
+}
+
+/// converts utf-8 output back to latin-1, bytes which are not valid utf-8 are written as is
+pub struct Latin1Writer<W: Write> {
+    inner: W,
+    /// incomplete utf-8 sequence at the end of the last write
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Latin1Writer<W> {
+    pub fn new(inner: W) -> Latin1Writer<W> {
+        Latin1Writer {
+            inner,
+            pending: vec![],
+        }
+    }
+}
+
+impl<W: Write> Write for Latin1Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.pending.extend_from_slice(buf);
+        let mut out = Vec::with_capacity(self.pending.len());
+        let mut rest = &self.pending[..];
+
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(s) => {
+                    encode_latin1(s, &mut out)?;
+                    rest = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, invalid) = rest.split_at(e.valid_up_to());
+                    encode_latin1(std::str::from_utf8(valid).unwrap(), &mut out)?;
+                    match e.error_len() {
+                        Some(len) => {
+                            out.extend_from_slice(&invalid[..len]);
+                            rest = &invalid[len..];
+                        }
+                        // incomplete sequence - wait for the next write
+                        None => {
+                            rest = invalid;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.pending = rest.to_vec();
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if !self.pending.is_empty() {
+            self.inner.write_all(&self.pending)?;
+            self.pending.clear();
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for Latin1Writer<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// appends the utf-16le encoding of `s`, ascii is every byte and a zero byte
+fn encode_utf16le(s: &str, out: &mut Vec<u8>) {
+    if s.is_ascii() {
+        for &b in s.as_bytes() {
+            out.extend_from_slice(&[b, 0]);
+        }
+        return;
+    }
+    for unit in s.encode_utf16() {
+        out.extend_from_slice(&unit.to_le_bytes());
+    }
+}
+
+/// converts utf-8 output to utf-16le, the newlines too. bytes which are not valid utf-8 fail the
+/// write, or are written as U+FFFD if `lossy`
+pub struct Utf16LeWriter<W: Write> {
+    inner: W,
+    lossy: bool,
+    /// incomplete utf-8 sequence at the end of the last write
+    pending: Vec<u8>,
+    /// the encoded bytes of a write, up to twice its size
+    encoded: Vec<u8>,
+}
+
+impl<W: Write> Utf16LeWriter<W> {
+    pub fn new(inner: W, lossy: bool) -> Utf16LeWriter<W> {
+        Utf16LeWriter {
+            inner,
+            lossy,
+            pending: vec![],
+            encoded: vec![],
+        }
+    }
+}
+
+impl<W: Write> Write for Utf16LeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.pending.extend_from_slice(buf);
+        self.encoded.clear();
+        // utf-16 takes 2 bytes for every ascii char and at most twice the utf-8 bytes
+        self.encoded.reserve(self.pending.len() * 2);
+        let mut rest = &self.pending[..];
+
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(s) => {
+                    encode_utf16le(s, &mut self.encoded);
+                    rest = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, invalid) = rest.split_at(e.valid_up_to());
+                    encode_utf16le(std::str::from_utf8(valid).unwrap(), &mut self.encoded);
+                    match e.error_len() {
+                        Some(len) if self.lossy => {
+                            encode_utf16le("\u{fffd}", &mut self.encoded);
+                            rest = &invalid[len..];
+                        }
+                        Some(len) => {
+                            let bytes: String = invalid[..len]
+                                .iter()
+                                .map(|b| format!("\\x{:02x}", b))
+                                .collect();
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                format!(
+                                    "cannot encode invalid utf-8 {} as utf16le, --lossy writes U+FFFD",
+                                    bytes
+                                ),
+                            ));
+                        }
+                        // incomplete sequence - wait for the next write
+                        None => {
+                            rest = invalid;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.pending = rest.to_vec();
+        self.inner.write_all(&self.encoded)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for Utf16LeWriter<W> {
+    fn drop(&mut self) {
+        // output ending within a sequence, the words end with a newline
+        if !self.pending.is_empty() && self.lossy {
+            let _ = self.inner.write_all(&0xfffdu16.to_le_bytes());
+        }
+        let _ = self.flush();
+    }
+}
+
+/// writes the lines with bytes outside of printable ascii as `$HEX[...]` like hashcat potfiles,
+/// the printable lines are written from the given buffer as they are. the words with newlines
+/// must be encoded before, by the generator (`set_hex_output`)
+pub struct HashcatHexWriter<W: Write> {
+    inner: W,
+    /// incomplete line at the end of the last write
+    partial: Vec<u8>,
+    /// the encoded line
+    encoded: Vec<u8>,
+}
+
+impl<W: Write> HashcatHexWriter<W> {
+    pub fn new(inner: W) -> HashcatHexWriter<W> {
+        HashcatHexWriter {
+            inner,
+            partial: vec![],
+            encoded: vec![],
+        }
+    }
+
+    /// writes `line` (without its newline) encoded, the newline is written if `newline`
+    fn write_encoded(&mut self, line: &[u8], newline: bool) -> Result<(), Error> {
+        self.encoded.resize(hex_word_len(line.len()), 0);
+        let len = encode_hex_word(line, &mut self.encoded) - usize::from(!newline);
+        self.inner.write_all(&self.encoded[..len])
+    }
+}
+
+impl<W: Write> Write for HashcatHexWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let mut rest = buf;
+        if !self.partial.is_empty() {
+            let pos = match rest.iter().position(|&c| c == b'\n') {
+                Some(pos) => pos,
+                None => {
+                    self.partial.extend_from_slice(rest);
+                    return Ok(buf.len());
+                }
+            };
+            let mut line = std::mem::take(&mut self.partial);
+            line.extend_from_slice(&rest[..pos]);
+            if needs_hex(&line, HexWords::Hashcat) {
+                self.write_encoded(&line, true)?;
+            } else {
+                line.push(b'\n');
+                self.inner.write_all(&line)?;
+            }
+            line.clear();
+            self.partial = line;
+            rest = &rest[pos + 1..];
+        }
+
+        // the printable lines from `plain` to the next encoded line are written at once
+        let mut plain = 0;
+        let mut start = 0;
+        while let Some(pos) = rest[start..].iter().position(|&c| c == b'\n') {
+            let end = start + pos;
+            if needs_hex(&rest[start..end], HexWords::Hashcat) {
+                self.inner.write_all(&rest[plain..start])?;
+                self.write_encoded(&rest[start..end], true)?;
+                plain = end + 1;
+            }
+            start = end + 1;
+        }
+        self.inner.write_all(&rest[plain..start])?;
+        self.partial.extend_from_slice(&rest[start..]);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            if needs_hex(&line, HexWords::Hashcat) {
+                self.write_encoded(&line, false)?;
+            } else {
+                self.inner.write_all(&line)?;
+            }
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for HashcatHexWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// appends the standard base64 encoding of `bytes` to `out`, padded with `=` if `pad`
+pub fn encode_base64(bytes: &[u8], pad: bool, out: &mut Vec<u8>) {
+    for chunk in bytes.chunks(3) {
+        let byte = |idx: usize| chunk.get(idx).copied().unwrap_or(0) as u32;
+        let n = (byte(0) << 16) | (byte(1) << 8) | byte(2);
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(BASE64_ALPHABET[((n >> (18 - 6 * idx)) & 0x3f) as usize]);
+            } else if pad {
+                out.push(b'=');
+            }
+        }
+    }
+}
+
+/// writes every line as its base64 encoding and a newline. the `$HEX[...]` words of the
+/// generator (`set_hex_output`) are decoded first, so the words with newlines are encoded whole
+pub struct Base64Writer<W: Write> {
+    inner: W,
+    pad: bool,
+    /// incomplete line at the end of the last write
+    partial: Vec<u8>,
+    /// the encoded lines of a write, reused by every write
+    encoded: Vec<u8>,
+}
+
+impl<W: Write> Base64Writer<W> {
+    pub fn new(inner: W, pad: bool) -> Base64Writer<W> {
+        Base64Writer {
+            inner,
+            pad,
+            partial: vec![],
+            encoded: Vec::with_capacity(BUFFER_SIZE * 4 / 3 + 4),
+        }
+    }
+}
+
+/// encodes the word of a line (without its newline) and a newline into `out`
+fn encode_base64_line(line: &[u8], pad: bool, out: &mut Vec<u8>) {
+    // malformed `$HEX[...]` words are encoded as they are
+    let decoded = match line.starts_with(HEX_PREFIX) {
+        true => decode_hex_word(line.to_vec()).ok(),
+        false => None,
+    };
+    encode_base64(decoded.as_deref().unwrap_or(line), pad, out);
+    out.push(b'\n');
+}
+
+impl<W: Write> Write for Base64Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let mut rest = buf;
+        self.encoded.clear();
+        if !self.partial.is_empty() {
+            let pos = match rest.iter().position(|&c| c == b'\n') {
+                Some(pos) => pos,
+                None => {
+                    self.partial.extend_from_slice(rest);
+                    return Ok(buf.len());
+                }
+            };
+            self.partial.extend_from_slice(&rest[..pos]);
+            encode_base64_line(&self.partial, self.pad, &mut self.encoded);
+            self.partial.clear();
+            rest = &rest[pos + 1..];
+        }
+        while let Some(pos) = rest.iter().position(|&c| c == b'\n') {
+            encode_base64_line(&rest[..pos], self.pad, &mut self.encoded);
+            rest = &rest[pos + 1..];
+        }
+        self.partial.extend_from_slice(rest);
+
+        self.inner.write_all(&self.encoded)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if !self.partial.is_empty() {
+            self.encoded.clear();
+            encode_base64_line(&self.partial, self.pad, &mut self.encoded);
+            // the output didn't end with a newline
+            self.encoded.pop();
+            self.inner.write_all(&self.encoded)?;
+            self.partial.clear();
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for Base64Writer<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{encode_base64, Base64Writer, HashcatHexWriter, Latin1Writer, Utf16LeWriter};
+    use crate::helpers::{needs_hex, HexWords};
+
+    #[test]
+    fn test_latin1_writer() {
+        let mut out = vec![];
+        {
+            let mut writer = Latin1Writer::new(&mut out);
+            let data = "émile\nzoë\n".as_bytes();
+            // split in the middle of a multi byte sequence
+            writer.write_all(&data[..1]).unwrap();
+            writer.write_all(&data[1..9]).unwrap();
+            writer.write_all(&data[9..]).unwrap();
+            writer.write_all(b"raw\xff\n").unwrap();
+        }
+        assert_eq!(out, b"\xe9mile\nzo\xeb\nraw\xff\n");
+
+        let mut writer = Latin1Writer::new(vec![]);
+        assert!(writer.write_all("€uro\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_utf16le_writer() {
+        let utf16le =
+            |s: &str| -> Vec<u8> { s.encode_utf16().flat_map(u16::to_le_bytes).collect() };
+        let mut out = vec![];
+        {
+            let mut writer = Utf16LeWriter::new(&mut out, false);
+            let data = "émile\nzoë\n😀\nascii\n".as_bytes();
+            // split in the middle of multi byte sequences
+            writer.write_all(&data[..1]).unwrap();
+            writer.write_all(&data[1..9]).unwrap();
+            writer.write_all(&data[9..13]).unwrap();
+            writer.write_all(&data[13..]).unwrap();
+        }
+        assert_eq!(out, utf16le("émile\nzoë\n😀\nascii\n"));
+
+        let mut writer = Utf16LeWriter::new(vec![], false);
+        let err = writer.write_all(b"raw\xff\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot encode invalid utf-8 \\xff as utf16le, --lossy writes U+FFFD"
+        );
+
+        let mut out = vec![];
+        {
+            let mut writer = Utf16LeWriter::new(&mut out, true);
+            writer.write_all(b"raw\xff\n\xc3").unwrap();
+        }
+        assert_eq!(out, utf16le("raw\u{fffd}\n\u{fffd}"));
+    }
+
+    #[test]
+    fn test_hashcat_hex_writer() {
+        assert!(needs_hex("émile".as_bytes(), HexWords::Hashcat));
+        assert!(needs_hex(b"tab\there", HexWords::Hashcat));
+        assert!(needs_hex(b"del\x7f", HexWords::Hashcat));
+        assert!(!needs_hex(b"pass word!~", HexWords::Hashcat));
+
+        let data = "plain\némile\n$HEX[00]\nzoë\nlast\n".as_bytes();
+        let expected = b"plain\n$HEX[c3a96d696c65]\n$HEX[00]\n$HEX[7a6fc3ab]\nlast\n";
+        // every split, lines across writes too
+        for split in 0..data.len() {
+            let mut out = vec![];
+            {
+                let mut writer = HashcatHexWriter::new(&mut out);
+                writer.write_all(&data[..split]).unwrap();
+                writer.write_all(&data[split..]).unwrap();
+            }
+            assert_eq!(out, expected, "{}", split);
+        }
+
+        let mut out = vec![];
+        {
+            let mut writer = HashcatHexWriter::new(&mut out);
+            writer.write_all(b"a\n\xc3\xa9").unwrap();
+        }
+        assert_eq!(out, b"a\n$HEX[c3a9]");
+    }
+
+    #[test]
+    fn test_encode_base64() {
+        let encode = |bytes: &[u8], pad: bool| {
+            let mut out = vec![];
+            encode_base64(bytes, pad, &mut out);
+            String::from_utf8(out).unwrap()
+        };
+        for (bytes, padded, unpadded) in [
+            (&b""[..], "", ""),
+            (b"f", "Zg==", "Zg"),
+            (b"fo", "Zm8=", "Zm8"),
+            (b"foo", "Zm9v", "Zm9v"),
+            (b"foob", "Zm9vYg==", "Zm9vYg"),
+            (b"\x00\xff\xfe", "AP/+", "AP/+"),
+        ] {
+            assert_eq!(encode(bytes, true), padded);
+            assert_eq!(encode(bytes, false), unpadded);
+        }
+
+        let data = b"foo\n$HEX[0a00]\n$HEX[0]\nfo";
+        for split in 0..data.len() {
+            let mut out = vec![];
+            {
+                let mut writer = Base64Writer::new(&mut out, true);
+                writer.write_all(&data[..split]).unwrap();
+                writer.write_all(&data[split..]).unwrap();
+            }
+            assert_eq!(out, b"Zm9v\nCgA=\nJEhFWFswXQ==\nZm8=", "{}", split);
+        }
+    }
+}
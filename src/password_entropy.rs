@@ -1,22 +1,26 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BinaryHeap, HashSet};
 use std::fs::File;
+use std::io::{Error, Write};
 use std::path::Path;
 
 use ordered_float::OrderedFloat;
-use pathfinding::astar;
-use simple_error::SimpleError;
+use serde::Serialize;
 
 use crate::charsets::SYMBOL2CHARSET;
-use crate::helpers::RawFileReader;
+use crate::helpers::{round2, to_hex, RawFileReader};
 use crate::BoxResult;
 
 const SYMBOLS_SPACE: &[u8; 32] = b"!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
 
+/// estimates passwords entropy as the cheapest split of the password into subwords of the
+/// builtin charsets and the given wordlists, a subword of a set of N words costs log2(N) bits
 pub struct EntropyEstimator {
-    words: Vec<(String, HashSet<Vec<u8>>)>,
+    /// symbol, words and length of the longest word of every set
+    words: Vec<(String, HashSet<Vec<u8>>, usize)>,
 }
 
-#[derive(PartialEq, Debug)]
+/// the estimates of both estimators, `write_json` writes the line of one of them
+#[derive(PartialEq, Debug, Serialize)]
 pub struct PasswordEntropyResult {
     pub mask_entropy: f64,
     pub charset_mask: String,
@@ -25,6 +29,60 @@ pub struct PasswordEntropyResult {
     pub subword_entropy_min_split: Vec<String>,
 }
 
+/// the `--format jsonl` line of a password, see `PasswordEntropyResult::write_json`
+#[derive(Serialize)]
+struct EntropyLine<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hex: Option<String>,
+    bits: f64,
+    mask: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tokens: Option<Vec<String>>,
+    estimator: &'a str,
+}
+
+impl PasswordEntropyResult {
+    /// writes the result of `estimator` (hybrid or charset) as a single json line. passwords
+    /// which are not valid utf-8 are written hex encoded and if `index` is given it replaces
+    /// both the password and its tokens
+    pub fn write_json<W: Write>(
+        &self,
+        out: &mut W,
+        pwd: &[u8],
+        index: Option<usize>,
+        estimator: &str,
+    ) -> Result<(), Error> {
+        let (bits, mask) = match estimator {
+            "hybrid" => (self.subword_entropy, &self.min_subword_mask),
+            "charset" => (self.mask_entropy, &self.charset_mask),
+            _ => unreachable!("invalid entropy type"),
+        };
+        let password = std::str::from_utf8(pwd).ok();
+        let tokens = match estimator {
+            "hybrid" => self.subword_entropy_min_split.clone(),
+            _ => String::from_utf8_lossy(pwd)
+                .chars()
+                .map(String::from)
+                .collect(),
+        };
+        let line = EntropyLine {
+            index,
+            password: password.filter(|_| index.is_none()),
+            hex: (index.is_none() && password.is_none()).then(|| to_hex(pwd)),
+            bits: round2(bits),
+            mask,
+            tokens: index.is_none().then_some(tokens),
+            estimator,
+        };
+        serde_json::to_writer(&mut *out, &line)?;
+        out.write_all(b"\n")
+    }
+}
+
 impl EntropyEstimator {
     pub fn from_files<P: AsRef<Path>>(filenames: &[P]) -> BoxResult<Self> {
         let mut words = Vec::with_capacity(filenames.len() + SYMBOL2CHARSET.len());
@@ -36,14 +94,16 @@ impl EntropyEstimator {
                 .iter()
                 .map(|ch| vec![ch.to_owned()])
                 .collect::<HashSet<_>>();
-            words.push((charset.symbol.to_string(), set));
+            words.push((charset.symbol.to_string(), set, 1));
         }
 
         for (i, filename) in filenames.iter().enumerate() {
-            words.push((format!("w{}", i + 1), Self::load_vocab(filename)?));
+            let set = Self::load_vocab(filename)?;
+            let max_len = set.iter().map(|word| word.len()).max().unwrap_or(0);
+            words.push((format!("w{}", i + 1), set, max_len));
         }
 
-        words.sort_by_key(|(_, set)| set.len());
+        words.sort_by_key(|(_, set, _)| set.len());
         Ok(EntropyEstimator { words })
     }
 
@@ -64,25 +124,40 @@ impl EntropyEstimator {
         &self,
         pwd: &[u8],
     ) -> BoxResult<(f64, Vec<String>, String)> {
-        // load vocab file
-        let amatch = astar(
-            &0usize,
-            |&n| {
-                let mut neighbours = vec![];
-                for (_, set) in self.words.iter() {
-                    for i in (n..=pwd.len()).rev() {
-                        if set.contains(&pwd[n..i]) {
-                            neighbours.push((i, OrderedFloat::<f64>((set.len() as f64).log2())));
-                        }
+        // best[i] is the lowest entropy of splitting pwd[..i] into subwords and prev[i] the
+        // start of the last subword of that split
+        let mut best = vec![f64::INFINITY; pwd.len() + 1];
+        let mut prev = vec![0usize; pwd.len() + 1];
+        best[0] = 0.0;
+        for start in 0..pwd.len() {
+            if best[start].is_infinite() {
+                continue;
+            }
+            for (_, set, max_len) in self.words.iter() {
+                let cost = (set.len() as f64).log2();
+                let max_end = pwd.len().min(start + max_len);
+                for end in start + 1..=max_end {
+                    let entropy = best[start] + cost;
+                    if entropy < best[end] && set.contains(&pwd[start..end]) {
+                        best[end] = entropy;
+                        prev[end] = start;
                     }
                 }
-                neighbours
-            },
-            |_| OrderedFloat::<f64>(0f64),
-            |&n| n == pwd.len(),
-        );
-        let (best_path, entropy) =
-            amatch.ok_or_else(|| SimpleError::new("bad characters in password"))?;
+            }
+        }
+        let entropy = best[pwd.len()];
+        if entropy.is_infinite() {
+            bail!("bad characters in password");
+        }
+
+        let mut best_path = vec![pwd.len()];
+        while let Some(&end) = best_path.last() {
+            if end == 0 {
+                break;
+            }
+            best_path.push(prev[end]);
+        }
+        best_path.reverse();
 
         let mut best_split = Vec::with_capacity(best_path.len() - 1);
         let mut best_mask = String::with_capacity(best_path.len() - 1);
@@ -90,7 +165,7 @@ impl EntropyEstimator {
         for i in best_path.into_iter().skip(1) {
             let word_i = &pwd[prev..i];
             let mut found = false;
-            for (symbol, set) in self.words.iter() {
+            for (symbol, set, _) in self.words.iter() {
                 if set.contains(word_i) {
                     found = true;
                     best_mask.push('?');
@@ -104,7 +179,7 @@ impl EntropyEstimator {
             best_split.push(String::from_utf8_lossy(word_i).to_string());
             prev = i;
         }
-        Ok((entropy.into_inner(), best_split, best_mask))
+        Ok((entropy, best_split, best_mask))
     }
 
     fn load_vocab<P: AsRef<Path>>(fname: P) -> BoxResult<HashSet<Vec<u8>>> {
@@ -126,37 +201,205 @@ impl EntropyEstimator {
     }
 }
 
+lazy_static! {
+    /// entropy of every byte value by the builtin charset it belongs to
+    static ref CHAR_ENTROPY: [f64; 256] = {
+        let mut table = [256f64.log2(); 256];
+        for ch in 0..=255u8 {
+            if ch.is_ascii_digit() {
+                table[ch as usize] = 10f64.log2();
+            } else if ch.is_ascii_lowercase() || ch.is_ascii_uppercase() {
+                table[ch as usize] = 26f64.log2();
+            } else if SYMBOLS_SPACE.contains(&ch) {
+                table[ch as usize] = (SYMBOLS_SPACE.len() as f64).log2();
+            }
+        }
+        table
+    };
+}
+
+/// same entropy as `password_mask_entropy` without building the mask - cheap enough to be
+/// computed for every generated word
+#[inline]
+pub fn password_mask_entropy_bits(pwd: &[u8]) -> f64 {
+    pwd.iter().map(|&ch| CHAR_ENTROPY[ch as usize]).sum()
+}
+
 pub fn password_mask_entropy(pwd: &[u8]) -> (f64, String) {
     let mut mask = String::with_capacity(pwd.len() * 2);
     let mask_entropy = pwd
         .iter()
-        .map(|ch| {
+        .map(|&ch| {
+            let class = char_class(ch);
             mask.push('?');
-            if ch.is_ascii_digit() {
-                mask.push('d');
-                10f64.log2()
-            } else if ch.is_ascii_lowercase() {
-                mask.push('l');
-                26f64.log2()
-            } else if ch.is_ascii_uppercase() {
-                mask.push('u');
-                26f64.log2()
-            } else if SYMBOLS_SPACE.contains(ch) {
-                mask.push('s');
-                (SYMBOLS_SPACE.len() as f64).log2()
-            } else {
-                mask.push('b');
-                256f64.log2()
+            mask.push(class);
+            match class {
+                'd' => 10f64.log2(),
+                'l' | 'u' => 26f64.log2(),
+                's' => (SYMBOLS_SPACE.len() as f64).log2(),
+                _ => 256f64.log2(),
             }
         })
         .sum();
     (mask_entropy, mask)
 }
 
+/// the charset symbol of the class of `ch` - d, l, u, s or b for the other bytes
+pub fn char_class(ch: u8) -> char {
+    if ch.is_ascii_digit() {
+        'd'
+    } else if ch.is_ascii_lowercase() {
+        'l'
+    } else if ch.is_ascii_uppercase() {
+        'u'
+    } else if SYMBOLS_SPACE.contains(&ch) {
+        's'
+    } else {
+        'b'
+    }
+}
+
+/// resolution of the entropies kept for the median (in bits)
+const MEDIAN_RESOLUTION: f64 = 0.01;
+
+/// streaming summary of passwords entropies, memory is bounded by the number of distinct
+/// entropies (rounded to `MEDIAN_RESOLUTION`) and the number of weakest passwords kept
+pub struct EntropySummary {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    rounded_counts: BTreeMap<u64, u64>,
+    bucket_size: u32,
+    histogram: BTreeMap<u64, u64>,
+    weakest_size: usize,
+    weakest: BinaryHeap<(OrderedFloat<f64>, Vec<u8>)>,
+}
+
+impl EntropySummary {
+    /// `bucket_size` is the width of each histogram bucket in bits and `weakest_size` the
+    /// number of weakest passwords to keep
+    pub fn new(bucket_size: u32, weakest_size: usize) -> EntropySummary {
+        EntropySummary {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            rounded_counts: BTreeMap::new(),
+            bucket_size: bucket_size.max(1),
+            histogram: BTreeMap::new(),
+            weakest_size,
+            weakest: BinaryHeap::with_capacity(weakest_size + 1),
+        }
+    }
+
+    pub fn add(&mut self, pwd: &[u8], entropy: f64) {
+        self.count += 1;
+        self.sum += entropy;
+        self.min = self.min.min(entropy);
+        self.max = self.max.max(entropy);
+
+        let rounded = (entropy / MEDIAN_RESOLUTION).round() as u64;
+        *self.rounded_counts.entry(rounded).or_insert(0) += 1;
+
+        let bucket = entropy as u64 / self.bucket_size as u64;
+        *self.histogram.entry(bucket).or_insert(0) += 1;
+
+        // max heap - the strongest of the weakest passwords is on top
+        if self.weakest_size > 0 {
+            let item = (OrderedFloat(entropy), pwd.to_vec());
+            if self.weakest.len() < self.weakest_size {
+                self.weakest.push(item);
+            } else if item < *self.weakest.peek().unwrap() {
+                self.weakest.pop();
+                self.weakest.push(item);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    /// returns the median entropy, accurate up to `MEDIAN_RESOLUTION`
+    pub fn median(&self) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let nth = |n: u64| {
+            let mut seen = 0;
+            for (&rounded, &count) in self.rounded_counts.iter() {
+                seen += count;
+                if seen > n {
+                    return rounded as f64 * MEDIAN_RESOLUTION;
+                }
+            }
+            unreachable!("n is smaller than count")
+        };
+        Some((nth((self.count - 1) / 2) + nth(self.count / 2)) / 2.0)
+    }
+
+    /// returns the histogram buckets as (from bits, to bits, count)
+    pub fn histogram(&self) -> Vec<(u64, u64, u64)> {
+        let bucket_size = self.bucket_size as u64;
+        self.histogram
+            .iter()
+            .map(|(&bucket, &count)| (bucket * bucket_size, (bucket + 1) * bucket_size, count))
+            .collect()
+    }
+
+    /// returns the weakest passwords sorted from the weakest
+    pub fn weakest(&self) -> Vec<(f64, &[u8])> {
+        let mut weakest: Vec<_> = self.weakest.iter().collect();
+        weakest.sort();
+        weakest
+            .into_iter()
+            .map(|(entropy, pwd)| (entropy.into_inner(), pwd.as_slice()))
+            .collect()
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> Result<(), std::io::Error> {
+        writeln!(out, "passwords: {}", self.count)?;
+        if self.count == 0 {
+            return Ok(());
+        }
+        writeln!(out, "min entropy: {:.2}", self.min)?;
+        writeln!(out, "median entropy: {:.2}", self.median().unwrap())?;
+        writeln!(out, "avg entropy: {:.2}", self.mean().unwrap())?;
+        writeln!(out, "max entropy: {:.2}", self.max)?;
+
+        writeln!(out, "histogram (bits):")?;
+        for (from, to, count) in self.histogram() {
+            writeln!(out, "  {}-{}: {}", from, to, count)?;
+        }
+
+        writeln!(out, "weakest passwords:")?;
+        for (entropy, pwd) in self.weakest() {
+            writeln!(out, "  {:.2} {}", entropy, String::from_utf8_lossy(pwd))?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::password_entropy::EntropyEstimator;
-    use crate::password_entropy::{password_mask_entropy, PasswordEntropyResult};
+    use crate::password_entropy::{
+        password_mask_entropy, password_mask_entropy_bits, EntropySummary, PasswordEntropyResult,
+    };
     use crate::test_util::wordlist_fname;
 
     #[test]
@@ -228,6 +471,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compute_password_subword_entropy_segmentation() {
+        let fname = wordlist_fname("entropy-tokens.txt");
+        let est = EntropyEstimator::from_files(vec![fname].as_ref()).unwrap();
+        let token_bits = 7f64.log2();
+
+        let cases = vec![
+            (
+                "summerLove2019",
+                vec!["summer", "Love", "2019"],
+                "?w1?w1?w1",
+            ),
+            // longest match first would split password|l|e
+            ("passwordle", vec!["pass", "wordle"], "?w1?w1"),
+            ("password1", vec!["password", "1"], "?w1?d"),
+            ("love", vec!["love"], "?w1"),
+        ];
+        for (pwd, split, mask) in cases {
+            let (entropy, best_split, best_mask) = est
+                .compute_password_subword_entropy(pwd.as_bytes())
+                .unwrap();
+            assert_eq!(best_split, split, "{}", pwd);
+            assert_eq!(best_mask, mask, "{}", pwd);
+            let mask_bits = mask.matches("?w1").count() as f64 * token_bits
+                + mask.matches("?d").count() as f64 * 10f64.log2();
+            assert!((entropy - mask_bits).abs() < 1e-9, "{}", pwd);
+        }
+
+        let (entropy, _, _) = est
+            .compute_password_subword_entropy(b"summerLove2019")
+            .unwrap();
+        assert!(entropy < password_mask_entropy(b"summerLove2019").0 / 5.0);
+    }
+
+    #[test]
+    fn test_password_entropy_result_write_json() {
+        let fname = wordlist_fname("entropy-tokens.txt");
+        let est = EntropyEstimator::from_files(vec![fname].as_ref()).unwrap();
+        let result = est.estimate_password_entropy(b"summerLove2019").unwrap();
+
+        let mut out = vec![];
+        result
+            .write_json(&mut out, b"summerLove2019", None, "hybrid")
+            .unwrap();
+        result
+            .write_json(&mut out, b"summerLove2019", Some(3), "hybrid")
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{"password":"summerLove2019","bits":8.42,"mask":"?w1?w1?w1","tokens":["summer","Love","2019"],"estimator":"hybrid"}
+{"index":3,"bits":8.42,"mask":"?w1?w1?w1","estimator":"hybrid"}
+"#
+        );
+    }
+
     #[test]
     fn test_compute_password_entropy_random_password() {
         let pwd = "E93gtaaE6yF7xDOWv3ww2QE6qD-Wye4mk8O3Vaerem8";
@@ -280,4 +578,70 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_entropy_summary() {
+        let mut summary = EntropySummary::new(10, 2);
+        assert_eq!(summary.median(), None);
+
+        let passwords = ["123456", "password", "abc123", "Aa123456!", "qwerty"];
+        for pwd in passwords {
+            summary.add(pwd.as_bytes(), password_mask_entropy(pwd.as_bytes()).0);
+        }
+        let entropy = |pwd: &str| password_mask_entropy(pwd.as_bytes()).0;
+
+        assert_eq!(summary.count(), 5);
+        assert_eq!(summary.min(), Some(entropy("123456")));
+        assert_eq!(summary.max(), Some(entropy("password")));
+        assert!((summary.median().unwrap() - entropy("qwerty")).abs() < 0.01);
+        let mean = passwords.iter().map(|pwd| entropy(pwd)).sum::<f64>() / 5.0;
+        assert!((summary.mean().unwrap() - mean).abs() < 1e-9);
+
+        // 19.93, 24.07, 28.20, 34.33, 37.60
+        assert_eq!(
+            summary.histogram(),
+            vec![(10, 20, 1), (20, 30, 2), (30, 40, 2)]
+        );
+        let weakest: Vec<_> = summary.weakest().into_iter().map(|(_, pwd)| pwd).collect();
+        assert_eq!(weakest, vec![&b"123456"[..], b"abc123"]);
+
+        // even number of passwords - average of the middle ones
+        summary.add(b"x", 30.0);
+        let expected = (entropy("qwerty") + 30.0) / 2.0;
+        assert!((summary.median().unwrap() - expected).abs() < 0.01);
+
+        let mut out = vec![];
+        summary.write(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.starts_with("passwords: 6\nmin entropy: 19.93\n"),
+            "{}",
+            out
+        );
+        assert!(out.contains("  20-30: 2\n"), "{}", out);
+        assert!(
+            out.ends_with("weakest passwords:\n  19.93 123456\n  24.07 abc123\n"),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_password_mask_entropy_bits() {
+        let pwds: [&[u8]; 5] = [
+            b"Aa123456!",
+            b"helloworld",
+            b"",
+            b"\x00\xff~ 9Zz",
+            b"E93gtaaE6yF7xDOWv3ww2QE6qD-Wye4mk8O3Vaerem8",
+        ];
+        for pwd in pwds {
+            assert_eq!(
+                password_mask_entropy_bits(pwd),
+                password_mask_entropy(pwd).0,
+                "{:?}",
+                pwd
+            );
+        }
+    }
 }
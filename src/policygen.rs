@@ -0,0 +1,338 @@
+//! generates the masks of the passwords complying with a password policy (like PACK's
+//! policygen) - every assignment of the ?l ?u ?d ?s classes to the positions of every length
+use std::cmp::Ordering;
+
+use num_bigint::{BigUint, ToBigUint};
+
+use crate::charsets::Charset;
+use crate::error::CrackenError;
+use crate::MAX_WORD_SIZE;
+
+/// the char classes of the policy masks in their mask order
+pub const CLASSES: [char; 4] = ['l', 'u', 'd', 's'];
+/// policies with more masks are refused, the masks are kept in memory to sort them
+pub const MAX_POLICY_MASKS: u64 = 1_000_000;
+
+/// a password policy - the length range and the number of chars of every class in `CLASSES`
+/// order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    pub minlen: usize,
+    pub maxlen: usize,
+    /// classes every password has at least one char of
+    pub required: [bool; 4],
+    /// maximum number of chars of every class, None is unlimited
+    pub max_counts: [Option<usize>; 4],
+}
+
+/// a mask of the policy and the number of words it generates
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyMask {
+    pub mask: String,
+    pub keyspace: BigUint,
+}
+
+/// parses a comma separated list of classes (e.g. `u,l,d`) into a flag per class
+pub fn parse_classes(classes: &str) -> Result<[bool; 4], CrackenError> {
+    let mut flags = [false; 4];
+    for class in classes.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+        let class = class.trim_start_matches('?');
+        match CLASSES
+            .iter()
+            .position(|c| class.len() == 1 && class.starts_with(*c))
+        {
+            Some(idx) => flags[idx] = true,
+            None => {
+                return Err(CrackenError::Charset(format!(
+                    "unknown policy class '{}', expected one of l, u, d and s",
+                    class
+                )))
+            }
+        }
+    }
+    Ok(flags)
+}
+
+impl Policy {
+    pub fn new(minlen: usize, maxlen: usize) -> Policy {
+        Policy {
+            minlen,
+            maxlen,
+            required: [false; 4],
+            max_counts: [None; 4],
+        }
+    }
+
+    fn validate(&self) -> Result<(), CrackenError> {
+        let invalid_length = |msg: String| Err(CrackenError::InvalidLength(msg));
+        if self.minlen == 0 || self.minlen > self.maxlen {
+            return invalid_length(format!(
+                "policy min length {} must be greater than zero and at most max length {}",
+                self.minlen, self.maxlen
+            ));
+        }
+        if self.maxlen >= MAX_WORD_SIZE {
+            return invalid_length(format!(
+                "policy max length {} is longer than the maximum word length {}",
+                self.maxlen,
+                MAX_WORD_SIZE - 1
+            ));
+        }
+        for (idx, class) in CLASSES.iter().enumerate() {
+            if self.required[idx] && self.max_counts[idx] == Some(0) {
+                return Err(CrackenError::Charset(format!(
+                    "policy requires ?{} but allows none",
+                    class
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// returns true iff passwords with these numbers of chars of every class comply
+    fn allows(&self, counts: &[usize; 4]) -> bool {
+        (0..CLASSES.len()).all(|idx| {
+            (!self.required[idx] || counts[idx] > 0)
+                && !matches!(self.max_counts[idx], Some(max) if counts[idx] > max)
+        })
+    }
+
+    /// number of masks of the policy and their total keyspace, counted without generating the
+    /// masks - the masks of every numbers of chars of the classes are their permutations
+    pub fn count(&self) -> Result<(BigUint, BigUint), CrackenError> {
+        self.validate()?;
+        let sizes = class_sizes();
+        let mut masks = 0.to_biguint().unwrap();
+        let mut keyspace = 0.to_biguint().unwrap();
+        for len in self.minlen..=self.maxlen {
+            for counts in class_counts(len) {
+                if !self.allows(&counts) {
+                    continue;
+                }
+                // len! / (l! u! d! s!)
+                let mut perms = factorial(len);
+                for &count in counts.iter() {
+                    perms /= factorial(count);
+                }
+                let words = counts
+                    .iter()
+                    .zip(sizes.iter())
+                    .fold(perms.clone(), |acc, (&count, &size)| {
+                        acc * size.to_biguint().unwrap().pow(count as u32)
+                    });
+                masks += perms;
+                keyspace += words;
+            }
+        }
+        Ok((masks, keyspace))
+    }
+
+    /// returns the masks of the policy ordered by keyspace, ties by mask
+    pub fn masks(&self) -> Result<Vec<PolicyMask>, CrackenError> {
+        let (count, _) = self.count()?;
+        if count > MAX_POLICY_MASKS.to_biguint().unwrap() {
+            return Err(CrackenError::Unsupported(format!(
+                "the policy has {} masks, more than the {} supported",
+                count, MAX_POLICY_MASKS
+            )));
+        }
+        let sizes = class_sizes();
+        let mut masks = vec![];
+        for len in self.minlen..=self.maxlen {
+            self.masks_of_length(len, &sizes, &mut masks);
+        }
+        masks.sort_by(|a, b| match a.keyspace.cmp(&b.keyspace) {
+            Ordering::Equal => a.mask.cmp(&b.mask),
+            ord => ord,
+        });
+        Ok(masks)
+    }
+
+    /// depth first over the classes of every position, pruning the positions which can't
+    /// comply anymore
+    fn masks_of_length(&self, len: usize, sizes: &[usize; 4], masks: &mut Vec<PolicyMask>) {
+        let mut classes = vec![0; len];
+        let mut counts = [0; 4];
+        // next class to try at every position
+        let mut next = vec![0; len + 1];
+        let mut pos = 0;
+        loop {
+            if next[pos] == CLASSES.len() {
+                if pos == 0 {
+                    return;
+                }
+                pos -= 1;
+                counts[classes[pos]] -= 1;
+                continue;
+            }
+            let class = next[pos];
+            next[pos] += 1;
+            if matches!(self.max_counts[class], Some(max) if counts[class] >= max) {
+                continue;
+            }
+            counts[class] += 1;
+            let missing = (0..CLASSES.len())
+                .filter(|&idx| self.required[idx] && counts[idx] == 0)
+                .count();
+            if missing > len - pos - 1 {
+                counts[class] -= 1;
+                continue;
+            }
+            classes[pos] = class;
+            if pos + 1 == len {
+                let mut mask = String::with_capacity(2 * len);
+                let mut keyspace = 1.to_biguint().unwrap();
+                for &class in classes.iter() {
+                    mask.push('?');
+                    mask.push(CLASSES[class]);
+                    keyspace *= sizes[class];
+                }
+                masks.push(PolicyMask { mask, keyspace });
+                counts[class] -= 1;
+                continue;
+            }
+            pos += 1;
+            next[pos] = 0;
+        }
+    }
+}
+
+fn class_sizes() -> [usize; 4] {
+    let mut sizes = [0; 4];
+    for (size, &class) in sizes.iter_mut().zip(CLASSES.iter()) {
+        *size = Charset::from_symbol(class).len;
+    }
+    sizes
+}
+
+/// every number of chars of the 4 classes adding up to `len`
+fn class_counts(len: usize) -> Vec<[usize; 4]> {
+    let mut all = vec![];
+    for l in 0..=len {
+        for u in 0..=len - l {
+            for d in 0..=len - l - u {
+                all.push([l, u, d, len - l - u - d]);
+            }
+        }
+    }
+    all
+}
+
+fn factorial(n: usize) -> BigUint {
+    (1..=n).fold(1.to_biguint().unwrap(), |acc, i| acc * i)
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::{BigUint, ToBigUint};
+
+    use super::{parse_classes, Policy, PolicyMask};
+    use crate::error::CrackenError;
+
+    /// every assignment of classes to the positions, filtered by the policy
+    fn brute_force(policy: &Policy) -> (usize, BigUint) {
+        let sizes = [26u32, 26, 10, 33];
+        let mut masks = 0;
+        let mut keyspace = 0.to_biguint().unwrap();
+        for len in policy.minlen..=policy.maxlen {
+            for n in 0..4usize.pow(len as u32) {
+                let mut counts = [0; 4];
+                let mut words = 1.to_biguint().unwrap();
+                let mut rest = n;
+                for _ in 0..len {
+                    counts[rest % 4] += 1;
+                    words *= sizes[rest % 4];
+                    rest /= 4;
+                }
+                let complies = (0..4).all(|idx| {
+                    (!policy.required[idx] || counts[idx] > 0)
+                        && !matches!(policy.max_counts[idx], Some(max) if counts[idx] > max)
+                });
+                if complies {
+                    masks += 1;
+                    keyspace += words;
+                }
+            }
+        }
+        (masks, keyspace)
+    }
+
+    #[test]
+    fn test_policy_masks() {
+        let mut policy = Policy::new(2, 2);
+        policy.required = parse_classes("u,d").unwrap();
+        let masks = policy.masks().unwrap();
+        let mask = |mask: &str, keyspace: u32| PolicyMask {
+            mask: mask.to_string(),
+            keyspace: keyspace.to_biguint().unwrap(),
+        };
+        assert_eq!(masks, vec![mask("?d?u", 260), mask("?u?d", 260)]);
+
+        let mut policy = Policy::new(1, 3);
+        policy.max_counts = [None, Some(0), Some(0), Some(0)];
+        let masks: Vec<_> = policy
+            .masks()
+            .unwrap()
+            .into_iter()
+            .map(|m| m.mask)
+            .collect();
+        assert_eq!(masks, vec!["?l", "?l?l", "?l?l?l"]);
+    }
+
+    #[test]
+    fn test_policy_brute_force() {
+        let mut policies = vec![];
+        let mut policy = Policy::new(4, 6);
+        policy.required = parse_classes("u,l,d").unwrap();
+        policy.max_counts[3] = Some(1);
+        policies.push(policy);
+        let mut policy = Policy::new(1, 5);
+        policy.required = parse_classes("?s").unwrap();
+        policy.max_counts = [Some(2), None, Some(1), Some(3)];
+        policies.push(policy);
+        policies.push(Policy::new(3, 4));
+
+        for policy in policies {
+            let (masks_count, keyspace) = brute_force(&policy);
+            let masks = policy.masks().unwrap();
+            assert_eq!(masks.len(), masks_count);
+            let total: BigUint = masks.iter().map(|m| &m.keyspace).sum();
+            assert_eq!(total, keyspace);
+            assert_eq!(
+                policy.count().unwrap(),
+                (masks_count.to_biguint().unwrap(), keyspace)
+            );
+            // distinct and ordered by keyspace
+            for pair in masks.windows(2) {
+                assert!((&pair[0].keyspace, &pair[0].mask) < (&pair[1].keyspace, &pair[1].mask));
+            }
+        }
+    }
+
+    #[test]
+    fn test_policy_errors() {
+        assert!(matches!(
+            parse_classes("u,x"),
+            Err(CrackenError::Charset(_))
+        ));
+        assert!(matches!(parse_classes("ul"), Err(CrackenError::Charset(_))));
+        assert_eq!(parse_classes("").unwrap(), [false; 4]);
+        assert!(matches!(
+            Policy::new(3, 2).masks(),
+            Err(CrackenError::InvalidLength(_))
+        ));
+        assert!(matches!(
+            Policy::new(0, 2).masks(),
+            Err(CrackenError::InvalidLength(_))
+        ));
+        let mut policy = Policy::new(1, 2);
+        policy.required[0] = true;
+        policy.max_counts[0] = Some(0);
+        assert!(matches!(policy.masks(), Err(CrackenError::Charset(_))));
+        // 4^16 masks
+        assert!(matches!(
+            Policy::new(16, 16).masks(),
+            Err(CrackenError::Unsupported(_))
+        ));
+    }
+}
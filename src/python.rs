@@ -0,0 +1,148 @@
+//! python module `cracken`, built with `maturin build --release` (see pyproject.toml)
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use num_bigint::BigUint;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::builder::GeneratorBuilder;
+use crate::error::CrackenError;
+use crate::words::Words;
+
+create_exception!(
+    cracken,
+    Error,
+    PyException,
+    "base class of the cracken errors"
+);
+create_exception!(cracken, MaskError, Error, "invalid mask syntax");
+create_exception!(
+    cracken,
+    CharsetError,
+    Error,
+    "invalid or missing custom charsets"
+);
+create_exception!(
+    cracken,
+    WordlistError,
+    Error,
+    "a wordlist can't be read or is invalid"
+);
+create_exception!(
+    cracken,
+    InvalidLengthError,
+    Error,
+    "invalid minlen / maxlen"
+);
+create_exception!(
+    cracken,
+    UnsupportedError,
+    Error,
+    "an unsupported option for the mask"
+);
+
+impl From<CrackenError> for PyErr {
+    fn from(e: CrackenError) -> PyErr {
+        let msg = e.to_string();
+        match e {
+            CrackenError::Mask(_) => MaskError::new_err(msg),
+            CrackenError::Charset(_) => CharsetError::new_err(msg),
+            CrackenError::Wordlist { .. } => WordlistError::new_err(msg),
+            CrackenError::InvalidLength(_) => InvalidLengthError::new_err(msg),
+            CrackenError::Unsupported(_) => UnsupportedError::new_err(msg),
+            // raised as the matching OSError subclass (e.g. FileNotFoundError)
+            CrackenError::Io(e) => e.into(),
+        }
+    }
+}
+
+/// iterates the words of `mask` as bytes (without newlines), same as `cracken generate`.
+/// `charsets` are the custom charsets ?1 to ?9 and `wordlists` the files of ?w1 to ?w9
+#[pyclass(module = "cracken")]
+struct Generator {
+    builder: GeneratorBuilder<'static>,
+    words: Words,
+}
+
+#[pymethods]
+impl Generator {
+    #[new]
+    #[pyo3(signature = (mask, charsets = Vec::new(), wordlists = Vec::new(), minlen = None, maxlen = None))]
+    fn new(
+        py: Python,
+        mask: String,
+        charsets: Vec<String>,
+        wordlists: Vec<String>,
+        minlen: Option<usize>,
+        maxlen: Option<usize>,
+    ) -> PyResult<Generator> {
+        let mut builder = GeneratorBuilder::new().mask(mask);
+        for charset in charsets {
+            builder = builder.custom_charset(charset);
+        }
+        for fname in wordlists {
+            builder = builder.wordlist_file(fname);
+        }
+        if let Some(minlen) = minlen {
+            builder = builder.minlen(minlen);
+        }
+        if let Some(maxlen) = maxlen {
+            builder = builder.maxlen(maxlen);
+        }
+
+        // loading the wordlists may take a while
+        let words = py.allow_threads(|| Words::new(builder.clone()))?;
+        Ok(Generator { builder, words })
+    }
+
+    /// number of words of the mask, including the already iterated ones
+    fn combinations(&self) -> BigUint {
+        self.words.combinations().clone()
+    }
+
+    /// skips the next `n` words and returns the number of skipped words
+    fn skip(&mut self, py: Python, n: u64) -> u64 {
+        let words = &mut self.words;
+        py.allow_threads(|| words.skip_words(n))
+    }
+
+    /// writes all the words of the mask to `path` (regardless of the iterated ones), same as
+    /// `cracken generate -o path`
+    fn generate_to_file(&self, py: Python, path: PathBuf) -> PyResult<()> {
+        let builder = self.builder.clone();
+        py.allow_threads(move || -> Result<(), CrackenError> {
+            let word_gen = builder.build()?;
+            let mut out: Box<dyn Write> = Box::new(BufWriter::new(File::create(path)?));
+            word_gen.gen(&mut out)?;
+            out.flush()?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> Option<PyObject> {
+        let word = self.words.next_word()?;
+        Some(PyBytes::new(py, word).into_py(py))
+    }
+}
+
+#[pymodule]
+fn cracken(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Generator>()?;
+    m.add("Error", py.get_type::<Error>())?;
+    m.add("MaskError", py.get_type::<MaskError>())?;
+    m.add("CharsetError", py.get_type::<CharsetError>())?;
+    m.add("WordlistError", py.get_type::<WordlistError>())?;
+    m.add("InvalidLengthError", py.get_type::<InvalidLengthError>())?;
+    m.add("UnsupportedError", py.get_type::<UnsupportedError>())?;
+    Ok(())
+}
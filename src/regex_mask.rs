@@ -0,0 +1,577 @@
+//! compiles a restricted regex (`cracken from-regex`) into charset masks - literal chars,
+//! char classes, `.`, bounded repetition (`?`, `{n}`, `{n,m}`) and alternation. the optional
+//! repetitions and the alternatives are expanded to one mask per combination
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::charsets::{CustomCharset, SYMBOL2CHARSET};
+use crate::error::CrackenError;
+use crate::generators::{CharsetGenerator, WordGenerator};
+use crate::mask::MaskOp;
+use crate::MAX_WORD_SIZE;
+
+/// maximum number of masks a regex expands to
+pub const MAX_REGEX_MASKS: usize = 10_000;
+
+/// the chars `.` and the negated classes match - printable ascii
+const PRINTABLE: std::ops::RangeInclusive<u8> = b' '..=b'~';
+
+/// the masks of a regex, the custom charsets of their `MaskOp::CustomCharset` ops are the
+/// regex classes which aren't builtin charsets
+#[derive(Debug, PartialEq)]
+pub struct RegexMasks {
+    pub masks: Vec<Vec<MaskOp>>,
+    pub charsets: Vec<String>,
+}
+
+impl RegexMasks {
+    /// a generator of every mask, in the order of the regex alternatives and repetitions
+    pub fn generators(&self) -> Result<Vec<Box<dyn WordGenerator>>, CrackenError> {
+        let charsets: Vec<_> = self
+            .charsets
+            .iter()
+            .map(|chars| CustomCharset::Chars(chars))
+            .collect();
+        self.masks
+            .iter()
+            .map(|mask| {
+                let generator = CharsetGenerator::new(mask.clone(), None, None, &charsets)?;
+                Ok(Box::new(generator) as Box<dyn WordGenerator>)
+            })
+            .collect()
+    }
+}
+
+/// compiles `regex` into the masks generating the words it matches. the empty word isn't
+/// generated, and the words matched by several alternatives (like `[ab]|a`) are generated
+/// once by each of them
+pub fn compile_regex(regex: &str) -> Result<RegexMasks, CrackenError> {
+    let mut parser = RegexParser {
+        regex,
+        chars: regex.char_indices().peekable(),
+        charsets: vec![],
+    };
+    if parser.chars.peek().map(|&(_, ch)| ch) == Some('^') {
+        parser.chars.next();
+    }
+    let masks = parser.alternation()?;
+    if let Some((pos, ch)) = parser.chars.next() {
+        // the only way out of the top level alternation is an unbalanced `)`
+        return Err(parser.mask_error(pos, &format!("unmatched {}", ch)));
+    }
+    let masks: Vec<_> = masks.into_iter().filter(|mask| !mask.is_empty()).collect();
+    if masks.is_empty() {
+        return Err(CrackenError::Mask(format!(
+            "regex {} matches only the empty word",
+            regex
+        )));
+    }
+    let charsets = parser
+        .charsets
+        .into_iter()
+        .map(|chars| String::from_utf8(chars).unwrap())
+        .collect();
+    Ok(RegexMasks { masks, charsets })
+}
+
+struct RegexParser<'r> {
+    regex: &'r str,
+    chars: Peekable<CharIndices<'r>>,
+    /// the chars of the custom charsets, sorted
+    charsets: Vec<Vec<u8>>,
+}
+
+impl<'r> RegexParser<'r> {
+    fn mask_error(&self, pos: usize, msg: &str) -> CrackenError {
+        CrackenError::Mask(format!(
+            "regex {} - {} at position {}",
+            self.regex, msg, pos
+        ))
+    }
+
+    fn unsupported(&self, pos: usize, construct: &str) -> CrackenError {
+        CrackenError::Unsupported(format!(
+            "regex {} - {} at position {} is not supported",
+            self.regex, construct, pos
+        ))
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, ch)| ch)
+    }
+
+    /// `sequence ('|' sequence)*`, stops before a `)` or the end
+    fn alternation(&mut self) -> Result<Vec<Vec<MaskOp>>, CrackenError> {
+        let mut masks = self.sequence()?;
+        while self.peek() == Some('|') {
+            self.chars.next();
+            let alternative = self.sequence()?;
+            masks = self.union(masks, alternative)?;
+        }
+        Ok(masks)
+    }
+
+    /// the concatenation of the quantified atoms until a `|`, a `)` or the end
+    fn sequence(&mut self) -> Result<Vec<Vec<MaskOp>>, CrackenError> {
+        let mut masks = vec![vec![]];
+        while let Some(&(pos, ch)) = self.chars.peek() {
+            let atom = match ch {
+                '|' | ')' => break,
+                '$' => {
+                    self.chars.next();
+                    if self.chars.peek().is_some() {
+                        return Err(self.unsupported(pos, "anchor $ before the end"));
+                    }
+                    break;
+                }
+                _ => self.atom()?,
+            };
+            let atom = self.quantifier(atom)?;
+            masks = self.product(&masks, &atom)?;
+        }
+        Ok(masks)
+    }
+
+    fn atom(&mut self) -> Result<Vec<Vec<MaskOp>>, CrackenError> {
+        let (pos, ch) = self.chars.next().unwrap();
+        let op = match ch {
+            '(' => return self.group(pos),
+            '[' => self.class(pos)?,
+            '.' => self.charset_op(PRINTABLE.collect()),
+            '\\' => match self.escape(pos)? {
+                Escaped::Char(ch) => MaskOp::Char(ch as char),
+                Escaped::Class(chars) => self.charset_op(chars),
+            },
+            '^' => return Err(self.unsupported(pos, "anchor ^ after the start")),
+            '*' | '+' => return Err(self.unsupported(pos, &format!("unbounded repetition {}", ch))),
+            '?' => return Err(self.mask_error(pos, "nothing to repeat")),
+            _ if !ch.is_ascii() || ch == '\n' || ch == '\0' => {
+                return Err(self.unsupported(pos, &format!("char {:?}", ch)))
+            }
+            _ => MaskOp::Char(ch),
+        };
+        Ok(vec![vec![op]])
+    }
+
+    /// a group after its `(`, capturing or `(?:`
+    fn group(&mut self, pos: usize) -> Result<Vec<Vec<MaskOp>>, CrackenError> {
+        if self.peek() == Some('?') {
+            self.chars.next();
+            let rest = &self.regex[self.chars.peek().map_or(self.regex.len(), |&(i, _)| i)..];
+            if rest.starts_with(':') {
+                self.chars.next();
+            } else if rest.starts_with('=') || rest.starts_with('!') {
+                return Err(self.unsupported(pos, "lookahead"));
+            } else if rest.starts_with("<=") || rest.starts_with("<!") {
+                return Err(self.unsupported(pos, "lookbehind"));
+            } else {
+                return Err(self.unsupported(pos, "group syntax (?"));
+            }
+        }
+        let masks = self.alternation()?;
+        match self.chars.next() {
+            Some((_, ')')) => Ok(masks),
+            _ => Err(self.mask_error(pos, "unclosed (")),
+        }
+    }
+
+    /// a char class after its `[`, as a single charset op
+    fn class(&mut self, pos: usize) -> Result<MaskOp, CrackenError> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.chars.next();
+        }
+        let mut chars = vec![];
+        let mut first = true;
+        loop {
+            let (chr_pos, ch) = match self.chars.next() {
+                Some(next) => next,
+                None => return Err(self.mask_error(pos, "unclosed [")),
+            };
+            // a leading `]` is a literal
+            if ch == ']' && !first {
+                break;
+            }
+            first = false;
+            let from = match ch {
+                '\\' => match self.escape(chr_pos)? {
+                    Escaped::Char(ch) => ch,
+                    Escaped::Class(class) => {
+                        chars.extend(class);
+                        continue;
+                    }
+                },
+                '[' if self.peek() == Some(':') => {
+                    return Err(self.unsupported(chr_pos, "posix class [:"))
+                }
+                _ => self.class_char(chr_pos, ch)?,
+            };
+            // a dash between two chars, a trailing dash is a literal
+            let mut ahead = self.chars.clone();
+            let is_range = matches!(ahead.next(), Some((_, '-')))
+                && !matches!(ahead.next(), Some((_, ']')) | None);
+            if !is_range {
+                chars.push(from);
+                continue;
+            }
+            self.chars.next();
+            let (to_pos, to) = self.chars.next().unwrap();
+            let to = match to {
+                '\\' => match self.escape(to_pos)? {
+                    Escaped::Char(ch) => ch,
+                    Escaped::Class(_) => return Err(self.mask_error(to_pos, "invalid range end")),
+                },
+                _ => self.class_char(to_pos, to)?,
+            };
+            if from > to {
+                return Err(self.mask_error(chr_pos, "reversed range"));
+            }
+            chars.extend(from..=to);
+        }
+        if negated {
+            chars = PRINTABLE.filter(|ch| !chars.contains(ch)).collect();
+        }
+        if chars.is_empty() {
+            return Err(self.mask_error(pos, "class matching no char"));
+        }
+        Ok(self.charset_op(chars))
+    }
+
+    fn class_char(&self, pos: usize, ch: char) -> Result<u8, CrackenError> {
+        if !ch.is_ascii() || ch == '\n' || ch == '\0' {
+            return Err(self.unsupported(pos, &format!("char {:?}", ch)));
+        }
+        Ok(ch as u8)
+    }
+
+    /// the escape after a `\`
+    fn escape(&mut self, pos: usize) -> Result<Escaped, CrackenError> {
+        let ch = match self.chars.next() {
+            Some((_, ch)) => ch,
+            None => return Err(self.mask_error(pos, "lone \\")),
+        };
+        let digits = || (b'0'..=b'9').collect::<Vec<_>>();
+        let word = || {
+            let mut chars = digits();
+            chars.extend(b'A'..=b'Z');
+            chars.push(b'_');
+            chars.extend(b'a'..=b'z');
+            chars
+        };
+        let space = || vec![b'\t', b' '];
+        let negate =
+            |chars: Vec<u8>| -> Vec<u8> { PRINTABLE.filter(|ch| !chars.contains(ch)).collect() };
+        Ok(match ch {
+            'd' => Escaped::Class(digits()),
+            'w' => Escaped::Class(word()),
+            's' => Escaped::Class(space()),
+            'D' => Escaped::Class(negate(digits())),
+            'W' => Escaped::Class(negate(word())),
+            'S' => Escaped::Class(negate(space())),
+            't' => Escaped::Char(b'\t'),
+            'x' => {
+                let hex: String = (0..2)
+                    .filter_map(|_| self.chars.next())
+                    .map(|(_, ch)| ch)
+                    .collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(ch) if hex.len() == 2 && ch.is_ascii() && ch != 0 && ch != b'\n' => {
+                        Escaped::Char(ch)
+                    }
+                    Ok(_) if hex.len() == 2 => {
+                        return Err(self.unsupported(pos, &format!("char \\x{}", hex)))
+                    }
+                    _ => return Err(self.mask_error(pos, &format!("invalid escape \\x{}", hex))),
+                }
+            }
+            '1'..='9' | 'k' => return Err(self.unsupported(pos, "backreference")),
+            'b' | 'B' | 'A' | 'z' | 'Z' => {
+                return Err(self.unsupported(pos, &format!("assertion \\{}", ch)))
+            }
+            _ if ch.is_ascii_alphanumeric() || !ch.is_ascii() => {
+                return Err(self.unsupported(pos, &format!("escape \\{}", ch)))
+            }
+            _ => Escaped::Char(ch as u8),
+        })
+    }
+
+    /// `atom` repeated by the quantifier following it, if any
+    fn quantifier(&mut self, atom: Vec<Vec<MaskOp>>) -> Result<Vec<Vec<MaskOp>>, CrackenError> {
+        let (pos, ch) = match self.chars.peek() {
+            Some(&(pos, ch)) => (pos, ch),
+            None => return Ok(atom),
+        };
+        let (min, max) = match ch {
+            '?' => (0, 1),
+            '*' | '+' => return Err(self.unsupported(pos, &format!("unbounded repetition {}", ch))),
+            '{' => match parse_bounds(&self.regex[pos..]) {
+                Some((_, None, _)) => {
+                    return Err(self.unsupported(pos, "unbounded repetition {n,}"))
+                }
+                Some((min, Some(max), len)) => {
+                    if min > max {
+                        return Err(self.mask_error(pos, "repetition with min greater than max"));
+                    }
+                    // skips the bounds but their last char, consumed below
+                    for _ in 0..len - 1 {
+                        self.chars.next();
+                    }
+                    (min, max)
+                }
+                // not a repetition, a literal {
+                None => return Ok(atom),
+            },
+            _ => return Ok(atom),
+        };
+        self.chars.next();
+        // a lazy quantifier matches the same words
+        if self.peek() == Some('?') {
+            self.chars.next();
+        }
+        if matches!(self.peek(), Some('*') | Some('+') | Some('?') | Some('{')) {
+            let (pos, _) = *self.chars.peek().unwrap();
+            if self.peek() != Some('{') || parse_bounds(&self.regex[pos..]).is_some() {
+                return Err(self.mask_error(pos, "repeated quantifier"));
+            }
+        }
+
+        let mut masks = vec![];
+        let mut repeated = vec![vec![]];
+        for count in 0..=max {
+            if count >= min {
+                masks = self.union(masks, repeated.clone())?;
+            }
+            if count < max {
+                repeated = self.product(&repeated, &atom)?;
+            }
+        }
+        Ok(masks)
+    }
+
+    /// the masks of `masks` followed by the ones of `other` which aren't in it
+    fn union(
+        &self,
+        mut masks: Vec<Vec<MaskOp>>,
+        other: Vec<Vec<MaskOp>>,
+    ) -> Result<Vec<Vec<MaskOp>>, CrackenError> {
+        for mask in other {
+            if !masks.contains(&mask) {
+                masks.push(mask);
+            }
+        }
+        self.check_masks(&masks)?;
+        Ok(masks)
+    }
+
+    /// every mask of `masks` followed by every mask of `other`
+    fn product(
+        &self,
+        masks: &[Vec<MaskOp>],
+        other: &[Vec<MaskOp>],
+    ) -> Result<Vec<Vec<MaskOp>>, CrackenError> {
+        if masks.len() * other.len() > MAX_REGEX_MASKS {
+            return Err(self.too_many_masks());
+        }
+        let mut product = vec![];
+        for mask in masks {
+            for suffix in other {
+                let mut mask = mask.clone();
+                mask.extend(suffix.iter().cloned());
+                if !product.contains(&mask) {
+                    product.push(mask);
+                }
+            }
+        }
+        self.check_masks(&product)?;
+        Ok(product)
+    }
+
+    fn check_masks(&self, masks: &[Vec<MaskOp>]) -> Result<(), CrackenError> {
+        if masks.len() > MAX_REGEX_MASKS {
+            return Err(self.too_many_masks());
+        }
+        if masks.iter().any(|mask| mask.len() >= MAX_WORD_SIZE) {
+            return Err(CrackenError::InvalidLength(format!(
+                "regex {} matches words longer than {} bytes",
+                self.regex,
+                MAX_WORD_SIZE - 1
+            )));
+        }
+        Ok(())
+    }
+
+    fn too_many_masks(&self) -> CrackenError {
+        CrackenError::Unsupported(format!(
+            "regex {} expands to more than {} masks",
+            self.regex, MAX_REGEX_MASKS
+        ))
+    }
+
+    /// the op of a class - a char, a builtin charset with the same chars or a custom charset
+    fn charset_op(&mut self, mut chars: Vec<u8>) -> MaskOp {
+        chars.sort_unstable();
+        chars.dedup();
+        if let [ch] = chars[..] {
+            return MaskOp::Char(ch as char);
+        }
+        let builtin = SYMBOL2CHARSET.iter().find(|charset| {
+            let mut builtin = charset.chars.to_vec();
+            builtin.sort_unstable();
+            builtin == chars
+        });
+        if let Some(charset) = builtin {
+            return MaskOp::BuiltinCharset(charset.symbol);
+        }
+        let idx = match self.charsets.iter().position(|charset| *charset == chars) {
+            Some(idx) => idx,
+            None => {
+                self.charsets.push(chars);
+                self.charsets.len() - 1
+            }
+        };
+        MaskOp::CustomCharset(idx)
+    }
+}
+
+/// a char or a class escape (like `\d`)
+enum Escaped {
+    Char(u8),
+    Class(Vec<u8>),
+}
+
+/// parses the `{n}`, `{n,}` or `{n,m}` at the start of `rest`, returns the min, the max and
+/// the length of the bounds
+fn parse_bounds(rest: &str) -> Option<(usize, Option<usize>, usize)> {
+    let end = rest.find('}')?;
+    let bounds = &rest[1..end];
+    let (min, max) = match bounds.split_once(',') {
+        Some((min, "")) => (min.parse().ok()?, None),
+        Some((min, max)) => (min.parse().ok()?, Some(max.parse().ok()?)),
+        None => {
+            let count = bounds.parse().ok()?;
+            (count, Some(count))
+        }
+    };
+    Some((min, max, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::compile_regex;
+    use crate::charsets::CustomCharset;
+    use crate::error::CrackenError;
+    use crate::generators::get_word_generator;
+
+    fn gen_regex(regex: &str) -> String {
+        let mut buf = vec![];
+        {
+            let mut out: Box<dyn Write> = Box::new(&mut buf);
+            for word_gen in compile_regex(regex).unwrap().generators().unwrap() {
+                word_gen.gen(&mut out).unwrap();
+            }
+        }
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn gen_masks(masks: &[&str], custom_charsets: &[&str]) -> String {
+        let custom_charsets: Vec<_> = custom_charsets
+            .iter()
+            .map(|&chars| CustomCharset::Chars(chars))
+            .collect();
+        let mut buf = vec![];
+        {
+            let mut out: Box<dyn Write> = Box::new(&mut buf);
+            for mask in masks {
+                let word_gen = get_word_generator(mask, None, None, &custom_charsets, &[]).unwrap();
+                word_gen.gen(&mut out).unwrap();
+            }
+        }
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_regex_masks() {
+        assert_eq!(
+            gen_regex("^[A-Z][a-z]{2}[0-9]$"),
+            gen_masks(&["?u?l?l?d"], &[])
+        );
+        assert_eq!(
+            gen_regex(r"\d{2,3}x?"),
+            gen_masks(&["?d?d", "?d?dx", "?d?d?d", "?d?d?dx"], &[])
+        );
+        assert_eq!(
+            gen_regex("(pass|word)[!@]"),
+            gen_masks(&["pass?1", "word?1"], &["!@"])
+        );
+        assert_eq!(
+            gen_regex("[a-c0_]{2}|(?:ab)"),
+            gen_masks(&["?1?1", "ab"], &["0_abc"])
+        );
+        assert_eq!(gen_regex("[^!-~]a[!-~]?"), gen_masks(&[" a", " a?a"], &[]));
+        assert_eq!(
+            gen_regex(r"\w\.\x41[]-]"),
+            gen_masks(
+                &["?1.A?2"],
+                &[
+                    "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz",
+                    "-]"
+                ]
+            )
+        );
+        assert_eq!(gen_regex("a{2}{"), "aa{\n");
+        // identical alternatives are generated once
+        assert_eq!(gen_regex("(a|a)b?b?"), "a\nab\nabb\n");
+
+        let masks = compile_regex("[a-z]{3,5}").unwrap();
+        assert_eq!(masks.masks.len(), 3);
+        let keyspace: u64 = masks
+            .generators()
+            .unwrap()
+            .iter()
+            .map(|word_gen| {
+                word_gen
+                    .combinations()
+                    .to_u64_digits()
+                    .first()
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .sum();
+        assert_eq!(keyspace, 26u64.pow(3) + 26u64.pow(4) + 26u64.pow(5));
+    }
+
+    #[test]
+    fn test_regex_unsupported() {
+        for (regex, msg) in [
+            ("[a-z]+", "unbounded repetition + at position 5"),
+            ("a*", "unbounded repetition * at position 1"),
+            ("a{2,}", "unbounded repetition {n,} at position 1"),
+            (r"(a)\1", "backreference at position 3"),
+            ("a(?=b)", "lookahead at position 1"),
+            ("(?<!a)b", "lookbehind at position 0"),
+            (r"\bab", "assertion \\b at position 0"),
+            ("a$b", "anchor $ before the end at position 1"),
+            ("a^", "anchor ^ after the start at position 1"),
+        ] {
+            match compile_regex(regex) {
+                Err(CrackenError::Unsupported(e)) => {
+                    assert_eq!(e, format!("regex {} - {} is not supported", regex, msg))
+                }
+                res => panic!("{}: {:?}", regex, res),
+            }
+        }
+        for regex in ["(ab", "ab)", "[ab", "a{3,2}", "[z-a]", "?", "a??+", "(|)"] {
+            assert!(
+                matches!(compile_regex(regex), Err(CrackenError::Mask(_))),
+                "{}",
+                regex
+            );
+        }
+        assert!(matches!(
+            compile_regex("(a|b){14}"),
+            Err(CrackenError::Unsupported(_))
+        ));
+    }
+}
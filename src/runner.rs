@@ -1,14 +1,97 @@
 use std::env;
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
 use std::fs::File;
-use std::io::{stdout, BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::io::{self, stderr, stdout, BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use clap::{
+    App, AppSettings, Arg, ArgMatches, Error as ClapError, ErrorKind as ClapErrorKind, SubCommand,
+};
+use num_bigint::{BigUint, ToBigUint};
+use regex::bytes::Regex;
+use serde::Serialize;
+use sha2::Digest;
 
+use crate::anagram::AnagramGenerator;
+use crate::analyze::{CharsetStats, FreqStats, MaskStats};
+use crate::builder::{affix, window, GeneratorBuilder};
+use crate::charsets::CustomCharset;
+use crate::config::{Config, Profile};
 use crate::create_smartlist::{SmartlistBuilder, SmartlistTokenizer, DEFAULT_VOCAB_SIZE};
-use crate::generators::get_word_generator;
-use crate::helpers::RawFileReader;
-use crate::password_entropy::EntropyEstimator;
-use crate::{built_info, BoxResult};
+use crate::crunch::CrunchMask;
+use crate::dedup::dedup;
+use crate::disk_space::{FsSpace, SpaceCheck};
+use crate::error::CrackenError;
+use crate::exclude::{CaseInsensitive, ExcludeSet};
+use crate::external_sort::{sort_wordlist, SortOptions};
+use crate::generate_options::{generate_subcommand, GenerateOptions};
+use crate::generators::{
+    get_word_generator, get_word_generator_with_options, hashcat_keyspace, LengthOrder,
+    RepeatLimits, WordGenerator,
+};
+use crate::helpers::{
+    decode_hex_word, format_bytes, format_duration, index_bytes, log2_biguint, parse_keyspace,
+    parse_rate, parse_size, round2, serialize_str, IndexedFormat, RawFileReader, WriteCounts,
+    HEX_PREFIX,
+};
+use crate::mask::{join_mask, palindrome_mask, parse_hcmask_line, translate_john_mask, MaskSyntax};
+use crate::model::{get_model_generator, Model};
+use crate::password_entropy::{EntropyEstimator, EntropySummary};
+use crate::policygen::{parse_classes, Policy, PolicyMask};
+use crate::regex_mask::compile_regex;
+use crate::shuffle::shuffle;
+use crate::verify::verify;
+use crate::wordlists::{
+    estimate_memory, merge_wordlists, parse_wordlist_arg, subtract_wordlist, SkippedLinesLog,
+    WordlistEncoding, WordlistOptions, WordlistSample,
+};
+use crate::words::Words;
+use crate::writer_chain::{load_exclude, PartialFiles, WriterChain, INTERRUPTED};
+use crate::{built_info, BoxResult, BUFFER_SIZE};
+
+/// default output file of generate when no -o is given
+pub(crate) const ENV_OUTPUT: &str = "CRACKEN_OUTPUT";
+/// directory of the relative -w wordlists paths
+pub(crate) const ENV_WORDLIST_DIR: &str = "CRACKEN_WORDLIST_DIR";
+/// default --io-buffer size
+pub(crate) const ENV_BUFFER_SIZE: &str = "CRACKEN_BUFFER_SIZE";
+/// default --max-keyspace
+pub(crate) const ENV_MAX_KEYSPACE: &str = "CRACKEN_MAX_KEYSPACE";
+/// largest --io-buffer, the buffer is allocated once per mask
+const MAX_IO_BUFFER: usize = 1 << 30;
+/// default --verify-max, the words of --verify are hashed in memory
+pub(crate) const DEFAULT_VERIFY_MAX: u64 = 10_000_000;
+/// default --max-keyspace, the words written to an output file without --yes-i-know
+const DEFAULT_MAX_KEYSPACE: u64 = 1_000_000_000_000;
+
+/// crunch options which are not supported by the crunch subcommand and whether they take a
+/// value, they fail instead of generating different words than crunch
+const CRUNCH_UNSUPPORTED: [(&str, bool); 11] = [
+    ("b", true),
+    ("c", true),
+    ("d", true),
+    ("f", true),
+    ("i", false),
+    ("l", true),
+    ("p", true),
+    ("q", true),
+    ("r", false),
+    ("u", false),
+    ("z", true),
+];
+
+/// the policygen maximum chars of every class args in the policy classes order, with their help
+const POLICY_MAX_ARGS: [(&str, &str); 4] = [
+    ("max-lower", "maximum number of lowercase chars"),
+    ("max-upper", "maximum number of uppercase chars"),
+    ("max-digits", "maximum number of digits"),
+    ("max-symbols", "maximum number of symbols"),
+];
 
 const EXAMPLE_USAGE: &str = r#"
 For specific subcommand help run: cracken <subcommand> --help
@@ -42,15 +125,68 @@ Example Usage:
   # 4 lowercase chars with years 2000-2019 suffix
   cracken -c 01 '?l?l?l?l20?1?d'
 
+  # multi-char tokens - 4 lowercase chars ending with one of !, !!, 123 or 2024
+  cracken --tokens '!|!!|123|2024' '?l?l?l?l?1'
+
   # starts with firstname from wordlist followed by 4 digits
   cracken -w firstnames.txt '?w1?d?d?d?d'
 
   # starts with firstname from wordlist with lastname from wordlist ending with symbol
   cracken -w firstnames.txt -w lastnames.txt -c '!@#$' '?w1?w2?1'
 
+  # capitalized firstnames followed by uppercase lastnames, transformed once at load time
+  cracken -w firstnames.txt:capitalize -w lastnames.txt:upper '?w1?w2'
+
+  # words followed by the same words reversed (e.g. passworddrowssap)
+  cracken -w words.txt '?w1?w1:rev'
+
+  # 4 chars prefixes of names followed by 4 digits pins
+  cracken -w names.txt '?w1[:4]?d?d?d?d'
+
+  # quick dry run on a reproducible random 1% of the wordlists
+  cracken -w firstnames.txt -w lastnames.txt --wordlist-sample 0.01 --seed 42 '?w1?w2'
+
   # repeating wordlists multiple times and combining charsets
   cracken -w verbs.txt -w nouns.txt '?w1?w2?w1?w2?w2?d?d?d'
 
+  # keyboard walks of length 4 to 6 followed by 2 digits (e.g. qwer12, 1qaz99)
+  cracken '?K{4-6}?d?d'
+
+  # firstname followed by a birthdate between 1970 and 2005 (e.g. john31121999)
+  cracken -w firstnames.txt '?w1?D{DDMMYYYY,1970-2005}'
+
+  # 8 lowercase chars without 3 identical chars in a row or any char used more than twice
+  cracken --seq-max 2 --occurrence-max 2 '?l?l?l?l?l?l?l?l'
+
+  # John the Ripper mask syntax with inline ranges (same words as john --stdout --mask=)
+  cracken --syntax john -w words.txt '[Pp]?w[0-9][!@#]'
+
+  # the mask of a file (or of stdin with @-), away from the shell quoting
+  cracken -w words.txt @mask.txt
+
+  # crunch compatible - the words of `crunch 8 8 -t pass%%%% -s pass5000` in the same order
+  cracken crunch 8 8 -t pass%%%% -s pass5000
+
+  # smallest masks set covering 60% of leaked passwords within 1e13 words
+  cracken maskgen -f leaks.txt --target-coverage 0.6 --max-keyspace 1e13 -o leaks.masks
+
+  # the words of a mask from the most likely according to the chars of leaked passwords
+  cracken train -f leaks.txt -o leaks.model
+  cracken --order leaks.model '?l?l?l?l?l?d?d'
+
+  # masks of the 8 to 10 chars passwords with upper, lower and digits and at most one symbol
+  cracken policygen --min 8 --max 10 --require u,l,d --max-symbols 1 -o policy.masks
+
+
+  # stream the words into hashcat and exit with its exit code
+  cracken --pipe-to 'hashcat -m 1000 hashes.txt -a 0 -w 3' '?u?l?l?l?l?d?d'
+
+  # number of words of a mask and how long trying them takes at 50 GH/s
+  cracken --stats --rate 50GH '?u?l?l?l?l?l?d?d'
+
+  # use the charsets and wordlists of the [corp] section of ~/.config/cracken/config.toml
+  cracken -P corp '?u?l?l?l?l?d?d?1'
+
 
   ## Create Smartlists Subcommand Examples:
 
@@ -60,10 +196,34 @@ Example Usage:
   # create smartlist from multiple files with multiple tokenization algorithms
   cracken create -t bpe -t unigram -t wordpiece -f rockyou.txt -f passwords.txt -f wikipedia.txt --smartlist smart.txt
 
+  # create a ranked smartlist of 50k BPE subwords
+  cracken create-smartlists -t bpe -f rockyou.txt -o tokens.txt --vocab-size 50000
+
   # create smartlist with minimum subword length of 3 and max numbers-only subwords of size 6
   cracken create -f rockyou.txt --min-word-len 3 --numbers-max-size 6 --smartlist smart.txt
 
 
+  ## Rank Subcommand Examples:
+
+  # position of a password in the generation order of a mask
+  cracken rank --mask '?u?l?l?l?l?l?d?d' 'Summer19'
+
+
+  ## Analyze and Validate Subcommands Examples:
+
+  # 10 most common charset masks of a passwords file
+  cracken analyze masks -n 10 passwords.txt
+
+  # check a mask and its wordlists without generating
+  cracken validate -w names.txt '?w1?d?d'
+
+
+  ## Version Subcommand Examples:
+
+  # build details for bug reports (same as cracken version)
+  cracken --version --verbose
+
+
   ## Entropy Subcommand Examples:
 
   # estimating entropy of a password
@@ -76,7 +236,13 @@ Example Usage:
   cracken entropy --smartlist vocab.txt -p passwords.txt
 "#;
 
-fn parse_args(args: Option<Vec<&str>>) -> ArgMatches<'static> {
+/// the parsed command line, with the options of the generate subcommand checked
+struct ParsedArgs {
+    matches: ArgMatches<'static>,
+    generate: Option<GenerateOptions>,
+}
+
+fn parse_args(args: Option<Vec<&str>>) -> Result<ParsedArgs, RunError> {
     let osargs: Vec<String>;
     let mut args = match args {
         Some(itr) => itr,
@@ -86,12 +252,46 @@ fn parse_args(args: Option<Vec<&str>>) -> ArgMatches<'static> {
         }
     };
 
+    // --version is a short form of the version subcommand, --version --verbose is the full one
+    if matches!(args.get(1), Some(&"--version") | Some(&"-V")) {
+        args[1] = "version";
+        match args
+            .iter()
+            .position(|&arg| arg == "--verbose" || arg == "-v")
+        {
+            Some(pos) => {
+                args.remove(pos);
+            }
+            None => args.insert(2, "--short"),
+        }
+    }
+
     // workaround for default subcommand
-    if args.len() >= 2 && !vec!["generate", "entropy", "create", "--help"].contains(&args[1]) {
+    if args.len() >= 2
+        && ![
+            "generate",
+            "entropy",
+            "create",
+            "create-smartlists",
+            "rank",
+            "analyze",
+            "maskgen",
+            "train",
+            "validate",
+            "crunch",
+            "anagram",
+            "from-regex",
+            "policygen",
+            "wordlist",
+            "version",
+            "--help",
+        ]
+        .contains(&args[1])
+    {
         args.insert(1, "generate");
     }
 
-    App::new(format!(
+    let matches = App::new(format!(
         "Cracken v{} - {}",
         built_info::PKG_VERSION,
         built_info::PKG_DESCRIPTION
@@ -109,92 +309,8 @@ fn parse_args(args: Option<Vec<&str>>) -> ArgMatches<'static> {
             built_info::PKG_HOMEPAGE,
         )
         .as_str())
-        .subcommand(SubCommand::with_name("generate")
-        .about("(default) - Generates newline separated words according to given mask and wordlist files")
-        .display_order(0)
-    .arg(
-        Arg::with_name("mask")
-            .long_help(
-                r#"the wordlist mask to generate.
-available masks are:
-    builtin charsets:
-    ?d - digits: "0123456789"
-    ?l - lowercase: "abcdefghijklmnopqrstuvwxyz"
-    ?u - uppercase: "ABCDEFGHIJKLMNOPQRSTUVWXYZ"
-    ?s - symbols: " !\"\#$%&'()*+,-./:;<=>?@[\\]^_`{|}~"
-    ?a - all characters: ?d + ?l + ?u + ?s
-    ?b - all binary values: (0-255)
-
-    custom charsets ?1 to ?9:
-    ?1 - first custom charset specified by --charset 'mychars'
-
-    wordlists ?w1 to ?w9:
-    ?w1 - first wordlist specified by --wordlist 'my-wordlist.txt'
-"#,
-            )
-            .takes_value(true)
-            .required_unless("masks-file"),
-    )
-    .arg(Arg::with_name("masks-file")
-            .short("i")
-            .long("masks-file")
-            .help("a file containing masks to generate")
-            .takes_value(true)
-            .required_unless("mask"),
-    )
-    .arg(
-        Arg::with_name("min-length")
-            .short("m")
-            .long("minlen")
-            .help("minimum length of the mask to start from")
-            .takes_value(true)
-            .required(false),
-    )
-    .arg(
-        Arg::with_name("max-length")
-            .short("x")
-            .long("maxlen")
-            .help("maximum length of the mask to start from")
-            .takes_value(true)
-            .required(false),
-    )
-    .arg(
-        Arg::with_name("stats")
-            .short("s")
-            .long("stats")
-            .help("prints the number of words this command will generate and exits")
-            .takes_value(false)
-            .required(false),
-    ).arg(
-        Arg::with_name("custom-charset")
-            .short("c")
-            .long("custom-charset")
-            .help("custom charset (string of chars). up to 9 custom charsets - ?1 to ?9. use ?1 on the mask for the first charset")
-            .takes_value(true)
-            .required(false)
-            .multiple(true)
-            .number_of_values(1)
-            .max_values(9),
-    )
-    .arg(
-        Arg::with_name("wordlist")
-            .short("w")
-            .long("wordlist")
-            .help("filename containing newline (0xA) separated words. note: currently all wordlists loaded to memory")
-            .takes_value(true)
-            .required(false)
-            .multiple(true)
-            .number_of_values(1)
-            .max_values(9),
-    )
-    .arg(
-        Arg::with_name("output-file")
-            .short("o")
-            .long("output-file")
-            .help("output file to write the wordlist to, defaults to stdout")
-            .takes_value(true)
-            .required(false),
-    )).subcommand(SubCommand::with_name("entropy")
+        .subcommand(generate_subcommand())
+    .subcommand(SubCommand::with_name("entropy")
         .about(r#"
 Computes the estimated entropy of password or password file.
 The entropy of a password is the log2(len(keyspace)) of the password.
@@ -230,7 +346,7 @@ There are two types of keyspace size estimations:
         Arg::with_name("summary")
             .short("s")
             .long("summary")
-            .help("output summary of entropy for password")
+            .help("output summary of the passwords entropy - min, median, avg and max entropy, histogram and the weakest passwords")
             .takes_value(false)
             .required(false)
             .conflicts_with("password"),
@@ -243,8 +359,46 @@ There are two types of keyspace size estimations:
             .required(false)
             .possible_values(&["hybrid", "charset"])
             .conflicts_with("password"),
+        ).arg(
+        Arg::with_name("bucket-size")
+            .long("bucket-size")
+            .help("width in bits of the summary entropy histogram buckets (default: 10)")
+            .takes_value(true)
+            .required(false)
+            .requires("summary"),
+        ).arg(
+        Arg::with_name("weakest")
+            .long("weakest")
+            .help("number of weakest passwords to show in the summary (default: 10)")
+            .takes_value(true)
+            .required(false)
+            .requires("summary"),
+        ).arg(
+        Arg::with_name("output-file")
+            .short("o")
+            .long("output-file")
+            .help("write a tab separated `password<TAB>entropy` line (or --format line) for every password to this file")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("password"),
+        ).arg(
+        Arg::with_name("format")
+            .long("format")
+            .help("format of the passwords file results. jsonl writes a json object per password with the password (or hex for invalid utf-8), bits, mask, tokens and estimator (default: csv)")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["csv", "jsonl"])
+            .conflicts_with("password"),
+        ).arg(
+        Arg::with_name("no-echo")
+            .long("no-echo")
+            .help("write the line index of the password instead of the password itself (jsonl also omits the tokens)")
+            .takes_value(false)
+            .required(false)
+            .conflicts_with("password"),
         )
     ).subcommand(SubCommand::with_name("create")
+        .alias("create-smartlists")
         .about("Create a new smartlist from input file(s)")
         .arg(
         Arg::with_name("file")
@@ -288,6 +442,7 @@ There are two types of keyspace size estimations:
             Arg::with_name("vocab_max_size")
             .short("m")
             .long("vocab-max-size")
+            .alias("vocab-size")
             .help("max vocabulary size")
             .takes_value(true)
             .required(false)
@@ -314,266 +469,6045 @@ There are two types of keyspace size estimations:
             .takes_value(true)
             .required(false)
         )
-    )
-    .get_matches_from(args)
-}
-
-/// helper for handling cast and optional values at same time, exiting on error
-macro_rules! optional_value_t_or_exit {
-    ($m:ident, $v:expr, $t:ty) => {
-        optional_value_t_or_exit!($m.value_of($v), $t)
-    };
-    ($m:ident.value_of($v:expr), $t:ty) => {
-        if let Some(v) = $m.value_of($v) {
-            match v.parse::<$t>() {
-                Ok(val) => Some(val),
-                Err(_) => clap::Error::value_validation_auto(format!(
-                    "The argument '{}' isn't a valid value",
-                    v
-                ))
-                .exit(),
-            }
-        } else {
-            None
-        }
-    };
-}
-
-pub fn run(args: Option<Vec<&str>>) -> BoxResult<()> {
-    // parse args
-    let arg_matches = parse_args(args);
-
-    match arg_matches.subcommand() {
-        ("generate", Some(matches)) => run_wordlist_generator(matches),
-        ("create", Some(matches)) => run_create_smartlist(matches),
-        ("entropy", Some(matches)) => run_entropy_estimator(matches),
-        (_, None) => bail!("invalid command"),
-        _ => unreachable!("oopsie, subcommand is required"),
-    }
-}
-
-pub fn run_wordlist_generator(args: &ArgMatches) -> BoxResult<()> {
-    let masks = match args.value_of("mask") {
-        Some(mask) => vec![mask.to_owned()],
-        None => {
-            let masks_fname = args.value_of("masks-file").unwrap();
-            let file = BufReader::new(File::open(masks_fname)?);
-            let masks: Result<Vec<_>, _> = file.lines().collect();
-            masks?
-        }
-    };
-
-    let minlen = optional_value_t_or_exit!(args, "min-length", usize);
-    let maxlen = optional_value_t_or_exit!(args, "max-length", usize);
-    let outfile = args.value_of("output-file");
-
-    // create output file
-    let mut out: Box<dyn Write> = match outfile {
-        Some(fname) => match File::create(fname) {
-            Ok(fp) => Box::new(fp),
-            Err(e) => bail!("cannot open file {}: {}", fname, e),
-        },
-        None => Box::new(stdout()),
-    };
-
-    let custom_charsets: Vec<&str> = args
-        .values_of("custom-charset")
-        .map(|x| x.collect())
-        .unwrap_or_else(Vec::new);
-
-    let wordlists: Vec<&str> = args
-        .values_of("wordlist")
-        .map(|x| x.collect())
-        .unwrap_or_else(Vec::new);
-
-    for mask in masks {
-        // create output file
-        let word_generator =
-            get_word_generator(&mask, minlen, maxlen, &custom_charsets, &wordlists)?;
-        if args.is_present("stats") {
-            let combs = word_generator.combinations();
-            println!("{}", combs);
-            return Ok(());
-        }
-
-        match word_generator.gen(&mut out) {
-            Ok(_) => {}
-            Err(e) => {
-                match e.kind() {
-                    // ignore broken pipe, (e.g. happens when using head)
-                    ErrorKind::BrokenPipe => return Ok(()),
-                    _ => bail!("error occurred writing to out: {}", e),
-                }
-            }
-        }
-    }
-    Ok(())
-}
-
-pub fn run_entropy_estimator(args: &ArgMatches) -> BoxResult<()> {
-    let smartlist_files: Vec<&str> = args.values_of("smartlist").map(|x| x.collect()).unwrap();
-    let est = EntropyEstimator::from_files(smartlist_files.as_ref())?;
-    let is_summary_only = args.is_present("summary");
-    let mask_type = args.value_of("mask_type").unwrap_or("hybrid");
-    let mut total_entropy = 0f64;
-    let mut pwd_count = 0usize;
-    let mut stdout = stdout();
-
-    if let Some(pwd) = args.value_of("password") {
-        let entropy_result = est.estimate_password_entropy(pwd.as_bytes())?;
-        let text = format!(
-            "hybrid-min-split: {:?}
-hybrid-mask: {}
-hybrid-min-entropy: {:.2}
---
-charset-mask: {}
-charset-mask-entropy: {:.2}
-            ",
-            entropy_result.subword_entropy_min_split,
-            entropy_result.min_subword_mask,
-            entropy_result.subword_entropy,
-            entropy_result.charset_mask,
-            entropy_result.mask_entropy,
-        );
-        if let Err(e) = write!(&mut stdout, "{}", text) {
-            match e.kind() {
-                // ignore broken pipe, (e.g. happens when using head)
-                ErrorKind::BrokenPipe => return Ok(()),
-                _ => bail!("error occurred writing to out: {}", e),
-            }
-        }
-    } else if let Some(pwd_file) = args.value_of("passwords-file") {
-        let file = File::open(pwd_file)?;
-        let reader = RawFileReader::new(file);
-        for pwd in reader.into_iter() {
-            let pwd = pwd?;
-            let entropy_result = est.estimate_password_entropy(&pwd)?;
-            let (pwd_entropy, pwd_mask) = match mask_type {
-                "hybrid" => (
-                    entropy_result.subword_entropy,
-                    entropy_result.min_subword_mask,
-                ),
-                "charset" => (entropy_result.mask_entropy, entropy_result.charset_mask),
-                _ => unreachable!("invalid entropy type"),
-            };
-            if !is_summary_only {
-                if let Err(e) = writeln!(
-                    &mut stdout,
-                    "{:.2},{},{}",
-                    pwd_entropy,
-                    pwd_mask,
-                    String::from_utf8_lossy(&pwd)
-                ) {
-                    match e.kind() {
-                        // ignore broken pipe, (e.g. happens when using head)
-                        ErrorKind::BrokenPipe => return Ok(()),
-                        _ => bail!("error occurred writing to out: {}", e),
-                    }
-                }
-            } else {
-                total_entropy += pwd_entropy;
-            }
-            pwd_count += 1;
-        }
-
-        if is_summary_only {
-            writeln!(
-                &mut stdout,
-                "avg entropy: {}",
-                total_entropy / pwd_count as f64
-            )?;
-        }
-    }
-    Ok(())
-}
-
-pub fn run_create_smartlist(args: &ArgMatches) -> BoxResult<()> {
-    let outfile = args.value_of("smartlist").unwrap();
-    let infiles = args.values_of("file").map(|x| x.collect()).unwrap();
-    let vocab_max_size =
-        optional_value_t_or_exit!(args, "vocab_max_size", u32).unwrap_or(DEFAULT_VOCAB_SIZE);
-    let min_frequency = optional_value_t_or_exit!(args, "min_frequency", u32).unwrap_or(0);
-    let print_progress = !args.is_present("quiet");
-    let numbers_max_size = optional_value_t_or_exit!(args, "numbers_max_size", u32);
-    let min_word_len = optional_value_t_or_exit!(args, "min_word_len", u32).unwrap_or(1);
-
-    let tokenizers = args
-        .values_of("tokenizer")
-        .map(|x| x.collect())
-        .unwrap_or_else(|| vec!["unigram"])
-        .into_iter()
-        .map(|x| match x {
-            "bpe" => SmartlistTokenizer::BPE,
-            "unigram" => SmartlistTokenizer::Unigram,
-            "wordpiece" => SmartlistTokenizer::WordPiece,
-            _ => unreachable!("invalid tokenizer {}", x),
-        });
-
-    let mut writer = BufWriter::new(File::create(outfile)?);
-    let vocab = SmartlistBuilder::new()
-        .infiles(infiles)
-        .min_frequency(min_frequency)
-        .vocab_max_size(vocab_max_size)
-        .tokenizers(tokenizers.into_iter())
-        .print_progress(print_progress)
-        .numbers_max_size(numbers_max_size)
-        .min_word_len(min_word_len)
-        .build()?;
-
-    // write to file
-    for word in vocab.iter() {
-        writer.write_all(word.as_bytes())?;
-        writer.write_all(b"\n")?;
-    }
-    Ok(())
-}
-
-#[cfg(test)]
+    ).subcommand(SubCommand::with_name("rank")
+        .about("Finds the position of a password in the generation order of a mask")
+        .arg(
+        Arg::with_name("mask")
+            .long("mask")
+            .help("the mask to rank the password by, see `cracken generate --help` for the mask syntax")
+            .takes_value(true)
+            .required(true),
+        ).arg(
+        Arg::with_name("password")
+            .help("password to find in the mask keyspace")
+            .takes_value(true)
+            .required(true),
+        ).arg(
+        Arg::with_name("min-length")
+            .short("m")
+            .long("minlen")
+            .help("minimum length of the mask to start from")
+            .takes_value(true)
+            .required(false),
+        ).arg(
+        Arg::with_name("max-length")
+            .short("x")
+            .long("maxlen")
+            .help("maximum length of the mask to start from")
+            .takes_value(true)
+            .required(false),
+        ).arg(
+        Arg::with_name("custom-charset")
+            .short("c")
+            .long("custom-charset")
+            .help("custom charset (string of chars), same as in generate")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .max_values(9),
+        ).arg(
+        Arg::with_name("tokens")
+            .long("tokens")
+            .help("custom charset of multi-char tokens separated by '|', same as in generate")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .max_values(9),
+        ).arg(
+        Arg::with_name("wordlist")
+            .short("w")
+            .long("wordlist")
+            .help("filename containing newline (0xA) separated words, same as in generate")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .max_values(9),
+        )
+    ).subcommand(SubCommand::with_name("analyze")
+        .about("Analyzes existing passwords to help building masks")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("masks")
+            .about("Counts the charset mask (e.g. ?u?l?l?l?d?d) of every password and prints the most common ones")
+            .arg(
+            Arg::with_name("passwords-file")
+                .help("newline separated passwords file to analyze")
+                .takes_value(true)
+                .required(true),
+            ).arg(
+            Arg::with_name("top")
+                .short("n")
+                .long("top")
+                .help("number of masks to print (default: 25)")
+                .takes_value(true)
+                .required(false),
+            )
+        )
+        .subcommand(SubCommand::with_name("charsets")
+            .about("Prints the share of the entries of every length made of chars of ?l, ?d, ?l?d, ?a etc., and the smallest mask of builtin charsets generating them all")
+            .arg(
+            Arg::with_name("wordlist")
+                .help("newline separated wordlist file to analyze")
+                .takes_value(true)
+                .required(true),
+            ).arg(
+            Arg::with_name("format")
+                .long("format")
+                .help("output format")
+                .takes_value(true)
+                .required(false)
+                .possible_values(&["text", "json"])
+                .default_value("text"),
+            )
+        )
+        .subcommand(SubCommand::with_name("freq")
+            .about("Prints the lengths, the char classes at every position and the most common passwords, prefixes and suffixes of passwords")
+            .arg(
+            Arg::with_name("passwords-file")
+                .help("newline separated passwords file to analyze")
+                .takes_value(true)
+                .required(true),
+            ).arg(
+            Arg::with_name("top")
+                .short("n")
+                .long("top")
+                .help("number of most common passwords, prefixes and suffixes to print (default: 10)")
+                .takes_value(true)
+                .required(false),
+            ).arg(
+            Arg::with_name("affix-len")
+                .long("affix-len")
+                .help("length in bytes of the prefixes and suffixes, of the passwords longer than it (default: 3)")
+                .takes_value(true)
+                .required(false),
+            ).arg(
+            Arg::with_name("max-distinct")
+                .long("max-distinct")
+                .help("most distinct passwords, prefixes and suffixes counted each, the least common are dropped above twice as many and the counts become approximate (default: 1000000)")
+                .takes_value(true)
+                .required(false),
+            ).arg(
+            Arg::with_name("format")
+                .long("format")
+                .help("output format")
+                .takes_value(true)
+                .required(false)
+                .possible_values(&["text", "json"])
+                .default_value("text"),
+            )
+        )
+    ).subcommand(SubCommand::with_name("wordlist")
+        .about("Wordlists tools")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("merge")
+            .about("Concatenates wordlists without the duplicate lines, keeping the order of their first occurrence. the counts are written to stderr")
+            .arg(
+            Arg::with_name("wordlists")
+                .help("newline separated wordlists files to merge, in order")
+                .takes_value(true)
+                .multiple(true)
+                .required(true),
+            ).arg(
+            Arg::with_name("output-file")
+                .short("o")
+                .long("output-file")
+                .help("output file to write the merged wordlist to, defaults to stdout")
+                .takes_value(true)
+                .required(false),
+            )
+        )
+        .subcommand(SubCommand::with_name("subtract")
+            .about("Writes the lines of a wordlist which are not in another one, in order. the counts are written to stderr")
+            .arg(
+            Arg::with_name("base")
+                .help("newline separated wordlist file to write the lines of")
+                .takes_value(true)
+                .required(true),
+            ).arg(
+            Arg::with_name("remove")
+                .help("newline separated wordlist file of the lines to remove, loaded to memory")
+                .takes_value(true)
+                .required(true),
+            ).arg(
+            Arg::with_name("output-file")
+                .short("o")
+                .long("output-file")
+                .help("output file to write the remaining lines to, defaults to stdout")
+                .takes_value(true)
+                .required(false),
+            ).arg(
+            Arg::with_name("ignore-case")
+                .short("i")
+                .long("ignore-case")
+                .help("compare the lines ignoring the case of the ascii letters")
+                .takes_value(false)
+                .required(false),
+            ).arg(
+            Arg::with_name("bloom")
+                .long("bloom")
+                .help("load the lines to remove to a bloom filter of about 2 bytes per line instead. about 1 in 1000 of the other lines is removed too")
+                .takes_value(false)
+                .required(false),
+            )
+        )
+        .subcommand(SubCommand::with_name("sort")
+            .about("Sorts a wordlist byte-wise, also larger than the memory: sorted chunks of it are written to temporary files and merged. the progress and counts are written to stderr")
+            .arg(
+            Arg::with_name("wordlist")
+                .help("newline separated wordlist file to sort")
+                .takes_value(true)
+                .required(true),
+            ).arg(
+            Arg::with_name("output-file")
+                .short("o")
+                .long("output-file")
+                .help("output file to write the sorted wordlist to, defaults to stdout")
+                .takes_value(true)
+                .required(false),
+            ).arg(
+            Arg::with_name("memory")
+                .long("memory")
+                .help("memory of the lines sorted at once (e.g. 4G), larger wordlists are sorted in chunks of this size")
+                .takes_value(true)
+                .required(false)
+                .default_value("1G"),
+            ).arg(
+            Arg::with_name("tmp-dir")
+                .long("tmp-dir")
+                .help("directory of the temporary sorted chunks, needs about the wordlist size free. defaults to the system temporary directory")
+                .takes_value(true)
+                .required(false),
+            ).arg(
+            Arg::with_name("unique")
+                .short("u")
+                .long("unique")
+                .help("write every distinct line once")
+                .takes_value(false)
+                .required(false),
+            ).arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .help("don't write the progress and counts to stderr")
+                .takes_value(false)
+                .required(false),
+            )
+        )
+    ).subcommand(SubCommand::with_name("maskgen")
+        .about("Selects the masks covering a target share of existing passwords with the smallest keyspace")
+        .arg(
+        Arg::with_name("passwords-file")
+            .short("f")
+            .long("passwords-file")
+            .help("newline separated passwords file to analyze")
+            .takes_value(true)
+            .required(true),
+        ).arg(
+        Arg::with_name("target-coverage")
+            .long("target-coverage")
+            .help("share of the passwords (0 to 1) the masks cover, masks are selected by their passwords per word until reaching it (default: 1)")
+            .takes_value(true)
+            .required(false),
+        ).arg(
+        Arg::with_name("max-keyspace")
+            .long("max-keyspace")
+            .help("maximum number of words of all the masks together, masks which don't fit are skipped (e.g. 1e13)")
+            .takes_value(true)
+            .required(false),
+        ).arg(
+        Arg::with_name("output-file")
+            .short("o")
+            .long("output-file")
+            .help("output file to write the masks to (usable with generate -i), defaults to stdout. the report (mask, hits, cumulative coverage and keyspace) is written to stderr")
+            .takes_value(true)
+            .required(false),
+        )
+    ).subcommand(SubCommand::with_name("train")
+        .about("Trains a model of the chars at every position of existing passwords, for generate --order")
+        .arg(
+        Arg::with_name("passwords-file")
+            .short("f")
+            .long("passwords-file")
+            .help("newline separated passwords file to train on")
+            .takes_value(true)
+            .required(true),
+        ).arg(
+        Arg::with_name("output-file")
+            .short("o")
+            .long("output-file")
+            .help("output file to write the model to")
+            .takes_value(true)
+            .required(true),
+        )
+    ).subcommand(SubCommand::with_name("validate")
+        .about("Validates a mask and its charsets and wordlists without generating words")
+        .arg(
+        Arg::with_name("mask")
+            .help("the mask to validate, see `cracken generate --help` for the mask syntax")
+            .takes_value(true)
+            .required(true),
+        ).arg(
+        Arg::with_name("custom-charset")
+            .short("c")
+            .long("custom-charset")
+            .help("custom charset (string of chars), same as in generate")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .max_values(9),
+        ).arg(
+        Arg::with_name("tokens")
+            .long("tokens")
+            .help("custom charset of multi-char tokens separated by '|', same as in generate")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .max_values(9),
+        ).arg(
+        Arg::with_name("wordlist")
+            .short("w")
+            .long("wordlist")
+            .help("filename containing newline (0xA) separated words, same as in generate")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .max_values(9),
+        )
+    ).subcommand(SubCommand::with_name("crunch")
+        .about("crunch compatible wordlist generation - `cracken crunch 1 3 abc` generates the same words as `crunch 1 3 abc`")
+        .display_order(5)
+    .arg(
+        Arg::with_name("min")
+            .help("minimum length of the words")
+            .takes_value(true)
+            .required(true),
+    ).arg(
+        Arg::with_name("max")
+            .help("maximum length of the words")
+            .takes_value(true)
+            .required(true),
+    ).arg(
+        Arg::with_name("charset")
+            .help("the chars of the words in generation order (default: lowercase). with -t up to 4 charsets of the @ , % ^ placeholders, + keeps the default one")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .max_values(4),
+    ).arg(
+        Arg::with_name("pattern")
+            .short("t")
+            .help("pattern of the words - @ lowercase, , uppercase, % digits, ^ symbols and any other char as is. min and max must be the pattern length")
+            .takes_value(true)
+            .required(false),
+    ).arg(
+        Arg::with_name("start")
+            .short("s")
+            .help("start at this word")
+            .takes_value(true)
+            .required(false),
+    ).arg(
+        Arg::with_name("end")
+            .short("e")
+            .help("stop after this word")
+            .takes_value(true)
+            .required(false),
+    ).arg(
+        Arg::with_name("output-file")
+            .short("o")
+            .help("output file to write the wordlist to, defaults to stdout")
+            .takes_value(true)
+            .required(false),
+    ).args(&CRUNCH_UNSUPPORTED
+        .iter()
+        .map(|&(name, takes_value)| {
+            Arg::with_name(name)
+                .short(name)
+                .takes_value(takes_value)
+                .required(false)
+                .hidden(true)
+        })
+        .collect::<Vec<_>>()
+    )
+    ).subcommand(SubCommand::with_name("anagram")
+        .about("Generates every distinct permutation of the chars of a word in lexicographic order")
+    .arg(
+        Arg::with_name("word")
+            .help("the chars to permute, repeated chars don't repeat words")
+            .takes_value(true)
+            .required(true),
+    ).arg(
+        Arg::with_name("output-file")
+            .short("o")
+            .long("output-file")
+            .help("output file to write the wordlist to, defaults to stdout")
+            .takes_value(true)
+            .required(false),
+    ).arg(
+        Arg::with_name("stats")
+            .short("s")
+            .long("stats")
+            .help("prints the number of words this command will generate and exits")
+            .takes_value(false)
+            .required(false),
+        )
+    ).subcommand(SubCommand::with_name("from-regex")
+        .about("Generates the words matched by a regex of literal chars, classes, bounded repetitions and alternations")
+    .arg(
+        Arg::with_name("regex")
+            .help("the regex, e.g. '^[A-Z][a-z]{5}[0-9]{2}$'. unbounded repetitions (* + {n,}), backreferences and lookarounds are not supported")
+            .takes_value(true)
+            .required(true),
+    ).arg(
+        Arg::with_name("output-file")
+            .short("o")
+            .long("output-file")
+            .help("output file to write the wordlist to, defaults to stdout")
+            .takes_value(true)
+            .required(false),
+    ).arg(
+        Arg::with_name("stats")
+            .short("s")
+            .long("stats")
+            .help("prints the number of words this command will generate and exits")
+            .takes_value(false)
+            .required(false),
+        )
+    ).subcommand(SubCommand::with_name("policygen")
+        .about("Generates the masks of the passwords complying with a password policy, ordered by keyspace")
+    .arg(
+        Arg::with_name("min")
+            .long("min")
+            .help("minimum length of the passwords")
+            .takes_value(true)
+            .required(true),
+    ).arg(
+        Arg::with_name("max")
+            .long("max")
+            .help("maximum length of the passwords")
+            .takes_value(true)
+            .required(true),
+    ).arg(
+        Arg::with_name("require")
+            .long("require")
+            .help("comma separated classes every password has at least one char of - l (lowercase), u (uppercase), d (digits) and s (symbols), e.g. u,l,d")
+            .takes_value(true)
+            .required(false),
+    ).args(&POLICY_MAX_ARGS
+        .iter()
+        .map(|&(name, class)| {
+            Arg::with_name(name)
+                .long(name)
+                .help(class)
+                .takes_value(true)
+                .required(false)
+        })
+        .collect::<Vec<_>>()
+    ).arg(
+        Arg::with_name("output-file")
+            .short("o")
+            .long("output-file")
+            .help("output file to write the masks to (usable with generate -i), defaults to stdout")
+            .takes_value(true)
+            .required(false),
+    ).arg(
+        Arg::with_name("stats")
+            .short("s")
+            .long("stats")
+            .help("writes the keyspace of every mask (mask<TAB>keyspace) and the total instead of the masks")
+            .takes_value(false)
+            .required(false),
+        )
+    ).subcommand(SubCommand::with_name("version")
+        .about("Prints the version, git commit, build time, target, compiler and features of this build")
+        .display_order(6)
+    .arg(
+        Arg::with_name("format")
+            .long("format")
+            .help("output format")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["text", "json"])
+            .default_value("text"),
+    ).arg(
+        Arg::with_name("short")
+            .long("short")
+            .help("print only the name and version (as --version does)")
+            .takes_value(false)
+            .required(false)
+            .hidden(true),
+        )
+    )
+    .get_matches_from_safe(args)
+    .map_err(|e| match e.kind {
+        ClapErrorKind::HelpDisplayed | ClapErrorKind::VersionDisplayed => e.exit(),
+        _ => RunError::Args(e),
+    })?;
+    let generate = match matches.subcommand_matches("generate") {
+        Some(args) => Some(GenerateOptions::from_args(args).map_err(RunError::from)?),
+        None => None,
+    };
+    Ok(ParsedArgs { matches, generate })
+}
+
+/// helper for handling cast and optional values at same time, returning a usage error on
+/// invalid values
+macro_rules! optional_value_t {
+    ($m:ident, $v:expr, $t:ty) => {
+        optional_value_t!($m.value_of($v), $t)
+    };
+    ($m:ident.value_of($v:expr), $t:ty) => {
+        if let Some(v) = $m.value_of($v) {
+            match v.parse::<$t>() {
+                Ok(val) => Some(val),
+                Err(_) => {
+                    return Err(RunError::Usage(format!(
+                        "invalid value '{}' for argument '{}'",
+                        v, $v
+                    ))
+                    .into())
+                }
+            }
+        } else {
+            None
+        }
+    };
+}
+pub(crate) use optional_value_t;
+
+/// failure classes of a run, each exits the process with its own code (see `exit_code`)
+#[derive(Debug)]
+pub enum RunError {
+    /// invalid arguments, mask or config (exit code 2)
+    Usage(String),
+    /// the command line can't be parsed, e.g. an unknown flag (exit code 2)
+    Args(ClapError),
+    /// the mask, charsets or wordlists of generate are invalid (exit code 2) or a wordlist
+    /// can't be read (exit code 3)
+    Generator(CrackenError),
+    /// an input file (masks, wordlists, passwords, smartlists) can't be read (exit code 3)
+    Input(String),
+    /// the generated words or results can't be written (exit code 4)
+    Output(String),
+    /// a safety guard refused to run, e.g. overwriting an existing file (exit code 5)
+    Guard(String),
+    /// --verify found masks generating other words than their combinations (exit code 6)
+    Verify(String),
+    /// the --pipe-to command failed (exits with the command exit code)
+    PipeTo(String, i32),
+    /// interrupted by ctrl-c (exit code 130)
+    Interrupted(String),
+}
+
+impl RunError {
+    /// the process exit code of this failure, 0 is success and 1 is left for panics
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunError::Usage(_) | RunError::Args(_) => 2,
+            RunError::Generator(CrackenError::Wordlist { .. })
+            | RunError::Generator(CrackenError::Io(_)) => 3,
+            RunError::Generator(_) => 2,
+            RunError::Input(_) => 3,
+            RunError::Output(_) => 4,
+            RunError::Guard(_) => 5,
+            RunError::Verify(_) => 6,
+            RunError::PipeTo(_, code) => *code,
+            RunError::Interrupted(_) => 130,
+        }
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Usage(msg)
+            | RunError::Input(msg)
+            | RunError::Output(msg)
+            | RunError::Guard(msg)
+            | RunError::Verify(msg)
+            | RunError::PipeTo(msg, _)
+            | RunError::Interrupted(msg) => write!(f, "{}", msg),
+            RunError::Args(e) => write!(f, "{}", e.message),
+            RunError::Generator(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for RunError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RunError::Args(e) => Some(e),
+            RunError::Generator(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Box<dyn Error>> for RunError {
+    /// errors not classified where they occur are kept when they are generator errors, other
+    /// io errors are errors of reading the inputs and anything else is an invalid argument
+    fn from(e: Box<dyn Error>) -> RunError {
+        let e = match e.downcast::<RunError>() {
+            Ok(e) => return *e,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<CrackenError>() {
+            Ok(e) => return RunError::Generator(*e),
+            Err(e) => e,
+        };
+        match e.downcast::<io::Error>() {
+            Ok(e) => RunError::Input(e.to_string()),
+            Err(e) => RunError::Usage(e.to_string()),
+        }
+    }
+}
+
+/// marks an error of writing the results as an output error
+fn output_error<E: fmt::Display>(e: E) -> RunError {
+    RunError::Output(format!("error occurred writing to out: {}", e))
+}
+
+/// same as `run` with the error formatted as its message, for callers which only report it
+pub fn run_cli(args: Option<Vec<&str>>) -> Result<(), String> {
+    run(args).map_err(|e| e.to_string())
+}
+
+pub fn run(args: Option<Vec<&str>>) -> Result<(), RunError> {
+    let argv = command_line(&args);
+    // parse args
+    let ParsedArgs { matches, generate } = parse_args(args)?;
+    if let Some(options) = generate {
+        return generate_wordlist(&options, None, &argv).map_err(RunError::from);
+    }
+
+    let res = match matches.subcommand() {
+        ("create", Some(matches)) => run_create_smartlist(matches),
+        ("entropy", Some(matches)) => run_entropy_estimator(matches),
+        ("rank", Some(matches)) => run_rank(matches),
+        ("analyze", Some(matches)) => run_analyze(matches),
+        ("wordlist", Some(matches)) => run_wordlist(matches),
+        ("maskgen", Some(matches)) => run_maskgen(matches),
+        ("train", Some(matches)) => run_train(matches),
+        ("validate", Some(matches)) => run_validate(matches),
+        ("crunch", Some(matches)) => run_crunch(matches),
+        ("anagram", Some(matches)) => run_anagram(matches),
+        ("from-regex", Some(matches)) => run_from_regex(matches),
+        ("policygen", Some(matches)) => run_policygen(matches),
+        ("version", Some(matches)) => run_version(matches),
+        (_, None) => return Err(RunError::Usage("invalid command".to_string())),
+        _ => unreachable!("oopsie, subcommand is required"),
+    };
+    res.map_err(RunError::from)
+}
+
+/// same as `run` with the generated words written to `out` instead of the output args,
+/// e.g. `io::sink()` to measure the generation alone. only the generate command is supported
+pub fn run_to(args: Option<Vec<&str>>, out: Box<dyn Write>) -> Result<(), RunError> {
+    let argv = command_line(&args);
+    match parse_args(args)?.generate {
+        Some(options) => generate_wordlist(&options, Some(out), &argv).map_err(RunError::from),
+        None => Err(RunError::Usage(
+            "only the generate command can write to a given output".to_string(),
+        )),
+    }
+}
+
+pub fn run_wordlist_generator(args: &ArgMatches) -> BoxResult<()> {
+    let options = GenerateOptions::from_args(args)?;
+    generate_wordlist(&options, None, &command_line(&None))
+}
+
+/// the given args or the process args, as typed - --distribute repeats them in its commands
+fn command_line(args: &Option<Vec<&str>>) -> Vec<String> {
+    match args {
+        Some(args) => args.iter().map(|arg| arg.to_string()).collect(),
+        None => env::args().collect(),
+    }
+}
+
+fn generate_wordlist(
+    options: &GenerateOptions,
+    given_out: Option<Box<dyn Write>>,
+    argv: &[String],
+) -> BoxResult<()> {
+    let started = Instant::now();
+    let specs = mask_generators(options)?;
+    if let Some(shards) = options.distribute {
+        let total = total_combinations(&specs)?;
+        // the commands can't read the mask from stdin again, they are given the mask read
+        let mut argv = argv.to_vec();
+        if options.stdin_mask {
+            let mask = &options.masks[0].mask;
+            let mask = match mask.starts_with('@') {
+                true => format!("\\{}", mask),
+                false => mask.clone(),
+            };
+            for arg in argv.iter_mut().filter(|arg| arg.as_str() == "@-") {
+                *arg = mask.clone();
+            }
+        }
+        write_shard_commands(&mut stdout(), &argv, shards, &total, options.json)
+            .map_err(output_error)?;
+        return Ok(());
+    }
+    let bounds = match options.slice {
+        Some(slice) => Some(slice_bounds(slice, &specs)?),
+        None if options.seek_skip => Some(skip_bounds(options.skip, &specs)?),
+        None => find_bounds(
+            options.start_from.as_deref(),
+            options.end_at.as_deref(),
+            &specs,
+        )?,
+    };
+    if let Some(rates) = &options.stats {
+        return write_generate_stats(options, &specs, bounds.as_ref(), rates);
+    }
+    if let Some(max_words) = options.verify {
+        let total = total_combinations(&specs)?;
+        if total > max_words.to_biguint().unwrap() {
+            let msg = format!(
+                "--verify: the masks generate {} words, more than --verify-max {}",
+                total, max_words
+            );
+            return Err(RunError::Guard(msg).into());
+        }
+        return verify_masks(&mut stdout(), &options.masks, &specs);
+    }
+
+    // the guards run before any output is created or command spawned, a refused run leaves
+    // the existing files as they are
+    let out_given = given_out.is_some();
+    let files = match out_given {
+        true => vec![],
+        false => options.outputs.disk_files(),
+    };
+    if !out_given {
+        options.outputs.check_files()?;
+    }
+    let discarded = options.discarded();
+    if !files.is_empty() && !options.yes_i_know && options.max_bytes.is_none() {
+        // the words discarded by --skip are not written
+        let words = range_combinations(&specs, bounds.as_ref())?;
+        let skipped = discarded.to_biguint().unwrap();
+        let words = match words > skipped {
+            true => words - skipped,
+            false => BigUint::default(),
+        };
+        if words > options.max_keyspace {
+            let msg = format!(
+                "refusing to write {} words to {}, more than --max-keyspace {}. pass --yes-i-know to write them anyway",
+                words,
+                files.join(", "),
+                options.max_keyspace
+            );
+            return Err(RunError::Guard(msg).into());
+        }
+    }
+    // the filtered words and the jsonl lines are not counted before generating them, the
+    // estimate is unavailable
+    let estimated = options.entropy.is_none()
+        && !options.repeat_limits.is_set()
+        && options.indexed_format != Some(IndexedFormat::Jsonl)
+        && !options.dedup_across_masks;
+    if !files.is_empty() && estimated && !options.outputs.force {
+        let mut bytes = options.encoded_size(range_output_bytes(
+            &specs,
+            bounds.as_ref(),
+            options.indexed_format == Some(IndexedFormat::Tab),
+        )?);
+        if discarded > 0 {
+            // the words discarded by --skip are about as long as the others
+            let words = range_combinations(&specs, bounds.as_ref())?;
+            let skipped = discarded.to_biguint().unwrap().min(words.clone());
+            if words > BigUint::default() {
+                bytes = bytes * (&words - skipped) / words;
+            }
+        }
+        if let Some(max_bytes) = options.max_bytes {
+            bytes = bytes.min(max_bytes.to_biguint().unwrap());
+        }
+        check_disk_space(&FsSpace, &files, &bytes, options.compression_ratio)?;
+    }
+    let excluded = match &options.exclude {
+        Some((fname, bloom)) => Some(load_exclude(fname, *bloom, options.quiet)?),
+        None => None,
+    };
+    let count_total: Box<dyn FnOnce() -> BigUint> = match options.status {
+        Some(_) => {
+            let total = total_combinations(&specs)?;
+            Box::new(move || total)
+        }
+        None => {
+            let specs = specs.clone();
+            Box::new(move || total_combinations(&specs).unwrap_or_default())
+        }
+    };
+
+    // dropped after the outputs, the partial files are closed before they are removed
+    let mut partials = PartialFiles::new(options.outputs.keep_partial);
+    let (out, mut child) = match given_out {
+        Some(out) => (out, None),
+        None => options.outputs.open(&mut partials)?,
+    };
+    let mut chain = WriterChain::new(out, options, excluded, count_total);
+    if let Some(bounds) = &bounds {
+        chain.generated.lines.set(bounds.resume_offset);
+    }
+    let mut interleaved = vec![];
+    let mut reported_skipped = 0;
+    for (mask_idx, (mask, mut spec)) in options.masks.iter().zip(specs).enumerate() {
+        spec.window = match &bounds {
+            Some(bounds) if mask_idx < bounds.start.0 => continue,
+            Some(bounds) => bounds.window(mask_idx),
+            None => (0, None),
+        };
+        let word_generator = spec.build()?;
+        if !options.quiet {
+            warn_skipped_lines(&options.wordlists_options, &mut reported_skipped);
+        }
+        chain.word_index.set(spec.window.0);
+        let last_mask =
+            matches!(&bounds, Some(bounds) if bounds.end.map(|(idx, _)| idx) == Some(mask_idx));
+        if options.verbose {
+            let outputs = &options.outputs;
+            let mut destinations: Vec<_> = outputs
+                .files
+                .iter()
+                .map(|fname| match outputs.append {
+                    true => format!("{} (append)", fname),
+                    false => fname.to_string(),
+                })
+                .collect();
+            destinations.extend(
+                outputs
+                    .pipe_to
+                    .as_ref()
+                    .map(|command| format!("pipe to '{}'", command)),
+            );
+            if out_given {
+                destinations = vec!["given output".to_string()];
+            } else if destinations.is_empty() {
+                destinations.push("stdout".to_string());
+            }
+            let mut output_bytes = word_generator.output_bytes();
+            if options.indexed_format == Some(IndexedFormat::Tab) {
+                output_bytes += index_bytes(spec.window.0, &word_generator.combinations());
+            }
+            write_banner(
+                &mut stderr(),
+                &mask.mask,
+                word_generator.as_ref(),
+                &options.encoded_size(output_bytes),
+                &destinations,
+            )
+            .map_err(output_error)?;
+        }
+        if options.interleave_chunk.is_some() {
+            // the generators can't move between threads, every one is built again on its own
+            drop(word_generator);
+            interleaved.push(Words::from_generator(move || spec.build())?);
+            continue;
+        }
+
+        if let Err(e) = word_generator.gen(&mut chain.out) {
+            match e {
+                // stop on broken pipe, (e.g. happens when using head)
+                CrackenError::Io(e) if e.kind() == ErrorKind::BrokenPipe => break,
+                _ if chain.stopped() => break,
+                _ => return Err(output_error(e).into()),
+            }
+        }
+        if last_mask {
+            break;
+        }
+    }
+    if let Some(chunk) = options.interleave_chunk {
+        let io_buffer = options.outputs.io_buffer;
+        if let Err(e) = write_interleaved(&mut chain.out, interleaved, chunk, io_buffer) {
+            if e.kind() != ErrorKind::BrokenPipe && !chain.stopped() {
+                return Err(output_error(e).into());
+            }
+        }
+    }
+
+    if let Err(e) = chain.out.flush() {
+        if e.kind() != ErrorKind::BrokenPipe {
+            return Err(output_error(e).into());
+        }
+    }
+    // closing the --pipe-to command stdin lets it finish
+    drop(chain.out);
+    let child_status = match child.as_mut() {
+        Some(child) => Some(child.wait().map_err(output_error)?),
+        None => None,
+    };
+    if options.verbose {
+        write_summary(
+            &mut stderr(),
+            &chain.write_counts,
+            started.elapsed(),
+            options.max_rate,
+        )
+        .map_err(output_error)?;
+    }
+    if let Some(algorithm) = &options.checksum {
+        let digest = chain.checksum.hasher.borrow().clone().finalize();
+        let bytes = chain.checksum.bytes.get();
+        writeln!(stderr(), "{}: {:x} ({} bytes)", algorithm, digest, bytes)
+            .map_err(output_error)?;
+    }
+    if let Some((fname, bloom)) = &options.exclude {
+        if !options.quiet {
+            let approximate = match bloom {
+                true => " (or a bloom filter false positive)",
+                false => "",
+            };
+            writeln!(
+                stderr(),
+                "excluded {} words found in {}{}",
+                chain.excluded_words.get(),
+                fname,
+                approximate
+            )
+            .map_err(output_error)?;
+        }
+    }
+    if chain.budget_exhausted.get() {
+        let words = chain.generated.lines.get();
+        writeln!(
+            stderr(),
+            "reached --max-bytes after {} words, resume with --skip {}",
+            words,
+            words
+        )
+        .map_err(output_error)?;
+    }
+    if chain.timed_out.get() {
+        // the timeout may expire before all the skipped words are generated
+        let words = chain.generated.lines.get().max(options.skip);
+        writeln!(
+            stderr(),
+            "reached --timeout after {} words, resume with --skip {}",
+            words,
+            words
+        )
+        .map_err(output_error)?;
+    }
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        let words = chain.generated.lines.get();
+        let msg = format!(
+            "interrupted after {} words, resume with --skip {}",
+            words, words
+        );
+        return Err(RunError::Interrupted(msg).into());
+    }
+    match child_status {
+        Some(status) if !status.success() => {
+            let command = options.outputs.pipe_to.as_deref().unwrap();
+            Err(match status.code() {
+                Some(code) => {
+                    let msg = format!("--pipe-to command '{}' exited with {}", command, code);
+                    RunError::PipeTo(msg, code)
+                }
+                None => RunError::Output(format!(
+                    "--pipe-to command '{}' was terminated by a signal",
+                    command
+                )),
+            }
+            .into())
+        }
+        _ => partials.finish(),
+    }
+}
+
+/// the generators of the masks, with the custom charsets of their hcmask lines and the john
+/// ranges, --join and --palindrome applied to the masks
+fn mask_generators(options: &GenerateOptions) -> BoxResult<Vec<MaskGenerator>> {
+    let mut specs = vec![];
+    for mask in options.masks.iter() {
+        // john ranges become the custom charsets following the given ones
+        let (gen_mask, ranges) = match options.syntax {
+            MaskSyntax::Hashcat => (mask.mask.clone(), vec![]),
+            MaskSyntax::John => translate_john_mask(&mask.mask, options.custom_charsets.len())?,
+        };
+        let gen_mask = match &options.join {
+            Some((separator, mode)) => join_mask(&gen_mask, separator, *mode)?,
+            None => gen_mask,
+        };
+        let mut charsets = options.custom_charsets.clone();
+        // the charsets of a hcmask line replace the given ones, like hashcat's -1 to -4
+        for (idx, charset) in mask.charsets.iter().enumerate() {
+            match charsets.get_mut(idx) {
+                Some(given) => *given = (charset.clone(), false),
+                None => charsets.push((charset.clone(), false)),
+            }
+        }
+        charsets.extend(ranges.into_iter().map(|range| (range, false)));
+        let mut spec = MaskGenerator {
+            mask: gen_mask,
+            lengths: options.lengths,
+            charsets,
+            wordlists: options.wordlists.clone(),
+            wordlists_options: options.wordlists_options.clone(),
+            output_hex: options.output_hex,
+            repeat_limits: options.repeat_limits,
+            length_order: options.length_order.clone(),
+            order: options.order.clone(),
+            shuffle: options.shuffle,
+            window: (0, None),
+            affix: (options.prefix.clone(), options.suffix.clone()),
+            io_buffer: options.outputs.io_buffer,
+            dedup: None,
+        };
+        // the second half of the mask repeats the first half backwards
+        if options.palindrome {
+            spec.mask = palindrome_mask(&spec.mask, &spec.custom_charsets())?;
+        }
+        specs.push(spec);
+    }
+    if options.dedup_across_masks {
+        let all = Arc::new(specs.clone());
+        for (idx, spec) in specs.iter_mut().enumerate() {
+            spec.dedup = Some((Arc::clone(&all), idx));
+        }
+    }
+    Ok(specs)
+}
+
+/// writes the --stats of generate: the keyspace of every mask of a masks file, of the words
+/// of a --start-from, --end-at or --slice range or of the mask
+fn write_generate_stats(
+    options: &GenerateOptions,
+    specs: &[MaskGenerator],
+    bounds: Option<&Bounds>,
+    rates: &[(String, u64)],
+) -> BoxResult<()> {
+    let mut reported_skipped = 0;
+    if let Some(bounds) = bounds {
+        // the range may span masks, its words are counted mask by mask
+        let range_words = range_combinations(specs, Some(bounds))?;
+        if !options.quiet {
+            warn_skipped_lines(&options.wordlists_options, &mut reported_skipped);
+        }
+        write_range_stats(&mut stdout(), &range_words, rates, options.json)
+            .map_err(output_error)?;
+        return Ok(());
+    }
+    if options.masks_file {
+        // the generators are built one at a time, only to count their words
+        let rows =
+            options
+                .masks
+                .iter()
+                .zip(specs.iter())
+                .map(|(mask, spec)| -> Result<_, CrackenError> {
+                    let word_generator = spec.build()?;
+                    Ok(MaskFileStats {
+                        line: mask.line,
+                        mask: mask.mask.clone(),
+                        charsets: spec
+                            .charsets
+                            .iter()
+                            .map(|(chars, _)| chars.clone())
+                            .collect(),
+                        combinations: word_generator.combinations(),
+                        bytes: options.encoded_size(word_generator.output_bytes()),
+                    })
+                });
+        write_masks_stats(
+            &mut stdout(),
+            rows,
+            options.sort_keyspace,
+            rates,
+            options.json,
+        )?;
+        return Ok(());
+    }
+
+    let spec = &specs[0];
+    let word_generator = spec.build()?;
+    if !options.quiet {
+        warn_skipped_lines(&options.wordlists_options, &mut reported_skipped);
+    }
+    let mut filters = vec![];
+    if options.entropy.is_some() {
+        filters.push("--min-entropy/--max-entropy");
+    }
+    if options.repeat_limits.seq_max.is_some() {
+        filters.push("--seq-max");
+    }
+    if options.repeat_limits.occurrence_max.is_some() {
+        filters.push("--occurrence-max");
+    }
+    let hashcat_keyspace = match options.keyspace_hashcat {
+        true => Some(hashcat_keyspace(&spec.mask, &spec.custom_charsets())?),
+        false => None,
+    };
+    let mut out = stdout();
+    write_stats(
+        &mut out,
+        word_generator.as_ref(),
+        &filters,
+        rates,
+        options.json,
+    )
+    .map_err(output_error)?;
+    if let Some(keyspace) = hashcat_keyspace {
+        writeln!(out, "hashcat keyspace: {}", keyspace).map_err(output_error)?;
+    }
+    if !options.json {
+        write_wordlists_memory(&mut out, spec).map_err(output_error)?;
+    }
+    Ok(())
+}
+
+/// writes the mask, keyspace, estimated size (`output_bytes`) of every output and the output
+/// destinations before generating
+fn write_banner<W: Write>(
+    out: &mut W,
+    mask: &str,
+    word_generator: &dyn WordGenerator,
+    output_bytes: &BigUint,
+    destinations: &[String],
+) -> BoxResult<()> {
+    writeln!(out, "mask: {}", mask)?;
+    writeln!(out, "keyspace: {}", word_generator.combinations())?;
+    match destinations.len() {
+        1 => writeln!(out, "estimated size: {} bytes", output_bytes)?,
+        outputs => writeln!(
+            out,
+            "estimated size: {} bytes per output, {} bytes in total",
+            output_bytes,
+            output_bytes * outputs
+        )?,
+    }
+    writeln!(out, "output: {}", destinations.join(", "))?;
+    Ok(())
+}
+
+/// the owned arguments of the generator of a mask, so --interleave can build it again on the
+/// thread of its `Words`
+#[derive(Clone)]
+struct MaskGenerator {
+    mask: String,
+    lengths: (Option<usize>, Option<usize>),
+    /// the custom charsets, true for --tokens
+    charsets: Vec<(String, bool)>,
+    wordlists: Vec<String>,
+    wordlists_options: Vec<WordlistOptions>,
+    output_hex: bool,
+    repeat_limits: RepeatLimits,
+    length_order: LengthOrder,
+    /// the --order model and beam
+    order: Option<(Arc<Model>, usize)>,
+    /// the --shuffle seed
+    shuffle: Option<u64>,
+    /// skip and limit
+    window: (u64, Option<u64>),
+    /// prefix and suffix
+    affix: (Vec<u8>, Vec<u8>),
+    io_buffer: usize,
+    /// --dedup-across-masks, all the masks and the index of this one, the words of the masks
+    /// before it are skipped
+    dedup: Option<(Arc<Vec<MaskGenerator>>, usize)>,
+}
+
+impl MaskGenerator {
+    fn custom_charsets(&self) -> Vec<CustomCharset<'_>> {
+        self.charsets
+            .iter()
+            .map(|(charset, tokens)| {
+                if *tokens {
+                    CustomCharset::Tokens(charset)
+                } else {
+                    CustomCharset::Chars(charset)
+                }
+            })
+            .collect()
+    }
+
+    /// the generator of the words of the mask in any order, only to look them up with
+    /// `index_of`
+    fn build_matcher(&self) -> Result<Box<dyn WordGenerator>, CrackenError> {
+        let wordlists: Vec<_> = self.wordlists.iter().map(|w| w.as_str()).collect();
+        get_word_generator_with_options(
+            &self.mask,
+            self.lengths.0,
+            self.lengths.1,
+            &self.custom_charsets(),
+            &wordlists,
+            &self.wordlists_options,
+        )
+    }
+
+    fn build(&self) -> Result<Box<dyn WordGenerator>, CrackenError> {
+        let charsets = self.custom_charsets();
+        let wordlists: Vec<_> = self.wordlists.iter().map(|w| w.as_str()).collect();
+        let mut word_generator = match &self.order {
+            Some((model, beam)) => get_model_generator(
+                &self.mask,
+                self.lengths.0,
+                self.lengths.1,
+                &charsets,
+                model,
+                *beam,
+            )?,
+            None => get_word_generator_with_options(
+                &self.mask,
+                self.lengths.0,
+                self.lengths.1,
+                &charsets,
+                &wordlists,
+                &self.wordlists_options,
+            )?,
+        };
+        if let Some(seed) = self.shuffle {
+            word_generator = shuffle(word_generator, seed)?;
+        }
+        if let Some((specs, idx)) = &self.dedup {
+            let earlier = specs[..*idx]
+                .iter()
+                .map(|spec| spec.build_matcher())
+                .collect::<Result<_, _>>()?;
+            word_generator = dedup(word_generator, earlier)?;
+        }
+        word_generator.set_hex_output(self.output_hex);
+        word_generator.set_repeat_limits(self.repeat_limits);
+        word_generator.set_length_order(self.length_order.clone());
+        let (skip, limit) = self.window;
+        if skip > 0 || limit.is_some() {
+            word_generator = window(word_generator, skip, limit);
+        }
+        let (prefix, suffix) = &self.affix;
+        if !prefix.is_empty() || !suffix.is_empty() {
+            word_generator = affix(word_generator, prefix, suffix)?;
+        }
+        word_generator.set_buffer_size(self.io_buffer);
+        Ok(word_generator)
+    }
+}
+
+/// writes `chunk` words of every generator in turn, the exhausted generators are left out of
+/// the next turns. the words are written about `io_buffer` bytes at once
+fn write_interleaved<W: Write + ?Sized>(
+    out: &mut W,
+    mut generators: Vec<Words>,
+    chunk: u64,
+    io_buffer: usize,
+) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(io_buffer);
+    let mut idx = 0;
+    while !generators.is_empty() {
+        let mut written = 0;
+        while written < chunk {
+            let word = match generators[idx].next_word() {
+                Some(word) => word,
+                None => break,
+            };
+            buf.extend_from_slice(word);
+            buf.push(b'\n');
+            written += 1;
+            if buf.len() >= io_buffer {
+                out.write_all(&buf)?;
+                buf.clear();
+            }
+        }
+        if written < chunk {
+            generators.remove(idx);
+        } else {
+            idx += 1;
+        }
+        if idx >= generators.len() {
+            idx = 0;
+        }
+    }
+    out.write_all(&buf)
+}
+
+/// writes the number of words and bytes written, the elapsed time and the average rate, with
+/// the --max-rate it was limited to
+fn write_summary<W: Write>(
+    out: &mut W,
+    counts: &WriteCounts,
+    elapsed: Duration,
+    max_rate: Option<u64>,
+) -> BoxResult<()> {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 {
+        counts.lines.get() as f64 / secs
+    } else {
+        0.0
+    };
+    writeln!(out, "words written: {}", counts.lines.get())?;
+    writeln!(out, "bytes written: {}", counts.bytes.get())?;
+    writeln!(out, "elapsed: {:.2}s", secs)?;
+    match max_rate {
+        Some(max_rate) => writeln!(out, "rate: {:.0} words/s (max {})", rate, max_rate)?,
+        None => writeln!(out, "rate: {:.0} words/s", rate)?,
+    }
+    Ok(())
+}
+
+/// writes the estimated memory of the wordlists of the mask, the most the words of their files
+/// need (see --max-wordlist-memory)
+fn write_wordlists_memory<W: Write>(out: &mut W, spec: &MaskGenerator) -> BoxResult<()> {
+    let mut total = 0;
+    let mut used = false;
+    for (idx, arg) in spec.wordlists.iter().enumerate() {
+        if !spec.mask.contains(&format!("?w{}", idx + 1)) {
+            continue;
+        }
+        let (fname, _) = parse_wordlist_arg(arg, &WordlistOptions::default());
+        total += estimate_memory(&fname).map_err(|e| format!("wordlist {}: {}", fname, e))?;
+        used = true;
+    }
+    if used {
+        writeln!(out, "wordlists memory: ~{}", format_bytes(total))?;
+    }
+    Ok(())
+}
+
+/// warns about the wordlist lines skipped by --skip-invalid-lines, after the `reported` ones
+fn warn_skipped_lines(wordlists_options: &[WordlistOptions], reported: &mut usize) {
+    let log = match wordlists_options
+        .first()
+        .and_then(|o| o.skipped_lines.as_ref())
+    {
+        Some(log) => log,
+        None => return,
+    };
+    let entries = log.entries();
+    for skipped in entries.iter().skip(*reported) {
+        let lines: Vec<_> = skipped.lines.iter().map(|line| line.to_string()).collect();
+        let more = if skipped.count > lines.len() {
+            ", ..."
+        } else {
+            ""
+        };
+        eprintln!(
+            "warning: skipped {} invalid lines of wordlist {} (lines {}{})",
+            skipped.count,
+            skipped.fname.display(),
+            lines.join(", "),
+            more
+        );
+    }
+    *reported = entries.len();
+}
+
+/// writes the number of words the generator generates and its log2 (bits), per length too
+/// if the generator generates multiple lengths with a table of the words up to every length,
+/// and the time to try them all at every rate. `json` writes them as a json object
+fn write_stats<W: Write>(
+    out: &mut W,
+    word_generator: &dyn WordGenerator,
+    filters: &[&str],
+    rates: &[(String, u64)],
+    json: bool,
+) -> BoxResult<()> {
+    let combs = word_generator.combinations();
+    let length_combs = word_generator.length_combinations();
+    // the lengths in generation order with the words up to them
+    let mut cumulative = BigUint::default();
+    let lengths: Vec<_> = length_combs
+        .iter()
+        .map(|(len, len_combs)| {
+            cumulative += len_combs;
+            let percent = percent_of(&cumulative, &combs);
+            (*len, len_combs, cumulative.clone(), percent)
+        })
+        .collect();
+    if json {
+        let json = StatsJson {
+            combinations: &combs,
+            bits: round2(log2_biguint(&combs)),
+            not_counting: filters,
+            lengths: lengths
+                .iter()
+                .map(
+                    |(length, combinations, cumulative, percent)| -> BoxResult<_> {
+                        Ok(LengthStatsJson {
+                            length: *length,
+                            combinations,
+                            cumulative,
+                            percent: percent.parse()?,
+                        })
+                    },
+                )
+                .collect::<BoxResult<_>>()?,
+            times: times_json(&combs, rates),
+        };
+        serde_json::to_writer(&mut *out, &json)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+
+    if !filters.is_empty() {
+        // words are filtered while generated so only the unfiltered count is known
+        writeln!(
+            out,
+            "{} (upper bound, not counting {})",
+            combs,
+            filters.join(", ")
+        )?;
+    } else {
+        writeln!(out, "{}", combs)?;
+    }
+    writeln!(out, "bits: {:.2}", log2_biguint(&combs))?;
+
+    if lengths.len() > 1 {
+        for (len, combs, _, _) in lengths.iter() {
+            writeln!(out, "bits (length {}): {:.2}", len, log2_biguint(combs))?;
+        }
+        writeln!(out, "length\tcombinations\tcumulative\tpercent")?;
+        for (len, combs, cumulative, percent) in lengths.iter() {
+            writeln!(out, "{}\t{}\t{}\t{}%", len, combs, cumulative, percent)?;
+        }
+    }
+    for (label, rate) in rates {
+        let secs = &combs / *rate;
+        writeln!(out, "time at {}: {}", label, format_duration(&secs))?;
+    }
+    Ok(())
+}
+
+/// `part` in percent of `total` truncated to 2 decimals, e.g. 23.81
+fn percent_of(part: &BigUint, total: &BigUint) -> String {
+    if total.bits() == 0 {
+        return "0.00".to_string();
+    }
+    let basis_points = part * 10000u32 / total;
+    format!("{}.{:02}", &basis_points / 100u32, basis_points % 100u32)
+}
+
+/// the json object of `write_stats`, the numbers of words are strings as they may exceed 2^53
+#[derive(Serialize)]
+struct StatsJson<'a> {
+    #[serde(serialize_with = "serialize_str")]
+    combinations: &'a BigUint,
+    bits: f64,
+    not_counting: &'a [&'a str],
+    lengths: Vec<LengthStatsJson<'a>>,
+    times: Vec<TimeJson<'a>>,
+}
+
+#[derive(Serialize)]
+struct LengthStatsJson<'a> {
+    length: usize,
+    #[serde(serialize_with = "serialize_str")]
+    combinations: &'a BigUint,
+    #[serde(serialize_with = "serialize_str")]
+    cumulative: &'a BigUint,
+    percent: f64,
+}
+
+/// the time to try the words at a rate of --rates
+#[derive(Serialize)]
+struct TimeJson<'a> {
+    rate: &'a str,
+    #[serde(serialize_with = "serialize_str")]
+    seconds: BigUint,
+}
+
+/// the `"times"` of the rates to try `words` at, for the json reports
+fn times_json<'a>(words: &BigUint, rates: &'a [(String, u64)]) -> Vec<TimeJson<'a>> {
+    rates
+        .iter()
+        .map(|(label, rate)| TimeJson {
+            rate: label,
+            seconds: words / *rate,
+        })
+        .collect()
+}
+
+/// the json object of `write_range_stats`, `bits` is null for an empty range
+#[derive(Serialize)]
+struct RangeStatsJson<'a> {
+    #[serde(serialize_with = "serialize_str")]
+    range: &'a BigUint,
+    bits: Option<f64>,
+    times: Vec<TimeJson<'a>>,
+}
+
+/// writes the number of words between --start-from and --end-at and the time to try them
+fn write_range_stats<W: Write>(
+    out: &mut W,
+    words: &BigUint,
+    rates: &[(String, u64)],
+    json: bool,
+) -> BoxResult<()> {
+    if json {
+        let json = RangeStatsJson {
+            range: words,
+            bits: (words.bits() > 0).then(|| round2(log2_biguint(words))),
+            times: times_json(words, rates),
+        };
+        serde_json::to_writer(&mut *out, &json)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+    writeln!(out, "{} (range)", words)?;
+    if words.bits() > 0 {
+        writeln!(out, "bits: {:.2}", log2_biguint(words))?;
+    }
+    for (label, rate) in rates {
+        let secs = words / *rate;
+        writeln!(out, "time at {}: {}", label, format_duration(&secs))?;
+    }
+    Ok(())
+}
+
+/// the keyspace of a mask of a masks file (--stats with -i)
+#[derive(Serialize)]
+struct MaskFileStats {
+    /// line of the mask in the masks file, from 1
+    line: usize,
+    mask: String,
+    /// the custom charsets of the mask, the given ones replaced by the charsets of its line
+    charsets: Vec<String>,
+    #[serde(serialize_with = "serialize_str")]
+    combinations: BigUint,
+    /// the estimated bytes of the words with their newlines, in the output encoding
+    #[serde(serialize_with = "serialize_str")]
+    bytes: BigUint,
+}
+
+impl MaskFileStats {
+    /// writes the tab separated row of the mask
+    fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let charsets: Vec<_> = self
+            .charsets
+            .iter()
+            .enumerate()
+            .map(|(idx, charset)| format!("?{}={}", idx + 1, charset))
+            .collect();
+        let charsets = match charsets.is_empty() {
+            true => "-".to_string(),
+            false => charsets.join(" "),
+        };
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}",
+            self.line, self.mask, charsets, self.combinations, self.bytes
+        )
+    }
+}
+
+/// the json object of `write_masks_stats`, `bits` is null for no words
+#[derive(Serialize)]
+struct MasksStatsJson<'a> {
+    masks: &'a [MaskFileStats],
+    #[serde(serialize_with = "serialize_str")]
+    combinations: &'a BigUint,
+    #[serde(serialize_with = "serialize_str")]
+    bytes: &'a BigUint,
+    bits: Option<f64>,
+    times: Vec<TimeJson<'a>>,
+}
+
+/// writes the keyspace of every mask of a masks file as soon as it's counted, or the largest
+/// first if `sort`, then the total and the time to try it at every rate. `json` writes them
+/// all at the end as a json object
+fn write_masks_stats<W, I>(
+    out: &mut W,
+    rows: I,
+    sort: bool,
+    rates: &[(String, u64)],
+    json: bool,
+) -> BoxResult<()>
+where
+    W: Write,
+    I: Iterator<Item = Result<MaskFileStats, CrackenError>>,
+{
+    if !json {
+        writeln!(out, "line\tmask\tcharsets\tcombinations\tbytes").map_err(output_error)?;
+    }
+    let mut combs = BigUint::default();
+    let mut bytes = BigUint::default();
+    let mut kept = vec![];
+    for row in rows {
+        let row = row?;
+        combs += &row.combinations;
+        bytes += &row.bytes;
+        if sort || json {
+            kept.push(row);
+            continue;
+        }
+        row.write(out).map_err(output_error)?;
+    }
+    if sort {
+        // the same keyspaces stay in the file order
+        kept.sort_by(|row1, row2| row2.combinations.cmp(&row1.combinations));
+    }
+
+    let mut write_total = || -> BoxResult<()> {
+        if json {
+            let json = MasksStatsJson {
+                masks: &kept,
+                combinations: &combs,
+                bytes: &bytes,
+                bits: (combs.bits() > 0).then(|| round2(log2_biguint(&combs))),
+                times: times_json(&combs, rates),
+            };
+            serde_json::to_writer(&mut *out, &json)?;
+            writeln!(out)?;
+            return Ok(());
+        }
+        for row in kept.iter() {
+            row.write(out)?;
+        }
+        writeln!(out, "total\t\t\t{}\t{}", combs, bytes)?;
+        if combs.bits() > 0 {
+            writeln!(out, "bits: {:.2}", log2_biguint(&combs))?;
+        }
+        for (label, rate) in rates {
+            let secs = &combs / *rate;
+            writeln!(out, "time at {}: {}", label, format_duration(&secs))?;
+        }
+        Ok(())
+    };
+    write_total().map_err(output_error)?;
+    Ok(())
+}
+
+/// the --start-from and --end-at positions, as the index of their mask and of the word in it
+struct Bounds {
+    /// the first mask generated and the number of its words to skip
+    start: (usize, u64),
+    /// the last mask generated and the index of the last word in it
+    end: Option<(usize, u64)>,
+    /// number of words of the masks and words skipped before the start, the resume point of
+    /// --skip counts them
+    resume_offset: u64,
+}
+
+impl Bounds {
+    /// the skip and limit of the words of a mask at or after the start mask
+    fn window(&self, mask_idx: usize) -> (u64, Option<u64>) {
+        let skip = if mask_idx == self.start.0 {
+            self.start.1
+        } else {
+            0
+        };
+        match self.end {
+            Some((end_idx, end)) if end_idx == mask_idx => (skip, Some(end + 1 - skip)),
+            _ => (skip, None),
+        }
+    }
+}
+
+/// the number of words of all the masks, their generators are built again to generate them
+fn total_combinations(specs: &[MaskGenerator]) -> BoxResult<BigUint> {
+    let mut total = 0.to_biguint().unwrap();
+    for spec in specs.iter() {
+        total += spec.build()?.combinations();
+    }
+    Ok(total)
+}
+
+/// number of words of the masks, of the range between the start and end of `bounds` if given
+fn range_combinations(specs: &[MaskGenerator], bounds: Option<&Bounds>) -> BoxResult<BigUint> {
+    let bounds = match bounds {
+        Some(bounds) => bounds,
+        None => return total_combinations(specs),
+    };
+    let mut total = 0.to_biguint().unwrap();
+    for (mask_idx, spec) in specs.iter().enumerate().skip(bounds.start.0) {
+        let mut spec = spec.clone();
+        spec.window = bounds.window(mask_idx);
+        total += spec.build()?.combinations();
+        if bounds.end.map(|(idx, _)| idx) == Some(mask_idx) {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+/// the bytes the masks write in the range of `bounds`, with the index of --with-index
+fn range_output_bytes(
+    specs: &[MaskGenerator],
+    bounds: Option<&Bounds>,
+    with_index: bool,
+) -> BoxResult<BigUint> {
+    let mut total = 0.to_biguint().unwrap();
+    for (mask_idx, spec) in specs.iter().enumerate() {
+        let mut spec = spec.clone();
+        if let Some(bounds) = bounds {
+            if mask_idx < bounds.start.0 {
+                continue;
+            }
+            spec.window = bounds.window(mask_idx);
+        }
+        let word_generator = spec.build()?;
+        total += word_generator.output_bytes();
+        if with_index {
+            total += index_bytes(spec.window.0, &word_generator.combinations());
+        }
+        if bounds.and_then(|bounds| bounds.end).map(|(idx, _)| idx) == Some(mask_idx) {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+/// fails when the output files of a filesystem take more than its free space, every file takes
+/// about `bytes` divided by `compression_ratio`. the files whose filesystem the os can't tell
+/// are checked on their own and the ones whose free space it can't tell are written anyway
+fn check_disk_space(
+    space: &dyn SpaceCheck,
+    files: &[&str],
+    bytes: &BigUint,
+    compression_ratio: f64,
+) -> BoxResult<()> {
+    let readable = |bytes: &BigUint| match u64::try_from(bytes) {
+        Ok(bytes) if bytes >= 1024 => format!("{} bytes ({})", bytes, format_bytes(bytes)),
+        _ => format!("{} bytes", bytes),
+    };
+    // the ratio in thousandths, a ratio of 1 leaves the estimate as is
+    let ratio = ((compression_ratio * 1000.0).round() as u64).max(1);
+    let file_bytes = bytes * 1000u32 / ratio;
+    let mut filesystems: Vec<(Option<u64>, Vec<&str>)> = vec![];
+    for fname in files {
+        let filesystem = space.filesystem(Path::new(fname));
+        let shared = filesystems
+            .iter_mut()
+            .find(|(id, _)| filesystem.is_some() && *id == filesystem);
+        match shared {
+            Some((_, fnames)) => fnames.push(fname),
+            None => filesystems.push((filesystem, vec![fname])),
+        }
+    }
+    for (_, fnames) in filesystems {
+        let available = match space.available_space(Path::new(fnames[0])) {
+            Ok(Some(available)) => available.to_biguint().unwrap(),
+            Ok(None) | Err(_) => continue,
+        };
+        let required = &file_bytes * fnames.len();
+        if required > available {
+            let compressed = match ratio {
+                1000 => String::new(),
+                _ => format!(" with --compression-ratio {}", compression_ratio),
+            };
+            let msg = format!(
+                "not enough space for {}: the words take about {}{}, {} are available. pass --force to write them anyway",
+                fnames.join(", "),
+                readable(&required),
+                compressed,
+                readable(&available)
+            );
+            return Err(RunError::Guard(msg).into());
+        }
+    }
+    Ok(())
+}
+
+/// generates the words of every mask without writing them, writes mask<TAB>PASS or FAIL with
+/// the numbers and fails if any mask failed
+fn verify_masks<W: Write>(
+    out: &mut W,
+    masks: &[MaskLine],
+    specs: &[MaskGenerator],
+) -> BoxResult<()> {
+    let mut failed = 0;
+    for (mask, spec) in masks.iter().zip(specs.iter()) {
+        let verification = verify(spec.build()?.as_ref())?;
+        if !verification.passed() {
+            failed += 1;
+        }
+        writeln!(out, "{}\t{}", mask.mask, verification).map_err(output_error)?;
+    }
+    if failed > 0 {
+        let msg = format!("--verify: {} of {} masks failed", failed, specs.len());
+        return Err(RunError::Verify(msg).into());
+    }
+    Ok(())
+}
+
+/// parses --slice i/n, the shares are numbered from 1
+pub(crate) fn parse_slice(slice: &str) -> BoxResult<(u64, u64)> {
+    let parsed = slice
+        .split_once('/')
+        .and_then(|(i, n)| Some((i.parse::<u64>().ok()?, n.parse::<u64>().ok()?)));
+    match parsed {
+        Some((i, n)) if i >= 1 && i <= n => Ok((i, n)),
+        _ => {
+            let msg = format!(
+                "invalid --slice {}: expected i/n with 1 <= i <= n (e.g. 3/8)",
+                slice
+            );
+            Err(RunError::Usage(msg).into())
+        }
+    }
+}
+
+/// the rank of the first word of share `i` of `n` of `total` words and of the first word
+/// after it. the shares differ by at most a word and together cover every word once
+fn slice_range(total: &BigUint, (i, n): (u64, u64)) -> (BigUint, BigUint) {
+    (total.clone() * (i - 1) / n, total.clone() * i / n)
+}
+
+/// the bounds of --slice, the masks and the indexes in them of the first and last word of
+/// the share. an empty share (fewer words than shares) starts after the last mask
+fn slice_bounds(slice: (u64, u64), specs: &[MaskGenerator]) -> BoxResult<Bounds> {
+    let mut combinations = vec![];
+    for spec in specs.iter() {
+        combinations.push(spec.build()?.combinations());
+    }
+    let total = combinations
+        .iter()
+        .fold(0.to_biguint().unwrap(), |total, words| {
+            total + words.clone()
+        });
+    let (start, end) = slice_range(&total, slice);
+    let resume_offset = match start.to_u64_digits()[..] {
+        [] => 0,
+        [words] => words,
+        _ => u64::MAX,
+    };
+    if start == end {
+        return Ok(Bounds {
+            start: (specs.len(), 0),
+            end: None,
+            resume_offset,
+        });
+    }
+    // the mask generating the word of this rank among the words of all the masks
+    let locate = |rank: &BigUint| -> BoxResult<(usize, u64)> {
+        let mut first = 0.to_biguint().unwrap();
+        for (mask_idx, words) in combinations.iter().enumerate() {
+            let next = first.clone() + words.clone();
+            if *rank < next {
+                return match (rank.clone() - first).to_u64_digits()[..] {
+                    [] => Ok((mask_idx, 0)),
+                    [index] => Ok((mask_idx, index)),
+                    _ => bail!(
+                        "--slice {}/{} starts or ends too far in the keyspace of its mask",
+                        slice.0,
+                        slice.1
+                    ),
+                };
+            }
+            first = next;
+        }
+        unreachable!("the share ends within the words of the masks")
+    };
+    Ok(Bounds {
+        start: locate(&start)?,
+        end: Some(locate(&(end - 1u32))?),
+        resume_offset,
+    })
+}
+
+/// the bounds of --skip, the mask and the index in it of the first word after the skipped
+/// ones. skipping all the words starts after the last mask
+fn skip_bounds(skip: u64, specs: &[MaskGenerator]) -> BoxResult<Bounds> {
+    let mut left = skip;
+    for (mask_idx, spec) in specs.iter().enumerate() {
+        match u64::try_from(&spec.build()?.combinations()) {
+            Ok(words) if left >= words => left -= words,
+            _ => {
+                return Ok(Bounds {
+                    start: (mask_idx, left),
+                    end: None,
+                    resume_offset: skip,
+                })
+            }
+        }
+    }
+    Ok(Bounds {
+        start: (specs.len(), 0),
+        end: None,
+        resume_offset: skip,
+    })
+}
+
+/// quotes `arg` for posix shells unless it only has chars they don't interpret
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: u8| c.is_ascii_alphanumeric() || b"_-+=/.,:@%".contains(&c);
+    if !arg.is_empty() && arg.bytes().all(plain) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// writes the commands of --distribute generating the `total` words in `shards` shares - the
+/// command line `argv` as typed with --slice i/n instead of --distribute (and its --format
+/// json), one per line or as a json array of the shares
+fn write_shard_commands<W: Write>(
+    out: &mut W,
+    argv: &[String],
+    shards: u64,
+    total: &BigUint,
+    json: bool,
+) -> BoxResult<()> {
+    let mut base = vec![];
+    // --slice goes before `--`, which ends the flags
+    let mut flags_end = None;
+    let mut args = argv.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--" => {
+                flags_end = Some(base.len());
+                base.push(arg.clone());
+                base.extend(args.by_ref().cloned());
+            }
+            "--distribute" => {
+                args.next();
+            }
+            "--format" if json => {
+                args.next();
+            }
+            "--format=json" => {}
+            _ if arg.starts_with("--distribute=") => {}
+            _ => base.push(arg.clone()),
+        }
+    }
+    let flags_end = flags_end.unwrap_or(base.len());
+
+    let mut json_shards = vec![];
+    for i in 1..=shards {
+        let slice = format!("{}/{}", i, shards);
+        let mut command = base[..flags_end].to_vec();
+        command.extend(["--slice".to_string(), slice.clone()]);
+        command.extend_from_slice(&base[flags_end..]);
+        if !json {
+            let quoted: Vec<_> = command.iter().map(|arg| shell_quote(arg)).collect();
+            writeln!(out, "{}", quoted.join(" "))?;
+            continue;
+        }
+        let (start, end) = slice_range(total, (i, shards));
+        json_shards.push(ShardJson {
+            slice,
+            words: end - &start,
+            start,
+            command,
+        });
+    }
+    if json {
+        serde_json::to_writer_pretty(&mut *out, &json_shards)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// a share of --distribute --format json, the numbers are strings as they may exceed 2^53
+#[derive(Serialize)]
+struct ShardJson {
+    slice: String,
+    #[serde(serialize_with = "serialize_str")]
+    start: BigUint,
+    #[serde(serialize_with = "serialize_str")]
+    words: BigUint,
+    command: Vec<String>,
+}
+
+/// returns the --start-from or --end-at word, decoding $HEX[...] words
+fn get_bound_word(word: Option<&str>) -> BoxResult<Option<Vec<u8>>> {
+    Ok(match word {
+        Some(word) if word.as_bytes().starts_with(HEX_PREFIX) => {
+            Some(decode_hex_word(word.as_bytes().to_vec())?)
+        }
+        Some(word) => Some(word.as_bytes().to_vec()),
+        None => None,
+    })
+}
+
+/// locates --start-from and --end-at in the masks before generating anything, the first mask
+/// generating a word and the lowest index of the word in it are its position
+fn find_bounds(
+    start_word: Option<&str>,
+    end_word: Option<&str>,
+    specs: &[MaskGenerator],
+) -> BoxResult<Option<Bounds>> {
+    let start_from = get_bound_word(start_word)?;
+    let end_at = get_bound_word(end_word)?;
+    if start_from.is_none() && end_at.is_none() {
+        return Ok(None);
+    }
+    let to_u64 = |index: BigUint, name: &str| -> BoxResult<u64> {
+        match index.to_u64_digits()[..] {
+            [] => Ok(0),
+            [index] => Ok(index),
+            _ => bail!("--{} word is too far in the keyspace of its mask", name),
+        }
+    };
+
+    let mut start = if start_from.is_none() {
+        Some((0, 0))
+    } else {
+        None
+    };
+    let mut end = None;
+    let mut skipped_words = 0.to_biguint().unwrap();
+    for (mask_idx, spec) in specs.iter().enumerate() {
+        let word_generator = spec.build()?;
+        if let (None, Some(word)) = (start, &start_from) {
+            match word_generator.index_of(word) {
+                // generation resumes from the next word
+                Some(rank) => {
+                    let skip = to_u64(rank.index + 1u32, "start-from")?;
+                    start = Some((mask_idx, skip));
+                    skipped_words += skip;
+                }
+                None => {
+                    skipped_words += word_generator.combinations();
+                    continue;
+                }
+            }
+        }
+        if let Some(word) = &end_at {
+            if let Some(rank) = word_generator.index_of(word) {
+                end = Some((mask_idx, to_u64(rank.index, "end-at")?));
+                break;
+            }
+        }
+    }
+
+    let start = match start {
+        Some(start) => start,
+        None => {
+            let word = start_word.unwrap();
+            let msg = format!("--start-from word {} is not generated by the masks", word);
+            return Err(RunError::Usage(msg).into());
+        }
+    };
+    match end {
+        None if end_at.is_some() => {
+            let word = end_word.unwrap();
+            let msg = match start_word {
+                Some(start_word) => format!(
+                    "--end-at word {} is not generated by the masks after --start-from {}",
+                    word, start_word
+                ),
+                None => format!("--end-at word {} is not generated by the masks", word),
+            };
+            return Err(RunError::Usage(msg).into());
+        }
+        // the range is empty when ending at the start word
+        Some((end_idx, end_index)) if end_idx == start.0 && end_index + 1 < start.1 => {
+            let msg = format!(
+                "--end-at word {} comes before --start-from word {}",
+                end_word.unwrap(),
+                start_word.unwrap()
+            );
+            return Err(RunError::Usage(msg).into());
+        }
+        _ => {}
+    }
+    let resume_offset = match skipped_words.to_u64_digits()[..] {
+        [] => 0,
+        [words] => words,
+        _ => u64::MAX,
+    };
+    Ok(Some(Bounds {
+        start,
+        end,
+        resume_offset,
+    }))
+}
+
+/// returns the --rate and --rate-file rates labeled by how they were given (e.g. "50GH (NTLM)")
+pub(crate) fn get_rates(args: &ArgMatches) -> BoxResult<Vec<(String, u64)>> {
+    let mut rates = vec![];
+    if let Some(rate) = args.value_of("rate") {
+        rates.push((rate.to_string(), parse_rate(rate)?));
+    }
+    if let Some(fname) = args.value_of("rate-file") {
+        let file = match File::open(fname) {
+            Ok(file) => BufReader::new(file),
+            Err(e) => {
+                let msg = format!("cannot open rate file {}: {}", fname, e);
+                return Err(RunError::Input(msg).into());
+            }
+        };
+        for (line_idx, line) in file.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, rate) = match line.rsplit_once(char::is_whitespace) {
+                Some((name, rate)) => (name.trim(), rate),
+                None => bail!(
+                    "invalid line {} of rate file {}: expected '<hash mode> <rate>'",
+                    line_idx + 1,
+                    fname
+                ),
+            };
+            match parse_rate(rate) {
+                Ok(parsed) => rates.push((format!("{} ({})", rate, name), parsed)),
+                Err(e) => bail!(
+                    "invalid line {} of rate file {}: {}",
+                    line_idx + 1,
+                    fname,
+                    e
+                ),
+            }
+        }
+    }
+    Ok(rates)
+}
+
+/// returns the --io-buffer size, `env` ($CRACKEN_BUFFER_SIZE) is used when the flag isn't given
+pub(crate) fn get_io_buffer(arg: Option<&str>, env: Option<String>) -> BoxResult<usize> {
+    let (size, name) = match (arg, env.as_deref()) {
+        (Some(size), _) => (size, "--io-buffer".to_string()),
+        (None, Some(size)) if !size.is_empty() => (size, format!("${}", ENV_BUFFER_SIZE)),
+        _ => return Ok(BUFFER_SIZE),
+    };
+    let size = match parse_size(size) {
+        Ok(size) => size,
+        Err(e) => return Err(RunError::Usage(format!("{}: {}", name, e)).into()),
+    };
+    if size > MAX_IO_BUFFER {
+        let msg = format!("{} must be at most 1G, got {} bytes", name, size);
+        return Err(RunError::Usage(msg).into());
+    }
+    Ok(size)
+}
+
+/// returns the --max-keyspace, `env` ($CRACKEN_MAX_KEYSPACE) is used when the flag isn't given
+/// and the config profile when neither is
+pub(crate) fn get_max_keyspace(
+    arg: Option<&str>,
+    env: Option<String>,
+    profile: &Profile,
+) -> BoxResult<BigUint> {
+    let (keyspace, name) = match (arg, env.as_deref(), &profile.max_keyspace) {
+        (Some(keyspace), _, _) => (keyspace, "--max-keyspace".to_string()),
+        (None, Some(keyspace), _) if !keyspace.is_empty() => {
+            (keyspace, format!("${}", ENV_MAX_KEYSPACE))
+        }
+        (None, _, Some(keyspace)) => (keyspace.as_str(), "max-keyspace".to_string()),
+        _ => return Ok(DEFAULT_MAX_KEYSPACE.to_biguint().unwrap()),
+    };
+    match parse_keyspace(keyspace) {
+        Ok(keyspace) => Ok(keyspace),
+        Err(e) => Err(RunError::Usage(format!("{}: {}", name, e)).into()),
+    }
+}
+
+/// prefixes the relative wordlists paths with `dir` ($CRACKEN_WORDLIST_DIR) if given
+pub(crate) fn with_wordlist_dir(wordlists: Vec<&str>, dir: Option<OsString>) -> Vec<String> {
+    let dir = match dir {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => return wordlists.into_iter().map(|w| w.to_string()).collect(),
+    };
+    wordlists
+        .into_iter()
+        .map(|wordlist| {
+            if Path::new(wordlist).is_absolute() {
+                wordlist.to_string()
+            } else {
+                dir.join(wordlist).to_string_lossy().into_owned()
+            }
+        })
+        .collect()
+}
+
+/// returns the config profile of the run: [default] merged with the -P profile. a missing
+/// config file is only an error when given explicitly with --config
+pub(crate) fn get_config_profile(args: &ArgMatches) -> BoxResult<Profile> {
+    if args.is_present("no-config") {
+        return Ok(Profile::default());
+    }
+    let config = match args.value_of("config") {
+        Some(fname) => Config::load(fname)?,
+        None => match Config::default_path() {
+            Some(path) if path.exists() => Config::load(path)?,
+            Some(path) => Config::empty(path),
+            None => Config::empty("~/.config/cracken/config.toml"),
+        },
+    };
+    config.profile(args.value_of("profile"))
+}
+
+/// parses a -m/-x length, returning an error instead of exiting so the run can report it
+pub(crate) fn get_length_arg(
+    args: &ArgMatches,
+    name: &str,
+    flag: &str,
+) -> BoxResult<Option<usize>> {
+    match args.value_of(name) {
+        None => Ok(None),
+        Some(value) => match value.parse::<usize>() {
+            Ok(0) => bail!("invalid value '0' for {}: must be greater than zero", flag),
+            Ok(len) => Ok(Some(len)),
+            Err(_) => bail!(
+                "invalid value '{}' for {}: expected a positive integer",
+                value,
+                flag
+            ),
+        },
+    }
+}
+
+/// returns the custom charsets (-c) and tokens (--tokens) ordered by their position on the args
+pub(crate) fn get_custom_charsets<'a>(args: &'a ArgMatches) -> Vec<CustomCharset<'a>> {
+    let mut custom_charsets = vec![];
+    if let (Some(indices), Some(values)) = (
+        args.indices_of("custom-charset"),
+        args.values_of("custom-charset"),
+    ) {
+        custom_charsets.extend(indices.zip(values.map(CustomCharset::Chars)));
+    }
+    if let (Some(indices), Some(values)) = (args.indices_of("tokens"), args.values_of("tokens")) {
+        custom_charsets.extend(indices.zip(values.map(CustomCharset::Tokens)));
+    }
+    custom_charsets.sort_by_key(|(idx, _)| *idx);
+    custom_charsets
+        .into_iter()
+        .map(|(_, charset)| charset)
+        .collect()
+}
+
+/// returns the loading options of each wordlist. --wordlist-filter applies to the
+/// wordlist preceding it on the command line, the other options apply to all wordlists
+/// (including the config profile wordlists when no -w is given)
+pub(crate) fn get_wordlists_options(
+    args: &ArgMatches,
+    profile: &Profile,
+) -> BoxResult<Vec<WordlistOptions>> {
+    let sample_fraction = optional_value_t!(args, "wordlist-sample", f64);
+    let sample_count = optional_value_t!(args, "wordlist-sample-n", usize);
+    let sample = match (sample_fraction, sample_count) {
+        (Some(frac), _) if !(frac > 0.0 && frac <= 1.0) => {
+            bail!("--wordlist-sample must be in range (0, 1] but got {}", frac)
+        }
+        (Some(frac), _) => Some(WordlistSample::Fraction(frac)),
+        (None, Some(0)) => bail!("--wordlist-sample-n must be positive"),
+        (None, Some(count)) => Some(WordlistSample::Count(count)),
+        (None, None) => None,
+    };
+
+    let wordlist_encoding = match profile.wordlist_encoding.as_deref() {
+        Some(encoding) if args.occurrences_of("wordlist-encoding") == 0 => encoding,
+        _ => args.value_of("wordlist-encoding").unwrap(),
+    };
+    let max_memory = match args.value_of("max-wordlist-memory") {
+        Some(size) => Some(size),
+        None => profile.max_wordlist_memory.as_deref(),
+    };
+    let max_memory = match max_memory.map(parse_size).transpose() {
+        Ok(max_memory) => max_memory.map(|size| size as u64),
+        Err(e) => bail!("--max-wordlist-memory: {}", e),
+    };
+    let wordlist_options = WordlistOptions {
+        trim: args.is_present("trim-wordlist"),
+        skip_blank: !args.is_present("keep-blank-lines"),
+        comment_prefix: args.value_of("wordlist-comments").map(|p| p.to_owned()),
+        skip_invalid: args.is_present("skip-invalid-lines"),
+        encoding: WordlistEncoding::from_name(wordlist_encoding).unwrap(),
+        decode_hex: !args.is_present("no-wordlist-hex"),
+        sample,
+        seed: optional_value_t!(args, "seed", u64),
+        skipped_lines: Some(SkippedLinesLog::default()),
+        max_memory,
+        ..Default::default()
+    };
+
+    let wordlists_indices: Vec<_> = args
+        .indices_of("wordlist")
+        .map(|x| x.collect())
+        .unwrap_or_default();
+    let wordlists_count = match wordlists_indices.len() {
+        0 => profile.wordlists.len(),
+        count => count,
+    };
+    let mut wordlists_options = vec![wordlist_options; wordlists_count];
+
+    if let (Some(indices), Some(values)) = (
+        args.indices_of("wordlist-filter"),
+        args.values_of("wordlist-filter"),
+    ) {
+        for (filter_idx, filter) in indices.zip(values) {
+            let wordlist_pos = match wordlists_indices.iter().rposition(|&idx| idx < filter_idx) {
+                Some(pos) => pos,
+                None => bail!(
+                    "--wordlist-filter {} must follow the -w it applies to",
+                    filter
+                ),
+            };
+            let options = &mut wordlists_options[wordlist_pos];
+            if options.filter.is_some() {
+                bail!(
+                    "multiple --wordlist-filter given for wordlist ?w{}",
+                    wordlist_pos + 1
+                );
+            }
+            options.filter = match Regex::new(filter) {
+                Ok(regex) => Some(regex),
+                Err(e) => bail!("invalid --wordlist-filter {}: {}", filter, e),
+            };
+        }
+    }
+    Ok(wordlists_options)
+}
+
+pub fn run_entropy_estimator(args: &ArgMatches) -> BoxResult<()> {
+    let smartlist_files: Vec<&str> = args.values_of("smartlist").map(|x| x.collect()).unwrap();
+    let est = EntropyEstimator::from_files(smartlist_files.as_ref())
+        .map_err(|e| RunError::Input(format!("cannot load smartlists: {}", e)))?;
+    let is_summary_only = args.is_present("summary");
+    let mask_type = args.value_of("mask_type").unwrap_or("hybrid");
+    let mut stdout = stdout();
+
+    if let Some(pwd) = args.value_of("password") {
+        let entropy_result = est.estimate_password_entropy(pwd.as_bytes())?;
+        let text = format!(
+            "hybrid-min-split: {:?}
+hybrid-mask: {}
+hybrid-min-entropy: {:.2}
+--
+charset-mask: {}
+charset-mask-entropy: {:.2}
+            ",
+            entropy_result.subword_entropy_min_split,
+            entropy_result.min_subword_mask,
+            entropy_result.subword_entropy,
+            entropy_result.charset_mask,
+            entropy_result.mask_entropy,
+        );
+        if let Err(e) = write!(&mut stdout, "{}", text) {
+            match e.kind() {
+                // ignore broken pipe, (e.g. happens when using head)
+                ErrorKind::BrokenPipe => return Ok(()),
+                _ => return Err(output_error(e).into()),
+            }
+        }
+    } else if let Some(pwd_file) = args.value_of("passwords-file") {
+        let bucket_size = optional_value_t!(args, "bucket-size", u32).unwrap_or(10);
+        let weakest_size = optional_value_t!(args, "weakest", usize).unwrap_or(10);
+        let mut summary = EntropySummary::new(bucket_size, weakest_size);
+        let mut outfile = match args.value_of("output-file") {
+            Some(fname) => match File::create(fname) {
+                Ok(fp) => Some(BufWriter::new(fp)),
+                Err(e) => {
+                    let msg = format!("cannot open file {}: {}", fname, e);
+                    return Err(RunError::Output(msg).into());
+                }
+            },
+            None => None,
+        };
+
+        let jsonl = args.value_of("format") == Some("jsonl");
+        let no_echo = args.is_present("no-echo");
+
+        let file = File::open(pwd_file)?;
+        let reader = RawFileReader::new(file);
+        for (line_idx, pwd) in reader.into_iter().enumerate() {
+            let mut pwd = pwd?;
+            if pwd.last() == Some(&b'\r') {
+                pwd.pop();
+            }
+            if pwd.is_empty() {
+                continue;
+            }
+
+            let entropy_result = est.estimate_password_entropy(&pwd)?;
+            let (pwd_entropy, pwd_mask) = match mask_type {
+                "hybrid" => (
+                    entropy_result.subword_entropy,
+                    &entropy_result.min_subword_mask,
+                ),
+                "charset" => (entropy_result.mask_entropy, &entropy_result.charset_mask),
+                _ => unreachable!("invalid entropy type"),
+            };
+            let index = no_echo.then_some(line_idx);
+
+            if let Some(outfile) = outfile.as_mut() {
+                let res = match (jsonl, index) {
+                    (true, _) => entropy_result.write_json(outfile, &pwd, index, mask_type),
+                    (false, Some(index)) => writeln!(outfile, "{}\t{:.2}", index, pwd_entropy),
+                    (false, None) => outfile
+                        .write_all(&pwd)
+                        .and_then(|_| writeln!(outfile, "\t{:.2}", pwd_entropy)),
+                };
+                res.map_err(output_error)?;
+            }
+            if is_summary_only {
+                summary.add(&pwd, pwd_entropy);
+            } else if outfile.is_none() {
+                let res = match (jsonl, index) {
+                    (true, _) => entropy_result.write_json(&mut stdout, &pwd, index, mask_type),
+                    (false, Some(index)) => {
+                        writeln!(&mut stdout, "{:.2},{},{}", pwd_entropy, pwd_mask, index)
+                    }
+                    (false, None) => writeln!(
+                        &mut stdout,
+                        "{:.2},{},{}",
+                        pwd_entropy,
+                        pwd_mask,
+                        String::from_utf8_lossy(&pwd)
+                    ),
+                };
+                if let Err(e) = res {
+                    match e.kind() {
+                        // ignore broken pipe, (e.g. happens when using head)
+                        ErrorKind::BrokenPipe => return Ok(()),
+                        _ => return Err(output_error(e).into()),
+                    }
+                }
+            }
+        }
+
+        if let Some(outfile) = outfile.as_mut() {
+            outfile.flush().map_err(output_error)?;
+        }
+        if is_summary_only {
+            summary.write(&mut stdout).map_err(output_error)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run_rank(args: &ArgMatches) -> BoxResult<()> {
+    let mask = args.value_of("mask").unwrap();
+    let pwd = args.value_of("password").unwrap();
+    let minlen = get_length_arg(args, "min-length", "--minlen")?;
+    let maxlen = get_length_arg(args, "max-length", "--maxlen")?;
+    let custom_charsets = get_custom_charsets(args);
+    let wordlists: Vec<&str> = args
+        .values_of("wordlist")
+        .map(|x| x.collect())
+        .unwrap_or_default();
+
+    let word_generator = get_word_generator(mask, minlen, maxlen, &custom_charsets, &wordlists)?;
+    write_rank(&mut stdout(), word_generator.as_ref(), mask, pwd.as_bytes())
+        .map_err(output_error)?;
+    Ok(())
+}
+
+/// writes whether `pwd` is generated by the mask and its index and percent of the keyspace
+fn write_rank<W: Write>(
+    out: &mut W,
+    word_generator: &dyn WordGenerator,
+    mask: &str,
+    pwd: &[u8],
+) -> BoxResult<()> {
+    let combs = word_generator.combinations();
+    writeln!(out, "mask: {}", mask)?;
+    writeln!(out, "keyspace: {}", combs)?;
+
+    let rank = match word_generator.index_of(pwd) {
+        Some(rank) => rank,
+        None => {
+            writeln!(out, "generatable: no")?;
+            return Ok(());
+        }
+    };
+    // index is always lower than the keyspace so this fits in u64
+    let basis_points: u64 = (&rank.index * 10000u32 / &combs).to_string().parse()?;
+    writeln!(out, "generatable: yes")?;
+    writeln!(out, "index: {}", rank.index)?;
+    writeln!(
+        out,
+        "percent: {}.{:02}%",
+        basis_points / 100,
+        basis_points % 100
+    )?;
+    if !rank.wordlist_words.is_empty() {
+        let words: Vec<_> = rank
+            .wordlist_words
+            .iter()
+            .map(|w| String::from_utf8_lossy(w))
+            .collect();
+        writeln!(out, "wordlist words: {}", words.join(", "))?;
+    }
+    Ok(())
+}
+
+pub fn run_analyze(args: &ArgMatches) -> BoxResult<()> {
+    match args.subcommand() {
+        ("masks", Some(matches)) => {
+            let top = optional_value_t!(matches, "top", usize).unwrap_or(25);
+            let stats = read_mask_stats(matches.value_of("passwords-file").unwrap())?;
+            stats.write(&mut stdout(), top).map_err(output_error)?;
+            Ok(())
+        }
+        ("charsets", Some(matches)) => {
+            let mut stats = CharsetStats::new();
+            read_passwords(matches.value_of("wordlist").unwrap(), |entry| {
+                stats.add(entry)
+            })?;
+            let mut out = stdout();
+            match matches.value_of("format") {
+                Some("json") => stats.write_json(&mut out),
+                _ => stats.write(&mut out),
+            }
+            .map_err(output_error)?;
+            Ok(())
+        }
+        ("freq", Some(matches)) => {
+            let top = optional_value_t!(matches, "top", usize).unwrap_or(10);
+            let affix_len = optional_value_t!(matches, "affix-len", usize).unwrap_or(3);
+            let capacity = optional_value_t!(matches, "max-distinct", usize).unwrap_or(1_000_000);
+            if affix_len == 0 {
+                bail!("--affix-len must be positive");
+            }
+            let mut stats = FreqStats::new(affix_len, capacity);
+            read_passwords(matches.value_of("passwords-file").unwrap(), |pwd| {
+                stats.add(pwd)
+            })?;
+            let mut out = stdout();
+            match matches.value_of("format") {
+                Some("json") => stats.write_json(&mut out, top),
+                _ => stats.write(&mut out, top),
+            }
+            .map_err(output_error)?;
+            Ok(())
+        }
+        _ => bail!("invalid analyze command"),
+    }
+}
+
+/// the -o file of a wordlist subcommand or stdout, which must not be one of the `wordlists`
+/// it reads
+fn wordlist_output(args: &ArgMatches, wordlists: &[&str]) -> BoxResult<Box<dyn Write>> {
+    let fname = match args.value_of("output-file") {
+        Some(fname) => fname,
+        None => return Ok(Box::new(BufWriter::new(stdout().lock()))),
+    };
+    // the output replaces the wordlist before it is read
+    let same_file = |wordlist: &&&str| {
+        matches!(
+            (std::fs::canonicalize(wordlist), std::fs::canonicalize(fname)),
+            (Ok(wordlist), Ok(out)) if wordlist == out
+        )
+    };
+    if let Some(wordlist) = wordlists.iter().find(same_file) {
+        bail!(
+            "the output file {} is the input wordlist {}",
+            fname,
+            wordlist
+        );
+    }
+    match File::create(fname) {
+        Ok(fp) => Ok(Box::new(BufWriter::new(fp))),
+        Err(e) => {
+            let msg = format!("cannot create file {}: {}", fname, e);
+            Err(RunError::Output(msg).into())
+        }
+    }
+}
+
+/// the wordlists errors of the wordlist subcommands are input errors
+fn wordlist_tool_error(e: CrackenError) -> RunError {
+    match e {
+        CrackenError::Io(e) => output_error(e),
+        e => RunError::Input(e.to_string()),
+    }
+}
+
+pub fn run_wordlist(args: &ArgMatches) -> BoxResult<()> {
+    match args.subcommand() {
+        ("merge", Some(matches)) => {
+            let wordlists: Vec<&str> = matches.values_of("wordlists").unwrap().collect();
+            let mut out = wordlist_output(matches, &wordlists)?;
+            let counts = merge_wordlists(&wordlists, &mut out).map_err(wordlist_tool_error)?;
+            out.flush().map_err(output_error)?;
+            writeln!(
+                stderr(),
+                "input lines: {}\nunique lines: {}\nduplicates removed: {}",
+                counts.input_lines,
+                counts.unique_lines,
+                counts.duplicates
+            )
+            .map_err(output_error)?;
+            Ok(())
+        }
+        ("subtract", Some(matches)) => {
+            let base = matches.value_of("base").unwrap();
+            let remove = matches.value_of("remove").unwrap();
+            let bloom = matches.is_present("bloom");
+            let load_error = |e| RunError::Input(format!("wordlist {}: {}", remove, e));
+            let mut out = wordlist_output(matches, &[base, remove])?;
+            let counts = if matches.is_present("ignore-case") {
+                let removed = CaseInsensitive::from_file(remove, bloom).map_err(load_error)?;
+                subtract_wordlist(base, |line| removed.contains(line), &mut out)
+            } else {
+                let removed = ExcludeSet::from_file(remove, bloom).map_err(load_error)?;
+                subtract_wordlist(base, |line| removed.contains(line), &mut out)
+            }
+            .map_err(wordlist_tool_error)?;
+            out.flush().map_err(output_error)?;
+            writeln!(
+                stderr(),
+                "input lines: {}\nremoved lines: {}\nremaining lines: {}",
+                counts.input_lines,
+                counts.removed,
+                counts.remaining
+            )
+            .map_err(output_error)?;
+            Ok(())
+        }
+        ("sort", Some(matches)) => {
+            let wordlist = matches.value_of("wordlist").unwrap();
+            let memory = match parse_size(matches.value_of("memory").unwrap()) {
+                Ok(memory) => memory,
+                Err(e) => bail!("--memory: {}", e),
+            };
+            let options = SortOptions {
+                memory,
+                tmp_dir: match matches.value_of("tmp-dir") {
+                    Some(dir) => PathBuf::from(dir),
+                    None => std::env::temp_dir(),
+                },
+                unique: matches.is_present("unique"),
+            };
+            let mut out = wordlist_output(matches, &[wordlist])?;
+            let mut progress: Box<dyn Write> = match matches.is_present("quiet") {
+                true => Box::new(io::sink()),
+                false => Box::new(stderr()),
+            };
+            let counts = sort_wordlist(wordlist, &mut out, &options, &mut progress)
+                .map_err(wordlist_tool_error)?;
+            out.flush().map_err(output_error)?;
+            writeln!(
+                progress,
+                "input lines: {}\noutput lines: {}",
+                counts.input_lines, counts.output_lines
+            )
+            .map_err(output_error)?;
+            Ok(())
+        }
+        _ => bail!("invalid wordlist command"),
+    }
+}
+
+/// a mask of a masks file with the custom charsets of its hcmask line
+pub(crate) struct MaskLine {
+    /// line of the mask in the file, from 1
+    pub line: usize,
+    pub mask: String,
+    pub charsets: Vec<String>,
+}
+
+/// reads the masks of `-i`, a mask per line. the empty and `#` comment lines of a `.hcmask`
+/// file are skipped and its lines may start with custom charsets (see `parse_hcmask_line`)
+pub(crate) fn read_masks_file(fname: &str) -> BoxResult<Vec<MaskLine>> {
+    let file = match File::open(fname) {
+        Ok(file) => BufReader::new(file),
+        Err(e) => {
+            let msg = format!("cannot open masks file {}: {}", fname, e);
+            return Err(RunError::Input(msg).into());
+        }
+    };
+    let hcmask = Path::new(fname).extension() == Some(OsStr::new("hcmask"));
+    let mut masks = vec![];
+    for (idx, line) in file.lines().enumerate() {
+        let line = line?;
+        if !hcmask {
+            masks.push(MaskLine {
+                line: idx + 1,
+                mask: line,
+                charsets: vec![],
+            });
+            continue;
+        }
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (charsets, mask) = match parse_hcmask_line(line) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let msg = format!("masks file {} line {}: {}", fname, idx + 1, e);
+                return Err(RunError::Input(msg).into());
+            }
+        };
+        masks.push(MaskLine {
+            line: idx + 1,
+            mask,
+            charsets,
+        });
+    }
+    Ok(masks)
+}
+
+/// the mask of the mask argument, `@file` reads it from a file and `@-` from stdin
+pub(crate) fn read_mask_arg(mask: &str) -> BoxResult<String> {
+    let fname = match mask.strip_prefix('@') {
+        Some(fname) => fname,
+        None => return Ok(mask.to_string()),
+    };
+    let content = match fname {
+        "-" => io::read_to_string(io::stdin()),
+        _ => std::fs::read_to_string(fname),
+    };
+    let name = if fname == "-" { "stdin" } else { fname };
+    let content = match content {
+        Ok(content) => content,
+        Err(e) => {
+            let msg = format!("cannot read mask file {}: {}", name, e);
+            return Err(RunError::Input(msg).into());
+        }
+    };
+    match parse_mask_file(&content) {
+        Ok(mask) => Ok(mask),
+        Err(e) => Err(RunError::Usage(format!("mask file {}: {}", name, e)).into()),
+    }
+}
+
+/// the mask of a mask file, its single line which isn't empty or a `#` comment. the newline
+/// is removed but not the other trailing whitespace, which is part of the mask
+fn parse_mask_file(content: &str) -> Result<String, String> {
+    let mut masks = content
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    match (masks.next(), masks.next()) {
+        (Some(mask), None) => Ok(mask.to_string()),
+        (None, _) => Err("no mask, only empty and comment lines".to_string()),
+        (Some(_), Some(_)) => {
+            Err("more than one mask, use -i for a file of several masks".to_string())
+        }
+    }
+}
+
+/// reads the lengths of a `length count` lines histogram, from the most common
+pub(crate) fn read_length_histogram(fname: &str) -> BoxResult<Vec<usize>> {
+    let file = File::open(fname)
+        .map_err(|e| format!("failed to open length histogram {}: {}", fname, e))?;
+    let mut counts = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let parsed = match fields[..] {
+            [len, count] => len.parse::<usize>().ok().zip(count.parse::<u64>().ok()),
+            _ => None,
+        };
+        match parsed {
+            Some(count) => counts.push(count),
+            None => bail!(
+                "invalid length histogram line '{}' in {}: expected a length and a count",
+                line,
+                fname
+            ),
+        }
+    }
+    // the most common first, ties by length
+    counts.sort_by(|(len1, count1), (len2, count2)| count2.cmp(count1).then(len1.cmp(len2)));
+    Ok(counts.into_iter().map(|(len, _)| len).collect())
+}
+
+/// calls `add` with every password of a newline separated file, without empty lines
+fn read_passwords<F: FnMut(&[u8])>(fname: &str, mut add: F) -> BoxResult<()> {
+    let file = File::open(fname)?;
+    for pwd in RawFileReader::new(file) {
+        let mut pwd = pwd?;
+        if pwd.last() == Some(&b'\r') {
+            pwd.pop();
+        }
+        if !pwd.is_empty() {
+            add(&pwd);
+        }
+    }
+    Ok(())
+}
+
+/// counts the masks of the non empty lines of a passwords file
+fn read_mask_stats(fname: &str) -> BoxResult<MaskStats> {
+    let mut stats = MaskStats::new();
+    read_passwords(fname, |pwd| stats.add(pwd))?;
+    Ok(stats)
+}
+
+pub fn run_train(args: &ArgMatches) -> BoxResult<()> {
+    let mut model = Model::new();
+    read_passwords(args.value_of("passwords-file").unwrap(), |pwd| {
+        model.add(pwd)
+    })?;
+    let fname = args.value_of("output-file").unwrap();
+    let mut out = match File::create(fname) {
+        Ok(fp) => BufWriter::new(fp),
+        Err(e) => {
+            let msg = format!("cannot create file {}: {}", fname, e);
+            return Err(RunError::Output(msg).into());
+        }
+    };
+    model
+        .write(&mut out)
+        .and_then(|_| out.flush())
+        .map_err(output_error)?;
+    writeln!(stderr(), "model trained on {} passwords", model.passwords()).map_err(output_error)?;
+    Ok(())
+}
+
+pub(crate) fn read_model(fname: &str) -> BoxResult<Model> {
+    let res = File::open(fname).and_then(|fp| Model::read(&mut BufReader::new(fp)));
+    match res {
+        Ok(model) => Ok(model),
+        Err(e) => bail!("failed to read model {}: {}", fname, e),
+    }
+}
+
+pub fn run_maskgen(args: &ArgMatches) -> BoxResult<()> {
+    let target_coverage = optional_value_t!(args, "target-coverage", f64).unwrap_or(1.0);
+    if !(0.0..=1.0).contains(&target_coverage) {
+        bail!(
+            "invalid --target-coverage {}: must be between 0 and 1",
+            target_coverage
+        );
+    }
+    let max_keyspace = match args.value_of("max-keyspace") {
+        Some(keyspace) => Some(parse_keyspace(keyspace)?),
+        None => None,
+    };
+    let stats = read_mask_stats(args.value_of("passwords-file").unwrap())?;
+    let selection = stats.select(target_coverage, max_keyspace.as_ref());
+
+    let mut out: Box<dyn Write> = match args.value_of("output-file") {
+        Some(fname) => match File::create(fname) {
+            Ok(fp) => Box::new(BufWriter::new(fp)),
+            Err(e) => {
+                let msg = format!("cannot create file {}: {}", fname, e);
+                return Err(RunError::Output(msg).into());
+            }
+        },
+        None => Box::new(BufWriter::new(stdout().lock())),
+    };
+    selection
+        .write_masks(&mut out)
+        .and_then(|_| out.flush())
+        .map_err(output_error)?;
+    selection
+        .write_report(&mut stderr())
+        .map_err(output_error)?;
+    Ok(())
+}
+
+pub fn run_validate(args: &ArgMatches) -> BoxResult<()> {
+    let mask = args.value_of("mask").unwrap();
+    let custom_charsets = get_custom_charsets(args);
+    let wordlists: Vec<&str> = args
+        .values_of("wordlist")
+        .map(|x| x.collect())
+        .unwrap_or_default();
+
+    // building the generator parses the mask and loads its charsets and wordlists
+    let word_generator = get_word_generator(mask, None, None, &custom_charsets, &wordlists)?;
+    println!("{}: valid, {} words", mask, word_generator.combinations());
+    Ok(())
+}
+
+pub fn run_crunch(args: &ArgMatches) -> BoxResult<()> {
+    if let Some((name, _)) = CRUNCH_UNSUPPORTED
+        .iter()
+        .find(|(name, _)| args.is_present(name))
+    {
+        let msg = format!("crunch option -{} is not supported", name);
+        return Err(RunError::Usage(msg).into());
+    }
+    let mut lengths = vec![];
+    for name in ["min", "max"] {
+        let value = args.value_of(name).unwrap();
+        match value.parse::<usize>() {
+            Ok(len) => lengths.push(len),
+            Err(_) => {
+                let msg = format!(
+                    "invalid crunch {} '{}': expected a positive integer",
+                    name, value
+                );
+                return Err(RunError::Usage(msg).into());
+            }
+        }
+    }
+    let charsets: Vec<&str> = args
+        .values_of("charset")
+        .map(|x| x.collect())
+        .unwrap_or_default();
+    let crunch = CrunchMask::new(lengths[0], lengths[1], &charsets, args.value_of("pattern"))?;
+
+    let mut builder = GeneratorBuilder::new().mask(crunch.mask.as_str());
+    for charset in crunch.custom_charsets.iter() {
+        builder = builder.custom_charset(charset.as_str());
+    }
+    if args.value_of("pattern").is_none() {
+        builder = builder.minlen(crunch.minlen).maxlen(crunch.maxlen);
+    }
+
+    // -s and -e are words in crunch order, the window is their indices in the generation order
+    let word_generator = builder.clone().build()?;
+    let index_of = |name: &str| -> BoxResult<Option<u64>> {
+        let word = match args.value_of(name) {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+        match word_generator.index_of(&crunch.to_generated(word.as_bytes())) {
+            Some(rank) => Ok(Some(
+                rank.index.to_u64_digits().first().copied().unwrap_or(0),
+            )),
+            None => bail!(
+                "crunch -{} {} is not generated by the given lengths, charsets and pattern",
+                &name[..1],
+                word
+            ),
+        }
+    };
+    let start = index_of("start")?;
+    let end = index_of("end")?;
+    let skip = start.unwrap_or(0);
+    if let Some(end) = end {
+        if end < skip {
+            bail!(
+                "crunch -e {} comes before -s {}",
+                args.value_of("end").unwrap(),
+                args.value_of("start").unwrap()
+            );
+        }
+        builder = builder.limit(end - skip + 1);
+    }
+    let word_generator = builder.skip(skip).build()?;
+
+    let out: Box<dyn Write> = match args.value_of("output-file") {
+        Some(fname) => match File::create(fname) {
+            Ok(fp) => Box::new(BufWriter::new(fp)),
+            Err(e) => {
+                let msg = format!("cannot create file {}: {}", fname, e);
+                return Err(RunError::Output(msg).into());
+            }
+        },
+        None => Box::new(BufWriter::new(stdout().lock())),
+    };
+    let mut out: Box<dyn Write> = if crunch.is_sorted() {
+        out
+    } else {
+        Box::new(crunch.writer(out))
+    };
+    if let Err(e) = word_generator.gen(&mut out) {
+        match e {
+            CrackenError::Io(e) if e.kind() == ErrorKind::BrokenPipe => return Ok(()),
+            _ => return Err(output_error(e).into()),
+        }
+    }
+    if let Err(e) = out.flush() {
+        if e.kind() != ErrorKind::BrokenPipe {
+            return Err(output_error(e).into());
+        }
+    }
+    Ok(())
+}
+
+pub fn run_anagram(args: &ArgMatches) -> BoxResult<()> {
+    let word_generator = AnagramGenerator::new(args.value_of("word").unwrap().as_bytes())?;
+    if args.is_present("stats") {
+        println!("{}", word_generator.combinations());
+        return Ok(());
+    }
+    write_generators(args, &[Box::new(word_generator)])
+}
+
+pub fn run_from_regex(args: &ArgMatches) -> BoxResult<()> {
+    let regex_masks = compile_regex(args.value_of("regex").unwrap())?;
+    let word_generators = regex_masks.generators()?;
+    if args.is_present("stats") {
+        let keyspace: BigUint = word_generators
+            .iter()
+            .map(|word_gen| word_gen.combinations())
+            .sum();
+        println!("{}", keyspace);
+        return Ok(());
+    }
+    write_generators(args, &word_generators)
+}
+
+/// writes the words of `word_generators` one after the other to the output file of `args`
+fn write_generators(
+    args: &ArgMatches,
+    word_generators: &[Box<dyn WordGenerator>],
+) -> BoxResult<()> {
+    let mut out: Box<dyn Write> = match args.value_of("output-file") {
+        Some(fname) => match File::create(fname) {
+            Ok(fp) => Box::new(BufWriter::new(fp)),
+            Err(e) => {
+                let msg = format!("cannot create file {}: {}", fname, e);
+                return Err(RunError::Output(msg).into());
+            }
+        },
+        None => Box::new(BufWriter::new(stdout().lock())),
+    };
+    for word_generator in word_generators {
+        if let Err(e) = word_generator.gen(&mut out) {
+            match e {
+                CrackenError::Io(e) if e.kind() == ErrorKind::BrokenPipe => return Ok(()),
+                _ => return Err(output_error(e).into()),
+            }
+        }
+    }
+    if let Err(e) = out.flush() {
+        if e.kind() != ErrorKind::BrokenPipe {
+            return Err(output_error(e).into());
+        }
+    }
+    Ok(())
+}
+
+pub fn run_policygen(args: &ArgMatches) -> BoxResult<()> {
+    let minlen = get_length_arg(args, "min", "--min")?.unwrap();
+    let maxlen = get_length_arg(args, "max", "--max")?.unwrap();
+    let mut policy = Policy::new(minlen, maxlen);
+    if let Some(classes) = args.value_of("require") {
+        policy.required = parse_classes(classes)?;
+    }
+    for (max_count, (name, _)) in policy.max_counts.iter_mut().zip(POLICY_MAX_ARGS.iter()) {
+        *max_count = optional_value_t!(args, name, usize);
+    }
+    let masks = policy.masks()?;
+
+    let mut out: Box<dyn Write> = match args.value_of("output-file") {
+        Some(fname) => match File::create(fname) {
+            Ok(fp) => Box::new(BufWriter::new(fp)),
+            Err(e) => {
+                let msg = format!("cannot create file {}: {}", fname, e);
+                return Err(RunError::Output(msg).into());
+            }
+        },
+        None => Box::new(BufWriter::new(stdout().lock())),
+    };
+    write_policy_masks(&mut out, &masks, args.is_present("stats")).map_err(output_error)?;
+    Ok(())
+}
+
+/// writes a mask per line, with stats its keyspace too and the total on the last line
+fn write_policy_masks<W: Write>(
+    out: &mut W,
+    masks: &[PolicyMask],
+    stats: bool,
+) -> Result<(), io::Error> {
+    for policy_mask in masks {
+        if stats {
+            writeln!(out, "{}\t{}", policy_mask.mask, policy_mask.keyspace)?;
+        } else {
+            writeln!(out, "{}", policy_mask.mask)?;
+        }
+    }
+    if stats {
+        let total: BigUint = masks.iter().map(|m| &m.keyspace).sum();
+        writeln!(out, "total: {} masks, {} words", masks.len(), total)?;
+    }
+    out.flush()
+}
+
+pub fn run_version(args: &ArgMatches) -> BoxResult<()> {
+    let mut out = stdout();
+    if args.is_present("short") {
+        writeln!(out, "{} {}", built_info::PKG_NAME, built_info::PKG_VERSION)
+    } else {
+        write_version(&mut out, args.value_of("format") == Some("json"))
+    }
+    .map_err(output_error)?;
+    Ok(())
+}
+
+/// the json object of `write_version`, the git fields are null outside of a git checkout
+#[derive(Serialize)]
+struct VersionJson {
+    name: &'static str,
+    version: &'static str,
+    git_commit: Option<&'static str>,
+    git_dirty: Option<bool>,
+    built_time: &'static str,
+    target: &'static str,
+    profile: &'static str,
+    rustc: &'static str,
+    features: Vec<String>,
+}
+
+/// writes everything known about this build, for bug reports
+fn write_version<W: Write>(out: &mut W, json: bool) -> Result<(), io::Error> {
+    let features: Vec<_> = built_info::FEATURES
+        .iter()
+        .map(|f| f.to_lowercase())
+        .collect();
+    if json {
+        let json = VersionJson {
+            name: built_info::PKG_NAME,
+            version: built_info::PKG_VERSION,
+            git_commit: built_info::GIT_COMMIT_HASH,
+            git_dirty: built_info::GIT_DIRTY,
+            built_time: built_info::BUILT_TIME_UTC,
+            target: built_info::TARGET,
+            profile: built_info::PROFILE,
+            rustc: built_info::RUSTC_VERSION,
+            features,
+        };
+        serde_json::to_writer(&mut *out, &json)?;
+        writeln!(out)
+    } else {
+        let commit = match (built_info::GIT_COMMIT_HASH, built_info::GIT_DIRTY) {
+            (Some(hash), Some(true)) => format!("{} (dirty)", hash),
+            (Some(hash), _) => hash.to_string(),
+            (None, _) => "unknown".to_string(),
+        };
+        let features = if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.join(", ")
+        };
+        writeln!(out, "{} {}", built_info::PKG_NAME, built_info::PKG_VERSION)?;
+        writeln!(out, "git commit: {}", commit)?;
+        writeln!(out, "built: {}", built_info::BUILT_TIME_UTC)?;
+        writeln!(
+            out,
+            "target: {} ({})",
+            built_info::TARGET,
+            built_info::PROFILE
+        )?;
+        writeln!(out, "rustc: {}", built_info::RUSTC_VERSION)?;
+        writeln!(out, "features: {}", features)
+    }
+}
+
+pub fn run_create_smartlist(args: &ArgMatches) -> BoxResult<()> {
+    let outfile = args.value_of("smartlist").unwrap();
+    let infiles = args.values_of("file").map(|x| x.collect()).unwrap();
+    let vocab_max_size =
+        optional_value_t!(args, "vocab_max_size", u32).unwrap_or(DEFAULT_VOCAB_SIZE);
+    let min_frequency = optional_value_t!(args, "min_frequency", u32).unwrap_or(0);
+    let print_progress = !args.is_present("quiet");
+    let numbers_max_size = optional_value_t!(args, "numbers_max_size", u32);
+    let min_word_len = optional_value_t!(args, "min_word_len", u32).unwrap_or(1);
+
+    let tokenizers = args
+        .values_of("tokenizer")
+        .map(|x| x.collect())
+        .unwrap_or_else(|| vec!["unigram"])
+        .into_iter()
+        .map(|x| match x {
+            "bpe" => SmartlistTokenizer::BPE,
+            "unigram" => SmartlistTokenizer::Unigram,
+            "wordpiece" => SmartlistTokenizer::WordPiece,
+            _ => unreachable!("invalid tokenizer {}", x),
+        });
+
+    let mut writer = match File::create(outfile) {
+        Ok(fp) => BufWriter::new(fp),
+        Err(e) => {
+            let msg = format!("cannot create file {}: {}", outfile, e);
+            return Err(RunError::Output(msg).into());
+        }
+    };
+    let vocab = SmartlistBuilder::new()
+        .infiles(infiles)
+        .min_frequency(min_frequency)
+        .vocab_max_size(vocab_max_size)
+        .tokenizers(tokenizers.into_iter())
+        .print_progress(print_progress)
+        .numbers_max_size(numbers_max_size)
+        .min_word_len(min_word_len)
+        .build()?;
+
+    // write to file
+    for word in vocab.iter() {
+        writer.write_all(word.as_bytes()).map_err(output_error)?;
+        writer.write_all(b"\n").map_err(output_error)?;
+    }
+    writer.flush().map_err(output_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
 mod tests {
-    use crate::{runner, test_util};
+    use std::fs::File;
+    use std::io;
+    use std::path::Path;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use clap::ErrorKind as ClapErrorKind;
+    use num_bigint::{BigUint, ToBigUint};
+
+    use crate::builder::affix;
+    use crate::charsets::CustomCharset;
+    use crate::config::Profile;
+    use crate::disk_space::SpaceCheck;
+    use crate::error::CrackenError;
+    use crate::generators::get_word_generator;
+    use crate::helpers::{decode_hex_word, index_bytes, CountingWriter, WriteCounts};
+    use crate::mask::palindrome_mask;
+    use crate::model::Model;
+    use crate::runner::RunError;
+    use crate::{built_info, runner, test_util};
+
+    #[test]
+    fn test_run_generate_smoke() {
+        for args in vec![vec!["cracken", "generate", "?d"], vec!["cracken", "?d"]] {
+            assert!(runner::run(Some(args)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_run_entropy_smoke() {
+        let vocab_fname = test_util::wordlist_fname("vocab.txt");
+        let args = Some(vec![
+            "cracken",
+            "entropy",
+            "--smartlist",
+            vocab_fname.to_str().unwrap(),
+            "helloworld123!",
+        ]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_entropy_passwords_file_summary() {
+        let vocab_fname = test_util::wordlist_fname("vocab.txt");
+        let passwords_fname = test_util::wordlist_fname("passwords.txt");
+        let out_fname = std::env::temp_dir().join("cracken-test-entropy-summary.tsv");
+        let args = Some(vec![
+            "cracken",
+            "entropy",
+            "--smartlist",
+            vocab_fname.to_str().unwrap(),
+            "-p",
+            passwords_fname.to_str().unwrap(),
+            "--summary",
+            "-t",
+            "charset",
+            "--weakest",
+            "2",
+            "-o",
+            out_fname.to_str().unwrap(),
+        ]);
+        assert!(runner::run(args).is_ok());
+
+        // blank lines are skipped
+        let out = std::fs::read_to_string(&out_fname).unwrap();
+        std::fs::remove_file(&out_fname).unwrap();
+        assert_eq!(
+            out,
+            "123456\t19.93\npassword\t37.60\nabc123\t24.07\nAa123456!\t34.33\nqwerty\t28.20\n"
+        );
+    }
+
+    #[test]
+    fn test_run_entropy_filter() {
+        let out_fname = std::env::temp_dir().join("cracken-test-entropy-filter.txt");
+        // aa: 9.40 bits, a1/1a: 8.02 bits, 11: 6.64 bits
+        let args = Some(vec![
+            "cracken",
+            "-c",
+            "a1",
+            "--min-entropy",
+            "7",
+            "--max-entropy",
+            "9",
+            "-o",
+            out_fname.to_str().unwrap(),
+            "?1?1",
+        ]);
+        assert!(runner::run(args).is_ok());
+        let out = std::fs::read_to_string(&out_fname).unwrap();
+        std::fs::remove_file(&out_fname).unwrap();
+        assert_eq!(out, "1a\na1\n");
+
+        let args = Some(vec!["cracken", "-s", "--max-entropy", "5", "?d?d"]);
+        assert!(runner::run(args).is_ok());
+
+        let args = Some(vec![
+            "cracken",
+            "-s",
+            "--min-entropy",
+            "9",
+            "--max-entropy",
+            "5",
+            "?d",
+        ]);
+        let err = runner::run(args).unwrap_err().to_string();
+        assert_eq!(err, "--min-entropy 9 is greater than --max-entropy 5");
+    }
+
+    #[test]
+    fn test_run_entropy_jsonl() {
+        let vocab_fname = test_util::wordlist_fname("vocab.txt");
+        let passwords_fname = test_util::wordlist_fname("passwords-jsonl.txt");
+        let out_fname = std::env::temp_dir().join("cracken-test-entropy.jsonl");
+        let run = |no_echo: bool| {
+            let mut args = vec![
+                "cracken",
+                "entropy",
+                "--smartlist",
+                vocab_fname.to_str().unwrap(),
+                "-p",
+                passwords_fname.to_str().unwrap(),
+                "-t",
+                "charset",
+                "--format",
+                "jsonl",
+                "-o",
+                out_fname.to_str().unwrap(),
+            ];
+            if no_echo {
+                args.push("--no-echo");
+            }
+            assert!(runner::run(Some(args)).is_ok());
+            let out = std::fs::read_to_string(&out_fname).unwrap();
+            std::fs::remove_file(&out_fname).unwrap();
+            out
+        };
+
+        // blank lines are skipped but still counted by the index
+        assert_eq!(
+            run(false),
+            r#"{"password":"abc123","bits":24.07,"mask":"?l?l?l?d?d?d","tokens":["a","b","c","1","2","3"],"estimator":"charset"}
+{"password":"pa\"s\\","bits":24.1,"mask":"?l?l?s?l?s","tokens":["p","a","\"","s","\\"],"estimator":"charset"}
+{"hex":"fffe6162","bits":25.4,"mask":"?b?b?l?l","tokens":["�","�","a","b"],"estimator":"charset"}
+"#
+        );
+        assert_eq!(
+            run(true),
+            r#"{"index":0,"bits":24.07,"mask":"?l?l?l?d?d?d","estimator":"charset"}
+{"index":2,"bits":24.1,"mask":"?l?l?s?l?s","estimator":"charset"}
+{"index":3,"bits":25.4,"mask":"?b?b?l?l","estimator":"charset"}
+"#
+        );
+    }
+
+    #[test]
+    fn test_run_rank() {
+        let args = Some(vec![
+            "cracken",
+            "rank",
+            "--mask",
+            "?u?l?l?l?l?l?d?d",
+            "Summer19",
+        ]);
+        assert!(runner::run(args).is_ok());
+
+        let rank = |mask: &str, pwd: &str, wordlists: &[&str]| {
+            let word_generator = get_word_generator(mask, None, None, &[], wordlists).unwrap();
+            let mut out = vec![];
+            runner::write_rank(&mut out, word_generator.as_ref(), mask, pwd.as_bytes()).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(
+            rank("?d?d?d", "750", &[]),
+            "mask: ?d?d?d\nkeyspace: 1000\ngeneratable: yes\nindex: 750\npercent: 75.00%\n"
+        );
+        assert_eq!(
+            rank("?d?d?d", "75a", &[]),
+            "mask: ?d?d?d\nkeyspace: 1000\ngeneratable: no\n"
+        );
+
+        let wordlist = test_util::wordlist_fname("wordlist1.txt");
+        assert_eq!(
+            rank("?w1?d", "qwerty7", &[wordlist.to_str().unwrap()]),
+            "mask: ?w1?d\nkeyspace: 100\ngeneratable: yes\nindex: 27\npercent: 27.00%\nwordlist words: qwerty\n"
+        );
+    }
+
+    #[test]
+    fn test_run_subcommands_dispatch() {
+        let passwords = test_util::wordlist_fname("passwords.txt");
+        let wordlist = test_util::wordlist_fname("wordlist1.txt");
+        let cases = vec![
+            vec!["cracken", "generate", "-s", "?d?d"],
+            vec!["cracken", "analyze", "masks", passwords.to_str().unwrap()],
+            vec![
+                "cracken",
+                "analyze",
+                "masks",
+                "-n",
+                "1",
+                passwords.to_str().unwrap(),
+            ],
+            vec!["cracken", "analyze", "freq", passwords.to_str().unwrap()],
+            vec!["cracken", "analyze", "charsets", wordlist.to_str().unwrap()],
+            vec![
+                "cracken",
+                "analyze",
+                "charsets",
+                "--format",
+                "json",
+                wordlist.to_str().unwrap(),
+            ],
+            vec![
+                "cracken",
+                "analyze",
+                "freq",
+                "--format",
+                "json",
+                "--affix-len",
+                "2",
+                passwords.to_str().unwrap(),
+            ],
+            vec!["cracken", "validate", "?d?1", "-c", "ab"],
+            vec![
+                "cracken",
+                "validate",
+                "-w",
+                wordlist.to_str().unwrap(),
+                "?w1?d",
+            ],
+        ];
+        for args in cases {
+            assert!(runner::run(Some(args.clone())).is_ok(), "{:?}", args);
+        }
+
+        let cases = vec![
+            vec!["cracken", "validate", "?d?1"],
+            vec!["cracken", "validate", "?w1"],
+            vec!["cracken", "validate", "?x"],
+            vec!["cracken", "analyze", "masks", "/this/file/does/not/exist"],
+            vec!["cracken", "analyze", "freq", "/this/file/does/not/exist"],
+            vec![
+                "cracken",
+                "analyze",
+                "charsets",
+                "/this/file/does/not/exist",
+            ],
+            vec![
+                "cracken",
+                "analyze",
+                "freq",
+                "--affix-len",
+                "0",
+                passwords.to_str().unwrap(),
+            ],
+        ];
+        for args in cases {
+            assert!(runner::run(Some(args.clone())).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_run_maskgen() {
+        let passwords = test_util::wordlist_fname("passwords.txt");
+        let out_fname = std::env::temp_dir().join("cracken-test-maskgen.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec![
+                "cracken",
+                "maskgen",
+                "-f",
+                passwords.to_str().unwrap(),
+                "-o",
+                out_fname,
+            ];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        assert_eq!(
+            run(&["--target-coverage", "0.4"]).unwrap(),
+            "?d?d?d?d?d?d\n?l?l?l?d?d?d\n"
+        );
+        assert_eq!(
+            run(&["--target-coverage", "0.4", "--max-keyspace", "1e6"]).unwrap(),
+            "?d?d?d?d?d?d\n"
+        );
+        assert_eq!(run(&[]).unwrap().lines().count(), 5);
+        for args in [
+            &["--target-coverage", "1.5"][..],
+            &["--target-coverage", "x"],
+            &["--max-keyspace", "1e-3"],
+        ] {
+            assert!(matches!(run(args), Err(RunError::Usage(_))), "{:?}", args);
+        }
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_bare_mask_defaults_to_generate() {
+        let out_fname = std::env::temp_dir().join("cracken-test-bare-mask.txt");
+        for subcommand in [None, Some("generate")] {
+            let mut args = vec!["cracken"];
+            args.extend(subcommand);
+            args.extend(["-o", out_fname.to_str().unwrap(), "?d"]);
+            assert!(runner::run(Some(args)).is_ok());
+            let out = std::fs::read_to_string(&out_fname).unwrap();
+            std::fs::remove_file(&out_fname).unwrap();
+            assert_eq!(out, "0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n");
+        }
+    }
+
+    #[test]
+    fn test_run_dev_null() {
+        let args = Some(vec!["cracken", "-o", "/dev/null", "?d"]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_to() {
+        let counts = Rc::new(WriteCounts::default());
+        let out = Box::new(CountingWriter::new(io::sink(), Rc::clone(&counts)));
+        let args = Some(vec!["cracken", "-m", "2", "?d?d?d"]);
+        runner::run_to(args, out).unwrap();
+        assert_eq!(counts.lines.get(), 1100);
+        assert_eq!(counts.bytes.get(), 100 * 3 + 1000 * 4);
+
+        // the given output replaces -o, which isn't created
+        let out_fname = std::env::temp_dir().join("cracken-test-run-to.txt");
+        let _ = std::fs::remove_file(&out_fname);
+        let args = vec!["cracken", "-o", out_fname.to_str().unwrap(), "?d"];
+        runner::run_to(Some(args), Box::new(io::sink())).unwrap();
+        assert!(!out_fname.exists());
+
+        let args = Some(vec!["cracken", "validate", "?d"]);
+        assert!(matches!(
+            runner::run_to(args, Box::new(io::sink())),
+            Err(RunError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_mask_file() {
+        let cases = vec![
+            ("?d?d\n", Ok("?d?d")),
+            ("?d?d", Ok("?d?d")),
+            ("# digits\n\n?d ?d \r\n# end\n", Ok("?d ?d ")),
+            ("?d#?d\n", Ok("?d#?d")),
+            ("\\#?d\n", Ok("\\#?d")),
+            (
+                "# only a comment\n",
+                Err("no mask, only empty and comment lines"),
+            ),
+            ("", Err("no mask, only empty and comment lines")),
+            (
+                "?d\n?l\n",
+                Err("more than one mask, use -i for a file of several masks"),
+            ),
+        ];
+        for (content, expected) in cases {
+            let expected = expected.map(str::to_string).map_err(str::to_string);
+            assert_eq!(runner::parse_mask_file(content), expected, "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn test_run_mask_file() {
+        let dir = std::env::temp_dir();
+        let mask_fname = dir.join("cracken-test-mask-file.txt");
+        let out_fname = dir.join("cracken-test-mask-file-out.txt");
+        let mask_arg = format!("@{}", mask_fname.to_str().unwrap());
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname.to_str().unwrap()];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(&out_fname).unwrap())
+        };
+
+        std::fs::write(&mask_fname, "# a digit and a custom char\n?d?1\n").unwrap();
+        let words = run(&["-c", "ab", mask_arg.as_str()]).unwrap();
+        assert_eq!(words, run(&["-c", "ab", "?d?1"]).unwrap());
+        assert_eq!(words.lines().count(), 20);
+        // a literal @ is escaped
+        let words = run(&["\\@?d"]).unwrap();
+        assert_eq!(words.lines().next(), Some("@0"));
+
+        std::fs::write(&mask_fname, "?d\n?d?d\n").unwrap();
+        let err = run(&[mask_arg.as_str()]).unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+        assert!(err.to_string().contains("more than one mask"), "{}", err);
+        std::fs::remove_file(&mask_fname).unwrap();
+        std::fs::remove_file(&out_fname).unwrap();
+
+        let err = run(&["@/tmp/this/dir/not/exisT"]).unwrap_err();
+        assert!(matches!(err, RunError::Input(_)), "{:?}", err);
+        assert_eq!(err.exit_code(), 3);
+        assert!(
+            err.to_string()
+                .starts_with("cannot read mask file /tmp/this/dir/not/exisT: "),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_run_custom_charset() {
+        let args = Some(vec!["cracken", "-c=abcdef0123456789", "?1"]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_custom_tokens() {
+        let args = Some(vec![
+            "cracken",
+            "-o",
+            "/dev/null",
+            "-c",
+            "ab",
+            "--tokens",
+            "!|!!|123|2024",
+            "?1?2?d",
+        ]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_wordlist_encoding() {
+        let wordlist = test_util::wordlist_fname("wordlist-latin1.txt");
+        let wordlist_arg = format!("{}:latin1:upper", wordlist.to_str().unwrap());
+        let args = Some(vec![
+            "cracken",
+            "-o",
+            "/dev/null",
+            "--output-encoding",
+            "latin1",
+            "-w",
+            &wordlist_arg,
+            "?w1?d",
+        ]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_output_utf16le() {
+        let wordlist = test_util::wordlist_fname("wordlist-utf8.txt");
+        let wordlist = wordlist.to_str().unwrap();
+        let run = |args: &[&str], fname: &str| {
+            let out_fname = std::env::temp_dir().join(fname);
+            let out_fname = out_fname.to_str().unwrap();
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname, "-w", wordlist];
+            run_args.extend(args);
+            let res = runner::run(Some(run_args));
+            let out = std::fs::read(out_fname).unwrap_or_default();
+            let _ = std::fs::remove_file(out_fname);
+            res.map(|_| out)
+        };
+        let utf8 = run(&["?w1?d"], "cracken-test-utf8.txt").unwrap();
+        let utf16 = run(
+            &["--output-encoding", "utf16le", "?w1?d"],
+            "cracken-test-utf16le.txt",
+        )
+        .unwrap();
+        let units: Vec<u16> = utf16
+            .chunks(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        assert_eq!(String::from_utf16(&units).unwrap().as_bytes(), utf8);
+        assert!(utf16.len() > utf8.len() && utf16.len() <= utf8.len() * 2);
+
+        let invalid = run(
+            &["--output-encoding", "utf16le", "?w1?b"],
+            "cracken-test-utf16le-invalid.txt",
+        );
+        assert!(matches!(invalid, Err(RunError::Output(_))));
+        let lossy = run(
+            &["--output-encoding", "utf16le", "--lossy", "?w1?b"],
+            "cracken-test-utf16le-lossy.txt",
+        )
+        .unwrap();
+        let units: Vec<u16> = lossy
+            .chunks(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        assert!(String::from_utf16(&units)
+            .unwrap()
+            .contains("john\u{fffd}\n"));
+
+        assert!(matches!(
+            run(&["--lossy", "?w1"], "cracken-test-lossy.txt"),
+            Err(RunError::Usage(_))
+        ));
+        assert!(run(
+            &["--output-encoding", "utf16le", "--max-bytes", "10", "?w1"],
+            "cracken-test-utf16le-max-bytes.txt"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_run_output_hashcat_hex() {
+        let wordlist = test_util::wordlist_fname("wordlist-utf8.txt");
+        let wordlist = wordlist.to_str().unwrap();
+        let out_fname = std::env::temp_dir().join("cracken-test-hashcat-hex.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname, "-w", wordlist];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read(out_fname).unwrap())
+        };
+        let out = run(&["--output-encoding", "hashcat-hex", "?w1?b"]).unwrap();
+        let lines: Vec<&[u8]> = out.split(|&c| c == b'\n').collect();
+        assert_eq!(lines.last(), Some(&&b""[..]));
+        let lines = &lines[..lines.len() - 1];
+        // the printable words are written as they are
+        assert!(lines.contains(&&b"john!"[..]));
+        assert!(lines.contains(&&b"$HEX[6a6f686e00]"[..]));
+        assert!(lines.contains(&&b"$HEX[6a6f686e0a]"[..]));
+        assert!(lines.contains(&&b"$HEX[c3a96d696c6561]"[..]));
+        assert!(lines
+            .iter()
+            .all(|line| line.iter().all(|&c| (0x20..0x7f).contains(&c))));
+
+        let mut decoded: Vec<Vec<u8>> = lines
+            .iter()
+            .map(|line| decode_hex_word(line.to_vec()).unwrap())
+            .collect();
+        decoded.sort();
+        let mut expected = vec![];
+        for word in std::fs::read_to_string(wordlist).unwrap().lines() {
+            for b in 0..=255u8 {
+                let mut candidate = word.as_bytes().to_vec();
+                candidate.push(b);
+                expected.push(candidate);
+            }
+        }
+        expected.sort();
+        assert_eq!(decoded, expected);
+
+        assert!(run(&["--output-encoding", "hashcat-hex", "--prefix", "a", "?d"]).is_err());
+        assert!(run(&["--output-encoding", "hashcat-hex", "--with-index", "?d"]).is_err());
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_join() {
+        let dir = std::env::temp_dir();
+        let (first, second) = (
+            dir.join("cracken-test-join-1.txt"),
+            dir.join("cracken-test-join-2.txt"),
+        );
+        std::fs::write(&first, "alpha\nbeta\n").unwrap();
+        std::fs::write(&second, "gamma\n").unwrap();
+        let out_fname = dir.join("cracken-test-join.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname];
+            run_args.extend([
+                "-w",
+                first.to_str().unwrap(),
+                "-w",
+                second.to_str().unwrap(),
+            ]);
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+
+        let words = run(&["--join", "-", "?w1?w2?d?d"]).unwrap();
+        let words: Vec<_> = words.lines().collect();
+        assert_eq!(words.len(), 200);
+        assert!(words.contains(&"alpha-gamma-42"));
+        assert!(words.contains(&"beta-gamma-07"));
+        let words = run(&["--join", "-", "--join-mode", "all", "?w1?w2?d?d"]).unwrap();
+        let words: Vec<_> = words.lines().collect();
+        assert_eq!(words.len(), 200);
+        assert!(words.contains(&"alpha-gamma-4-2"));
+        // literal chars are a single item, the separator is a byte string
+        let words = run(&["--join", "\\x2e", "--join-mode", "all", "?w2ab?d"]).unwrap();
+        assert!(words.starts_with("gamma.ab.0\ngamma.ab.1\n"));
+        assert_eq!(run(&["--join", "::", "?w2"]).unwrap(), "gamma\n");
+
+        assert!(matches!(
+            run(&["--join-mode", "all", "?w1"]),
+            Err(RunError::Args(_))
+        ));
+        assert!(run(&["--join", "\\xff", "?w1?w2"]).is_err());
+        assert!(runner::run(Some(vec!["cracken", "-s", "--join", "-", "?d?d"])).is_ok());
+        for fname in [first.to_str().unwrap(), second.to_str().unwrap(), out_fname] {
+            std::fs::remove_file(fname).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_run_backref() {
+        let word_generator = get_word_generator("?d?l?r1", None, None, &[], &[]).unwrap();
+        let mut out = vec![];
+        runner::write_stats(&mut out, word_generator.as_ref(), &[], &[], true).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"combinations\":\"260\",\"bits\":8.02,\"not_counting\":[],\"lengths\":[],\
+             \"times\":[]}\n"
+        );
+        assert!(runner::run(Some(vec!["cracken", "-s", "?d?l?r1"])).is_ok());
+
+        let out_fname = std::env::temp_dir().join("cracken-test-backref.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        let expected = test_util::wordlist_fname("backref-digit-lower.txt");
+        assert_eq!(
+            run(&["?d?l?r1"]).unwrap(),
+            std::fs::read_to_string(expected).unwrap()
+        );
+        // the joined backreference still repeats the digit
+        let words = run(&["--join", "-", "--join-mode", "all", "?d?l?r1"]).unwrap();
+        assert!(words.starts_with("0-a-0\n0-b-0\n"));
+        assert_eq!(words.lines().count(), 260);
+        assert!(matches!(
+            run(&["?d?r2"]),
+            Err(RunError::Generator(CrackenError::Mask(_)))
+        ));
+        assert!(run(&["-m", "1", "?d?r1"]).is_err());
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_palindrome() {
+        let out_fname = std::env::temp_dir().join("cracken-test-palindrome.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname, "--palindrome"];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        let words = run(&["?d?d?d?d"]).unwrap();
+        let words: Vec<_> = words.lines().collect();
+        assert_eq!(words.len(), 100);
+        assert_eq!(&words[..3], ["0000", "0110", "0220"]);
+        assert!(words
+            .iter()
+            .all(|word| word.chars().rev().collect::<String>() == *word));
+        let unique: std::collections::HashSet<_> = words.iter().collect();
+        assert_eq!(unique.len(), 100);
+        let words = run(&["-c", "ab", "?1?d?1"]).unwrap();
+        assert_eq!(words.lines().count(), 20);
+        assert!(words.starts_with("a0a\na1a\n"));
+
+        // the reduced keyspace
+        let mask = palindrome_mask("?d?d?d?d?d", &[]).unwrap();
+        let word_generator = get_word_generator(&mask, None, None, &[], &[]).unwrap();
+        assert_eq!(word_generator.combinations(), 1000.to_biguint().unwrap());
+        assert!(runner::run(Some(vec!["cracken", "-s", "--palindrome", "?d?d?d?d"])).is_ok());
+
+        assert!(matches!(
+            run(&["?d?l"]),
+            Err(RunError::Generator(CrackenError::Mask(_)))
+        ));
+        assert!(matches!(
+            run(&["-m", "2", "?d?d?d"]),
+            Err(RunError::Usage(_))
+        ));
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_output_base64() {
+        let decode = |line: &[u8]| -> Vec<u8> {
+            const ALPHABET: &[u8] =
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+            let mut bits = 0u32;
+            let mut nbits = 0;
+            let mut decoded = vec![];
+            for &c in line.iter().filter(|&&c| c != b'=') {
+                bits = (bits << 6) | ALPHABET.iter().position(|&a| a == c).unwrap() as u32;
+                nbits += 6;
+                if nbits >= 8 {
+                    nbits -= 8;
+                    decoded.push((bits >> nbits) as u8);
+                    bits &= (1 << nbits) - 1;
+                }
+            }
+            decoded
+        };
+        let out_fname = std::env::temp_dir().join("cracken-test-base64.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        for pad in [true, false] {
+            let mut args = vec!["cracken", "--force", "-o", out_fname];
+            args.extend(["--output-encoding", "base64", "?b?b"]);
+            if !pad {
+                args.push("--base64-no-pad");
+            }
+            runner::run(Some(args)).unwrap();
+            let out = std::fs::read(out_fname).unwrap();
+            let lines: Vec<&[u8]> = out.split(|&c| c == b'\n').collect();
+            assert_eq!(lines.len(), 65537);
+            assert_eq!(lines[65536], b"");
+            let pairs: std::collections::HashSet<Vec<u8>> = lines[..65536]
+                .iter()
+                .map(|line| {
+                    assert_eq!(line.len(), if pad { 4 } else { 3 }, "{:?}", line);
+                    decode(line)
+                })
+                .collect();
+            assert_eq!(pairs.len(), 65536);
+            assert!(pairs.iter().all(|pair| pair.len() == 2));
+        }
+        std::fs::remove_file(out_fname).unwrap();
+
+        assert!(matches!(
+            runner::run(Some(vec!["cracken", "--base64-no-pad", "?d"])),
+            Err(RunError::Usage(_))
+        ));
+        let args = vec![
+            "cracken",
+            "--output-encoding",
+            "base64",
+            "--suffix",
+            "!",
+            "?d",
+        ];
+        assert_eq!(
+            runner::run(Some(args)).unwrap_err().to_string(),
+            "--prefix and --suffix don't support --output-encoding base64, the words are encoded without them"
+        );
+    }
+
+    #[test]
+    fn test_run_wordlist_filter() {
+        let wordlist = test_util::wordlist_fname("wordlist1.txt");
+        let wordlist = wordlist.to_str().unwrap();
+        let args = Some(vec![
+            "cracken",
+            "-s",
+            "-w",
+            wordlist,
+            "--wordlist-filter",
+            "^[0-9]+$",
+            "-w",
+            wordlist,
+            "?w1?w2",
+        ]);
+        assert!(runner::run(args).is_ok());
+
+        let args = Some(vec![
+            "cracken",
+            "-s",
+            "--wordlist-filter",
+            "^[0-9]+$",
+            "-w",
+            wordlist,
+            "?w1",
+        ]);
+        let err = runner::run(args).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "--wordlist-filter ^[0-9]+$ must follow the -w it applies to"
+        );
+
+        let args = Some(vec![
+            "cracken",
+            "-s",
+            "-w",
+            wordlist,
+            "--wordlist-filter",
+            "^[0-9+$",
+            "?w1",
+        ]);
+        let err = runner::run(args).unwrap_err().to_string();
+        assert!(
+            err.starts_with("invalid --wordlist-filter ^[0-9+$"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_run_wordlist_sample() {
+        let wordlist = test_util::wordlist_fname("wordlist1.txt");
+        let wordlist = wordlist.to_str().unwrap();
+        let args = Some(vec![
+            "cracken",
+            "-s",
+            "-w",
+            wordlist,
+            "--wordlist-sample",
+            "0.3",
+            "--seed",
+            "1337",
+            "?w1",
+        ]);
+        assert!(runner::run(args).is_ok());
+
+        for sample in ["0", "1.5", "NaN"] {
+            let args = Some(vec![
+                "cracken",
+                "-s",
+                "-w",
+                wordlist,
+                "--wordlist-sample",
+                sample,
+                "?w1",
+            ]);
+            let err = runner::run(args).unwrap_err().to_string();
+            assert!(
+                err.starts_with("--wordlist-sample must be in range"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_stats_bits() {
+        let stats = |mask: &str, minlen: Option<usize>| {
+            let charsets = vec![CustomCharset::Chars("ab"), CustomCharset::Chars("abcd")];
+            let word_generator = get_word_generator(mask, minlen, None, &charsets, &[]).unwrap();
+            let mut out = vec![];
+            runner::write_stats(&mut out, word_generator.as_ref(), &[], &[], false).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(stats("?1?2?2", None), "32\nbits: 5.00\n");
+        // 2^120 words does not fit a u64
+        assert_eq!(
+            stats(&"?b".repeat(15), None),
+            "1329227995784915872903807060280344576\nbits: 120.00\n"
+        );
+        assert_eq!(
+            stats("?1?2?2", Some(1)),
+            "42\nbits: 5.39\nbits (length 1): 1.00\nbits (length 2): 3.00\nbits (length 3): 5.00\n\
+             length\tcombinations\tcumulative\tpercent\n\
+             1\t2\t2\t4.76%\n\
+             2\t8\t10\t23.80%\n\
+             3\t32\t42\t100.00%\n"
+        );
+        assert_eq!(stats("?d?d?d", None), "1000\nbits: 9.97\n");
+    }
+
+    #[test]
+    fn test_write_stats_json() {
+        let word_generator = get_word_generator("?d?l", Some(1), None, &[], &[]).unwrap();
+        let rates = vec![("10H".to_string(), 10)];
+        let mut out = vec![];
+        runner::write_stats(
+            &mut out,
+            word_generator.as_ref(),
+            &["--seq-max"],
+            &rates,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"combinations\":\"270\",\"bits\":8.08,\"not_counting\":[\"--seq-max\"],\
+             \"lengths\":[{\"length\":1,\"combinations\":\"10\",\"cumulative\":\"10\",\"percent\":3.7},\
+             {\"length\":2,\"combinations\":\"260\",\"cumulative\":\"270\",\"percent\":100.0}],\
+             \"times\":[{\"rate\":\"10H\",\"seconds\":\"27\"}]}\n"
+        );
+
+        let mut out = vec![];
+        runner::write_range_stats(&mut out, &BigUint::default(), &[], true).unwrap();
+        assert_eq!(out, b"{\"range\":\"0\",\"bits\":null,\"times\":[]}\n");
+
+        let args = Some(vec!["cracken", "--stats", "--format", "json", "?d"]);
+        assert!(runner::run(args).is_ok());
+        let args = Some(vec!["cracken", "--format", "json", "?d"]);
+        assert!(runner::run(args).is_err());
+    }
+
+    #[test]
+    fn test_write_masks_stats() {
+        let masks_fname = test_util::wordlist_fname("masks.hcmask");
+        let masks_fname = masks_fname.to_str().unwrap();
+        let masks = runner::read_masks_file(masks_fname).unwrap();
+        let write = |sort: bool, json: bool| {
+            let rows = masks.iter().map(|mask| -> Result<_, CrackenError> {
+                let charsets: Vec<_> = mask
+                    .charsets
+                    .iter()
+                    .map(|chars| CustomCharset::Chars(chars))
+                    .collect();
+                let word_generator = get_word_generator(&mask.mask, None, None, &charsets, &[])?;
+                Ok(runner::MaskFileStats {
+                    line: mask.line,
+                    mask: mask.mask.clone(),
+                    charsets: mask.charsets.clone(),
+                    combinations: word_generator.combinations(),
+                    bytes: word_generator.output_bytes(),
+                })
+            });
+            let rates = vec![("1H".to_string(), 1)];
+            let mut out = vec![];
+            runner::write_masks_stats(&mut out, rows, sort, &rates, json).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let lower_digits = "abcdefghijklmnopqrstuvwxyz0123456789";
+        let rows = [
+            "2\t?d?d\t-\t100\t300".to_string(),
+            format!("3\t?1?1\t?1={}\t1296\t3888", lower_digits),
+            "5\t?1?1?d\t?1=abc\t90\t360".to_string(),
+            "6\tx?1\t?1=,.\t2\t6".to_string(),
+            format!(
+                "7\t?2?2\t?1={} ?2={}ABCDEFGHIJKLMNOPQRSTUVWXYZ\t3844\t11532",
+                lower_digits, lower_digits
+            ),
+        ];
+        let report = |order: &[usize]| {
+            let mut report = "line\tmask\tcharsets\tcombinations\tbytes\n".to_string();
+            for &idx in order {
+                report.push_str(&rows[idx]);
+                report.push('\n');
+            }
+            report + "total\t\t\t5332\t16086\nbits: 12.38\ntime at 1H: 1.4 hours\n"
+        };
+        assert_eq!(write(false, false), report(&[0, 1, 2, 3, 4]));
+        assert_eq!(write(true, false), report(&[4, 1, 0, 2, 3]));
+
+        let json = write(true, true);
+        assert!(json.starts_with(
+            "{\"masks\":[{\"line\":7,\"mask\":\"?2?2\",\"charsets\":[\"abcdefghijklmnopqrstuvwxyz0123456789\","
+        ));
+        assert!(json.contains(
+            "{\"line\":6,\"mask\":\"x?1\",\"charsets\":[\",.\"],\"combinations\":\"2\",\"bytes\":\"6\"}"
+        ));
+        assert!(json.ends_with(
+            "],\"combinations\":\"5332\",\"bytes\":\"16086\",\"bits\":12.38,\
+             \"times\":[{\"rate\":\"1H\",\"seconds\":\"5332\"}]}\n"
+        ));
+
+        for args in [
+            vec!["cracken", "-s", "-i", masks_fname],
+            vec![
+                "cracken",
+                "-s",
+                "--sort-keyspace",
+                "--format",
+                "json",
+                "-i",
+                masks_fname,
+            ],
+            vec!["cracken", "-s", "-c", "xyz", "-i", masks_fname],
+        ] {
+            assert!(runner::run(Some(args.clone())).is_ok(), "{:?}", args);
+        }
+        for args in [
+            vec!["cracken", "-s", "--sort-keyspace", "?d"],
+            vec!["cracken", "-s", "--keyspace-hashcat", "-i", masks_fname],
+            vec!["cracken", "-s", "--syntax", "john", "-i", masks_fname],
+        ] {
+            assert!(runner::run(Some(args.clone())).is_err(), "{:?}", args);
+        }
+
+        // the words of every line are generated with the charsets of the line
+        let out_fname = std::env::temp_dir().join("cracken-test-hcmask.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let args = vec!["cracken", "--force", "-o", out_fname, "-i", masks_fname];
+        runner::run(Some(args)).unwrap();
+        let words = std::fs::read_to_string(out_fname).unwrap();
+        assert_eq!(words.lines().count(), 5332);
+        assert!(words.contains("\nx,\nx.\n"));
+        assert!(words.contains("\nab1\n"));
+        assert!(words.contains("\naZ\n"));
+        std::fs::remove_file(out_fname).unwrap();
+
+        let bad_fname = std::env::temp_dir().join("cracken-test-bad.hcmask");
+        std::fs::write(&bad_fname, "?d\n?x,?1\n").unwrap();
+        let err = runner::run(Some(vec![
+            "cracken",
+            "-s",
+            "-i",
+            bad_fname.to_str().unwrap(),
+        ]))
+        .unwrap_err();
+        assert!(matches!(err, RunError::Input(_)));
+        assert!(
+            err.to_string()
+                .contains(" line 2: hcmask charset ?x uses ?x"),
+            "{}",
+            err
+        );
+        std::fs::remove_file(bad_fname).unwrap();
+    }
+
+    #[test]
+    fn test_write_stats_rates() {
+        let mask = "?a".repeat(8);
+        let word_generator = get_word_generator(&mask, None, None, &[], &[]).unwrap();
+        let rates = vec![
+            ("50GH".to_string(), 50_000_000_000),
+            ("1k (bcrypt)".to_string(), 1000),
+        ];
+        let mut out = vec![];
+        runner::write_stats(&mut out, word_generator.as_ref(), &[], &rates, false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "6095689385410816\nbits: 52.44\ntime at 50GH: 1.4 days\n\
+             time at 1k (bcrypt): 193293.0 years\n"
+        );
+
+        let args = Some(vec!["cracken", "--stats", "--rate", "50X", "?d"]);
+        let err = runner::run(args).unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+        assert!(err.to_string().starts_with("invalid rate '50X'"), "{}", err);
+
+        let rates_fname = test_util::wordlist_fname("rates.txt");
+        let args = vec![
+            "cracken",
+            "-s",
+            "--rate-file",
+            rates_fname.to_str().unwrap(),
+            "?d",
+        ];
+        assert!(runner::run(Some(args)).is_ok());
+    }
+
+    #[test]
+    fn test_run_stats() {
+        let args = Some(vec!["cracken", "-s", "?d?s?u?l?a?b"]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_dedup_across_masks() {
+        let dir = std::env::temp_dir();
+        let masks_fname = dir.join("cracken-test-dedup-masks.txt");
+        let out_fname = dir.join("cracken-test-dedup-out.txt");
+        let masks = ["?l?d", "?a?a", "?d?d", "?1?1", "?1?d?d", "?d?d?d"];
+        std::fs::write(&masks_fname, masks.join("\n") + "\n").unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname.to_str().unwrap()];
+            run_args.extend(["-c", "a1"]);
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            let out = std::fs::read_to_string(&out_fname).unwrap();
+            Ok::<_, RunError>(out.lines().map(|line| line.to_string()).collect::<Vec<_>>())
+        };
+
+        // the union of the words of the masks, in the order they are first generated
+        let mut seen = std::collections::HashSet::new();
+        let mut union = vec![];
+        for mask in masks {
+            for word in run(&[mask]).unwrap() {
+                if seen.insert(word.clone()) {
+                    union.push(word);
+                }
+            }
+        }
+        let masks_arg = ["-i", masks_fname.to_str().unwrap()];
+        let words = run(&masks_arg).unwrap();
+        assert!(words.len() > union.len());
+        let mut args = masks_arg.to_vec();
+        args.push("--dedup-across-masks");
+        assert_eq!(run(&args).unwrap(), union);
+
+        // the skipped words are the deduplicated ones
+        let skip_args = [args.clone(), vec!["--skip", "9000"]].concat();
+        assert_eq!(run(&skip_args).unwrap(), union[9000..].to_vec());
+        let seq_args = [args.clone(), vec!["--seq-max", "1"]].concat();
+        let expected: Vec<_> = union
+            .iter()
+            .filter(|word| word.as_bytes().windows(2).all(|pair| pair[0] != pair[1]))
+            .cloned()
+            .collect();
+        assert_eq!(run(&seq_args).unwrap(), expected);
+        let interleave_args = [args.clone(), vec!["--interleave"]].concat();
+        let mut words = run(&interleave_args).unwrap();
+        words.sort();
+        let mut sorted = union.clone();
+        sorted.sort();
+        assert_eq!(words, sorted);
+        std::fs::remove_file(&out_fname).unwrap();
+
+        let verify_args = [vec!["cracken", "-c", "a1", "--verify"], args].concat();
+        assert!(runner::run(Some(verify_args)).is_ok());
+        std::fs::remove_file(&masks_fname).unwrap();
+
+        let err = runner::run(Some(vec!["cracken", "--dedup-across-masks", "?d"])).unwrap_err();
+        assert!(matches!(err, RunError::Args(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn test_run_max_keyspace() {
+        let out_fname = std::env::temp_dir().join("cracken-test-max-keyspace.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--max-keyspace", "999"];
+            run_args.extend(args);
+            runner::run(Some(run_args))
+        };
+        let to_file = |args: &[&'static str]| [&["--force", "-o", out_fname][..], args].concat();
+
+        let err = run(&to_file(&["?d?d?d?d"])).unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "refusing to write 10000 words to {}, more than --max-keyspace 999. pass --yes-i-know to write them anyway",
+                out_fname
+            )
+        );
+        // the refused run creates no output, an existing file is left as it was even with --force
+        // and the --pipe-to command is not run
+        std::fs::write(out_fname, "existing\n").unwrap();
+        let marker = std::env::temp_dir().join("cracken-test-max-keyspace-piped");
+        let _ = std::fs::remove_file(&marker);
+        let command = format!("touch {}", marker.to_str().unwrap());
+        let err = run(&[
+            "--force",
+            "-o",
+            out_fname,
+            "--pipe-to",
+            &command,
+            "?d?d?d?d",
+        ])
+        .unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        assert_eq!(std::fs::read(out_fname).unwrap(), b"existing\n");
+        assert!(!marker.exists());
+        assert!(run(&to_file(&["?d?d"])).is_ok());
+        assert!(run(&to_file(&["--yes-i-know", "?d?d?d?d"])).is_ok());
+        assert_eq!(std::fs::read_to_string(out_fname).unwrap().len(), 50000);
+        // the words skipped or out of the range are not counted
+        assert!(run(&to_file(&["--skip", "9001", "?d?d?d?d"])).is_ok());
+        assert!(run(&to_file(&["--slice", "1/11", "?d?d?d?d"])).is_ok());
+        assert!(run(&to_file(&["--slice", "1/10", "?d?d?d?d"])).is_err());
+        assert!(run(&to_file(&["--seq-max", "3", "--skip", "9001", "?d?d?d?d"])).is_ok());
+        // exempted: --max-bytes bounds the file, --stats writes no words, the others aren't files
+        assert!(run(&to_file(&["--max-bytes", "1k", "?d?d?d?d"])).is_ok());
+        assert!(run(&to_file(&["--stats", "?d?d?d?d"])).is_ok());
+        assert!(run(&["-o", "/dev/null", "?d?d?d?d"]).is_ok());
+        assert!(run(&["?d?d?d?d"]).is_ok());
+        let args = vec!["cracken", "--max-keyspace", "999", "?d?d?d?d"];
+        assert!(runner::run_to(Some(args), Box::new(io::sink())).is_ok());
+        std::fs::remove_file(out_fname).unwrap();
+
+        let err = runner::run(Some(vec!["cracken", "--max-keyspace", "x", "?d"])).unwrap_err();
+        assert!(matches!(err, RunError::Usage(_)), "{:?}", err);
+    }
+
+    /// reports the same free space for every path, the paths in the current directory are on
+    /// the same filesystem and the filesystem of the other paths is unknown
+    struct FakeSpace(io::Result<Option<u64>>);
+
+    impl SpaceCheck for FakeSpace {
+        fn available_space(&self, _path: &Path) -> io::Result<Option<u64>> {
+            match &self.0 {
+                Ok(available) => Ok(*available),
+                Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+            }
+        }
+
+        fn filesystem(&self, path: &Path) -> Option<u64> {
+            match path.parent() {
+                Some(dir) if dir.as_os_str().is_empty() => Some(1),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_disk_space() {
+        let check = |available, files: &[&str], ratio| {
+            let bytes = 50000.to_biguint().unwrap();
+            runner::check_disk_space(&FakeSpace(available), files, &bytes, ratio)
+        };
+        let files = ["words.txt"];
+        assert!(check(Ok(Some(50000)), &files, 1.0).is_ok());
+        let err = check(Ok(Some(4096)), &files, 1.0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "not enough space for words.txt: the words take about 50000 bytes (48.8 KiB), 4096 bytes (4 KiB) are available. pass --force to write them anyway"
+        );
+        let err = RunError::from(err);
+        assert_eq!(err.exit_code(), 5);
+        // the space the os can't tell isn't checked
+        assert!(check(Ok(None), &files, 1.0).is_ok());
+        let unsupported = io::Error::new(io::ErrorKind::Unsupported, "statvfs");
+        assert!(check(Err(unsupported), &files, 1.0).is_ok());
+        assert!(check(Ok(Some(0)), &[], 1.0).is_ok());
+
+        // the files of a filesystem share its space
+        let files = ["words.txt", "more-words.txt"];
+        let err = check(Ok(Some(50000)), &files, 1.0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "not enough space for words.txt, more-words.txt: the words take about 100000 bytes (97.7 KiB), 50000 bytes (48.8 KiB) are available. pass --force to write them anyway"
+        );
+        assert!(check(Ok(Some(100000)), &files, 1.0).is_ok());
+        // the files of unknown filesystems are checked on their own
+        assert!(check(Ok(Some(50000)), &["words.txt", "out/words.txt"], 1.0).is_ok());
+
+        // the estimate of the compressed files
+        assert!(check(Ok(Some(4096)), &["words.txt"], 20.0).is_ok());
+        let err = check(Ok(Some(4096)), &["words.txt"], 10.0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "not enough space for words.txt: the words take about 5000 bytes (4.9 KiB) with --compression-ratio 10, 4096 bytes (4 KiB) are available. pass --force to write them anyway"
+        );
+    }
+
+    #[test]
+    fn test_run_refused_creates_no_output() {
+        let out_fname = std::env::temp_dir().join("cracken-test-refused.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let marker = std::env::temp_dir().join("cracken-test-refused-piped");
+        let command = format!("touch {}", marker.to_str().unwrap());
+        let _ = std::fs::remove_file(out_fname);
+        let _ = std::fs::remove_file(&marker);
+
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "-o", out_fname, "--pipe-to", &command];
+            run_args.extend(args);
+            runner::run(Some(run_args))
+        };
+        // the invalid args are refused before the outputs are created
+        for args in [
+            vec!["--interleave-chunk", "0", "?d"],
+            vec!["--lossy", "?d"],
+            vec!["--base64-no-pad", "?d"],
+            vec!["--min-entropy", "9", "--max-entropy", "1", "?d"],
+            vec!["--timeout", "soon", "?d"],
+            vec!["--palindrome", "--minlen", "1", "?d?d"],
+            vec!["--length-order", "likely", "?d"],
+            vec!["--compression-ratio", "0", "?d"],
+        ] {
+            let err = run(&args).unwrap_err();
+            assert_eq!(err.exit_code(), 2, "{:?}: {}", args, err);
+            assert!(!Path::new(out_fname).exists(), "{:?}", args);
+            assert!(!marker.exists(), "{:?}", args);
+        }
+        // so is a run taking more than the free space of the disk
+        let err = run(&["--yes-i-know", "?b?b?b?b?b?b?b?b"]).unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        assert!(
+            err.to_string().starts_with("not enough space for "),
+            "{}",
+            err
+        );
+        assert!(!Path::new(out_fname).exists());
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn test_run_atomic() {
+        let out_fname = std::env::temp_dir().join("cracken-test-atomic.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let partial = format!("{}.partial", out_fname);
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--atomic", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))
+        };
+        let _ = std::fs::remove_file(out_fname);
+
+        // a run refused by a guard creates no file
+        let err = run(&["--max-keyspace", "99", "?d?d?d"]).unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        assert!(!Path::new(out_fname).exists());
+        assert!(!Path::new(&partial).exists());
+
+        // a run failing after the output was created never writes the output file
+        let failing = ["--tee-best-effort", "--pipe-to", "exit 7", "?d?d?d"];
+        let err = run(&failing).unwrap_err();
+        assert_eq!(err.exit_code(), 7);
+        assert!(!Path::new(out_fname).exists());
+        assert_eq!(std::fs::read_to_string(&partial).unwrap().len(), 4000);
+        let err = run(&[&["--no-keep-partial"][..], &failing].concat()).unwrap_err();
+        assert_eq!(err.exit_code(), 7);
+        assert!(!Path::new(out_fname).exists());
+        assert!(!Path::new(&partial).exists());
+
+        // the partial file of an earlier run is replaced
+        std::fs::write(&partial, "stale\n").unwrap();
+        assert!(run(&["?d?d"]).is_ok());
+        assert_eq!(std::fs::read_to_string(out_fname).unwrap().len(), 300);
+        assert!(!Path::new(&partial).exists());
+
+        // the existing output is kept until the new one is complete
+        let err = run(&["--force", "--max-keyspace", "99", "?d?d?d"]).unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        assert_eq!(std::fs::read_to_string(out_fname).unwrap().len(), 300);
+        assert!(run(&["--force", "?d"]).is_ok());
+        assert_eq!(std::fs::read_to_string(out_fname).unwrap().len(), 20);
+        assert!(!Path::new(&partial).exists());
+        std::fs::remove_file(out_fname).unwrap();
+
+        // devices are written in place
+        let args = vec!["cracken", "--atomic", "-o", "/dev/null", "?d"];
+        assert!(runner::run(Some(args)).is_ok());
+        assert!(!Path::new("/dev/null.partial").exists());
+
+        for args in [
+            vec!["cracken", "--atomic", "--append", "-o", out_fname, "?d"],
+            vec!["cracken", "--atomic", "--stats", "-o", out_fname, "?d"],
+            vec!["cracken", "--no-keep-partial", "-o", out_fname, "?d"],
+        ] {
+            let err = runner::run(Some(args)).unwrap_err();
+            assert!(matches!(err, RunError::Args(_)), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn test_run_verify() {
+        let wordlist = test_util::wordlist_fname("wordlist1.txt");
+        let wordlist = wordlist.to_str().unwrap();
+        for args in [
+            vec!["?d?d?d"],
+            vec!["-w", wordlist, "?l?w1"],
+            vec!["-c", "ab", "?1?d?1"],
+            vec!["--minlen", "1", "?d?d?d"],
+        ] {
+            let mut run_args = vec!["cracken", "--verify"];
+            run_args.extend(args);
+            assert!(
+                runner::run(Some(run_args.clone())).is_ok(),
+                "{:?}",
+                run_args
+            );
+        }
+        // the output file isn't created
+        let fname = std::env::temp_dir().join("cracken-test-verify-output.txt");
+        let _ = std::fs::remove_file(&fname);
+        let args = vec!["cracken", "--verify", "-o", fname.to_str().unwrap(), "?d"];
+        assert!(runner::run(Some(args)).is_ok());
+        assert!(!fname.exists());
+
+        // a wordlist with a repeated word generates it twice, fewer distinct words than words
+        let fname = std::env::temp_dir().join("cracken-test-verify-duplicates.txt");
+        std::fs::write(&fname, "abc\ndef\nabc\n").unwrap();
+        let args = vec![
+            "cracken",
+            "--verify",
+            "-w",
+            fname.to_str().unwrap(),
+            "?w1?d",
+        ];
+        let err = runner::run(Some(args)).unwrap_err();
+        std::fs::remove_file(&fname).unwrap();
+        assert!(matches!(err, RunError::Verify(_)), "{:?}", err);
+        assert_eq!(err.exit_code(), 6);
+        assert_eq!(err.to_string(), "--verify: 1 of 1 masks failed");
+
+        let args = vec!["cracken", "--verify", "--verify-max", "999", "?d?d?d?d"];
+        let err = runner::run(Some(args)).unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        assert_eq!(
+            err.to_string(),
+            "--verify: the masks generate 10000 words, more than --verify-max 999"
+        );
+        let args = vec!["cracken", "--verify", "--verify-max", "10000", "?d?d?d?d"];
+        assert!(runner::run(Some(args)).is_ok());
+        let args = vec!["cracken", "--verify", "--seq-max", "2", "?d?d?d"];
+        assert_eq!(runner::run(Some(args)).unwrap_err().exit_code(), 2);
+    }
+
+    #[test]
+    fn test_run_max_wordlist_memory() {
+        let wordlist = test_util::wordlist_fname("wordlist1.txt");
+        let wordlist = wordlist.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "-s", "-w", wordlist];
+            run_args.extend(args);
+            runner::run(Some(run_args))
+        };
+        let err = run(&["--max-wordlist-memory", "10", "?w1"]).unwrap_err();
+        assert!(matches!(err, RunError::Generator(_)), "{}", err);
+        let msg = err.to_string();
+        assert!(msg.contains(wordlist), "{}", msg);
+        assert!(msg.contains(", limit is 10 B;"), "{}", msg);
+        // prints the wordlists memory
+        assert!(run(&["--max-wordlist-memory", "1M", "?w1"]).is_ok());
+        assert!(run(&["--max-wordlist-memory", "1X", "?w1"]).is_err());
+    }
+
+    #[test]
+    fn test_run_keyspace_hashcat() {
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "-s", "--keyspace-hashcat"];
+            run_args.extend(args);
+            runner::run(Some(run_args))
+        };
+        assert!(run(&["?d?d?d?d?d?d?d?d"]).is_ok());
+        assert!(run(&["-c", "ab", "?1?1?1"]).is_ok());
+        let err = run(&["-m", "2", "?d?d?d"]).unwrap_err();
+        assert!(matches!(err, RunError::Usage(_)));
+        let err = run(&["?K{3-5}?d"]).unwrap_err();
+        assert!(matches!(
+            err,
+            RunError::Generator(CrackenError::Unsupported(_))
+        ));
+        // requires --stats
+        let args = vec!["cracken", "--keyspace-hashcat", "?d"];
+        assert!(matches!(runner::run(Some(args)), Err(RunError::Args(_))));
+    }
+
+    #[test]
+    fn test_run_keywalk_stats() {
+        let args = Some(vec!["cracken", "-s", "?K{3-5,shift}?d"]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_dates_stats() {
+        let args = Some(vec!["cracken", "--stats", "?D{MMDDYYYY,1990-1999}"]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_perm_denied() {
+        let args = Some(vec!["cracken", "-o", "/tmp/this/dir/not/exisT", "?d"]);
+        assert!(runner::run(args).is_err());
+    }
+
+    #[test]
+    fn test_run_existing_output_file() {
+        let out_fname = std::env::temp_dir().join("cracken-test-existing.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        std::fs::write(out_fname, "keep me\n").unwrap();
+
+        let args = vec!["cracken", "-o", out_fname, "?d"];
+        let err = runner::run(Some(args)).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            format!(
+                "output file {} exists, pass --force to overwrite (or --append to extend)",
+                out_fname
+            )
+        );
+        assert_eq!(std::fs::read_to_string(out_fname).unwrap(), "keep me\n");
+
+        let args = vec!["cracken", "--force", "-o", out_fname, "?d"];
+        assert!(runner::run(Some(args)).is_ok());
+        assert_eq!(std::fs::read_to_string(out_fname).unwrap().len(), 20);
+        std::fs::remove_file(out_fname).unwrap();
+
+        // devices are not refused
+        for _ in 0..2 {
+            let args = vec!["cracken", "-o", "/dev/null", "?d"];
+            assert!(runner::run(Some(args)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_run_verbose_summary() {
+        let args = Some(vec!["cracken", "-v", "-o", "/dev/null", "?d?d"]);
+        assert!(runner::run(args).is_ok());
+        let args = Some(vec!["cracken", "-q", "-v", "?d"]);
+        assert!(runner::run(args).is_err());
+
+        let word_generator = get_word_generator("?d?d", None, None, &[], &[]).unwrap();
+        let mut out = vec![];
+        let output_bytes = word_generator.output_bytes();
+        runner::write_banner(
+            &mut out,
+            "?d?d",
+            word_generator.as_ref(),
+            &output_bytes,
+            &["stdout".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "mask: ?d?d\nkeyspace: 100\nestimated size: 300 bytes\noutput: stdout\n"
+        );
+
+        let counts = WriteCounts::default();
+        counts.lines.set(100);
+        counts.bytes.set(300);
+        let mut out = vec![];
+        runner::write_summary(&mut out, &counts, Duration::from_millis(2500), None).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "words written: 100\nbytes written: 300\nelapsed: 2.50s\nrate: 40 words/s\n"
+        );
+        let mut out = vec![];
+        runner::write_summary(&mut out, &counts, Duration::from_millis(2500), Some(50)).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .ends_with("\nrate: 40 words/s (max 50)\n"));
+    }
+
+    #[test]
+    fn test_run_config_profile() {
+        let config_fname = test_util::wordlist_fname("config.toml");
+        let config_fname = config_fname.to_str().unwrap();
+        let out_fname = std::env::temp_dir().join("cracken-test-config.txt");
+        let out_fname = out_fname.to_str().unwrap();
+
+        let run = |extra_args: &[&str]| {
+            let mut args = vec![
+                "cracken",
+                "--config",
+                config_fname,
+                "--force",
+                "-o",
+                out_fname,
+            ];
+            args.extend(extra_args);
+            runner::run(Some(args)).unwrap();
+            std::fs::read_to_string(out_fname).unwrap()
+        };
+        // [digits] overrides the [default] custom charset
+        assert_eq!(run(&["-P", "digits", "?1?1"]), "00\n01\n10\n11\n");
+        // command line flags override the profile
+        assert_eq!(
+            run(&["-P", "digits", "-c", "ab", "?1?1"]),
+            "aa\nab\nba\nbb\n"
+        );
+        // the chars of a custom charset are sorted
+        assert_eq!(run(&["?1"]), "!\n#\n$\n@\n");
+        // --no-config conflicts with --config, it ignores the default config file
+        let args = vec![
+            "cracken",
+            "--no-config",
+            "--force",
+            "-o",
+            out_fname,
+            "-c",
+            "xy",
+            "?1",
+        ];
+        runner::run(Some(args)).unwrap();
+        assert_eq!(std::fs::read_to_string(out_fname).unwrap(), "x\ny\n");
+        std::fs::remove_file(out_fname).unwrap();
+
+        let args = vec!["cracken", "--config", config_fname, "-P", "nope", "?d"];
+        let err = runner::run(Some(args)).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            format!("profile 'nope' not found in config file {}", config_fname)
+        );
+        let args = vec!["cracken", "--config", "/tmp/this/dir/not/exisT", "?d"];
+        let err = runner::run(Some(args)).unwrap_err().to_string();
+        assert!(
+            err.starts_with("cannot read config file /tmp/this/dir/not/exisT"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_run_pipe_to() {
+        let out_fname = std::env::temp_dir().join("cracken-test-pipe-to.txt");
+        let out_fname = out_fname.to_str().unwrap();
+
+        let command = format!("wc -l > {}", out_fname);
+        let args = vec!["cracken", "--pipe-to", &command, "?d?d"];
+        runner::run(Some(args)).unwrap();
+        let out = std::fs::read_to_string(out_fname).unwrap();
+        assert_eq!(out.trim(), "100");
+
+        // the command exiting before reading everything is a clean stop
+        let command = format!("head -n 5 > {}", out_fname);
+        let args = vec!["cracken", "--pipe-to", &command, "?d?d?d?d?d?d"];
+        runner::run(Some(args)).unwrap();
+        let out = std::fs::read_to_string(out_fname).unwrap();
+        assert_eq!(out, "000000\n000001\n000002\n000003\n000004\n");
+        std::fs::remove_file(out_fname).unwrap();
+
+        let args = vec!["cracken", "--pipe-to", "exit 7", "?d?d?d?d?d?d"];
+        let err = runner::run(Some(args)).unwrap_err();
+        assert_eq!(err.exit_code(), 7);
+        assert_eq!(err.to_string(), "--pipe-to command 'exit 7' exited with 7");
+
+        // -o and --pipe-to both get the words
+        let args = vec![
+            "cracken",
+            "--pipe-to",
+            "cat > /dev/null",
+            "-o",
+            "/dev/null",
+            "?d",
+        ];
+        assert!(runner::run(Some(args)).is_ok());
+    }
+
+    #[test]
+    fn test_run_tee() {
+        let out_fnames: Vec<_> = ["cracken-test-tee-1.txt", "cracken-test-tee-2.txt"]
+            .iter()
+            .map(|fname| std::env::temp_dir().join(fname))
+            .collect();
+        let (out1, out2) = (
+            out_fnames[0].to_str().unwrap(),
+            out_fnames[1].to_str().unwrap(),
+        );
+        let piped_fname = std::env::temp_dir().join("cracken-test-tee-piped.txt");
+        let command = format!("cat > {}", piped_fname.to_str().unwrap());
+
+        let args = vec![
+            "cracken",
+            "--force",
+            "-o",
+            out1,
+            "-o",
+            out2,
+            "--pipe-to",
+            &command,
+        ];
+        let mut run_args = args.clone();
+        run_args.push("?d?d?d?d");
+        runner::run(Some(run_args)).unwrap();
+        let words = std::fs::read(out1).unwrap();
+        assert_eq!(words.len(), 10000 * 5);
+        assert_eq!(std::fs::read(out2).unwrap(), words);
+        assert_eq!(std::fs::read(&piped_fname).unwrap(), words);
+
+        // a command exiting early fails the run, other than with a single output
+        let args = vec!["cracken", "--force", "-o", out1, "--pipe-to", "exit 0"];
+        let mut run_args = args.clone();
+        run_args.push("?d?d?d?d?d?d");
+        let err = runner::run(Some(run_args)).unwrap_err();
+        assert!(matches!(err, RunError::Output(_)));
+        assert!(
+            err.to_string().contains("--pipe-to command 'exit 0': "),
+            "{}",
+            err
+        );
+        let mut run_args = args.clone();
+        run_args.extend(["--tee-best-effort", "?d?d?d?d?d?d"]);
+        runner::run(Some(run_args)).unwrap();
+        assert_eq!(std::fs::metadata(out1).unwrap().len(), 1_000_000 * 7);
+
+        let args = vec!["cracken", "--force", "-o", out1, "-o", out1, "?d"];
+        assert!(matches!(runner::run(Some(args)), Err(RunError::Usage(_))));
+        for fname in out_fnames.iter().chain([&piped_fname]) {
+            std::fs::remove_file(fname).unwrap();
+        }
+    }
 
     #[test]
-    fn test_run_generate_smoke() {
-        for args in vec![vec!["cracken", "generate", "?d"], vec!["cracken", "?d"]] {
-            assert!(runner::run(Some(args)).is_ok());
+    fn test_run_skip() {
+        let out_fname = std::env::temp_dir().join("cracken-test-skip.txt");
+        let out_fname = out_fname.to_str().unwrap();
+
+        let run = |skip: &str, mask: &str| {
+            let args = vec!["cracken", "--force", "--skip", skip, "-o", out_fname, mask];
+            runner::run(Some(args)).unwrap();
+            std::fs::read_to_string(out_fname).unwrap()
+        };
+        assert_eq!(run("95", "?d?d"), "95\n96\n97\n98\n99\n");
+        assert_eq!(run("0", "?d").len(), 20);
+        assert_eq!(run("100", "?d?d"), "");
+
+        // the masks before the first word are skipped, the same words as generating and
+        // discarding them (--seq-max 2 filters none of them)
+        let masks_fname = std::env::temp_dir().join("cracken-test-skip-masks.txt");
+        std::fs::write(&masks_fname, "?d\nx?d?d\ny?d\n").unwrap();
+        let masks = masks_fname.to_str().unwrap();
+        let run_masks = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname, "-i", masks];
+            run_args.extend(args);
+            runner::run(Some(run_args)).unwrap();
+            std::fs::read_to_string(out_fname).unwrap()
+        };
+        let full = run_masks(&[]);
+        let lines: Vec<_> = full.lines().collect();
+        for skip in [0, 9, 10, 57, 109, 110, 119, 120, 500] {
+            let skip_arg = skip.to_string();
+            let expected: String = lines
+                .iter()
+                .skip(skip)
+                .map(|w| format!("{}\n", w))
+                .collect();
+            assert_eq!(run_masks(&["--skip", &skip_arg]), expected, "{}", skip);
+            let discarded = run_masks(&["--skip", &skip_arg, "--seq-max", "2"]);
+            assert_eq!(discarded, expected, "{}", skip);
         }
+        std::fs::remove_file(masks_fname).unwrap();
+        std::fs::remove_file(out_fname).unwrap();
     }
 
     #[test]
-    fn test_run_entropy_smoke() {
-        let vocab_fname = test_util::wordlist_fname("vocab.txt");
-        let args = Some(vec![
+    fn test_run_start_from() {
+        let out_fname = std::env::temp_dir().join("cracken-test-start-from.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let wordlist_fname = std::env::temp_dir().join("cracken-test-start-from-words.txt");
+        // 121 is both 1+21 and 12+1
+        std::fs::write(&wordlist_fname, "1\n12\n2\n21\n").unwrap();
+        let wordlist = wordlist_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+
+        for args in [
+            &["-m", "1", "-c", "ab1", "?1?1?1"][..],
+            &["-w", wordlist, "?w1?w1"],
+        ] {
+            let full = run(args).unwrap();
+            let words: Vec<_> = full.lines().collect();
+            for word in words.iter() {
+                // the tail after the first occurrence of the word
+                let first = words.iter().position(|w| w == word).unwrap();
+                let expected: String = words[first + 1..]
+                    .iter()
+                    .map(|w| format!("{}\n", w))
+                    .collect();
+                let mut start_args = args.to_vec();
+                start_args.extend(["--start-from", word]);
+                assert_eq!(run(&start_args).unwrap(), expected, "{:?} {}", args, word);
+            }
+        }
+
+        // the masks before the one generating the word are skipped
+        let masks_fname = std::env::temp_dir().join("cracken-test-start-from-masks.txt");
+        std::fs::write(&masks_fname, "?d\nx?d\ny?d\n").unwrap();
+        let masks = masks_fname.to_str().unwrap();
+        assert_eq!(
+            run(&["-i", masks, "--start-from", "x8"]).unwrap(),
+            "x9\ny0\ny1\ny2\ny3\ny4\ny5\ny6\ny7\ny8\ny9\n"
+        );
+        assert_eq!(run(&["--start-from", "$HEX[38]", "?d"]).unwrap(), "9\n");
+
+        let err = run(&["-i", masks, "--start-from", "z1"]).unwrap_err();
+        assert!(matches!(err, RunError::Usage(_)));
+        assert_eq!(
+            err.to_string(),
+            "--start-from word z1 is not generated by the masks"
+        );
+        assert!(matches!(
+            run(&["--skip", "1", "--start-from", "1", "?d"]),
+            Err(RunError::Args(_))
+        ));
+        assert!(run(&["--seq-max", "1", "--start-from", "1", "?d"]).is_err());
+        std::fs::remove_file(masks_fname).unwrap();
+        std::fs::remove_file(wordlist_fname).unwrap();
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_end_at() {
+        let out_fname = std::env::temp_dir().join("cracken-test-end-at.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+
+        // a middle slice of the 3 chars words of -m 2 (100 words of length 2 first)
+        let full = run(&["-m", "2", "?d?d?d"]).unwrap();
+        let words: Vec<_> = full.lines().collect();
+        let slice = run(&[
+            "-m",
+            "2",
+            "--start-from",
+            "099",
+            "--end-at",
+            "123",
+            "?d?d?d",
+        ])
+        .unwrap();
+        let expected: Vec<_> = words[200..=223].to_vec();
+        assert_eq!(slice.lines().collect::<Vec<_>>(), expected);
+        assert_eq!(run(&["--end-at", "3", "?d"]).unwrap(), "0\n1\n2\n3\n");
+        assert_eq!(
+            run(&["--start-from", "3", "--end-at", "3", "?d"]).unwrap(),
+            ""
+        );
+
+        // the range spans masks
+        let masks_fname = std::env::temp_dir().join("cracken-test-end-at-masks.txt");
+        std::fs::write(&masks_fname, "?d\nx?d\ny?d\nz?d\n").unwrap();
+        let masks = masks_fname.to_str().unwrap();
+        assert_eq!(
+            run(&["-i", masks, "--start-from", "x8", "--end-at", "z0"]).unwrap(),
+            "x9\ny0\ny1\ny2\ny3\ny4\ny5\ny6\ny7\ny8\ny9\nz0\n"
+        );
+
+        let err = run(&["--start-from", "5", "--end-at", "3", "?d"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--end-at word 3 comes before --start-from word 5"
+        );
+        let err = run(&["-i", masks, "--start-from", "y1", "--end-at", "x1"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--end-at word x1 is not generated by the masks after --start-from y1"
+        );
+        assert!(matches!(
+            run(&["--end-at", "a", "?d"]),
+            Err(RunError::Usage(_))
+        ));
+
+        let args = vec![
             "cracken",
-            "entropy",
-            "--smartlist",
-            vocab_fname.to_str().unwrap(),
-            "helloworld123!",
-        ]);
-        assert!(runner::run(args).is_ok());
+            "-s",
+            "-i",
+            masks,
+            "--start-from",
+            "x8",
+            "--end-at",
+            "z0",
+        ];
+        assert!(runner::run(Some(args)).is_ok());
+        let mut out = vec![];
+        let rates = vec![("10H".to_string(), 10)];
+        runner::write_range_stats(&mut out, &12.to_biguint().unwrap(), &rates, false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "12 (range)\nbits: 3.58\ntime at 10H: 1 second\n"
+        );
+        std::fs::remove_file(masks_fname).unwrap();
+        std::fs::remove_file(out_fname).unwrap();
     }
 
     #[test]
-    fn test_run_dev_null() {
-        let args = Some(vec!["cracken", "-o", "/dev/null", "?d"]);
-        assert!(runner::run(args).is_ok());
+    fn test_run_slice() {
+        let out_fname = std::env::temp_dir().join("cracken-test-slice.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let masks_fname = std::env::temp_dir().join("cracken-test-slice-masks.txt");
+        std::fs::write(&masks_fname, "?d\nx?d?d\ny?d\n").unwrap();
+        let masks = masks_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+
+        // the shares generate every word of the masks once and in order, more shares than
+        // words leave some of them empty
+        let full = run(&["-i", masks]).unwrap();
+        for shards in [1, 3, 7, 120, 200] {
+            let mut words = String::new();
+            let mut sizes = vec![];
+            for i in 1..=shards {
+                let slice = format!("{}/{}", i, shards);
+                let share = run(&["-i", masks, "--slice", &slice]).unwrap();
+                sizes.push(share.lines().count());
+                words.push_str(&share);
+            }
+            assert_eq!(words, full, "{}", shards);
+            let (min, max) = (sizes.iter().min().unwrap(), sizes.iter().max().unwrap());
+            assert!(max - min <= 1, "{} {:?}", shards, sizes);
+        }
+        assert_eq!(
+            run(&["--slice", "2/4", "?d?d"]).unwrap().lines().next(),
+            Some("25")
+        );
+
+        for slice in ["0/4", "5/4", "1/0", "1", "a/b"] {
+            let err = run(&["--slice", slice, "?d"]).unwrap_err();
+            assert!(matches!(err, RunError::Usage(_)), "{}", slice);
+        }
+        assert!(matches!(
+            run(&["--slice", "1/2", "--start-from", "1", "?d"]),
+            Err(RunError::Args(_))
+        ));
+        assert!(run(&["--slice", "1/2", "--seq-max", "1", "?d"]).is_err());
+        std::fs::remove_file(masks_fname).unwrap();
+        std::fs::remove_file(out_fname).unwrap();
     }
 
     #[test]
-    fn test_run_custom_charset() {
-        let args = Some(vec!["cracken", "-c=abcdef0123456789", "?1"]);
-        assert!(runner::run(args).is_ok());
+    fn test_run_exclude() {
+        let out_fname = std::env::temp_dir().join("cracken-test-exclude.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let exclude_fname = std::env::temp_dir().join("cracken-test-exclude-words.txt");
+        let exclude = exclude_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+
+        // excluding the even words leaves exactly the odd ones
+        let words: Vec<_> = (0..100).map(|i| format!("{:02}", i)).collect();
+        let even: String = words
+            .iter()
+            .step_by(2)
+            .map(|w| format!("{}\n", w))
+            .collect();
+        let odd: String = words
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .map(|w| format!("{}\n", w))
+            .collect();
+        std::fs::write(&exclude_fname, &even).unwrap();
+        assert_eq!(run(&["--exclude", exclude, "?d?d"]).unwrap(), odd);
+        // the bloom filter has no false negatives, a false positive of 50 words is unlikely
+        assert_eq!(
+            run(&["--exclude", exclude, "--exclude-bloom", "?d?d"]).unwrap(),
+            odd
+        );
+        // windows line endings and --output-hex words
+        std::fs::write(&exclude_fname, "a\r\nb\n").unwrap();
+        assert_eq!(
+            run(&["--exclude", exclude, "-c", "abc", "?1"]).unwrap(),
+            "c\n"
+        );
+        assert_eq!(
+            run(&["--exclude", exclude, "--output-hex", "-c", "abc", "?1"]).unwrap(),
+            "c\n"
+        );
+
+        assert!(matches!(
+            run(&["--exclude", "/no/such/file", "?d"]),
+            Err(RunError::Input(_))
+        ));
+        assert!(run(&["--exclude-bloom", "?d"]).is_err());
+        std::fs::remove_file(exclude_fname).unwrap();
+        std::fs::remove_file(out_fname).unwrap();
     }
 
     #[test]
-    fn test_run_stats() {
-        let args = Some(vec!["cracken", "-s", "?d?s?u?l?a?b"]);
-        assert!(runner::run(args).is_ok());
+    fn test_run_shuffle() {
+        let out_fname = std::env::temp_dir().join("cracken-test-shuffle.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+
+        let shuffled = run(&["--shuffle", "--seed", "5", "?d?d"]).unwrap();
+        assert_eq!(
+            shuffled,
+            run(&["--shuffle", "--seed", "5", "?d?d"]).unwrap()
+        );
+        let mut words: Vec<_> = shuffled.lines().collect();
+        assert_ne!(words[..3], ["00", "01", "02"]);
+        words.sort();
+        let expected: Vec<_> = (0..100).map(|i| format!("{:02}", i)).collect();
+        assert_eq!(words, expected);
+        // the same seed resumes the order
+        let resumed = run(&["--shuffle", "--seed", "5", "--skip", "40", "?d?d"]).unwrap();
+        assert_eq!(
+            resumed,
+            shuffled
+                .lines()
+                .skip(40)
+                .map(|w| format!("{}\n", w))
+                .collect::<String>()
+        );
+
+        assert!(run(&["--shuffle", "?d"]).is_err());
+        assert!(run(&[
+            "--shuffle",
+            "--seed",
+            "1",
+            "--length-order",
+            "desc",
+            "-m",
+            "1",
+            "?d?d"
+        ])
+        .is_err());
+        std::fs::remove_file(out_fname).unwrap();
     }
 
     #[test]
-    fn test_run_perm_denied() {
-        let args = Some(vec!["cracken", "-o", "/tmp/this/dir/not/exisT", "?d"]);
-        assert!(runner::run(args).is_err());
+    fn test_write_shard_commands() {
+        let total = 1000.to_biguint().unwrap();
+        let argv: Vec<String> = [
+            "cracken",
+            "-o",
+            "out file.txt",
+            "--distribute",
+            "3",
+            "?d?d?d",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let mut out = vec![];
+        runner::write_shard_commands(&mut out, &argv, 3, &total, false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "cracken -o 'out file.txt' '?d?d?d' --slice 1/3\n\
+             cracken -o 'out file.txt' '?d?d?d' --slice 2/3\n\
+             cracken -o 'out file.txt' '?d?d?d' --slice 3/3\n"
+        );
+
+        // the commands parse to the same args with --slice instead of --distribute
+        let argv: Vec<String> = [
+            "cracken",
+            "generate",
+            "--distribute=4",
+            "-c",
+            "it's",
+            "--format",
+            "json",
+            "--",
+            "?1?1",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let mut out = vec![];
+        runner::write_shard_commands(&mut out, &argv, 4, &total, true).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let shares: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(shares.as_array().unwrap().len(), 4);
+        assert_eq!(
+            shares[1],
+            serde_json::json!({
+                "slice": "2/4",
+                "start": "250",
+                "words": "250",
+                "command": ["cracken", "generate", "-c", "it's", "--slice", "2/4", "--", "?1?1"],
+            })
+        );
+        let command = vec![
+            "cracken", "generate", "-c", "it's", "--slice", "2/4", "--", "?1?1",
+        ];
+        let parsed = runner::parse_args(Some(command)).unwrap();
+        let matches = parsed.matches.subcommand_matches("generate").unwrap();
+        assert_eq!(matches.value_of("slice"), Some("2/4"));
+        assert_eq!(matches.value_of("custom-charset"), Some("it's"));
+        assert_eq!(matches.value_of("mask"), Some("?1?1"));
+        assert!(!matches.is_present("distribute") && !matches.is_present("format"));
+
+        // the shares of an uneven total differ by at most a word and cover it exactly
+        let total = 10.to_biguint().unwrap();
+        let mut next = 0.to_biguint().unwrap();
+        for i in 1..=4 {
+            let (start, end) = runner::slice_range(&total, (i, 4));
+            assert_eq!(start, next);
+            let words = end.clone() - start;
+            assert!(words == 2u32.into() || words == 3u32.into());
+            next = end;
+        }
+        assert_eq!(next, total);
+
+        assert_eq!(runner::shell_quote("-m=3,hex:4"), "-m=3,hex:4");
+        assert_eq!(runner::shell_quote("?d?d"), "'?d?d'");
+        assert_eq!(runner::shell_quote(""), "''");
+        assert_eq!(runner::shell_quote("a b$"), "'a b$'");
+        assert_eq!(runner::shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_run_format_jsonl() {
+        let masks_fname = std::env::temp_dir().join("cracken-test-jsonl-masks.txt");
+        let masks = masks_fname.to_str().unwrap();
+        let out_fname = std::env::temp_dir().join("cracken-test-jsonl.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        std::fs::write(&masks_fname, "?d?l\nx\"?d\n").unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        // (pw, len, index) of every json line
+        let parse = |out: String| -> Vec<(String, usize, u64)> {
+            out.lines()
+                .map(|line| {
+                    let rest = line.strip_prefix("{\"pw\":\"").unwrap();
+                    let (pw, rest) = rest.split_once("\",\"len\":").unwrap();
+                    let (len, rest) = rest.split_once(",\"index\":").unwrap();
+                    let index = rest.strip_suffix('}').unwrap();
+                    (
+                        pw.replace("\\\"", "\""),
+                        len.parse().unwrap(),
+                        index.parse().unwrap(),
+                    )
+                })
+                .collect()
+        };
+
+        let plain = run(&["-i", masks]).unwrap();
+        let words = parse(run(&["--format", "jsonl", "-i", masks]).unwrap());
+        assert_eq!(words.len(), 270);
+        for (idx, (line, (pw, len, index))) in plain.lines().zip(words).enumerate() {
+            assert_eq!(pw, line);
+            assert_eq!(len, line.len());
+            // the index starts over on every mask
+            assert_eq!(
+                index,
+                if idx < 260 {
+                    idx as u64
+                } else {
+                    idx as u64 - 260
+                }
+            );
+        }
+
+        let words = parse(run(&["--format", "jsonl", "--start-from", "9y", "-i", masks]).unwrap());
+        assert_eq!(words[0], ("9z".to_string(), 2, 259));
+        assert_eq!(words[1], ("x\"0".to_string(), 3, 0));
+
+        let out = run(&["--format", "jsonl", "--prefix", "\\xff", "?d"]).unwrap();
+        assert!(out.starts_with("{\"hex\":\"ff30\",\"len\":2,\"index\":0}\n"));
+        let err = run(&["--format", "jsonl", "--output-hex", "?d"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--format jsonl doesn't support --output-hex, every word is written with its index in the generation order"
+        );
+        std::fs::remove_file(masks_fname).unwrap();
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_with_index() {
+        let out_fname = std::env::temp_dir().join("cracken-test-with-index.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname, "--with-index"];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        let out = run(&["?d?d?d?d"]).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 10000);
+        assert_eq!(lines[0], "0\t0000");
+        assert_eq!(lines[42], "42\t0042");
+        assert_eq!(lines[9999], "9999\t9999");
+        assert_eq!(
+            out.len() as u64,
+            10000 * 5 + index_bytes(0, &BigUint::from(10000u32)).to_u64_digits()[0]
+        );
+
+        let out = run(&["--skip", "100", "?d?d?d?d"]).unwrap();
+        assert!(out.starts_with("100\t0100\n101\t0101\n"));
+        assert!(out.ends_with("9999\t9999\n"));
+        let out = run(&["--start-from", "0041", "?d?d?d?d"]).unwrap();
+        assert!(out.starts_with("42\t0042\n"));
+
+        let err = run(&["--format", "jsonl", "?d"]).unwrap_err();
+        assert!(matches!(err, RunError::Usage(_)));
+        let err = run(&["--seq-max", "2", "?d"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--with-index doesn't support --seq-max and --occurrence-max, every word is written with its index in the generation order"
+        );
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_prefix_suffix() {
+        let out_fname = std::env::temp_dir().join("cracken-test-prefix.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read(out_fname).unwrap())
+        };
+
+        let expected = run(&["pwd?d?d"]).unwrap();
+        assert_eq!(run(&["--prefix", "pwd", "?d?d"]).unwrap(), expected);
+        let expected = [run(&["p?d!"]).unwrap(), run(&["p?d?d!"]).unwrap()].concat();
+        assert_eq!(
+            run(&["--prefix", "p", "--suffix", "!", "-m", "1", "?d?d"]).unwrap(),
+            expected
+        );
+        // escapes, wordlists masks and --start-from/--end-at windows
+        let wordlist = test_util::wordlist_fname("wordlist1.txt");
+        let words = run(&["-w", wordlist.to_str().unwrap(), "?w1"]).unwrap();
+        let expected: Vec<u8> = words
+            .split(|&b| b == b'\n')
+            .filter(|w| !w.is_empty())
+            .flat_map(|w| [b"\x00".as_ref(), w, b"\\\n"].concat())
+            .collect();
+        let args = ["--prefix", "\\x00", "--suffix", "\\\\"];
+        let mut affixed_args = args.to_vec();
+        affixed_args.extend(["-w", wordlist.to_str().unwrap(), "?w1"]);
+        assert_eq!(run(&affixed_args).unwrap(), expected);
+        assert_eq!(
+            run(&["--suffix", "x", "--start-from", "3", "--end-at", "5", "?d"]).unwrap(),
+            b"4x\n5x\n"
+        );
+
+        // the stats count the prefix
+        let build = |mask: &str| get_word_generator(mask, None, None, &[], &[]).unwrap();
+        let prefixed = affix(build("?d?d"), b"pwd", b"").unwrap();
+        let literal = build("pwd?d?d");
+        assert_eq!(prefixed.output_bytes(), literal.output_bytes());
+        assert_eq!(
+            prefixed.length_combinations(),
+            literal.length_combinations()
+        );
+
+        assert!(matches!(
+            run(&["--prefix", "\\q", "?d"]),
+            Err(RunError::Usage(_))
+        ));
+        assert!(matches!(
+            run(&["--suffix", "\\x0a", "?d"]),
+            Err(RunError::Generator(_))
+        ));
+        assert!(matches!(
+            run(&["--output-hex", "--prefix", "a", "?d"]),
+            Err(RunError::Args(_))
+        ));
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_interleave() {
+        let out_fname = std::env::temp_dir().join("cracken-test-interleave.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let masks_fname = std::env::temp_dir().join("cracken-test-interleave-masks.txt");
+        std::fs::write(&masks_fname, "?d?d\na?d?d\nb?d?d\nc?d\n").unwrap();
+        let masks = masks_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname, "-i", masks];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+
+        let sequential = run(&[]).unwrap();
+        let interleaved = run(&["--interleave", "--interleave-chunk", "40"]).unwrap();
+        assert_ne!(interleaved, sequential);
+        let mut sequential: Vec<_> = sequential.lines().collect();
+        let mut words: Vec<_> = interleaved.lines().collect();
+        assert_eq!(words.len(), sequential.len());
+        // the first 3 chunks come from the first 3 masks
+        assert!(words[..40].iter().all(|w| w.len() == 2));
+        assert!(words[40..80].iter().all(|w| w.starts_with('a')));
+        assert!(words[80..120].iter().all(|w| w.starts_with('b')));
+        assert_eq!(
+            words[120..130],
+            ["c0", "c1", "c2", "c3", "c4", "c5", "c6", "c7", "c8", "c9"]
+        );
+        // c?d is exhausted and left out of the next turns
+        assert_eq!(words[130..132], ["40", "41"]);
+        assert_eq!(words[170], "a40");
+        words.sort_unstable();
+        sequential.sort_unstable();
+        assert_eq!(words, sequential);
+
+        let err = run(&["--interleave", "--interleave-chunk", "0"]).unwrap_err();
+        assert!(matches!(err, RunError::Usage(_)));
+        assert!(matches!(
+            run(&["--interleave", "--start-from", "a00"]),
+            Err(RunError::Args(_))
+        ));
+        std::fs::remove_file(masks_fname).unwrap();
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_length_order() {
+        let out_fname = std::env::temp_dir().join("cracken-test-length-order.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let histogram_fname = std::env::temp_dir().join("cracken-test-length-histogram.txt");
+        let histogram = histogram_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname, "-m", "1"];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        let ones = run(&["?d"]).unwrap();
+        let ascending = run(&["?d?d"]).unwrap();
+        let twos = &ascending[ones.len()..];
+
+        assert_eq!(
+            run(&["--length-order", "desc", "?d?d"]).unwrap(),
+            format!("{}{}", twos, ones)
+        );
+        assert_eq!(
+            run(&["--length-order", "desc", "--start-from", "98", "?d?d"]).unwrap(),
+            format!("99\n{}", ones)
+        );
+        let interleaved = run(&[
+            "--length-order",
+            "interleave",
+            "--interleave-chunk",
+            "4",
+            "?d?d",
+        ])
+        .unwrap();
+        assert!(interleaved.starts_with("0\n1\n2\n3\n00\n01\n02\n03\n4\n"));
+        let mut words: Vec<_> = interleaved.lines().collect();
+        let mut expected: Vec<_> = ascending.lines().collect();
+        words.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(words, expected);
+
+        std::fs::write(&histogram_fname, "# length count\n1 12\n\n2 40\n3 40\n").unwrap();
+        assert_eq!(
+            run(&[
+                "--length-order",
+                "likely",
+                "--length-histogram",
+                histogram,
+                "?d?d"
+            ])
+            .unwrap(),
+            format!("{}{}", twos, ones)
+        );
+
+        let err = run(&["--length-order", "likely", "?d"]).unwrap_err();
+        assert!(matches!(err, RunError::Usage(_)));
+        let err = run(&["--length-histogram", histogram, "?d"]).unwrap_err();
+        assert!(matches!(err, RunError::Usage(_)));
+        let err = run(&["--length-order", "interleave", "--start-from", "1", "?d?d"]).unwrap_err();
+        assert!(matches!(err, RunError::Usage(_)));
+        std::fs::write(&histogram_fname, "1 12\n2\n").unwrap();
+        let err = run(&[
+            "--length-order",
+            "likely",
+            "--length-histogram",
+            histogram,
+            "?d",
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "invalid length histogram line '2' in {}: expected a length and a count",
+                histogram
+            )
+        );
+        std::fs::remove_file(histogram_fname).unwrap();
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_anagram() {
+        let out_fname = std::env::temp_dir().join("cracken-test-anagram.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        runner::run(Some(vec!["cracken", "anagram", "-o", out_fname, "aab"])).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(out_fname).unwrap(),
+            "aab\naba\nbaa\n"
+        );
+        let err = runner::run(Some(vec!["cracken", "anagram", ""])).unwrap_err();
+        assert!(matches!(
+            err,
+            RunError::Generator(CrackenError::InvalidLength(_))
+        ));
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_from_regex() {
+        let out_fname = std::env::temp_dir().join("cracken-test-from-regex.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "from-regex", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        assert_eq!(run(&["^(ab|c)[0-1]$"]).unwrap(), "ab0\nab1\nc0\nc1\n");
+        let err = run(&["[0-9]+"]).unwrap_err();
+        assert!(matches!(
+            err,
+            RunError::Generator(CrackenError::Unsupported(_))
+        ));
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_wordlist_merge() {
+        let first = test_util::wordlist_fname("merge-a.txt");
+        let first = first.to_str().unwrap();
+        let second = test_util::wordlist_fname("merge-b.txt");
+        let second = second.to_str().unwrap();
+        let out_fname = std::env::temp_dir().join("cracken-test-merge.txt");
+        let out_fname = out_fname.to_str().unwrap();
+
+        let args = vec![
+            "cracken", "wordlist", "merge", first, second, "-o", out_fname,
+        ];
+        runner::run(Some(args)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(out_fname).unwrap(),
+            "password\n123456\nqwerty\ndragon\nmonkey\nletmein\n"
+        );
+        // merging the output with itself would truncate it first
+        let args = vec![
+            "cracken", "wordlist", "merge", out_fname, first, "-o", out_fname,
+        ];
+        assert!(runner::run(Some(args)).is_err());
+        assert_eq!(
+            std::fs::read_to_string(out_fname).unwrap().lines().count(),
+            6
+        );
+
+        let args = vec![
+            "cracken",
+            "wordlist",
+            "merge",
+            "/no/such/file",
+            "-o",
+            out_fname,
+        ];
+        assert!(matches!(runner::run(Some(args)), Err(RunError::Input(_))));
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_wordlist_subtract() {
+        let base = test_util::wordlist_fname("merge-b.txt");
+        let base = base.to_str().unwrap();
+        let remove = test_util::wordlist_fname("merge-a.txt");
+        let remove = remove.to_str().unwrap();
+        let out_fname = std::env::temp_dir().join("cracken-test-subtract-out.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "wordlist", "subtract", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        let remaining = "dragon\nmonkey\nletmein\n";
+        assert_eq!(run(&[base, remove]).unwrap(), remaining);
+        assert_eq!(run(&["--bloom", base, remove]).unwrap(), remaining);
+        assert_eq!(run(&["-i", base, remove]).unwrap(), remaining);
+        assert!(matches!(
+            run(&[base, "/no/such/file"]),
+            Err(RunError::Input(_))
+        ));
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_wordlist_sort() {
+        let wordlist = test_util::wordlist_fname("merge-b.txt");
+        let wordlist = wordlist.to_str().unwrap();
+        let out_fname = std::env::temp_dir().join("cracken-test-sort-out.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "wordlist", "sort", "-q", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        let sorted = "123456\ndragon\nletmein\nmonkey\nqwerty\n";
+        assert_eq!(run(&[wordlist]).unwrap(), sorted);
+        // a chunk of about every line
+        assert_eq!(run(&["--memory", "40", wordlist]).unwrap(), sorted);
+        assert!(run(&["--memory", "0", wordlist]).is_err());
+        assert!(run(&[out_fname]).is_err());
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_train_order() {
+        let passwords = test_util::wordlist_fname("passwords.txt");
+        let model_fname = std::env::temp_dir().join("cracken-test-train.model");
+        let model_fname = model_fname.to_str().unwrap();
+        let out_fname = std::env::temp_dir().join("cracken-test-order.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        runner::run(Some(vec![
+            "cracken",
+            "train",
+            "-f",
+            passwords.to_str().unwrap(),
+            "-o",
+            model_fname,
+        ]))
+        .unwrap();
+        let model = Model::read(&mut File::open(model_fname).unwrap()).unwrap();
+        assert_eq!(model.passwords(), 5);
+
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname, "-m", "1"];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        let plain = run(&["?l?d"]).unwrap();
+        for beam in ["1000000", "300"] {
+            let ordered = run(&["--order", model_fname, "--beam", beam, "?l?d"]).unwrap();
+            let mut words: Vec<_> = ordered.lines().collect();
+            let mut expected: Vec<_> = plain.lines().collect();
+            let best = expected
+                .iter()
+                .map(|w| model.score(w.as_bytes()))
+                .fold(f64::MIN, f64::max);
+            assert_eq!(model.score(words[0].as_bytes()), best);
+            words.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(words, expected);
+        }
+
+        let err = run(&[
+            "--order",
+            model_fname,
+            "?w1",
+            "-w",
+            passwords.to_str().unwrap(),
+        ])
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            RunError::Generator(CrackenError::Unsupported(_))
+        ));
+        let err = run(&["--order", model_fname, "--length-order", "desc", "?d"]).unwrap_err();
+        assert!(matches!(err, RunError::Usage(_)));
+        let err = run(&["--order", passwords.to_str().unwrap(), "?d"]).unwrap_err();
+        assert!(err.to_string().starts_with("failed to read model"));
+        std::fs::remove_file(model_fname).unwrap();
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_john_syntax() {
+        let out_fname = std::env::temp_dir().join("cracken-test-john-syntax.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let wordlist = test_util::wordlist_fname("wordlist-no-newline.txt");
+
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname, "--syntax", "john"];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        assert_eq!(
+            run(&["-c", "!@", "[Pp]w?1"]).unwrap(),
+            "Pw!\nPw@\npw!\npw@\n"
+        );
+        assert_eq!(
+            run(&["-w", wordlist.to_str().unwrap(), "?w[\\x30-\\x31]"]).unwrap(),
+            "john0\njohn1\nemile0\nemile1\nMcDonald0\nMcDonald1\n"
+        );
+        assert_eq!(run(&["?h"]).unwrap().len(), 16 * 2);
+        // the default syntax has no ranges
+        let args = vec!["cracken", "--force", "-o", out_fname, "[ab]"];
+        runner::run(Some(args)).unwrap();
+        assert_eq!(std::fs::read_to_string(out_fname).unwrap(), "[ab]\n");
+
+        assert!(run(&["?b"]).is_err());
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_repeat_limits() {
+        let out_fname = std::env::temp_dir().join("cracken-test-repeat-limits.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "--force", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        assert_eq!(
+            run(&["-c", "ab", "--seq-max", "1", "?1?1?1"]).unwrap(),
+            "aba\nbab\n"
+        );
+        assert_eq!(
+            run(&["-c", "abc", "-r", "1", "?1?1"]).unwrap(),
+            "ab\nac\nba\nbc\nca\ncb\n"
+        );
+        assert_eq!(
+            run(&["--seq-max", "1", "-r", "1", "-m", "1", "?d?d"])
+                .unwrap()
+                .lines()
+                .count(),
+            10 + 90
+        );
+        assert!(run(&["--seq-max", "0", "?d"]).is_err());
+        assert!(run(&["-r", "x", "?d"]).is_err());
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_crunch() {
+        let out_fname = std::env::temp_dir().join("cracken-test-crunch.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "crunch"];
+            run_args.extend(args);
+            run_args.extend(["-o", out_fname]);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        // golden files are the crunch order - the charset order with the last position first
+        let golden = |fname| std::fs::read_to_string(test_util::wordlist_fname(fname)).unwrap();
+        assert_eq!(
+            run(&["1", "3", "ba1"]).unwrap(),
+            golden("crunch-1-3-ba1.txt")
+        );
+        assert_eq!(
+            run(&["4", "4", "cab", "ZY", "210", "-t", "@,%-"]).unwrap(),
+            golden("crunch-t-cab-ZY-210.txt")
+        );
+        assert_eq!(
+            run(&["2", "2", "+", "+", "+", "-t", "?%"]).unwrap(),
+            "?0\n?1\n?2\n?3\n?4\n?5\n?6\n?7\n?8\n?9\n"
+        );
+
+        // -s and -e are inclusive and in crunch order
+        assert_eq!(
+            run(&["2", "2", "cba", "-s", "bb", "-e", "ac"]).unwrap(),
+            "bb\nba\nac\n"
+        );
+        assert_eq!(
+            run(&["1", "2", "cba", "-s", "a"]).unwrap().lines().count(),
+            1 + 9
+        );
+        assert_eq!(
+            run(&["2", "2", "cba", "-s", "bb", "-e", "cc"])
+                .unwrap_err()
+                .to_string(),
+            "crunch -e cc comes before -s bb"
+        );
+        assert!(run(&["2", "2", "cba", "-s", "bd"]).is_err());
+
+        for args in [
+            &["1", "2", "-d", "2"][..],
+            &["1", "2", "-i"],
+            &["1", "2", "-z", "gzip"],
+        ] {
+            let err = run(args).unwrap_err();
+            assert!(matches!(err, RunError::Usage(_)));
+            assert_eq!(
+                err.to_string(),
+                format!("crunch option {} is not supported", args[2])
+            );
+        }
+        assert!(run(&["3", "2"]).is_err());
+        assert!(run(&["2", "3", "-t", "@@"]).is_err());
+        assert!(run(&["x", "3"]).is_err());
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_policygen() {
+        let out_fname = std::env::temp_dir().join("cracken-test-policygen.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut run_args = vec!["cracken", "policygen", "-o", out_fname];
+            run_args.extend(args);
+            runner::run(Some(run_args))?;
+            Ok::<_, RunError>(std::fs::read_to_string(out_fname).unwrap())
+        };
+        assert_eq!(
+            run(&["--min", "2", "--max", "2", "--require", "u,d"]).unwrap(),
+            "?d?u\n?u?d\n"
+        );
+        assert_eq!(
+            run(&[
+                "--min",
+                "1",
+                "--max",
+                "2",
+                "--require",
+                "d",
+                "--max-lower",
+                "0",
+                "--max-upper",
+                "0",
+                "--max-symbols",
+                "1",
+                "-s"
+            ])
+            .unwrap(),
+            "?d\t10\n?d?d\t100\n?d?s\t330\n?s?d\t330\ntotal: 4 masks, 770 words\n"
+        );
+
+        // the masks generate the policy passwords
+        let masks = run(&[
+            "--min",
+            "3",
+            "--max",
+            "3",
+            "--require",
+            "u,d",
+            "--max-symbols",
+            "0",
+        ])
+        .unwrap();
+        let masks_fname = std::env::temp_dir().join("cracken-test-policygen-masks.txt");
+        std::fs::write(&masks_fname, masks).unwrap();
+        let args = vec!["cracken", "-s", "-i", masks_fname.to_str().unwrap()];
+        assert!(runner::run(Some(args)).is_ok());
+        std::fs::remove_file(masks_fname).unwrap();
+
+        assert!(matches!(
+            run(&["--min", "3", "--max", "2"]),
+            Err(RunError::Generator(CrackenError::InvalidLength(_)))
+        ));
+        assert!(matches!(
+            run(&["--min", "2", "--max", "2", "--require", "x"]),
+            Err(RunError::Generator(CrackenError::Charset(_)))
+        ));
+        assert!(matches!(
+            run(&["--min", "2", "--max", "2", "--max-digits", "x"]),
+            Err(RunError::Usage(_))
+        ));
+        std::fs::remove_file(out_fname).unwrap();
+    }
+
+    #[test]
+    fn test_run_io_buffer() {
+        let out_fname = std::env::temp_dir().join("cracken-test-io-buffer.txt");
+        let out_fname = out_fname.to_str().unwrap();
+
+        let run = |io_buffer: &str| {
+            let args = vec![
+                "cracken",
+                "--force",
+                "--io-buffer",
+                io_buffer,
+                "-o",
+                out_fname,
+                "-m",
+                "1",
+                "?d?l?d",
+            ];
+            runner::run(Some(args)).unwrap();
+            std::fs::read_to_string(out_fname).unwrap()
+        };
+        let expected = run("8k");
+        assert_eq!(expected.lines().count(), 10 + 10 * 26 + 10 * 26 * 10);
+        for io_buffer in ["1", "3", "1000", "64k", "4M"] {
+            assert!(run(io_buffer) == expected, "{}", io_buffer);
+        }
+        std::fs::remove_file(out_fname).unwrap();
+
+        for io_buffer in ["0", "abc", "2G"] {
+            let args = vec!["cracken", "--io-buffer", io_buffer, "?d"];
+            let err = runner::run(Some(args)).unwrap_err();
+            assert_eq!(err.exit_code(), 2, "{}", io_buffer);
+            assert!(err.to_string().starts_with("--io-buffer"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_get_io_buffer() {
+        assert_eq!(runner::get_io_buffer(None, None).unwrap(), 8192);
+        assert_eq!(
+            runner::get_io_buffer(None, Some("".to_string())).unwrap(),
+            8192
+        );
+        assert_eq!(
+            runner::get_io_buffer(None, Some("1M".to_string())).unwrap(),
+            1 << 20
+        );
+        assert_eq!(
+            runner::get_io_buffer(Some("64"), Some("1M".to_string())).unwrap(),
+            64
+        );
+        let err = runner::get_io_buffer(None, Some("lots".to_string())).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("$CRACKEN_BUFFER_SIZE: invalid size 'lots'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_get_max_keyspace() {
+        let keyspace = |arg, env: Option<&str>, config: Option<&str>| {
+            let profile = Profile {
+                max_keyspace: config.map(str::to_string),
+                ..Default::default()
+            };
+            runner::get_max_keyspace(arg, env.map(str::to_string), &profile)
+        };
+        let big = |keyspace: u64| keyspace.to_biguint().unwrap();
+        assert_eq!(keyspace(None, None, None).unwrap(), big(1_000_000_000_000));
+        assert_eq!(
+            keyspace(None, Some(""), None).unwrap(),
+            big(1_000_000_000_000)
+        );
+        assert_eq!(keyspace(None, None, Some("1e6")).unwrap(), big(1_000_000));
+        assert_eq!(keyspace(None, Some("5e3"), Some("1e6")).unwrap(), big(5000));
+        assert_eq!(
+            keyspace(Some("10"), Some("5e3"), Some("1e6")).unwrap(),
+            big(10)
+        );
+        let err = keyspace(None, Some("lots"), None).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("$CRACKEN_MAX_KEYSPACE: invalid keyspace 'lots'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_write_version() {
+        let mut out = vec![];
+        runner::write_version(&mut out, true).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert!(
+            json.starts_with(&format!(
+                "{{\"name\":\"cracken\",\"version\":\"{}\",",
+                built_info::PKG_VERSION
+            )),
+            "{}",
+            json
+        );
+        for key in [
+            "git_commit",
+            "git_dirty",
+            "built_time",
+            "target",
+            "profile",
+            "rustc",
+            "features",
+        ] {
+            assert!(
+                json.contains(&format!(",\"{}\":", key)),
+                "{}: {}",
+                key,
+                json
+            );
+        }
+        assert!(json.ends_with("]}\n"), "{}", json);
+
+        let mut out = vec![];
+        runner::write_version(&mut out, false).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with(&format!(
+            "cracken {}\ngit commit: ",
+            built_info::PKG_VERSION
+        )));
+        assert_eq!(text.lines().count(), 6);
+
+        for args in [
+            vec!["cracken", "version"],
+            vec!["cracken", "version", "--format", "json"],
+            vec!["cracken", "--version"],
+            vec!["cracken", "--version", "--verbose"],
+        ] {
+            assert!(runner::run(Some(args)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_with_wordlist_dir() {
+        let wordlists = vec!["names.txt", "/abs/words.txt", "sub/dir.txt:latin1"];
+        assert_eq!(
+            runner::with_wordlist_dir(wordlists.clone(), Some("/data/lists".into())),
+            vec![
+                "/data/lists/names.txt",
+                "/abs/words.txt",
+                "/data/lists/sub/dir.txt:latin1"
+            ]
+        );
+        assert_eq!(
+            runner::with_wordlist_dir(wordlists.clone(), Some("".into())),
+            wordlists
+        );
+        assert_eq!(
+            runner::with_wordlist_dir(wordlists.clone(), None),
+            wordlists
+        );
+    }
+
+    #[test]
+    fn test_run_append() {
+        let out_fname = std::env::temp_dir().join("cracken-test-append.txt");
+        let out_fname = out_fname.to_str().unwrap();
+        let _ = std::fs::remove_file(out_fname);
+
+        let run = |mask: &str, append: bool| {
+            let mut args = vec!["cracken", "-o", out_fname, mask];
+            args.insert(1, if append { "--append" } else { "--force" });
+            runner::run(Some(args)).unwrap();
+            std::fs::read_to_string(out_fname).unwrap()
+        };
+        assert_eq!(run("a", true), "a\n");
+        assert_eq!(
+            run("b?d", true),
+            "a\nb0\nb1\nb2\nb3\nb4\nb5\nb6\nb7\nb8\nb9\n"
+        );
+        assert_eq!(run("c", false), "c\n");
+        std::fs::remove_file(out_fname).unwrap();
+
+        let args = vec!["cracken", "--append", "-o", "/tmp/this/dir/not/exisT", "?d"];
+        let err = runner::run(Some(args)).unwrap_err().to_string();
+        assert!(
+            err.starts_with("cannot append to file /tmp/this/dir/not/exisT"),
+            "{}",
+            err
+        );
+        let args = vec!["cracken", "-o", "/tmp/this/dir/not/exisT", "?d"];
+        let err = runner::run(Some(args)).unwrap_err().to_string();
+        assert!(
+            err.starts_with("cannot create file /tmp/this/dir/not/exisT"),
+            "{}",
+            err
+        );
     }
 
     #[test]
@@ -582,6 +6516,85 @@ mod tests {
         assert!(runner::run(args).is_err());
     }
 
+    #[test]
+    fn test_run_exit_codes() {
+        let existing_fname = test_util::wordlist_fname("wordlist1.txt");
+        let existing_fname = existing_fname.to_str().unwrap();
+        let cases = vec![
+            (vec!["cracken", "--minlen", "x", "?d"], 2),
+            (vec!["cracken", "--no-such-flag", "?d"], 2),
+            (vec!["cracken", "?d?z"], 2),
+            (vec!["cracken", "-i", "/tmp/this/dir/not/exisT"], 3),
+            (vec!["cracken", "-w", "/tmp/this/dir/not/exisT", "?w1"], 3),
+            (vec!["cracken", "-o", "/tmp/this/dir/not/exisT", "?d"], 4),
+            (vec!["cracken", "-o", "/dev/full", "?d?d?d?d"], 4),
+            (vec!["cracken", "-o", existing_fname, "?d"], 5),
+        ];
+        for (args, exit_code) in cases {
+            let err = runner::run(Some(args.clone())).unwrap_err();
+            assert_eq!(err.exit_code(), exit_code, "{:?}: {}", args, err);
+        }
+    }
+
+    #[test]
+    fn test_run_error_variants() {
+        let err = runner::run(Some(vec!["cracken", "?d?z"])).unwrap_err();
+        assert!(
+            matches!(err, RunError::Generator(CrackenError::Mask(_))),
+            "{:?}",
+            err
+        );
+
+        let err = runner::run(Some(vec!["cracken", "--no-such-flag", "?d"])).unwrap_err();
+        match &err {
+            RunError::Args(e) => assert_eq!(e.kind, ClapErrorKind::UnknownArgument),
+            _ => panic!("unexpected error {:?}", err),
+        }
+
+        let err = runner::run(Some(vec![
+            "cracken",
+            "-w",
+            "/tmp/this/dir/not/exisT",
+            "?w1",
+        ]));
+        let err = err.unwrap_err();
+        assert!(
+            matches!(err, RunError::Generator(CrackenError::Wordlist { .. })),
+            "{:?}",
+            err
+        );
+        assert_eq!(
+            runner::run_cli(Some(vec!["cracken", "?d?z"])).unwrap_err(),
+            "Invalid mask"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_error_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fname = std::env::temp_dir().join("cracken-test-permission-denied.txt");
+        std::fs::write(&fname, "abc\n").unwrap();
+        std::fs::set_permissions(&fname, std::fs::Permissions::from_mode(0o000)).unwrap();
+        // root reads the file anyway
+        if std::fs::File::open(&fname).is_ok() {
+            std::fs::remove_file(&fname).unwrap();
+            return;
+        }
+        let args = vec!["cracken", "-w", fname.to_str().unwrap(), "?w1"];
+        let err = runner::run(Some(args)).unwrap_err();
+        std::fs::remove_file(&fname).unwrap();
+        assert_eq!(err.exit_code(), 3);
+        match &err {
+            RunError::Generator(CrackenError::Wordlist { source, .. }) => {
+                let source = source.downcast_ref::<io::Error>().unwrap();
+                assert_eq!(source.kind(), io::ErrorKind::PermissionDenied);
+            }
+            _ => panic!("unexpected error {:?}", err),
+        }
+    }
+
     #[test]
     fn test_run_bad_args2() {
         let args = Some(vec!["cracken", "?x"]);
@@ -593,4 +6606,65 @@ mod tests {
         let args = Some(vec!["cracken", "-x", "5", "?d"]);
         assert!(runner::run(args).is_err());
     }
+
+    #[test]
+    fn test_run_bad_args_lengths() {
+        let cases = vec![
+            (
+                vec!["-m", "abc"],
+                "invalid value 'abc' for --minlen: expected a positive integer",
+            ),
+            (
+                vec!["-x", "2.5"],
+                "invalid value '2.5' for --maxlen: expected a positive integer",
+            ),
+            (
+                vec!["-m", "0"],
+                "invalid value '0' for --minlen: must be greater than zero",
+            ),
+            (
+                vec!["-x", "0"],
+                "invalid value '0' for --maxlen: must be greater than zero",
+            ),
+            (vec!["-m", "4"], "minlen 4 is longer than the mask length 3"),
+            (vec!["-x", "4"], "maxlen 4 is longer than the mask length 3"),
+            (
+                vec!["-m", "3", "-x", "2"],
+                "minlen 3 is greater than maxlen 2",
+            ),
+        ];
+        for (len_args, expected) in cases {
+            let mut args = vec!["cracken", "-s"];
+            args.extend(len_args);
+            args.push("?d?d?d");
+            let err = runner::run(Some(args)).unwrap_err();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_run_bad_args_charset_out_of_range() {
+        let args = Some(vec!["cracken", "-c", "ab", "-c", "cd", "?1?2?3"]);
+        let err = runner::run(args).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "mask uses ?3 but only 2 custom charsets were provided (-c or --tokens)"
+        );
+    }
+
+    #[test]
+    fn test_run_bad_args_wordlist_out_of_range() {
+        let wordlist = test_util::wordlist_fname("wordlist1.txt");
+        let args = Some(vec![
+            "cracken",
+            "-w",
+            wordlist.to_str().unwrap(),
+            "?w1?d?w2",
+        ]);
+        let err = runner::run(args).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "mask uses ?w2 but only 1 wordlists were provided (-w)"
+        );
+    }
 }
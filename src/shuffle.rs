@@ -0,0 +1,296 @@
+//! a reproducible pseudo random order of the words of a generator (`--shuffle`)
+use std::io::Write;
+
+use num_bigint::BigUint;
+
+use crate::error::CrackenError;
+use crate::generators::{
+    max_written_len, write_word, LengthOrder, RepeatLimits, WordGenerator, WordRank,
+};
+use crate::helpers::SplitMix64;
+use crate::stackbuf::StackBuf;
+use crate::{BUFFER_SIZE, MAX_WORD_SIZE};
+
+/// rounds of the feistel network, enough to scatter neighbouring indices
+const ROUNDS: usize = 6;
+
+/// a permutation of the indices `0..len` - a balanced feistel network over the smallest even
+/// number of bits holding them, walking the cycle until the index is back in range. the same
+/// seed always gives the same permutation
+pub struct Permutation {
+    len: u64,
+    /// bits of each half of the network input
+    half_bits: u32,
+    keys: [u64; ROUNDS],
+}
+
+impl Permutation {
+    pub fn new(len: u64, seed: u64) -> Permutation {
+        let bits = 64 - len.saturating_sub(1).leading_zeros();
+        let half_bits = bits.div_ceil(2).max(1);
+        let mut rng = SplitMix64::new(seed);
+        let mut keys = [0; ROUNDS];
+        for key in keys.iter_mut() {
+            *key = rng.next_u64();
+        }
+        Permutation {
+            len,
+            half_bits,
+            keys,
+        }
+    }
+
+    fn half_mask(&self) -> u64 {
+        (1 << self.half_bits) - 1
+    }
+
+    /// the round function, a splitmix64 finalizer of the half and the round key
+    fn round(&self, half: u64, key: u64) -> u64 {
+        let mut z = half ^ key;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        (z ^ (z >> 31)) & self.half_mask()
+    }
+
+    fn encrypt(&self, value: u64) -> u64 {
+        let (mut left, mut right) = (value >> self.half_bits, value & self.half_mask());
+        for &key in self.keys.iter() {
+            (left, right) = (right, left ^ self.round(right, key));
+        }
+        (left << self.half_bits) | right
+    }
+
+    fn decrypt(&self, value: u64) -> u64 {
+        let (mut left, mut right) = (value >> self.half_bits, value & self.half_mask());
+        for &key in self.keys.iter().rev() {
+            (left, right) = (right ^ self.round(left, key), left);
+        }
+        (left << self.half_bits) | right
+    }
+
+    /// the index at position `index` of the permutation, `index` must be below `len`
+    pub fn apply(&self, index: u64) -> u64 {
+        let mut value = self.encrypt(index);
+        while value >= self.len {
+            value = self.encrypt(value);
+        }
+        value
+    }
+
+    /// the position of `index` in the permutation, the inverse of `apply`
+    pub fn position(&self, index: u64) -> u64 {
+        let mut value = self.decrypt(index);
+        while value >= self.len {
+            value = self.decrypt(value);
+        }
+        value
+    }
+}
+
+/// generates the words of `inner` in the order of a seeded permutation of their indices,
+/// every word by its index with `WordGenerator::word_at`
+pub struct ShuffledGenerator {
+    inner: Box<dyn WordGenerator>,
+    permutation: Permutation,
+    hex_output: bool,
+    buffer_size: usize,
+    repeat_limits: RepeatLimits,
+}
+
+/// shuffles the words of `generator` with `seed`. the generator must generate its words by
+/// index and generate at most 2^64 words
+pub fn shuffle(
+    generator: Box<dyn WordGenerator>,
+    seed: u64,
+) -> Result<Box<dyn WordGenerator>, CrackenError> {
+    let len = match generator.combinations().to_u64_digits()[..] {
+        [] => 0,
+        [len] => len,
+        _ => {
+            return Err(CrackenError::Unsupported(
+                "shuffle supports masks of at most 2^64 words".to_string(),
+            ))
+        }
+    };
+    if len > 0 && generator.word_at(0).is_none() {
+        return Err(CrackenError::Unsupported(
+            "shuffle supports charsets and wordlists masks only".to_string(),
+        ));
+    }
+    Ok(Box::new(ShuffledGenerator {
+        inner: generator,
+        permutation: Permutation::new(len, seed),
+        hex_output: false,
+        buffer_size: BUFFER_SIZE,
+        repeat_limits: RepeatLimits::default(),
+    }))
+}
+
+impl WordGenerator for ShuffledGenerator {
+    fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), CrackenError> {
+        let max_word_len = max_written_len(MAX_WORD_SIZE + 1, self.hex_output);
+        let mut buf = StackBuf::with_size(self.buffer_size.max(max_word_len));
+        let mut word = Vec::with_capacity(MAX_WORD_SIZE + 1);
+        for index in 0..self.permutation.len {
+            word.clear();
+            word.extend(self.inner.word_at(self.permutation.apply(index)).unwrap());
+            if self.repeat_limits.is_set() && !self.repeat_limits.allows(&word) {
+                continue;
+            }
+            word.push(b'\n');
+            if buf.remaining() < max_written_len(word.len(), self.hex_output) {
+                out.write_all(buf.getdata())?;
+                buf.clear();
+            }
+            write_word(&mut buf, &word, self.hex_output);
+        }
+        out.write_all(buf.getdata())?;
+        Ok(())
+    }
+
+    fn combinations(&self) -> BigUint {
+        self.inner.combinations()
+    }
+
+    /// the lengths are mixed
+    fn length_combinations(&self) -> Vec<(usize, BigUint)> {
+        vec![]
+    }
+
+    fn output_bytes(&self) -> BigUint {
+        self.inner.output_bytes()
+    }
+
+    fn set_hex_output(&mut self, hex_output: bool) {
+        self.hex_output = hex_output;
+    }
+
+    fn set_buffer_size(&mut self, buffer_size: usize) {
+        self.buffer_size = buffer_size;
+    }
+
+    /// the words are checked as they are generated, `combinations` still counts them
+    fn set_repeat_limits(&mut self, repeat_limits: RepeatLimits) {
+        self.repeat_limits = repeat_limits;
+    }
+
+    fn index_of(&self, word: &[u8]) -> Option<WordRank> {
+        let rank = self.inner.index_of(word)?;
+        let index = match rank.index.to_u64_digits()[..] {
+            [] => 0,
+            [index] => index,
+            _ => return None,
+        };
+        Some(WordRank {
+            index: self.permutation.position(index).into(),
+            wordlist_words: rank.wordlist_words,
+        })
+    }
+
+    /// the words are shuffled, there is no order of the lengths
+    fn set_length_order(&mut self, _length_order: LengthOrder) {}
+
+    fn word_at(&self, index: u64) -> Option<Vec<u8>> {
+        if index >= self.permutation.len {
+            return None;
+        }
+        self.inner.word_at(self.permutation.apply(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use num_bigint::BigUint;
+
+    use super::{shuffle, Permutation};
+    use crate::error::CrackenError;
+    use crate::generators::{get_word_generator, WordGenerator};
+    use crate::test_util::wordlist_fname;
+
+    fn gen_str(word_gen: &dyn WordGenerator) -> String {
+        let mut buf = vec![];
+        {
+            let mut out: Box<dyn Write> = Box::new(&mut buf);
+            word_gen.gen(&mut out).unwrap();
+        }
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_permutation() {
+        for len in [0, 1, 2, 3, 10, 255, 256, 257, 1000] {
+            let permutation = Permutation::new(len, 7);
+            let mut seen = vec![false; len as usize];
+            for index in 0..len {
+                let value = permutation.apply(index);
+                assert!(!seen[value as usize], "{} {}", len, value);
+                seen[value as usize] = true;
+                assert_eq!(permutation.position(value), index);
+            }
+        }
+        // another seed, another order
+        let first: Vec<_> = (0..100)
+            .map(|i| Permutation::new(100, 1).apply(i))
+            .collect();
+        let second: Vec<_> = (0..100)
+            .map(|i| Permutation::new(100, 2).apply(i))
+            .collect();
+        assert_ne!(first, second);
+        // the largest domain doesn't overflow
+        let permutation = Permutation::new(u64::MAX, 3);
+        assert_eq!(permutation.position(permutation.apply(12345)), 12345);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let wordlist = wordlist_fname("wordlist1.txt");
+        let wordlist = wordlist.to_str().unwrap();
+        for (mask, minlen, wordlists) in [
+            ("?d?l", None, vec![]),
+            ("?d?d?d", Some(1), vec![]),
+            ("?w1?d", None, vec![wordlist]),
+        ] {
+            let ordered = get_word_generator(mask, minlen, None, &[], &wordlists).unwrap();
+            let mut expected: Vec<_> = gen_str(ordered.as_ref())
+                .lines()
+                .map(|w| w.to_string())
+                .collect();
+
+            let shuffle_gen = |seed| {
+                let inner = get_word_generator(mask, minlen, None, &[], &wordlists).unwrap();
+                shuffle(inner, seed).unwrap()
+            };
+            let shuffled = gen_str(shuffle_gen(42).as_ref());
+            assert_eq!(shuffled, gen_str(shuffle_gen(42).as_ref()));
+            assert_ne!(shuffled, gen_str(shuffle_gen(43).as_ref()));
+            let mut words: Vec<_> = shuffled.lines().map(|w| w.to_string()).collect();
+            let word_gen = shuffle_gen(42);
+            for (idx, word) in words.iter().enumerate() {
+                let index = word_gen.index_of(word.as_bytes()).unwrap().index;
+                assert_eq!(index, BigUint::from(idx), "{} {}", mask, word);
+            }
+            assert_ne!(words, expected);
+            words.sort();
+            expected.sort();
+            assert_eq!(words, expected, "{}", mask);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_unsupported() {
+        let inner = get_word_generator(
+            "?d?d?d?d?d?d?d?d?d?d?d?d?d?d?d?d?d?d?d?d",
+            None,
+            None,
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert!(matches!(
+            shuffle(inner, 1),
+            Err(CrackenError::Unsupported(_))
+        ));
+    }
+}
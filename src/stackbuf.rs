@@ -1,24 +1,113 @@
+use std::error::Error;
+use std::fmt;
+
 use crate::BUFFER_SIZE;
 
+/// buffers larger than this are allocated on the heap instead of the stack
+pub const MAX_STACK_SIZE: usize = BUFFER_SIZE;
+
+#[allow(clippy::large_enum_variant)]
+enum Storage {
+    Stack([u8; MAX_STACK_SIZE]),
+    Heap(Box<[u8]>),
+}
+
+/// returned by `StackBuf::try_write` when the word doesn't fit the remaining capacity
+#[derive(Debug, PartialEq)]
+pub struct BufFullError {
+    pub len: usize,
+    pub remaining: usize,
+}
+
+impl fmt::Display for BufFullError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cannot write {} bytes to the buffer, only {} bytes remaining",
+            self.len, self.remaining
+        )
+    }
+}
+
+impl Error for BufFullError {}
+
 pub struct StackBuf {
-    buf: [u8; BUFFER_SIZE],
+    buf: Storage,
+    size: usize,
     pos: usize,
 }
 
 impl StackBuf {
     pub fn new() -> StackBuf {
+        StackBuf::with_size(BUFFER_SIZE)
+    }
+
+    /// a buffer of `size` bytes, on the heap if it doesn't fit MAX_STACK_SIZE
+    pub fn with_size(size: usize) -> StackBuf {
+        if size > MAX_STACK_SIZE {
+            return StackBuf::on_heap(size);
+        }
         StackBuf {
-            buf: [0; BUFFER_SIZE],
+            buf: Storage::Stack([0; MAX_STACK_SIZE]),
+            size,
+            pos: 0,
+        }
+    }
+
+    /// a buffer of `size` bytes allocated on the heap whatever its size
+    pub fn on_heap(size: usize) -> StackBuf {
+        StackBuf {
+            buf: Storage::Heap(vec![0; size].into_boxed_slice()),
+            size,
             pos: 0,
         }
     }
 
+    #[inline]
+    pub fn is_heap(&self) -> bool {
+        matches!(self.buf, Storage::Heap(_))
+    }
+
+    #[inline]
+    fn data(&self) -> &[u8] {
+        match &self.buf {
+            Storage::Stack(buf) => &buf[..self.size],
+            Storage::Heap(buf) => buf,
+        }
+    }
+
+    /// writes `word` to the buffer, the caller checks it fits the `remaining()` bytes
     #[inline]
     pub fn write(&mut self, word: &[u8]) {
-        self.buf[self.pos..self.pos + word.len()].copy_from_slice(word);
+        debug_assert!(
+            word.len() <= self.remaining(),
+            "{}",
+            BufFullError {
+                len: word.len(),
+                remaining: self.remaining()
+            }
+        );
+        let buf = match &mut self.buf {
+            Storage::Stack(buf) => &mut buf[..self.size],
+            Storage::Heap(buf) => &mut buf[..],
+        };
+        buf[self.pos..self.pos + word.len()].copy_from_slice(word);
         self.pos += word.len();
     }
 
+    /// writes `word` to the buffer or returns an error without writing if it doesn't fit
+    #[inline]
+    pub fn try_write(&mut self, word: &[u8]) -> Result<(), BufFullError> {
+        if word.len() > self.remaining() {
+            return Err(BufFullError {
+                len: word.len(),
+                remaining: self.remaining(),
+            });
+        }
+        self.write(word);
+        Ok(())
+    }
+
     #[inline]
     pub fn clear(&mut self) {
         self.pos = 0;
@@ -26,7 +115,7 @@ impl StackBuf {
 
     #[inline]
     pub fn getdata(&self) -> &[u8] {
-        &self.buf[..self.pos]
+        &self.data()[..self.pos]
     }
 
     #[inline]
@@ -36,7 +125,13 @@ impl StackBuf {
 
     #[inline]
     pub fn len(&self) -> usize {
-        self.buf.len()
+        self.size
+    }
+
+    /// number of bytes which can still be written before the buffer is full
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.size - self.pos
     }
 
     #[inline]
@@ -53,7 +148,7 @@ impl Default for StackBuf {
 
 #[cfg(test)]
 mod tests {
-    use super::StackBuf;
+    use super::{BufFullError, StackBuf};
     #[test]
     fn test_stack_buf() {
         let buf = StackBuf::new();
@@ -62,4 +157,84 @@ mod tests {
         let default_buf = StackBuf::default();
         assert_eq!(default_buf.pos, 0);
     }
+
+    #[test]
+    fn test_stack_buf_with_size() {
+        for (size, heap) in [(16, false), (8192, false), (8193, true), (1 << 20, true)] {
+            let mut buf = StackBuf::with_size(size);
+            assert_eq!(buf.is_heap(), heap, "{}", size);
+            assert_eq!(buf.len(), size);
+            buf.write(b"abc\n");
+            buf.write(b"de\n");
+            assert_eq!(buf.getdata(), b"abc\nde\n");
+            assert_eq!(buf.pos(), 7);
+            buf.clear();
+            assert_eq!(buf.getdata(), b"");
+        }
+    }
+
+    #[test]
+    fn test_stack_buf_backings_boundaries() {
+        for (mut buf, heap) in [
+            (StackBuf::with_size(8), false),
+            (StackBuf::on_heap(8), true),
+        ] {
+            assert_eq!(buf.is_heap(), heap);
+            assert_eq!(buf.len(), 8);
+            // exact fill
+            buf.write(b"abc\n");
+            buf.write(b"def\n");
+            assert_eq!(buf.pos(), buf.len());
+            assert_eq!(buf.getdata(), b"abc\ndef\n");
+
+            // wrap at capacity, the next batch starts from the beginning
+            buf.clear();
+            buf.write(b"0123456\n");
+            assert_eq!(buf.getdata(), b"0123456\n");
+            buf.clear();
+            buf.write(b"x\n");
+            assert_eq!(buf.getdata(), b"x\n");
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_stack_buf_overflow() {
+        let mut buf = StackBuf::with_size(4);
+        buf.write(b"abc\n");
+        buf.write(b"d");
+    }
+
+    #[test]
+    fn test_stack_buf_try_write() {
+        for mut buf in [StackBuf::with_size(8), StackBuf::on_heap(8)] {
+            assert_eq!(buf.remaining(), 8);
+            buf.try_write(b"abcde\n").unwrap();
+            assert_eq!(buf.remaining(), 2);
+
+            // nothing is written when the word doesn't fit
+            let err = buf.try_write(b"fgh\n").unwrap_err();
+            assert_eq!(
+                err,
+                BufFullError {
+                    len: 4,
+                    remaining: 2
+                }
+            );
+            assert_eq!(
+                err.to_string(),
+                "cannot write 4 bytes to the buffer, only 2 bytes remaining"
+            );
+            assert_eq!(buf.getdata(), b"abcde\n");
+
+            buf.try_write(b"f\n").unwrap();
+            assert_eq!(buf.remaining(), 0);
+            assert!(buf.try_write(b"\n").is_err());
+            assert!(buf.try_write(b"").is_ok());
+
+            buf.clear();
+            assert!(buf.try_write(b"012345678\n").is_err());
+            assert_eq!(buf.remaining(), 8);
+        }
+    }
 }
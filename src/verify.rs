@@ -0,0 +1,257 @@
+//! `--verify`, generates the words of a generator into a counting sink and checks them against
+//! its `combinations()`
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hasher;
+use std::io::{self, Write};
+
+use num_bigint::BigUint;
+
+use crate::error::CrackenError;
+use crate::generators::WordGenerator;
+
+/// the words a generator wrote compared with the words it should write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    /// words written, at most `combinations + 1` as the generation stops after one too many
+    pub words: u64,
+    /// distinct words written, by their 64 bit hash
+    pub distinct: u64,
+    pub combinations: BigUint,
+}
+
+impl Verification {
+    /// true if every word was written exactly once
+    pub fn passed(&self) -> bool {
+        self.words == self.distinct && BigUint::from(self.words) == self.combinations
+    }
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let words = match BigUint::from(self.words) > self.combinations {
+            true => format!("more than {}", self.combinations),
+            false => self.words.to_string(),
+        };
+        write!(
+            f,
+            "{}: {} words, {} distinct, {} combinations",
+            if self.passed() { "PASS" } else { "FAIL" },
+            words,
+            self.distinct,
+            self.combinations
+        )
+    }
+}
+
+/// generates every word of `word_gen` without writing them and counts them. the words are
+/// hashed to count the distinct ones, 16 bytes of memory per word
+pub fn verify(word_gen: &dyn WordGenerator) -> Result<Verification, CrackenError> {
+    let combinations = word_gen.combinations();
+    let max_words = u64::try_from(&combinations)
+        .ok()
+        .and_then(|combs| combs.checked_add(1))
+        .ok_or_else(|| {
+            CrackenError::Unsupported(format!(
+                "{} combinations are too many words to verify",
+                combinations
+            ))
+        })?;
+    let mut sink = CountingSink {
+        words: 0,
+        max_words,
+        hashes: HashSet::new(),
+        hasher: DefaultHasher::new(),
+        partial: false,
+    };
+    let res = {
+        let mut out: Box<dyn Write + '_> = Box::new(&mut sink);
+        word_gen.gen(&mut out)
+    };
+    match res {
+        // a generator writing too many words is stopped instead of generating all of them
+        Err(_) if sink.words >= max_words => {}
+        Err(e) => return Err(e),
+        Ok(()) => sink.finish(),
+    }
+    Ok(Verification {
+        words: sink.words,
+        distinct: sink.hashes.len() as u64,
+        combinations,
+    })
+}
+
+/// counts the lines written to it and their distinct hashes, a word may be split between
+/// writes. refuses the writes after `max_words`
+struct CountingSink {
+    words: u64,
+    max_words: u64,
+    hashes: HashSet<u64>,
+    /// hash of the bytes of the current word written so far
+    hasher: DefaultHasher,
+    partial: bool,
+}
+
+impl CountingSink {
+    fn end_word(&mut self) {
+        let hasher = std::mem::take(&mut self.hasher);
+        self.hashes.insert(hasher.finish());
+        self.words += 1;
+        self.partial = false;
+    }
+
+    /// counts a last word without a newline
+    fn finish(&mut self) {
+        if self.partial {
+            self.end_word();
+        }
+    }
+}
+
+impl Write for CountingSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&c| c == b'\n') {
+            if self.words >= self.max_words {
+                return Err(io::Error::other("more words than combinations"));
+            }
+            match line.split_last() {
+                Some((b'\n', word)) => {
+                    self.hasher.write(word);
+                    self.end_word();
+                }
+                _ => {
+                    self.hasher.write(line);
+                    self.partial = true;
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use num_bigint::{BigUint, ToBigUint};
+
+    use super::{verify, Verification};
+    use crate::error::CrackenError;
+    use crate::generators::{
+        get_word_generator, LengthOrder, RepeatLimits, WordGenerator, WordRank,
+    };
+
+    /// writes `words` in two writes splitting a word, and reports `combinations` words
+    struct FakeGenerator {
+        words: Vec<&'static str>,
+        combinations: u64,
+    }
+
+    impl WordGenerator for FakeGenerator {
+        fn gen<'b>(&self, out: &mut Box<dyn Write + 'b>) -> Result<(), CrackenError> {
+            let words = self.words.join("\n") + "\n";
+            let (first, second) = words.as_bytes().split_at(words.len() / 2);
+            out.write_all(first).map_err(CrackenError::Io)?;
+            out.write_all(second).map_err(CrackenError::Io)?;
+            Ok(())
+        }
+
+        fn combinations(&self) -> BigUint {
+            self.combinations.to_biguint().unwrap()
+        }
+
+        fn length_combinations(&self) -> Vec<(usize, BigUint)> {
+            vec![]
+        }
+
+        fn output_bytes(&self) -> BigUint {
+            BigUint::default()
+        }
+
+        fn set_hex_output(&mut self, _hex_output: bool) {}
+
+        fn set_buffer_size(&mut self, _buffer_size: usize) {}
+
+        fn set_repeat_limits(&mut self, _repeat_limits: RepeatLimits) {}
+
+        fn index_of(&self, _word: &[u8]) -> Option<WordRank> {
+            None
+        }
+
+        fn set_length_order(&mut self, _length_order: LengthOrder) {}
+    }
+
+    #[test]
+    fn test_verify() {
+        for (mask, combinations) in [("?d?d", 100), ("?l?d?d", 2600), ("?1?1", 9)] {
+            let word_gen = get_word_generator(mask, None, None, &["abc".into()], &[]).unwrap();
+            let verification = verify(word_gen.as_ref()).unwrap();
+            assert_eq!(
+                verification,
+                Verification {
+                    words: combinations,
+                    distinct: combinations,
+                    combinations: combinations.to_biguint().unwrap(),
+                }
+            );
+            assert!(verification.passed());
+            assert_eq!(
+                verification.to_string(),
+                format!(
+                    "PASS: {0} words, {0} distinct, {0} combinations",
+                    combinations
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_mismatch() {
+        // the words split between the writes are counted once
+        let words = vec!["aaaa", "bbb", "cccc", "dd"];
+        let fake = FakeGenerator {
+            words: words.clone(),
+            combinations: 4,
+        };
+        assert!(verify(&fake).unwrap().passed());
+
+        let fake = FakeGenerator {
+            words: words.clone(),
+            combinations: 5,
+        };
+        let verification = verify(&fake).unwrap();
+        assert!(!verification.passed());
+        assert_eq!(
+            verification.to_string(),
+            "FAIL: 4 words, 4 distinct, 5 combinations"
+        );
+
+        let fake = FakeGenerator {
+            words: vec!["aaaa", "bbb", "aaaa", "dd"],
+            combinations: 4,
+        };
+        let verification = verify(&fake).unwrap();
+        assert!(!verification.passed());
+        assert_eq!(
+            verification.to_string(),
+            "FAIL: 4 words, 3 distinct, 4 combinations"
+        );
+
+        // the generation stops at the first word too many
+        let fake = FakeGenerator {
+            words,
+            combinations: 2,
+        };
+        let verification = verify(&fake).unwrap();
+        assert_eq!(verification.words, 3);
+        assert_eq!(
+            verification.to_string(),
+            "FAIL: more than 2 words, 3 distinct, 2 combinations"
+        );
+    }
+}
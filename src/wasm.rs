@@ -0,0 +1,71 @@
+//! wasm-bindgen exports of the mask parsing and generation, for keyspace tools in the browser.
+//! there are no files in the browser, so masks with wordlists (?w1) are rejected
+
+use std::io::Write;
+
+use wasm_bindgen::prelude::*;
+
+use crate::builder::GeneratorBuilder;
+use crate::mask::parse_mask;
+
+/// maximum number of words returned by `preview_js`
+pub const MAX_PREVIEW_WORDS: u32 = 10_000;
+
+/// returns the parsed ops of `mask` (e.g. `BuiltinCharset('d')`), throws on invalid masks
+#[wasm_bindgen]
+pub fn parse_mask_js(mask: &str) -> Result<Vec<String>, JsError> {
+    let mask_ops = parse_mask(mask).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(mask_ops.iter().map(|op| format!("{:?}", op)).collect())
+}
+
+/// returns the number of words of `mask` as a decimal string (it may not fit a js number).
+/// `custom_charsets` are the optional charsets ?1 to ?9
+#[wasm_bindgen]
+pub fn combinations_js(
+    mask: &str,
+    custom_charsets: Option<Vec<String>>,
+) -> Result<String, JsError> {
+    let word_gen = builder(mask, custom_charsets)
+        .build()
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(word_gen.combinations().to_string())
+}
+
+/// returns the first `n` words of `mask` (up to `MAX_PREVIEW_WORDS`), invalid UTF-8 is
+/// replaced with U+FFFD. `custom_charsets` are the optional charsets ?1 to ?9
+#[wasm_bindgen]
+pub fn preview_js(
+    mask: &str,
+    n: u32,
+    custom_charsets: Option<Vec<String>>,
+) -> Result<Vec<String>, JsError> {
+    let n = n.min(MAX_PREVIEW_WORDS);
+    if n == 0 {
+        return Ok(vec![]);
+    }
+    let word_gen = builder(mask, custom_charsets)
+        .limit(n as u64)
+        .build()
+        .map_err(|e| JsError::new(&e.to_string()))?;
+
+    // the browser has no threads for `Words`, the few words are generated to memory instead
+    let mut buf = vec![];
+    {
+        let mut out: Box<dyn Write> = Box::new(&mut buf);
+        word_gen
+            .gen(&mut out)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+    }
+    Ok(buf
+        .split(|&c| c == b'\n')
+        .filter(|word| !word.is_empty())
+        .map(|word| String::from_utf8_lossy(word).into_owned())
+        .collect())
+}
+
+fn builder(mask: &str, custom_charsets: Option<Vec<String>>) -> GeneratorBuilder<'static> {
+    custom_charsets.unwrap_or_default().into_iter().fold(
+        GeneratorBuilder::new().mask(mask.to_string()),
+        |builder, charset| builder.custom_charset(charset),
+    )
+}
@@ -1,78 +1,706 @@
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::hash::Hasher;
+use std::io::{BufRead, BufReader, Error, Read, Seek, SeekFrom, Write};
+use std::ops::Index;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use crate::BoxResult;
+use regex::bytes::Regex;
 
-/// a buffer containing words of the same length
-#[derive(Debug)]
-struct WordsBuf {
-    len: usize,
-    words: Vec<u8>,
+use crate::error::CrackenError;
+use crate::exclude::read_lines;
+use crate::helpers::{decode_hex_word, format_bytes, RawFileReader, SplitMix64};
+use crate::keyboard::{QWERTY_ROW_NEIGHBOURS, QWERTY_SHIFT_TABLE};
+use crate::{BoxResult, MAX_WORD_SIZE};
+
+/// maximum number of invalid lines listed in the error of a wordlist
+const MAX_REPORTED_INVALID_LINES: usize = 5;
+
+/// separates a wordlist filename from its encoding and transform on the command line
+/// (`names.txt:upper`)
+pub const TRANSFORM_SEPARATOR: char = ':';
+
+/// a case transform applied to every word of a wordlist when it is loaded.
+/// only ascii letters are changed, other bytes (e.g. utf-8 sequences) are left untouched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WordTransform {
+    Lower,
+    Upper,
+    /// first letter uppercase, the rest lowercase
+    Capitalize,
+    /// swaps the case of every letter
+    Invert,
+}
+
+impl WordTransform {
+    pub fn from_name(name: &str) -> Option<WordTransform> {
+        match name {
+            "lower" => Some(WordTransform::Lower),
+            "upper" => Some(WordTransform::Upper),
+            "capitalize" => Some(WordTransform::Capitalize),
+            "invert" => Some(WordTransform::Invert),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, word: &mut [u8]) {
+        match self {
+            WordTransform::Lower => word.make_ascii_lowercase(),
+            WordTransform::Upper => word.make_ascii_uppercase(),
+            WordTransform::Capitalize => {
+                word.make_ascii_lowercase();
+                if let Some(first) = word.first_mut() {
+                    first.make_ascii_uppercase();
+                }
+            }
+            WordTransform::Invert => word.iter_mut().for_each(|ch| {
+                if ch.is_ascii_lowercase() {
+                    ch.make_ascii_uppercase();
+                } else if ch.is_ascii_uppercase() {
+                    ch.make_ascii_lowercase();
+                }
+            }),
+        }
+    }
+}
+
+/// returns the word reversed by utf-8 code points, or by bytes if it is not valid utf-8
+pub fn reverse_word(word: &[u8]) -> Vec<u8> {
+    match std::str::from_utf8(word) {
+        Ok(word) => word.chars().rev().collect::<String>().into_bytes(),
+        Err(_) => word.iter().rev().copied().collect(),
+    }
+}
+
+/// returns the `start..end` slice of the word by utf-8 code points, or by bytes if it is not
+/// valid utf-8. out of range indices are clamped to the word length
+pub fn slice_word(word: &[u8], start: usize, end: Option<usize>) -> Vec<u8> {
+    match std::str::from_utf8(word) {
+        Ok(word) => {
+            let len = word.chars().count();
+            let end = end.unwrap_or(len).min(len);
+            word.chars()
+                .take(end)
+                .skip(start)
+                .collect::<String>()
+                .into_bytes()
+        }
+        Err(_) => {
+            let end = end.unwrap_or(word.len()).min(word.len());
+            word[start.min(end)..end].to_vec()
+        }
+    }
+}
+
+/// a transform of a wordlist token on the mask (like ?w1:rev), applied to a copy of the wordlist
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenTransform {
+    Reverse,
+    /// python like slice of every word (like ?w1[:4] or ?w1[1:5])
+    Slice {
+        start: usize,
+        end: Option<usize>,
+    },
+    /// every word typed with shift held on a qwerty keyboard (like ?w1:shift)
+    Shift,
+    /// the words with a key replaced by its left or right qwerty neighbour (like ?w1:adjacent)
+    Adjacent,
+}
+
+impl TokenTransform {
+    /// returns the words made of `word`, several ones for `Adjacent` and a single one for the
+    /// other transforms
+    pub fn apply(&self, word: &[u8]) -> Vec<Vec<u8>> {
+        match self {
+            TokenTransform::Reverse => vec![reverse_word(word)],
+            TokenTransform::Slice { start, end } => vec![slice_word(word, *start, *end)],
+            TokenTransform::Shift => vec![shift_word(word, &QWERTY_SHIFT_TABLE)],
+            TokenTransform::Adjacent => {
+                adjacent_words(word, &QWERTY_ROW_NEIGHBOURS, MAX_ADJACENT_WORDS)
+            }
+        }
+    }
+}
+
+/// most words `TokenTransform::Adjacent` makes of a single word
+pub const MAX_ADJACENT_WORDS: usize = 32;
+
+/// returns the word typed with shift held, every byte replaced by its key in `shift_table`
+pub fn shift_word(word: &[u8], shift_table: &[u8; 256]) -> Vec<u8> {
+    word.iter().map(|&chr| shift_table[chr as usize]).collect()
+}
+
+/// returns the words with a single key replaced by one of its `neighbours`, from the first key
+/// and in the order of the neighbours, at most `max_words` of them
+pub fn adjacent_words(word: &[u8], neighbours: &[Vec<u8>], max_words: usize) -> Vec<Vec<u8>> {
+    let mut words = vec![];
+    for (pos, &chr) in word.iter().enumerate() {
+        for &neighbour in neighbours[chr as usize].iter() {
+            if words.len() == max_words {
+                return words;
+            }
+            let mut adjacent = word.to_vec();
+            adjacent[pos] = neighbour;
+            words.push(adjacent);
+        }
+    }
+    words
+}
+
+/// random sample of a wordlist, taken without replacement keeping the words order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WordlistSample {
+    /// fraction of the words (0, 1] - at least one word is always kept
+    Fraction(f64),
+    Count(usize),
+}
+
+impl WordlistSample {
+    /// returns the number of words to sample out of `len` words
+    pub fn sample_len(&self, len: usize) -> usize {
+        match self {
+            WordlistSample::Fraction(frac) => ((len as f64 * frac).ceil() as usize).clamp(1, len),
+            WordlistSample::Count(count) => len.min(*count),
+        }
+    }
+}
+
+/// options controlling how a wordlist file is loaded
+#[derive(Debug, Clone)]
+pub struct WordlistOptions {
+    pub transform: Option<WordTransform>,
+    /// reverse every word
+    pub reverse: bool,
+    /// trim trailing spaces and tabs of every word
+    pub trim: bool,
+    /// skip lines containing only spaces and tabs (empty lines are always skipped)
+    pub skip_blank: bool,
+    /// skip lines starting with this prefix (e.g. `#`)
+    pub comment_prefix: Option<String>,
+    /// words longer than this (in bytes) are invalid
+    pub max_len: usize,
+    /// skip invalid words (too long or containing NUL bytes) instead of failing
+    pub skip_invalid: bool,
+    pub encoding: WordlistEncoding,
+    /// decode `$HEX[...]` lines to their raw bytes
+    pub decode_hex: bool,
+    /// load only words matching this regex
+    pub filter: Option<Regex>,
+    /// load only a random sample of the words
+    pub sample: Option<WordlistSample>,
+    /// seed of the random sample, a random seed is used if not set
+    pub seed: Option<u64>,
+    /// records the invalid lines skipped with `skip_invalid`, nothing is printed
+    pub skipped_lines: Option<SkippedLinesLog>,
+    /// fail loading once the words need more memory than this (in bytes)
+    pub max_memory: Option<u64>,
+}
+
+/// the invalid lines of a wordlist file skipped with `WordlistOptions::skip_invalid`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedLines {
+    pub fname: PathBuf,
+    pub count: usize,
+    /// numbers of the first skipped lines (from 1), at most 5 of them
+    pub lines: Vec<usize>,
+}
+
+/// the `SkippedLines` of every wordlist loaded with the options holding it, shared by their
+/// clones. a wordlist loaded again is recorded once
+#[derive(Debug, Clone, Default)]
+pub struct SkippedLinesLog(Arc<Mutex<Vec<SkippedLines>>>);
+
+impl SkippedLinesLog {
+    fn record(&self, skipped: SkippedLines) {
+        let mut log = self.0.lock().unwrap();
+        if !log.contains(&skipped) {
+            log.push(skipped);
+        }
+    }
+
+    /// the skipped lines of the wordlists loaded so far, in loading order
+    pub fn entries(&self) -> Vec<SkippedLines> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Default for WordlistOptions {
+    fn default() -> Self {
+        WordlistOptions {
+            transform: None,
+            reverse: false,
+            trim: false,
+            skip_blank: true,
+            comment_prefix: None,
+            max_len: MAX_WORD_SIZE - 1,
+            skip_invalid: false,
+            encoding: WordlistEncoding::Utf8,
+            decode_hex: true,
+            filter: None,
+            sample: None,
+            seed: None,
+            skipped_lines: None,
+            max_memory: None,
+        }
+    }
+}
+
+impl WordlistOptions {
+    /// returns true if the line should not be loaded as a word
+    fn is_skipped(&self, line: &[u8]) -> bool {
+        line.is_empty()
+            || (self.skip_blank && line.iter().all(|&c| c == b' ' || c == b'\t'))
+            || matches!(&self.comment_prefix, Some(prefix) if line.starts_with(prefix.as_bytes()))
+    }
+}
+
+/// parses a `-w` value of the form `<filename>[:<encoding>][:<transform>][:reverse]` and returns
+/// the filename with `options` overridden by the suffixes (e.g. `names.txt:latin1:capitalize`).
+/// a suffix which is not a known encoding or transform is kept as part of the filename,
+/// colons in the filename can be escaped with `\:` (e.g. `my\:list.txt:upper`)
+pub fn parse_wordlist_arg(arg: &str, options: &WordlistOptions) -> (String, WordlistOptions) {
+    let mut options = options.clone();
+    let mut fname = arg;
+
+    loop {
+        // find the last unescaped separator
+        let sep_pos = fname
+            .char_indices()
+            .rev()
+            .find(|&(pos, ch)| ch == TRANSFORM_SEPARATOR && !fname[..pos].ends_with('\\'))
+            .map(|(pos, _)| pos);
+        let pos = match sep_pos {
+            Some(pos) => pos,
+            None => break,
+        };
+
+        let suffix = &fname[pos + 1..];
+        if let Some(transform) = WordTransform::from_name(suffix) {
+            options.transform = Some(transform);
+        } else if suffix == "reverse" {
+            options.reverse = true;
+        } else if let Some(encoding) = WordlistEncoding::from_name(suffix) {
+            options.encoding = encoding;
+        } else {
+            break;
+        }
+        fname = &fname[..pos];
+    }
+
+    (fname.replace("\\:", ":"), options)
+}
+
+/// the character encoding of a wordlist file, words are converted to utf-8 when loaded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WordlistEncoding {
+    /// words are loaded as is
+    Utf8,
+    /// every byte is a latin-1 (iso-8859-1) character
+    Latin1,
+    /// words which are not valid utf-8 are decoded as latin-1
+    Auto,
+}
+
+impl WordlistEncoding {
+    pub fn from_name(name: &str) -> Option<WordlistEncoding> {
+        match name {
+            "utf8" => Some(WordlistEncoding::Utf8),
+            "latin1" => Some(WordlistEncoding::Latin1),
+            "auto" => Some(WordlistEncoding::Auto),
+            _ => None,
+        }
+    }
+
+    pub fn decode(&self, word: Vec<u8>) -> Vec<u8> {
+        match self {
+            WordlistEncoding::Utf8 => word,
+            WordlistEncoding::Latin1 => latin1_to_utf8(&word),
+            WordlistEncoding::Auto => match std::str::from_utf8(&word) {
+                Ok(_) => word,
+                Err(_) => latin1_to_utf8(&word),
+            },
+        }
+    }
+}
+
+pub fn latin1_to_utf8(word: &[u8]) -> Vec<u8> {
+    word.iter()
+        .map(|&c| c as char)
+        .collect::<String>()
+        .into_bytes()
+}
+
+/// the most bytes the words of a wordlist can take, the offsets of the words are u32
+const MAX_WORDS_BYTES: usize = u32::MAX as usize;
+
+/// memory of the offset of a word besides its bytes, counted against `WordlistOptions::max_memory`
+const WORD_OFFSET_SIZE: u64 = std::mem::size_of::<u32>() as u64;
+
+/// the memory the words of a wordlist file need at most - the file size without the newlines,
+/// plus an offset per line. `--max-wordlist-memory` counts the words actually loaded
+pub fn estimate_memory<P: AsRef<Path>>(fname: P) -> Result<u64, Error> {
+    let mut fp = File::open(fname)?;
+    let mut buf = vec![0; 64 * 1024];
+    let (mut size, mut lines) = (0u64, 0u64);
+    let mut last = b'\n';
+    loop {
+        let read = fp.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        size += read as u64;
+        lines += buf[..read].iter().filter(|&&b| b == b'\n').count() as u64;
+        last = buf[read - 1];
+    }
+    // the last line may have no newline
+    if last != b'\n' {
+        lines += 1;
+    }
+    Ok(size - lines.min(size) + (lines + 1) * WORD_OFFSET_SIZE)
 }
 
+/// the words in a single buffer in the order they were added, and their offsets grouped by
+/// length, shortest first. the word at position `i` starts at `offsets[i]` and has the length of
+/// its group
 #[derive(Debug)]
 pub struct Wordlist {
-    words_bufs: Vec<WordsBuf>,
+    words: Vec<u8>,
+    /// the start of every word, in iteration order
+    offsets: Vec<u32>,
+    /// the distinct lengths of the words and the position of their first word
+    lens: Vec<(usize, usize)>,
 }
 
 pub struct WordlistIterator<'a> {
     wordlist: &'a Wordlist,
-    wordbuf_pos: usize,
-    word_pos: usize,
+    /// position of the next word
+    pos: usize,
+    /// the group in `Wordlist::lens` of the next word
+    group: usize,
+}
+
+/// appends the words to a single buffer as they come. their lengths are enough to group their
+/// offsets by length once all the words are added
+#[derive(Default)]
+struct WordlistBuilder {
+    words: Vec<u8>,
+    word_lens: Vec<u16>,
+}
+
+impl WordlistBuilder {
+    fn push(&mut self, word: &[u8]) -> Result<(), String> {
+        if self.words.len() + word.len() > MAX_WORDS_BYTES {
+            return Err(format!(
+                "the words are more than {} bytes, the most a wordlist can hold",
+                MAX_WORDS_BYTES
+            ));
+        }
+        let len = u16::try_from(word.len())
+            .map_err(|_| format!("word of {} bytes is too long", word.len()))?;
+        self.words.extend_from_slice(word);
+        self.word_lens.push(len);
+        Ok(())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.word_lens.is_empty()
+    }
+
+    /// places the offsets of the words by length with a counting sort, keeping their order
+    fn build(mut self) -> Wordlist {
+        let max_len = self.word_lens.iter().max().map_or(0, |&len| len as usize);
+        let mut counts = vec![0; max_len + 1];
+        for &len in self.word_lens.iter() {
+            counts[len as usize] += 1;
+        }
+
+        let mut lens = vec![];
+        let mut next = vec![0; max_len + 1];
+        let mut pos = 0;
+        for (len, &count) in counts.iter().enumerate().filter(|(_, &count)| count > 0) {
+            lens.push((len, pos));
+            next[len] = pos;
+            pos += count;
+        }
+
+        let mut offsets = vec![0; self.word_lens.len()];
+        let mut start = 0;
+        for &len in self.word_lens.iter() {
+            offsets[next[len as usize]] = start as u32;
+            next[len as usize] += 1;
+            start += len as usize;
+        }
+
+        self.words.shrink_to_fit();
+        Wordlist {
+            words: self.words,
+            offsets,
+            lens,
+        }
+    }
 }
 
 impl Wordlist {
-    pub fn from_file<P: AsRef<Path>>(fname: P) -> BoxResult<Wordlist> {
+    pub fn from_file<P: AsRef<Path>>(fname: P) -> Result<Wordlist, CrackenError> {
+        Self::from_file_with_options(fname, &WordlistOptions::default())
+    }
+
+    pub fn from_file_with_options<P: AsRef<Path>>(
+        fname: P,
+        options: &WordlistOptions,
+    ) -> Result<Wordlist, CrackenError> {
+        let fname = fname.as_ref();
+        Self::load(fname, options).map_err(|e| CrackenError::wordlist(fname, e))
+    }
+
+    /// errors are reported with the wordlist path by `from_file_with_options`
+    fn load(
+        fname: &Path,
+        options: &WordlistOptions,
+    ) -> Result<Wordlist, Box<dyn std::error::Error + Send + Sync>> {
         let fp = BufReader::new(File::open(fname)?);
-        let mut len2words = HashMap::new();
+        let file_size = fp.get_ref().metadata()?.len();
+        let mut builder = WordlistBuilder::default();
 
-        fp.split(b'\n')
-            .try_for_each::<_, Result<(), std::io::Error>>(|word| {
-                let mut word = word?;
-                if !word.is_empty() {
-                    if word.last() == Some(&b'\n') {
-                        word.pop();
-                    }
+        let mut invalid_lines = vec![];
+        let mut invalid_count = 0;
+        // bytes of the file read so far and of the memory of the words loaded from them
+        let mut read_bytes = 0u64;
+        let mut memory = 0u64;
 
-                    let lenvec = len2words.entry(word.len()).or_insert_with(Vec::new);
-                    lenvec.extend_from_slice(&word);
+        for (line_idx, word) in fp.split(b'\n').enumerate() {
+            let mut word = word?;
+            read_bytes += word.len() as u64 + 1;
 
-                    // avoid small allocations of memory for large wordlists
-                    lenvec.reserve(word.len() * 1024 * 1024);
+            // windows line endings
+            if word.last() == Some(&b'\r') {
+                word.pop();
+            }
+            if options.trim {
+                while matches!(word.last(), Some(b' ') | Some(b'\t')) {
+                    word.pop();
                 }
-                Ok(())
-            })?;
+            }
 
-        len2words
-            .iter_mut()
-            .for_each(|(_, words)| words.shrink_to_fit());
+            if options.is_skipped(&word) {
+                continue;
+            }
+            // NUL bytes are only valid in hex encoded words
+            let has_nul = word.contains(&0);
+            if options.decode_hex {
+                word = match decode_hex_word(word) {
+                    Ok(word) => word,
+                    Err(e) => bail!("line {}: {}", line_idx + 1, e),
+                };
+                if word.is_empty() {
+                    continue;
+                }
+            }
+            let mut word = options.encoding.decode(word);
+            if matches!(&options.filter, Some(filter) if !filter.is_match(&word)) {
+                continue;
+            }
 
-        let mut words_bufs: Vec<_> = len2words
-            .into_iter()
-            .map(|(len, words)| WordsBuf { len, words })
-            .collect();
+            if has_nul || word.len() > options.max_len {
+                invalid_count += 1;
+                if invalid_lines.len() < MAX_REPORTED_INVALID_LINES {
+                    invalid_lines.push((line_idx + 1, word.len(), has_nul));
+                }
+                continue;
+            }
+
+            if let Some(transform) = options.transform {
+                transform.apply(&mut word);
+            }
+            if options.reverse {
+                word = reverse_word(&word);
+            }
+
+            if let Err(e) = builder.push(&word) {
+                bail!(
+                    "line {}: {}; consider --wordlist-sample or --wordlist-filter",
+                    line_idx + 1,
+                    e
+                );
+            }
+            memory += word.len() as u64 + WORD_OFFSET_SIZE;
+            if let Some(max_memory) = options.max_memory {
+                if memory > max_memory {
+                    // the rest of the file likely needs as much memory per byte
+                    let needed =
+                        memory as f64 * file_size.max(read_bytes) as f64 / read_bytes as f64;
+                    bail!(
+                        "would need ~{}, limit is {}; consider --wordlist-sample or --wordlist-filter",
+                        format_bytes(needed as u64),
+                        format_bytes(max_memory)
+                    );
+                }
+            }
+        }
+
+        if invalid_count > 0 {
+            if !options.skip_invalid {
+                let lines: Vec<_> = invalid_lines
+                    .iter()
+                    .map(|&(line, len, has_nul)| {
+                        let nul = if has_nul { ", NUL byte" } else { "" };
+                        format!("line {} ({} bytes{})", line, len, nul)
+                    })
+                    .collect();
+                bail!(
+                    "has {} lines longer than {} bytes or containing a NUL byte: {}{} (use --skip-invalid-lines to skip them)",
+                    invalid_count,
+                    options.max_len,
+                    lines.join(", "),
+                    if invalid_count > lines.len() { ", ..." } else { "" }
+                );
+            }
+            if let Some(log) = &options.skipped_lines {
+                log.record(SkippedLines {
+                    fname: fname.to_path_buf(),
+                    count: invalid_count,
+                    lines: invalid_lines.iter().map(|&(line, _, _)| line).collect(),
+                });
+            }
+        }
+
+        if builder.is_empty() {
+            match &options.filter {
+                Some(filter) => bail!("does not contain any words matching {}", filter),
+                None => bail!("does not contain any words"),
+            }
+        }
 
-        words_bufs.sort_unstable_by(|a, b| a.len.cmp(&b.len));
-        Ok(Wordlist { words_bufs })
+        let wordlist = builder.build();
+        match options.sample {
+            Some(sample) => {
+                let mut rng = match options.seed {
+                    Some(seed) => SplitMix64::new(seed),
+                    None => SplitMix64::from_time(),
+                };
+                Ok(wordlist.sample(sample.sample_len(wordlist.len()), &mut rng))
+            }
+            None => Ok(wordlist),
+        }
+    }
+
+    /// returns a random sample of `count` words, keeping the order of the words.
+    /// uses selection sampling - every word is kept with probability `needed / left`
+    pub fn sample(&self, count: usize, rng: &mut SplitMix64) -> Wordlist {
+        let len = self.len();
+        let mut builder = WordlistBuilder::default();
+        let mut selected = 0;
+
+        for (idx, word) in self.iter().enumerate() {
+            if selected == count {
+                break;
+            }
+            if rng.next_f64() * ((len - idx) as f64) < (count - selected) as f64 {
+                // a sample is never larger than its wordlist
+                builder.push(word).unwrap();
+                selected += 1;
+            }
+        }
+        builder.build()
+    }
+
+    /// creates a new wordlist with `transform` applied to every word. the words are deduplicated
+    /// except for the reversed ones, since many words may share the same slice (e.g. the same 4
+    /// chars prefix) or the same shifted or adjacent keys word
+    pub fn transformed(&self, transform: TokenTransform) -> BoxResult<Wordlist> {
+        let mut words: Vec<_> = self.iter().flat_map(|word| transform.apply(word)).collect();
+        if transform != TokenTransform::Reverse {
+            let mut seen = HashSet::with_capacity(words.len());
+            words.retain(|word| seen.insert(word.clone()));
+        }
+        Self::from_words(words.iter().map(|word| word.as_slice()))
+    }
+
+    /// creates an in-memory wordlist from the given words, skipping empty ones
+    pub fn from_words<W: AsRef<[u8]>, I: IntoIterator<Item = W>>(words: I) -> BoxResult<Wordlist> {
+        let mut builder = WordlistBuilder::default();
+        for word in words.into_iter() {
+            let word = word.as_ref();
+            if !word.is_empty() {
+                builder.push(word)?;
+            }
+        }
+
+        if builder.is_empty() {
+            bail!("wordlist must contain at least one non empty word");
+        }
+        Ok(builder.build())
     }
 
     #[inline]
     pub fn iter(&self) -> WordlistIterator {
+        self.iter_from(0)
+    }
+
+    /// returns an iterator starting at the word at position `index` in iteration order, an
+    /// empty one past the last word
+    pub fn iter_from(&self, index: usize) -> WordlistIterator<'_> {
+        let pos = index.min(self.len());
         WordlistIterator {
             wordlist: self,
-            wordbuf_pos: 0,
-            word_pos: 0,
+            pos,
+            group: self.group_of(pos),
         }
     }
 
     #[inline]
     pub fn len(&self) -> usize {
-        self.words_bufs
+        self.offsets.len()
+    }
+
+    /// returns the group in `lens` of the word at position `index`, the last one past the end
+    #[inline]
+    fn group_of(&self, index: usize) -> usize {
+        self.lens
+            .partition_point(|&(_, first)| first <= index)
+            .saturating_sub(1)
+    }
+
+    /// returns the word at position `index` in iteration order
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        if index < self.len() {
+            Some(&self[index])
+        } else {
+            None
+        }
+    }
+
+    /// returns the position of the first occurrence of `word` in iteration order
+    pub fn index_of(&self, word: &[u8]) -> Option<usize> {
+        let group = self.lens.iter().position(|&(len, _)| len == word.len())?;
+        let start = self.lens[group].1;
+        let end = self
+            .lens
+            .get(group + 1)
+            .map_or(self.len(), |&(_, start)| start);
+        self.offsets[start..end]
             .iter()
-            .map(|wb| wb.words.len() / wb.len)
-            .sum()
+            .position(|&offset| &self.words[offset as usize..offset as usize + word.len()] == word)
+            .map(|idx| start + idx)
+    }
+
+    /// returns the distinct lengths of the words, shortest first
+    pub fn word_lens(&self) -> impl Iterator<Item = usize> + '_ {
+        self.lens.iter().map(|&(len, _)| len)
+    }
+
+    /// returns the total size of all the words in bytes
+    pub fn total_bytes(&self) -> usize {
+        self.words.len()
+    }
+
+    /// returns the length of the longest word
+    #[inline]
+    pub fn max_len(&self) -> usize {
+        self.lens.last().map_or(0, |&(len, _)| len)
     }
 
     #[inline]
@@ -81,48 +709,225 @@ impl Wordlist {
     }
 }
 
+impl Index<usize> for Wordlist {
+    type Output = [u8];
+
+    #[inline]
+    fn index(&self, index: usize) -> &Self::Output {
+        let start = self.offsets[index] as usize;
+        &self.words[start..start + self.lens[self.group_of(index)].0]
+    }
+}
+
 impl<'a> Iterator for WordlistIterator<'a> {
     type Item = &'a [u8];
 
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        let mut word_buf = &self.wordlist.words_bufs[self.wordbuf_pos];
+        let wordlist = self.wordlist;
+        if self.pos >= wordlist.len() {
+            return None;
+        }
+        // the groups are never empty, the next word is at most in the next one
+        if matches!(wordlist.lens.get(self.group + 1), Some(&(_, first)) if first == self.pos) {
+            self.group += 1;
+        }
+        let start = wordlist.offsets[self.pos] as usize;
+        self.pos += 1;
+        Some(&wordlist.words[start..start + wordlist.lens[self.group].0])
+    }
+}
+
+impl<'a> WordlistIterator<'a> {
+    /// returns the current length of word of this iterator
+    #[inline]
+    pub fn current_len(&self) -> usize {
+        self.wordlist.lens[self.group].0
+    }
+}
 
-        // advance next word
-        if self.word_pos < word_buf.words.len() {
-            let prev_word_pos = self.word_pos;
-            self.word_pos += word_buf.len;
+/// the counts of `merge_wordlists`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MergeCounts {
+    /// non empty lines of all the wordlists
+    pub input_lines: u64,
+    pub unique_lines: u64,
+    pub duplicates: u64,
+}
+
+/// where the first occurrence of a line was read, to compare the lines of the same hash
+#[derive(Clone, Copy)]
+struct LineRef {
+    file: u32,
+    offset: u64,
+}
+
+/// reads the line starting at `offset` of `reader` into `line`, without the line ending
+fn read_line_at(
+    reader: &mut BufReader<File>,
+    offset: u64,
+    line: &mut Vec<u8>,
+) -> Result<(), Error> {
+    reader.seek(SeekFrom::Start(offset))?;
+    line.clear();
+    reader.read_until(b'\n', line)?;
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(())
+}
 
-            Some(&word_buf.words[prev_word_pos..self.word_pos])
+fn line_hash(line: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(line);
+    hasher.finish()
+}
 
-        // finished the current wordbuf - advance to next one
-        } else if self.wordbuf_pos < self.wordlist.words_bufs.len() - 1 {
-            self.wordbuf_pos += 1;
-            word_buf = &self.wordlist.words_bufs[self.wordbuf_pos];
-            self.word_pos = word_buf.len;
+/// writes the lines of the `fnames` wordlists in order, every distinct line once at its first
+/// occurrence. windows line endings are removed and empty lines skipped. only the hash and the
+/// position of every unique line are kept in memory, lines of the same hash are read again from
+/// their wordlist to tell the duplicates from the hash collisions
+pub fn merge_wordlists<P: AsRef<Path>, W: Write>(
+    fnames: &[P],
+    out: &mut W,
+) -> Result<MergeCounts, CrackenError> {
+    let mut counts = MergeCounts::default();
+    let mut seen: HashMap<u64, LineRef> = HashMap::new();
+    // the other unique lines of a hash already in `seen`, rare with 64 bits hashes
+    let mut collisions: HashMap<u64, Vec<LineRef>> = HashMap::new();
+    let mut readers = Vec::with_capacity(fnames.len());
+    let mut previous = Vec::with_capacity(256);
 
-            Some(&word_buf.words[..self.word_pos])
+    for (file_idx, fname) in fnames.iter().enumerate() {
+        let fname = fname.as_ref();
+        let wordlist_error = |e: Error| CrackenError::wordlist(fname, e);
+        let fp = File::open(fname).map_err(wordlist_error)?;
+        // a second reader of the wordlist to read its lines again
+        readers.push(BufReader::new(File::open(fname).map_err(wordlist_error)?));
+        let mut offset = 0;
 
-        // finished all wordbufs on the wordlist - no next item
-        } else {
-            None
+        for line in RawFileReader::new(fp) {
+            let mut line = line.map_err(wordlist_error)?;
+            let line_ref = LineRef {
+                file: file_idx as u32,
+                offset,
+            };
+            // the offset of the next line, there is none after a last line without a newline
+            offset += line.len() as u64 + 1;
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if line.is_empty() {
+                continue;
+            }
+            counts.input_lines += 1;
+
+            let hash = line_hash(&line);
+            let first = match seen.get(&hash) {
+                Some(&first) => first,
+                None => {
+                    seen.insert(hash, line_ref);
+                    counts.unique_lines += 1;
+                    out.write_all(&line)?;
+                    out.write_all(b"\n")?;
+                    continue;
+                }
+            };
+            let mut duplicate = false;
+            let others = collisions
+                .get(&hash)
+                .map(|refs| refs.as_slice())
+                .unwrap_or(&[]);
+            for other in std::iter::once(&first).chain(others) {
+                let reader = &mut readers[other.file as usize];
+                read_line_at(reader, other.offset, &mut previous)
+                    .map_err(|e| CrackenError::wordlist(fnames[other.file as usize].as_ref(), e))?;
+                if previous == line {
+                    duplicate = true;
+                    break;
+                }
+            }
+            if duplicate {
+                counts.duplicates += 1;
+            } else {
+                collisions.entry(hash).or_default().push(line_ref);
+                counts.unique_lines += 1;
+                out.write_all(&line)?;
+                out.write_all(b"\n")?;
+            }
         }
     }
+    Ok(counts)
 }
 
-impl<'a> WordlistIterator<'a> {
-    /// returns the current length of word of this iterator
-    #[inline]
-    pub fn current_len(&self) -> usize {
-        let word_buf = &self.wordlist.words_bufs[self.wordbuf_pos];
-        word_buf.len
+/// the counts of `subtract_wordlist`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SubtractCounts {
+    /// non empty lines of the base wordlist
+    pub input_lines: u64,
+    pub removed: u64,
+    pub remaining: u64,
+}
+
+/// writes the lines of the `base` wordlist for which `removed` is false, in order. windows line
+/// endings are removed and empty lines skipped
+pub fn subtract_wordlist<P: AsRef<Path>, W: Write, F: Fn(&[u8]) -> bool>(
+    base: P,
+    removed: F,
+    out: &mut W,
+) -> Result<SubtractCounts, CrackenError> {
+    let base = base.as_ref();
+    let mut counts = SubtractCounts::default();
+    for line in read_lines(base).map_err(|e| CrackenError::wordlist(base, e))? {
+        let line = line.map_err(|e| CrackenError::wordlist(base, e))?;
+        if line.is_empty() {
+            continue;
+        }
+        counts.input_lines += 1;
+        if removed(&line) {
+            counts.removed += 1;
+            continue;
+        }
+        counts.remaining += 1;
+        out.write_all(&line)?;
+        out.write_all(b"\n")?;
     }
+    Ok(counts)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::test_util::wordlist_fname;
 
-    use super::Wordlist;
+    use regex::bytes::Regex;
+
+    use super::{
+        adjacent_words, estimate_memory, merge_wordlists, parse_wordlist_arg, reverse_word,
+        slice_word, subtract_wordlist, MergeCounts, SkippedLines, SkippedLinesLog, SubtractCounts,
+        TokenTransform, WordTransform, Wordlist, WordlistEncoding, WordlistOptions, WordlistSample,
+    };
+    use crate::exclude::{CaseInsensitive, ExcludeSet};
+    use crate::helpers::SplitMix64;
+    use crate::keyboard::QWERTY_ROW_NEIGHBOURS;
+
+    #[test]
+    fn test_wordlist_from_words() {
+        let tokens: Vec<&[u8]> = vec![b"2024", b"!", b"", b"123", b"!!", b"?"];
+        let wordlist = Wordlist::from_words(tokens).unwrap();
+        let words: Vec<_> = wordlist.iter().collect();
+        let expected: Vec<&[u8]> = vec![b"!", b"?", b"!!", b"123", b"2024"];
+        assert_eq!(words, expected);
+        assert_eq!(wordlist.len(), 5);
+        for index in 0..=5 {
+            let words: Vec<_> = wordlist.iter_from(index).collect();
+            assert_eq!(words, expected[index..], "{}", index);
+        }
+
+        assert!(Wordlist::from_words(vec![&b""[..]]).is_err());
+    }
 
     #[test]
     fn test_wordlist_from_file() {
@@ -148,4 +953,619 @@ password
             .collect();
         assert_eq!(words, expected);
     }
+
+    #[test]
+    fn test_word_transforms() {
+        let cases = vec![
+            (WordTransform::Lower, "McDonald", "mcdonald"),
+            (WordTransform::Upper, "McDonald", "MCDONALD"),
+            (WordTransform::Capitalize, "mcDONALD", "Mcdonald"),
+            (WordTransform::Invert, "McDonald", "mCdONALD"),
+            (WordTransform::Upper, "émile", "éMILE"),
+            (WordTransform::Capitalize, "émile", "émile"),
+            (WordTransform::Invert, "Ünïcode1", "ÜNïCODE1"),
+        ];
+        for (transform, word, expected) in cases {
+            let mut word_ = word.as_bytes().to_vec();
+            transform.apply(&mut word_);
+            assert_eq!(
+                String::from_utf8(word_).unwrap(),
+                expected,
+                "{:?} {}",
+                transform,
+                word
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_wordlist_arg() {
+        let cases = vec![
+            ("names.txt", "names.txt", None),
+            (
+                "names.txt:capitalize",
+                "names.txt",
+                Some(WordTransform::Capitalize),
+            ),
+            (
+                "surnames.txt:upper",
+                "surnames.txt",
+                Some(WordTransform::Upper),
+            ),
+            ("a:b.txt:lower", "a:b.txt", Some(WordTransform::Lower)),
+            ("a:b.txt", "a:b.txt", None),
+            (
+                "C:\\words.txt:invert",
+                "C:\\words.txt",
+                Some(WordTransform::Invert),
+            ),
+            (
+                "my\\:list.txt:upper",
+                "my:list.txt",
+                Some(WordTransform::Upper),
+            ),
+            ("list\\:upper", "list:upper", None),
+            ("list.txt:unknown", "list.txt:unknown", None),
+        ];
+        for (arg, fname, transform) in cases {
+            let (fname_, options) = parse_wordlist_arg(arg, &WordlistOptions::default());
+            assert_eq!(fname_, fname, "{}", arg);
+            assert_eq!(options.transform, transform, "{}", arg);
+        }
+    }
+
+    #[test]
+    fn test_parse_wordlist_arg_encoding() {
+        let defaults = WordlistOptions {
+            encoding: WordlistEncoding::Auto,
+            ..Default::default()
+        };
+        let cases = vec![
+            ("names.txt", "names.txt", WordlistEncoding::Auto, None),
+            (
+                "names.txt:latin1",
+                "names.txt",
+                WordlistEncoding::Latin1,
+                None,
+            ),
+            (
+                "names.txt:utf8:upper",
+                "names.txt",
+                WordlistEncoding::Utf8,
+                Some(WordTransform::Upper),
+            ),
+            (
+                "a:b.txt:upper:latin1",
+                "a:b.txt",
+                WordlistEncoding::Latin1,
+                Some(WordTransform::Upper),
+            ),
+        ];
+        for (arg, fname, encoding, transform) in cases {
+            let (fname_, options) = parse_wordlist_arg(arg, &defaults);
+            assert_eq!(fname_, fname, "{}", arg);
+            assert_eq!(options.encoding, encoding, "{}", arg);
+            assert_eq!(options.transform, transform, "{}", arg);
+        }
+    }
+
+    #[test]
+    fn test_wordlist_from_file_encoding() {
+        let cases = vec![
+            ("wordlist-latin1.txt", WordlistEncoding::Latin1),
+            ("wordlist-latin1.txt", WordlistEncoding::Auto),
+            ("wordlist-utf8.txt", WordlistEncoding::Utf8),
+            ("wordlist-utf8.txt", WordlistEncoding::Auto),
+            ("wordlist-mixed-encoding.txt", WordlistEncoding::Auto),
+        ];
+        for (fname, encoding) in cases {
+            let options = WordlistOptions {
+                encoding,
+                ..Default::default()
+            };
+            let words = load_words(fname, &options);
+            assert!(
+                words.contains(&"émile".to_owned()),
+                "{} {:?}",
+                fname,
+                encoding
+            );
+            assert!(
+                words.contains(&"zoë".to_owned()),
+                "{} {:?}",
+                fname,
+                encoding
+            );
+        }
+
+        // utf-8 decoded as latin-1 is mojibake
+        let options = WordlistOptions {
+            encoding: WordlistEncoding::Latin1,
+            ..Default::default()
+        };
+        let words = load_words("wordlist-utf8.txt", &options);
+        assert!(words.contains(&"Ã©mile".to_owned()));
+    }
+
+    #[test]
+    fn test_wordlist_from_file_transform() {
+        let options = WordlistOptions {
+            transform: Some(WordTransform::Upper),
+            ..Default::default()
+        };
+        let wordlist =
+            Wordlist::from_file_with_options(&wordlist_fname("wordlist-utf8.txt"), &options)
+                .unwrap();
+        let words = wordlist
+            .iter()
+            .map(|c| String::from_utf8(c.to_vec()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(words, vec!["JOHN", "ZOë", "éMILE", "MCDONALD"]);
+    }
+
+    fn load_words(fname: &str, options: &WordlistOptions) -> Vec<String> {
+        Wordlist::from_file_with_options(&wordlist_fname(fname), options)
+            .unwrap()
+            .iter()
+            .map(|c| String::from_utf8(c.to_vec()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_wordlist_from_file_crlf() {
+        let words = load_words("wordlist-crlf.txt", &WordlistOptions::default());
+        assert_eq!(words, vec!["john", "zoe\t", "emile ", "McDonald"]);
+        assert!(words.iter().all(|w| !w.contains('\r')));
+
+        let options = WordlistOptions {
+            trim: true,
+            ..Default::default()
+        };
+        let words = load_words("wordlist-crlf.txt", &options);
+        assert_eq!(words, vec!["zoe", "john", "emile", "McDonald"]);
+    }
+
+    #[test]
+    fn test_wordlist_from_file_no_trailing_newline() {
+        let words = load_words("wordlist-no-newline.txt", &WordlistOptions::default());
+        assert_eq!(words, vec!["john", "emile", "McDonald"]);
+    }
+
+    #[test]
+    fn test_wordlist_from_file_blank_lines_and_comments() {
+        // blank lines in the middle of the list are not loaded as words
+        let words = load_words("wordlist-comments.txt", &WordlistOptions::default());
+        assert_eq!(words, vec!["john", "emile", "# names", "McDonald"]);
+
+        let options = WordlistOptions {
+            skip_blank: false,
+            ..Default::default()
+        };
+        let words = load_words("wordlist-comments.txt", &options);
+        assert_eq!(words, vec!["  \t", "john", "emile", "# names", "McDonald"]);
+
+        let options = WordlistOptions {
+            comment_prefix: Some("#".to_owned()),
+            ..Default::default()
+        };
+        let words = load_words("wordlist-comments.txt", &options);
+        assert_eq!(words, vec!["john", "emile", "McDonald"]);
+    }
+
+    #[test]
+    fn test_wordlist_from_file_no_words() {
+        let options = WordlistOptions {
+            comment_prefix: Some("#".to_owned()),
+            ..Default::default()
+        };
+        let res = Wordlist::from_file_with_options(
+            &wordlist_fname("wordlist-only-comments.txt"),
+            &options,
+        );
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains("does not contain any words"), "{}", err);
+    }
+
+    #[test]
+    fn test_merge_wordlists() {
+        let fnames = [wordlist_fname("merge-a.txt"), wordlist_fname("merge-b.txt")];
+        let mut out = vec![];
+        let counts = merge_wordlists(&fnames, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "password\n123456\nqwerty\ndragon\nmonkey\nletmein\n"
+        );
+        let expected = MergeCounts {
+            input_lines: 9,
+            unique_lines: 6,
+            duplicates: 3,
+        };
+        assert_eq!(counts, expected);
+
+        // the first occurrence order of the other wordlist first
+        let mut out = vec![];
+        let counts = merge_wordlists(&[&fnames[1], &fnames[0]], &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "dragon\n123456\nmonkey\nqwerty\nletmein\npassword\n"
+        );
+        assert_eq!(counts, expected);
+
+        let err = merge_wordlists(&["/no/such/wordlist.txt"], &mut std::io::sink()).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("wordlist /no/such/wordlist.txt: "),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_subtract_wordlist() {
+        let base = wordlist_fname("merge-b.txt");
+        let remove = std::env::temp_dir().join("cracken-test-subtract.txt");
+        std::fs::write(&remove, "QWERTY\r\nmonkey\n123456\n").unwrap();
+        let subtract = |removed: &dyn Fn(&[u8]) -> bool| {
+            let mut out = vec![];
+            let counts = subtract_wordlist(&base, removed, &mut out).unwrap();
+            (String::from_utf8(out).unwrap(), counts)
+        };
+
+        // the remaining lines keep the order of the base wordlist
+        let exact = ExcludeSet::from_file(&remove, false).unwrap();
+        let (remaining, counts) = subtract(&|line| exact.contains(line));
+        assert_eq!(remaining, "dragon\nqwerty\nletmein\n");
+        let expected = SubtractCounts {
+            input_lines: 5,
+            removed: 2,
+            remaining: 3,
+        };
+        assert_eq!(counts, expected);
+
+        let ignore_case = CaseInsensitive::from_file(&remove, false).unwrap();
+        let (remaining, counts) = subtract(&|line| ignore_case.contains(line));
+        assert_eq!(remaining, "dragon\nletmein\n");
+        assert_eq!(counts.removed, 3);
+        std::fs::remove_file(remove).unwrap();
+    }
+
+    #[test]
+    fn test_wordlist_from_file_max_memory() {
+        let fname = std::env::temp_dir().join("cracken-test-max-memory.txt");
+        let words: String = (0..1000).map(|i| format!("word{:04}\n", i)).collect();
+        std::fs::write(&fname, words).unwrap();
+
+        // 1000 words of 8 bytes and their offsets of 4 bytes
+        assert_eq!(estimate_memory(&fname).unwrap(), 12004);
+        let options = WordlistOptions {
+            max_memory: Some(1000),
+            ..Default::default()
+        };
+        let err = Wordlist::from_file_with_options(&fname, &options)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(fname.to_str().unwrap()), "{}", err);
+        assert!(
+            err.ends_with("would need ~11.7 KiB, limit is 1000 B; consider --wordlist-sample or --wordlist-filter"),
+            "{}",
+            err
+        );
+
+        let options = WordlistOptions {
+            max_memory: Some(12000),
+            ..Default::default()
+        };
+        let wordlist = Wordlist::from_file_with_options(&fname, &options).unwrap();
+        assert_eq!(wordlist.len(), 1000);
+        std::fs::remove_file(fname).unwrap();
+    }
+
+    #[test]
+    fn test_wordlist_from_file_invalid_lines() {
+        let fname = wordlist_fname("wordlist-invalid-lines.txt");
+        let options = WordlistOptions {
+            max_len: 100,
+            ..Default::default()
+        };
+        let err = Wordlist::from_file_with_options(&fname, &options)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("has 2 lines longer than 100 bytes"), "{}", err);
+        assert!(
+            err.contains("line 3 (500 bytes), line 5 (8 bytes, NUL byte)"),
+            "{}",
+            err
+        );
+
+        // 500 bytes are allowed by default
+        let err = Wordlist::from_file(&fname).unwrap_err().to_string();
+        assert!(err.contains("has 1 lines"), "{}", err);
+        assert!(err.contains("line 5 (8 bytes, NUL byte)"), "{}", err);
+
+        let options = WordlistOptions {
+            max_len: 100,
+            skip_invalid: true,
+            ..Default::default()
+        };
+        let words = load_words("wordlist-invalid-lines.txt", &options);
+        assert_eq!(words, vec!["john", "emile", "McDonald"]);
+
+        // the skipped lines are recorded once for the caller to report them
+        let log = SkippedLinesLog::default();
+        let options = WordlistOptions {
+            skipped_lines: Some(log.clone()),
+            ..options
+        };
+        for _ in 0..2 {
+            Wordlist::from_file_with_options(&fname, &options).unwrap();
+        }
+        assert_eq!(
+            log.entries(),
+            vec![SkippedLines {
+                fname: fname.clone(),
+                count: 2,
+                lines: vec![3, 5],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_wordlist_storage_compact() {
+        let wordlist = Wordlist::from_file(&wordlist_fname("wordlist-no-newline.txt")).unwrap();
+        let words: Vec<_> = wordlist.iter().collect();
+        let words_bytes: usize = words.iter().map(|w| w.len()).sum();
+        assert_eq!(wordlist.words.len(), words_bytes);
+        assert_eq!(wordlist.offsets.len(), words.len());
+        assert_eq!(wordlist.lens.len(), 3);
+        assert_eq!(words.last().unwrap(), b"McDonald");
+        // the last word ends at the end of the buffer
+        assert_eq!(&wordlist[words.len() - 1], b"McDonald");
+        assert_eq!(wordlist.get(words.len()), None);
+        for (idx, word) in words.iter().enumerate() {
+            assert_eq!(&wordlist[idx], *word);
+            assert_eq!(wordlist.index_of(word), Some(idx));
+        }
+        assert_eq!(wordlist.iter_from(words.len()).next(), None);
+        assert_eq!(wordlist.iter_from(words.len()).current_len(), 8);
+    }
+
+    #[test]
+    fn test_wordlist_from_file_hex() {
+        let fname = wordlist_fname("wordlist-hex.txt");
+        let words: Vec<_> = Wordlist::from_file(&fname)
+            .unwrap()
+            .iter()
+            .map(|w| w.to_vec())
+            .collect();
+        let expected: Vec<&[u8]> = vec![b"\n", b"\x00\xff", b"ABC", b"abc\n", b"plain", b"$HEX[]"];
+        assert_eq!(words, expected);
+
+        let options = WordlistOptions {
+            decode_hex: false,
+            ..Default::default()
+        };
+        let words = load_words("wordlist-hex.txt", &options);
+        assert!(words.contains(&"$HEX[0a]".to_owned()));
+        assert_eq!(words.len(), 6);
+
+        let err = Wordlist::from_file(&wordlist_fname("wordlist-hex-invalid.txt"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("line 2: odd number of hex digits"), "{}", err);
+    }
+
+    #[test]
+    fn test_wordlist_from_file_filter() {
+        let options = WordlistOptions {
+            filter: Some(Regex::new("^[a-z]{4,6}$").unwrap()),
+            ..Default::default()
+        };
+        let words = load_words("wordlist1.txt", &options);
+        assert_eq!(words, vec!["qwerty"]);
+
+        let options = WordlistOptions {
+            filter: Some(Regex::new("^[0-9]+$").unwrap()),
+            ..Default::default()
+        };
+        let words = load_words("wordlist1.txt", &options);
+        assert_eq!(
+            words,
+            vec![
+                "12345",
+                "123456",
+                "123123",
+                "111111",
+                "1234567",
+                "12345678",
+                "123456789"
+            ]
+        );
+
+        let options = WordlistOptions {
+            filter: Some(Regex::new("^[A-Z]+$").unwrap()),
+            ..Default::default()
+        };
+        let err = Wordlist::from_file_with_options(&wordlist_fname("wordlist1.txt"), &options)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("does not contain any words matching ^[A-Z]+$"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_reverse_word() {
+        assert_eq!(reverse_word(b"password"), b"drowssap");
+        assert_eq!(reverse_word("émile".as_bytes()), "elimé".as_bytes());
+        assert_eq!(reverse_word("zoë€".as_bytes()), "€ëoz".as_bytes());
+        // not valid utf-8 - reversed by bytes
+        assert_eq!(reverse_word(b"ab\xe9\xff"), b"\xff\xe9ba");
+        assert_eq!(reverse_word(b""), b"");
+    }
+
+    #[test]
+    fn test_wordlist_reverse() {
+        let options = WordlistOptions {
+            reverse: true,
+            ..Default::default()
+        };
+        let words = load_words("wordlist-utf8.txt", &options);
+        assert_eq!(words, vec!["nhoj", "ëoz", "elimé", "dlanoDcM"]);
+
+        let (fname, options) = parse_wordlist_arg("words.txt:reverse:upper", &Default::default());
+        assert_eq!(fname, "words.txt");
+        assert!(options.reverse);
+        assert_eq!(options.transform, Some(WordTransform::Upper));
+
+        let wordlist = Wordlist::from_file(&wordlist_fname("wordlist-utf8.txt")).unwrap();
+        let reversed = wordlist.transformed(TokenTransform::Reverse).unwrap();
+        let words: Vec<_> = reversed
+            .iter()
+            .map(|w| String::from_utf8(w.to_vec()).unwrap())
+            .collect();
+        assert_eq!(words, vec!["nhoj", "ëoz", "elimé", "dlanoDcM"]);
+    }
+
+    #[test]
+    fn test_slice_word() {
+        let cases: Vec<(&str, usize, Option<usize>, &str)> = vec![
+            ("michael", 0, Some(4), "mich"),
+            ("michael", 2, None, "chael"),
+            ("michael", 1, Some(5), "icha"),
+            ("jo", 0, Some(4), "jo"),
+            ("jo", 5, None, ""),
+            ("michael", 4, Some(2), ""),
+            ("émile", 0, Some(2), "ém"),
+            ("zoë€", 2, None, "ë€"),
+        ];
+        for (word, start, end, expected) in cases {
+            assert_eq!(
+                slice_word(word.as_bytes(), start, end),
+                expected.as_bytes(),
+                "{}",
+                word
+            );
+        }
+        // not valid utf-8 - sliced by bytes
+        assert_eq!(slice_word(b"\xe9mile", 0, Some(2)), b"\xe9m");
+    }
+
+    #[test]
+    fn test_keyboard_transforms() {
+        assert_eq!(
+            TokenTransform::Shift.apply(b"pass1[x]"),
+            vec![b"PASS!{X}".to_vec()]
+        );
+        assert_eq!(
+            TokenTransform::Shift.apply("émile".as_bytes()),
+            vec!["éMILE".as_bytes().to_vec()]
+        );
+        let adjacent: Vec<_> = TokenTransform::Adjacent
+            .apply(b"pass")
+            .into_iter()
+            .map(|word| String::from_utf8(word).unwrap())
+            .collect();
+        assert_eq!(
+            adjacent,
+            ["oass", "[ass", "psss", "paas", "pads", "pasa", "pasd"]
+        );
+        assert_eq!(
+            adjacent_words(b"qwerty", &QWERTY_ROW_NEIGHBOURS, 3),
+            vec![b"wwerty".to_vec(), b"qqerty".to_vec(), b"qeerty".to_vec()]
+        );
+        assert!(TokenTransform::Adjacent.apply(b" ").is_empty());
+
+        let wordlist = Wordlist::from_file(&wordlist_fname("wordlist1.txt")).unwrap();
+        let shifted = wordlist.transformed(TokenTransform::Shift).unwrap();
+        assert_eq!(shifted.len(), 10);
+        assert!(shifted.index_of(b"PASSWORD").is_some());
+        assert!(shifted.index_of(b"!@#$%^").is_some());
+        // the adjacent keys words of all the words, deduplicated
+        let adjacent = wordlist.transformed(TokenTransform::Adjacent).unwrap();
+        assert_eq!(adjacent.len(), 131);
+        assert!(adjacent.index_of(b"[assword").is_some());
+        assert!(adjacent.index_of(b"password").is_none());
+        let again = wordlist.transformed(TokenTransform::Adjacent).unwrap();
+        assert!(adjacent.iter().eq(again.iter()));
+    }
+
+    #[test]
+    fn test_wordlist_slice_dedup() {
+        let wordlist = Wordlist::from_file(&wordlist_fname("names.txt")).unwrap();
+        assert_eq!(wordlist.len(), 9);
+
+        let prefixes = wordlist
+            .transformed(TokenTransform::Slice {
+                start: 0,
+                end: Some(4),
+            })
+            .unwrap();
+        let words: Vec<_> = prefixes.iter().collect();
+        let expected: Vec<&[u8]> = vec![b"john", b"anna", b"mich"];
+        assert_eq!(words, expected);
+
+        let suffixes = wordlist
+            .transformed(TokenTransform::Slice {
+                start: 4,
+                end: None,
+            })
+            .unwrap();
+        assert_eq!(suffixes.len(), 7);
+
+        // all the slices are empty
+        assert!(wordlist
+            .transformed(TokenTransform::Slice {
+                start: 10,
+                end: None
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_wordlist_sample() {
+        let wordlist = Wordlist::from_file(&wordlist_fname("wordlist1.txt")).unwrap();
+        let all_words: Vec<_> = wordlist.iter().collect();
+
+        for count in 1..=10 {
+            let sample = wordlist.sample(count, &mut SplitMix64::new(count as u64));
+            let words: Vec<_> = sample.iter().collect();
+            assert_eq!(words.len(), count);
+
+            // sampled words keep their relative order
+            let positions: Vec<_> = words
+                .iter()
+                .map(|w| all_words.iter().position(|x| x == w).unwrap())
+                .collect();
+            assert!(positions.windows(2).all(|p| p[0] < p[1]), "{:?}", positions);
+        }
+
+        assert_eq!(WordlistSample::Fraction(0.01).sample_len(1000), 10);
+        assert_eq!(WordlistSample::Fraction(0.01).sample_len(10), 1);
+        assert_eq!(WordlistSample::Fraction(1.0).sample_len(10), 10);
+        assert_eq!(WordlistSample::Count(1000).sample_len(10), 10);
+        assert_eq!(WordlistSample::Count(3).sample_len(10), 3);
+    }
+
+    #[test]
+    fn test_wordlist_from_file_sample_seed() {
+        let options = WordlistOptions {
+            sample: Some(WordlistSample::Fraction(0.3)),
+            seed: Some(1337),
+            ..Default::default()
+        };
+        let words = load_words("wordlist1.txt", &options);
+        assert_eq!(words, load_words("wordlist1.txt", &options));
+        assert_eq!(words, vec!["qwerty", "111111", "password"]);
+
+        let options = WordlistOptions {
+            sample: Some(WordlistSample::Count(4)),
+            seed: Some(1337),
+            ..Default::default()
+        };
+        let words = load_words("wordlist1.txt", &options);
+        assert_eq!(words.len(), 4);
+        assert_eq!(words, load_words("wordlist1.txt", &options));
+    }
 }
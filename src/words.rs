@@ -0,0 +1,510 @@
+use std::cmp::max;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use num_bigint::BigUint;
+
+use crate::builder::GeneratorBuilder;
+use crate::error::CrackenError;
+use crate::generators::WordGenerator;
+
+/// number of generated chunks queued before the generating thread blocks
+const CHUNKS_QUEUE_SIZE: usize = 16;
+/// most words generated at once for `next_back`
+const BACK_CHUNK_WORDS: u64 = 1024;
+
+/// builds the generator of a `Words` on its thread
+type BuildFn = Box<dyn FnOnce() -> Result<Box<dyn WordGenerator>, CrackenError> + Send>;
+
+/// iterates the words of a generator one by one (without their newlines). the generator runs
+/// on its own thread and is stopped when `Words` is dropped. the words can be taken from both
+/// ends, a second generator on its own thread generates the words before the back with
+/// `WordGenerator::gen_range` once `next_back` is first called. `len` is the number of words
+/// left by `combinations`, saturated to `usize::MAX`
+///
+/// ```
+/// use cracken::builder::GeneratorBuilder;
+/// use cracken::words::Words;
+///
+/// let words = Words::new(GeneratorBuilder::new().mask("?d?d").skip(98)).unwrap();
+/// assert_eq!(words.collect::<Vec<_>>(), vec![b"98".to_vec(), b"99".to_vec()]);
+/// ```
+pub struct Words {
+    combinations: BigUint,
+    chunks: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+    worker: Option<JoinHandle<()>>,
+    /// number of words taken from the front
+    front: u64,
+    /// index of the word after the last one left, the words `front..back` are left
+    back: u64,
+    /// the words before `back` already generated, the last one is taken first
+    back_words: Vec<Vec<u8>>,
+    /// builds the generator of the back worker, taken once it is started
+    build_back: Option<BuildFn>,
+    back_worker: Option<BackWorker>,
+}
+
+/// generates the ranges of words requested by `Words::next_back` on its own thread
+struct BackWorker {
+    ranges: SyncSender<(u64, u64)>,
+    words: Receiver<Vec<u8>>,
+    handle: JoinHandle<()>,
+}
+
+impl BackWorker {
+    fn start(build: BuildFn) -> BackWorker {
+        let (ranges_tx, ranges_rx) = sync_channel::<(u64, u64)>(1);
+        let (words_tx, words_rx) = sync_channel(1);
+        let handle = thread::spawn(move || {
+            // a failure closes the channel, the back is then never reached
+            let word_gen = match build() {
+                Ok(word_gen) => word_gen,
+                Err(_) => return,
+            };
+            for (start, end) in ranges_rx.iter() {
+                let mut words = vec![];
+                if word_gen.gen_range(start, end, &mut words).is_err() {
+                    return;
+                }
+                if words_tx.send(words).is_err() {
+                    return;
+                }
+            }
+        });
+        BackWorker {
+            ranges: ranges_tx,
+            words: words_rx,
+            handle,
+        }
+    }
+}
+
+impl Words {
+    /// builds the generator on a new thread, returning once it is ready to generate
+    pub fn new(builder: GeneratorBuilder<'static>) -> Result<Words, CrackenError> {
+        Words::from_generator(move || builder.build())
+    }
+
+    /// same as `new` for generators the builder doesn't support, `build` runs on the new thread
+    /// and a clone of it on the thread of `next_back`. the words filtered by the repeat limits
+    /// are counted by `len` and can't be taken from the back
+    pub fn from_generator<F>(build: F) -> Result<Words, CrackenError>
+    where
+        F: FnOnce() -> Result<Box<dyn WordGenerator>, CrackenError> + Clone + Send + 'static,
+    {
+        let build_back: BuildFn = Box::new(build.clone());
+        let (ready_tx, ready_rx) = sync_channel(1);
+        let (chunks_tx, chunks_rx) = sync_channel(CHUNKS_QUEUE_SIZE);
+        let worker = thread::spawn(move || {
+            // generators share their wordlists with `Rc`, so they must stay on this thread
+            let word_gen = match build() {
+                Ok(word_gen) => word_gen,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            if ready_tx.send(Ok(word_gen.combinations())).is_err() {
+                return;
+            }
+            let mut out: Box<dyn Write> = Box::new(ChannelWriter(chunks_tx));
+            // fails only once `Words` is dropped
+            let _ = word_gen.gen(&mut out);
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(combinations)) => Ok(Words {
+                back: u64::try_from(&combinations).unwrap_or(u64::MAX),
+                combinations,
+                chunks: chunks_rx,
+                chunk: vec![],
+                pos: 0,
+                worker: Some(worker),
+                front: 0,
+                back_words: vec![],
+                build_back: Some(build_back),
+                back_worker: None,
+            }),
+            Ok(Err(e)) => {
+                let _ = worker.join();
+                Err(e)
+            }
+            Err(_) => Err(CrackenError::Io(io::Error::other(
+                "the generator thread panicked",
+            ))),
+        }
+    }
+
+    /// number of words of the generator, including the already iterated ones
+    pub fn combinations(&self) -> &BigUint {
+        &self.combinations
+    }
+
+    /// returns the next word without consuming it, or None after the last word
+    pub fn peek_word(&mut self) -> Option<&[u8]> {
+        let len = self.next_len()?;
+        Some(&self.chunk[self.pos..self.pos + len])
+    }
+
+    /// returns the next word, or None after the last word
+    pub fn next_word(&mut self) -> Option<&[u8]> {
+        let len = self.next_len()?;
+        let start = self.pos;
+        self.pos += len + 1;
+        self.front += 1;
+        Some(&self.chunk[start..start + len])
+    }
+
+    /// replaces the content of `buf` with as many of the next words as fit in `max_bytes`,
+    /// every one followed by a newline, and returns their number, 0 after the last word. a
+    /// word longer than `max_bytes` is returned on its own
+    pub fn next_chunk(&mut self, buf: &mut Vec<u8>, max_bytes: usize) -> usize {
+        buf.clear();
+        let mut count = 0;
+        while let Some(len) = self.next_len() {
+            if count > 0 && buf.len() + len + 1 > max_bytes {
+                break;
+            }
+            let left = usize::try_from(self.back - self.front).unwrap_or(usize::MAX);
+            let chunk = &self.chunk[self.pos..];
+            let (consumed, words) = take_words(chunk, len, buf, max_bytes, left);
+            self.pos += consumed;
+            self.front += words as u64;
+            count += words;
+        }
+        count
+    }
+
+    /// skips the next `n` words and returns the number of skipped words, less than `n` only
+    /// after the last word
+    pub fn skip_words(&mut self, n: u64) -> u64 {
+        for skipped in 0..n {
+            if self.next_word().is_none() {
+                return skipped;
+            }
+        }
+        n
+    }
+
+    /// length of the next word, receiving the next chunk if the current one is consumed. None
+    /// once the words left were taken from the back
+    fn next_len(&mut self) -> Option<usize> {
+        if self.front >= self.back {
+            return None;
+        }
+        loop {
+            if let Some(len) = self.chunk[self.pos..].iter().position(|&c| c == b'\n') {
+                return Some(len);
+            }
+            // the generators never split a word between writes
+            self.chunk = self.chunks.recv().ok()?;
+            self.pos = 0;
+        }
+    }
+
+    /// generates the words before `back` on the back worker, at most `BACK_CHUNK_WORDS` of them
+    /// and none taken from the front. false if the generator failed
+    fn fill_back(&mut self) -> bool {
+        if self.back_worker.is_none() {
+            match self.build_back.take() {
+                Some(build) => self.back_worker = Some(BackWorker::start(build)),
+                None => return false,
+            }
+        }
+        let worker = self.back_worker.as_ref().unwrap();
+        let start = max(self.front, self.back.saturating_sub(BACK_CHUNK_WORDS));
+        if worker.ranges.send((start, self.back)).is_err() {
+            return false;
+        }
+        let words = match worker.words.recv() {
+            Ok(words) => words,
+            Err(_) => return false,
+        };
+        self.back_words = words
+            .split_inclusive(|&c| c == b'\n')
+            .map(|word| word[..word.len() - 1].to_vec())
+            .collect();
+        // fewer words than asked for (e.g. filtered by the repeat limits) aren't the words
+        // before the back
+        if self.back_words.len() as u64 != self.back - start {
+            self.back_words.clear();
+            return false;
+        }
+        true
+    }
+}
+
+impl Iterator for Words {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        self.next_word().map(|word| word.to_vec())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = usize::try_from(self.back - self.front).unwrap_or(usize::MAX);
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for Words {}
+
+/// the words of a keyspace of more than `u64::MAX` words can't be taken from the back
+impl DoubleEndedIterator for Words {
+    fn next_back(&mut self) -> Option<Vec<u8>> {
+        if self.front >= self.back || self.combinations.bits() > 64 {
+            return None;
+        }
+        if self.back_words.is_empty() && !self.fill_back() {
+            return None;
+        }
+        self.back -= 1;
+        self.back_words.pop()
+    }
+}
+
+impl Drop for Words {
+    fn drop(&mut self) {
+        // closing the channel fails the pending write and stops the generating thread
+        drop(mem::replace(&mut self.chunks, sync_channel(0).1));
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+        // the back worker stops once its ranges channel is closed
+        if let Some(BackWorker { ranges, handle, .. }) = self.back_worker.take() {
+            drop(ranges);
+            let _ = handle.join();
+        }
+    }
+}
+
+/// appends the words of `chunk` which fit in `max_bytes` to `buf`, at least the first one of
+/// length `len` and at most `max_words` (at least 1), and returns the number of consumed bytes
+/// and words
+pub(crate) fn take_words(
+    chunk: &[u8],
+    len: usize,
+    buf: &mut Vec<u8>,
+    max_bytes: usize,
+    max_words: usize,
+) -> (usize, usize) {
+    let room = max_bytes.saturating_sub(buf.len()).min(chunk.len());
+    let mut end = match chunk[..room].iter().rposition(|&c| c == b'\n') {
+        Some(pos) => pos + 1,
+        None => len + 1,
+    };
+    let mut words = chunk[..end].iter().filter(|&&c| c == b'\n').count();
+    if words > max_words {
+        let newlines = chunk.iter().enumerate().filter(|&(_, &c)| c == b'\n');
+        end = newlines.map(|(pos, _)| pos + 1).nth(max_words - 1).unwrap();
+        words = max_words;
+    }
+    buf.extend_from_slice(&chunk[..end]);
+    (end, words)
+}
+
+/// sends every write as a chunk to `Words`
+struct ChannelWriter(SyncSender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::Words;
+    use crate::builder::GeneratorBuilder;
+    use crate::error::CrackenError;
+    use crate::helpers::SplitMix64;
+    use crate::test_util::wordlist_fname;
+
+    #[test]
+    fn test_words_match_gen() {
+        let builder = || {
+            let wordlist = wordlist_fname("wordlist-simple.txt");
+            GeneratorBuilder::new()
+                .mask("?w1?d?1")
+                .custom_charset("!@")
+                .wordlist_file(wordlist.to_str().unwrap().to_string())
+        };
+        let mut expected = vec![];
+        {
+            let word_gen = builder().build().unwrap();
+            let mut out: Box<dyn Write> = Box::new(&mut expected);
+            word_gen.gen(&mut out).unwrap();
+        }
+
+        let words = Words::new(builder()).unwrap();
+        assert_eq!(words.combinations().to_string(), "200");
+        let words: Vec<u8> = words
+            .flat_map(|word| [word, b"\n".to_vec()])
+            .flatten()
+            .collect();
+        assert!(words == expected);
+
+        // more words than a single chunk
+        let last = Words::new(GeneratorBuilder::new().mask("?d?d?d?d?d"))
+            .unwrap()
+            .last()
+            .unwrap();
+        assert_eq!(last, b"99999");
+    }
+
+    #[test]
+    fn test_words_next_chunk() {
+        let wordlist = wordlist_fname("wordlist-simple.txt");
+        let builders = [
+            GeneratorBuilder::new().mask("?d?d?d?d?l"),
+            GeneratorBuilder::new()
+                .mask("?w1?d?1")
+                .custom_charset("!@")
+                .wordlist_file(wordlist.to_str().unwrap().to_string()),
+        ];
+        for builder in builders {
+            let mut expected = vec![];
+            {
+                let word_gen = builder.clone().build().unwrap();
+                let mut out: Box<dyn Write> = Box::new(&mut expected);
+                word_gen.gen(&mut out).unwrap();
+            }
+            for max_bytes in [1, 7, 100, 65536] {
+                let mut words = Words::new(builder.clone()).unwrap();
+                let mut chunk = vec![];
+                let mut chunks = vec![];
+                let mut count = 0;
+                loop {
+                    let n = words.next_chunk(&mut chunk, max_bytes);
+                    if n == 0 {
+                        break;
+                    }
+                    // only a single word may exceed the size
+                    assert!(chunk.len() <= max_bytes || n == 1);
+                    assert_eq!(chunk.iter().filter(|&&c| c == b'\n').count(), n);
+                    assert_eq!(chunk.last(), Some(&b'\n'));
+                    count += n;
+                    chunks.extend_from_slice(&chunk);
+                }
+                assert!(chunk.is_empty());
+                assert_eq!(count.to_string(), words.combinations().to_string());
+                assert!(chunks == expected, "{}", max_bytes);
+            }
+        }
+
+        // resumes after the words pulled one by one
+        let mut words = Words::new(GeneratorBuilder::new().mask("?d?d")).unwrap();
+        assert_eq!(words.next_word(), Some(&b"00"[..]));
+        let mut chunk = vec![];
+        assert_eq!(words.next_chunk(&mut chunk, 10), 3);
+        assert_eq!(chunk, b"01\n02\n03\n");
+        assert_eq!(words.next_word(), Some(&b"04"[..]));
+    }
+
+    #[test]
+    fn test_words_skip_and_peek() {
+        let mut words = Words::new(GeneratorBuilder::new().mask("?d?d?d?d")).unwrap();
+        assert_eq!(words.skip_words(1234), 1234);
+        assert_eq!(words.peek_word(), Some(&b"1234"[..]));
+        assert_eq!(words.next_word(), Some(&b"1234"[..]));
+        assert_eq!(words.skip_words(10000), 8765);
+        assert_eq!(words.next_word(), None);
+        assert_eq!(words.peek_word(), None);
+
+        // dropping stops the generator before it ends
+        let mut words = Words::new(GeneratorBuilder::new().mask("?d?d?d?d?d?d?d?d")).unwrap();
+        assert_eq!(words.next(), Some(b"00000000".to_vec()));
+        drop(words);
+    }
+
+    #[test]
+    fn test_words_both_ends() {
+        let wordlist = wordlist_fname("wordlist-simple.txt");
+        let builders = [
+            GeneratorBuilder::new().mask("?d?d?d?d"),
+            GeneratorBuilder::new().mask("?d?d?l").minlen(1),
+            GeneratorBuilder::new()
+                .mask("?w1?d?1")
+                .custom_charset("!@")
+                .wordlist_file(wordlist.to_str().unwrap().to_string())
+                .skip(15)
+                .limit(170),
+        ];
+        for builder in builders {
+            let all: Vec<_> = Words::new(builder.clone()).unwrap().collect();
+            let mut reversed: Vec<_> = Words::new(builder.clone()).unwrap().rev().collect();
+            reversed.reverse();
+            assert_eq!(reversed, all);
+
+            // the front and the back meet in between, every word is taken once
+            let mut rng = SplitMix64::new(7);
+            for back_share in [0, 1, 2, 5] {
+                let mut words = Words::new(builder.clone()).unwrap();
+                assert_eq!(words.len(), all.len());
+                let (mut front, mut back) = (vec![], vec![]);
+                loop {
+                    let from_back = rng.next_u64() % 6 < back_share;
+                    let word = if from_back {
+                        words.next_back()
+                    } else {
+                        words.next()
+                    };
+                    match word {
+                        Some(word) if from_back => back.push(word),
+                        Some(word) => front.push(word),
+                        None => break,
+                    }
+                    assert_eq!(words.len(), all.len() - front.len() - back.len());
+                }
+                assert_eq!(words.next(), None);
+                assert_eq!(words.next_back(), None);
+                assert_eq!(words.len(), 0);
+                back.reverse();
+                front.extend(back);
+                assert_eq!(front, all, "{}", back_share);
+            }
+        }
+
+        // the words pulled in chunks stop at the back too
+        let mut words = Words::new(GeneratorBuilder::new().mask("?d?d")).unwrap();
+        assert_eq!(words.next_back(), Some(b"99".to_vec()));
+        assert_eq!(words.skip_words(90), 90);
+        let mut chunk = vec![];
+        assert_eq!(words.next_chunk(&mut chunk, 100), 9);
+        assert_eq!(chunk, b"90\n91\n92\n93\n94\n95\n96\n97\n98\n");
+        assert_eq!(words.peek_word(), None);
+        assert_eq!(words.next_back(), None);
+
+        let mut words = Words::new(GeneratorBuilder::new().mask("?d?d?d")).unwrap();
+        assert_eq!(words.rposition(|word| word.starts_with(b"4")), Some(499));
+    }
+
+    #[test]
+    fn test_words_errors() {
+        assert!(matches!(
+            Words::new(GeneratorBuilder::new().mask("?d?")),
+            Err(CrackenError::Mask(_))
+        ));
+        assert!(matches!(
+            Words::new(
+                GeneratorBuilder::new()
+                    .mask("?w1")
+                    .wordlist_file("/tmp/this/dir/not/exisT.txt")
+            ),
+            Err(CrackenError::Wordlist { .. })
+        ));
+    }
+}
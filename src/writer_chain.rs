@@ -0,0 +1,498 @@
+//! the writers of the generated words: the outputs (files, --pipe-to command and stdout) and
+//! the encodings, filters and limits the words go through before reaching them
+use std::cell::{Cell, RefCell};
+use std::fs::{File, OpenOptions};
+use std::io::{self, stderr, stdout, BufWriter, IsTerminal, Write};
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once};
+use std::time::Instant;
+
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::exclude::ExcludeSet;
+use crate::generate_options::GenerateOptions;
+use crate::helpers::{
+    decode_hex_word, BudgetWriter, CountingWriter, IndexedWriter, LineFilterWriter,
+    RateLimitWriter, StatusWriter, StopWriter, TeeWriter, TimeoutWriter, WriteCounts, HEX_PREFIX,
+};
+use crate::io_backend::{backend_writer, BackendOutput, IoBackend};
+use crate::output_encoding::{Base64Writer, HashcatHexWriter, Latin1Writer, Utf16LeWriter};
+use crate::password_entropy::password_mask_entropy_bits;
+use crate::runner::RunError;
+use crate::BoxResult;
+
+/// --exclude files larger than this warn about the memory of their words
+const EXCLUDE_MEMORY_WARNING: u64 = 1 << 30;
+
+/// set by the ctrl-c handler, stops the generation at the next buffer flush
+pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// the outputs of a generate run, all the masks are written to them one after the other
+pub struct OutputOptions {
+    /// the -o files, or $CRACKEN_OUTPUT
+    pub files: Vec<String>,
+    pub append: bool,
+    pub force: bool,
+    pub atomic: bool,
+    /// the partial files of --atomic are kept when the run fails, unless --no-keep-partial
+    pub keep_partial: bool,
+    pub pipe_to: Option<String>,
+    pub tee_best_effort: bool,
+    pub io_buffer: usize,
+    pub io_backend: IoBackend,
+}
+
+impl OutputOptions {
+    /// the output files written to a filesystem, the regular files and the files to create.
+    /// the words of stdout, --pipe-to and devices like /dev/null are read by another program
+    pub fn disk_files(&self) -> Vec<&str> {
+        self.files
+            .iter()
+            .map(|fname| fname.as_str())
+            .filter(|fname| !matches!(std::fs::metadata(fname), Ok(m) if !m.is_file()))
+            .collect()
+    }
+
+    /// the `.partial` file of --atomic the words of `fname` are written to, None without
+    /// --atomic and for devices like /dev/null, which are written in place
+    fn partial_path(&self, fname: &str) -> Option<String> {
+        if !self.atomic {
+            return None;
+        }
+        match std::fs::metadata(fname) {
+            Ok(m) if !m.is_file() => None,
+            _ => Some(format!("{}.partial", fname)),
+        }
+    }
+
+    /// fails when an output file exists without --force or --append, before any output is
+    /// created. devices like /dev/null are not regular files and are always allowed
+    pub fn check_files(&self) -> BoxResult<()> {
+        if self.append || self.force {
+            return Ok(());
+        }
+        for fname in self.files.iter() {
+            if matches!(std::fs::metadata(fname), Ok(m) if m.is_file()) {
+                let msg = format!(
+                    "output file {} exists, pass --force to overwrite (or --append to extend)",
+                    fname
+                );
+                return Err(RunError::Guard(msg).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// creates the output files and spawns the --pipe-to command, stdout without them. the
+    /// partial files of --atomic are added to `partials`
+    pub fn open(&self, partials: &mut PartialFiles) -> BoxResult<(Box<dyn Write>, Option<Child>)> {
+        let mut sinks = vec![];
+        for fname in self.files.iter() {
+            let partial = self.partial_path(fname);
+            let path = partial.as_deref().unwrap_or(fname);
+            let sink = self.create_file(fname, path)?;
+            sinks.push((fname.to_string(), sink));
+            if let Some(partial) = partial {
+                partials.files.push((partial, fname.to_string()));
+            }
+        }
+        let mut child = None;
+        if let Some(command) = &self.pipe_to {
+            let mut spawned = spawn_pipe_to(command)?;
+            let stdin = spawned.stdin.take().unwrap();
+            child = Some(spawned);
+            let sink = match self.io_backend {
+                IoBackend::Std => Box::new(stdin),
+                _ => open_backend(stdin, self.io_backend, self.io_buffer),
+            };
+            sinks.push((format!("--pipe-to command '{}'", command), sink));
+        }
+        let out = match sinks.len() {
+            // locked once and buffered, unlike stdout() which locks and flushes every line
+            0 => open_backend(stdout().lock(), self.io_backend, self.io_buffer),
+            1 => sinks.pop().unwrap().1,
+            _ => Box::new(TeeWriter::new(sinks, self.tee_best_effort, stderr())),
+        };
+        Ok((out, child))
+    }
+
+    /// creates the output file `fname` (or extends it with --append), the words are written
+    /// to `path`, the partial file of --atomic or `fname` itself
+    fn create_file(&self, fname: &str, path: &str) -> BoxResult<Box<dyn Write>> {
+        if self.append {
+            return match OpenOptions::new().append(true).create(true).open(fname) {
+                Ok(fp) => Ok(open_backend(fp, self.io_backend, self.io_buffer)),
+                Err(e) => {
+                    let msg = format!("cannot append to file {}: {}", fname, e);
+                    Err(RunError::Output(msg).into())
+                }
+            };
+        }
+        match File::create(path) {
+            Ok(fp) => Ok(open_backend(fp, self.io_backend, self.io_buffer)),
+            Err(e) => {
+                let msg = format!("cannot create file {}: {}", path, e);
+                Err(RunError::Output(msg).into())
+            }
+        }
+    }
+}
+
+/// the partial files of --atomic and their output files. `finish` renames them once the run
+/// succeeded, dropped before that they are left in place, or removed with --no-keep-partial
+pub struct PartialFiles {
+    files: Vec<(String, String)>,
+    keep: bool,
+}
+
+impl PartialFiles {
+    pub fn new(keep: bool) -> PartialFiles {
+        PartialFiles {
+            files: vec![],
+            keep,
+        }
+    }
+
+    pub fn finish(mut self) -> BoxResult<()> {
+        while let Some((partial, fname)) = self.files.first() {
+            if let Err(e) = std::fs::rename(partial, fname) {
+                let msg = format!("cannot rename {} to {}: {}", partial, fname, e);
+                return Err(RunError::Output(msg).into());
+            }
+            self.files.remove(0);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PartialFiles {
+    fn drop(&mut self) {
+        if !self.keep {
+            for (partial, _) in &self.files {
+                let _ = std::fs::remove_file(partial);
+            }
+        }
+    }
+}
+
+/// the writer of `--io-backend` for `out`, a `BufWriter` with a note on stderr when the backend
+/// isn't supported
+fn open_backend<W: BackendOutput + 'static>(
+    out: W,
+    io_backend: IoBackend,
+    io_buffer: usize,
+) -> Box<dyn Write> {
+    match backend_writer(out, io_backend, io_buffer) {
+        Ok(writer) => writer,
+        Err((out, reason)) => {
+            eprintln!(
+                "--io-backend {} is not supported ({}), falling back to std",
+                io_backend, reason
+            );
+            Box::new(BufWriter::with_capacity(io_buffer, out))
+        }
+    }
+}
+
+/// spawns `command` with the shell, the generated words are written to its stdin
+fn spawn_pipe_to(command: &str) -> BoxResult<Child> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    match shell.stdin(Stdio::piped()).spawn() {
+        Ok(child) => Ok(child),
+        Err(e) => {
+            let msg = format!("cannot run --pipe-to command '{}': {}", command, e);
+            Err(RunError::Output(msg).into())
+        }
+    }
+}
+
+/// loads the words of --exclude, warning about the memory of a large file
+pub fn load_exclude(fname: &str, bloom: bool, quiet: bool) -> BoxResult<ExcludeSet> {
+    let file_size = std::fs::metadata(fname)
+        .map_err(|e| RunError::Input(format!("--exclude {}: {}", fname, e)))?
+        .len();
+    if !bloom && file_size > EXCLUDE_MEMORY_WARNING && !quiet {
+        eprintln!(
+            "warning: --exclude {} ({} bytes) is loaded to memory and takes more than its size, use --exclude-bloom for a smaller approximate set",
+            fname, file_size
+        );
+    }
+    let excluded = ExcludeSet::from_file(fname, bloom)
+        .map_err(|e| RunError::Input(format!("--exclude {}: {}", fname, e)))?;
+    Ok(excluded)
+}
+
+/// the sha256 of the bytes written so far and their number (--checksum)
+#[derive(Default)]
+pub struct Checksum {
+    pub hasher: RefCell<Sha256>,
+    pub bytes: Cell<u64>,
+}
+
+/// adds every buffer written to `checksum`, once written to the inner writer
+struct ChecksumWriter<W: Write> {
+    inner: W,
+    checksum: Rc<Checksum>,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    fn new(inner: W, checksum: Rc<Checksum>) -> ChecksumWriter<W> {
+        ChecksumWriter { inner, checksum }
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.checksum.hasher.borrow_mut().update(&buf[..written]);
+        let bytes = self.checksum.bytes.get() + written as u64;
+        self.checksum.bytes.set(bytes);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// the writer the words are generated to and what its writers count and report while the
+/// words go through them, read once the generation stops
+pub struct WriterChain {
+    pub out: Box<dyn Write>,
+    /// the bytes as written to every output, after all the other writers
+    pub checksum: Rc<Checksum>,
+    /// the words and bytes written, with --verbose
+    pub write_counts: Rc<WriteCounts>,
+    pub excluded_words: Rc<Cell<u64>>,
+    /// the generated words (including the skipped ones) for the resume point printed when the
+    /// generation stops early
+    pub generated: Rc<WriteCounts>,
+    pub timed_out: Rc<Cell<bool>>,
+    pub budget_exhausted: Rc<Cell<bool>>,
+    /// the index of the next word of the mask generated, set when every mask starts
+    pub word_index: Rc<Cell<u64>>,
+}
+
+impl WriterChain {
+    /// wraps the opened outputs `out` with the writers of `options`. `count_total` counts the
+    /// words of the status lines, at once with --status and at the first status requested
+    /// without it
+    pub fn new(
+        mut out: Box<dyn Write>,
+        options: &GenerateOptions,
+        excluded: Option<ExcludeSet>,
+        count_total: Box<dyn FnOnce() -> BigUint>,
+    ) -> WriterChain {
+        let checksum = Rc::new(Checksum::default());
+        if options.checksum.is_some() {
+            out = Box::new(ChecksumWriter::new(out, Rc::clone(&checksum)));
+        }
+        let write_counts = Rc::new(WriteCounts::default());
+        if options.verbose {
+            out = Box::new(CountingWriter::new(out, Rc::clone(&write_counts)));
+        }
+        match options.output_encoding.as_str() {
+            "latin1" => out = Box::new(Latin1Writer::new(out)),
+            "utf16le" => out = Box::new(Utf16LeWriter::new(out, options.lossy)),
+            "hashcat-hex" => out = Box::new(HashcatHexWriter::new(out)),
+            "base64" => out = Box::new(Base64Writer::new(out, options.base64_pad)),
+            _ => {}
+        }
+
+        let output_hex = options.output_hex;
+        if let Some((min_entropy, max_entropy)) = options.entropy {
+            out = Box::new(LineFilterWriter::new(out, move |word: &[u8]| {
+                // --output-hex words are encoded before reaching the filter
+                let entropy = if output_hex && word.starts_with(HEX_PREFIX) {
+                    match decode_hex_word(word.to_vec()) {
+                        Ok(decoded) => password_mask_entropy_bits(&decoded),
+                        Err(_) => password_mask_entropy_bits(word),
+                    }
+                } else {
+                    password_mask_entropy_bits(word)
+                };
+                (min_entropy..=max_entropy).contains(&entropy)
+            }));
+        }
+        let excluded_words = Rc::new(Cell::new(0u64));
+        if let Some(excluded) = excluded {
+            let excluded_words = Rc::clone(&excluded_words);
+            out = Box::new(LineFilterWriter::new(out, move |word: &[u8]| {
+                // --output-hex words are encoded before reaching the filter
+                let found = if output_hex && word.starts_with(HEX_PREFIX) {
+                    match decode_hex_word(word.to_vec()) {
+                        Ok(decoded) => excluded.contains(&decoded),
+                        Err(_) => excluded.contains(word),
+                    }
+                } else {
+                    excluded.contains(word)
+                };
+                if found {
+                    excluded_words.set(excluded_words.get() + 1);
+                }
+                !found
+            }));
+        }
+
+        let discarded = options.discarded();
+        if discarded > 0 {
+            let mut skipped = 0;
+            out = Box::new(LineFilterWriter::new(out, move |_: &[u8]| {
+                skipped += 1;
+                skipped > discarded
+            }));
+        }
+        // counts the generated words (including the skipped ones) for the resume point printed
+        // when interrupted, ctrl-c stops the generation at the next buffer flush
+        let generated = Rc::new(WriteCounts::default());
+        out = Box::new(CountingWriter::new(out, Rc::clone(&generated)));
+        out = Box::new(StopWriter::new(out, &INTERRUPTED));
+        let timed_out = Rc::new(Cell::new(false));
+        let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+        if let Some(deadline) = deadline {
+            out = Box::new(TimeoutWriter::new(out, deadline, Rc::clone(&timed_out)));
+        }
+        let budget_exhausted = Rc::new(Cell::new(false));
+        if let Some(max_bytes) = options.max_bytes {
+            // the skipped words are generated but not written
+            out = Box::new(BudgetWriter::new(
+                out,
+                max_bytes,
+                discarded,
+                Rc::clone(&budget_exhausted),
+            ));
+        }
+        if let Some(rate) = options.max_rate {
+            // waits before the writers above refuse the words, the skipped words don't wait
+            out = Box::new(RateLimitWriter::new(
+                out,
+                rate,
+                discarded,
+                &INTERRUPTED,
+                deadline,
+            ));
+        }
+        install_interrupt_handler();
+
+        let word_index = Rc::new(Cell::new(0));
+        if let Some(format) = options.indexed_format {
+            out = Box::new(IndexedWriter::new(out, format, Rc::clone(&word_index)));
+        }
+        if !options.quiet {
+            // SIGUSR1 prints a status at the next write, with or without --status
+            let requested = install_status_handler();
+            // the words enter it first, before any other writer changes them
+            match (options.status, requested) {
+                (Some(interval), requested) => {
+                    let tty = stderr().is_terminal();
+                    let total = count_total();
+                    let mut writer = StatusWriter::new(
+                        out,
+                        stderr(),
+                        tty,
+                        interval,
+                        total,
+                        Rc::clone(&generated),
+                    );
+                    if let Some(requested) = requested {
+                        writer = writer.with_request(requested);
+                    }
+                    out = Box::new(writer);
+                }
+                (None, Some(requested)) => {
+                    out = Box::new(StatusWriter::on_request(
+                        out,
+                        stderr(),
+                        requested,
+                        count_total,
+                        Rc::clone(&generated),
+                    ));
+                }
+                (None, None) => {}
+            }
+        }
+        WriterChain {
+            out,
+            checksum,
+            write_counts,
+            excluded_words,
+            generated,
+            timed_out,
+            budget_exhausted,
+            word_index,
+        }
+    }
+
+    /// the generation stopped early on purpose: interrupted, out of --max-bytes or --timeout
+    pub fn stopped(&self) -> bool {
+        INTERRUPTED.load(Ordering::SeqCst) || self.budget_exhausted.get() || self.timed_out.get()
+    }
+}
+
+/// sets `INTERRUPTED` on ctrl-c instead of exiting, so the run can print its resume point
+fn install_interrupt_handler() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        // without the handler ctrl-c just exits as before
+        let _ = ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst));
+    });
+}
+
+/// the flag set on SIGUSR1, which prints a status without stopping the generation. none where
+/// the signal doesn't exist or its handler can't be installed
+#[cfg(unix)]
+fn install_status_handler() -> Option<Arc<AtomicBool>> {
+    lazy_static! {
+        static ref STATUS_REQUESTED: Option<Arc<AtomicBool>> = {
+            let requested = Arc::new(AtomicBool::new(false));
+            // the handler only sets the flag, the status is printed by the writer
+            signal_hook::flag::register(signal_hook::consts::SIGUSR1, Arc::clone(&requested))
+                .ok()
+                .map(|_| requested)
+        };
+    }
+    STATUS_REQUESTED.clone()
+}
+
+#[cfg(not(unix))]
+fn install_status_handler() -> Option<Arc<AtomicBool>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::rc::Rc;
+
+    use sha2::Digest;
+
+    use super::{Checksum, ChecksumWriter};
+
+    #[test]
+    fn test_checksum_writer() {
+        let checksum = Rc::new(Checksum::default());
+        let mut out = vec![];
+        {
+            let mut writer = ChecksumWriter::new(&mut out, Rc::clone(&checksum));
+            writer.write_all(b"a").unwrap();
+            writer.write_all(b"bc").unwrap();
+        }
+        assert_eq!(out, b"abc");
+        assert_eq!(checksum.bytes.get(), 3);
+        assert_eq!(
+            format!("{:x}", checksum.hasher.borrow().clone().finalize()),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
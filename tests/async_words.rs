@@ -0,0 +1,95 @@
+#![cfg(feature = "async")]
+use std::io::Write;
+
+use cracken::async_words::AsyncWords;
+use cracken::builder::GeneratorBuilder;
+use cracken::error::CrackenError;
+use tokio::io::{duplex, AsyncReadExt};
+
+fn builders() -> Vec<GeneratorBuilder<'static>> {
+    let wordlist = format!(
+        "{}/test-resources/wordlist-simple.txt",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    vec![
+        GeneratorBuilder::new().mask("?d?d?d?d?l"),
+        GeneratorBuilder::new()
+            .mask("?w1?d?1")
+            .custom_charset("!@")
+            .wordlist_file(wordlist),
+    ]
+}
+
+fn gen_sync(builder: &GeneratorBuilder<'static>) -> Vec<u8> {
+    let mut buf = vec![];
+    {
+        let word_gen = builder.clone().build().unwrap();
+        let mut out: Box<dyn Write> = Box::new(&mut buf);
+        word_gen.gen(&mut out).unwrap();
+    }
+    buf
+}
+
+#[tokio::test]
+async fn test_gen_async_duplex() {
+    for builder in builders() {
+        // a small pipe keeps the writer waiting on the reader
+        let (writer, mut reader) = duplex(1024);
+        let mut out = vec![];
+        let (written, read) = tokio::join!(
+            async move { builder.gen_async(writer).await.map(|_| builder) },
+            reader.read_to_end(&mut out)
+        );
+        let builder = written.unwrap();
+        assert_eq!(read.unwrap(), out.len());
+        assert!(out == gen_sync(&builder));
+    }
+}
+
+#[tokio::test]
+async fn test_async_next_chunk() {
+    for builder in builders() {
+        let expected = gen_sync(&builder);
+        let mut words = AsyncWords::new(builder).await.unwrap();
+        let mut chunk = vec![];
+        let mut out = vec![];
+        let mut count = 0;
+        loop {
+            let n = words.next_chunk(&mut chunk, 100).await;
+            if n == 0 {
+                break;
+            }
+            assert!(chunk.len() <= 100);
+            count += n;
+            out.extend_from_slice(&chunk);
+        }
+        assert_eq!(count.to_string(), words.combinations().to_string());
+        assert!(out == expected);
+    }
+
+    // the rest of a partly pulled generator
+    let mut words = AsyncWords::new(GeneratorBuilder::new().mask("?d?d"))
+        .await
+        .unwrap();
+    let mut chunk = vec![];
+    assert_eq!(words.next_chunk(&mut chunk, 290).await, 96);
+    let mut out = vec![];
+    words.write_to(&mut out).await.unwrap();
+    assert_eq!(out, b"96\n97\n98\n99\n");
+}
+
+#[tokio::test]
+async fn test_gen_async_errors() {
+    let (writer, _reader) = duplex(64);
+    let res = GeneratorBuilder::new().mask("?d?").gen_async(writer).await;
+    assert!(matches!(res, Err(CrackenError::Mask(_))));
+
+    // the reader went away
+    let (writer, reader) = duplex(64);
+    drop(reader);
+    let res = GeneratorBuilder::new()
+        .mask("?d?d?d")
+        .gen_async(writer)
+        .await;
+    assert!(matches!(res, Err(CrackenError::Io(_))));
+}
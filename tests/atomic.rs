@@ -0,0 +1,40 @@
+#![cfg(all(unix, feature = "cli"))]
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+
+#[test]
+fn test_atomic_interrupted() {
+    let out_fname = std::env::temp_dir().join("cracken-test-atomic-interrupted.txt");
+    let partial = out_fname.with_extension("txt.partial");
+    let _ = std::fs::remove_file(&out_fname);
+    let child = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args([
+            "--atomic",
+            "--yes-i-know",
+            "--force",
+            "--max-rate",
+            "10k",
+            "-o",
+        ])
+        .arg(&out_fname)
+        .arg("?a?a?a?a?a?a?a?a")
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(500));
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(130), "{}", stderr);
+    // the words written so far are left in the partial file only
+    assert!(!out_fname.exists());
+    let words = std::fs::read(&partial).unwrap();
+    assert_eq!(words.len() % 9, 0);
+    std::fs::remove_file(&partial).unwrap();
+}
@@ -0,0 +1,49 @@
+#![cfg(all(unix, feature = "cli"))]
+use std::path::PathBuf;
+use std::process::Command;
+
+/// the sha256 and size cracken prints for its output file and the sha256 of the file by
+/// sha256sum
+fn checksums(name: &str, args: &[&str]) -> (String, String) {
+    let out_fname = std::env::temp_dir().join(format!("cracken-test-checksum-{}.txt", name));
+    let out_fname = out_fname.to_str().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(["--force", "--checksum", "sha256", "-o", out_fname])
+        .args(args)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", stderr);
+    let size = std::fs::metadata(out_fname).unwrap().len();
+    let (printed, printed_size) = stderr
+        .trim()
+        .strip_prefix("sha256: ")
+        .unwrap()
+        .split_once(' ')
+        .unwrap();
+    assert_eq!(printed_size, format!("({} bytes)", size));
+
+    let output = Command::new("sha256sum").arg(out_fname).output().unwrap();
+    let expected = String::from_utf8(output.stdout).unwrap();
+    let expected = expected.split_whitespace().next().unwrap().to_string();
+    std::fs::remove_file(out_fname).unwrap();
+    (printed.to_string(), expected)
+}
+
+#[test]
+fn test_checksum_charsets() {
+    let (printed, expected) = checksums("charsets", &["-m", "1", "?l?d?d?d"]);
+    assert_eq!(printed, expected);
+    // the skipped words aren't written
+    let (printed, expected) = checksums("skip", &["--skip", "500", "?d?d?d"]);
+    assert_eq!(printed, expected);
+}
+
+#[test]
+fn test_checksum_wordlists() {
+    let mut wordlist = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    wordlist.extend(["test-resources", "wordlist1.txt"]);
+    let wordlist = wordlist.to_str().unwrap();
+    let (printed, expected) = checksums("wordlists", &["-w", wordlist, "?w1?d"]);
+    assert_eq!(printed, expected);
+}
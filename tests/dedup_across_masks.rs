@@ -0,0 +1,26 @@
+#![cfg(feature = "cli")]
+use std::process::Command;
+
+#[test]
+fn test_dedup_across_masks_stats() {
+    let masks_fname = std::env::temp_dir().join("cracken-test-dedup-stats.txt");
+    std::fs::write(&masks_fname, "?a?a\n?l?d\n?d?d?d\n?1?d?d\n").unwrap();
+    let stats = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+            .args(["-s", "-c", "a1", "-i", masks_fname.to_str().unwrap()])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let out = String::from_utf8(output.stdout).unwrap();
+        let total = out
+            .lines()
+            .find(|line| line.starts_with("total\t"))
+            .unwrap();
+        total.to_string()
+    };
+    assert_eq!(stats(&[]), "total\t\t\t10296\t32088");
+    // ?l?d is in ?a?a and ?d?d?d has the 100 words of ?1?d?d starting with 1
+    assert_eq!(stats(&["--dedup-across-masks"]), "total\t\t\t9936\t30908");
+    std::fs::remove_file(&masks_fname).unwrap();
+}
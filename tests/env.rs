@@ -0,0 +1,81 @@
+#![cfg(feature = "cli")]
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn cracken(args: &[&str], envs: &[(&str, &str)]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(args)
+        .env_remove("CRACKEN_OUTPUT")
+        .env_remove("CRACKEN_WORDLIST_DIR")
+        .envs(envs.iter().copied())
+        .output()
+        .unwrap()
+}
+
+fn test_resources() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-resources")
+}
+
+#[test]
+fn test_env_output() {
+    let out_fname = std::env::temp_dir().join("cracken-test-env-output.txt");
+    let out_fname = out_fname.to_str().unwrap();
+    let _ = std::fs::remove_file(out_fname);
+
+    let output = cracken(&["?d"], &[("CRACKEN_OUTPUT", out_fname)]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(std::fs::read_to_string(out_fname).unwrap().len(), 20);
+
+    // -o takes precedence over the environment
+    let output = cracken(
+        &["-o", "/dev/null", "?d"],
+        &[("CRACKEN_OUTPUT", "/tmp/this/dir/not/exisT")],
+    );
+    assert!(output.status.success());
+
+    // --force applies to the environment output file too
+    let output = cracken(&["?d"], &[("CRACKEN_OUTPUT", out_fname)]);
+    assert_eq!(output.status.code(), Some(5));
+    let output = cracken(&["--force", "?d?d"], &[("CRACKEN_OUTPUT", out_fname)]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(out_fname).unwrap().len(), 300);
+    std::fs::remove_file(out_fname).unwrap();
+
+    // a bad value fails the same way as the same flag value
+    let bad_fname = "/tmp/this/dir/not/exisT";
+    let from_env = cracken(&["?d"], &[("CRACKEN_OUTPUT", bad_fname)]);
+    let from_flag = cracken(&["-o", bad_fname, "?d"], &[]);
+    assert_eq!(from_env.status.code(), Some(4));
+    assert_eq!(from_env.status.code(), from_flag.status.code());
+    assert_eq!(from_env.stderr, from_flag.stderr);
+}
+
+#[test]
+fn test_env_wordlist_dir() {
+    let resources = test_resources();
+    let resources = resources.to_str().unwrap();
+
+    let output = cracken(
+        &["-w", "wordlist1.txt", "?w1"],
+        &[("CRACKEN_WORDLIST_DIR", resources)],
+    );
+    assert!(output.status.success());
+    let expected = std::fs::read(test_resources().join("wordlist1.txt")).unwrap();
+    let mut words: Vec<_> = output.stdout.split(|&c| c == b'\n').collect();
+    let mut expected: Vec<_> = expected.split(|&c| c == b'\n').collect();
+    words.sort_unstable();
+    expected.sort_unstable();
+    words.retain(|w| !w.is_empty());
+    expected.retain(|w| !w.is_empty());
+    assert_eq!(words, expected);
+
+    // a missing wordlist fails the same way as the full path on the flag
+    let from_env = cracken(
+        &["-w", "wordlist1.txt", "?w1"],
+        &[("CRACKEN_WORDLIST_DIR", "/tmp/this/dir/not")],
+    );
+    let from_flag = cracken(&["-w", "/tmp/this/dir/not/wordlist1.txt", "?w1"], &[]);
+    assert_eq!(from_env.status.code(), Some(3));
+    assert_eq!(from_env.stderr, from_flag.stderr);
+}
@@ -0,0 +1,58 @@
+//! builds tests/ffi/test_ffi.c against the cdylib and include/cracken.h and runs it
+#![cfg(unix)]
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// the directory of the cdylib built for the tests (target/<profile>)
+fn lib_dir() -> PathBuf {
+    let mut dir = env::current_exe().unwrap();
+    dir.pop();
+    if dir.ends_with("deps") {
+        dir.pop();
+    }
+    dir
+}
+
+#[test]
+fn test_ffi_c_program() {
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if Command::new(&cc).arg("--version").output().is_err() {
+        eprintln!("skipping the C FFI test, {} not found", cc);
+        return;
+    }
+
+    let lib_dir = lib_dir();
+    let exe = lib_dir.join("cracken-test-ffi");
+    let status = Command::new(&cc)
+        .arg(manifest_dir().join("tests/ffi/test_ffi.c"))
+        .arg("-I")
+        .arg(manifest_dir().join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-lcracken")
+        .arg("-o")
+        .arg(&exe)
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling test_ffi.c failed");
+
+    let wordlist = manifest_dir().join("test-resources/wordlist-no-newline.txt");
+    let output = Command::new(&exe)
+        .arg(wordlist)
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .env("DYLD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(output.stdout, b"ok\n");
+}
@@ -0,0 +1,82 @@
+/* pulls words through the C API, built and run by tests/ffi.rs */
+#include <stdio.h>
+#include <string.h>
+
+#include "cracken.h"
+
+#define CHECK(cond)                                                        \
+    do {                                                                   \
+        if (!(cond)) {                                                     \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,        \
+                    __LINE__, #cond);                                      \
+            return 1;                                                      \
+        }                                                                  \
+    } while (0)
+
+static int test_generate(const char *wordlist) {
+    const char *charsets[] = {"ab"};
+    const char *wordlists[] = {wordlist};
+    const char *expected[] = {"johna", "johnb", "emilea", "emileb", "McDonalda", "McDonaldb"};
+    uint8_t buf[CRACKEN_MAX_WORD_SIZE];
+    char *err = NULL;
+    size_t i = 0;
+    ssize_t len;
+
+    CrackenGenerator *gen = cracken_generator_new("?w1?1", charsets, 1, wordlists, 1, &err);
+    CHECK(gen != NULL);
+    CHECK(err == NULL);
+    CHECK(cracken_generator_combinations(gen) == 6);
+
+    while ((len = cracken_generator_next(gen, buf, sizeof(buf))) > 0) {
+        CHECK(i < 6);
+        CHECK((size_t)len == strlen(expected[i]));
+        CHECK(memcmp(buf, expected[i], len) == 0);
+        i++;
+    }
+    CHECK(len == 0);
+    CHECK(i == 6);
+    cracken_generator_free(gen);
+    return 0;
+}
+
+static int test_skip(void) {
+    uint8_t buf[4];
+    CrackenGenerator *gen = cracken_generator_new("?d?d?d?d", NULL, 0, NULL, 0, NULL);
+    CHECK(gen != NULL);
+    CHECK(cracken_generator_combinations(gen) == 10000);
+    CHECK(cracken_generator_skip(gen, 1234) == 1234);
+    CHECK(cracken_generator_next(gen, buf, 3) == CRACKEN_ERR_BUF_TOO_SMALL);
+    CHECK(cracken_generator_next(gen, buf, sizeof(buf)) == 4);
+    CHECK(memcmp(buf, "1234", 4) == 0);
+    CHECK(cracken_generator_skip(gen, 100000) == 8765);
+    CHECK(cracken_generator_next(gen, buf, sizeof(buf)) == 0);
+    cracken_generator_free(gen);
+    return 0;
+}
+
+static int test_errors(void) {
+    uint8_t buf[4];
+    char *err = NULL;
+    CrackenGenerator *gen = cracken_generator_new("?d?", NULL, 0, NULL, 0, &err);
+    CHECK(gen == NULL);
+    CHECK(err != NULL);
+    CHECK(strcmp(err, "Invalid mask") == 0);
+    cracken_string_free(err);
+
+    CHECK(cracken_generator_next(NULL, buf, sizeof(buf)) == CRACKEN_ERR_NULL);
+    cracken_generator_free(NULL);
+    cracken_string_free(NULL);
+    return 0;
+}
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s WORDLIST\n", argv[0]);
+        return 2;
+    }
+    if (test_generate(argv[1]) || test_skip() || test_errors()) {
+        return 1;
+    }
+    printf("ok\n");
+    return 0;
+}
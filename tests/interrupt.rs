@@ -0,0 +1,39 @@
+#![cfg(all(unix, feature = "cli"))]
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+
+#[test]
+fn test_interrupt_prints_resume_point() {
+    let child = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(["-o", "/dev/null", "?a?a?a?a?a?a?a?a"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(500));
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(130), "{}", stderr);
+
+    let skip: u64 = stderr
+        .trim()
+        .rsplit("--skip ")
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(skip > 0, "{}", stderr);
+    assert_eq!(
+        stderr.trim(),
+        format!(
+            "interrupted after {} words, resume with --skip {}",
+            skip, skip
+        )
+    );
+}
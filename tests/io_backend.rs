@@ -0,0 +1,84 @@
+#![cfg(all(feature = "cli", target_os = "linux"))]
+use std::process::Command;
+
+/// runs cracken with `args`, returns its stdout (a pipe) and stderr
+fn cracken(args: &[&str]) -> (Vec<u8>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(args)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", stderr);
+    (output.stdout, stderr)
+}
+
+/// io_uring may be disabled by the kernel or a sandbox, the writes fall back to std then
+fn assert_backend_note(stderr: &str, backend: &str) {
+    let fallback = format!("--io-backend {} is not supported (", backend);
+    assert!(
+        stderr.is_empty() || (backend == "uring" && stderr.starts_with(&fallback)),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_io_backend_pipe() {
+    for mask in ["?d?d?d", "?d?d?d?d?d?d"] {
+        for io_buffer in ["64", "8k", "1M"] {
+            let (expected, _) = cracken(&["--io-buffer", io_buffer, mask]);
+            for backend in ["vmsplice", "uring"] {
+                let (out, stderr) =
+                    cracken(&["--io-backend", backend, "--io-buffer", io_buffer, mask]);
+                assert_backend_note(&stderr, backend);
+                assert!(out == expected, "{} {} {}", backend, io_buffer, mask);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_io_backend_file() {
+    let out_fname = std::env::temp_dir().join("cracken-test-io-backend.txt");
+    let out_fname = out_fname.to_str().unwrap();
+    let (expected, _) = cracken(&["?l?d?d?d"]);
+
+    let (_, stderr) = cracken(&[
+        "--io-backend",
+        "uring",
+        "--force",
+        "-o",
+        out_fname,
+        "?l?d?d?d",
+    ]);
+    assert_backend_note(&stderr, "uring");
+    assert!(std::fs::read(out_fname).unwrap() == expected);
+
+    // every write extends a file opened for appending
+    let (_, stderr) = cracken(&[
+        "--io-backend",
+        "uring",
+        "--append",
+        "-o",
+        out_fname,
+        "?l?d?d?d",
+    ]);
+    assert_backend_note(&stderr, "uring");
+    assert!(std::fs::read(out_fname).unwrap() == [&expected[..], &expected[..]].concat());
+
+    // vmsplice needs a pipe
+    let (_, stderr) = cracken(&[
+        "--io-backend",
+        "vmsplice",
+        "--force",
+        "-o",
+        out_fname,
+        "?d?d",
+    ]);
+    assert_eq!(
+        stderr,
+        "--io-backend vmsplice is not supported (the output is not a pipe), falling back to std\n"
+    );
+    assert_eq!(std::fs::read(out_fname).unwrap().len(), 300);
+    std::fs::remove_file(out_fname).unwrap();
+}
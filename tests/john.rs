@@ -0,0 +1,63 @@
+//! compares `--syntax john` with `john --stdout --mask=` of documented John the Ripper masks,
+//! skipped when john isn't installed. the words are compared sorted since john changes the
+//! first positions first
+#![cfg(all(unix, feature = "cli"))]
+use std::path::PathBuf;
+use std::process::Command;
+
+/// (mask, wordlist) with small keyspaces
+const MASKS: &[(&str, Option<&str>)] = &[
+    ("?d?d", None),
+    ("[Pp]assword?d", None),
+    ("?l[aeiou]?h", None),
+    ("[a-c\\x41-\\x43][?d?s]", None),
+    ("?H?u", None),
+    ("pass\\?[0-9a-f]", None),
+    ("?w?d", Some("wordlist-simple.txt")),
+    ("[Xx]?w", Some("wordlist-no-newline.txt")),
+];
+
+fn sorted_lines(stdout: &[u8]) -> Vec<String> {
+    let mut lines: Vec<String> = String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| line.to_string())
+        .collect();
+    lines.sort();
+    lines
+}
+
+#[test]
+fn test_john_syntax_matches_john() {
+    if Command::new("john")
+        .arg("--stdout")
+        .arg("--mask=?d")
+        .output()
+        .is_err()
+    {
+        eprintln!("john not found, skipping");
+        return;
+    }
+    let resources = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-resources");
+    for (mask, wordlist) in MASKS {
+        let mut john = Command::new("john");
+        john.arg("--stdout").arg(format!("--mask={}", mask));
+        let mut cracken = Command::new(env!("CARGO_BIN_EXE_cracken"));
+        cracken
+            .env_remove("CRACKEN_OUTPUT")
+            .args(["--syntax", "john"]);
+        if let Some(wordlist) = wordlist {
+            let wordlist = resources.join(wordlist);
+            john.arg(format!("--wordlist={}", wordlist.to_str().unwrap()));
+            cracken.arg("-w").arg(&wordlist);
+        }
+        let john = john.output().unwrap();
+        let cracken = cracken.arg(mask).output().unwrap();
+        assert!(cracken.status.success(), "{}", mask);
+        assert_eq!(
+            sorted_lines(&cracken.stdout),
+            sorted_lines(&john.stdout),
+            "{}",
+            mask
+        );
+    }
+}
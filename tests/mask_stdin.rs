@@ -0,0 +1,60 @@
+#![cfg(feature = "cli")]
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// runs cracken with `stdin` written to its stdin
+fn cracken(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(args)
+        .env_remove("CRACKEN_OUTPUT")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_mask_stdin() {
+    let output = cracken(&["@-"], "# two digits\n?d?d\n");
+    assert!(output.status.success());
+    let out = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(out.lines().count(), 100);
+    assert!(out.starts_with("00\n01\n"));
+    assert!(out.ends_with("99\n"));
+
+    let output = cracken(&["-c", "ab", "@-"], "?1?d");
+    assert!(output.status.success());
+    assert_eq!(output.stdout.len(), 20 * 3);
+
+    let output = cracken(&["@-"], "?d\n?l\n");
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("mask file stdin: more than one mask"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_mask_stdin_distribute() {
+    // the commands are given the mask, stdin can't be read again
+    let output = cracken(&["--distribute", "2", "@-"], "?d?d\n");
+    assert!(output.status.success());
+    let out = String::from_utf8(output.stdout).unwrap();
+    let commands: Vec<_> = out.lines().collect();
+    assert_eq!(commands.len(), 2);
+    assert!(
+        commands[0].ends_with("'?d?d' --slice 1/2"),
+        "{}",
+        commands[0]
+    );
+}
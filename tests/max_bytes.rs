@@ -0,0 +1,75 @@
+#![cfg(feature = "cli")]
+use std::process::Command;
+
+/// runs cracken writing to `out_fname`, returns its output and the --skip it printed
+fn cracken(args: &[&str], out_fname: &str) -> (String, u64) {
+    let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(["--force", "-o", out_fname])
+        .args(args)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", stderr);
+    let skip: u64 = stderr
+        .trim()
+        .rsplit("--skip ")
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(
+        stderr.trim(),
+        format!(
+            "reached --max-bytes after {} words, resume with --skip {}",
+            skip, skip
+        )
+    );
+    (std::fs::read_to_string(out_fname).unwrap(), skip)
+}
+
+#[test]
+fn test_max_bytes() {
+    let out_fname = std::env::temp_dir().join("cracken-test-max-bytes.txt");
+    let out_fname = out_fname.to_str().unwrap();
+
+    // every word is 5 bytes, the 201st doesn't fit
+    let (out, skip) = cracken(&["--max-bytes", "1003", "?d?d?d?d"], out_fname);
+    assert_eq!(out.len(), 1000);
+    assert_eq!(skip, 200);
+    assert!(out.ends_with("0199\n"));
+
+    let (out, skip) = cracken(
+        &["--max-bytes", "1k", "--skip", "200", "?d?d?d?d"],
+        out_fname,
+    );
+    assert_eq!(out.len(), 1020);
+    assert!(out.starts_with("0200\n"));
+    assert_eq!(skip, 404);
+
+    // the prefix and suffix are counted, 7 bytes per word
+    let (out, skip) = cracken(
+        &[
+            "--max-bytes",
+            "1003",
+            "--prefix",
+            "a",
+            "--suffix",
+            "b",
+            "?d?d?d?d",
+        ],
+        out_fname,
+    );
+    assert_eq!(out.len(), 1001);
+    assert_eq!(skip, 143);
+    assert!(out.ends_with("a0142b\n"));
+
+    // a budget larger than the output isn't reported
+    let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(["--force", "-o", out_fname, "--max-bytes", "1M", "?d?d"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    assert_eq!(std::fs::read_to_string(out_fname).unwrap().len(), 300);
+    std::fs::remove_file(out_fname).unwrap();
+}
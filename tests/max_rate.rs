@@ -0,0 +1,77 @@
+#![cfg(feature = "cli")]
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+/// runs cracken, returns its output and how long it ran
+fn cracken(args: &[&str]) -> (Output, Duration) {
+    let started = Instant::now();
+    let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(args)
+        .output()
+        .unwrap();
+    let elapsed = started.elapsed();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    (output, elapsed)
+}
+
+#[test]
+fn test_max_rate() {
+    // 100 words at 250 words per second, in buffers of 16 words
+    let (output, elapsed) = cracken(&["--max-rate", "250", "--io-buffer", "48", "-v", "?d?d"]);
+    assert_eq!(output.stdout.len(), 100 * 3);
+    assert!(elapsed >= Duration::from_millis(380), "{:?}", elapsed);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let rate = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("rate: "))
+        .unwrap();
+    let (rate, max) = rate.split_once(" words/s ").unwrap();
+    assert_eq!(max, "(max 250)");
+    assert!(rate.parse::<u64>().unwrap() <= 260, "{}", stderr);
+
+    // the skipped words don't wait
+    let (output, elapsed) = cracken(&["--max-rate", "1k", "--skip", "9990", "?d?d?d?d"]);
+    assert_eq!(
+        output.stdout,
+        b"9990\n9991\n9992\n9993\n9994\n9995\n9996\n9997\n9998\n9999\n"
+    );
+    assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+}
+
+#[test]
+fn test_max_rate_timeout() {
+    // 1000 words at 100 words per second stop at the timeout, not after 10 seconds
+    let (output, elapsed) = cracken(&[
+        "--max-rate",
+        "100",
+        "--io-buffer",
+        "40",
+        "--timeout",
+        "500ms",
+        "?d?d?d",
+    ]);
+    assert!(elapsed < Duration::from_secs(3), "{:?}", elapsed);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let words = output.stdout.len() as u64 / 4;
+    assert!(words > 0 && words < 1000, "{}", words);
+    assert_eq!(
+        stderr.trim(),
+        format!(
+            "reached --timeout after {} words, resume with --skip {}",
+            words, words
+        )
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(["--max-rate", "fast", "?d"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("--max-rate: invalid rate 'fast'"));
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "cli")]
+use std::process::Command;
+
+/// runs cracken to /dev/null, returns its stderr - a pipe, so the status is printed in lines
+fn cracken_stderr(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(["-o", "/dev/null"])
+        .args(args)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", stderr);
+    stderr
+}
+
+#[test]
+fn test_status_lines() {
+    let stderr = cracken_stderr(&["--status=20ms", "?d?d?d?d?d?d?d"]);
+    let lines: Vec<_> = stderr.lines().collect();
+    assert!(lines.len() >= 2, "{}", stderr);
+    assert!(!stderr.contains('\r'));
+    let mut previous = 0;
+    for line in lines.iter() {
+        // progress: 1234/10000000 (0.01%), 1.20M/s (avg 1.15M/s), eta 8 seconds, last: 0001233
+        let rest = line.strip_prefix("progress: ").unwrap();
+        let (done, rest) = rest.split_once('/').unwrap();
+        let done: u64 = done.parse().unwrap();
+        assert!(rest.starts_with("10000000 ("), "{}", line);
+        assert!(rest.contains("/s (avg "), "{}", line);
+        assert!(done >= previous);
+        previous = done;
+        let last = line.rsplit(", last: ").next().unwrap();
+        assert_eq!(last, format!("{:07}", done - 1));
+    }
+    assert_eq!(
+        *lines.last().unwrap(),
+        format!(
+            "{}, done, last: 9999999",
+            lines.last().unwrap().split(", done").next().unwrap()
+        )
+    );
+    assert!(lines
+        .last()
+        .unwrap()
+        .starts_with("progress: 10000000/10000000 (100.00%), "));
+}
+
+#[test]
+fn test_status_quiet_and_invalid() {
+    assert_eq!(cracken_stderr(&["--status=1ms", "--quiet", "?d?d?d?d"]), "");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(["--status=soon", "?d"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("--status: invalid duration 'soon'"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_status_on_sigusr1() {
+    use std::process::Stdio;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(["-o", "/dev/null", "?a?a?a?a?a?a?a?a"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let pid = child.id().to_string();
+    let signal = |name: &str| {
+        let sent = Command::new("kill")
+            .args([name, pid.as_str()])
+            .status()
+            .unwrap();
+        assert!(sent.success());
+    };
+    sleep(Duration::from_millis(300));
+    signal("-USR1");
+    sleep(Duration::from_millis(300));
+    signal("-USR1");
+    sleep(Duration::from_millis(300));
+    // the signal doesn't stop the generation
+    assert!(child.try_wait().unwrap().is_none());
+    signal("-INT");
+
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(130), "{}", stderr);
+    let lines: Vec<_> = stderr.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", stderr);
+    let mut previous = 0;
+    for line in lines[..2].iter() {
+        let rest = line.strip_prefix("progress: ").unwrap();
+        let (done, rest) = rest.split_once('/').unwrap();
+        let done: u64 = done.parse().unwrap();
+        assert!(rest.starts_with("6095689385410816 ("), "{}", line);
+        assert!(done > previous, "{}", stderr);
+        previous = done;
+    }
+    assert!(lines[2].starts_with("interrupted after "), "{}", stderr);
+}
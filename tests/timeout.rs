@@ -0,0 +1,91 @@
+#![cfg(feature = "cli")]
+use std::process::Command;
+
+const MASK: &str = "?d?d?d?d?d?d?d?d?d?d?d?d";
+
+/// runs cracken, returns its stdout and the --skip it printed once the timeout expired
+fn cracken(args: &[&str]) -> (String, u64) {
+    let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(args)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", stderr);
+    let skip: u64 = stderr
+        .trim()
+        .rsplit("--skip ")
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(
+        stderr.trim(),
+        format!(
+            "reached --timeout after {} words, resume with --skip {}",
+            skip, skip
+        )
+    );
+    (String::from_utf8(output.stdout).unwrap(), skip)
+}
+
+#[test]
+fn test_timeout() {
+    let (out, skip) = cracken(&["--timeout", "1s", MASK]);
+    // whole buffers of whole words, the words generated are the words written
+    assert!(skip > 0);
+    assert_eq!(out.lines().count() as u64, skip);
+    assert!(out.ends_with(&format!("{:012}\n", skip - 1)));
+
+    // the skipped words are not generated again, so resuming gets more time
+    let (out, resumed) = cracken(&["--timeout", "2s", "--skip", &skip.to_string(), MASK]);
+    assert!(resumed > skip);
+    assert!(out.starts_with(&format!("{:012}\n", skip)));
+    assert_eq!(out.lines().count() as u64, resumed - skip);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_timeout_pipe_to() {
+    let out_fname = std::env::temp_dir().join("cracken-test-timeout.txt");
+    let command = format!("wc -l > {}", out_fname.to_str().unwrap());
+    // the command reads until its stdin is closed
+    let (out, skip) = cracken(&["--timeout", "1s", "--pipe-to", &command, MASK]);
+    assert!(out.is_empty());
+    let count = std::fs::read_to_string(&out_fname).unwrap();
+    assert_eq!(count.trim().parse::<u64>().unwrap(), skip);
+    std::fs::remove_file(&out_fname).unwrap();
+}
+
+#[test]
+fn test_timeout_while_skipping() {
+    // the mask seeks past the skipped words at once
+    let (out, resumed) = cracken(&["--timeout", "1s", "--skip", "100000000000", MASK]);
+    assert!(out.starts_with("100000000000\n"));
+    assert_eq!(out.lines().count() as u64, resumed - 100000000000);
+
+    // the words filtered by --seq-max are generated to be counted, it expires long before
+    // skipping 10^11 words and the resume point is still the --skip
+    let (out, skip) = cracken(&[
+        "--timeout",
+        "1s",
+        "--seq-max",
+        "12",
+        "--skip",
+        "100000000000",
+        MASK,
+    ]);
+    assert!(out.is_empty());
+    assert_eq!(skip, 100000000000);
+}
+
+#[test]
+fn test_timeout_invalid() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cracken"))
+        .args(["--timeout", "10x", "?d"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("--timeout: invalid duration '10x'"));
+}
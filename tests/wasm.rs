@@ -0,0 +1,49 @@
+//! runs the wasm exports in a headless browser:
+//! `wasm-pack test --headless --firefox --no-default-features --features wasm -- --test wasm`
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use wasm_bindgen_test::*;
+
+use cracken::wasm::{combinations_js, parse_mask_js, preview_js, MAX_PREVIEW_WORDS};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn test_parse_mask_js() {
+    assert_eq!(
+        parse_mask_js("a?d?1").unwrap(),
+        vec!["Char('a')", "BuiltinCharset('d')", "CustomCharset(0)"]
+    );
+    assert!(parse_mask_js("?d?").is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_combinations_js() {
+    assert_eq!(combinations_js("?d?d?l", None).unwrap(), "2600");
+    assert_eq!(
+        combinations_js("?1?1", Some(vec!["abc".to_string()])).unwrap(),
+        "9"
+    );
+    // larger than the js numbers precision
+    assert_eq!(
+        combinations_js(&"?d".repeat(20), None).unwrap(),
+        "100000000000000000000"
+    );
+    assert!(combinations_js("?1", None).is_err());
+    // no files in the browser
+    assert!(combinations_js("?w1", None).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_preview_js() {
+    assert_eq!(preview_js("?d?d", 3, None).unwrap(), vec!["00", "01", "02"]);
+    assert_eq!(
+        preview_js("x?1", 5, Some(vec!["ab".to_string()])).unwrap(),
+        vec!["xa", "xb"]
+    );
+    assert!(preview_js("?d?d", 0, None).unwrap().is_empty());
+    assert_eq!(
+        preview_js("?d?d?d?d?d", u32::MAX, None).unwrap().len(),
+        MAX_PREVIEW_WORDS as usize
+    );
+}